@@ -0,0 +1,318 @@
+//! Audit log of the writes the app issues (row edits, inserts, deletes, DDL, mutating raw
+//! SQL), kept in the app store.
+//!
+//! Entries form a hash chain: each one's hash covers its own fields and the previous entry's
+//! hash, so editing or deleting a row in the middle breaks every later link and shows up in
+//! [`verify_audit_log`].
+
+use std::fmt::Write as _;
+
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tauri::State;
+
+use crate::db::{JsonRow, ResultColumn};
+use crate::export::{self, ExportFormat};
+use crate::{encoding, schema_cache, store, workspaces, AppState};
+
+/// `prev_hash` of the first entry.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+const DEFAULT_LIMIT: i64 = 500;
+/// Statements of a batch kept verbatim in its entry; the rest are only counted.
+const MAX_BATCH_STATEMENTS: usize = 100;
+
+/// Serializes appends, which read the previous hash before inserting.
+static APPEND: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+  pub id: i64,
+  pub workspace: String,
+  pub connection_id: String,
+  pub statement: String,
+  /// Bound values as a JSON array.
+  pub params: String,
+  /// `None` for statements that don't report affected rows (DDL).
+  pub rows_affected: Option<i64>,
+  pub executed_at: i64,
+  pub prev_hash: String,
+  pub hash: String,
+}
+
+fn entry_hash(
+  prev_hash: &str,
+  workspace: &str,
+  connection_id: &str,
+  statement: &str,
+  params: &str,
+  rows_affected: Option<i64>,
+  executed_at: i64,
+) -> String {
+  let mut hasher = Sha256::new();
+  for part in [prev_hash, workspace, connection_id, statement, params] {
+    hasher.update(part.as_bytes());
+    hasher.update([0]);
+  }
+  hasher.update(
+    rows_affected
+      .map(|n| n.to_string())
+      .unwrap_or_default()
+      .as_bytes(),
+  );
+  hasher.update([0]);
+  hasher.update(executed_at.to_string().as_bytes());
+  encoding::hex(&hasher.finalize())
+}
+
+async fn append(
+  pool: &SqlitePool,
+  workspace: &str,
+  connection_id: &str,
+  statement: &str,
+  params: &str,
+  rows_affected: Option<i64>,
+) -> Result<(), String> {
+  let _guard = APPEND.lock().await;
+  let prev: Option<(String,)> =
+    sqlx::query_as("SELECT hash FROM audit_log ORDER BY id DESC LIMIT 1")
+      .fetch_optional(pool)
+      .await
+      .map_err(|e| e.to_string())?;
+  let prev_hash = prev.map_or_else(|| GENESIS.to_string(), |(hash,)| hash);
+  let executed_at = store::now_ms();
+  let hash = entry_hash(
+    &prev_hash,
+    workspace,
+    connection_id,
+    statement,
+    params,
+    rows_affected,
+    executed_at,
+  );
+  sqlx::query(
+    "INSERT INTO audit_log (workspace, connection_id, statement, params, rows_affected, \
+     executed_at, prev_hash, hash) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+  )
+  .bind(workspace)
+  .bind(connection_id)
+  .bind(statement)
+  .bind(params)
+  .bind(rows_affected)
+  .bind(executed_at)
+  .bind(&prev_hash)
+  .bind(&hash)
+  .execute(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Records a write that succeeded on `connection_id`. `params` is a JSON array of the bound
+/// values. Failures to record are logged, never returned.
+pub async fn record(
+  state: &AppState,
+  connection_id: &str,
+  statement: &str,
+  params: serde_json::Value,
+  rows_affected: Option<u64>,
+) {
+  schema_cache::note_statement(state, connection_id, statement);
+  let Ok(pool) = store::pool(state) else {
+    return;
+  };
+  let recorded = append(
+    &pool,
+    &workspaces::current(state),
+    connection_id,
+    statement,
+    &params.to_string(),
+    rows_affected.map(|n| i64::try_from(n).unwrap_or(i64::MAX)),
+  )
+  .await;
+  if let Err(e) = recorded {
+    tracing::warn!("Failed to record audit entry: {}", e);
+  }
+}
+
+/// Records a batch of statements run together (a template, an import) as one entry.
+pub async fn record_batch(
+  state: &AppState,
+  connection_id: &str,
+  statements: &[String],
+  rows_affected: u64,
+) {
+  let mut text = statements
+    .iter()
+    .take(MAX_BATCH_STATEMENTS)
+    .map(String::as_str)
+    .collect::<Vec<_>>()
+    .join(";\n");
+  if statements.len() > MAX_BATCH_STATEMENTS {
+    let _ = write!(
+      text,
+      ";\n-- ... {} more statements",
+      statements.len() - MAX_BATCH_STATEMENTS
+    );
+  }
+  record(
+    state,
+    connection_id,
+    &text,
+    serde_json::json!([]),
+    Some(rows_affected),
+  )
+  .await;
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditFilter {
+  pub connection_id: Option<String>,
+  /// Case-insensitive substring of the statement.
+  pub contains: Option<String>,
+  /// Inclusive lower bound, in ms since the epoch.
+  pub since: Option<i64>,
+  /// Exclusive upper bound, in ms since the epoch.
+  pub until: Option<i64>,
+  /// Every workspace instead of the active one.
+  #[serde(default)]
+  pub all_workspaces: bool,
+  pub limit: Option<i64>,
+  pub offset: Option<i64>,
+}
+
+async fn query(state: &AppState, filter: &AuditFilter) -> Result<Vec<AuditEntry>, String> {
+  let pool = store::pool(state)?;
+  let workspace = (!filter.all_workspaces).then(|| workspaces::current(state));
+  let contains = filter.contains.as_ref().filter(|c| !c.is_empty()).map(|c| {
+    format!(
+      "%{}%",
+      c.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+    )
+  });
+  sqlx::query_as(
+    "SELECT id, workspace, connection_id, statement, params, rows_affected, executed_at, \
+     prev_hash, hash FROM audit_log \
+     WHERE (?1 IS NULL OR workspace = ?1) AND (?2 IS NULL OR connection_id = ?2) \
+     AND (?3 IS NULL OR statement LIKE ?3 ESCAPE '\\') \
+     AND (?4 IS NULL OR executed_at >= ?4) AND (?5 IS NULL OR executed_at < ?5) \
+     ORDER BY id DESC LIMIT ?6 OFFSET ?7",
+  )
+  .bind(workspace)
+  .bind(&filter.connection_id)
+  .bind(contains)
+  .bind(filter.since)
+  .bind(filter.until)
+  .bind(filter.limit.unwrap_or(DEFAULT_LIMIT))
+  .bind(filter.offset.unwrap_or(0))
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())
+}
+
+/// Audit entries matching `filter`, newest first.
+#[tauri::command]
+pub async fn query_audit_log(
+  state: State<'_, AppState>,
+  filter: Option<AuditFilter>,
+) -> Result<Vec<AuditEntry>, String> {
+  query(&state, &filter.unwrap_or_default()).await
+}
+
+/// Writes the entries matching `filter` (without a limit unless one is given) to `path` as
+/// CSV, JSON or NDJSON, oldest first. Returns the number of entries written.
+#[tauri::command]
+pub async fn export_audit_log(
+  state: State<'_, AppState>,
+  path: String,
+  format: Option<String>,
+  filter: Option<AuditFilter>,
+) -> Result<usize, String> {
+  let format = match format {
+    Some(name) => ExportFormat::parse(&name)?,
+    None => ExportFormat::Csv,
+  };
+  let mut filter = filter.unwrap_or_default();
+  filter.limit = Some(filter.limit.unwrap_or(-1));
+  let mut entries = query(&state, &filter).await?;
+  entries.reverse();
+
+  let columns: Vec<ResultColumn> = [
+    ("id", true),
+    ("workspace", false),
+    ("connectionId", false),
+    ("statement", false),
+    ("params", false),
+    ("rowsAffected", true),
+    ("executedAt", true),
+    ("prevHash", false),
+    ("hash", false),
+  ]
+  .into_iter()
+  .map(|(name, numeric)| ResultColumn {
+    name: name.to_string(),
+    type_name: if numeric { "INTEGER" } else { "TEXT" }.to_string(),
+    numeric,
+  })
+  .collect();
+  let rows: Vec<JsonRow> = entries
+    .iter()
+    .filter_map(|entry| match serde_json::to_value(entry) {
+      Ok(serde_json::Value::Object(row)) => Some(row),
+      _ => None,
+    })
+    .collect();
+  let file = std::fs::File::create(&path).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+  let mut out = std::io::BufWriter::new(file);
+  export::write_rows(&mut out, format, &columns, &rows)?;
+  std::io::Write::flush(&mut out).map_err(|e| e.to_string())?;
+  Ok(rows.len())
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditVerification {
+  pub entries: usize,
+  pub valid: bool,
+  /// First entry whose hash or link doesn't match, when the chain is broken.
+  pub first_invalid_id: Option<i64>,
+}
+
+/// Recomputes the hash chain over the whole log.
+#[tauri::command]
+pub async fn verify_audit_log(state: State<'_, AppState>) -> Result<AuditVerification, String> {
+  let pool = store::pool(&state)?;
+  let entries: Vec<AuditEntry> = sqlx::query_as(
+    "SELECT id, workspace, connection_id, statement, params, rows_affected, executed_at, \
+     prev_hash, hash FROM audit_log ORDER BY id",
+  )
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  let mut expected_prev = GENESIS.to_string();
+  let first_invalid_id = entries
+    .iter()
+    .find(|entry| {
+      let hash = entry_hash(
+        &entry.prev_hash,
+        &entry.workspace,
+        &entry.connection_id,
+        &entry.statement,
+        &entry.params,
+        entry.rows_affected,
+        entry.executed_at,
+      );
+      let intact = entry.prev_hash == expected_prev && entry.hash == hash;
+      expected_prev.clone_from(&entry.hash);
+      !intact
+    })
+    .map(|entry| entry.id);
+  Ok(AuditVerification {
+    entries: entries.len(),
+    valid: first_invalid_id.is_none(),
+    first_invalid_id,
+  })
+}
@@ -0,0 +1,157 @@
+//! Developer-only latency harness for backend hot paths (row decoding, serialization, ...).
+//! Only available in debug builds.
+
+use std::time::{Duration, Instant};
+
+use tauri::State;
+
+use crate::db::{self, JsonRow};
+use crate::{results, AppState};
+
+const MAX_ITERATIONS: u32 = 10_000;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryArgs {
+  connection: String,
+  sql: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageArgs {
+  result_id: String,
+  #[serde(default)]
+  offset: usize,
+  #[serde(default = "default_page_size")]
+  limit: usize,
+}
+
+fn default_page_size() -> usize {
+  1000
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchReport {
+  pub name: String,
+  pub iterations: u32,
+  pub total_ms: f64,
+  pub mean_ms: f64,
+  pub min_ms: f64,
+  pub p50_ms: f64,
+  pub p90_ms: f64,
+  pub p99_ms: f64,
+  pub max_ms: f64,
+}
+
+fn millis(d: Duration) -> f64 {
+  d.as_secs_f64() * 1000.0
+}
+
+fn report(name: String, mut samples: Vec<Duration>) -> BenchReport {
+  samples.sort();
+  let percentile = |p: usize| millis(samples[(samples.len() - 1) * p / 100]);
+  let total: Duration = samples.iter().sum();
+  let iterations = u32::try_from(samples.len()).unwrap_or(u32::MAX);
+  BenchReport {
+    name,
+    iterations,
+    total_ms: millis(total),
+    mean_ms: millis(total / iterations),
+    min_ms: millis(samples[0]),
+    p50_ms: percentile(50),
+    p90_ms: percentile(90),
+    p99_ms: percentile(99),
+    max_ms: millis(samples[samples.len() - 1]),
+  }
+}
+
+fn parse_args<T: serde::de::DeserializeOwned>(
+  name: &str,
+  args: serde_json::Value,
+) -> Result<T, String> {
+  serde_json::from_value(args).map_err(|e| format!("Invalid arguments for '{}': {}", name, e))
+}
+
+/// Runs a backend operation `iterations` times and reports its latency distribution.
+///
+/// Operations:
+/// - `fetch` `{connection, sql}`: query round trip plus row decoding
+/// - `serialize` `{connection, sql}`: JSON serialization of rows fetched once up front
+/// - `result_page` `{resultId, offset?, limit?}`: paging and serializing a cached result
+/// - `result_columnar` `{resultId, offset?, limit?}`: the same page in column-major form
+#[tauri::command]
+pub async fn bench_command(
+  state: State<'_, AppState>,
+  name: String,
+  args: serde_json::Value,
+  iterations: u32,
+) -> Result<BenchReport, String> {
+  if !cfg!(debug_assertions) {
+    return Err("bench_command is only available in development builds".to_string());
+  }
+  if iterations == 0 || iterations > MAX_ITERATIONS {
+    return Err(format!(
+      "iterations must be between 1 and {}",
+      MAX_ITERATIONS
+    ));
+  }
+
+  let mut samples = Vec::with_capacity(iterations as usize);
+  match name.as_str() {
+    "fetch" => {
+      let args: QueryArgs = parse_args(&name, args)?;
+      let pool = db::sql_pool(&state, &args.connection)?;
+      for _ in 0..iterations {
+        let start = Instant::now();
+        pool.fetch_with_columns(&args.sql, &[], None).await?;
+        samples.push(start.elapsed());
+      }
+    }
+    "serialize" => {
+      let args: QueryArgs = parse_args(&name, args)?;
+      let pool = db::sql_pool(&state, &args.connection)?;
+      let (_, rows) = pool.fetch_with_columns(&args.sql, &[], None).await?;
+      for _ in 0..iterations {
+        let start = Instant::now();
+        let json: Vec<String> = rows
+          .iter()
+          .map(|row| serde_json::Value::Object(row.clone()).to_string())
+          .collect();
+        samples.push(start.elapsed());
+        std::hint::black_box(json);
+      }
+    }
+    "result_page" => {
+      let args: PageArgs = parse_args(&name, args)?;
+      let result = results::cached(&state, &args.result_id)?;
+      for _ in 0..iterations {
+        let start = Instant::now();
+        let page: Vec<&JsonRow> = result
+          .rows
+          .iter()
+          .skip(args.offset)
+          .take(args.limit)
+          .collect();
+        let json = serde_json::to_string(&page).map_err(|e| e.to_string())?;
+        samples.push(start.elapsed());
+        std::hint::black_box(json);
+      }
+    }
+    "result_columnar" => {
+      let args: PageArgs = parse_args(&name, args)?;
+      let result = results::cached(&state, &args.result_id)?;
+      for _ in 0..iterations {
+        let start = Instant::now();
+        let page = results::columnar_page(&result, args.offset, args.limit);
+        let json = serde_json::to_string(&page).map_err(|e| e.to_string())?;
+        samples.push(start.elapsed());
+        std::hint::black_box(json);
+      }
+    }
+    other => return Err(format!("Unknown benchmark: {}", other)),
+  }
+
+  Ok(report(name, samples))
+}
@@ -0,0 +1,1600 @@
+//! A small MySQL replication client for change feeds: logs in like a replica, asks for the
+//! binary log from a position with `COM_BINLOG_DUMP`, and decodes the row events of the
+//! watched tables into [`RowChange`]s.
+//!
+//! Needs `binlog_format = ROW` and a user with the `REPLICATION SLAVE` and `REPLICATION
+//! CLIENT` privileges. Row events carry values but not column names, so those come from
+//! `information_schema`; values are rendered in their text form, timestamps in UTC.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use base64::Engine;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Oaep, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use sqlx::mysql::{MySqlConnectOptions, MySqlSslMode};
+use sqlx::{MySqlPool, Row};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::cdc::{Operation, RowChange};
+use crate::db::JsonRow;
+use crate::encoding;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often the server sends a heartbeat while the log is idle.
+const HEARTBEAT: Duration = Duration::from_secs(30);
+/// Largest payload of one wire packet; longer ones continue in the next.
+const MAX_PACKET: usize = 0xFF_FFFF;
+const UTF8MB4_GENERAL_CI: u8 = 45;
+
+const CLIENT_LONG_PASSWORD: u32 = 0x1;
+const CLIENT_PROTOCOL_41: u32 = 0x200;
+const CLIENT_SSL: u32 = 0x800;
+const CLIENT_TRANSACTIONS: u32 = 0x2000;
+const CLIENT_SECURE_CONNECTION: u32 = 0x8000;
+const CLIENT_PLUGIN_AUTH: u32 = 0x8_0000;
+const CLIENT_PLUGIN_AUTH_LENENC_DATA: u32 = 0x20_0000;
+
+const COM_QUERY: u8 = 0x03;
+const COM_BINLOG_DUMP: u8 = 0x12;
+
+const EVENT_HEADER_LEN: usize = 19;
+const ROTATE_EVENT: u8 = 4;
+const TABLE_MAP_EVENT: u8 = 19;
+const WRITE_ROWS_EVENT_V1: u8 = 23;
+const UPDATE_ROWS_EVENT_V1: u8 = 24;
+const DELETE_ROWS_EVENT_V1: u8 = 25;
+const WRITE_ROWS_EVENT: u8 = 30;
+const UPDATE_ROWS_EVENT: u8 = 31;
+const DELETE_ROWS_EVENT: u8 = 32;
+const PARTIAL_UPDATE_ROWS_EVENT: u8 = 39;
+const TRANSACTION_PAYLOAD_EVENT: u8 = 40;
+/// Rows event flag marking the last event of a statement.
+const STMT_END_F: u16 = 0x1;
+
+const TYPE_TINY: u8 = 1;
+const TYPE_SHORT: u8 = 2;
+const TYPE_LONG: u8 = 3;
+const TYPE_FLOAT: u8 = 4;
+const TYPE_DOUBLE: u8 = 5;
+const TYPE_NULL: u8 = 6;
+const TYPE_TIMESTAMP: u8 = 7;
+const TYPE_LONGLONG: u8 = 8;
+const TYPE_INT24: u8 = 9;
+const TYPE_DATE: u8 = 10;
+const TYPE_TIME: u8 = 11;
+const TYPE_DATETIME: u8 = 12;
+const TYPE_YEAR: u8 = 13;
+const TYPE_VARCHAR: u8 = 15;
+const TYPE_BIT: u8 = 16;
+const TYPE_TIMESTAMP2: u8 = 17;
+const TYPE_DATETIME2: u8 = 18;
+const TYPE_TIME2: u8 = 19;
+const TYPE_JSON: u8 = 245;
+const TYPE_NEWDECIMAL: u8 = 246;
+const TYPE_ENUM: u8 = 247;
+const TYPE_SET: u8 = 248;
+const TYPE_BLOB: u8 = 252;
+const TYPE_VAR_STRING: u8 = 253;
+const TYPE_STRING: u8 = 254;
+const TYPE_GEOMETRY: u8 = 255;
+
+/// Bytes holding 0 to 9 decimal digits in a packed `DECIMAL`.
+const DIGIT_BYTES: [usize; 10] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4];
+
+/// Where the replication connection goes and as whom.
+pub struct Login {
+  host: String,
+  port: u16,
+  socket: Option<PathBuf>,
+  username: String,
+  password: String,
+  ssl_mode: MySqlSslMode,
+}
+
+impl Login {
+  /// The server of a pool's connections, logging in as `username`.
+  pub fn new(options: &MySqlConnectOptions, username: String, password: String) -> Self {
+    Login {
+      host: options.get_host().to_string(),
+      port: options.get_port(),
+      socket: options.get_socket().cloned(),
+      username,
+      password,
+      ssl_mode: options.get_ssl_mode(),
+    }
+  }
+}
+
+/// Where the server is writing its binary log, and whether its events end in a checksum.
+pub struct Source {
+  pub file: String,
+  pub position: u64,
+  checksum: bool,
+}
+
+/// The current binary log position, once the server is known to log row images.
+pub async fn source(pool: &MySqlPool) -> Result<Source, String> {
+  let (format, checksum): (String, String) = sqlx::query_as(
+    "SELECT CAST(@@GLOBAL.binlog_format AS CHAR), CAST(@@GLOBAL.binlog_checksum AS CHAR)",
+  )
+  .fetch_one(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  if !format.eq_ignore_ascii_case("ROW") {
+    return Err(format!(
+      "Change feeds need binlog_format = ROW; this server logs {}",
+      format
+    ));
+  }
+  // Renamed in MySQL 8.4
+  let status = match sqlx::query("SHOW BINARY LOG STATUS")
+    .fetch_optional(pool)
+    .await
+  {
+    Ok(status) => status,
+    Err(_) => sqlx::query("SHOW MASTER STATUS")
+      .fetch_optional(pool)
+      .await
+      .map_err(|e| e.to_string())?,
+  };
+  let status = status.ok_or("Binary logging is disabled on this server")?;
+  Ok(Source {
+    file: status.try_get("File").map_err(|e| e.to_string())?,
+    position: status.try_get("Position").map_err(|e| e.to_string())?,
+    checksum: !checksum.eq_ignore_ascii_case("NONE"),
+  })
+}
+
+/// Names and declared types of `table`'s columns, in order.
+pub async fn columns(pool: &MySqlPool, schema: &str, table: &str) -> Result<Columns, String> {
+  let rows: Vec<(String, String)> = sqlx::query_as(
+    "SELECT CAST(COLUMN_NAME AS CHAR), CAST(COLUMN_TYPE AS CHAR) FROM information_schema.COLUMNS \
+     WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? ORDER BY ORDINAL_POSITION",
+  )
+  .bind(schema)
+  .bind(table)
+  .fetch_all(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(Columns::new(rows))
+}
+
+/// What row events need to know about a table beyond the binlog's own column types.
+pub struct Columns {
+  names: Vec<String>,
+  unsigned: Vec<bool>,
+  /// Members of `ENUM` and `SET` columns.
+  labels: Vec<Vec<String>>,
+}
+
+impl Columns {
+  /// From `(name, COLUMN_TYPE)` pairs such as `("size", "enum('s','m','l')")`.
+  pub fn new(columns: Vec<(String, String)>) -> Self {
+    let mut names = Vec::with_capacity(columns.len());
+    let mut unsigned = Vec::with_capacity(columns.len());
+    let mut labels = Vec::with_capacity(columns.len());
+    for (name, declared) in columns {
+      let declared = declared.to_lowercase();
+      unsigned.push(declared.contains(" unsigned"));
+      labels.push(members(&declared));
+      names.push(name);
+    }
+    Columns {
+      names,
+      unsigned,
+      labels,
+    }
+  }
+}
+
+/// Quoted members of an `enum(...)` or `set(...)` column type.
+fn members(declared: &str) -> Vec<String> {
+  let Some(list) = declared
+    .strip_prefix("enum(")
+    .or_else(|| declared.strip_prefix("set("))
+  else {
+    return Vec::new();
+  };
+  let mut members = Vec::new();
+  let mut chars = list.chars().peekable();
+  while chars.next() == Some('\'') {
+    let mut member = String::new();
+    while let Some(c) = chars.next() {
+      if c == '\'' {
+        if chars.peek() == Some(&'\'') {
+          chars.next();
+        } else {
+          break;
+        }
+      }
+      member.push(c);
+    }
+    members.push(member);
+    // Past the comma before the next member
+    chars.next();
+  }
+  members
+}
+
+trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+/// A logged-in replication connection streaming binary log events.
+pub struct Stream {
+  io: Box<dyn Io>,
+  /// Bytes read but not yet returned as a packet.
+  buffer: Vec<u8>,
+  seq: u8,
+  tls: bool,
+  checksum: bool,
+  last_read: Instant,
+}
+
+impl Stream {
+  /// Logs in as `login` and asks for the binary log from `file` at `position`. Returns the
+  /// first event as well, so that a refused request fails here.
+  pub async fn open(
+    login: &Login,
+    source: &Source,
+    file: &str,
+    position: u64,
+  ) -> Result<(Self, Vec<u8>), String> {
+    tokio::time::timeout(CONNECT_TIMEOUT, async {
+      let stream = Stream {
+        io: connect(login).await?,
+        buffer: Vec::new(),
+        seq: 0,
+        tls: false,
+        checksum: source.checksum,
+        last_read: Instant::now(),
+      };
+      let mut stream = stream.log_in(login).await?;
+      stream
+        .query(&format!(
+          "SET @master_binlog_checksum = @@GLOBAL.binlog_checksum, \
+           @source_binlog_checksum = @@GLOBAL.binlog_checksum, @master_heartbeat_period = {}",
+          HEARTBEAT.as_nanos()
+        ))
+        .await?;
+      stream.dump(file, position).await?;
+      let first = stream.next_event().await?;
+      Ok((stream, first))
+    })
+    .await
+    .map_err(|_| "Timed out logging in for the binary log".to_string())?
+  }
+
+  /// How long the server has been silent; a live stream hears at least a heartbeat every
+  /// 30 seconds.
+  pub fn idle(&self) -> Duration {
+    self.last_read.elapsed()
+  }
+
+  /// The next event, header included and checksum left off. Cancel safe: dropping the future
+  /// loses no data.
+  pub async fn next_event(&mut self) -> Result<Vec<u8>, String> {
+    let mut packet = self.read_packet().await?;
+    match packet.first() {
+      Some(0x00) => {
+        packet.remove(0);
+        if self.checksum {
+          packet.truncate(packet.len().saturating_sub(4));
+        }
+        Ok(packet)
+      }
+      Some(0xFF) => Err(server_error(&packet)),
+      Some(0xFE) if packet.len() < 9 => Err("The server ended the binary log stream".to_string()),
+      _ => Err("Unexpected packet in the binary log stream".to_string()),
+    }
+  }
+
+  async fn read_packet(&mut self) -> Result<Vec<u8>, String> {
+    loop {
+      if let Some(packet) = self.split_packet() {
+        return Ok(packet);
+      }
+      self.buffer.reserve(64 * 1024);
+      let read = self
+        .io
+        .read_buf(&mut self.buffer)
+        .await
+        .map_err(|e| e.to_string())?;
+      if read == 0 {
+        return Err("The server closed the replication connection".to_string());
+      }
+      self.last_read = Instant::now();
+    }
+  }
+
+  /// Takes the first complete packet, joining the wire packets a long one is split into.
+  fn split_packet(&mut self) -> Option<Vec<u8>> {
+    let mut chunks = Vec::new();
+    let mut at = 0;
+    loop {
+      let header = self.buffer.get(at..at + 4)?;
+      let len = usize::from(header[0]) | usize::from(header[1]) << 8 | usize::from(header[2]) << 16;
+      let seq = header[3];
+      let chunk = self.buffer.get(at + 4..at + 4 + len)?;
+      chunks.push(chunk);
+      at += 4 + len;
+      if len < MAX_PACKET {
+        self.seq = seq.wrapping_add(1);
+        break;
+      }
+    }
+    let packet = chunks.concat();
+    self.buffer.drain(..at);
+    Some(packet)
+  }
+
+  async fn write_packet(&mut self, payload: &[u8]) -> Result<(), String> {
+    let len = u32::try_from(payload.len())
+      .ok()
+      .filter(|len| *len < 0xFF_FFFF)
+      .ok_or("Replication packet too long")?;
+    let mut packet = Vec::with_capacity(payload.len() + 4);
+    packet.extend_from_slice(&len.to_le_bytes()[..3]);
+    packet.push(self.seq);
+    packet.extend_from_slice(payload);
+    self.seq = self.seq.wrapping_add(1);
+    self
+      .io
+      .write_all(&packet)
+      .await
+      .map_err(|e| e.to_string())?;
+    self.io.flush().await.map_err(|e| e.to_string())
+  }
+
+  async fn command(&mut self, payload: &[u8]) -> Result<(), String> {
+    self.seq = 0;
+    self.write_packet(payload).await
+  }
+
+  async fn log_in(mut self, login: &Login) -> Result<Self, String> {
+    let handshake = Handshake::parse(&self.read_packet().await?)?;
+    if handshake.capabilities & CLIENT_PROTOCOL_41 == 0
+      || handshake.capabilities & CLIENT_PLUGIN_AUTH == 0
+    {
+      return Err("The server is too old for change feeds".to_string());
+    }
+    let mut capabilities = (CLIENT_LONG_PASSWORD
+      | CLIENT_PROTOCOL_41
+      | CLIENT_TRANSACTIONS
+      | CLIENT_SECURE_CONNECTION
+      | CLIENT_PLUGIN_AUTH
+      | CLIENT_PLUGIN_AUTH_LENENC_DATA)
+      & handshake.capabilities;
+    let server_tls = handshake.capabilities & CLIENT_SSL != 0;
+    match login.ssl_mode {
+      MySqlSslMode::Disabled => {}
+      MySqlSslMode::Preferred if !server_tls => {}
+      _ if !server_tls => return Err("The server doesn't support TLS".to_string()),
+      mode => {
+        capabilities |= CLIENT_SSL;
+        self.write_packet(&client_header(capabilities)).await?;
+        self = self.start_tls(login, mode).await?;
+      }
+    }
+
+    let mut plugin = handshake.plugin;
+    let mut nonce = handshake.nonce;
+    let mut response = client_header(capabilities);
+    response.extend_from_slice(login.username.as_bytes());
+    response.push(0);
+    let auth = self.auth_response(&plugin, &login.password, &nonce)?;
+    if capabilities & CLIENT_PLUGIN_AUTH_LENENC_DATA == 0 {
+      response.push(u8::try_from(auth.len()).map_err(|e| e.to_string())?);
+    } else {
+      lenenc(&mut response, auth.len());
+    }
+    response.extend_from_slice(&auth);
+    response.extend_from_slice(plugin.as_bytes());
+    response.push(0);
+    self.write_packet(&response).await?;
+
+    loop {
+      let packet = self.read_packet().await?;
+      match packet.first() {
+        Some(0x00) => return Ok(self),
+        Some(0xFF) => return Err(server_error(&packet)),
+        // Switch to the plugin the user's account needs
+        Some(0xFE) => {
+          let mut switch = Reader::new(&packet[1..]);
+          plugin = String::from_utf8_lossy(switch.cstr()?).into_owned();
+          nonce = trim_nul(switch.rest()).to_vec();
+          let auth = self.auth_response(&plugin, &login.password, &nonce)?;
+          self.write_packet(&auth).await?;
+        }
+        Some(0x01) => match &packet[1..] {
+          // caching_sha2_password found the password in its cache
+          [0x03] => {}
+          // caching_sha2_password needs the password itself: in the clear over TLS, else
+          // encrypted with the server's public key
+          [0x04] if self.tls => self.write_packet(&cleartext(&login.password)).await?,
+          [0x04] => self.write_packet(&[0x02]).await?,
+          pem => {
+            let encrypted = rsa_password(pem, &login.password, &nonce)?;
+            self.write_packet(&encrypted).await?;
+          }
+        },
+        _ => return Err("Unexpected packet while logging in".to_string()),
+      }
+    }
+  }
+
+  fn auth_response(&self, plugin: &str, password: &str, nonce: &[u8]) -> Result<Vec<u8>, String> {
+    if password.is_empty() {
+      return Ok(Vec::new());
+    }
+    match plugin {
+      "mysql_native_password" => Ok(scramble::<Sha1>(password, nonce)),
+      "caching_sha2_password" => Ok(scramble::<Sha256>(password, nonce)),
+      "sha256_password" | "mysql_clear_password" if self.tls => Ok(cleartext(password)),
+      // Asks for the server's public key
+      "sha256_password" => Ok(vec![0x01]),
+      other => Err(format!("Unsupported authentication plugin: {}", other)),
+    }
+  }
+
+  async fn start_tls(mut self, login: &Login, mode: MySqlSslMode) -> Result<Self, String> {
+    let connector = native_tls::TlsConnector::builder()
+      .danger_accept_invalid_certs(matches!(
+        mode,
+        MySqlSslMode::Preferred | MySqlSslMode::Required
+      ))
+      .danger_accept_invalid_hostnames(!matches!(mode, MySqlSslMode::VerifyIdentity))
+      .build()
+      .map_err(|e| e.to_string())?;
+    let domain = if login.socket.is_some() {
+      "localhost"
+    } else {
+      login.host.as_str()
+    };
+    let tls = tokio_native_tls::TlsConnector::from(connector)
+      .connect(domain, self.io)
+      .await
+      .map_err(|e| format!("TLS handshake with {} failed: {}", domain, e))?;
+    self.io = Box::new(tls);
+    self.tls = true;
+    Ok(self)
+  }
+
+  async fn query(&mut self, sql: &str) -> Result<(), String> {
+    let mut payload = vec![COM_QUERY];
+    payload.extend_from_slice(sql.as_bytes());
+    self.command(&payload).await?;
+    let packet = self.read_packet().await?;
+    match packet.first() {
+      Some(0x00) => Ok(()),
+      Some(0xFF) => Err(server_error(&packet)),
+      _ => Err(format!("Unexpected reply to {}", sql)),
+    }
+  }
+
+  async fn dump(&mut self, file: &str, position: u64) -> Result<(), String> {
+    let position = u32::try_from(position).map_err(|_| "Binary log position past 4 GiB")?;
+    let mut payload = vec![COM_BINLOG_DUMP];
+    payload.extend_from_slice(&position.to_le_bytes());
+    // Flags: block waiting for new events
+    payload.extend_from_slice(&0u16.to_le_bytes());
+    // A replica id of its own, well away from the small ids servers are usually given
+    payload.extend_from_slice(&(OsRng.next_u32() | 0x8000_0000).to_le_bytes());
+    payload.extend_from_slice(file.as_bytes());
+    self.command(&payload).await
+  }
+}
+
+#[cfg(unix)]
+async fn socket(path: &Path) -> Result<Box<dyn Io>, String> {
+  let stream = tokio::net::UnixStream::connect(path)
+    .await
+    .map_err(|e| format!("Cannot connect to {}: {}", path.display(), e))?;
+  Ok(Box::new(stream))
+}
+
+#[cfg(not(unix))]
+async fn socket(path: &Path) -> Result<Box<dyn Io>, String> {
+  Err(format!(
+    "Change feeds can't follow the binary log over {}",
+    path.display()
+  ))
+}
+
+async fn connect(login: &Login) -> Result<Box<dyn Io>, String> {
+  if let Some(path) = &login.socket {
+    return socket(path).await;
+  }
+  let stream = tokio::net::TcpStream::connect((login.host.as_str(), login.port))
+    .await
+    .map_err(|e| format!("Cannot connect to {}:{}: {}", login.host, login.port, e))?;
+  Ok(Box::new(stream))
+}
+
+struct Handshake {
+  capabilities: u32,
+  nonce: Vec<u8>,
+  plugin: String,
+}
+
+impl Handshake {
+  fn parse(packet: &[u8]) -> Result<Self, String> {
+    if packet.first() == Some(&0xFF) {
+      return Err(server_error(packet));
+    }
+    let mut r = Reader::new(packet);
+    if r.u8()? != 10 {
+      return Err("Unsupported MySQL protocol version".to_string());
+    }
+    let _server_version = r.cstr()?;
+    let _connection_id = r.take(4)?;
+    let mut nonce = r.take(8)?.to_vec();
+    let _filler = r.u8()?;
+    let mut capabilities = u32::from(r.u16()?);
+    let _charset = r.u8()?;
+    let _status = r.u16()?;
+    capabilities |= u32::from(r.u16()?) << 16;
+    let nonce_len = usize::from(r.u8()?);
+    let _reserved = r.take(10)?;
+    if capabilities & CLIENT_SECURE_CONNECTION != 0 {
+      let rest = r.take(nonce_len.saturating_sub(8).max(13))?;
+      nonce.extend_from_slice(trim_nul(rest));
+    }
+    let plugin = if capabilities & CLIENT_PLUGIN_AUTH == 0 {
+      "mysql_native_password".to_string()
+    } else {
+      String::from_utf8_lossy(trim_nul(r.rest())).into_owned()
+    };
+    Ok(Handshake {
+      capabilities,
+      nonce,
+      plugin,
+    })
+  }
+}
+
+/// Capabilities, packet size and character set: how both the TLS request and the login
+/// response begin.
+fn client_header(capabilities: u32) -> Vec<u8> {
+  let mut header = Vec::with_capacity(64);
+  header.extend_from_slice(&capabilities.to_le_bytes());
+  header.extend_from_slice(&0x0100_0000u32.to_le_bytes());
+  header.push(UTF8MB4_GENERAL_CI);
+  header.extend_from_slice(&[0; 23]);
+  header
+}
+
+fn lenenc(out: &mut Vec<u8>, n: usize) {
+  match u16::try_from(n) {
+    Ok(n) if n < 251 => out.extend_from_slice(&n.to_le_bytes()[..1]),
+    Ok(n) => {
+      out.push(0xFC);
+      out.extend_from_slice(&n.to_le_bytes());
+    }
+    Err(_) => {
+      out.push(0xFE);
+      out.extend_from_slice(&u64::try_from(n).unwrap_or(u64::MAX).to_le_bytes());
+    }
+  }
+}
+
+fn trim_nul(bytes: &[u8]) -> &[u8] {
+  bytes.strip_suffix(&[0]).unwrap_or(bytes)
+}
+
+fn cleartext(password: &str) -> Vec<u8> {
+  let mut bytes = password.as_bytes().to_vec();
+  bytes.push(0);
+  bytes
+}
+
+/// `H(password) XOR H(nonce + H(H(password)))`, the challenge response of
+/// `mysql_native_password` (SHA-1) and `caching_sha2_password` (SHA-256, hashes swapped).
+fn scramble<D: Digest>(password: &str, nonce: &[u8]) -> Vec<u8> {
+  let hashed = D::digest(password.as_bytes());
+  let twice = D::digest(&hashed);
+  let mut salted = D::new();
+  if <D as Digest>::output_size() == 20 {
+    salted.update(nonce);
+    salted.update(&twice);
+  } else {
+    salted.update(&twice);
+    salted.update(nonce);
+  }
+  let salted = salted.finalize();
+  hashed
+    .iter()
+    .zip(salted.iter())
+    .map(|(a, b)| a ^ b)
+    .collect()
+}
+
+/// The password XOR-ed with the nonce and encrypted with the server's PEM public key.
+fn rsa_password(pem: &[u8], password: &str, nonce: &[u8]) -> Result<Vec<u8>, String> {
+  let pem = std::str::from_utf8(pem).map_err(|e| e.to_string())?;
+  let key = RsaPublicKey::from_public_key_pem(pem).map_err(|e| e.to_string())?;
+  let mut plain = cleartext(password);
+  for (byte, mask) in plain.iter_mut().zip(nonce.iter().cycle()) {
+    *byte ^= mask;
+  }
+  key
+    .encrypt(&mut OsRng, Oaep::new::<Sha1>(), &plain)
+    .map_err(|e| e.to_string())
+}
+
+fn server_error(packet: &[u8]) -> String {
+  let mut r = Reader::new(packet.get(1..).unwrap_or_default());
+  let code = r.u16().unwrap_or_default();
+  let mut message = r.rest();
+  if message.first() == Some(&b'#') {
+    message = message.get(6..).unwrap_or_default();
+  }
+  format!("MySQL error {}: {}", code, String::from_utf8_lossy(message))
+}
+
+/// Reader over one packet or event.
+struct Reader<'a> {
+  data: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn new(data: &'a [u8]) -> Self {
+    Reader { data, pos: 0 }
+  }
+
+  fn is_empty(&self) -> bool {
+    self.pos >= self.data.len()
+  }
+
+  fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+    let bytes = self
+      .data
+      .get(self.pos..self.pos + n)
+      .ok_or("Truncated binary log event")?;
+    self.pos += n;
+    Ok(bytes)
+  }
+
+  fn rest(&mut self) -> &'a [u8] {
+    let rest = self.data.get(self.pos..).unwrap_or_default();
+    self.pos = self.data.len();
+    rest
+  }
+
+  fn u8(&mut self) -> Result<u8, String> {
+    Ok(self.take(1)?[0])
+  }
+
+  fn u16(&mut self) -> Result<u16, String> {
+    let bytes = self.take(2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+  }
+
+  /// Little-endian unsigned integer of `n` (at most 8) bytes.
+  fn uint(&mut self, n: usize) -> Result<u64, String> {
+    let bytes = self.take(n)?;
+    Ok(bytes.iter().rev().fold(0, |n, b| n << 8 | u64::from(*b)))
+  }
+
+  /// Big-endian unsigned integer of `n` (at most 8) bytes.
+  fn uint_be(&mut self, n: usize) -> Result<u64, String> {
+    let bytes = self.take(n)?;
+    Ok(bytes.iter().fold(0, |n, b| n << 8 | u64::from(*b)))
+  }
+
+  /// Length-encoded integer.
+  fn lenenc(&mut self) -> Result<u64, String> {
+    match self.u8()? {
+      0xFC => self.uint(2),
+      0xFD => self.uint(3),
+      0xFE => self.uint(8),
+      n => Ok(u64::from(n)),
+    }
+  }
+
+  fn len(&mut self, n: usize) -> Result<usize, String> {
+    usize::try_from(self.uint(n)?).map_err(|e| e.to_string())
+  }
+
+  fn cstr(&mut self) -> Result<&'a [u8], String> {
+    let rest = self.data.get(self.pos..).unwrap_or_default();
+    let len = rest
+      .iter()
+      .position(|b| *b == 0)
+      .ok_or("Unterminated string in packet")?;
+    self.pos += len + 1;
+    Ok(&rest[..len])
+  }
+}
+
+/// A table as described by its last `TABLE_MAP` event.
+struct TableMap {
+  schema: String,
+  table: String,
+  types: Vec<u8>,
+  /// Type metadata: lengths, precision, fractional digits, or a string's real type.
+  meta: Vec<[u8; 2]>,
+}
+
+impl TableMap {
+  /// Table id and the table it maps to.
+  fn parse(body: &[u8]) -> Result<(u64, Self), String> {
+    let mut r = Reader::new(body);
+    let id = r.uint(6)?;
+    let _flags = r.u16()?;
+    let len = usize::from(r.u8()?);
+    let schema = String::from_utf8_lossy(r.take(len)?).into_owned();
+    let _nul = r.u8()?;
+    let len = usize::from(r.u8()?);
+    let table = String::from_utf8_lossy(r.take(len)?).into_owned();
+    let _nul = r.u8()?;
+    let count = usize::try_from(r.lenenc()?).map_err(|e| e.to_string())?;
+    let types = r.take(count)?.to_vec();
+    let len = usize::try_from(r.lenenc()?).map_err(|e| e.to_string())?;
+    let mut m = Reader::new(r.take(len)?);
+    let meta = types
+      .iter()
+      .map(|t| match *t {
+        TYPE_FLOAT | TYPE_DOUBLE | TYPE_TIMESTAMP2 | TYPE_DATETIME2 | TYPE_TIME2 | TYPE_JSON
+        | TYPE_BLOB | TYPE_GEOMETRY => Ok([m.u8()?, 0]),
+        TYPE_VARCHAR | TYPE_BIT | TYPE_NEWDECIMAL | TYPE_ENUM | TYPE_SET | TYPE_VAR_STRING
+        | TYPE_STRING => {
+          let bytes = m.take(2)?;
+          Ok([bytes[0], bytes[1]])
+        }
+        _ => Ok([0, 0]),
+      })
+      .collect::<Result<_, String>>()?;
+    Ok((
+      id,
+      TableMap {
+        schema,
+        table,
+        types,
+        meta,
+      },
+    ))
+  }
+}
+
+/// Turns the events of one binary log into row changes of the watched tables.
+pub struct Decoder {
+  schema: String,
+  tables: Vec<String>,
+  file: String,
+  /// End of the last event read.
+  position: u64,
+  /// Where a new stream picks up without losing a statement's table maps.
+  resume: u64,
+  maps: HashMap<u64, TableMap>,
+  columns: HashMap<String, Columns>,
+}
+
+impl Decoder {
+  /// Watches `tables` of database `schema`, reading `file` from `position`.
+  pub fn new(schema: String, tables: Vec<String>, file: String, position: u64) -> Self {
+    Decoder {
+      schema,
+      tables,
+      file,
+      position,
+      resume: position,
+      maps: HashMap::new(),
+      columns: HashMap::new(),
+    }
+  }
+
+  pub fn schema(&self) -> &str {
+    &self.schema
+  }
+
+  /// File and position a new stream should start from.
+  pub fn resume(&self) -> (&str, u64) {
+    (&self.file, self.resume)
+  }
+
+  fn watched(&self, schema: &str, table: &str) -> bool {
+    schema == self.schema && self.tables.iter().any(|t| t == table)
+  }
+
+  /// A watched table whose columns must be looked up before `event` can be decoded.
+  pub fn table_to_describe(&self, event: &[u8]) -> Option<String> {
+    if event.get(4) != Some(&TABLE_MAP_EVENT) {
+      return None;
+    }
+    let (_, map) = TableMap::parse(event.get(EVENT_HEADER_LEN..)?).ok()?;
+    let known = self
+      .columns
+      .get(&map.table)
+      .is_some_and(|c| c.names.len() == map.types.len());
+    (self.watched(&map.schema, &map.table) && !known).then_some(map.table)
+  }
+
+  pub fn describe(&mut self, table: String, columns: Columns) {
+    self.columns.insert(table, columns);
+  }
+
+  /// Row changes in `event` (as returned by [`Stream::next_event`]).
+  pub fn decode(&mut self, event: &[u8]) -> Result<Vec<RowChange>, String> {
+    let mut header = Reader::new(event);
+    let _timestamp = header.take(4)?;
+    let kind = header.u8()?;
+    let _server_id = header.take(4)?;
+    let _size = header.take(4)?;
+    let end = header.uint(4)?;
+    let _flags = header.u16()?;
+    let body = &event[EVENT_HEADER_LEN..];
+    // Artificial events (the rotate and format description opening a stream) have no place
+    if end != 0 {
+      self.position = end;
+    }
+    let position = format!("{}:{}", self.file, self.position);
+    let mut statement_ends = true;
+    let changes = match kind {
+      ROTATE_EVENT => {
+        let mut r = Reader::new(body);
+        self.position = r.uint(8)?;
+        self.file = String::from_utf8_lossy(r.rest()).into_owned();
+        Vec::new()
+      }
+      TABLE_MAP_EVENT => {
+        let (id, map) = TableMap::parse(body)?;
+        // Ids are reused once a table leaves the table cache
+        if self.watched(&map.schema, &map.table) {
+          self.maps.insert(id, map);
+        } else {
+          self.maps.remove(&id);
+        }
+        statement_ends = false;
+        Vec::new()
+      }
+      WRITE_ROWS_EVENT_V1 | UPDATE_ROWS_EVENT_V1 | DELETE_ROWS_EVENT_V1 | WRITE_ROWS_EVENT
+      | UPDATE_ROWS_EVENT | DELETE_ROWS_EVENT => {
+        let (changes, flags) = self.rows(kind, body, &position)?;
+        statement_ends = flags & STMT_END_F != 0;
+        changes
+      }
+      PARTIAL_UPDATE_ROWS_EVENT => {
+        return Err("Partial JSON updates (binlog_row_value_options) aren't supported".to_string())
+      }
+      TRANSACTION_PAYLOAD_EVENT => {
+        return Err(
+          "Compressed transactions (binlog_transaction_compression) aren't supported".to_string(),
+        )
+      }
+      _ => Vec::new(),
+    };
+    if statement_ends {
+      self.resume = self.position;
+    }
+    Ok(changes)
+  }
+
+  /// Changes in a rows event, and the event's flags.
+  fn rows(&self, kind: u8, body: &[u8], position: &str) -> Result<(Vec<RowChange>, u16), String> {
+    let mut r = Reader::new(body);
+    let id = r.uint(6)?;
+    let flags = r.u16()?;
+    if kind >= WRITE_ROWS_EVENT {
+      let extra = usize::from(r.u16()?);
+      r.take(extra.saturating_sub(2))?;
+    }
+    let Some(map) = self.maps.get(&id) else {
+      return Ok((Vec::new(), flags));
+    };
+    let count = usize::try_from(r.lenenc()?).map_err(|e| e.to_string())?;
+    let before = bitmap(r.take(count.div_ceil(8))?, count);
+    let after = if matches!(kind, UPDATE_ROWS_EVENT_V1 | UPDATE_ROWS_EVENT) {
+      bitmap(r.take(count.div_ceil(8))?, count)
+    } else {
+      before.clone()
+    };
+    let columns = self.columns.get(&map.table);
+    let mut changes = Vec::new();
+    while !r.is_empty() {
+      let image = image(&mut r, map, columns, &before)?;
+      let (operation, row, old) = match kind {
+        WRITE_ROWS_EVENT_V1 | WRITE_ROWS_EVENT => (Operation::Insert, Some(image), None),
+        DELETE_ROWS_EVENT_V1 | DELETE_ROWS_EVENT => (Operation::Delete, None, Some(image)),
+        _ => (
+          Operation::Update,
+          Some(self::image(&mut r, map, columns, &after)?),
+          Some(image),
+        ),
+      };
+      changes.push(RowChange {
+        table: map.table.clone(),
+        operation,
+        row,
+        old,
+        position: position.to_string(),
+      });
+    }
+    Ok((changes, flags))
+  }
+}
+
+fn bitmap(bytes: &[u8], count: usize) -> Vec<bool> {
+  (0..count)
+    .map(|i| bytes[i / 8] & (1 << (i % 8)) != 0)
+    .collect()
+}
+
+/// One row image, holding the columns flagged in `present`.
+fn image(
+  r: &mut Reader,
+  map: &TableMap,
+  columns: Option<&Columns>,
+  present: &[bool],
+) -> Result<JsonRow, String> {
+  let count = present.iter().filter(|p| **p).count();
+  let nulls = bitmap(r.take(count.div_ceil(8))?, count);
+  let mut row = JsonRow::new();
+  let included = present.iter().enumerate().filter(|(_, p)| **p);
+  for ((i, _), null) in included.zip(nulls) {
+    let name = columns
+      .and_then(|c| c.names.get(i))
+      .cloned()
+      .unwrap_or_else(|| format!("column{}", i + 1));
+    let value = if null {
+      serde_json::Value::Null
+    } else {
+      let unsigned = columns.is_some_and(|c| c.unsigned.get(i) == Some(&true));
+      let labels = columns
+        .and_then(|c| c.labels.get(i))
+        .map_or(&[][..], Vec::as_slice);
+      value(r, map.types[i], map.meta[i], unsigned, labels)?
+    };
+    row.insert(name, value);
+  }
+  Ok(row)
+}
+
+fn text(s: String) -> serde_json::Value {
+  serde_json::Value::String(s)
+}
+
+/// Character data as text, or `0x`-prefixed hex when it isn't UTF-8.
+fn bytes_text(bytes: &[u8]) -> serde_json::Value {
+  text(
+    std::str::from_utf8(bytes)
+      .map_or_else(|_| format!("0x{}", encoding::hex(bytes)), str::to_string),
+  )
+}
+
+/// Sign-extends the low `bytes` bytes of `n`.
+fn signed(n: u64, bytes: u32) -> i64 {
+  let shift = 64 - bytes * 8;
+  i64::from_ne_bytes((n << shift).to_ne_bytes()) >> shift
+}
+
+fn integer(r: &mut Reader, bytes: usize, unsigned: bool) -> Result<serde_json::Value, String> {
+  let n = r.uint(bytes)?;
+  Ok(text(if unsigned {
+    n.to_string()
+  } else {
+    signed(n, u32::try_from(bytes).map_err(|e| e.to_string())?).to_string()
+  }))
+}
+
+/// One non-null column value in its text form.
+fn value(
+  r: &mut Reader,
+  kind: u8,
+  meta: [u8; 2],
+  unsigned: bool,
+  labels: &[String],
+) -> Result<serde_json::Value, String> {
+  Ok(match kind {
+    TYPE_TINY => integer(r, 1, unsigned)?,
+    TYPE_SHORT => integer(r, 2, unsigned)?,
+    TYPE_INT24 => integer(r, 3, unsigned)?,
+    TYPE_LONG => integer(r, 4, unsigned)?,
+    TYPE_LONGLONG => integer(r, 8, unsigned)?,
+    TYPE_FLOAT => {
+      let bytes = r.take(4)?;
+      text(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).to_string())
+    }
+    TYPE_DOUBLE => text(f64::from_bits(r.uint(8)?).to_string()),
+    TYPE_NEWDECIMAL => text(decimal(r, meta[0], meta[1])?),
+    TYPE_YEAR => match r.u8()? {
+      0 => text("0000".to_string()),
+      year => text((1900 + u32::from(year)).to_string()),
+    },
+    TYPE_DATE => {
+      let date = r.uint(3)?;
+      text(format!(
+        "{:04}-{:02}-{:02}",
+        date >> 9,
+        (date >> 5) & 15,
+        date & 31
+      ))
+    }
+    TYPE_TIME => {
+      let time = signed(r.uint(3)?, 3);
+      let sign = if time < 0 { "-" } else { "" };
+      let time = time.abs();
+      text(format!(
+        "{}{:02}:{:02}:{:02}",
+        sign,
+        time / 10000,
+        time / 100 % 100,
+        time % 100
+      ))
+    }
+    TYPE_DATETIME => {
+      let n = r.uint(8)?;
+      let (date, time) = (n / 1_000_000, n % 1_000_000);
+      text(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        date / 10000,
+        date / 100 % 100,
+        date % 100,
+        time / 10000,
+        time / 100 % 100,
+        time % 100
+      ))
+    }
+    TYPE_TIMESTAMP => text(timestamp(r.uint(4)?, 0, 0)),
+    TYPE_TIMESTAMP2 => {
+      let seconds = r.uint_be(4)?;
+      let micros = fraction(r, meta[0])?;
+      text(timestamp(seconds, micros, meta[0]))
+    }
+    TYPE_DATETIME2 => {
+      let int = i64::try_from(r.uint_be(5)?).map_err(|e| e.to_string())? - 0x80_0000_0000;
+      let micros = i64::from(fraction(r, meta[0])?);
+      text(datetime((int << 24) + micros, meta[0]))
+    }
+    TYPE_TIME2 => text(time(time2_packed(r, meta[0])?, meta[0])),
+    TYPE_BIT => {
+      let bits = usize::from(meta[1]) * 8 + usize::from(meta[0]);
+      let n = r.uint_be(usize::from(meta[1]) + usize::from(meta[0] > 0))?;
+      text(format!("{:0width$b}", n, width = bits))
+    }
+    TYPE_VARCHAR | TYPE_VAR_STRING => {
+      let max = u16::from_le_bytes(meta);
+      let len = r.len(if max < 256 { 1 } else { 2 })?;
+      bytes_text(r.take(len)?)
+    }
+    TYPE_STRING | TYPE_ENUM | TYPE_SET => {
+      // CHAR lengths past 255 borrow two bits of the type byte
+      let (real, max) = if meta[0] & 0x30 == 0x30 {
+        (meta[0], usize::from(meta[1]))
+      } else {
+        (
+          meta[0] | 0x30,
+          usize::from(meta[1]) | usize::from((meta[0] & 0x30) ^ 0x30) << 4,
+        )
+      };
+      match real {
+        TYPE_ENUM => {
+          let index = r.len(usize::from(meta[1]))?;
+          text(match index {
+            0 => String::new(),
+            n => labels.get(n - 1).cloned().unwrap_or_else(|| n.to_string()),
+          })
+        }
+        TYPE_SET => {
+          let bits = r.uint(usize::from(meta[1]))?;
+          let chosen: Vec<String> = (0..64)
+            .filter(|i| bits & (1 << i) != 0)
+            .map(|i| labels.get(i).cloned().unwrap_or_else(|| i.to_string()))
+            .collect();
+          text(chosen.join(","))
+        }
+        _ => {
+          let len = r.len(if max < 256 { 1 } else { 2 })?;
+          bytes_text(r.take(len)?)
+        }
+      }
+    }
+    TYPE_BLOB | TYPE_GEOMETRY => {
+      let len = r.len(usize::from(meta[0]))?;
+      bytes_text(r.take(len)?)
+    }
+    TYPE_JSON => {
+      let len = r.len(usize::from(meta[0]))?;
+      let json = match r.take(len)? {
+        [] => serde_json::Value::Null,
+        [kind, value @ ..] => json(*kind, value)?,
+      };
+      text(json.to_string())
+    }
+    TYPE_NULL => serde_json::Value::Null,
+    other => return Err(format!("Unsupported column type {} in a row event", other)),
+  })
+}
+
+/// Microseconds stored in `fsp` fractional digits.
+fn fraction(r: &mut Reader, fsp: u8) -> Result<u32, String> {
+  let micros = match fsp {
+    0 => 0,
+    1 | 2 => r.uint_be(1)? * 10000,
+    3 | 4 => r.uint_be(2)? * 100,
+    _ => r.uint_be(3)?,
+  };
+  u32::try_from(micros).map_err(|e| e.to_string())
+}
+
+/// `.ffffff` cut to `fsp` digits.
+fn fraction_text(micros: i64, fsp: u8) -> String {
+  let digits = format!(".{:06}", micros);
+  digits[..=usize::from(fsp.min(6))]
+    .trim_end_matches('.')
+    .to_string()
+}
+
+fn timestamp(seconds: u64, micros: u32, fsp: u8) -> String {
+  if seconds == 0 {
+    return "0000-00-00 00:00:00".to_string();
+  }
+  let time = i64::try_from(seconds)
+    .ok()
+    .and_then(|s| chrono::DateTime::from_timestamp(s, micros * 1000));
+  match time {
+    Some(time) => format!(
+      "{}{}",
+      time.format("%Y-%m-%d %H:%M:%S"),
+      fraction_text(i64::from(micros), fsp)
+    ),
+    None => seconds.to_string(),
+  }
+}
+
+/// A `DATETIME` in MySQL's packed form: date and time bits over 24 bits of microseconds.
+fn datetime(packed: i64, fsp: u8) -> String {
+  let whole = packed >> 24;
+  let (date, time) = (whole >> 17, whole % (1 << 17));
+  let month = date >> 5;
+  format!(
+    "{:04}-{:02}-{:02} {:02}:{:02}:{:02}{}",
+    month / 13,
+    month % 13,
+    date % 32,
+    time >> 12,
+    (time >> 6) % 64,
+    time % 64,
+    fraction_text(packed % (1 << 24), fsp)
+  )
+}
+
+/// A `TIME` in MySQL's packed form.
+fn time(packed: i64, fsp: u8) -> String {
+  let sign = if packed < 0 { "-" } else { "" };
+  let packed = packed.abs();
+  let whole = packed >> 24;
+  format!(
+    "{}{:02}:{:02}:{:02}{}",
+    sign,
+    (whole >> 12) % (1 << 10),
+    (whole >> 6) % 64,
+    whole % 64,
+    fraction_text(packed % (1 << 24), fsp)
+  )
+}
+
+/// Packed form of a `TIME2` column, whose negative values borrow from the whole seconds.
+fn time2_packed(r: &mut Reader, fsp: u8) -> Result<i64, String> {
+  let read = |r: &mut Reader, n| {
+    r.uint_be(n)
+      .and_then(|n| i64::try_from(n).map_err(|e| e.to_string()))
+  };
+  if fsp >= 5 {
+    return Ok(read(r, 6)? - 0x8000_0000_0000);
+  }
+  let mut whole = read(r, 3)? - 0x80_0000;
+  let (bytes, scale) = match fsp {
+    0 => return Ok(whole << 24),
+    1 | 2 => (1, 10000),
+    _ => (2, 100),
+  };
+  let mut frac = read(r, bytes)?;
+  if whole < 0 && frac != 0 {
+    whole += 1;
+    frac -= 1 << (8 * bytes);
+  }
+  Ok((whole << 24) + frac * scale)
+}
+
+/// A packed `DECIMAL(precision, scale)`: nine digits per four bytes, sign in the top bit.
+fn decimal(r: &mut Reader, precision: u8, scale: u8) -> Result<String, String> {
+  let (whole, scale) = (
+    usize::from(precision.saturating_sub(scale)),
+    usize::from(scale),
+  );
+  let size = whole / 9 * 4 + DIGIT_BYTES[whole % 9] + scale / 9 * 4 + DIGIT_BYTES[scale % 9];
+  let mut bytes = r.take(size)?.to_vec();
+  let Some(first) = bytes.first_mut() else {
+    return Ok("0".to_string());
+  };
+  let negative = *first & 0x80 == 0;
+  *first ^= 0x80;
+  if negative {
+    for b in &mut bytes {
+      *b = !*b;
+    }
+  }
+  let mut d = Reader::new(&bytes);
+  let mut digits = String::new();
+  if whole % 9 > 0 {
+    let _ = write!(digits, "{}", d.uint_be(DIGIT_BYTES[whole % 9])?);
+  }
+  for _ in 0..whole / 9 {
+    let _ = write!(digits, "{:09}", d.uint_be(4)?);
+  }
+  let mut number = if negative { "-" } else { "" }.to_string();
+  match digits.trim_start_matches('0') {
+    "" => number.push('0'),
+    digits => number.push_str(digits),
+  }
+  if scale > 0 {
+    number.push('.');
+    for _ in 0..scale / 9 {
+      let _ = write!(number, "{:09}", d.uint_be(4)?);
+    }
+    if scale % 9 > 0 {
+      let _ = write!(
+        number,
+        "{:0width$}",
+        d.uint_be(DIGIT_BYTES[scale % 9])?,
+        width = scale % 9
+      );
+    }
+  }
+  Ok(number)
+}
+
+/// Length of a string in a binary JSON document: seven bits per byte, low bits first.
+fn json_len(r: &mut Reader) -> Result<usize, String> {
+  let mut len = 0;
+  for shift in (0..35).step_by(7) {
+    let byte = r.u8()?;
+    len |= usize::from(byte & 0x7F) << shift;
+    if byte & 0x80 == 0 {
+      return Ok(len);
+    }
+  }
+  Err("Malformed JSON length".to_string())
+}
+
+/// A value of MySQL's binary JSON format of type `kind`, starting at `data`.
+fn json(kind: u8, data: &[u8]) -> Result<serde_json::Value, String> {
+  use serde_json::Value;
+  let mut r = Reader::new(data);
+  Ok(match kind {
+    0x00..=0x03 => return json_container(data, kind & 1 == 1, kind < 2),
+    0x04 => match r.u8()? {
+      1 => Value::Bool(true),
+      2 => Value::Bool(false),
+      _ => Value::Null,
+    },
+    0x05 => Value::from(signed(r.uint(2)?, 2)),
+    0x06 => Value::from(r.uint(2)?),
+    0x07 => Value::from(signed(r.uint(4)?, 4)),
+    0x08 => Value::from(r.uint(4)?),
+    0x09 => Value::from(signed(r.uint(8)?, 8)),
+    0x0A => Value::from(r.uint(8)?),
+    0x0B => Value::from(f64::from_bits(r.uint(8)?)),
+    0x0C => {
+      let len = json_len(&mut r)?;
+      Value::String(String::from_utf8_lossy(r.take(len)?).into_owned())
+    }
+    0x0F => {
+      let field = r.u8()?;
+      let len = json_len(&mut r)?;
+      let mut opaque = Reader::new(r.take(len)?);
+      match field {
+        TYPE_NEWDECIMAL => {
+          let (precision, scale) = (opaque.u8()?, opaque.u8()?);
+          let number = decimal(&mut opaque, precision, scale)?;
+          serde_json::from_str(&number).unwrap_or(Value::String(number))
+        }
+        TYPE_DATETIME | TYPE_TIMESTAMP | TYPE_DATE | TYPE_TIME => {
+          let packed = signed(opaque.uint(8)?, 8);
+          let fsp = if packed % (1 << 24) == 0 { 0 } else { 6 };
+          Value::String(match field {
+            TYPE_DATE => datetime(packed, 0)[..10].to_string(),
+            TYPE_TIME => time(packed, fsp),
+            _ => datetime(packed, fsp),
+          })
+        }
+        _ => Value::String(format!(
+          "base64:type{}:{}",
+          field,
+          base64::engine::general_purpose::STANDARD.encode(opaque.rest())
+        )),
+      }
+    }
+    other => return Err(format!("Unsupported JSON value type {}", other)),
+  })
+}
+
+/// An object or array of the binary JSON format; offsets count from its start.
+fn json_container(data: &[u8], large: bool, object: bool) -> Result<serde_json::Value, String> {
+  let size = if large { 4 } else { 2 };
+  let mut r = Reader::new(data);
+  let count = r.len(size)?;
+  let _bytes = r.len(size)?;
+  let mut keys = Vec::with_capacity(if object { count } else { 0 });
+  if object {
+    for _ in 0..count {
+      let offset = r.len(size)?;
+      let len = r.len(2)?;
+      let key = data
+        .get(offset..offset + len)
+        .ok_or("Malformed JSON object key")?;
+      keys.push(String::from_utf8_lossy(key).into_owned());
+    }
+  }
+  let mut values = Vec::with_capacity(count);
+  for _ in 0..count {
+    let kind = r.u8()?;
+    let entry = r.take(size)?;
+    // Literals and integers that fit sit in the entry itself
+    let inline = matches!(kind, 0x04..=0x06) || (large && matches!(kind, 0x07 | 0x08));
+    let value = if inline {
+      json(kind, entry)?
+    } else {
+      let offset = Reader::new(entry).len(size)?;
+      json(
+        kind,
+        data.get(offset..).ok_or("Malformed JSON value offset")?,
+      )?
+    };
+    values.push(value);
+  }
+  Ok(if object {
+    serde_json::Value::Object(keys.into_iter().zip(values).collect())
+  } else {
+    serde_json::Value::Array(values)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn event(kind: u8, end: u32, body: &[u8]) -> Vec<u8> {
+    let mut event = vec![0; 4];
+    event.push(kind);
+    event.extend_from_slice(&1u32.to_le_bytes());
+    let size = u32::try_from(EVENT_HEADER_LEN + body.len()).unwrap();
+    event.extend_from_slice(&size.to_le_bytes());
+    event.extend_from_slice(&end.to_le_bytes());
+    event.extend_from_slice(&0u16.to_le_bytes());
+    event.extend_from_slice(body);
+    event
+  }
+
+  fn table_map(id: u8, table: &str) -> Vec<u8> {
+    let mut body = vec![id, 0, 0, 0, 0, 0, 0, 0, 4];
+    body.extend_from_slice(b"shop\0");
+    body.push(u8::try_from(table.len()).unwrap());
+    body.extend_from_slice(table.as_bytes());
+    body.push(0);
+    // id INT, name VARCHAR(20), price DECIMAL(14,4)
+    body.extend_from_slice(&[3, TYPE_LONG, TYPE_VARCHAR, TYPE_NEWDECIMAL]);
+    body.extend_from_slice(&[4, 20, 0, 14, 4]);
+    body.push(0b110);
+    event(TABLE_MAP_EVENT, 200, &body)
+  }
+
+  const PRICE: [u8; 7] = [0x81, 0x0D, 0xFB, 0x38, 0xD2, 0x04, 0xD2];
+
+  fn rows(kind: u8, end: u32, flags: u8, images: &[&[u8]]) -> Vec<u8> {
+    let mut body = vec![7, 0, 0, 0, 0, 0, flags, 0, 2, 0, 3, 0b111];
+    if kind == UPDATE_ROWS_EVENT {
+      body.push(0b111);
+    }
+    for image in images {
+      body.extend_from_slice(image);
+    }
+    event(kind, end, &body)
+  }
+
+  fn decoder() -> Decoder {
+    let mut decoder = Decoder::new(
+      "shop".to_string(),
+      vec!["items".to_string()],
+      "binlog.000001".to_string(),
+      100,
+    );
+    decoder.describe(
+      "items".to_string(),
+      Columns::new(vec![
+        ("id".to_string(), "int unsigned".to_string()),
+        ("name".to_string(), "varchar(20)".to_string()),
+        ("price".to_string(), "decimal(14,4)".to_string()),
+      ]),
+    );
+    decoder
+  }
+
+  fn image(id: u8, name: Option<&str>) -> Vec<u8> {
+    let mut image = vec![if name.is_some() { 0 } else { 0b010 }, id, 0, 0, 0];
+    if let Some(name) = name {
+      image.push(u8::try_from(name.len()).unwrap());
+      image.extend_from_slice(name.as_bytes());
+    }
+    image.extend_from_slice(&PRICE);
+    image
+  }
+
+  #[test]
+  fn decodes_row_events_of_watched_tables() {
+    let mut decoder = decoder();
+    assert!(decoder.decode(&table_map(7, "items")).unwrap().is_empty());
+    let insert = rows(
+      WRITE_ROWS_EVENT,
+      300,
+      1,
+      &[&image(1, Some("pen")), &image(2, None)],
+    );
+    let changes = decoder.decode(&insert).unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0].operation, Operation::Insert);
+    assert_eq!(changes[0].position, "binlog.000001:300");
+    let row = changes[0].row.as_ref().unwrap();
+    assert_eq!(row["id"], "1");
+    assert_eq!(row["name"], "pen");
+    assert_eq!(row["price"], "1234567890.1234");
+    assert_eq!(
+      changes[1].row.as_ref().unwrap()["name"],
+      serde_json::Value::Null
+    );
+
+    let update = rows(
+      UPDATE_ROWS_EVENT,
+      400,
+      1,
+      &[&image(1, Some("pen")), &image(1, Some("ink"))],
+    );
+    let changes = decoder.decode(&update).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].operation, Operation::Update);
+    assert_eq!(changes[0].old.as_ref().unwrap()["name"], "pen");
+    assert_eq!(changes[0].row.as_ref().unwrap()["name"], "ink");
+
+    let delete = rows(DELETE_ROWS_EVENT, 500, 1, &[&image(2, None)]);
+    let changes = decoder.decode(&delete).unwrap();
+    assert_eq!(changes[0].operation, Operation::Delete);
+    assert!(changes[0].row.is_none());
+    assert_eq!(decoder.resume(), ("binlog.000001", 500));
+  }
+
+  #[test]
+  fn skips_unwatched_tables_and_resumes_at_statement_ends() {
+    let mut decoder = decoder();
+    assert_eq!(decoder.table_to_describe(&table_map(7, "items")), None);
+    assert_eq!(decoder.table_to_describe(&table_map(7, "orders")), None);
+    decoder.decode(&table_map(7, "orders")).unwrap();
+    let insert = rows(WRITE_ROWS_EVENT, 300, 1, &[&image(1, Some("pen"))]);
+    assert!(decoder.decode(&insert).unwrap().is_empty());
+
+    decoder.decode(&table_map(7, "items")).unwrap();
+    assert_eq!(decoder.resume(), ("binlog.000001", 300));
+    let partial = rows(WRITE_ROWS_EVENT, 400, 0, &[&image(1, Some("pen"))]);
+    assert_eq!(decoder.decode(&partial).unwrap().len(), 1);
+    assert_eq!(decoder.resume(), ("binlog.000001", 300));
+
+    let mut rotate = 4u64.to_le_bytes().to_vec();
+    rotate.extend_from_slice(b"binlog.000002");
+    decoder.decode(&event(ROTATE_EVENT, 0, &rotate)).unwrap();
+    assert_eq!(decoder.resume(), ("binlog.000002", 4));
+  }
+
+  #[test]
+  fn asks_for_columns_of_new_or_altered_tables() {
+    let mut decoder = Decoder::new(
+      "shop".to_string(),
+      vec!["items".to_string()],
+      "binlog.000001".to_string(),
+      4,
+    );
+    let map = table_map(7, "items");
+    assert_eq!(decoder.table_to_describe(&map), Some("items".to_string()));
+    decoder.describe(
+      "items".to_string(),
+      Columns::new(vec![("id".to_string(), "int".to_string())]),
+    );
+    assert_eq!(decoder.table_to_describe(&map), Some("items".to_string()));
+  }
+
+  #[test]
+  fn parses_enum_and_set_members() {
+    assert_eq!(members("enum('s','m','it''s')"), ["s", "m", "it's"]);
+    assert_eq!(members("set('a,b','c')"), ["a,b", "c"]);
+    assert!(members("varchar(20)").is_empty());
+  }
+
+  #[test]
+  fn decodes_packed_decimals() {
+    assert_eq!(
+      decimal(&mut Reader::new(&PRICE), 14, 4).unwrap(),
+      "1234567890.1234"
+    );
+    let negative = [0x7E, 0xF2, 0x04, 0xC7, 0x2D, 0xFB, 0x2D];
+    assert_eq!(
+      decimal(&mut Reader::new(&negative), 14, 4).unwrap(),
+      "-1234567890.1234"
+    );
+    assert_eq!(
+      decimal(&mut Reader::new(&[0x80, 0x00]), 4, 2).unwrap(),
+      "0.00"
+    );
+  }
+
+  fn text_of(bytes: &[u8], kind: u8, meta: [u8; 2], labels: &[String]) -> serde_json::Value {
+    value(&mut Reader::new(bytes), kind, meta, false, labels).unwrap()
+  }
+
+  #[test]
+  fn decodes_temporal_values() {
+    let whole: u64 = ((((2024 * 13 + 3) << 5) + 5) << 17) + (10 << 12) + (20 << 6) + 30;
+    let datetime = (whole + 0x80_0000_0000).to_be_bytes();
+    assert_eq!(
+      text_of(&datetime[3..], TYPE_DATETIME2, [0, 0], &[]),
+      "2024-03-05 10:20:30"
+    );
+    let time = [0x7F, 0xEF, 0xFF, 0xCE];
+    assert_eq!(text_of(&time, TYPE_TIME2, [1, 0], &[]), "-01:00:00.5");
+    assert_eq!(
+      text_of(&[0x65, 0xD0, 0x0F], TYPE_DATE, [0, 0], &[]),
+      "2024-03-05"
+    );
+  }
+
+  #[test]
+  fn decodes_enum_set_and_bit_values() {
+    let labels = ["s".to_string(), "m".to_string(), "l".to_string()];
+    assert_eq!(text_of(&[2], TYPE_STRING, [TYPE_ENUM, 1], &labels), "m");
+    assert_eq!(
+      text_of(&[0b101], TYPE_STRING, [TYPE_SET, 1], &labels),
+      "s,l"
+    );
+    assert_eq!(
+      text_of(&[3, b'a', b'b', b'c'], TYPE_STRING, [TYPE_STRING, 12], &[]),
+      "abc"
+    );
+    assert_eq!(text_of(&[0b101], TYPE_BIT, [3, 0], &[]), "101");
+  }
+
+  #[test]
+  fn decodes_binary_json() {
+    let document = [
+      0x02, 0x00, 0x20, 0x00, 0x12, 0x00, 0x01, 0x00, 0x13, 0x00, 0x01, 0x00, 0x05, 0x01, 0x00,
+      0x02, 0x14, 0x00, b'a', b'b', 0x02, 0x00, 0x0C, 0x00, 0x04, 0x01, 0x00, 0x0C, 0x0A, 0x00,
+      0x01, b'x',
+    ];
+    assert_eq!(
+      json(0x00, &document).unwrap(),
+      serde_json::json!({"a": 1, "b": [true, "x"]})
+    );
+  }
+
+  #[test]
+  fn scrambles_native_passwords() {
+    // The challenge response is what the server checks against SHA1(SHA1(password))
+    let nonce = b"01234567890123456789";
+    let response = scramble::<Sha1>("secret", nonce);
+    let stored = Sha1::digest(Sha1::digest(b"secret"));
+    let mut salted = Sha1::new();
+    salted.update(nonce);
+    salted.update(stored);
+    let hashed: Vec<u8> = response
+      .iter()
+      .zip(salted.finalize().iter())
+      .map(|(a, b)| a ^ b)
+      .collect();
+    assert_eq!(Sha1::digest(&hashed).as_slice(), stored.as_slice());
+  }
+}
@@ -0,0 +1,161 @@
+//! Encrypted profile bundles, for sharing a set of connections within a team: the profiles of
+//! the active workspace, passwords included, sealed with AES-256-GCM under a key derived from
+//! a passphrase (PBKDF2-HMAC-SHA256). Without the passphrase the file reveals nothing beyond
+//! being a bundle.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use sha2::Sha256;
+use tauri::State;
+
+use crate::importers::{self, ImportedConnections};
+use crate::profiles::{self, ConnectionProfile};
+use crate::secrets::{self, SecretKind};
+use crate::{encoding, AppState};
+
+/// Marks the file type; also bound to the ciphertext as associated data.
+const FORMAT: &str = "spectra-profile-bundle";
+const VERSION: u32 = 1;
+const KDF_ITERATIONS: u32 = 600_000;
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+  format: String,
+  version: u32,
+  iterations: u32,
+  /// Hex-encoded.
+  salt: String,
+  nonce: String,
+  ciphertext: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct Contents {
+  profiles: Vec<ConnectionProfile>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+  let mut key = [0u8; 32];
+  pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+  key
+}
+
+fn seal(passphrase: &str, plaintext: &[u8]) -> Result<Envelope, String> {
+  let mut salt = [0u8; 16];
+  OsRng.fill_bytes(&mut salt);
+  let key = derive_key(passphrase, &salt, KDF_ITERATIONS);
+  let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+  let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+  let ciphertext = cipher
+    .encrypt(
+      &nonce,
+      Payload {
+        msg: plaintext,
+        aad: FORMAT.as_bytes(),
+      },
+    )
+    .map_err(|_| "Encryption failed".to_string())?;
+  Ok(Envelope {
+    format: FORMAT.to_string(),
+    version: VERSION,
+    iterations: KDF_ITERATIONS,
+    salt: encoding::hex(&salt),
+    nonce: encoding::hex(&nonce),
+    ciphertext: encoding::hex(&ciphertext),
+  })
+}
+
+fn open(passphrase: &str, envelope: &Envelope) -> Result<Vec<u8>, String> {
+  if envelope.format != FORMAT {
+    return Err("Not a profile bundle".to_string());
+  }
+  if envelope.version > VERSION {
+    return Err("This profile bundle was made by a newer version of the app".to_string());
+  }
+  let corrupt = || "Corrupt profile bundle".to_string();
+  if !(100_000..=10_000_000).contains(&envelope.iterations) {
+    return Err(corrupt());
+  }
+  let salt = encoding::unhex(&envelope.salt).ok_or_else(corrupt)?;
+  let nonce = encoding::unhex(&envelope.nonce)
+    .filter(|n| n.len() == 12)
+    .ok_or_else(corrupt)?;
+  let ciphertext = encoding::unhex(&envelope.ciphertext).ok_or_else(corrupt)?;
+  let key = derive_key(passphrase, &salt, envelope.iterations);
+  let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+  cipher
+    .decrypt(
+      Nonce::from_slice(&nonce),
+      Payload {
+        msg: &ciphertext,
+        aad: FORMAT.as_bytes(),
+      },
+    )
+    .map_err(|_| "Wrong passphrase, or the bundle was modified".to_string())
+}
+
+/// The profile with its passwords read back from the credential store.
+async fn with_secrets(mut profile: ConnectionProfile) -> Result<ConnectionProfile, String> {
+  let id = profile.id.clone();
+  profile.password = secrets::resolve(Some(&id), SecretKind::Password, profile.password).await?;
+  profile.ssh_config = secrets::resolve_ssh(Some(&id), profile.ssh_config).await?;
+  Ok(profile)
+}
+
+/// Writes every profile of the active workspace, with passwords unless `include_passwords`
+/// is false, to an encrypted bundle at `path`. Returns the number of profiles written.
+#[tauri::command]
+pub async fn export_profiles(
+  state: State<'_, AppState>,
+  path: String,
+  passphrase: String,
+  include_passwords: Option<bool>,
+) -> Result<usize, String> {
+  if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+    return Err(format!(
+      "Use a passphrase of at least {} characters",
+      MIN_PASSPHRASE_LEN
+    ));
+  }
+  let mut profiles = Vec::new();
+  for profile in profiles::all_profiles(&state, None).await? {
+    profiles.push(if include_passwords.unwrap_or(true) {
+      with_secrets(profile).await?
+    } else {
+      profile
+    });
+  }
+  let count = profiles.len();
+  let plaintext = serde_json::to_vec(&Contents { profiles }).map_err(|e| e.to_string())?;
+  // Key derivation is deliberately slow
+  let envelope = secrets::blocking(move || seal(&passphrase, &plaintext)).await?;
+  let json = serde_json::to_string_pretty(&envelope).map_err(|e| e.to_string())?;
+  std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+  Ok(count)
+}
+
+/// Adds the profiles of the bundle at `path` to the active workspace, under new ids and with
+/// their passwords moved into the credential store. Profiles whose name is already taken are
+/// skipped.
+#[tauri::command]
+pub async fn import_profiles(
+  state: State<'_, AppState>,
+  path: String,
+  passphrase: String,
+) -> Result<ImportedConnections, String> {
+  let json =
+    std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+  let envelope: Envelope = serde_json::from_str(&json).map_err(|_| "Not a profile bundle")?;
+  let plaintext = secrets::blocking(move || open(&passphrase, &envelope)).await?;
+  let contents: Contents =
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Corrupt profile bundle: {}", e))?;
+  importers::save_all(
+    &state,
+    "bundle",
+    contents.profiles.into_iter().map(Ok).collect(),
+  )
+  .await
+}
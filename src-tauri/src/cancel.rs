@@ -0,0 +1,332 @@
+//! Cancellation of running statements. Commands that take a `query_id` run their statement
+//! on a connection of their own and register it here, so `cancel_query` can stop it on the
+//! server: `KILL QUERY` on MySQL, `pg_cancel_backend` on Postgres, and a progress handler
+//! that interrupts the statement on SQLite. The same connections carry the statement
+//! timeout (see [`crate::timeouts`]).
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::time::{Duration, Instant};
+
+use sqlx::pool::PoolConnection;
+use sqlx::{MySql, MySqlPool, PgPool, Postgres, Sqlite, SqliteConnection, SqlitePool};
+use tauri::State;
+
+use crate::{store, timeouts, AppState};
+
+/// SQLite virtual machine steps between checks for cancellation.
+const SQLITE_CHECK_STEPS: i32 = 1000;
+
+#[derive(Clone)]
+enum Backend {
+  /// Pool to send `KILL QUERY` through, and the thread id running the statement.
+  MySql(MySqlPool, u64),
+  /// Pool to call `pg_cancel_backend` through, and the backend pid running the statement.
+  Postgres(PgPool, i32),
+  /// Interrupted by its progress handler once the flag is set.
+  Sqlite,
+}
+
+pub struct RunningQuery {
+  connection: String,
+  started_at: i64,
+  backend: Backend,
+  cancelled: Arc<AtomicBool>,
+}
+
+pub type RunningQueries = HashMap<String, RunningQuery>;
+
+/// Registration of a running statement; unregisters it when dropped.
+pub struct Running<'a> {
+  state: &'a AppState,
+  query_id: Option<String>,
+  cancelled: Arc<AtomicBool>,
+  started: Instant,
+  timeout: Option<Duration>,
+}
+
+impl Running<'_> {
+  /// Error text for a failed statement, which says so when it failed by being cancelled or
+  /// by running out of time.
+  pub fn error(&self, e: impl ToString) -> String {
+    if self.cancelled.load(Ordering::Relaxed) {
+      return "Query cancelled".to_string();
+    }
+    match self.timeout {
+      Some(limit) if self.started.elapsed() >= limit => timeouts::error(limit),
+      _ => e.to_string(),
+    }
+  }
+}
+
+impl Drop for Running<'_> {
+  fn drop(&mut self) {
+    if let Some(id) = &self.query_id {
+      self
+        .state
+        .running_queries
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(id);
+    }
+  }
+}
+
+fn register<'a>(
+  state: &'a AppState,
+  query_id: Option<String>,
+  connection: &str,
+  backend: Option<Backend>,
+  cancelled: Arc<AtomicBool>,
+  started: Instant,
+  timeout: Option<Duration>,
+) -> Running<'a> {
+  if let (Some(id), Some(backend)) = (&query_id, backend) {
+    state
+      .running_queries
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .insert(
+        id.clone(),
+        RunningQuery {
+          connection: connection.to_string(),
+          started_at: store::now_ms(),
+          backend,
+          cancelled: cancelled.clone(),
+        },
+      );
+  }
+  Running {
+    state,
+    query_id,
+    cancelled,
+    started,
+    timeout,
+  }
+}
+
+/// A MySQL connection to run a statement on, registered as `query_id` when given. The
+/// statement itself carries the timeout, see [`timeouts::mysql_hint`].
+pub async fn mysql<'a>(
+  state: &'a AppState,
+  query_id: Option<String>,
+  connection: &str,
+  pool: &MySqlPool,
+  timeout: Option<Duration>,
+) -> Result<(PoolConnection<MySql>, Running<'a>), String> {
+  let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+  let backend = match &query_id {
+    Some(_) => {
+      let (thread_id,): (u64,) = sqlx::query_as("SELECT CONNECTION_ID()")
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+      Some(Backend::MySql(pool.clone(), thread_id))
+    }
+    None => None,
+  };
+  let running = register(
+    state,
+    query_id,
+    connection,
+    backend,
+    Arc::default(),
+    Instant::now(),
+    timeout,
+  );
+  Ok((conn, running))
+}
+
+/// A Postgres connection to run a statement on, registered as `query_id` when given. Pass it
+/// to [`reset_postgres`] once the statement is done.
+pub async fn postgres<'a>(
+  state: &'a AppState,
+  query_id: Option<String>,
+  connection: &str,
+  pool: &PgPool,
+  timeout: Option<Duration>,
+) -> Result<(PoolConnection<Postgres>, Running<'a>), String> {
+  let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+  if let Some(limit) = timeout {
+    let sql = format!("SET statement_timeout = {}", limit.as_millis().max(1));
+    sqlx::query(&sql)
+      .execute(&mut *conn)
+      .await
+      .map_err(|e| e.to_string())?;
+  }
+  let backend = match &query_id {
+    Some(_) => {
+      let (pid,): (i32,) = sqlx::query_as("SELECT pg_backend_pid()")
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+      Some(Backend::Postgres(pool.clone(), pid))
+    }
+    None => None,
+  };
+  let running = register(
+    state,
+    query_id,
+    connection,
+    backend,
+    Arc::default(),
+    Instant::now(),
+    timeout,
+  );
+  Ok((conn, running))
+}
+
+/// Puts the statement timeout of a connection from [`postgres`] back before it returns to the
+/// pool; a connection where that fails is closed instead.
+pub async fn reset_postgres(conn: &mut PoolConnection<Postgres>, running: &Running<'_>) {
+  reset_postgres_timeout(conn, running.timeout).await;
+}
+
+/// [`reset_postgres`] for a connection that outlived its registration, such as a console
+/// cursor's.
+pub async fn reset_postgres_timeout(
+  conn: &mut PoolConnection<Postgres>,
+  timeout: Option<Duration>,
+) {
+  if timeout.is_none() {
+    return;
+  }
+  if let Err(e) = sqlx::query("SET statement_timeout = DEFAULT")
+    .execute(&mut **conn)
+    .await
+  {
+    tracing::warn!("Cannot reset statement_timeout: {}", e);
+    conn.close_on_drop();
+  }
+}
+
+/// A SQLite connection to run a statement on, registered as `query_id` when given.
+///
+/// The progress handler interrupts the statement once it is cancelled or past its timeout.
+/// [`release_sqlite`] takes it off again when the connection goes back to the pool.
+pub async fn sqlite<'a>(
+  state: &'a AppState,
+  query_id: Option<String>,
+  connection: &str,
+  pool: &SqlitePool,
+  timeout: Option<Duration>,
+) -> Result<(PoolConnection<Sqlite>, Running<'a>), String> {
+  let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+  let cancelled = Arc::new(AtomicBool::new(false));
+  let started = Instant::now();
+  let mut handle = conn.lock_handle().await.map_err(|e| e.to_string())?;
+  if query_id.is_some() || timeout.is_some() {
+    let flag = cancelled.clone();
+    let deadline = timeout.map(|limit| started + limit);
+    handle.set_progress_handler(SQLITE_CHECK_STEPS, move || {
+      !flag.load(Ordering::Relaxed) && deadline.is_none_or(|at| Instant::now() < at)
+    });
+  }
+  drop(handle);
+  let backend = query_id.as_ref().map(|_| Backend::Sqlite);
+  let running = register(
+    state, query_id, connection, backend, cancelled, started, timeout,
+  );
+  Ok((conn, running))
+}
+
+/// Removes the progress handler of [`sqlite`] from a connection returning to the pool, so a
+/// cancelled flag or a passed deadline can't interrupt whatever runs on it next. For the
+/// pool's `after_release` hook.
+pub async fn release_sqlite(conn: &mut SqliteConnection) -> Result<bool, sqlx::Error> {
+  conn.lock_handle().await?.remove_progress_handler();
+  Ok(true)
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningQueryInfo {
+  pub query_id: String,
+  pub connection: String,
+  pub started_at: i64,
+}
+
+#[tauri::command]
+pub fn list_running_queries(state: State<'_, AppState>) -> Vec<RunningQueryInfo> {
+  let mut queries: Vec<RunningQueryInfo> = state
+    .running_queries
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .iter()
+    .map(|(id, q)| RunningQueryInfo {
+      query_id: id.clone(),
+      connection: q.connection.clone(),
+      started_at: q.started_at,
+    })
+    .collect();
+  queries.sort_by_key(|q| q.started_at);
+  queries
+}
+
+/// Asks the server to stop the statement registered as `query_id`. Returns whether such a
+/// statement was running; the command running it then fails with "Query cancelled".
+#[tauri::command]
+pub async fn cancel_query(state: State<'_, AppState>, query_id: String) -> Result<bool, String> {
+  let backend = {
+    let running = state
+      .running_queries
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    let Some(query) = running.get(&query_id) else {
+      return Ok(false);
+    };
+    query.cancelled.store(true, Ordering::Relaxed);
+    query.backend.clone()
+  };
+  match backend {
+    Backend::MySql(pool, thread_id) => {
+      sqlx::query(&format!("KILL QUERY {}", thread_id))
+        .execute(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Backend::Postgres(pool, pid) => {
+      sqlx::query("SELECT pg_cancel_backend($1)")
+        .bind(pid)
+        .execute(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Backend::Sqlite => {}
+  }
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use sqlx::sqlite::SqlitePoolOptions;
+
+  #[tokio::test]
+  async fn released_sqlite_connections_lose_the_progress_handler() {
+    let pool = SqlitePoolOptions::new()
+      .max_connections(1)
+      .after_release(|conn, _| Box::pin(release_sqlite(conn)))
+      .connect("sqlite::memory:")
+      .await
+      .unwrap();
+    let mut conn = pool.acquire().await.unwrap();
+    // Interrupts everything, like a cancelled statement's handler
+    conn
+      .lock_handle()
+      .await
+      .unwrap()
+      .set_progress_handler(1, || false);
+    drop(conn);
+
+    let (count,): (i64,) = sqlx::query_as(
+      "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 10000) \
+       SELECT COUNT(*) FROM n",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(count, 10000);
+  }
+}
@@ -0,0 +1,226 @@
+//! Curated views of the system catalogs (settings, indexes, sessions, InnoDB metrics, ...)
+//! so they can be browsed without remembering the catalog query for each.
+
+use tauri::State;
+
+use crate::db::{self, ResultColumn, SqlPool};
+use crate::{timezone, AppState};
+
+struct Catalog {
+  name: &'static str,
+  description: &'static str,
+  sql: &'static str,
+}
+
+const POSTGRES_CATALOGS: &[Catalog] = &[
+  Catalog {
+    name: "settings",
+    description: "Server configuration parameters (pg_settings)",
+    sql: "SELECT name, setting, unit, category, short_desc, context, source, pending_restart \
+          FROM pg_settings ORDER BY category, name",
+  },
+  Catalog {
+    name: "indexes",
+    description: "Indexes of user tables with their definitions (pg_indexes)",
+    sql: "SELECT i.schemaname, i.tablename, i.indexname, i.indexdef, \
+          pg_size_pretty(pg_relation_size(format('%I.%I', i.schemaname, i.indexname)::regclass)) \
+          AS size \
+          FROM pg_indexes i WHERE i.schemaname NOT IN ('pg_catalog', 'information_schema') \
+          ORDER BY i.schemaname, i.tablename, i.indexname",
+  },
+  Catalog {
+    name: "activity",
+    description: "Sessions and what they are running (pg_stat_activity)",
+    sql: "SELECT pid, usename, datname, application_name, client_addr::text, state, \
+          backend_start, query_start, wait_event_type, wait_event, query \
+          FROM pg_stat_activity WHERE backend_type = 'client backend' ORDER BY query_start",
+  },
+  Catalog {
+    name: "locks",
+    description: "Locks held or awaited (pg_locks)",
+    sql: "SELECT l.pid, l.locktype, l.mode, l.granted, l.relation::regclass::text AS relation, \
+          a.usename, a.query FROM pg_locks l LEFT JOIN pg_stat_activity a ON a.pid = l.pid \
+          ORDER BY l.granted, l.pid",
+  },
+  Catalog {
+    name: "table_sizes",
+    description: "User tables by total size, with row estimates and vacuum times",
+    sql: "SELECT schemaname, relname AS table_name, n_live_tup AS estimated_rows, \
+          pg_size_pretty(pg_total_relation_size(relid)) AS total_size, \
+          last_vacuum, last_autovacuum, last_analyze \
+          FROM pg_stat_user_tables ORDER BY pg_total_relation_size(relid) DESC",
+  },
+  Catalog {
+    name: "extensions",
+    description: "Installed extensions (pg_extension)",
+    sql: "SELECT e.extname, e.extversion, n.nspname AS schema \
+          FROM pg_extension e JOIN pg_namespace n ON n.oid = e.extnamespace ORDER BY e.extname",
+  },
+  Catalog {
+    name: "roles",
+    description: "Roles and their attributes (pg_roles)",
+    sql: "SELECT rolname, rolsuper, rolcreaterole, rolcreatedb, rolcanlogin, rolreplication, \
+          rolconnlimit, rolvaliduntil FROM pg_roles ORDER BY rolname",
+  },
+  Catalog {
+    name: "databases",
+    description: "Databases with their sizes (pg_database)",
+    sql: "SELECT datname, pg_size_pretty(pg_database_size(datname)) AS size, \
+          pg_encoding_to_char(encoding) AS encoding, datcollate, datallowconn \
+          FROM pg_database ORDER BY pg_database_size(datname) DESC",
+  },
+];
+
+const MYSQL_VIEWS: &[Catalog] = &[
+  Catalog {
+    name: "variables",
+    description: "Global server variables",
+    sql: "SELECT VARIABLE_NAME, VARIABLE_VALUE FROM performance_schema.global_variables \
+          ORDER BY VARIABLE_NAME",
+  },
+  Catalog {
+    name: "status",
+    description: "Global status counters",
+    sql: "SELECT VARIABLE_NAME, VARIABLE_VALUE FROM performance_schema.global_status \
+          ORDER BY VARIABLE_NAME",
+  },
+  Catalog {
+    name: "innodb_metrics",
+    description: "Enabled InnoDB metrics (INNODB_METRICS)",
+    sql: "SELECT NAME, SUBSYSTEM, COUNT, MAX_COUNT, AVG_COUNT, TYPE, COMMENT \
+          FROM information_schema.INNODB_METRICS WHERE STATUS = 'enabled' \
+          ORDER BY SUBSYSTEM, NAME",
+  },
+  Catalog {
+    name: "processlist",
+    description: "Connections and what they are running (PROCESSLIST)",
+    sql: "SELECT ID, USER, HOST, DB, COMMAND, TIME, STATE, INFO \
+          FROM information_schema.PROCESSLIST ORDER BY TIME DESC",
+  },
+  Catalog {
+    name: "tables",
+    description: "Tables of the current database with sizes and row estimates (TABLES)",
+    sql: "SELECT TABLE_NAME, ENGINE, TABLE_ROWS, DATA_LENGTH, INDEX_LENGTH, \
+          AUTO_INCREMENT, CREATE_TIME, UPDATE_TIME, TABLE_COLLATION \
+          FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE() \
+          ORDER BY DATA_LENGTH + INDEX_LENGTH DESC",
+  },
+  Catalog {
+    name: "indexes",
+    description: "Indexes of the current database, one row per column (STATISTICS)",
+    sql: "SELECT TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX, COLUMN_NAME, NON_UNIQUE, \
+          INDEX_TYPE, CARDINALITY FROM information_schema.STATISTICS \
+          WHERE TABLE_SCHEMA = DATABASE() ORDER BY TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX",
+  },
+  Catalog {
+    name: "engines",
+    description: "Storage engines and their support (ENGINES)",
+    sql: "SELECT ENGINE, SUPPORT, TRANSACTIONS, XA, SAVEPOINTS, COMMENT \
+          FROM information_schema.ENGINES ORDER BY ENGINE",
+  },
+];
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogInfo {
+  pub name: String,
+  pub description: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogResult {
+  pub name: String,
+  pub description: String,
+  pub columns: Vec<ResultColumn>,
+  pub rows: Vec<serde_json::Value>,
+}
+
+fn catalogs_of(pool: &SqlPool) -> &'static [Catalog] {
+  match pool {
+    SqlPool::Postgres(_) => POSTGRES_CATALOGS,
+    SqlPool::MySql(_) => MYSQL_VIEWS,
+    SqlPool::Sqlite(_) => &[],
+  }
+}
+
+async fn run_catalog(
+  state: &AppState,
+  connection: &str,
+  pool: &SqlPool,
+  name: &str,
+) -> Result<CatalogResult, String> {
+  let catalogs = catalogs_of(pool);
+  let catalog = catalogs.iter().find(|c| c.name == name).ok_or_else(|| {
+    let names: Vec<&str> = catalogs.iter().map(|c| c.name).collect();
+    format!(
+      "Unknown catalog '{}'; expected one of {}",
+      name,
+      names.join(", ")
+    )
+  })?;
+  let tz = timezone::display_zone(state, connection);
+  let (columns, rows) = pool
+    .fetch_with_columns(catalog.sql, &[], tz.as_ref())
+    .await?;
+  Ok(CatalogResult {
+    name: catalog.name.to_string(),
+    description: catalog.description.to_string(),
+    columns,
+    rows: rows.into_iter().map(serde_json::Value::Object).collect(),
+  })
+}
+
+/// Catalog views available for `connection`; none for SQLite.
+#[tauri::command]
+pub fn list_catalogs(
+  state: State<'_, AppState>,
+  connection: String,
+) -> Result<Vec<CatalogInfo>, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  Ok(
+    catalogs_of(&pool)
+      .iter()
+      .map(|c| CatalogInfo {
+        name: c.name.to_string(),
+        description: c.description.to_string(),
+      })
+      .collect(),
+  )
+}
+
+/// One of the curated Postgres catalog views (`settings`, `indexes`, `activity`, `locks`,
+/// `table_sizes`, `extensions`, `roles`, `databases`).
+#[tauri::command]
+pub async fn postgres_get_catalog(
+  state: State<'_, AppState>,
+  connection: String,
+  name: String,
+) -> Result<CatalogResult, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  if !matches!(pool, SqlPool::Postgres(_)) {
+    return Err(format!(
+      "Connection '{}' is not a Postgres connection",
+      connection
+    ));
+  }
+  run_catalog(&state, &connection, &pool, &name).await
+}
+
+/// One of the curated MySQL server views (`variables`, `status`, `innodb_metrics`,
+/// `processlist`, `tables`, `indexes`, `engines`).
+#[tauri::command]
+pub async fn mysql_get_information_schema(
+  state: State<'_, AppState>,
+  connection: String,
+  view: String,
+) -> Result<CatalogResult, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  if !matches!(pool, SqlPool::MySql(_)) {
+    return Err(format!(
+      "Connection '{}' is not a MySQL connection",
+      connection
+    ));
+  }
+  run_catalog(&state, &connection, &pool, &view).await
+}
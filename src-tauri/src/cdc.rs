@@ -0,0 +1,677 @@
+//! Experimental change capture for a live "what's changing right now" panel, emitted as
+//! `change-feed` events.
+//!
+//! Postgres: a temporary logical replication slot decoded with `pgoutput`, over a
+//! publication for the selected tables. Needs `wal_level = logical` and a role allowed to
+//! replicate. The slot lives as long as the feed's session, so it can't be left behind to
+//! hold back WAL; the publication is dropped when the feed stops.
+//!
+//! MySQL: the binary log, read by [`crate::binlog`] as a replica would.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::time::Duration;
+
+use sqlx::{Connection, MySqlPool, PgConnection, PgPool};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::binlog::{self, Decoder, Login, Source, Stream};
+use crate::db::{self, JsonRow, SqlPool};
+use crate::secrets::{self, SecretKind};
+use crate::{masking, readonly, store, AppState};
+
+const DEFAULT_INTERVAL_MS: u64 = 1000;
+const MIN_INTERVAL_MS: u64 = 250;
+/// Most changes consumed per poll.
+const MAX_BATCH: i32 = 1000;
+/// Silence after which a binary log stream is taken for dead; the server sends a heartbeat
+/// every 30 seconds.
+const BINLOG_STALL: Duration = Duration::from_secs(90);
+const BINLOG_RETRY: Duration = Duration::from_secs(5);
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Operation {
+  Insert,
+  Update,
+  Delete,
+  Truncate,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RowChange {
+  pub table: String,
+  pub operation: Operation,
+  /// New row for inserts and updates. Values are in their text form.
+  pub row: Option<JsonRow>,
+  /// Old row (or its key columns) for updates and deletes, when the table's replica
+  /// identity provides it.
+  pub old: Option<JsonRow>,
+  /// WAL LSN (Postgres) or binary log `file:position` (MySQL) of the change.
+  pub position: String,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ChangeFeedEvent {
+  feed_id: String,
+  changes: Vec<RowChange>,
+  error: Option<String>,
+}
+
+pub struct ChangeFeed {
+  connection: String,
+  tables: Vec<String>,
+  started_at: i64,
+  changes_emitted: Arc<AtomicU64>,
+  cleanup: Cleanup,
+  task: tokio::task::JoinHandle<()>,
+}
+
+/// What is left to drop once a feed's task is stopped.
+enum Cleanup {
+  /// The publication, named like the slot.
+  Postgres {
+    pool: PgPool,
+    name: String,
+  },
+  None,
+}
+
+pub type ChangeFeeds = HashMap<String, ChangeFeed>;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeFeedInfo {
+  pub feed_id: String,
+  pub connection: String,
+  pub tables: Vec<String>,
+  pub started_at: i64,
+  pub changes_emitted: u64,
+}
+
+struct Relation {
+  name: String,
+  columns: Vec<String>,
+}
+
+/// Reader over one pgoutput message.
+struct Message<'a> {
+  data: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Message<'a> {
+  fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+    let end = self.pos + n;
+    let bytes = self
+      .data
+      .get(self.pos..end)
+      .ok_or("Truncated pgoutput message")?;
+    self.pos = end;
+    Ok(bytes)
+  }
+
+  fn u8(&mut self) -> Result<u8, String> {
+    Ok(self.take(1)?[0])
+  }
+
+  fn i16(&mut self) -> Result<i16, String> {
+    let bytes = self.take(2)?;
+    Ok(i16::from_be_bytes([bytes[0], bytes[1]]))
+  }
+
+  fn i32(&mut self) -> Result<i32, String> {
+    let bytes = self.take(4)?;
+    Ok(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+  }
+
+  fn cstr(&mut self) -> Result<String, String> {
+    let rest = &self.data[self.pos..];
+    let len = rest
+      .iter()
+      .position(|b| *b == 0)
+      .ok_or("Unterminated string in pgoutput message")?;
+    let s = String::from_utf8_lossy(&rest[..len]).into_owned();
+    self.pos += len + 1;
+    Ok(s)
+  }
+
+  fn tuple(&mut self, relation: &Relation) -> Result<JsonRow, String> {
+    let count = usize::try_from(self.i16()?).unwrap_or(0);
+    let mut row = JsonRow::new();
+    for i in 0..count {
+      let value = match self.u8()? {
+        b'n' => Some(serde_json::Value::Null),
+        // Unchanged TOASTed value: not sent, so leave the column out
+        b'u' => None,
+        b't' => {
+          let len = usize::try_from(self.i32()?).unwrap_or(0);
+          let text = String::from_utf8_lossy(self.take(len)?).into_owned();
+          Some(serde_json::Value::String(text))
+        }
+        other => return Err(format!("Unsupported tuple value kind: {}", other as char)),
+      };
+      if let Some(value) = value {
+        let name = relation
+          .columns
+          .get(i)
+          .cloned()
+          .unwrap_or_else(|| format!("column{}", i + 1));
+        row.insert(name, value);
+      }
+    }
+    Ok(row)
+  }
+}
+
+fn relation(relations: &HashMap<i32, Relation>, oid: i32) -> Result<&Relation, String> {
+  relations
+    .get(&oid)
+    .ok_or_else(|| format!("pgoutput change for unknown relation {}", oid))
+}
+
+/// Decodes one pgoutput (protocol version 1) message, updating `relations` and returning the
+/// row changes it carries.
+fn decode_pgoutput(
+  data: &[u8],
+  position: &str,
+  relations: &mut HashMap<i32, Relation>,
+) -> Result<Vec<RowChange>, String> {
+  let mut msg = Message { data, pos: 0 };
+  let change = |table: &str, operation, row, old| RowChange {
+    table: table.to_string(),
+    operation,
+    row,
+    old,
+    position: position.to_string(),
+  };
+  match msg.u8()? {
+    b'R' => {
+      let oid = msg.i32()?;
+      let _namespace = msg.cstr()?;
+      let name = msg.cstr()?;
+      let _replica_identity = msg.u8()?;
+      let count = usize::try_from(msg.i16()?).unwrap_or(0);
+      let mut columns = Vec::with_capacity(count);
+      for _ in 0..count {
+        let _flags = msg.u8()?;
+        columns.push(msg.cstr()?);
+        let _type_oid = msg.i32()?;
+        let _type_modifier = msg.i32()?;
+      }
+      relations.insert(oid, Relation { name, columns });
+      Ok(Vec::new())
+    }
+    b'I' => {
+      let rel = relation(relations, msg.i32()?)?;
+      let _new = msg.u8()?;
+      let row = msg.tuple(rel)?;
+      Ok(vec![change(&rel.name, Operation::Insert, Some(row), None)])
+    }
+    b'U' => {
+      let rel = relation(relations, msg.i32()?)?;
+      let mut old = None;
+      let mut kind = msg.u8()?;
+      if kind == b'K' || kind == b'O' {
+        old = Some(msg.tuple(rel)?);
+        kind = msg.u8()?;
+      }
+      if kind != b'N' {
+        return Err("Malformed pgoutput update".to_string());
+      }
+      let row = msg.tuple(rel)?;
+      Ok(vec![change(&rel.name, Operation::Update, Some(row), old)])
+    }
+    b'D' => {
+      let rel = relation(relations, msg.i32()?)?;
+      let _kind = msg.u8()?;
+      let old = msg.tuple(rel)?;
+      Ok(vec![change(&rel.name, Operation::Delete, None, Some(old))])
+    }
+    b'T' => {
+      let count = usize::try_from(msg.i32()?).unwrap_or(0);
+      let _options = msg.u8()?;
+      let mut changes = Vec::with_capacity(count);
+      for _ in 0..count {
+        let rel = relation(relations, msg.i32()?)?;
+        changes.push(change(&rel.name, Operation::Truncate, None, None));
+      }
+      Ok(changes)
+    }
+    // Begin, commit, origin, type and message records carry no row changes
+    _ => Ok(Vec::new()),
+  }
+}
+
+/// Creates the publication (named `spectra_cdc_<ms>`) and a temporary replication slot of
+/// the same name for a feed, returning the name and the session holding the slot.
+async fn start_postgres(
+  pool: &PgPool,
+  sql_pool: &SqlPool,
+  schema: &str,
+  tables: &[String],
+) -> Result<(String, PgConnection), String> {
+  let name = format!("spectra_cdc_{}", store::now_ms());
+  let table_list = tables
+    .iter()
+    .map(|t| sql_pool.table_ref_in(schema, t))
+    .collect::<Vec<_>>()
+    .join(", ");
+  sqlx::query(&format!(
+    "CREATE PUBLICATION {} FOR TABLE {}",
+    name, table_list
+  ))
+  .execute(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  match slot_session(pool, &name).await {
+    Ok(conn) => Ok((name, conn)),
+    Err(e) => {
+      let _ = drop_publication(pool, &name).await;
+      Err(format!("Could not create a replication slot: {}", e))
+    }
+  }
+}
+
+/// A connection of its own holding a temporary slot, which the server drops when the
+/// session ends.
+async fn slot_session(pool: &PgPool, name: &str) -> Result<PgConnection, String> {
+  let mut conn = pool.acquire().await.map_err(|e| e.to_string())?.detach();
+  sqlx::query("SELECT pg_create_logical_replication_slot($1::name, 'pgoutput', true)")
+    .bind(name)
+    .execute(&mut conn)
+    .await
+    .map_err(|e| e.to_string())?;
+  Ok(conn)
+}
+
+async fn poll_postgres(
+  conn: &mut PgConnection,
+  name: &str,
+  relations: &mut HashMap<i32, Relation>,
+) -> Result<Vec<RowChange>, String> {
+  let rows: Vec<(String, Vec<u8>)> = sqlx::query_as(
+    "SELECT lsn::text, data FROM pg_logical_slot_get_binary_changes($1::name, NULL, $2, \
+     'proto_version', '1', 'publication_names', $3)",
+  )
+  .bind(name)
+  .bind(MAX_BATCH)
+  .bind(name)
+  .fetch_all(conn)
+  .await
+  .map_err(|e| e.to_string())?;
+  let mut changes = Vec::new();
+  for (lsn, data) in rows {
+    changes.extend(decode_pgoutput(&data, &lsn, relations)?);
+  }
+  Ok(changes)
+}
+
+async fn drop_publication(pool: &PgPool, name: &str) -> Result<(), String> {
+  sqlx::query(&format!("DROP PUBLICATION IF EXISTS {}", name))
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Polls the slot until the feed stops. An error ends the feed: the slot goes with its
+/// session, and the error is the last event.
+async fn follow_postgres(
+  mut conn: PgConnection,
+  name: String,
+  interval: Duration,
+  emit: impl Fn(Vec<RowChange>, Option<String>),
+) {
+  let mut relations = HashMap::new();
+  let mut ticker = tokio::time::interval(interval);
+  loop {
+    ticker.tick().await;
+    match poll_postgres(&mut conn, &name, &mut relations).await {
+      Ok(changes) => emit(changes, None),
+      Err(e) => {
+        emit(Vec::new(), Some(e));
+        let _ = conn.close().await;
+        return;
+      }
+    }
+  }
+}
+
+/// Decodes a binary log event into `pending`, first looking up the columns of a newly
+/// mapped table. Errors are reported and the event skipped.
+async fn apply_event(
+  pool: &MySqlPool,
+  decoder: &mut Decoder,
+  event: &[u8],
+  pending: &mut Vec<RowChange>,
+  emit: &impl Fn(Vec<RowChange>, Option<String>),
+) {
+  if let Some(table) = decoder.table_to_describe(event) {
+    match binlog::columns(pool, decoder.schema(), &table).await {
+      Ok(columns) => decoder.describe(table, columns),
+      Err(e) => emit(Vec::new(), Some(e)),
+    }
+  }
+  match decoder.decode(event) {
+    Ok(changes) => pending.extend(changes),
+    Err(e) => emit(Vec::new(), Some(e)),
+  }
+}
+
+/// Follows the binary log until the feed stops, emitting the changes gathered each
+/// interval. A dropped or silent stream is reported and picked up again from the end of
+/// the last whole statement, which may repeat the rows of a statement cut short.
+async fn follow_binlog(
+  pool: MySqlPool,
+  login: Login,
+  source: Source,
+  mut decoder: Decoder,
+  mut stream: Stream,
+  interval: Duration,
+  emit: impl Fn(Vec<RowChange>, Option<String>),
+) {
+  let mut pending = Vec::new();
+  let mut ticker = tokio::time::interval(interval);
+  loop {
+    let failure = tokio::select! {
+      event = stream.next_event() => match event {
+        Ok(event) => {
+          apply_event(&pool, &mut decoder, &event, &mut pending, &emit).await;
+          None
+        }
+        Err(e) => Some(e),
+      },
+      _ = ticker.tick() => {
+        emit(std::mem::take(&mut pending), None);
+        (stream.idle() > BINLOG_STALL)
+          .then(|| "The binary log stream stopped responding".to_string())
+      }
+    };
+    let Some(error) = failure else {
+      continue;
+    };
+    emit(std::mem::take(&mut pending), Some(error));
+    stream = loop {
+      tokio::time::sleep(BINLOG_RETRY).await;
+      let (file, position) = decoder.resume();
+      match Stream::open(&login, &source, file, position).await {
+        Ok((stream, first)) => {
+          apply_event(&pool, &mut decoder, &first, &mut pending, &emit).await;
+          break stream;
+        }
+        Err(e) => emit(Vec::new(), Some(e)),
+      }
+    };
+  }
+}
+
+/// Logs in for the binary log as the replication user, or as the connection's own user
+/// with the profile's password.
+async fn binlog_login(
+  pool: &MySqlPool,
+  replication: Option<Replication>,
+  profile_id: Option<String>,
+) -> Result<Login, String> {
+  let options = pool.connect_options();
+  let (username, password) = match replication {
+    Some(user) => (user.username, user.password),
+    None => (
+      options.get_username().to_string(),
+      secrets::resolve(profile_id.as_deref(), SecretKind::Password, None).await?,
+    ),
+  };
+  Ok(Login::new(&options, username, password.unwrap_or_default()))
+}
+
+/// A user with replication privileges to read the MySQL binary log as.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Replication {
+  pub username: String,
+  pub password: Option<String>,
+}
+
+/// Starts streaming changes to `tables` (of `schema` on Postgres) as `change-feed` events.
+/// Only changes made after the call are reported. MySQL feeds read the binary log as
+/// `replication`, falling back to the connection's user.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn start_change_feed(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  connection: String,
+  tables: Vec<String>,
+  schema: Option<String>,
+  replication: Option<Replication>,
+  profile_id: Option<String>,
+  interval_ms: Option<u64>,
+) -> Result<String, String> {
+  if tables.is_empty() {
+    return Err("Select at least one table".to_string());
+  }
+  let pool = db::sql_pool(&state, &connection)?;
+  let schema = match &pool {
+    SqlPool::Postgres(pg) => {
+      // Feeds create a publication and a replication slot
+      readonly::ensure_writable(&state, &connection)?;
+      db::pg_schema(pg, schema).await?
+    }
+    SqlPool::MySql(_) => db::PG_DEFAULT_SCHEMA.to_string(),
+    SqlPool::Sqlite(_) => {
+      return Err("Change feeds need a Postgres or MySQL connection".to_string())
+    }
+  };
+  let mut resolved = Vec::with_capacity(tables.len());
+  for table in &tables {
+    resolved.push(pool.resolve_table_in(&schema, table).await?);
+  }
+  let tables = resolved;
+  let interval = Duration::from_millis(
+    interval_ms
+      .unwrap_or(DEFAULT_INTERVAL_MS)
+      .max(MIN_INTERVAL_MS),
+  );
+
+  let feed_id = crate::next_id("cdc");
+  let changes_emitted = Arc::new(AtomicU64::new(0));
+  let mask = masking::active(&state, &connection);
+  let emit = {
+    let feed_id = feed_id.clone();
+    let changes_emitted = changes_emitted.clone();
+    move |mut changes: Vec<RowChange>, error: Option<String>| {
+      if changes.is_empty() && error.is_none() {
+        return;
+      }
+      if let Some(mask) = &mask {
+        for change in &mut changes {
+          change
+            .row
+            .iter_mut()
+            .chain(change.old.iter_mut())
+            .for_each(|row| mask.apply(row));
+        }
+      }
+      changes_emitted.fetch_add(changes.len() as u64, Ordering::Relaxed);
+      let _ = app.emit(
+        "change-feed",
+        ChangeFeedEvent {
+          feed_id: feed_id.clone(),
+          changes,
+          error,
+        },
+      );
+    }
+  };
+
+  let (task, cleanup) = match &pool {
+    SqlPool::Postgres(pg) => {
+      let (name, conn) = start_postgres(pg, &pool, &schema, &tables).await?;
+      let task = tokio::spawn(follow_postgres(conn, name.clone(), interval, emit));
+      let cleanup = Cleanup::Postgres {
+        pool: pg.clone(),
+        name,
+      };
+      (task, cleanup)
+    }
+    SqlPool::MySql(my) => {
+      let login = binlog_login(my, replication, profile_id).await?;
+      let source = binlog::source(my).await?;
+      let (database,): (Option<String>,) = sqlx::query_as("SELECT DATABASE()")
+        .fetch_one(my)
+        .await
+        .map_err(|e| e.to_string())?;
+      let database = database.ok_or("The connection has no database selected")?;
+      let mut decoder = Decoder::new(
+        database,
+        tables.clone(),
+        source.file.clone(),
+        source.position,
+      );
+      // Reading the first event brings out a missing privilege now rather than in the feed
+      let (stream, first) = Stream::open(&login, &source, &source.file, source.position).await?;
+      decoder.decode(&first)?;
+      let task = tokio::spawn(follow_binlog(
+        my.clone(),
+        login,
+        source,
+        decoder,
+        stream,
+        interval,
+        emit,
+      ));
+      (task, Cleanup::None)
+    }
+    SqlPool::Sqlite(_) => {
+      return Err("Change feeds need a Postgres or MySQL connection".to_string())
+    }
+  };
+
+  state
+    .change_feeds
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(
+      feed_id.clone(),
+      ChangeFeed {
+        connection,
+        tables,
+        started_at: store::now_ms(),
+        changes_emitted,
+        cleanup,
+        task,
+      },
+    );
+  Ok(feed_id)
+}
+
+/// Stops a feed; a Postgres feed's slot goes with its session and its publication is
+/// dropped.
+#[tauri::command]
+pub async fn stop_change_feed(state: State<'_, AppState>, feed_id: String) -> Result<(), String> {
+  let feed = state
+    .change_feeds
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&feed_id)
+    .ok_or("Unknown change feed")?;
+  feed.task.abort();
+  match feed.cleanup {
+    Cleanup::Postgres { pool, name } => drop_publication(&pool, &name).await,
+    Cleanup::None => Ok(()),
+  }
+}
+
+#[tauri::command]
+pub fn list_change_feeds(state: State<'_, AppState>) -> Vec<ChangeFeedInfo> {
+  let feeds = state
+    .change_feeds
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let mut list: Vec<ChangeFeedInfo> = feeds
+    .iter()
+    .map(|(id, feed)| ChangeFeedInfo {
+      feed_id: id.clone(),
+      connection: feed.connection.clone(),
+      tables: feed.tables.clone(),
+      started_at: feed.started_at,
+      changes_emitted: feed.changes_emitted.load(Ordering::Relaxed),
+    })
+    .collect();
+  list.sort_by(|a, b| a.feed_id.cmp(&b.feed_id));
+  list
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Against the server in `SPECTRA_TEST_POSTGRES_URL` when one is given; it needs
+  /// `wal_level = logical`.
+  #[tokio::test]
+  async fn postgres_feed_follows_a_schema_and_cleans_up() {
+    let Ok(url) = std::env::var("SPECTRA_TEST_POSTGRES_URL") else {
+      return;
+    };
+    let pool = sqlx::postgres::PgPoolOptions::new()
+      .max_connections(2)
+      .connect(&url)
+      .await
+      .unwrap();
+    sqlx::raw_sql(
+      "DROP SCHEMA IF EXISTS cdc_test CASCADE; CREATE SCHEMA cdc_test; \
+       CREATE TABLE cdc_test.items (id int PRIMARY KEY, name text)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let sql_pool = SqlPool::Postgres(pool.clone());
+    let tables = vec!["items".to_string()];
+    let (name, mut conn) = start_postgres(&pool, &sql_pool, "cdc_test", &tables)
+      .await
+      .unwrap();
+    sqlx::query("INSERT INTO cdc_test.items VALUES (1, 'one')")
+      .execute(&pool)
+      .await
+      .unwrap();
+    let changes = poll_postgres(&mut conn, &name, &mut HashMap::new())
+      .await
+      .unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].table, "items");
+    assert_eq!(changes[0].operation, Operation::Insert);
+    assert_eq!(changes[0].row.as_ref().unwrap()["name"], "one");
+
+    drop(conn);
+    drop_publication(&pool, &name).await.unwrap();
+    let mut slots = 1;
+    for _ in 0..50 {
+      (slots,) = sqlx::query_as::<_, (i64,)>(
+        "SELECT count(*) FROM pg_replication_slots WHERE slot_name = $1",
+      )
+      .bind(&name)
+      .fetch_one(&pool)
+      .await
+      .unwrap();
+      if slots == 0 {
+        break;
+      }
+      tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(slots, 0);
+    let (publications,): (i64,) =
+      sqlx::query_as("SELECT count(*) FROM pg_publication WHERE pubname = $1")
+        .bind(&name)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(publications, 0);
+    sqlx::raw_sql("DROP SCHEMA cdc_test CASCADE")
+      .execute(&pool)
+      .await
+      .unwrap();
+  }
+}
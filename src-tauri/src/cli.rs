@@ -0,0 +1,175 @@
+//! Headless command-line mode (`spectra-studio export --profile staging --table users`).
+//! Runs on the same profile, connection and export code as the app, without opening a window.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, PoisonError};
+
+use crate::export::{self, Compression, Encoder, ExportFormat};
+use crate::{db, destinations, profiles, readonly, store, variables, workspaces, AppState};
+
+const USAGE: &str = "Usage:
+  spectra-studio profiles
+  spectra-studio check --profile <name|id>
+  spectra-studio export --profile <name|id> (--table <table> | --query <sql>)
+                        [--format csv|json|ndjson] [--compress gzip|zstd]
+                        [--output <file> | --destination <name|id>]
+
+Every command also takes --workspace <name> (default: the app's active workspace).";
+
+const SUBCOMMANDS: &[&str] = &["profiles", "check", "export", "help", "--help"];
+
+/// `--name value` pairs following the subcommand.
+fn parse_flags(args: &[String]) -> Result<HashMap<String, String>, String> {
+  let mut flags = HashMap::new();
+  let mut iter = args.iter();
+  while let Some(arg) = iter.next() {
+    let name = arg
+      .strip_prefix("--")
+      .ok_or_else(|| format!("Unexpected argument: {}", arg))?;
+    let value = iter
+      .next()
+      .ok_or_else(|| format!("Missing value for --{}", name))?;
+    flags.insert(name.to_string(), value.clone());
+  }
+  Ok(flags)
+}
+
+fn required<'a>(flags: &'a HashMap<String, String>, name: &str) -> Result<&'a str, String> {
+  flags
+    .get(name)
+    .map(String::as_str)
+    .ok_or_else(|| format!("--{} is required", name))
+}
+
+/// App state on top of the app's store, in `workspace` or the app's active workspace.
+async fn open_state(workspace: Option<&String>) -> Result<AppState, String> {
+  let state = AppState::new();
+  let path = store::default_path().ok_or("Could not determine the app data directory")?;
+  *state.store.lock().unwrap_or_else(PoisonError::into_inner) = Some(store::open(&path).await?);
+  match workspace {
+    Some(name) => state
+      .workspace
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .clone_from(name),
+    None => workspaces::load_active(&state).await?,
+  }
+  variables::load(&state).await?;
+  Ok(state)
+}
+
+async fn connect(
+  state: &AppState,
+  flags: &HashMap<String, String>,
+) -> Result<profiles::ConnectionProfile, String> {
+  let profile = profiles::find_profile(state, required(flags, "profile")?).await?;
+  profiles::connect(state, profile.clone()).await?;
+  Ok(profile)
+}
+
+async fn run_command(command: &str, flags: HashMap<String, String>) -> Result<(), String> {
+  let state = open_state(flags.get("workspace")).await?;
+  match command {
+    "profiles" => {
+      for profile in profiles::all_profiles(&state, None).await? {
+        println!("{}\t{}\t{}", profile.id, profile.engine, profile.name);
+      }
+    }
+    "check" => {
+      let profile = connect(&state, &flags).await?;
+      if let Ok(pool) = db::sql_pool(&state, &profile.id) {
+        pool.fetch_with_columns("SELECT 1", &[], None).await?;
+      }
+      println!("{}: ok", profile.name);
+    }
+    "export" => {
+      let format = match flags.get("format") {
+        Some(name) => ExportFormat::parse(name)?,
+        None => ExportFormat::Csv,
+      };
+      let compression = match flags.get("compress") {
+        Some(name) => Compression::parse(name)?,
+        None => Compression::None,
+      };
+      let profile = connect(&state, &flags).await?;
+      let pool = db::sql_pool(&state, &profile.id)?;
+      let table = flags.get("table");
+      let sql = match (table, flags.get("query")) {
+        (Some(table), None) => {
+          let table = pool.resolve_table(table).await?;
+          format!("SELECT * FROM {}", pool.table_ref(&table))
+        }
+        (None, Some(query)) => {
+          readonly::check_statement(&state, &profile.id, query)?;
+          query.clone()
+        }
+        _ => return Err("Pass exactly one of --table or --query".to_string()),
+      };
+      let (columns, rows) = pool.fetch_with_columns(&sql, &[], None).await?;
+      match (flags.get("output"), flags.get("destination")) {
+        (Some(_), Some(_)) => {
+          return Err("Pass at most one of --output or --destination".to_string());
+        }
+        (None, Some(name)) => {
+          let destination = destinations::find_destination(&state, name).await?;
+          let file_name = compression.file_name(&destinations::default_file_name(
+            table.map(String::as_str),
+            format,
+          ));
+          let mut body = Encoder::new(Vec::new(), compression).map_err(|e| e.to_string())?;
+          export::write_rows(&mut body, format, &columns, &rows)?;
+          let body = body.finish().map_err(|e| e.to_string())?;
+          let location = destinations::upload(
+            &destination,
+            &file_name,
+            compression.content_type(format),
+            body,
+            Arc::new(|_, _| {}),
+          )
+          .await?;
+          eprintln!("Exported {} rows to {}", rows.len(), location);
+        }
+        (Some(path), None) => {
+          let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+          let mut out =
+            Encoder::new(std::io::BufWriter::new(file), compression).map_err(|e| e.to_string())?;
+          export::write_rows(&mut out, format, &columns, &rows)?;
+          out
+            .finish()
+            .and_then(|mut out| out.flush())
+            .map_err(|e| e.to_string())?;
+          eprintln!("Exported {} rows to {}", rows.len(), path);
+        }
+        (None, None) => {
+          let mut out =
+            Encoder::new(std::io::stdout().lock(), compression).map_err(|e| e.to_string())?;
+          export::write_rows(&mut out, format, &columns, &rows)?;
+          out.finish().map(drop).map_err(|e| e.to_string())?;
+        }
+      }
+    }
+    _ => println!("{}", USAGE),
+  }
+  Ok(())
+}
+
+/// Runs a CLI subcommand when `args` (without the program name) starts with one, returning
+/// the process exit code; `None` means the app should start normally.
+pub fn run(args: &[String]) -> Option<i32> {
+  let command = args.first()?;
+  if !SUBCOMMANDS.contains(&command.as_str()) {
+    return None;
+  }
+  let outcome = parse_flags(&args[1..]).and_then(|flags| {
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    runtime.block_on(run_command(command, flags))
+  });
+  Some(match outcome {
+    Ok(()) => 0,
+    Err(e) => {
+      eprintln!("error: {}", e);
+      1
+    }
+  })
+}
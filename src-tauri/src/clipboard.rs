@@ -0,0 +1,197 @@
+//! Copying grid selections to the system clipboard as CSV, TSV, a Markdown table, a JSON array
+//! or `INSERT` statements. The text is built here rather than in the webview, so copying a
+//! selection of many thousands of rows doesn't stall the UI.
+
+use std::fmt::Write as _;
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::db::{self, JsonRow, ResultColumn};
+use crate::export::{Delimited, ExportFormat, RowWriter};
+use crate::ident::{self, Dialect};
+use crate::AppState;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CopyFormat {
+  /// CSV, TSV or JSON, written like exports are.
+  Rows(ExportFormat),
+  Markdown,
+  Insert,
+}
+
+impl CopyFormat {
+  fn parse(name: &str) -> Result<Self, String> {
+    match name.to_lowercase().as_str() {
+      "csv" => Ok(CopyFormat::Rows(ExportFormat::Csv)),
+      "tsv" | "tab" => Ok(CopyFormat::Rows(ExportFormat::Tsv)),
+      "json" => Ok(CopyFormat::Rows(ExportFormat::Json)),
+      "markdown" | "md" => Ok(CopyFormat::Markdown),
+      "insert" | "sql" => Ok(CopyFormat::Insert),
+      other => Err(format!("Unsupported copy format: {}", other)),
+    }
+  }
+}
+
+/// How the selection is laid out; everything is optional.
+#[derive(serde::Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyOptions {
+  /// Column order; the keys of the first row otherwise.
+  pub columns: Option<Vec<String>>,
+  /// Start CSV / TSV with the column names (on by default).
+  pub header: Option<bool>,
+  /// Table the `INSERT` statements target (`table_name` by default).
+  pub table: Option<String>,
+  /// Connection whose SQL dialect the `INSERT` statements are written in; standard SQL
+  /// quoting without one.
+  pub connection: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopySummary {
+  pub rows: usize,
+  /// Characters placed on the clipboard.
+  pub characters: usize,
+}
+
+/// Markdown cell text: pipes escaped and line breaks as `<br>` so the row stays one line.
+fn markdown_text(text: &str) -> String {
+  text
+    .replace('\\', "\\\\")
+    .replace('|', "\\|")
+    .replace("\r\n", "<br>")
+    .replace(['\n', '\r'], "<br>")
+}
+
+fn markdown_cell(value: Option<&serde_json::Value>) -> String {
+  match value {
+    None | Some(serde_json::Value::Null) => String::new(),
+    Some(serde_json::Value::String(s)) => markdown_text(s),
+    Some(other) => markdown_text(&other.to_string()),
+  }
+}
+
+fn markdown_line(cells: impl Iterator<Item = String>) -> String {
+  format!("| {} |\n", cells.collect::<Vec<_>>().join(" | "))
+}
+
+/// A Markdown table, with columns holding only numbers aligned right.
+fn markdown(columns: &[String], rows: &[JsonRow]) -> String {
+  let mut text = markdown_line(columns.iter().map(|c| markdown_text(c)));
+  text.push_str(&markdown_line(columns.iter().map(|c| {
+    let mut values = rows
+      .iter()
+      .filter_map(|row| row.get(c))
+      .filter(|v| !v.is_null())
+      .peekable();
+    let numeric = values.peek().is_some() && values.all(serde_json::Value::is_number);
+    if numeric { "---:" } else { "---" }.to_string()
+  })));
+  for row in rows {
+    text.push_str(&markdown_line(
+      columns.iter().map(|c| markdown_cell(row.get(c))),
+    ));
+  }
+  text
+}
+
+fn sql_literal(dialect: Dialect, value: Option<&serde_json::Value>) -> String {
+  match value {
+    None | Some(serde_json::Value::Null) => "NULL".to_string(),
+    Some(serde_json::Value::Bool(true)) => "TRUE".to_string(),
+    Some(serde_json::Value::Bool(false)) => "FALSE".to_string(),
+    Some(serde_json::Value::Number(n)) => n.to_string(),
+    Some(serde_json::Value::String(s)) => db::quote_literal(dialect, s),
+    Some(other) => db::quote_literal(dialect, &other.to_string()),
+  }
+}
+
+/// One `INSERT` statement per row.
+fn inserts(dialect: Dialect, table: &str, columns: &[String], rows: &[JsonRow]) -> String {
+  let names: Vec<String> = columns.iter().map(|c| ident::render(dialect, c)).collect();
+  let prefix = format!(
+    "INSERT INTO {} ({}) VALUES",
+    ident::render(dialect, table),
+    names.join(", ")
+  );
+  rows.iter().fold(String::new(), |mut out, row| {
+    let values: Vec<String> = columns
+      .iter()
+      .map(|c| sql_literal(dialect, row.get(c)))
+      .collect();
+    let _ = writeln!(out, "{} ({});", prefix, values.join(", "));
+    out
+  })
+}
+
+/// `rows` as text in `format`.
+fn render(
+  state: &AppState,
+  rows: &[JsonRow],
+  format: CopyFormat,
+  options: &CopyOptions,
+) -> Result<String, String> {
+  let columns: Vec<String> = match &options.columns {
+    Some(columns) => columns.clone(),
+    None => rows
+      .first()
+      .map(|row| row.keys().cloned().collect())
+      .unwrap_or_default(),
+  };
+  match format {
+    CopyFormat::Rows(format) => {
+      let mut delimited = Delimited::of(format);
+      delimited.header = options.header.unwrap_or(true);
+      let columns: Vec<ResultColumn> = columns
+        .into_iter()
+        .map(|name| ResultColumn {
+          name,
+          type_name: String::new(),
+          numeric: false,
+        })
+        .collect();
+      let mut out = Vec::new();
+      let mut writer = RowWriter::new(format, delimited);
+      writer
+        .begin(&mut out, &columns)
+        .and_then(|()| rows.iter().try_for_each(|row| writer.row(&mut out, row)))
+        .and_then(|()| writer.end(&mut out))
+        .map_err(|e| e.to_string())?;
+      String::from_utf8(out).map_err(|e| e.to_string())
+    }
+    CopyFormat::Markdown => Ok(markdown(&columns, rows)),
+    CopyFormat::Insert => {
+      let dialect = match &options.connection {
+        Some(connection) => db::sql_pool(state, connection)?.dialect(),
+        None => Dialect::Postgres,
+      };
+      let table = options.table.as_deref().unwrap_or("table_name");
+      Ok(inserts(dialect, table, &columns, rows))
+    }
+  }
+}
+
+/// Puts `rows` on the system clipboard as `csv`, `tsv`, `markdown`, `json` or `insert`
+/// statements.
+#[tauri::command]
+pub async fn copy_rows_to_clipboard(
+  app: AppHandle,
+  rows: Vec<JsonRow>,
+  format: String,
+  options: Option<CopyOptions>,
+) -> Result<CopySummary, String> {
+  let format = CopyFormat::parse(&format)?;
+  let options = options.unwrap_or_default();
+  let text = render(&app.state::<AppState>(), &rows, format, &options)?;
+  let characters = text.chars().count();
+  app
+    .clipboard()
+    .write_text(text)
+    .map_err(|e| format!("Cannot write to the clipboard: {}", e))?;
+  Ok(CopySummary {
+    rows: rows.len(),
+    characters,
+  })
+}
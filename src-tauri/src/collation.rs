@@ -0,0 +1,131 @@
+//! Side-by-side ORDER BY previews under different collations, for tracking down sort order
+//! differences between environments.
+
+use tauri::State;
+
+use crate::db::{self, JsonRow, SqlPool};
+use crate::{masking, timezone, AppState};
+
+const DEFAULT_PREVIEW_ROWS: usize = 50;
+const MAX_PREVIEW_ROWS: usize = 1000;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SortOrdering {
+  /// `None` for the column's own collation.
+  pub collation: Option<String>,
+  pub rows: Vec<JsonRow>,
+  /// Set instead of `rows` when the engine rejected the collation.
+  pub error: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SortPreview {
+  pub column: String,
+  pub orderings: Vec<SortOrdering>,
+}
+
+/// The `COLLATE` operand for `collation`, quoted the way the dialect expects.
+fn collate_clause(pool: &SqlPool, collation: &str) -> Result<String, String> {
+  let collation = collation.trim();
+  match pool {
+    // Postgres collation names are identifiers and may contain `-` and `.` (`en-US-x-icu`)
+    SqlPool::Postgres(_) if !collation.is_empty() => Ok(pool.quote_ident(collation)),
+    _ if !collation.is_empty()
+      && collation
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+    {
+      Ok(collation.to_string())
+    }
+    _ => Err(format!("Invalid collation name: {}", collation)),
+  }
+}
+
+/// First `limit` rows of `table` ordered by `column`, once with the column's own collation
+/// and once per entry of `collations`.
+#[tauri::command]
+pub async fn preview_sort(
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+  column: String,
+  collations: Vec<String>,
+  limit: Option<usize>,
+) -> Result<SortPreview, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let table = pool.resolve_table(&table).await?;
+  let limit = limit
+    .unwrap_or(DEFAULT_PREVIEW_ROWS)
+    .clamp(1, MAX_PREVIEW_ROWS);
+  let tz = timezone::display_zone(&state, &connection);
+  let mask = masking::active(&state, &connection);
+
+  let mut orderings = Vec::with_capacity(collations.len() + 1);
+  for collation in std::iter::once(None).chain(collations.into_iter().map(Some)) {
+    let collate = match &collation {
+      Some(name) => format!(" COLLATE {}", collate_clause(&pool, name)?),
+      None => String::new(),
+    };
+    let sql = format!(
+      "SELECT * FROM {} ORDER BY {}{} LIMIT {}",
+      pool.table_ref(&table),
+      pool.quote_ident(&column),
+      collate,
+      limit
+    );
+    let ordering = match pool.fetch_with_columns(&sql, &[], tz.as_ref()).await {
+      Ok((_, mut rows)) => {
+        if let Some(mask) = &mask {
+          for row in &mut rows {
+            mask.apply(row);
+          }
+        }
+        SortOrdering {
+          collation,
+          rows,
+          error: None,
+        }
+      }
+      Err(e) => SortOrdering {
+        collation,
+        rows: Vec::new(),
+        error: Some(e),
+      },
+    };
+    orderings.push(ordering);
+  }
+
+  Ok(SortPreview { column, orderings })
+}
+
+/// Collation names the connected server knows about.
+#[tauri::command]
+pub async fn list_collations(
+  state: State<'_, AppState>,
+  connection: String,
+) -> Result<Vec<String>, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let names = match &pool {
+    SqlPool::MySql(mysql) => {
+      sqlx::query_as::<_, (String,)>(
+        "SELECT CAST(COLLATION_NAME AS CHAR) FROM information_schema.COLLATIONS \
+         ORDER BY COLLATION_NAME",
+      )
+      .fetch_all(mysql)
+      .await
+    }
+    SqlPool::Postgres(pg) => {
+      sqlx::query_as::<_, (String,)>("SELECT DISTINCT collname::text FROM pg_collation ORDER BY 1")
+        .fetch_all(pg)
+        .await
+    }
+    SqlPool::Sqlite(sqlite) => sqlx::query_as::<_, (i32, String)>("PRAGMA collation_list")
+      .fetch_all(sqlite)
+      .await
+      .map(|rows| rows.into_iter().map(|(_, name)| (name,)).collect()),
+  }
+  .map_err(|e| e.to_string())?;
+  Ok(names.into_iter().map(|(name,)| name).collect())
+}
@@ -0,0 +1,93 @@
+//! Typed confirmations for destructive commands. Called without a confirmation, such a command
+//! returns a challenge instead of acting; it only runs when called again with the challenge's
+//! token and the phrase the user typed. Tokens are single-use, tied to the exact action they
+//! were issued for and expire after a minute.
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+
+use crate::{store, AppState};
+
+const TOKEN_TTL: Duration = Duration::from_mins(1);
+
+pub struct Pending {
+  action: String,
+  phrase: String,
+  expires_at: Instant,
+}
+
+pub type Confirmations = HashMap<String, Pending>;
+
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Confirmation {
+  pub token: String,
+  /// What the user typed; must equal the challenge's phrase.
+  pub typed: String,
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Challenge {
+  pub token: String,
+  /// Text the user has to type to go ahead.
+  pub phrase: String,
+  /// What will happen, for the confirmation dialog.
+  pub summary: String,
+  pub expires_in_ms: u64,
+}
+
+/// Result of a guarded command: a challenge on the first call, the outcome once confirmed.
+#[derive(serde::Serialize, Debug)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum Guarded<T> {
+  ConfirmationRequired(Challenge),
+  Done(T),
+}
+
+/// Issues a token for `action` (a key identifying exactly what will be done).
+pub fn challenge(state: &AppState, action: &str, phrase: &str, summary: String) -> Challenge {
+  let mut pending = state
+    .confirmations
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let now = Instant::now();
+  pending.retain(|_, p| p.expires_at > now);
+  let token = format!("{}-{:x}", crate::next_id("confirm"), store::now_ms());
+  pending.insert(
+    token.clone(),
+    Pending {
+      action: action.to_string(),
+      phrase: phrase.to_string(),
+      expires_at: now + TOKEN_TTL,
+    },
+  );
+  Challenge {
+    token,
+    phrase: phrase.to_string(),
+    summary,
+    expires_in_ms: u64::try_from(TOKEN_TTL.as_millis()).unwrap_or(u64::MAX),
+  }
+}
+
+/// Consumes the token of `confirmation` if it was issued for `action` and the typed phrase
+/// matches.
+pub fn redeem(state: &AppState, confirmation: &Confirmation, action: &str) -> Result<(), String> {
+  let pending = state
+    .confirmations
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&confirmation.token)
+    .ok_or("Unknown or already used confirmation token")?;
+  if pending.expires_at <= Instant::now() {
+    return Err("Confirmation expired; request a new one".to_string());
+  }
+  if pending.action != action {
+    return Err("Confirmation token was issued for a different action".to_string());
+  }
+  if confirmation.typed.trim() != pending.phrase {
+    return Err(format!("Type '{}' to confirm", pending.phrase));
+  }
+  Ok(())
+}
@@ -0,0 +1,174 @@
+//! Registry of open connections keyed by a connection id, so several servers of the same
+//! engine can be open side by side. Commands called without an id use the engine name
+//! (`mysql`, `postgres`, `sqlite`, `redis`, `mongodb`) as the id, which keeps single-connection
+//! callers working unchanged.
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+
+use sqlx::{MySqlPool, PgPool, SqlitePool};
+use tauri::State;
+
+use crate::{reconnect, AppState};
+
+#[derive(Default)]
+pub struct Connections {
+  pub redis: HashMap<String, redis::Client>,
+  pub mysql: HashMap<String, MySqlPool>,
+  pub postgres: HashMap<String, PgPool>,
+  pub sqlite: HashMap<String, SqlitePool>,
+  pub mongodb: HashMap<String, mongodb::Client>,
+}
+
+impl Connections {
+  pub fn engine_of(&self, id: &str) -> Option<&'static str> {
+    if self.mysql.contains_key(id) {
+      Some("mysql")
+    } else if self.postgres.contains_key(id) {
+      Some("postgres")
+    } else if self.sqlite.contains_key(id) {
+      Some("sqlite")
+    } else if self.redis.contains_key(id) {
+      Some("redis")
+    } else if self.mongodb.contains_key(id) {
+      Some("mongodb")
+    } else {
+      None
+    }
+  }
+}
+
+/// The id a connect command registers under: the given one, or the engine name.
+pub fn resolve_id(connection_id: Option<String>, engine: &str) -> Result<String, String> {
+  let id = connection_id.unwrap_or_else(|| engine.to_string());
+  if id.trim().is_empty() {
+    return Err("Connection id cannot be empty".to_string());
+  }
+  Ok(id)
+}
+
+/// Fails when `id` is already open as a different engine. Reconnecting with the same engine
+/// replaces the previous connection.
+pub fn ensure_available(state: &AppState, id: &str, engine: &str) -> Result<(), String> {
+  match state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .engine_of(id)
+  {
+    Some(existing) if existing != engine => Err(format!(
+      "Connection id '{}' is already used by a {} connection",
+      id, existing
+    )),
+    _ => Ok(()),
+  }
+}
+
+fn lookup<T: Clone>(map: &HashMap<String, T>, id: &str) -> Result<T, String> {
+  map
+    .get(id)
+    .cloned()
+    .ok_or_else(|| "Not connected".to_string())
+}
+
+pub fn mysql(state: &AppState, connection_id: Option<&str>) -> Result<MySqlPool, String> {
+  let id = connection_id.unwrap_or("mysql");
+  let found = lookup(
+    &state
+      .connections
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .mysql,
+    id,
+  )?;
+  reconnect::ensure_up(state, id)?;
+  Ok(found)
+}
+
+pub fn postgres(state: &AppState, connection_id: Option<&str>) -> Result<PgPool, String> {
+  let id = connection_id.unwrap_or("postgres");
+  let found = lookup(
+    &state
+      .connections
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .postgres,
+    id,
+  )?;
+  reconnect::ensure_up(state, id)?;
+  Ok(found)
+}
+
+pub fn sqlite(state: &AppState, connection_id: Option<&str>) -> Result<SqlitePool, String> {
+  lookup(
+    &state
+      .connections
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .sqlite,
+    connection_id.unwrap_or("sqlite"),
+  )
+}
+
+pub fn redis(state: &AppState, connection_id: Option<&str>) -> Result<redis::Client, String> {
+  let id = connection_id.unwrap_or("redis");
+  let found = lookup(
+    &state
+      .connections
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .redis,
+    id,
+  )?;
+  reconnect::ensure_up(state, id)?;
+  Ok(found)
+}
+
+pub fn mongodb(state: &AppState, connection_id: Option<&str>) -> Result<mongodb::Client, String> {
+  let id = connection_id.unwrap_or("mongodb");
+  let found = lookup(
+    &state
+      .connections
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .mongodb,
+    id,
+  )?;
+  reconnect::ensure_up(state, id)?;
+  Ok(found)
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionEntry {
+  pub connection_id: String,
+  pub engine: &'static str,
+  pub ssh_tunnel: bool,
+  pub status: reconnect::Status,
+}
+
+#[tauri::command]
+pub fn list_connections(state: State<'_, AppState>) -> Vec<ConnectionEntry> {
+  let connections = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let tunnels = state.tunnels.lock().unwrap_or_else(PoisonError::into_inner);
+  let mut entries = Vec::new();
+  for (engine, ids) in [
+    ("mysql", connections.mysql.keys().collect::<Vec<_>>()),
+    ("postgres", connections.postgres.keys().collect()),
+    ("sqlite", connections.sqlite.keys().collect()),
+    ("redis", connections.redis.keys().collect()),
+    ("mongodb", connections.mongodb.keys().collect()),
+  ] {
+    entries.extend(ids.into_iter().map(|id| ConnectionEntry {
+      connection_id: id.clone(),
+      engine,
+      ssh_tunnel: tunnels.contains_key(id),
+      status: reconnect::status(&state, id),
+    }));
+  }
+  entries.sort_by(|a, b| a.connection_id.cmp(&b.connection_id));
+  entries
+}
@@ -0,0 +1,287 @@
+//! "Test" button of the connection dialog: connects exactly like the `connect_*` commands
+//! (credentials, IAM, SSH tunnel and all), reports the server version and latency, then tears
+//! everything down again. Runs on a scratch [`AppState`], so open connections are untouched.
+
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+
+use crate::db;
+use crate::iam::IamAuth;
+use crate::secrets::{self, SecretKind};
+use crate::{AppState, SshConfig};
+
+/// Id the connection under test is registered under in the scratch state.
+const TEST_ID: &str = "connection-test";
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionTest {
+  pub server_version: String,
+  /// Round trip of one query once connected.
+  pub latency_ms: u64,
+  /// Time taken to connect, including the SSH tunnel.
+  pub connect_ms: u64,
+  pub ssh_tunnel: bool,
+}
+
+fn millis(duration: Duration) -> u64 {
+  u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+async fn sql_version(scratch: &AppState) -> Result<(String, Duration), String> {
+  let pool = db::sql_pool(scratch, TEST_ID)?;
+  let sql = match pool.engine() {
+    "sqlite" => "SELECT sqlite_version() AS version",
+    "postgres" => "SELECT current_setting('server_version') AS version",
+    _ => "SELECT VERSION() AS version",
+  };
+  let started = Instant::now();
+  let rows = pool.fetch_rows(sql, &[]).await?;
+  let latency = started.elapsed();
+  let version = rows
+    .first()
+    .and_then(|row| row.get("version"))
+    .and_then(|v| v.as_str())
+    .unwrap_or_default()
+    .to_string();
+  Ok((version, latency))
+}
+
+async fn redis_version(scratch: &AppState) -> Result<(String, Duration), String> {
+  let client = crate::connections::redis(scratch, Some(TEST_ID))?;
+  let mut con = client
+    .get_multiplexed_async_connection()
+    .await
+    .map_err(|e| e.to_string())?;
+  let started = Instant::now();
+  let info: String = redis::cmd("INFO")
+    .arg("server")
+    .query_async(&mut con)
+    .await
+    .map_err(|e| e.to_string())?;
+  let latency = started.elapsed();
+  let version = info
+    .lines()
+    .find_map(|line| line.strip_prefix("redis_version:"))
+    .unwrap_or_default()
+    .trim()
+    .to_string();
+  Ok((version, latency))
+}
+
+async fn mongodb_version(scratch: &AppState) -> Result<(String, Duration), String> {
+  let client = scratch
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .mongodb
+    .get(TEST_ID)
+    .cloned()
+    .ok_or("Not connected")?;
+  let started = Instant::now();
+  let info = client
+    .database("admin")
+    .run_command(mongodb::bson::doc! { "buildInfo": 1 })
+    .await
+    .map_err(|e| e.to_string())?;
+  let latency = started.elapsed();
+  let version = info.get_str("version").unwrap_or_default().to_string();
+  Ok((version, latency))
+}
+
+/// Measures the connection opened in `scratch` by `opened`, then closes everything.
+async fn finish(
+  scratch: AppState,
+  started: Instant,
+  opened: Result<String, String>,
+) -> Result<ConnectionTest, String> {
+  let connect_ms = millis(started.elapsed());
+  let measured = match opened {
+    Ok(_) => {
+      let engine = scratch
+        .connections
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .engine_of(TEST_ID);
+      match engine {
+        Some("redis") => redis_version(&scratch).await,
+        Some("mongodb") => mongodb_version(&scratch).await,
+        Some(_) => sql_version(&scratch).await,
+        None => Err("Not connected".to_string()),
+      }
+    }
+    Err(e) => Err(e),
+  };
+  let ssh_tunnel = scratch
+    .tunnels
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .contains_key(TEST_ID);
+
+  let pools = std::mem::take(
+    &mut *scratch
+      .connections
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner),
+  );
+  for pool in pools.mysql.into_values() {
+    pool.close().await;
+  }
+  for pool in pools.postgres.into_values() {
+    pool.close().await;
+  }
+  for pool in pools.sqlite.into_values() {
+    pool.close().await;
+  }
+  // Dropping the scratch state closes the tunnel
+  drop(scratch);
+
+  let (server_version, latency) = measured?;
+  Ok(ConnectionTest {
+    server_version,
+    latency_ms: millis(latency),
+    connect_ms,
+    ssh_tunnel,
+  })
+}
+
+#[tauri::command]
+pub async fn test_sqlite_connection(path: String) -> Result<ConnectionTest, String> {
+  let scratch = AppState::new();
+  let started = Instant::now();
+  let opened = crate::open_sqlite(&scratch, path, Some(TEST_ID.to_string()), None).await;
+  finish(scratch, started, opened).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn test_redis_connection(
+  host: String,
+  port: u16,
+  password: Option<String>,
+  timeout_sec: Option<u64>,
+  ssh_config: Option<SshConfig>,
+  socket_path: Option<String>,
+  profile_id: Option<String>,
+) -> Result<ConnectionTest, String> {
+  let password = secrets::resolve(profile_id.as_deref(), SecretKind::Password, password).await?;
+  let ssh_config = secrets::resolve_ssh(profile_id.as_deref(), ssh_config).await?;
+  let scratch = AppState::new();
+  let started = Instant::now();
+  let opened = crate::open_redis(
+    &scratch,
+    host,
+    port,
+    password,
+    0,
+    timeout_sec,
+    ssh_config,
+    socket_path,
+    Some(TEST_ID.to_string()),
+  )
+  .await;
+  finish(scratch, started, opened).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn test_mysql_connection(
+  host: String,
+  port: u16,
+  username: String,
+  password: Option<String>,
+  database: Option<String>,
+  timeout_sec: Option<u64>,
+  ssh_config: Option<SshConfig>,
+  iam_auth: Option<IamAuth>,
+  socket_path: Option<String>,
+  profile_id: Option<String>,
+) -> Result<ConnectionTest, String> {
+  let password = secrets::resolve(profile_id.as_deref(), SecretKind::Password, password).await?;
+  let ssh_config = secrets::resolve_ssh(profile_id.as_deref(), ssh_config).await?;
+  let scratch = AppState::new();
+  let started = Instant::now();
+  let opened = crate::open_mysql(
+    &scratch,
+    host,
+    port,
+    username,
+    password,
+    database,
+    timeout_sec,
+    ssh_config,
+    iam_auth,
+    false,
+    socket_path,
+    Some(TEST_ID.to_string()),
+    None,
+  )
+  .await;
+  finish(scratch, started, opened).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn test_postgres_connection(
+  host: String,
+  port: u16,
+  username: String,
+  password: Option<String>,
+  database: Option<String>,
+  timeout_sec: Option<u64>,
+  ssh_config: Option<SshConfig>,
+  iam_auth: Option<IamAuth>,
+  socket_path: Option<String>,
+  profile_id: Option<String>,
+) -> Result<ConnectionTest, String> {
+  let password = secrets::resolve(profile_id.as_deref(), SecretKind::Password, password).await?;
+  let ssh_config = secrets::resolve_ssh(profile_id.as_deref(), ssh_config).await?;
+  let scratch = AppState::new();
+  let started = Instant::now();
+  let opened = crate::open_postgres(
+    &scratch,
+    host,
+    port,
+    username,
+    password,
+    database,
+    timeout_sec,
+    ssh_config,
+    iam_auth,
+    false,
+    socket_path,
+    Some(TEST_ID.to_string()),
+    None,
+  )
+  .await;
+  finish(scratch, started, opened).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn test_mongodb_connection(
+  host: String,
+  port: u16,
+  username: Option<String>,
+  password: Option<String>,
+  timeout_sec: Option<u64>,
+  ssh_config: Option<SshConfig>,
+  profile_id: Option<String>,
+) -> Result<ConnectionTest, String> {
+  let password = secrets::resolve(profile_id.as_deref(), SecretKind::Password, password).await?;
+  let ssh_config = secrets::resolve_ssh(profile_id.as_deref(), ssh_config).await?;
+  let scratch = AppState::new();
+  let started = Instant::now();
+  let opened = crate::open_mongodb(
+    &scratch,
+    host,
+    port,
+    username,
+    password,
+    timeout_sec,
+    ssh_config,
+    Some(TEST_ID.to_string()),
+  )
+  .await;
+  finish(scratch, started, opened).await
+}
@@ -0,0 +1,381 @@
+//! Row limit for the raw console. A SELECT without its own LIMIT only returns the first
+//! `console.rowLimit` rows (a workspace setting, 1000 by default, `0` to turn the guard off);
+//! the rest stay behind a cursor that `fetch_next_page` reads from, so the result is never
+//! buffered whole.
+//!
+//! A cursor holds one pooled connection with the statement's row stream open on it, so a
+//! connection keeps at most one cursor fewer than its pool has connections. It is closed when
+//! it runs out of rows, by `close_cursor`, when its connection or console closes, or after
+//! sitting idle for [`IDLE_TIMEOUT`]. The connection is taken through [`crate::cancel`], so the statement can
+//! be cancelled until its first page is in and carries the statement timeout for as long as
+//! the cursor stays open.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use sqlparser::ast::{SetExpr, Statement};
+use sqlparser::dialect::{Dialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect};
+use sqlparser::parser::Parser;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::cancel::{self, Running};
+use crate::db::{self, JsonRow, SqlPool};
+use crate::{masking, readonly, timeouts, timezone, transfer, workspaces, AppState};
+
+const ROW_LIMIT_SETTING: &str = "console.rowLimit";
+const DEFAULT_ROW_LIMIT: usize = 1000;
+const IDLE_TIMEOUT: Duration = Duration::from_mins(1);
+
+/// Orders cursors by when they were opened.
+static OPENED: AtomicU64 = AtomicU64::new(0);
+
+type PageReply = oneshot::Sender<Result<(Vec<JsonRow>, bool), String>>;
+
+pub struct Cursor {
+  connection: String,
+  opened: u64,
+  pages: mpsc::Sender<(usize, PageReply)>,
+}
+
+pub type Cursors = HashMap<String, Cursor>;
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TruncatedEvent {
+  connection_id: String,
+  /// `None` when the pool has no connection to spare for a cursor.
+  cursor_id: Option<String>,
+  rows_returned: usize,
+  limit: usize,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorPage {
+  pub rows: Vec<serde_json::Value>,
+  /// Whether the cursor may have more rows; once it hasn't, it is closed.
+  pub has_more: bool,
+}
+
+/// Whether `sql` is a single SELECT with no LIMIT, FETCH or TOP of its own.
+fn is_unbounded_select(pool: &SqlPool, sql: &str) -> bool {
+  let dialect: Box<dyn Dialect> = match pool {
+    SqlPool::MySql(_) => Box::new(MySqlDialect {}),
+    SqlPool::Postgres(_) => Box::new(PostgreSqlDialect {}),
+    SqlPool::Sqlite(_) => Box::new(SQLiteDialect {}),
+  };
+  let Ok(statements) = Parser::parse_sql(dialect.as_ref(), sql) else {
+    return false;
+  };
+  match statements.as_slice() {
+    [Statement::Query(query)] => {
+      let top = match query.body.as_ref() {
+        SetExpr::Select(select) => select.top.is_some(),
+        _ => false,
+      };
+      query.limit.is_none() && query.fetch.is_none() && !top
+    }
+    _ => false,
+  }
+}
+
+/// Cursors `pool` can hold while leaving a connection for everything else; opening another
+/// closes the oldest.
+fn max_cursors(pool: &SqlPool) -> usize {
+  let size = match pool {
+    SqlPool::MySql(pool) => pool.options().get_max_connections(),
+    SqlPool::Postgres(pool) => pool.options().get_max_connections(),
+    SqlPool::Sqlite(pool) => pool.options().get_max_connections(),
+  };
+  size.saturating_sub(1) as usize
+}
+
+async fn configured_limit(state: &AppState) -> usize {
+  match workspaces::setting(state, ROW_LIMIT_SETTING).await {
+    Ok(Some(value)) => value.trim().parse().unwrap_or(DEFAULT_ROW_LIMIT),
+    _ => DEFAULT_ROW_LIMIT,
+  }
+}
+
+/// Row limit the console applies to `sql`, or `None` when it runs unguarded.
+pub async fn row_limit(state: &AppState, pool: &SqlPool, sql: &str) -> Option<usize> {
+  let limit = configured_limit(state).await;
+  (limit > 0 && is_unbounded_select(pool, sql)).then_some(limit)
+}
+
+/// Answers page requests from the held row stream until it runs out, the cursor is
+/// closed, or no request arrives for [`IDLE_TIMEOUT`].
+async fn serve(
+  mut rows: impl Stream<Item = Result<JsonRow, String>> + Unpin,
+  mut pages: mpsc::Receiver<(usize, PageReply)>,
+) {
+  while let Ok(Some((size, reply))) = tokio::time::timeout(IDLE_TIMEOUT, pages.recv()).await {
+    let mut page = Vec::with_capacity(size);
+    let mut failed = None;
+    while page.len() < size {
+      match rows.next().await {
+        Some(Ok(row)) => page.push(row),
+        Some(Err(e)) => {
+          failed = Some(e);
+          break;
+        }
+        None => break,
+      }
+    }
+    // A page that stopped short is the last one
+    let has_more = failed.is_none() && page.len() == size;
+    let _ = reply.send(match failed {
+      Some(e) => Err(e),
+      None => Ok((page, has_more)),
+    });
+    if !has_more {
+      return;
+    }
+  }
+}
+
+/// Opens a cursor over `sql` on a dedicated pooled connection, registered as `query_id`
+/// until the returned registration is dropped.
+async fn open<'a>(
+  state: &'a AppState,
+  query_id: Option<String>,
+  connection: &str,
+  pool: &SqlPool,
+  sql: &str,
+  binds: &[String],
+  timeout: Option<Duration>,
+) -> Result<(mpsc::Sender<(usize, PageReply)>, Running<'a>), String> {
+  let tz = timezone::display_zone(state, connection);
+  let guard = readonly::guard(state, connection, pool);
+  let (sender, receiver) = mpsc::channel(1);
+  let binds = binds.to_vec();
+  let running = match pool {
+    SqlPool::MySql(pool) => {
+      let (mut conn, running) = cancel::mysql(state, query_id, connection, pool, timeout).await?;
+      if let Some(guard) = &guard {
+        guard.begin(&mut *conn).await?;
+      }
+      let sql = timeouts::mysql_hint(sql, timeout).into_owned();
+      tokio::spawn(async move {
+        let mut query = sqlx::query(&sql);
+        for value in &binds {
+          query = query.bind(value);
+        }
+        let rows = query.fetch(&mut *conn).map(|row| {
+          row
+            .map(|row| db::mysql_row_to_json(&row, tz.as_ref()))
+            .map_err(|e| e.to_string())
+        });
+        serve(rows, receiver).await;
+        if let Some(guard) = &guard {
+          if !guard.end(&mut *conn).await {
+            conn.close_on_drop();
+          }
+        }
+      });
+      running
+    }
+    SqlPool::Postgres(pool) => {
+      let (mut conn, running) =
+        cancel::postgres(state, query_id, connection, pool, timeout).await?;
+      if let Some(guard) = &guard {
+        guard.begin(&mut *conn).await?;
+      }
+      let sql = sql.to_string();
+      tokio::spawn(async move {
+        let mut query = sqlx::query(&sql);
+        for value in &binds {
+          query = query.bind(value);
+        }
+        let rows = query.fetch(&mut *conn).map(|row| {
+          row
+            .map(|row| db::pg_row_to_json(&row, tz.as_ref()))
+            .map_err(|e| e.to_string())
+        });
+        serve(rows, receiver).await;
+        if let Some(guard) = &guard {
+          if !guard.end(&mut *conn).await {
+            conn.close_on_drop();
+          }
+        }
+        cancel::reset_postgres_timeout(&mut conn, timeout).await;
+      });
+      running
+    }
+    SqlPool::Sqlite(pool) => {
+      let (mut conn, running) = cancel::sqlite(state, query_id, connection, pool, timeout).await?;
+      if let Some(guard) = &guard {
+        guard.begin(&mut *conn).await?;
+      }
+      let sql = sql.to_string();
+      tokio::spawn(async move {
+        let mut query = sqlx::query(&sql);
+        for value in &binds {
+          query = query.bind(value);
+        }
+        let rows = query.fetch(&mut *conn).map(|row| {
+          row
+            .map(|row| db::sqlite_row_to_json(&row))
+            .map_err(|e| e.to_string())
+        });
+        serve(rows, receiver).await;
+        if let Some(guard) = &guard {
+          if !guard.end(&mut *conn).await {
+            conn.close_on_drop();
+          }
+        }
+      });
+      running
+    }
+  };
+  Ok((sender, running))
+}
+
+async fn next_page(
+  pages: &mpsc::Sender<(usize, PageReply)>,
+  size: usize,
+) -> Result<(Vec<JsonRow>, bool), String> {
+  let expired = || "Cursor expired; run the query again".to_string();
+  let (reply, answer) = oneshot::channel();
+  pages.send((size, reply)).await.map_err(|_| expired())?;
+  answer.await.map_err(|_| expired())?
+}
+
+/// Runs an unbounded SELECT from the console, returning its first `limit` rows. When more
+/// are left, they are kept behind a cursor and a `console-result-truncated` event carries
+/// its id. `query_id` cancels the statement until the first page is in.
+#[allow(clippy::too_many_arguments)]
+pub async fn first_page(
+  app: &AppHandle,
+  state: &AppState,
+  connection: &str,
+  pool: &SqlPool,
+  sql: &str,
+  binds: &[String],
+  limit: usize,
+  query_id: Option<String>,
+  timeout: Option<Duration>,
+) -> Result<Vec<serde_json::Value>, String> {
+  let (pages, running) = open(state, query_id, connection, pool, sql, binds, timeout).await?;
+  let (rows, has_more) = next_page(&pages, limit)
+    .await
+    .map_err(|e| running.error(e))?;
+  drop(running);
+  if has_more {
+    let max = max_cursors(pool);
+    let cursor_id = (max > 0).then(|| crate::next_id("cursor"));
+    if let Some(cursor_id) = &cursor_id {
+      let mut cursors = state
+        .console_cursors
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+      let mut open_here: Vec<(u64, String)> = cursors
+        .iter()
+        .filter(|(_, c)| c.connection == connection)
+        .map(|(id, c)| (c.opened, id.clone()))
+        .collect();
+      open_here.sort();
+      let excess = (open_here.len() + 1).saturating_sub(max);
+      for (_, oldest) in &open_here[..excess] {
+        cursors.remove(oldest);
+      }
+      cursors.insert(
+        cursor_id.clone(),
+        Cursor {
+          connection: connection.to_string(),
+          opened: OPENED.fetch_add(1, Ordering::Relaxed),
+          pages,
+        },
+      );
+    }
+    let _ = app.emit(
+      "console-result-truncated",
+      TruncatedEvent {
+        connection_id: connection.to_string(),
+        cursor_id,
+        rows_returned: rows.len(),
+        limit,
+      },
+    );
+  }
+  Ok(rows.into_iter().map(serde_json::Value::Object).collect())
+}
+
+/// Next `page_size` rows (the console row limit by default) of a truncated console result.
+#[tauri::command]
+pub async fn fetch_next_page(
+  state: State<'_, AppState>,
+  cursor_id: String,
+  page_size: Option<usize>,
+) -> Result<CursorPage, String> {
+  let (connection, pages) = {
+    let cursors = state
+      .console_cursors
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    let cursor = cursors
+      .get(&cursor_id)
+      .ok_or_else(|| format!("Unknown or closed cursor: {}", cursor_id))?;
+    (cursor.connection.clone(), cursor.pages.clone())
+  };
+  let size = match page_size {
+    Some(size) => size,
+    None => configured_limit(&state).await,
+  }
+  .max(1);
+  let fetched = next_page(&pages, size).await;
+  if !matches!(fetched, Ok((_, true))) {
+    state
+      .console_cursors
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .remove(&cursor_id);
+  }
+  let (mut rows, has_more) = fetched?;
+  if let Some(mask) = masking::active(&state, &connection) {
+    for row in &mut rows {
+      mask.apply(row);
+    }
+  }
+  transfer::record(
+    &state,
+    &connection,
+    transfer::Category::Query,
+    rows.len(),
+    transfer::rows_bytes(&rows),
+  );
+  Ok(CursorPage {
+    rows: rows.into_iter().map(serde_json::Value::Object).collect(),
+    has_more,
+  })
+}
+
+/// Closes a cursor early, releasing its connection.
+#[tauri::command]
+pub fn close_cursor(state: State<'_, AppState>, cursor_id: String) {
+  state
+    .console_cursors
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&cursor_id);
+}
+
+/// Closes every cursor of `connection`, releasing their pooled connections. A pool only
+/// finishes closing once they are back.
+pub fn close_for(state: &AppState, connection: &str) {
+  state
+    .console_cursors
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .retain(|_, cursor| cursor.connection != connection);
+}
+
+/// Closes the cursors a connection's console left open, e.g. when it is closed.
+#[tauri::command]
+pub fn close_cursors(state: State<'_, AppState>, connection_id: String) {
+  close_for(&state, &connection_id);
+}
@@ -0,0 +1,739 @@
+//! Engine-agnostic helpers shared by features that work across MySQL, Postgres and SQLite.
+
+use std::sync::PoisonError;
+
+use chrono_tz::Tz;
+use futures::TryStreamExt;
+use sqlx::mysql::MySqlRow;
+use sqlx::postgres::PgRow;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Column, MySqlPool, PgPool, Row, SqlitePool, TypeInfo, ValueRef};
+
+use crate::ident::{self, Dialect};
+use crate::variables::Placeholder;
+use crate::AppState;
+use crate::{reconnect, session, timezone};
+
+pub type JsonRow = serde_json::Map<String, serde_json::Value>;
+
+/// Largest integer a JS number holds exactly; bigger values are sent as strings.
+const MAX_SAFE_INTEGER: i128 = (1 << 53) - 1;
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultColumn {
+  pub name: String,
+  /// Engine type name, e.g. `BIGINT UNSIGNED` or `NUMERIC`.
+  pub type_name: String,
+  /// Values are numbers even when they arrive as strings (exact decimals, huge integers).
+  pub numeric: bool,
+}
+
+fn is_numeric_type(type_name: &str) -> bool {
+  let base = type_name.trim_end_matches(" UNSIGNED").to_ascii_uppercase();
+  matches!(
+    base.as_str(),
+    "TINYINT"
+      | "SMALLINT"
+      | "MEDIUMINT"
+      | "INT"
+      | "INTEGER"
+      | "BIGINT"
+      | "INT2"
+      | "INT4"
+      | "INT8"
+      | "FLOAT"
+      | "FLOAT4"
+      | "FLOAT8"
+      | "DOUBLE"
+      | "REAL"
+      | "DECIMAL"
+      | "NUMERIC"
+  )
+}
+
+/// Integer as a JSON number when JS can represent it exactly, otherwise as a string.
+fn int_json(value: i128) -> serde_json::Value {
+  match i64::try_from(value) {
+    Ok(n) if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&value) => serde_json::Value::from(n),
+    _ => serde_json::Value::String(value.to_string()),
+  }
+}
+
+/// Columns of a result set, in order; empty when there are no rows.
+pub fn result_columns<R: Row>(rows: &[R]) -> Vec<ResultColumn> {
+  rows
+    .first()
+    .map(|row| {
+      row
+        .columns()
+        .iter()
+        .map(|c| {
+          let type_name = c.type_info().name().to_string();
+          ResultColumn {
+            name: c.name().to_string(),
+            numeric: is_numeric_type(&type_name),
+            type_name,
+          }
+        })
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Textual form for date/time values that every engine accepts back as a literal.
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// Schema Postgres tables are addressed in when a command doesn't name one.
+pub const PG_DEFAULT_SCHEMA: &str = "public";
+
+/// Schemas of a Postgres database the user may look into, leaving out the system ones.
+pub async fn pg_schemas(pool: &PgPool) -> Result<Vec<String>, String> {
+  let rows: Vec<(String,)> = sqlx::query_as(
+    "SELECT nspname::text FROM pg_namespace \
+     WHERE nspname NOT LIKE 'pg\\_%' AND nspname <> 'information_schema' \
+     AND has_schema_privilege(oid, 'USAGE') ORDER BY nspname",
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(rows.into_iter().map(|(name,)| name).collect())
+}
+
+/// Catalog name of the Postgres schema a command was given, resolved among the database's
+/// schemas like a table name; [`PG_DEFAULT_SCHEMA`] when none was.
+pub async fn pg_schema(pool: &PgPool, schema: Option<String>) -> Result<String, String> {
+  let Some(schema) = schema else {
+    return Ok(PG_DEFAULT_SCHEMA.to_string());
+  };
+  let schemas = pg_schemas(pool).await?;
+  ident::resolve(Dialect::Postgres, &schema, &schemas).map(str::to_string)
+}
+
+/// Collects up to `max_rows` rows of `stream`, and whether there were more.
+async fn take_rows<R>(
+  mut stream: impl futures::Stream<Item = Result<R, sqlx::Error>> + Unpin,
+  max_rows: usize,
+) -> Result<(Vec<R>, bool), String> {
+  let mut rows = Vec::new();
+  while let Some(row) = stream.try_next().await.map_err(|e| e.to_string())? {
+    if rows.len() == max_rows {
+      return Ok((rows, true));
+    }
+    rows.push(row);
+  }
+  Ok((rows, false))
+}
+
+#[derive(Clone)]
+pub enum SqlPool {
+  MySql(MySqlPool),
+  Postgres(PgPool),
+  Sqlite(SqlitePool),
+}
+
+/// Looks up the open pool for a SQL connection id (`mysql`, `postgres` and `sqlite` for
+/// connections opened without an explicit id).
+pub fn sql_pool(state: &AppState, connection: &str) -> Result<SqlPool, String> {
+  let connections = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  if let Some(pool) = connections.mysql.get(connection) {
+    reconnect::ensure_up(state, connection)?;
+    return Ok(SqlPool::MySql(pool.clone()));
+  }
+  if let Some(pool) = connections.postgres.get(connection) {
+    reconnect::ensure_up(state, connection)?;
+    return Ok(SqlPool::Postgres(pool.clone()));
+  }
+  if let Some(pool) = connections.sqlite.get(connection) {
+    return Ok(SqlPool::Sqlite(pool.clone()));
+  }
+  match connections.engine_of(connection) {
+    Some(engine) => Err(format!(
+      "Connection '{}' is not a SQL connection ({})",
+      connection, engine
+    )),
+    None => Err("Not connected".to_string()),
+  }
+}
+
+/// String literal of `value` in `dialect`; MySQL also treats backslashes as escapes.
+pub fn quote_literal(dialect: Dialect, value: &str) -> String {
+  let escaped = match dialect {
+    Dialect::MySql => value.replace('\\', "\\\\").replace('\'', "''"),
+    _ => value.replace('\'', "''"),
+  };
+  format!("'{}'", escaped)
+}
+
+impl SqlPool {
+  pub fn dialect(&self) -> Dialect {
+    match self {
+      SqlPool::MySql(_) => Dialect::MySql,
+      SqlPool::Postgres(_) => Dialect::Postgres,
+      SqlPool::Sqlite(_) => Dialect::Sqlite,
+    }
+  }
+
+  /// Identifier for generated SQL, quoted only when the bare name would not reach the same
+  /// object (see [`ident::render`]).
+  pub fn quote_ident(&self, name: &str) -> String {
+    ident::render(self.dialect(), name)
+  }
+
+  /// Catalog name of the table the user means by `table`, e.g. `users` for `Users` on
+  /// Postgres, following the dialect's case rules.
+  pub async fn resolve_table(&self, table: &str) -> Result<String, String> {
+    self.resolve_table_in(PG_DEFAULT_SCHEMA, table).await
+  }
+
+  /// [`SqlPool::resolve_table`] among the tables of a Postgres `schema`; the other engines
+  /// ignore it.
+  pub async fn resolve_table_in(&self, schema: &str, table: &str) -> Result<String, String> {
+    let names: Vec<(String,)> = match self {
+      SqlPool::MySql(pool) => {
+        sqlx::query_as(
+          "SELECT CAST(TABLE_NAME AS CHAR) FROM information_schema.TABLES \
+           WHERE TABLE_SCHEMA = DATABASE()",
+        )
+        .fetch_all(pool)
+        .await
+      }
+      SqlPool::Postgres(pool) => {
+        sqlx::query_as(
+          "SELECT table_name::text FROM information_schema.tables WHERE table_schema = $1",
+        )
+        .bind(schema)
+        .fetch_all(pool)
+        .await
+      }
+      SqlPool::Sqlite(pool) => {
+        sqlx::query_as("SELECT name FROM sqlite_master WHERE type IN ('table', 'view')")
+          .fetch_all(pool)
+          .await
+      }
+    }
+    .map_err(|e| e.to_string())?;
+    let names: Vec<String> = names.into_iter().map(|(name,)| name).collect();
+    ident::resolve(self.dialect(), table, &names).map(str::to_string)
+  }
+
+  /// String literal for SQL that can't take bind parameters (DDL, `CREATE USER`, ...).
+  pub fn quote_literal(&self, value: &str) -> String {
+    quote_literal(self.dialect(), value)
+  }
+
+  /// Table reference as the existing commands address it (Postgres tables live in `public`).
+  pub fn table_ref(&self, table: &str) -> String {
+    self.table_ref_in(PG_DEFAULT_SCHEMA, table)
+  }
+
+  /// [`SqlPool::table_ref`] for a table of a Postgres `schema`; the other engines ignore it.
+  pub fn table_ref_in(&self, schema: &str, table: &str) -> String {
+    match self {
+      SqlPool::Postgres(_) => format!("{}.{}", self.quote_ident(schema), self.quote_ident(table)),
+      _ => self.quote_ident(table),
+    }
+  }
+
+  /// Placeholder for the `n`-th (1-based) bound parameter.
+  pub fn placeholder(&self, n: usize) -> String {
+    match self {
+      SqlPool::Postgres(_) => format!("${}", n),
+      _ => "?".to_string(),
+    }
+  }
+
+  /// Postgres type name (`udt_name`) of a column of a table in `schema`, used to cast text
+  /// binds.
+  pub async fn pg_column_type(
+    &self,
+    schema: &str,
+    table: &str,
+    column: &str,
+  ) -> Result<Option<String>, String> {
+    let SqlPool::Postgres(pool) = self else {
+      return Ok(None);
+    };
+    let row: Option<(String,)> = sqlx::query_as(
+      "SELECT udt_name::text FROM information_schema.columns WHERE table_schema = $3 AND table_name = $1 AND column_name = $2",
+    )
+    .bind(table)
+    .bind(column)
+    .bind(schema)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(row.map(|(t,)| t))
+  }
+
+  /// `column = <param n>` comparing as text, since values arrive from the UI as strings.
+  pub fn text_eq(&self, column: &str, n: usize) -> String {
+    match self {
+      SqlPool::Postgres(_) => format!("{}::text = ${}", self.quote_ident(column), n),
+      _ => format!("{} = ?", self.quote_ident(column)),
+    }
+  }
+
+  /// Runs a SELECT with string binds and returns each row as a JSON object.
+  ///
+  /// Postgres rows go through `row_to_json` so every column type keeps its JSON shape.
+  /// A read that fails because the server dropped its connection is retried once.
+  pub async fn fetch_rows(&self, sql: &str, binds: &[String]) -> Result<Vec<JsonRow>, String> {
+    match self {
+      SqlPool::MySql(pool) => {
+        let rows = session::retry_once(|| {
+          let mut query = sqlx::query(sql);
+          for value in binds {
+            query = query.bind(value);
+          }
+          query.fetch_all(pool)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        Ok(
+          rows
+            .iter()
+            .map(|row| mysql_row_to_json(row, None))
+            .collect(),
+        )
+      }
+      SqlPool::Postgres(pool) => {
+        let wrapped = format!("SELECT row_to_json(t)::text FROM ({}) t", sql);
+        let rows = session::retry_once(|| {
+          let mut query = sqlx::query_as::<_, (String,)>(&wrapped);
+          for value in binds {
+            query = query.bind(value);
+          }
+          query.fetch_all(pool)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        rows
+          .into_iter()
+          .map(|(json,)| serde_json::from_str(&json).map_err(|e| e.to_string()))
+          .collect()
+      }
+      SqlPool::Sqlite(pool) => {
+        let mut query = sqlx::query(sql);
+        for value in binds {
+          query = query.bind(value);
+        }
+        let rows = query.fetch_all(pool).await.map_err(|e| e.to_string())?;
+        Ok(rows.iter().map(sqlite_row_to_json).collect())
+      }
+    }
+  }
+
+  /// Like `fetch_rows`, but decodes every engine natively and also returns the column order,
+  /// which JSON objects don't preserve. Instants are rendered in `tz` when given.
+  pub async fn fetch_with_columns(
+    &self,
+    sql: &str,
+    binds: &[String],
+    tz: Option<&Tz>,
+  ) -> Result<(Vec<ResultColumn>, Vec<JsonRow>), String> {
+    match self {
+      SqlPool::MySql(pool) => {
+        let mut query = sqlx::query(sql);
+        for value in binds {
+          query = query.bind(value);
+        }
+        let rows = query.fetch_all(pool).await.map_err(|e| e.to_string())?;
+        Ok((
+          result_columns(&rows),
+          rows.iter().map(|row| mysql_row_to_json(row, tz)).collect(),
+        ))
+      }
+      SqlPool::Postgres(pool) => {
+        let mut query = sqlx::query(sql);
+        for value in binds {
+          query = query.bind(value);
+        }
+        let rows = query.fetch_all(pool).await.map_err(|e| e.to_string())?;
+        Ok((
+          result_columns(&rows),
+          rows.iter().map(|row| pg_row_to_json(row, tz)).collect(),
+        ))
+      }
+      SqlPool::Sqlite(pool) => {
+        let mut query = sqlx::query(sql);
+        for value in binds {
+          query = query.bind(value);
+        }
+        let rows = query.fetch_all(pool).await.map_err(|e| e.to_string())?;
+        Ok((
+          result_columns(&rows),
+          rows.iter().map(sqlite_row_to_json).collect(),
+        ))
+      }
+    }
+  }
+
+  /// Runs a query in a transaction the server itself keeps from writing (`READ ONLY` on
+  /// MySQL and Postgres, `PRAGMA query_only` on SQLite), so functions with side effects fail
+  /// too. Returns at most `max_rows` rows, and whether more were left out.
+  pub async fn fetch_read_only(
+    &self,
+    sql: &str,
+    tz: Option<&Tz>,
+    max_rows: usize,
+  ) -> Result<(Vec<ResultColumn>, Vec<JsonRow>, bool), String> {
+    match self {
+      SqlPool::MySql(pool) => {
+        let mut tx = pool
+          .begin_with("START TRANSACTION READ ONLY")
+          .await
+          .map_err(|e| e.to_string())?;
+        let (rows, truncated) = take_rows(sqlx::query(sql).fetch(&mut *tx), max_rows).await?;
+        let json = rows.iter().map(|row| mysql_row_to_json(row, tz)).collect();
+        Ok((result_columns(&rows), json, truncated))
+      }
+      SqlPool::Postgres(pool) => {
+        let mut tx = pool
+          .begin_with("BEGIN READ ONLY")
+          .await
+          .map_err(|e| e.to_string())?;
+        let (rows, truncated) = take_rows(sqlx::query(sql).fetch(&mut *tx), max_rows).await?;
+        let json = rows.iter().map(|row| pg_row_to_json(row, tz)).collect();
+        Ok((result_columns(&rows), json, truncated))
+      }
+      SqlPool::Sqlite(pool) => {
+        let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+        sqlx::query("PRAGMA query_only = ON")
+          .execute(&mut *conn)
+          .await
+          .map_err(|e| e.to_string())?;
+        let fetched = take_rows(sqlx::query(sql).fetch(&mut *conn), max_rows).await;
+        // The pragma sticks to the connection, which goes back to the pool
+        if sqlx::query("PRAGMA query_only = OFF")
+          .execute(&mut *conn)
+          .await
+          .is_err()
+        {
+          conn.close_on_drop();
+        }
+        let (rows, truncated) = fetched?;
+        let json = rows.iter().map(sqlite_row_to_json).collect();
+        Ok((result_columns(&rows), json, truncated))
+      }
+    }
+  }
+
+  /// Runs statements in order inside one transaction, rolling back on the first failure.
+  ///
+  /// MySQL commits implicitly around most DDL, so there only DML is truly all-or-nothing.
+  pub async fn execute_in_transaction(&self, statements: &[String]) -> Result<u64, String> {
+    let failed = |i: usize, e: sqlx::Error| format!("Statement {} failed: {}", i + 1, e);
+    let mut affected = 0;
+    match self {
+      SqlPool::MySql(pool) => {
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+        for (i, sql) in statements.iter().enumerate() {
+          let result = sqlx::query(sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| failed(i, e))?;
+          affected += result.rows_affected();
+        }
+        tx.commit().await.map_err(|e| e.to_string())?;
+      }
+      SqlPool::Postgres(pool) => {
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+        for (i, sql) in statements.iter().enumerate() {
+          let result = sqlx::query(sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| failed(i, e))?;
+          affected += result.rows_affected();
+        }
+        tx.commit().await.map_err(|e| e.to_string())?;
+      }
+      SqlPool::Sqlite(pool) => {
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+        for (i, sql) in statements.iter().enumerate() {
+          let result = sqlx::query(sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| failed(i, e))?;
+          affected += result.rows_affected();
+        }
+        tx.commit().await.map_err(|e| e.to_string())?;
+      }
+    }
+    Ok(affected)
+  }
+
+  pub fn engine(&self) -> &'static str {
+    match self {
+      SqlPool::MySql(_) => "mysql",
+      SqlPool::Postgres(_) => "postgres",
+      SqlPool::Sqlite(_) => "sqlite",
+    }
+  }
+
+  pub fn placeholder_style(&self) -> Placeholder {
+    match self {
+      SqlPool::MySql(_) => Placeholder::Question,
+      SqlPool::Postgres(_) => Placeholder::Dollar,
+      SqlPool::Sqlite(_) => Placeholder::SqliteQuestion,
+    }
+  }
+}
+
+pub fn sqlite_row_to_json(row: &SqliteRow) -> JsonRow {
+  let mut map = serde_json::Map::new();
+  for col in row.columns() {
+    let i = col.ordinal();
+    let value = match row.try_get_raw(i) {
+      Ok(raw) if !raw.is_null() => match raw.type_info().name() {
+        "INTEGER" => row.try_get::<i64, _>(i).map(|v| int_json(v.into())),
+        "REAL" => row.try_get::<f64, _>(i).map(serde_json::Value::from),
+        "BOOLEAN" => row.try_get::<bool, _>(i).map(serde_json::Value::Bool),
+        "BLOB" => row
+          .try_get::<Vec<u8>, _>(i)
+          .map(|v| serde_json::Value::String(String::from_utf8_lossy(&v).to_string())),
+        _ => row.try_get::<String, _>(i).map(serde_json::Value::String),
+      }
+      .unwrap_or(serde_json::Value::Null),
+      _ => serde_json::Value::Null,
+    };
+    map.insert(col.name().to_string(), value);
+  }
+  map
+}
+
+/// `tz` is the display zone for `TIMESTAMP` columns; without one they stay in UTC.
+pub fn mysql_row_to_json(row: &MySqlRow, tz: Option<&Tz>) -> JsonRow {
+  let mut map = serde_json::Map::new();
+  for col in row.columns() {
+    let i = col.ordinal();
+    let raw = match row.try_get_raw(i) {
+      Ok(raw) if !raw.is_null() => raw,
+      _ => {
+        map.insert(col.name().to_string(), serde_json::Value::Null);
+        continue;
+      }
+    };
+    let typed = match raw.type_info().name() {
+      "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT" | "BIGINT" => {
+        row.try_get::<i64, _>(i).ok().map(|v| int_json(v.into()))
+      }
+      "TINYINT UNSIGNED" | "SMALLINT UNSIGNED" | "MEDIUMINT UNSIGNED" | "INT UNSIGNED"
+      | "BIGINT UNSIGNED" => row.try_get::<u64, _>(i).ok().map(|v| int_json(v.into())),
+      "FLOAT" | "DOUBLE" => row.try_get::<f64, _>(i).ok().map(serde_json::Value::from),
+      // Exact decimals travel as strings; an f64 would silently round them
+      "DECIMAL" => row
+        .try_get::<bigdecimal::BigDecimal, _>(i)
+        .ok()
+        .map(|v| serde_json::Value::String(v.to_plain_string())),
+      "BOOLEAN" => row.try_get::<bool, _>(i).ok().map(serde_json::Value::Bool),
+      "DATETIME" => row
+        .try_get::<chrono::NaiveDateTime, _>(i)
+        .ok()
+        .map(|v| serde_json::Value::String(v.format(DATETIME_FORMAT).to_string())),
+      "TIMESTAMP" => row
+        .try_get::<chrono::DateTime<chrono::Utc>, _>(i)
+        .ok()
+        .map(|v| {
+          serde_json::Value::String(match tz {
+            Some(tz) => timezone::format_instant(v, *tz),
+            None => v.format(DATETIME_FORMAT).to_string(),
+          })
+        }),
+      "DATE" => row
+        .try_get::<chrono::NaiveDate, _>(i)
+        .ok()
+        .map(|v| serde_json::Value::String(v.to_string())),
+      "TIME" => row
+        .try_get::<chrono::NaiveTime, _>(i)
+        .ok()
+        .map(|v| serde_json::Value::String(v.to_string())),
+      _ => None,
+    };
+    // MySQL may hand back VARBINARY for text; try bytes before String
+    let value = typed
+      .or_else(|| {
+        row
+          .try_get::<Vec<u8>, _>(i)
+          .ok()
+          .map(|bytes| serde_json::Value::String(String::from_utf8_lossy(&bytes).to_string()))
+      })
+      .or_else(|| {
+        row
+          .try_get::<String, _>(i)
+          .ok()
+          .map(serde_json::Value::String)
+      })
+      .unwrap_or(serde_json::Value::Null);
+    map.insert(col.name().to_string(), value);
+  }
+  map
+}
+
+/// `tz` is the display zone for `timestamptz` columns; without one they stay in UTC.
+pub fn pg_row_to_json(row: &PgRow, tz: Option<&Tz>) -> JsonRow {
+  let mut map = serde_json::Map::new();
+  for col in row.columns() {
+    let i = col.ordinal();
+    let raw = match row.try_get_raw(i) {
+      Ok(raw) if !raw.is_null() => raw,
+      _ => {
+        map.insert(col.name().to_string(), serde_json::Value::Null);
+        continue;
+      }
+    };
+    let type_name = raw.type_info().name().to_string();
+    let value = match type_name.as_str() {
+      "INT2" => row.try_get::<i16, _>(i).map(serde_json::Value::from),
+      "INT4" => row.try_get::<i32, _>(i).map(serde_json::Value::from),
+      "INT8" => row.try_get::<i64, _>(i).map(|v| int_json(v.into())),
+      "FLOAT4" => row.try_get::<f32, _>(i).map(serde_json::Value::from),
+      "FLOAT8" => row.try_get::<f64, _>(i).map(serde_json::Value::from),
+      "BOOL" => row.try_get::<bool, _>(i).map(serde_json::Value::Bool),
+      "JSON" | "JSONB" => row.try_get::<serde_json::Value, _>(i),
+      "NUMERIC" => row
+        .try_get::<bigdecimal::BigDecimal, _>(i)
+        .map(|v| serde_json::Value::String(v.to_plain_string())),
+      "TIMESTAMP" => row
+        .try_get::<chrono::NaiveDateTime, _>(i)
+        .map(|v| serde_json::Value::String(v.format(DATETIME_FORMAT).to_string())),
+      "TIMESTAMPTZ" => row.try_get::<chrono::DateTime<chrono::Utc>, _>(i).map(|v| {
+        serde_json::Value::String(match tz {
+          Some(tz) => timezone::format_instant(v, *tz),
+          None => v.to_rfc3339(),
+        })
+      }),
+      "DATE" => row
+        .try_get::<chrono::NaiveDate, _>(i)
+        .map(|v| serde_json::Value::String(v.to_string())),
+      "TIME" => row
+        .try_get::<chrono::NaiveTime, _>(i)
+        .map(|v| serde_json::Value::String(v.to_string())),
+      "BYTEA" => row
+        .try_get::<Vec<u8>, _>(i)
+        .map(|v| serde_json::Value::String(String::from_utf8_lossy(&v).to_string())),
+      _ => row.try_get::<String, _>(i).map(serde_json::Value::String),
+    }
+    .unwrap_or_else(|_| serde_json::Value::String(format!("<{}>", type_name)));
+    map.insert(col.name().to_string(), value);
+  }
+  map
+}
+
+/// Lowercased `DATA_TYPE` of a MySQL column in the current database.
+pub async fn mysql_data_type(
+  pool: &MySqlPool,
+  table: &str,
+  column: &str,
+) -> Result<Option<String>, String> {
+  let row: Option<(String,)> = sqlx::query_as(
+    "SELECT CAST(DATA_TYPE AS CHAR) FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND COLUMN_NAME = ?",
+  )
+  .bind(table)
+  .bind(column)
+  .fetch_optional(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(row.map(|(t,)| t.to_lowercase()))
+}
+
+/// Binds a UI string for a MySQL column of `data_type` without losing precision.
+///
+/// MySQL compares a string against an integer column as doubles, so ids above 2^53 could
+/// match the wrong row; integers are bound as `i64`/`u64` and decimals as exact decimals.
+pub fn mysql_bind<'q>(
+  query: sqlx::query::Query<'q, sqlx::MySql, sqlx::mysql::MySqlArguments>,
+  data_type: Option<&str>,
+  value: String,
+) -> Result<sqlx::query::Query<'q, sqlx::MySql, sqlx::mysql::MySqlArguments>, String> {
+  let text = value.trim();
+  Ok(match data_type {
+    Some("decimal") => {
+      let decimal: bigdecimal::BigDecimal = text
+        .parse()
+        .map_err(|_| format!("Invalid decimal value: {}", value))?;
+      query.bind(decimal)
+    }
+    Some("tinyint" | "smallint" | "mediumint" | "int" | "bigint") => {
+      if let Ok(v) = text.parse::<i64>() {
+        query.bind(v)
+      } else if let Ok(v) = text.parse::<u64>() {
+        query.bind(v)
+      } else {
+        query.bind(value)
+      }
+    }
+    _ => query.bind(value),
+  })
+}
+
+/// New value of a cell from the grid. A bare string is text; `{"type": "null"}` sets SQL
+/// `NULL` (as opposed to the text `"NULL"`) and `{"type": "expression", "value": "now()"}`
+/// writes an SQL expression into the statement as is.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(from = "CellInput")]
+pub enum CellValue {
+  Null,
+  Text(String),
+  Expression(String),
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum CellInput {
+  Text(String),
+  Tagged(TaggedCell),
+}
+
+#[derive(serde::Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+enum TaggedCell {
+  Null,
+  Text(String),
+  Expression(String),
+}
+
+impl From<CellInput> for CellValue {
+  fn from(input: CellInput) -> Self {
+    match input {
+      CellInput::Text(text) | CellInput::Tagged(TaggedCell::Text(text)) => CellValue::Text(text),
+      CellInput::Tagged(TaggedCell::Null) => CellValue::Null,
+      CellInput::Tagged(TaggedCell::Expression(expr)) => CellValue::Expression(expr),
+    }
+  }
+}
+
+impl CellValue {
+  /// Whether the value takes a bind parameter (an expression doesn't).
+  pub fn is_bound(&self) -> bool {
+    !matches!(self, CellValue::Expression(_))
+  }
+
+  /// SQL for the value: `placeholder` when it's bound, the expression otherwise.
+  pub fn sql(&self, placeholder: &str) -> String {
+    match self {
+      CellValue::Expression(expr) => expr.clone(),
+      _ => placeholder.to_string(),
+    }
+  }
+
+  /// What to bind for the placeholder: `None` for `NULL`.
+  pub fn bound(self) -> Option<String> {
+    match self {
+      CellValue::Text(text) => Some(text),
+      _ => None,
+    }
+  }
+
+  /// The value as the audit log records it.
+  pub fn audit(&self) -> serde_json::Value {
+    match self {
+      CellValue::Null => serde_json::Value::Null,
+      CellValue::Text(text) => serde_json::Value::String(text.clone()),
+      CellValue::Expression(expr) => serde_json::json!({ "expression": expr }),
+    }
+  }
+}
@@ -0,0 +1,1086 @@
+//! Table designer backend: turns a structured table definition into the dialect's
+//! `CREATE TABLE` statement, and a list of column changes into the statements that alter an
+//! existing table. The SQL is always returned so the designer can preview it; it is only run
+//! when the caller asks for it.
+//!
+//! Column types, defaults and check expressions are SQL fragments written by the user and go
+//! into the statement as they are; only a `;` outside quotes is refused, so one definition
+//! can't smuggle in a second statement.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+  visit_expressions, visit_expressions_mut, ColumnDef, ColumnOption, ColumnOptionDef, CreateIndex,
+  CreateTable, Expr, Ident, ObjectName, Statement, TableConstraint,
+};
+use sqlparser::dialect::SQLiteDialect;
+use sqlparser::parser::Parser;
+use sqlx::SqlitePool;
+use tauri::State;
+
+use crate::db::SqlPool;
+use crate::ident::{self, Dialect};
+use crate::{audit, connections, readonly, schema_cache, AppState};
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnSpec {
+  pub name: String,
+  /// Type as the engine spells it, e.g. `varchar(255)`, `numeric(12, 2)` or `jsonb`.
+  pub data_type: String,
+  /// Allows NULL unless set to false.
+  #[serde(default)]
+  pub nullable: Option<bool>,
+  /// Default expression, e.g. `0`, `'draft'` or `CURRENT_TIMESTAMP`.
+  #[serde(default)]
+  pub default: Option<String>,
+  /// Numbered by the database: `AUTO_INCREMENT`, an identity column or SQLite's
+  /// `AUTOINCREMENT`.
+  #[serde(default)]
+  pub auto_increment: bool,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UniqueSpec {
+  #[serde(default)]
+  pub name: Option<String>,
+  pub columns: Vec<String>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeignKeySpec {
+  #[serde(default)]
+  pub name: Option<String>,
+  pub columns: Vec<String>,
+  pub referenced_table: String,
+  /// Same order as `columns`.
+  pub referenced_columns: Vec<String>,
+  /// `NO ACTION` (default), `RESTRICT`, `CASCADE`, `SET NULL` or `SET DEFAULT`.
+  #[serde(default)]
+  pub on_update: Option<String>,
+  #[serde(default)]
+  pub on_delete: Option<String>,
+}
+
+/// A table as the designer describes it.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TableSpec {
+  pub name: String,
+  pub columns: Vec<ColumnSpec>,
+  #[serde(default)]
+  pub primary_key: Vec<String>,
+  #[serde(default)]
+  pub unique: Vec<UniqueSpec>,
+  #[serde(default)]
+  pub foreign_keys: Vec<ForeignKeySpec>,
+  /// `CHECK` expressions.
+  #[serde(default)]
+  pub checks: Vec<String>,
+  #[serde(default)]
+  pub if_not_exists: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTableResult {
+  pub sql: String,
+  pub executed: bool,
+}
+
+/// One change to an existing table; `*_alter_table` applies them in list order.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ColumnChange {
+  Add {
+    column: ColumnSpec,
+  },
+  Drop {
+    name: String,
+  },
+  Rename {
+    name: String,
+    new_name: String,
+  },
+  /// New type and, when given, nullability and default (`NULL` removes the default). MySQL
+  /// restates the whole column, so there whatever is left out is reset.
+  Retype {
+    name: String,
+    data_type: String,
+    #[serde(default)]
+    nullable: Option<bool>,
+    #[serde(default)]
+    default: Option<String>,
+  },
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlterTableResult {
+  pub statements: Vec<String>,
+  /// SQLite copies the table into a new one for changes its `ALTER TABLE` can't make.
+  pub rebuild: bool,
+  pub executed: bool,
+}
+
+const ACTIONS: &[&str] = &[
+  "NO ACTION",
+  "RESTRICT",
+  "CASCADE",
+  "SET NULL",
+  "SET DEFAULT",
+];
+
+/// Fails when `fragment` holds a `;` outside a quoted string or identifier.
+fn check_fragment(what: &str, fragment: &str) -> Result<(), String> {
+  let mut quote = None;
+  for c in fragment.chars() {
+    match (quote, c) {
+      (None, '\'' | '"' | '`') => quote = Some(c),
+      (Some(open), _) if c == open => quote = None,
+      (None, ';') => return Err(format!("{} can't contain ';': {}", what, fragment)),
+      _ => {}
+    }
+  }
+  if fragment.trim().is_empty() {
+    return Err(format!("{} is empty", what));
+  }
+  Ok(())
+}
+
+fn name(dialect: Dialect, name: &str) -> Result<String, String> {
+  ident::validate(dialect, name)?;
+  Ok(ident::render(dialect, name))
+}
+
+fn name_list(dialect: Dialect, names: &[String]) -> Result<String, String> {
+  let names: Vec<String> = names
+    .iter()
+    .map(|n| name(dialect, n))
+    .collect::<Result<_, _>>()?;
+  Ok(names.join(", "))
+}
+
+fn action(rule: Option<&str>) -> Result<Option<String>, String> {
+  let Some(rule) = rule else {
+    return Ok(None);
+  };
+  let rule = rule.split_whitespace().collect::<Vec<_>>().join(" ");
+  ACTIONS
+    .iter()
+    .find(|action| action.eq_ignore_ascii_case(&rule))
+    .map(|action| Some(action.to_string()))
+    .ok_or_else(|| format!("Unknown referential action: {}", rule))
+}
+
+/// Fails unless every name in `names` is a column of the table.
+fn check_columns(what: &str, names: &[String], columns: &HashSet<&str>) -> Result<(), String> {
+  if names.is_empty() {
+    return Err(format!("{} lists no columns", what));
+  }
+  match names.iter().find(|n| !columns.contains(n.as_str())) {
+    Some(missing) => Err(format!("{} names an unknown column: {}", what, missing)),
+    None => Ok(()),
+  }
+}
+
+/// `column` as it is declared in `CREATE TABLE` or `ADD COLUMN`.
+fn column_definition(dialect: Dialect, column: &ColumnSpec) -> Result<String, String> {
+  check_fragment(&format!("The type of {}", column.name), &column.data_type)?;
+  let mut line = format!(
+    "{} {}",
+    name(dialect, &column.name)?,
+    column.data_type.trim()
+  );
+  if column.auto_increment {
+    line.push_str(match dialect {
+      Dialect::MySql => " NOT NULL AUTO_INCREMENT",
+      Dialect::Postgres => " GENERATED BY DEFAULT AS IDENTITY",
+      Dialect::Sqlite => " PRIMARY KEY AUTOINCREMENT",
+    });
+  } else if column.nullable == Some(false) {
+    line.push_str(" NOT NULL");
+  }
+  if let Some(default) = &column.default {
+    if column.auto_increment {
+      return Err(format!(
+        "The auto-increment column {} can't have a default",
+        column.name
+      ));
+    }
+    check_fragment(&format!("The default of {}", column.name), default)?;
+    let _ = write!(line, " DEFAULT {}", default.trim());
+  }
+  Ok(line)
+}
+
+/// The `CREATE TABLE` statement for `spec` in `dialect`, with Postgres tables created in
+/// `public` like the rest of the app reads them.
+pub fn create_table_sql(dialect: Dialect, spec: &TableSpec) -> Result<String, String> {
+  if spec.columns.is_empty() {
+    return Err("A table needs at least one column".to_string());
+  }
+  let mut columns = HashSet::new();
+  for column in &spec.columns {
+    if !columns.insert(column.name.as_str()) {
+      return Err(format!("Column {} is defined twice", column.name));
+    }
+  }
+  if !spec.primary_key.is_empty() {
+    check_columns("The primary key", &spec.primary_key, &columns)?;
+  }
+
+  // SQLite only numbers a lone INTEGER PRIMARY KEY, declared on the column itself
+  let numbered: Vec<&ColumnSpec> = spec.columns.iter().filter(|c| c.auto_increment).collect();
+  let inline_key = match numbered.as_slice() {
+    [] => false,
+    _ if dialect != Dialect::Sqlite => false,
+    [column] => {
+      if spec.primary_key != [column.name.clone()]
+        || !column.data_type.trim().eq_ignore_ascii_case("integer")
+      {
+        return Err(
+          "SQLite only auto-increments a column of type INTEGER that is the whole primary key"
+            .to_string(),
+        );
+      }
+      true
+    }
+    _ => return Err("SQLite allows only one auto-increment column".to_string()),
+  };
+  if dialect == Dialect::MySql {
+    if let Some(column) = spec
+      .columns
+      .iter()
+      .find(|c| c.auto_increment && !spec.primary_key.contains(&c.name))
+    {
+      return Err(format!(
+        "MySQL needs the auto-increment column {} in the primary key",
+        column.name
+      ));
+    }
+  }
+
+  let mut lines = Vec::new();
+  for column in &spec.columns {
+    lines.push(format!("  {}", column_definition(dialect, column)?));
+  }
+  if !spec.primary_key.is_empty() && !inline_key {
+    lines.push(format!(
+      "  PRIMARY KEY ({})",
+      name_list(dialect, &spec.primary_key)?
+    ));
+  }
+  for unique in &spec.unique {
+    check_columns("A unique constraint", &unique.columns, &columns)?;
+    let constraint = match &unique.name {
+      Some(constraint) => format!("CONSTRAINT {} ", name(dialect, constraint)?),
+      None => String::new(),
+    };
+    lines.push(format!(
+      "  {}UNIQUE ({})",
+      constraint,
+      name_list(dialect, &unique.columns)?
+    ));
+  }
+  for key in &spec.foreign_keys {
+    check_columns("A foreign key", &key.columns, &columns)?;
+    if key.referenced_columns.len() != key.columns.len() {
+      return Err(format!(
+        "The foreign key on {} references {} column(s) for {}",
+        key.columns.join(", "),
+        key.referenced_columns.len(),
+        key.columns.len()
+      ));
+    }
+    let constraint = match &key.name {
+      Some(constraint) => format!("CONSTRAINT {} ", name(dialect, constraint)?),
+      None => String::new(),
+    };
+    let mut line = format!(
+      "  {}FOREIGN KEY ({}) REFERENCES {} ({})",
+      constraint,
+      name_list(dialect, &key.columns)?,
+      name(dialect, &key.referenced_table)?,
+      name_list(dialect, &key.referenced_columns)?
+    );
+    if let Some(rule) = action(key.on_update.as_deref())? {
+      let _ = write!(line, " ON UPDATE {}", rule);
+    }
+    if let Some(rule) = action(key.on_delete.as_deref())? {
+      let _ = write!(line, " ON DELETE {}", rule);
+    }
+    lines.push(line);
+  }
+  for check in &spec.checks {
+    check_fragment("A check constraint", check)?;
+    lines.push(format!("  CHECK ({})", check.trim()));
+  }
+
+  let table = name(dialect, &spec.name)?;
+  Ok(format!(
+    "CREATE TABLE {}{}{} (\n{}\n)",
+    if spec.if_not_exists {
+      "IF NOT EXISTS "
+    } else {
+      ""
+    },
+    if dialect == Dialect::Postgres {
+      "public."
+    } else {
+      ""
+    },
+    table,
+    lines.join(",\n")
+  ))
+}
+
+/// Builds the statement for `spec` and runs it when `execute` is set.
+async fn create_table(
+  state: &AppState,
+  pool: SqlPool,
+  connection: &str,
+  spec: TableSpec,
+  execute: Option<bool>,
+) -> Result<CreateTableResult, String> {
+  let sql = create_table_sql(pool.dialect(), &spec)?;
+  if !execute.unwrap_or(false) {
+    return Ok(CreateTableResult {
+      sql,
+      executed: false,
+    });
+  }
+  readonly::ensure_writable(state, connection)?;
+  pool
+    .execute_in_transaction(std::slice::from_ref(&sql))
+    .await?;
+  audit::record(state, connection, &sql, serde_json::json!([]), None).await;
+  Ok(CreateTableResult {
+    sql,
+    executed: true,
+  })
+}
+
+/// `CREATE TABLE` for `spec` in MySQL, run only when `execute` is set.
+#[tauri::command]
+pub async fn mysql_create_table(
+  state: State<'_, AppState>,
+  spec: TableSpec,
+  execute: Option<bool>,
+  connection_id: Option<String>,
+) -> Result<CreateTableResult, String> {
+  let pool = SqlPool::MySql(connections::mysql(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("mysql");
+  create_table(&state, pool, connection, spec, execute).await
+}
+
+/// `CREATE TABLE` for `spec` in PostgreSQL, run only when `execute` is set.
+#[tauri::command]
+pub async fn postgres_create_table(
+  state: State<'_, AppState>,
+  spec: TableSpec,
+  execute: Option<bool>,
+  connection_id: Option<String>,
+) -> Result<CreateTableResult, String> {
+  let pool = SqlPool::Postgres(connections::postgres(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("postgres");
+  create_table(&state, pool, connection, spec, execute).await
+}
+
+/// `CREATE TABLE` for `spec` in SQLite, run only when `execute` is set.
+#[tauri::command]
+pub async fn sqlite_create_table(
+  state: State<'_, AppState>,
+  spec: TableSpec,
+  execute: Option<bool>,
+  connection_id: Option<String>,
+) -> Result<CreateTableResult, String> {
+  let pool = SqlPool::Sqlite(connections::sqlite(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("sqlite");
+  create_table(&state, pool, connection, spec, execute).await
+}
+
+/// Whether `default` asks for the default to be removed.
+fn no_default(default: &str) -> bool {
+  default.trim().eq_ignore_ascii_case("null")
+}
+
+/// One or more `ALTER TABLE` statements per change, for MySQL and PostgreSQL and the changes
+/// SQLite makes in place. `table` is quoted already.
+fn alter_statements(
+  dialect: Dialect,
+  table: &str,
+  changes: &[ColumnChange],
+) -> Result<Vec<String>, String> {
+  let alter = format!("ALTER TABLE {}", table);
+  let mut statements = Vec::new();
+  for change in changes {
+    match change {
+      ColumnChange::Add { column } => {
+        if dialect == Dialect::Sqlite && column.auto_increment {
+          return Err("SQLite can't add an auto-increment column to a table".to_string());
+        }
+        statements.push(format!(
+          "{} ADD COLUMN {}",
+          alter,
+          column_definition(dialect, column)?
+        ));
+      }
+      ColumnChange::Drop { name: column } => {
+        statements.push(format!("{} DROP COLUMN {}", alter, name(dialect, column)?));
+      }
+      ColumnChange::Rename {
+        name: column,
+        new_name,
+      } => statements.push(format!(
+        "{} RENAME COLUMN {} TO {}",
+        alter,
+        name(dialect, column)?,
+        name(dialect, new_name)?
+      )),
+      ColumnChange::Retype {
+        name: column,
+        data_type,
+        nullable,
+        default,
+      } => {
+        check_fragment(&format!("The type of {}", column), data_type)?;
+        if let Some(default) = default {
+          check_fragment(&format!("The default of {}", column), default)?;
+        }
+        let column = name(dialect, column)?;
+        let data_type = data_type.trim();
+        match dialect {
+          Dialect::MySql => {
+            let mut line = format!("{} MODIFY COLUMN {} {}", alter, column, data_type);
+            if *nullable == Some(false) {
+              line.push_str(" NOT NULL");
+            }
+            if let Some(default) = default.as_deref().filter(|d| !no_default(d)) {
+              let _ = write!(line, " DEFAULT {}", default.trim());
+            }
+            statements.push(line);
+          }
+          Dialect::Postgres => {
+            statements.push(format!(
+              "{} ALTER COLUMN {} TYPE {} USING {}::{}",
+              alter, column, data_type, column, data_type
+            ));
+            match nullable {
+              Some(true) => {
+                statements.push(format!("{} ALTER COLUMN {} DROP NOT NULL", alter, column));
+              }
+              Some(false) => {
+                statements.push(format!("{} ALTER COLUMN {} SET NOT NULL", alter, column));
+              }
+              None => {}
+            }
+            match default {
+              Some(default) if no_default(default) => {
+                statements.push(format!("{} ALTER COLUMN {} DROP DEFAULT", alter, column));
+              }
+              Some(default) => statements.push(format!(
+                "{} ALTER COLUMN {} SET DEFAULT {}",
+                alter,
+                column,
+                default.trim()
+              )),
+              None => {}
+            }
+          }
+          Dialect::Sqlite => {
+            return Err("SQLite can't change a column's type in place".to_string())
+          }
+        }
+      }
+    }
+  }
+  Ok(statements)
+}
+
+/// Whether SQLite's own `ALTER TABLE` can make all of `changes`. Dropping goes through the
+/// rebuild too: `DROP COLUMN` refuses indexed, key and constrained columns.
+fn sqlite_in_place(changes: &[ColumnChange]) -> bool {
+  changes.iter().all(|change| {
+    matches!(
+      change,
+      ColumnChange::Add { .. } | ColumnChange::Rename { .. }
+    )
+  })
+}
+
+static SQLITE: SQLiteDialect = SQLiteDialect {};
+
+pub(crate) fn sqlite_parser(what: &str, sql: &str) -> Result<Parser<'static>, String> {
+  Parser::new(&SQLITE)
+    .try_with_sql(sql)
+    .map_err(|e| format!("Cannot parse {}: {}", what, e))
+}
+
+/// `name` as an identifier, quoted when it has to be.
+pub(crate) fn sqlite_ident(name: &str) -> Ident {
+  if ident::render(Dialect::Sqlite, name) == name {
+    Ident::new(name)
+  } else {
+    Ident::with_quote('"', name)
+  }
+}
+
+/// Renames the column identifiers `from` to `to` in every expression of `node`.
+fn rename_in_expressions<V: sqlparser::ast::VisitMut>(node: &mut V, from: &str, to: &str) {
+  let _ = visit_expressions_mut(node, |expr| {
+    if let Expr::Identifier(column) = expr {
+      if column.value.eq_ignore_ascii_case(from) {
+        *column = sqlite_ident(to);
+      }
+    }
+    ControlFlow::<()>::Continue(())
+  });
+}
+
+/// Whether an expression of `node` reads the column `column`.
+fn mentions_column<V: sqlparser::ast::Visit>(node: &V, column: &str) -> bool {
+  visit_expressions(node, |expr| match expr {
+    Expr::Identifier(ident) if ident.value.eq_ignore_ascii_case(column) => ControlFlow::Break(()),
+    _ => ControlFlow::Continue(()),
+  })
+  .is_break()
+}
+
+/// Whether `text` has `word` in it as a whole word, ignoring case.
+fn mentions_word(text: &str, word: &str) -> bool {
+  let word = word.to_lowercase();
+  text
+    .to_lowercase()
+    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+    .any(|token| token == word)
+}
+
+/// The column lists of a table constraint naming columns of `table`: its own, and the
+/// referenced ones of a foreign key to the table itself.
+fn constraint_columns<'a>(
+  constraint: &'a mut TableConstraint,
+  table: &str,
+) -> Vec<&'a mut Vec<Ident>> {
+  match constraint {
+    TableConstraint::Unique { columns, .. } | TableConstraint::PrimaryKey { columns, .. } => {
+      vec![columns]
+    }
+    TableConstraint::ForeignKey {
+      columns,
+      foreign_table,
+      referred_columns,
+      ..
+    } => {
+      let to_itself = foreign_table
+        .0
+        .last()
+        .is_some_and(|t| t.value.eq_ignore_ascii_case(table));
+      if to_itself {
+        vec![columns, referred_columns]
+      } else {
+        vec![columns]
+      }
+    }
+    _ => Vec::new(),
+  }
+}
+
+fn position(create: &CreateTable, column: &str) -> Result<usize, String> {
+  create
+    .columns
+    .iter()
+    .position(|c| c.name.value.eq_ignore_ascii_case(column))
+    .ok_or_else(|| format!("Unknown column: {}", column))
+}
+
+/// Applies one change to the parsed `CREATE TABLE` of `table`. `origins` follows the columns
+/// and holds the name each had in the old table, `None` for added ones.
+fn apply_change(
+  create: &mut CreateTable,
+  origins: &mut Vec<Option<String>>,
+  table: &str,
+  change: &ColumnChange,
+) -> Result<(), String> {
+  match change {
+    ColumnChange::Add { column } => {
+      if column.auto_increment {
+        return Err("SQLite can't add an auto-increment column to a table".to_string());
+      }
+      let definition = column_definition(Dialect::Sqlite, column)?;
+      let parsed: ColumnDef = sqlite_parser("the new column", &definition)?
+        .parse_column_def()
+        .map_err(|e| format!("Cannot parse the new column: {}", e))?;
+      if position(create, &column.name).is_ok() {
+        return Err(format!("Column {} already exists", column.name));
+      }
+      create.columns.push(parsed);
+      origins.push(None);
+    }
+    ColumnChange::Drop { name: column } => {
+      let i = position(create, column)?;
+      if create.columns.len() == 1 {
+        return Err("A table needs at least one column".to_string());
+      }
+      create.columns.remove(i);
+      origins.remove(i);
+      let constrained = create.constraints.iter_mut().any(|constraint| {
+        constraint_columns(constraint, table)
+          .iter()
+          .any(|columns| columns.iter().any(|c| c.value.eq_ignore_ascii_case(column)))
+      });
+      if constrained || mentions_column(create, column) {
+        return Err(format!(
+          "Column {} is used by a constraint or generated column; change that first",
+          column
+        ));
+      }
+    }
+    ColumnChange::Rename {
+      name: column,
+      new_name,
+    } => {
+      ident::validate(Dialect::Sqlite, new_name)?;
+      let i = position(create, column)?;
+      if position(create, new_name).is_ok_and(|other| other != i) {
+        return Err(format!("Column {} already exists", new_name));
+      }
+      create.columns[i].name = sqlite_ident(new_name);
+      for constraint in &mut create.constraints {
+        for columns in constraint_columns(constraint, table) {
+          for c in columns.iter_mut() {
+            if c.value.eq_ignore_ascii_case(column) {
+              *c = sqlite_ident(new_name);
+            }
+          }
+        }
+      }
+      rename_in_expressions(create, column, new_name);
+    }
+    ColumnChange::Retype {
+      name: column,
+      data_type,
+      nullable,
+      default,
+    } => {
+      check_fragment(&format!("The type of {}", column), data_type)?;
+      let data_type = sqlite_parser("the type", data_type)?
+        .parse_data_type()
+        .map_err(|e| format!("Cannot parse the type {}: {}", data_type, e))?;
+      let default = match default {
+        Some(default) if no_default(default) => Some(None),
+        Some(default) => {
+          check_fragment(&format!("The default of {}", column), default)?;
+          let expr = sqlite_parser("the default", default)?
+            .parse_expr()
+            .map_err(|e| format!("Cannot parse the default {}: {}", default, e))?;
+          Some(Some(expr))
+        }
+        None => None,
+      };
+      let i = position(create, column)?;
+      let definition = &mut create.columns[i];
+      definition.data_type = data_type;
+      if let Some(nullable) = nullable {
+        definition
+          .options
+          .retain(|o| !matches!(o.option, ColumnOption::Null | ColumnOption::NotNull));
+        if !nullable {
+          definition.options.push(ColumnOptionDef {
+            name: None,
+            option: ColumnOption::NotNull,
+          });
+        }
+      }
+      if let Some(default) = default {
+        definition
+          .options
+          .retain(|o| !matches!(o.option, ColumnOption::Default(_)));
+        if let Some(expr) = default {
+          definition.options.push(ColumnOptionDef {
+            name: None,
+            option: ColumnOption::Default(expr),
+          });
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Replays the renames and drops of `changes` on an index of the table; `None` when the index
+/// covers a dropped column and goes with it.
+fn replay_on_index(mut index: CreateIndex, changes: &[ColumnChange]) -> Option<CreateIndex> {
+  for change in changes {
+    match change {
+      ColumnChange::Rename { name, new_name } => rename_in_expressions(&mut index, name, new_name),
+      ColumnChange::Drop { name } if mentions_column(&index, name) => return None,
+      _ => {}
+    }
+  }
+  Some(index)
+}
+
+/// SQLite's table rebuild for changes `ALTER TABLE` can't make: create the altered table
+/// under a new name, copy the rows, drop the old table, rename the new one into its place and
+/// recreate its indexes and triggers, with foreign key enforcement off meanwhile and checked
+/// before committing.
+async fn sqlite_rebuild(
+  pool: &SqlitePool,
+  table: &str,
+  changes: &[ColumnChange],
+) -> Result<Vec<String>, String> {
+  let objects: Vec<(String, String, String, Option<String>)> =
+    sqlx::query_as("SELECT type, name, tbl_name, sql FROM sqlite_master")
+      .fetch_all(pool)
+      .await
+      .map_err(|e| e.to_string())?;
+  let sql = objects
+    .iter()
+    .find(|(kind, name, _, _)| kind == "table" && name == table)
+    .and_then(|(_, _, _, sql)| sql.clone())
+    .ok_or_else(|| format!("Table not found: {}", table))?;
+  let Statement::CreateTable(mut create) = sqlite_parser("the table", &sql)?
+    .parse_statement()
+    .map_err(|e| format!("Cannot parse the table definition: {}", e))?
+  else {
+    return Err(format!("{} is not a plain table", table));
+  };
+
+  let mut origins: Vec<Option<String>> = create
+    .columns
+    .iter()
+    .map(|c| Some(c.name.value.clone()))
+    .collect();
+  for change in changes {
+    apply_change(&mut create, &mut origins, table, change)?;
+  }
+
+  let taken: HashSet<String> = objects
+    .iter()
+    .map(|(_, name, _, _)| name.to_lowercase())
+    .collect();
+  // One of the first `taken.len() + 1` names is free
+  let temporary = (1..=taken.len() + 1)
+    .map(|n| match n {
+      1 => format!("{}_new", table),
+      n => format!("{}_new{}", table, n),
+    })
+    .find(|name| !taken.contains(&name.to_lowercase()))
+    .ok_or_else(|| format!("No free name to rebuild {} under", table))?;
+  create.name = ObjectName(vec![sqlite_ident(&temporary)]);
+  create.if_not_exists = false;
+
+  // Generated columns are computed again, added ones start at their default
+  let (targets, sources): (Vec<String>, Vec<String>) = create
+    .columns
+    .iter()
+    .zip(&origins)
+    .filter(|(column, _)| {
+      !column
+        .options
+        .iter()
+        .any(|o| matches!(o.option, ColumnOption::Generated { .. }))
+    })
+    .filter_map(|(column, origin)| {
+      let origin = origin.as_deref()?;
+      Some((
+        ident::render(Dialect::Sqlite, &column.name.value),
+        ident::render(Dialect::Sqlite, origin),
+      ))
+    })
+    .unzip();
+
+  let old = ident::render(Dialect::Sqlite, table);
+  let new = ident::render(Dialect::Sqlite, &temporary);
+  let mut statements = vec![
+    "PRAGMA foreign_keys = OFF".to_string(),
+    "PRAGMA legacy_alter_table = ON".to_string(),
+    "BEGIN".to_string(),
+    Statement::CreateTable(create).to_string(),
+    format!(
+      "INSERT INTO {} ({}) SELECT {} FROM {}",
+      new,
+      targets.join(", "),
+      sources.join(", "),
+      old
+    ),
+    format!("DROP TABLE {}", old),
+    format!("ALTER TABLE {} RENAME TO {}", new, old),
+  ];
+  for (kind, name, owner, sql) in &objects {
+    let Some(sql) = sql.as_deref().filter(|_| owner == table) else {
+      continue;
+    };
+    match kind.as_str() {
+      "index" => {
+        let Statement::CreateIndex(index) = sqlite_parser("an index", sql)?
+          .parse_statement()
+          .map_err(|e| format!("Cannot parse index {}: {}", name, e))?
+        else {
+          continue;
+        };
+        if let Some(index) = replay_on_index(index, changes) {
+          statements.push(Statement::CreateIndex(index).to_string());
+        }
+      }
+      "trigger" => {
+        // Triggers are recreated as written; ones reading a changed column must be redone
+        let affected = changes.iter().find_map(|change| match change {
+          ColumnChange::Rename { name, .. } | ColumnChange::Drop { name } => {
+            mentions_word(sql, name).then_some(name)
+          }
+          _ => None,
+        });
+        if let Some(column) = affected {
+          return Err(format!(
+            "Trigger {} uses column {}; drop it before altering the table",
+            name, column
+          ));
+        }
+        statements.push(sql.to_string());
+      }
+      _ => {}
+    }
+  }
+  statements.extend([
+    "PRAGMA foreign_key_check".to_string(),
+    "COMMIT".to_string(),
+    "PRAGMA legacy_alter_table = OFF".to_string(),
+    "PRAGMA foreign_keys = ON".to_string(),
+  ]);
+  Ok(statements)
+}
+
+/// Runs a rebuild from [`sqlite_rebuild`] on one connection, rolling back when a statement
+/// fails or `foreign_key_check` reports broken references.
+async fn run_rebuild(pool: &SqlitePool, statements: &[String]) -> Result<(), String> {
+  let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+  let mut outcome = Ok(());
+  for (i, sql) in statements.iter().enumerate() {
+    let failed = |e: sqlx::Error| format!("Statement {} failed: {}", i + 1, e);
+    if sql.starts_with("PRAGMA foreign_key_check") {
+      match sqlx::query(sql).fetch_all(&mut *conn).await {
+        Ok(rows) if rows.is_empty() => {}
+        Ok(rows) => {
+          outcome = Err(format!(
+            "The altered table breaks {} foreign key reference(s)",
+            rows.len()
+          ));
+          break;
+        }
+        Err(e) => {
+          outcome = Err(failed(e));
+          break;
+        }
+      }
+    } else if let Err(e) = sqlx::query(sql).execute(&mut *conn).await {
+      outcome = Err(failed(e));
+      break;
+    }
+  }
+  if outcome.is_err() {
+    // The connection goes back to the pool, so put its settings back too
+    for sql in [
+      "ROLLBACK",
+      "PRAGMA legacy_alter_table = OFF",
+      "PRAGMA foreign_keys = ON",
+    ] {
+      let _ = sqlx::query(sql).execute(&mut *conn).await;
+    }
+  }
+  outcome
+}
+
+/// Builds the statements for `changes` to `table_name` and runs them when `execute` is set.
+async fn alter_table(
+  state: &AppState,
+  pool: SqlPool,
+  connection: &str,
+  table_name: String,
+  changes: Vec<ColumnChange>,
+  execute: Option<bool>,
+) -> Result<AlterTableResult, String> {
+  if changes.is_empty() {
+    return Err("No changes to make".to_string());
+  }
+  let table = pool.resolve_table(&table_name).await?;
+  let (statements, rebuild) = match &pool {
+    SqlPool::Sqlite(sqlite) if !sqlite_in_place(&changes) => {
+      (sqlite_rebuild(sqlite, &table, &changes).await?, true)
+    }
+    _ => (
+      alter_statements(pool.dialect(), &pool.table_ref(&table), &changes)?,
+      false,
+    ),
+  };
+  if !execute.unwrap_or(false) {
+    return Ok(AlterTableResult {
+      statements,
+      rebuild,
+      executed: false,
+    });
+  }
+  readonly::ensure_writable(state, connection)?;
+  match &pool {
+    SqlPool::Sqlite(sqlite) if rebuild => run_rebuild(sqlite, &statements).await?,
+    _ => {
+      pool.execute_in_transaction(&statements).await?;
+    }
+  }
+  let sql = statements.join(";\n");
+  audit::record(state, connection, &sql, serde_json::json!([]), None).await;
+  schema_cache::invalidate(state, connection, None);
+  Ok(AlterTableResult {
+    statements,
+    rebuild,
+    executed: true,
+  })
+}
+
+/// `ALTER TABLE` statements for `changes` to a MySQL table, run only when `execute` is set.
+/// MySQL commits each one as it runs.
+#[tauri::command]
+pub async fn mysql_alter_table(
+  state: State<'_, AppState>,
+  table_name: String,
+  changes: Vec<ColumnChange>,
+  execute: Option<bool>,
+  connection_id: Option<String>,
+) -> Result<AlterTableResult, String> {
+  let pool = SqlPool::MySql(connections::mysql(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("mysql");
+  alter_table(&state, pool, connection, table_name, changes, execute).await
+}
+
+/// `ALTER TABLE` statements for `changes` to a PostgreSQL table, run in one transaction only
+/// when `execute` is set.
+#[tauri::command]
+pub async fn postgres_alter_table(
+  state: State<'_, AppState>,
+  table_name: String,
+  changes: Vec<ColumnChange>,
+  execute: Option<bool>,
+  connection_id: Option<String>,
+) -> Result<AlterTableResult, String> {
+  let pool = SqlPool::Postgres(connections::postgres(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("postgres");
+  alter_table(&state, pool, connection, table_name, changes, execute).await
+}
+
+/// The statements for `changes` to a SQLite table, run only when `execute` is set: `ALTER
+/// TABLE` when SQLite can make them in place, the table rebuild otherwise.
+#[tauri::command]
+pub async fn sqlite_alter_table(
+  state: State<'_, AppState>,
+  table_name: String,
+  changes: Vec<ColumnChange>,
+  execute: Option<bool>,
+  connection_id: Option<String>,
+) -> Result<AlterTableResult, String> {
+  let pool = SqlPool::Sqlite(connections::sqlite(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("sqlite");
+  alter_table(&state, pool, connection, table_name, changes, execute).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn column(name: &str, data_type: &str, default: Option<&str>) -> ColumnSpec {
+    ColumnSpec {
+      name: name.to_string(),
+      data_type: data_type.to_string(),
+      nullable: None,
+      default: default.map(str::to_string),
+      auto_increment: false,
+    }
+  }
+
+  #[tokio::test]
+  async fn sqlite_rebuild_keeps_rows_indexes_and_foreign_keys() {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+      .max_connections(1)
+      .connect("sqlite::memory:")
+      .await
+      .unwrap();
+    sqlx::raw_sql(
+      "PRAGMA foreign_keys = ON; \
+       CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT); \
+       CREATE TABLE books (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES authors (id), \
+         title TEXT NOT NULL, note TEXT); \
+       CREATE INDEX books_title ON books (title); \
+       CREATE TABLE reviews (id INTEGER PRIMARY KEY, book_id INTEGER REFERENCES books (id)); \
+       INSERT INTO authors VALUES (1, 'Le Guin'), (2, 'Lem'); \
+       INSERT INTO books VALUES (1, 1, 'The Dispossessed', 'x'), (2, 2, 'Solaris', NULL); \
+       INSERT INTO reviews VALUES (1, 2);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let changes = vec![
+      ColumnChange::Add {
+        column: column("pages", "INTEGER", Some("0")),
+      },
+      ColumnChange::Drop {
+        name: "note".to_string(),
+      },
+    ];
+    let result = alter_table(
+      &AppState::new(),
+      SqlPool::Sqlite(pool.clone()),
+      "sqlite",
+      "books".to_string(),
+      changes,
+      Some(true),
+    )
+    .await
+    .unwrap();
+    assert!(result.rebuild && result.executed);
+
+    let rows: Vec<(i64, i64, String, i64)> =
+      sqlx::query_as("SELECT id, author_id, title, pages FROM books ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+      rows,
+      vec![
+        (1, 1, "The Dispossessed".to_string(), 0),
+        (2, 2, "Solaris".to_string(), 0),
+      ]
+    );
+    let columns: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info('books')")
+      .fetch_all(&pool)
+      .await
+      .unwrap();
+    assert!(!columns.iter().any(|(name,)| name == "note"));
+    let indexes: Vec<(String,)> =
+      sqlx::query_as("SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'books'")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(indexes, vec![("books_title".to_string(),)]);
+    let broken = sqlx::query("PRAGMA foreign_key_check")
+      .fetch_all(&pool)
+      .await
+      .unwrap();
+    assert!(broken.is_empty());
+    // Foreign keys are enforced again, on the rebuilt table and on the one referencing it
+    assert!(
+      sqlx::query("INSERT INTO books (id, author_id, title) VALUES (3, 9, 'x')")
+        .execute(&pool)
+        .await
+        .is_err()
+    );
+    assert!(sqlx::query("INSERT INTO reviews VALUES (2, 9)")
+      .execute(&pool)
+      .await
+      .is_err());
+  }
+}
@@ -0,0 +1,711 @@
+//! Where exports go: besides a local directory, an export can be uploaded straight to S3 (or
+//! an S3-compatible endpoint), to a server over SFTP (on the same SSH stack as tunnels,
+//! jump hosts included) or with an HTTP PUT. Destinations are saved per workspace, with their
+//! passwords in the OS credential store; uploads report progress as `export-progress` events.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, State};
+use tokio::io::AsyncWriteExt;
+
+use crate::export::{self, Compression, Encoder, ExportFormat};
+use crate::secrets::{self, SecretKind};
+use crate::{
+  db, encoding, iam, masking, readonly, store, tasks, timezone, transfer, workspaces, AppState,
+  SshConfig,
+};
+
+const UPLOAD_CHUNK: usize = 64 * 1024;
+const HTTP_TIMEOUT: Duration = Duration::from_mins(1);
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Target {
+  /// Directory on this machine.
+  Local { directory: String },
+  /// S3 bucket; credentials come from the environment or `~/.aws/credentials` like IAM auth.
+  #[serde(rename_all = "camelCase")]
+  S3 {
+    bucket: String,
+    region: String,
+    /// Key prefix ("folder") the file is put under.
+    #[serde(default)]
+    prefix: String,
+    /// S3-compatible endpoint (MinIO, R2, ...) addressed path-style, e.g. `http://localhost:9000`.
+    #[serde(default)]
+    endpoint: Option<String>,
+    /// Named profile in `~/.aws/credentials`.
+    #[serde(default)]
+    profile: Option<String>,
+  },
+  /// Directory on an SSH server.
+  #[serde(rename_all = "camelCase")]
+  Sftp { ssh: SshConfig, directory: String },
+  /// HTTP(S) PUT; `{file}` in the URL is replaced with the file name.
+  #[serde(rename_all = "camelCase")]
+  Http {
+    url: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// `Authorization` header value; kept in the credential store once saved.
+    #[serde(default)]
+    authorization: Option<String>,
+  },
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportDestination {
+  /// Left empty when saving a new destination; an id is assigned.
+  #[serde(default)]
+  pub id: String,
+  pub name: String,
+  pub target: Target,
+  #[serde(default)]
+  pub updated_at: i64,
+}
+
+/// Called with the bytes sent so far and the total.
+pub type Progress = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
+fn decode(config: &str) -> Result<ExportDestination, String> {
+  serde_json::from_str(config).map_err(|e| format!("Corrupt export destination: {}", e))
+}
+
+/// Stores passwords in the OS credential store and strips them from the destination.
+async fn move_secrets_to_keychain(destination: &mut ExportDestination) -> Result<(), String> {
+  let mut pending = Vec::new();
+  match &mut destination.target {
+    Target::Sftp { ssh, .. } => {
+      if let Some(password) = ssh.password.take().filter(|p| !p.is_empty()) {
+        pending.push((SecretKind::SshPassword, password));
+      }
+      for (n, hop) in ssh.jump_hosts.iter_mut().enumerate() {
+        if let Some(password) = hop.password.take().filter(|p| !p.is_empty()) {
+          pending.push((SecretKind::JumpHostPassword(n), password));
+        }
+      }
+    }
+    Target::Http { authorization, .. } => {
+      if let Some(value) = authorization.take().filter(|v| !v.is_empty()) {
+        pending.push((SecretKind::Password, value));
+      }
+    }
+    Target::Local { .. } | Target::S3 { .. } => {}
+  }
+  let id = destination.id.clone();
+  secrets::blocking(move || {
+    pending
+      .iter()
+      .try_for_each(|(kind, secret)| secrets::store(&id, *kind, secret))
+  })
+  .await
+}
+
+/// Fills the passwords stripped by [`move_secrets_to_keychain`] back in.
+async fn with_secrets(destination: &ExportDestination) -> Result<Target, String> {
+  let id = Some(destination.id.as_str()).filter(|id| !id.is_empty());
+  Ok(match destination.target.clone() {
+    Target::Sftp { ssh, directory } => Target::Sftp {
+      ssh: secrets::resolve_ssh(id, Some(ssh))
+        .await?
+        .ok_or("SFTP destination has no SSH settings")?,
+      directory,
+    },
+    Target::Http {
+      url,
+      headers,
+      authorization,
+    } => Target::Http {
+      url,
+      headers,
+      authorization: secrets::resolve(id, SecretKind::Password, authorization).await?,
+    },
+    other => other,
+  })
+}
+
+/// Looks a destination up by id, falling back to a case-insensitive name match.
+pub async fn find_destination(
+  state: &AppState,
+  name_or_id: &str,
+) -> Result<ExportDestination, String> {
+  let mut destinations = all_destinations(state).await?;
+  let index = destinations
+    .iter()
+    .position(|d| d.id == name_or_id)
+    .or_else(|| {
+      destinations
+        .iter()
+        .position(|d| d.name.eq_ignore_ascii_case(name_or_id))
+    })
+    .ok_or_else(|| format!("Unknown export destination: {}", name_or_id))?;
+  Ok(destinations.swap_remove(index))
+}
+
+async fn all_destinations(state: &AppState) -> Result<Vec<ExportDestination>, String> {
+  let pool = store::pool(state)?;
+  let rows: Vec<(String,)> = sqlx::query_as(
+    "SELECT config FROM export_destinations WHERE workspace = ? ORDER BY name COLLATE NOCASE",
+  )
+  .bind(workspaces::current(state))
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  rows.iter().map(|(config,)| decode(config)).collect()
+}
+
+/// Creates or replaces a destination and returns it as stored.
+#[tauri::command]
+pub async fn save_export_destination(
+  state: State<'_, AppState>,
+  destination: ExportDestination,
+) -> Result<ExportDestination, String> {
+  if destination.name.trim().is_empty() {
+    return Err("Destination name cannot be empty".to_string());
+  }
+  let mut destination = destination;
+  if destination.id.trim().is_empty() {
+    destination.id = crate::next_id(&format!("destination-{}", store::now_ms()));
+  }
+  destination.updated_at = store::now_ms();
+  move_secrets_to_keychain(&mut destination).await?;
+
+  let pool = store::pool(&state)?;
+  let config = serde_json::to_string(&destination).map_err(|e| e.to_string())?;
+  sqlx::query(
+    "INSERT INTO export_destinations (id, workspace, name, config, updated_at) \
+     VALUES (?, ?, ?, ?, ?) \
+     ON CONFLICT(id) DO UPDATE SET name = excluded.name, config = excluded.config, \
+     updated_at = excluded.updated_at",
+  )
+  .bind(&destination.id)
+  .bind(workspaces::current(&state))
+  .bind(&destination.name)
+  .bind(config)
+  .bind(destination.updated_at)
+  .execute(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(destination)
+}
+
+/// Destinations of the active workspace.
+#[tauri::command]
+pub async fn list_export_destinations(
+  state: State<'_, AppState>,
+) -> Result<Vec<ExportDestination>, String> {
+  all_destinations(&state).await
+}
+
+#[tauri::command]
+pub async fn delete_export_destination(
+  state: State<'_, AppState>,
+  destination_id: String,
+) -> Result<(), String> {
+  let pool = store::pool(&state)?;
+  let result = sqlx::query("DELETE FROM export_destinations WHERE id = ?")
+    .bind(&destination_id)
+    .execute(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  if result.rows_affected() == 0 {
+    return Err(format!("Unknown export destination: {}", destination_id));
+  }
+  secrets::blocking(move || secrets::delete_all(&destination_id)).await
+}
+
+struct HttpUrl {
+  tls: bool,
+  /// `host[:port]` as written, for the Host header.
+  authority: String,
+  host: String,
+  port: u16,
+  /// Path and query.
+  path: String,
+}
+
+fn parse_http_url(url: &str) -> Result<HttpUrl, String> {
+  let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+    (true, rest)
+  } else if let Some(rest) = url.strip_prefix("http://") {
+    (false, rest)
+  } else {
+    return Err(format!("Not an http(s) URL: {}", url));
+  };
+  let (authority, path) = match rest.find('/') {
+    Some(slash) => (&rest[..slash], &rest[slash..]),
+    None => (rest, "/"),
+  };
+  let default_port = if tls { 443 } else { 80 };
+  let (host, port) = match authority.strip_prefix('[') {
+    // IPv6 literal
+    Some(rest) => {
+      let (host, after) = rest
+        .split_once(']')
+        .ok_or_else(|| format!("Invalid host in URL: {}", url))?;
+      (host, after.strip_prefix(':'))
+    }
+    None => match authority.rsplit_once(':') {
+      Some((host, port)) => (host, Some(port)),
+      None => (authority, None),
+    },
+  };
+  let port = match port {
+    Some(port) => port
+      .parse()
+      .map_err(|_| format!("Invalid port in URL: {}", url))?,
+    None => default_port,
+  };
+  Ok(HttpUrl {
+    tls,
+    authority: authority.to_string(),
+    host: host.to_string(),
+    port,
+    path: path.to_string(),
+  })
+}
+
+trait Connection: Read + Write {}
+impl<T: Read + Write> Connection for T {}
+
+/// Blocking TCP connection to `url`'s host, through TLS for `https`.
+fn connect(url: &HttpUrl) -> Result<Box<dyn Connection>, String> {
+  let addr = (url.host.as_str(), url.port)
+    .to_socket_addrs()
+    .map_err(|e| format!("Cannot resolve {}: {}", url.host, e))?
+    .next()
+    .ok_or_else(|| format!("Cannot resolve {}", url.host))?;
+  let tcp = TcpStream::connect_timeout(&addr, HTTP_TIMEOUT).map_err(|e| e.to_string())?;
+  tcp
+    .set_read_timeout(Some(HTTP_TIMEOUT))
+    .and_then(|()| tcp.set_write_timeout(Some(HTTP_TIMEOUT)))
+    .map_err(|e| e.to_string())?;
+  if url.tls {
+    let connector = native_tls::TlsConnector::new().map_err(|e| e.to_string())?;
+    Ok(Box::new(connector.connect(&url.host, tcp).map_err(
+      |e| format!("TLS handshake with {} failed: {}", url.host, e),
+    )?))
+  } else {
+    Ok(Box::new(tcp))
+  }
+}
+
+/// Sends `body` with a blocking HTTP/1.1 PUT, reporting progress per chunk.
+fn http_put(
+  url: &HttpUrl,
+  headers: &[(String, String)],
+  body: &[u8],
+  progress: &Progress,
+) -> Result<(), String> {
+  let mut stream = connect(url)?;
+
+  let mut head = format!(
+    "PUT {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+    url.path,
+    url.authority,
+    body.len()
+  );
+  for (name, value) in headers {
+    let _ = write!(head, "{}: {}\r\n", name, value);
+  }
+  head.push_str("\r\n");
+  stream
+    .write_all(head.as_bytes())
+    .map_err(|e| e.to_string())?;
+  let total = body.len() as u64;
+  let mut sent = 0;
+  for chunk in body.chunks(UPLOAD_CHUNK) {
+    stream.write_all(chunk).map_err(|e| e.to_string())?;
+    sent += chunk.len() as u64;
+    progress(sent, total);
+  }
+  stream.flush().map_err(|e| e.to_string())?;
+
+  let mut response = Vec::new();
+  stream
+    .read_to_end(&mut response)
+    .map_err(|e| e.to_string())?;
+  let response = String::from_utf8_lossy(&response);
+  let status_line = response.lines().next().unwrap_or_default();
+  let status: u16 = status_line
+    .split_whitespace()
+    .nth(1)
+    .and_then(|code| code.parse().ok())
+    .ok_or("Malformed HTTP response")?;
+  if (200..300).contains(&status) {
+    Ok(())
+  } else {
+    let body = response
+      .split_once("\r\n\r\n")
+      .map(|(_, body)| body.trim())
+      .unwrap_or_default();
+    Err(format!(
+      "Upload failed: {} {}",
+      status_line,
+      body.chars().take(300).collect::<String>()
+    ))
+  }
+}
+
+/// URL and SigV4-signed headers for putting `body` at `key` in `bucket`.
+#[allow(clippy::too_many_arguments)]
+fn s3_put_request(
+  bucket: &str,
+  region: &str,
+  endpoint: Option<&str>,
+  profile: Option<&str>,
+  key: &str,
+  content_type: &str,
+  body: &[u8],
+  now: chrono::DateTime<chrono::Utc>,
+) -> Result<(String, Vec<(String, String)>), String> {
+  let creds = iam::load_aws_credentials(profile)?;
+  let encoded_key = key
+    .split('/')
+    .map(encoding::uri_encode)
+    .collect::<Vec<_>>()
+    .join("/");
+  let url = match endpoint {
+    Some(endpoint) => format!(
+      "{}/{}/{}",
+      endpoint.trim_end_matches('/'),
+      encoding::uri_encode(bucket),
+      encoded_key
+    ),
+    None => format!(
+      "https://{}.s3.{}.amazonaws.com/{}",
+      bucket, region, encoded_key
+    ),
+  };
+  let parsed = parse_http_url(&url)?;
+
+  let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+  let date = now.format("%Y%m%d").to_string();
+  let scope = format!("{}/{}/s3/aws4_request", date, region);
+  let payload_hash = encoding::hex(&Sha256::digest(body));
+
+  let mut signed = vec![
+    ("host".to_string(), parsed.authority.clone()),
+    ("x-amz-content-sha256".to_string(), payload_hash.clone()),
+    ("x-amz-date".to_string(), amz_date.clone()),
+  ];
+  if let Some(token) = &creds.session_token {
+    signed.push(("x-amz-security-token".to_string(), token.clone()));
+  }
+  let canonical_headers = signed.iter().fold(String::new(), |mut out, (name, value)| {
+    let _ = writeln!(out, "{}:{}", name, value);
+    out
+  });
+  let signed_names = signed
+    .iter()
+    .map(|(name, _)| name.as_str())
+    .collect::<Vec<_>>()
+    .join(";");
+  let canonical_request = format!(
+    "PUT\n{}\n\n{}\n{}\n{}",
+    parsed.path, canonical_headers, signed_names, payload_hash
+  );
+  let string_to_sign = format!(
+    "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+    amz_date,
+    scope,
+    encoding::hex(&Sha256::digest(canonical_request.as_bytes()))
+  );
+  let key = iam::signing_key(&creds, &date, region, "s3")?;
+  let signature = encoding::hex(&encoding::hmac_sha256(&key, string_to_sign.as_bytes())?);
+
+  let mut headers: Vec<(String, String)> = signed.into_iter().skip(1).collect();
+  headers.push(("Content-Type".to_string(), content_type.to_string()));
+  headers.push((
+    "Authorization".to_string(),
+    format!(
+      "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+      creds.access_key_id, scope, signed_names, signature
+    ),
+  ));
+  Ok((url, headers))
+}
+
+async fn put_blocking(
+  url: String,
+  headers: Vec<(String, String)>,
+  body: Vec<u8>,
+  progress: Progress,
+) -> Result<(), String> {
+  tokio::task::spawn_blocking(move || http_put(&parse_http_url(&url)?, &headers, &body, &progress))
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+async fn sftp_put(
+  ssh: SshConfig,
+  path: &str,
+  body: &[u8],
+  progress: &Progress,
+) -> Result<(), String> {
+  // Jump host sessions carry the target's and have to outlive the upload
+  let mut sessions = crate::connect_ssh_chain(ssh).await?;
+  let session = sessions
+    .pop()
+    .ok_or("SFTP destination has no target host")?;
+  let channel = session
+    .channel_open_session()
+    .await
+    .map_err(|e| format!("SSH channel error: {}", e))?;
+  channel
+    .request_subsystem(true, "sftp")
+    .await
+    .map_err(|e| format!("SFTP subsystem unavailable: {}", e))?;
+  let sftp = russh_sftp::client::SftpSession::new(channel.into_stream())
+    .await
+    .map_err(|e| format!("SFTP error: {}", e))?;
+  let mut file = sftp
+    .create(path)
+    .await
+    .map_err(|e| format!("Cannot create {}: {}", path, e))?;
+  let total = body.len() as u64;
+  let mut sent = 0;
+  for chunk in body.chunks(UPLOAD_CHUNK) {
+    file
+      .write_all(chunk)
+      .await
+      .map_err(|e| format!("SFTP write failed: {}", e))?;
+    sent += chunk.len() as u64;
+    progress(sent, total);
+  }
+  file
+    .shutdown()
+    .await
+    .map_err(|e| format!("SFTP write failed: {}", e))?;
+  let _ = sftp.close().await;
+  drop(sessions);
+  Ok(())
+}
+
+fn join_path(directory: &str, file_name: &str) -> String {
+  match directory.trim_end_matches('/') {
+    "" if directory.starts_with('/') => format!("/{}", file_name),
+    "" => file_name.to_string(),
+    dir => format!("{}/{}", dir, file_name),
+  }
+}
+
+/// Uploads `body` as `file_name` to `destination`; returns where it ended up.
+pub async fn upload(
+  destination: &ExportDestination,
+  file_name: &str,
+  content_type: &str,
+  body: Vec<u8>,
+  progress: Progress,
+) -> Result<String, String> {
+  let total = body.len() as u64;
+  match with_secrets(destination).await? {
+    Target::Local { directory } => {
+      let path = std::path::Path::new(&directory).join(file_name);
+      tokio::fs::create_dir_all(&directory)
+        .await
+        .map_err(|e| e.to_string())?;
+      tokio::fs::write(&path, &body)
+        .await
+        .map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+      progress(total, total);
+      Ok(path.display().to_string())
+    }
+    Target::S3 {
+      bucket,
+      region,
+      prefix,
+      endpoint,
+      profile,
+    } => {
+      let key = join_path(prefix.trim_start_matches('/'), file_name);
+      let (url, headers) = s3_put_request(
+        &bucket,
+        &region,
+        endpoint.as_deref(),
+        profile.as_deref(),
+        &key,
+        content_type,
+        &body,
+        chrono::Utc::now(),
+      )?;
+      put_blocking(url, headers, body, progress).await?;
+      Ok(format!("s3://{}/{}", bucket, key))
+    }
+    Target::Sftp { ssh, directory } => {
+      let location = format!("sftp://{}@{}", ssh.username, ssh.host);
+      let path = join_path(&directory, file_name);
+      sftp_put(ssh, &path, &body, &progress).await?;
+      Ok(format!(
+        "{}{}{}",
+        location,
+        if path.starts_with('/') { "" } else { "/" },
+        path
+      ))
+    }
+    Target::Http {
+      url,
+      headers,
+      authorization,
+    } => {
+      let url = url.replace("{file}", &encoding::uri_encode(file_name));
+      let mut headers: Vec<(String, String)> = headers.into_iter().collect();
+      if !headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+      {
+        headers.push(("Content-Type".to_string(), content_type.to_string()));
+      }
+      if let Some(authorization) = authorization {
+        headers.push(("Authorization".to_string(), authorization));
+      }
+      put_blocking(url.clone(), headers, body, progress).await?;
+      Ok(url)
+    }
+  }
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ExportProgress {
+  export_id: String,
+  sent_bytes: u64,
+  total_bytes: u64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportUpload {
+  pub export_id: String,
+  pub file_name: String,
+  /// Path or URL of the uploaded file.
+  pub location: String,
+  pub rows: usize,
+  pub bytes: u64,
+}
+
+/// Default file name: the table (or `query`) and a timestamp.
+pub fn default_file_name(table: Option<&str>, format: ExportFormat) -> String {
+  let stem: String = table
+    .unwrap_or("query")
+    .chars()
+    .map(|c| {
+      if c.is_alphanumeric() || c == '-' {
+        c
+      } else {
+        '_'
+      }
+    })
+    .collect();
+  format!(
+    "{}-{}.{}",
+    stem,
+    chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+    format.extension()
+  )
+}
+
+/// Exports a table or query result of `connection` to a saved destination, compressed while
+/// it is written when `compression` is given. Progress events carry `export_id` (generated
+/// when omitted).
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_to_destination(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  connection: String,
+  destination_id: String,
+  table: Option<String>,
+  query: Option<String>,
+  format: Option<String>,
+  file_name: Option<String>,
+  export_id: Option<String>,
+  compression: Option<String>,
+) -> Result<ExportUpload, String> {
+  let destination = find_destination(&state, &destination_id).await?;
+  let format = match format {
+    Some(name) => ExportFormat::parse(&name)?,
+    None => ExportFormat::Csv,
+  };
+  let compression = match compression {
+    Some(name) => Compression::parse(&name)?,
+    None => Compression::None,
+  };
+  let pool = db::sql_pool(&state, &connection)?;
+  let sql = match (&table, query) {
+    (Some(table), None) => {
+      let table = pool.resolve_table(table).await?;
+      format!("SELECT * FROM {}", pool.table_ref(&table))
+    }
+    (None, Some(query)) => {
+      readonly::check_statement(&state, &connection, &query)?;
+      query
+    }
+    _ => return Err("Pass exactly one of table or query".to_string()),
+  };
+
+  let tz = timezone::display_zone(&state, &connection);
+  let (columns, mut rows) = pool.fetch_with_columns(&sql, &[], tz.as_ref()).await?;
+  if let Some(mask) = masking::active(&state, &connection) {
+    for row in &mut rows {
+      mask.apply(row);
+    }
+  }
+  transfer::record(
+    &state,
+    &connection,
+    transfer::Category::Export,
+    rows.len(),
+    transfer::rows_bytes(&rows),
+  );
+  let mut body = Encoder::new(Vec::new(), compression).map_err(|e| e.to_string())?;
+  export::write_rows(&mut body, format, &columns, &rows)?;
+  let body = body.finish().map_err(|e| e.to_string())?;
+
+  let export_id = export_id.unwrap_or_else(|| crate::next_id("export"));
+  let file_name = file_name
+    .unwrap_or_else(|| compression.file_name(&default_file_name(table.as_deref(), format)));
+  let bytes = body.len() as u64;
+  let progress: Progress = {
+    let export_id = export_id.clone();
+    Arc::new(move |sent_bytes, total_bytes| {
+      tasks::report(
+        &app,
+        &export_id,
+        sent_bytes,
+        Some(total_bytes),
+        Some("Uploading"),
+      );
+      let _ = app.emit(
+        "export-progress",
+        ExportProgress {
+          export_id: export_id.clone(),
+          sent_bytes,
+          total_bytes,
+        },
+      );
+    })
+  };
+  let location = upload(
+    &destination,
+    &file_name,
+    compression.content_type(format),
+    body,
+    progress,
+  )
+  .await?;
+  Ok(ExportUpload {
+    export_id,
+    file_name,
+    location,
+    rows: rows.len(),
+    bytes,
+  })
+}
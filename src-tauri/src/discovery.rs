@@ -0,0 +1,496 @@
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const DOCKER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Container ports we recognise as database services, with the engine they usually carry.
+const KNOWN_PORTS: &[(u16, &str)] = &[
+  (3306, "mysql"),
+  (5432, "postgres"),
+  (6379, "redis"),
+  (27017, "mongodb"),
+];
+
+/// Arguments for the matching `connect_*` command, ready to be passed straight to `invoke`.
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectPayload {
+  pub command: String,
+  pub host: String,
+  pub port: u16,
+  pub username: Option<String>,
+  pub password: Option<String>,
+  pub database: Option<String>,
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredDatabase {
+  pub container_id: String,
+  pub container_name: String,
+  pub image: String,
+  pub engine: String,
+  pub container_port: u16,
+  pub host_ip: String,
+  pub host_port: u16,
+  pub connect: ConnectPayload,
+}
+
+enum DockerEndpoint {
+  #[cfg_attr(not(unix), allow(dead_code))]
+  Unix(String),
+  #[cfg_attr(not(windows), allow(dead_code))]
+  NamedPipe(String),
+  Tcp(String),
+}
+
+fn docker_endpoint() -> DockerEndpoint {
+  if let Ok(host) = std::env::var("DOCKER_HOST") {
+    if let Some(path) = host.strip_prefix("unix://") {
+      return DockerEndpoint::Unix(path.to_string());
+    }
+    if let Some(pipe) = host.strip_prefix("npipe://") {
+      return DockerEndpoint::NamedPipe(pipe.replace('/', "\\"));
+    }
+    if let Some(addr) = host.strip_prefix("tcp://") {
+      return DockerEndpoint::Tcp(addr.to_string());
+    }
+  }
+  if cfg!(windows) {
+    DockerEndpoint::NamedPipe(r"\\.\pipe\docker_engine".to_string())
+  } else {
+    DockerEndpoint::Unix("/var/run/docker.sock".to_string())
+  }
+}
+
+async fn http_get<S>(mut stream: S, path: &str) -> Result<String, String>
+where
+  S: AsyncRead + AsyncWrite + Unpin,
+{
+  // HTTP/1.0 keeps the daemon from answering with a chunked body
+  let request = format!("GET {} HTTP/1.0\r\nHost: docker\r\n\r\n", path);
+  stream
+    .write_all(request.as_bytes())
+    .await
+    .map_err(|e| e.to_string())?;
+  let mut response = Vec::new();
+  stream
+    .read_to_end(&mut response)
+    .await
+    .map_err(|e| e.to_string())?;
+  let response = String::from_utf8_lossy(&response);
+  let (head, body) = response
+    .split_once("\r\n\r\n")
+    .ok_or("Malformed Docker API response")?;
+  let status = head.lines().next().unwrap_or_default();
+  if !status.contains(" 200 ") {
+    return Err(format!("Docker API error: {}", status));
+  }
+  Ok(body.to_string())
+}
+
+async fn docker_get(path: &str) -> Result<serde_json::Value, String> {
+  let body = match docker_endpoint() {
+    #[cfg(unix)]
+    DockerEndpoint::Unix(socket) => {
+      let stream = tokio::net::UnixStream::connect(&socket)
+        .await
+        .map_err(|e| format!("Docker daemon not reachable at {}: {}", socket, e))?;
+      http_get(stream, path).await?
+    }
+    #[cfg(windows)]
+    DockerEndpoint::NamedPipe(pipe) => {
+      let stream = tokio::net::windows::named_pipe::ClientOptions::new()
+        .open(&pipe)
+        .map_err(|e| format!("Docker daemon not reachable at {}: {}", pipe, e))?;
+      http_get(stream, path).await?
+    }
+    DockerEndpoint::Tcp(addr) => {
+      let stream = tokio::net::TcpStream::connect(&addr)
+        .await
+        .map_err(|e| format!("Docker daemon not reachable at {}: {}", addr, e))?;
+      http_get(stream, path).await?
+    }
+    _ => return Err("Docker endpoint not supported on this platform".to_string()),
+  };
+  serde_json::from_str(&body).map_err(|e| e.to_string())
+}
+
+fn engine_for(image: &str, container_port: u16) -> Option<&'static str> {
+  let image = image.to_lowercase();
+  if image.contains("mysql") || image.contains("mariadb") {
+    return Some("mysql");
+  }
+  if image.contains("postgres") || image.contains("postgis") || image.contains("timescale") {
+    return Some("postgres");
+  }
+  if image.contains("redis") || image.contains("valkey") {
+    return Some("redis");
+  }
+  if image.contains("mongo") {
+    return Some("mongodb");
+  }
+  KNOWN_PORTS
+    .iter()
+    .find(|(port, _)| *port == container_port)
+    .map(|(_, engine)| *engine)
+}
+
+fn env_lookup<'a>(env: &'a [String], names: &[&str]) -> Option<&'a str> {
+  names.iter().find_map(|name| {
+    env.iter().find_map(|kv| {
+      kv.strip_prefix(name)
+        .and_then(|rest| rest.strip_prefix('='))
+    })
+  })
+}
+
+/// Derives default credentials from the official images' environment variables.
+fn connect_payload(engine: &str, host: String, port: u16, env: &[String]) -> ConnectPayload {
+  let (username, password, database) = match engine {
+    "mysql" => {
+      let user = env_lookup(env, &["MYSQL_USER", "MARIADB_USER"]);
+      let password = if user.is_some() {
+        env_lookup(env, &["MYSQL_PASSWORD", "MARIADB_PASSWORD"])
+      } else {
+        env_lookup(env, &["MYSQL_ROOT_PASSWORD", "MARIADB_ROOT_PASSWORD"])
+      };
+      (
+        Some(user.unwrap_or("root")),
+        password,
+        env_lookup(env, &["MYSQL_DATABASE", "MARIADB_DATABASE"]),
+      )
+    }
+    "postgres" => {
+      let user = env_lookup(env, &["POSTGRES_USER"]).unwrap_or("postgres");
+      (
+        Some(user),
+        env_lookup(env, &["POSTGRES_PASSWORD"]),
+        Some(env_lookup(env, &["POSTGRES_DB"]).unwrap_or(user)),
+      )
+    }
+    "redis" => (None, env_lookup(env, &["REDIS_PASSWORD"]), None),
+    "mongodb" => (
+      env_lookup(env, &["MONGO_INITDB_ROOT_USERNAME"]),
+      env_lookup(env, &["MONGO_INITDB_ROOT_PASSWORD"]),
+      None,
+    ),
+    _ => (None, None, None),
+  };
+  ConnectPayload {
+    command: format!("connect_{}", engine),
+    host,
+    port,
+    username: username.map(str::to_string),
+    password: password.map(str::to_string),
+    database: database.map(str::to_string),
+  }
+}
+
+/// Lists running Docker containers that publish a database port on the host.
+#[tauri::command]
+pub async fn discover_local_databases() -> Result<Vec<DiscoveredDatabase>, String> {
+  let containers = tokio::time::timeout(DOCKER_TIMEOUT, docker_get("/containers/json"))
+    .await
+    .map_err(|_| "Docker daemon timed out".to_string())??;
+
+  let mut found = Vec::new();
+  for container in containers.as_array().into_iter().flatten() {
+    let id = container["Id"].as_str().unwrap_or_default().to_string();
+    let image = container["Image"].as_str().unwrap_or_default().to_string();
+    let name = container["Names"][0]
+      .as_str()
+      .unwrap_or_default()
+      .trim_start_matches('/')
+      .to_string();
+
+    let mut env: Option<Vec<String>> = None;
+    for port in container["Ports"].as_array().into_iter().flatten() {
+      let (Some(private), Some(public)) =
+        (port["PrivatePort"].as_u64(), port["PublicPort"].as_u64())
+      else {
+        continue;
+      };
+      let (Ok(private), Ok(public)) = (u16::try_from(private), u16::try_from(public)) else {
+        continue;
+      };
+      if port["Type"].as_str() != Some("tcp")
+        || !KNOWN_PORTS.iter().any(|(known, _)| *known == private)
+      {
+        continue;
+      }
+      let Some(engine) = engine_for(&image, private) else {
+        continue;
+      };
+
+      // Inspect lazily: only containers that actually expose a database port
+      if env.is_none() {
+        let details = tokio::time::timeout(
+          DOCKER_TIMEOUT,
+          docker_get(&format!("/containers/{}/json", id)),
+        )
+        .await
+        .ok()
+        .and_then(Result::ok);
+        env = Some(
+          details
+            .and_then(|d| {
+              d["Config"]["Env"].as_array().map(|vars| {
+                vars
+                  .iter()
+                  .filter_map(|v| v.as_str().map(str::to_string))
+                  .collect()
+              })
+            })
+            .unwrap_or_default(),
+        );
+      }
+
+      let host_ip = match port["IP"].as_str() {
+        Some("0.0.0.0" | "::") | None => "127.0.0.1".to_string(),
+        Some(ip) => ip.to_string(),
+      };
+      found.push(DiscoveredDatabase {
+        container_id: id.chars().take(12).collect(),
+        container_name: name.clone(),
+        image: image.clone(),
+        engine: engine.to_string(),
+        container_port: private,
+        host_ip: host_ip.clone(),
+        host_port: public,
+        connect: connect_payload(engine, host_ip, public, env.as_deref().unwrap_or_default()),
+      });
+    }
+  }
+
+  // Docker lists IPv4 and IPv6 bindings separately; keep one entry per published port
+  found.sort_by(|a, b| {
+    (&a.container_name, a.host_port, &a.host_ip).cmp(&(&b.container_name, b.host_port, &b.host_ip))
+  });
+  found.dedup_by(|a, b| a.container_id == b.container_id && a.host_port == b.host_port);
+  Ok(found)
+}
+
+const DEFAULT_PROBE_PORTS: &[u16] = &[3306, 3307, 5432, 5433, 6379, 6380, 27017, 27018];
+const PROBE_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const PROBE_READ_TIMEOUT: Duration = Duration::from_millis(1500);
+// MySQL speaks first; anything else waits for the client
+const GREETING_WAIT: Duration = Duration::from_millis(400);
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeResult {
+  pub port: u16,
+  pub open: bool,
+  pub service: Option<String>,
+  pub version: Option<String>,
+  pub detail: Option<String>,
+  pub latency_ms: Option<u64>,
+}
+
+struct Identified {
+  service: &'static str,
+  version: Option<String>,
+  detail: Option<String>,
+}
+
+async fn read_some(stream: &mut tokio::net::TcpStream, wait: Duration) -> Option<Vec<u8>> {
+  let mut buf = vec![0u8; 4096];
+  match tokio::time::timeout(wait, stream.read(&mut buf)).await {
+    Ok(Ok(n)) if n > 0 => {
+      buf.truncate(n);
+      Some(buf)
+    }
+    _ => None,
+  }
+}
+
+fn parse_mysql_greeting(data: &[u8]) -> Option<Identified> {
+  // 3-byte payload length + sequence id, then the handshake payload
+  let payload = data.get(4..)?;
+  match payload.first()? {
+    0x0a => {
+      let end = payload[1..].iter().position(|b| *b == 0)? + 1;
+      Some(Identified {
+        service: "mysql",
+        version: Some(String::from_utf8_lossy(&payload[1..end]).to_string()),
+        detail: None,
+      })
+    }
+    // Error packet sent instead of a greeting, e.g. "Host is not allowed to connect"
+    0xff => Some(Identified {
+      service: "mysql",
+      version: None,
+      detail: payload
+        .get(3..)
+        .map(|msg| String::from_utf8_lossy(msg).to_string()),
+    }),
+    _ => None,
+  }
+}
+
+async fn probe_postgres(stream: &mut tokio::net::TcpStream) -> Option<Identified> {
+  // SSLRequest: length 8, magic code 80877103
+  let mut msg = Vec::with_capacity(8);
+  msg.extend_from_slice(&8i32.to_be_bytes());
+  msg.extend_from_slice(&80_877_103i32.to_be_bytes());
+  stream.write_all(&msg).await.ok()?;
+  let reply = read_some(stream, PROBE_READ_TIMEOUT).await?;
+  match reply.as_slice() {
+    [b'S'] => Some(Identified {
+      service: "postgres",
+      version: None,
+      detail: Some("SSL supported".to_string()),
+    }),
+    [b'N'] => Some(Identified {
+      service: "postgres",
+      version: None,
+      detail: Some("SSL not supported".to_string()),
+    }),
+    _ => None,
+  }
+}
+
+async fn probe_redis(stream: &mut tokio::net::TcpStream) -> Option<Identified> {
+  stream.write_all(b"PING\r\n").await.ok()?;
+  let reply = read_some(stream, PROBE_READ_TIMEOUT).await?;
+  let reply = String::from_utf8_lossy(&reply);
+  let line = reply.lines().next().unwrap_or_default();
+  if line.starts_with("+PONG") {
+    Some(Identified {
+      service: "redis",
+      version: None,
+      detail: None,
+    })
+  } else if line.starts_with("-NOAUTH") || line.starts_with("-DENIED") {
+    Some(Identified {
+      service: "redis",
+      version: None,
+      detail: Some(line.trim_start_matches('-').to_string()),
+    })
+  } else {
+    None
+  }
+}
+
+async fn probe_mongodb(stream: &mut tokio::net::TcpStream) -> Option<Identified> {
+  use mongodb::bson::{doc, Document};
+
+  let body = mongodb::bson::to_vec(&doc! { "isMaster": 1, "$db": "admin" }).ok()?;
+  // OP_MSG: header, flagBits, one kind-0 section holding the command document
+  let len = i32::try_from(16 + 4 + 1 + body.len()).ok()?;
+  let mut msg = Vec::new();
+  msg.extend_from_slice(&len.to_le_bytes());
+  msg.extend_from_slice(&1i32.to_le_bytes());
+  msg.extend_from_slice(&0i32.to_le_bytes());
+  msg.extend_from_slice(&2013i32.to_le_bytes());
+  msg.extend_from_slice(&0u32.to_le_bytes());
+  msg.push(0);
+  msg.extend_from_slice(&body);
+  stream.write_all(&msg).await.ok()?;
+
+  let reply = read_some(stream, PROBE_READ_TIMEOUT).await?;
+  let reply_doc: Document = mongodb::bson::from_slice(reply.get(21..)?).ok()?;
+  let wire = reply_doc.get_i32("maxWireVersion").ok();
+  let replica_set = reply_doc.get_str("setName").ok();
+  Some(Identified {
+    service: "mongodb",
+    version: wire.map(|w| format!("wire protocol {}", w)),
+    detail: replica_set.map(|set| format!("replica set {}", set)),
+  })
+}
+
+fn port_hint(port: u16) -> Option<&'static str> {
+  match port {
+    5432 | 5433 => Some("postgres"),
+    6379 | 6380 => Some("redis"),
+    27017..=27019 => Some("mongodb"),
+    _ => None,
+  }
+}
+
+async fn probe_port(host: &str, port: u16) -> ProbeResult {
+  let started = std::time::Instant::now();
+  let connect = || {
+    tokio::time::timeout(
+      PROBE_CONNECT_TIMEOUT,
+      tokio::net::TcpStream::connect((host, port)),
+    )
+  };
+
+  let Ok(Ok(mut stream)) = connect().await else {
+    return ProbeResult {
+      port,
+      open: false,
+      service: None,
+      version: None,
+      detail: None,
+      latency_ms: None,
+    };
+  };
+  let latency_ms = u64::try_from(started.elapsed().as_millis()).ok();
+
+  let mut identified = match read_some(&mut stream, GREETING_WAIT).await {
+    Some(greeting) => parse_mysql_greeting(&greeting),
+    None => None,
+  };
+
+  if identified.is_none() {
+    // Try the protocol the port usually carries first; each attempt gets a fresh connection
+    let mut order = vec!["postgres", "redis", "mongodb"];
+    if let Some(hint) = port_hint(port) {
+      order.retain(|s| *s != hint);
+      order.insert(0, hint);
+    }
+    let mut fresh = Some(stream);
+    for service in order {
+      let mut stream = match fresh.take() {
+        Some(stream) => stream,
+        None => match connect().await {
+          Ok(Ok(stream)) => stream,
+          _ => break,
+        },
+      };
+      identified = match service {
+        "postgres" => probe_postgres(&mut stream).await,
+        "redis" => probe_redis(&mut stream).await,
+        _ => probe_mongodb(&mut stream).await,
+      };
+      if identified.is_some() {
+        break;
+      }
+    }
+  }
+
+  match identified {
+    Some(id) => ProbeResult {
+      port,
+      open: true,
+      service: Some(id.service.to_string()),
+      version: id.version,
+      detail: id.detail,
+      latency_ms,
+    },
+    None => ProbeResult {
+      port,
+      open: true,
+      service: None,
+      version: None,
+      detail: Some("Unrecognized service".to_string()),
+      latency_ms,
+    },
+  }
+}
+
+/// Checks which database services are listening on `host`, identifying each one by its
+/// protocol handshake rather than trusting the port number.
+#[tauri::command]
+pub async fn probe_host(host: String, ports: Option<Vec<u16>>) -> Result<Vec<ProbeResult>, String> {
+  let ports = ports.unwrap_or_else(|| DEFAULT_PROBE_PORTS.to_vec());
+  if ports.is_empty() {
+    return Err("No ports to probe".to_string());
+  }
+  let results = futures::future::join_all(ports.iter().map(|port| probe_port(&host, *port))).await;
+  Ok(results)
+}
@@ -0,0 +1,124 @@
+//! Per-monitor DPI handling for the floating widget. Sizes from the frontend are kept in
+//! logical pixels per window and re-applied whenever the window's scale factor changes, which
+//! Windows reports (`WM_DPICHANGED`) when the widget is dragged onto a monitor with a different
+//! DPI. Without that the widget keeps the physical size of the old monitor and drifts by the
+//! ratio of the two scales.
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+
+use tauri::{LogicalSize, Manager, PhysicalPosition, PhysicalRect, PhysicalSize, State};
+
+use crate::AppState;
+
+/// Logical size last requested for each window, by label.
+pub type WidgetSizes = HashMap<String, LogicalSize<f64>>;
+
+pub fn remember_size(state: &AppState, label: &str, size: LogicalSize<f64>) {
+  state
+    .widget_sizes
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(label.to_string(), size);
+}
+
+/// `position` moved the least needed for a window of `size` to fit in `area`; the top-left
+/// corner wins when it's larger than the area.
+fn clamp_into(
+  area: &PhysicalRect<i32, u32>,
+  position: PhysicalPosition<i32>,
+  size: PhysicalSize<u32>,
+) -> PhysicalPosition<i32> {
+  let clamp = |at: i32, start: i32, room: u32, extent: u32| {
+    let end = start
+      .saturating_add_unsigned(room)
+      .saturating_sub_unsigned(extent);
+    at.min(end).max(start)
+  };
+  PhysicalPosition::new(
+    clamp(position.x, area.position.x, area.size.width, size.width),
+    clamp(position.y, area.position.y, area.size.height, size.height),
+  )
+}
+
+/// Re-applies the remembered logical size at the window's new `scale` and keeps it inside the
+/// work area of the monitor it's now on. Called from the `ScaleFactorChanged` window event.
+pub fn on_scale_changed(window: &tauri::Window, scale: f64) {
+  let state = window.state::<AppState>();
+  let Some(size) = state
+    .widget_sizes
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(window.label())
+    .copied()
+  else {
+    return;
+  };
+  let _ = window.set_size(tauri::Size::Logical(size));
+  if let (Ok(position), Ok(Some(monitor))) = (window.outer_position(), window.current_monitor()) {
+    let clamped = clamp_into(monitor.work_area(), position, size.to_physical(scale));
+    if clamped != position {
+      let _ = window.set_position(tauri::Position::Physical(clamped));
+    }
+  }
+}
+
+/// Moves the window onto monitor `index`, in the order `get_all_monitors_work_area` lists
+/// them, at the same relative spot of its work area and at the monitor's scale.
+#[tauri::command]
+pub fn move_to_monitor(
+  window: tauri::Window,
+  state: State<'_, AppState>,
+  index: usize,
+) -> Result<(), String> {
+  let monitors = window.available_monitors().map_err(|e| e.to_string())?;
+  let target = monitors
+    .get(index)
+    .ok_or_else(|| format!("No monitor {} (found {})", index, monitors.len()))?;
+  let saved = state
+    .widget_sizes
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(window.label())
+    .copied();
+  let size = if let Some(size) = saved {
+    size
+  } else {
+    let scale = window.scale_factor().map_err(|e| e.to_string())?;
+    window
+      .outer_size()
+      .map_err(|e| e.to_string())?
+      .to_logical(scale)
+  };
+
+  // Fraction of the current work area the window's corner sits at, kept on the new one
+  let position = window.outer_position().map_err(|e| e.to_string())?;
+  let (fx, fy) = match window.current_monitor().map_err(|e| e.to_string())? {
+    Some(current) => {
+      let area = current.work_area();
+      (
+        f64::from(position.x - area.position.x) / f64::from(area.size.width.max(1)),
+        f64::from(position.y - area.position.y) / f64::from(area.size.height.max(1)),
+      )
+    }
+    None => (0.0, 0.0),
+  };
+  let area = target.work_area();
+  let physical = size.to_physical(target.scale_factor());
+  #[allow(clippy::cast_possible_truncation)]
+  let position = clamp_into(
+    area,
+    PhysicalPosition::new(
+      area.position.x + (fx * f64::from(area.size.width)) as i32,
+      area.position.y + (fy * f64::from(area.size.height)) as i32,
+    ),
+    physical,
+  );
+  window
+    .set_position(tauri::Position::Physical(position))
+    .map_err(|e| e.to_string())?;
+  // Also handled by the scale change this causes, but the scale may be the same
+  window
+    .set_size(tauri::Size::Logical(size))
+    .map_err(|e| e.to_string())
+}
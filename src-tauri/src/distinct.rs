@@ -0,0 +1,111 @@
+//! Distinct values of a column with their counts, grouped on the server for the grid's filter
+//! dropdowns and "show unique values".
+
+use tauri::State;
+
+use crate::db::{self, SqlPool};
+use crate::{masking, timezone, AppState};
+
+const DEFAULT_DISTINCT_LIMIT: usize = 100;
+const MAX_DISTINCT_LIMIT: usize = 10_000;
+/// Alias of the count column; unusual enough not to clash with the grouped column.
+const COUNT_ALIAS: &str = "__spectra_count";
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DistinctValue {
+  pub value: serde_json::Value,
+  pub count: i64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DistinctValues {
+  pub column: String,
+  /// Most frequent first.
+  pub values: Vec<DistinctValue>,
+  /// More distinct values exist than `limit`.
+  pub truncated: bool,
+}
+
+/// Condition keeping rows whose `column` text contains the bound (lower-cased) needle.
+fn contains_filter(pool: &SqlPool, column: &str) -> String {
+  match pool {
+    SqlPool::MySql(_) => format!("LOCATE(?, LOWER(CAST({} AS CHAR))) > 0", column),
+    SqlPool::Postgres(_) => format!("strpos(lower({}::text), $1) > 0", column),
+    SqlPool::Sqlite(_) => format!("instr(lower(CAST({} AS TEXT)), ?) > 0", column),
+  }
+}
+
+/// Up to `limit` distinct values of `column` with how many rows hold each. `filter` keeps only
+/// values whose text contains it, ignoring case.
+#[tauri::command]
+pub async fn distinct_values(
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+  column: String,
+  filter: Option<String>,
+  limit: Option<usize>,
+) -> Result<DistinctValues, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let table = pool.resolve_table(&table).await?;
+  let limit = limit
+    .unwrap_or(DEFAULT_DISTINCT_LIMIT)
+    .clamp(1, MAX_DISTINCT_LIMIT);
+  let col = pool.quote_ident(&column);
+
+  let (condition, binds) = match filter.filter(|f| !f.is_empty()) {
+    Some(needle) => (
+      format!(" WHERE {}", contains_filter(&pool, &col)),
+      vec![needle.to_lowercase()],
+    ),
+    None => (String::new(), Vec::new()),
+  };
+  // One extra row tells whether the list was cut off
+  let sql = format!(
+    "SELECT {col}, COUNT(*) AS {count} FROM {table}{condition} GROUP BY {col} \
+     ORDER BY COUNT(*) DESC, {col} LIMIT {limit}",
+    col = col,
+    count = pool.quote_ident(COUNT_ALIAS),
+    table = pool.table_ref(&table),
+    condition = condition,
+    limit = limit + 1
+  );
+
+  let tz = timezone::display_zone(&state, &connection);
+  let (_, mut rows) = pool.fetch_with_columns(&sql, &binds, tz.as_ref()).await?;
+  let truncated = rows.len() > limit;
+  rows.truncate(limit);
+  if let Some(mask) = masking::active(&state, &connection) {
+    for row in &mut rows {
+      mask.apply(row);
+    }
+  }
+
+  let values = rows
+    .into_iter()
+    .map(|mut row| {
+      let count = row
+        .remove(COUNT_ALIAS)
+        .and_then(|count| match count {
+          serde_json::Value::Number(n) => n.as_i64(),
+          serde_json::Value::String(s) => s.parse().ok(),
+          _ => None,
+        })
+        .unwrap_or(0);
+      // The only other key is the column, under whatever name the engine reported
+      let value = row
+        .into_iter()
+        .next()
+        .map_or(serde_json::Value::Null, |(_, value)| value);
+      DistinctValue { value, count }
+    })
+    .collect();
+
+  Ok(DistinctValues {
+    column,
+    values,
+    truncated,
+  })
+}
@@ -0,0 +1,163 @@
+//! Windows 11 window styling for the floating widget: Mica/Acrylic system backdrops, corner
+//! rounding and the drop shadow, set through DWM attributes. Each command returns whether the
+//! effect took; on other platforms, and on Windows builds too old for the attribute, they do
+//! nothing and return `false`.
+//!
+//! The native frame also follows the OS light/dark setting. Tauri reports changes as
+//! `ThemeChanged` window events (on Windows from the `AppsUseLightTheme` registry value,
+//! re-read on `WM_SETTINGCHANGE`); each one flips DWM's dark-mode attribute and is passed on
+//! to the web UI as a `theme://changed` event.
+
+use tauri::{Emitter, Theme};
+
+#[cfg(target_os = "windows")]
+use windows::Win32::Foundation::HWND;
+#[cfg(target_os = "windows")]
+use windows::Win32::Graphics::Dwm::{
+  DwmSetWindowAttribute, DWMSBT_AUTO, DWMSBT_MAINWINDOW, DWMSBT_NONE, DWMSBT_TABBEDWINDOW,
+  DWMSBT_TRANSIENTWINDOW, DWMWA_SYSTEMBACKDROP_TYPE, DWMWA_USE_IMMERSIVE_DARK_MODE,
+  DWMWA_WINDOW_CORNER_PREFERENCE, DWMWCP_DEFAULT, DWMWCP_DONOTROUND, DWMWCP_ROUND,
+  DWMWCP_ROUNDSMALL, DWMWINDOWATTRIBUTE,
+};
+
+/// Sets a 32-bit DWM attribute on `window`; `false` when DWM rejects it (older Windows).
+#[cfg(target_os = "windows")]
+fn set_dwm_attribute(window: &tauri::Window, attribute: DWMWINDOWATTRIBUTE, value: i32) -> bool {
+  let Ok(handle) = window.hwnd() else {
+    return false;
+  };
+  let result = unsafe {
+    DwmSetWindowAttribute(
+      HWND(handle.0 as _),
+      attribute,
+      &value as *const _ as *const _,
+      std::mem::size_of::<i32>() as u32,
+    )
+  };
+  if let Err(e) = &result {
+    tracing::debug!("DwmSetWindowAttribute({}) failed: {}", attribute.0, e);
+  }
+  result.is_ok()
+}
+
+fn unknown(what: &str, value: &str, expected: &str) -> String {
+  format!("Unknown {} '{}'; expected {}", what, value, expected)
+}
+
+/// System backdrop behind the window: `mica`, `acrylic`, `tabbed` (Mica Alt), `none` or
+/// `auto` (let DWM decide). Needs Windows 11 22H2 or later.
+#[tauri::command]
+pub fn set_window_backdrop(window: tauri::Window, backdrop: String) -> Result<bool, String> {
+  const EXPECTED: &str = "mica, acrylic, tabbed, none or auto";
+  #[cfg(target_os = "windows")]
+  {
+    let value = match backdrop.as_str() {
+      "mica" => DWMSBT_MAINWINDOW,
+      "acrylic" => DWMSBT_TRANSIENTWINDOW,
+      "tabbed" => DWMSBT_TABBEDWINDOW,
+      "none" => DWMSBT_NONE,
+      "auto" => DWMSBT_AUTO,
+      other => return Err(unknown("backdrop", other, EXPECTED)),
+    };
+    Ok(set_dwm_attribute(
+      &window,
+      DWMWA_SYSTEMBACKDROP_TYPE,
+      value.0,
+    ))
+  }
+  #[cfg(not(target_os = "windows"))]
+  {
+    let _ = window;
+    match backdrop.as_str() {
+      "mica" | "acrylic" | "tabbed" | "none" | "auto" => Ok(false),
+      other => Err(unknown("backdrop", other, EXPECTED)),
+    }
+  }
+}
+
+/// Corner style: `round`, `roundSmall`, `square` or `default`. Needs Windows 11.
+#[tauri::command]
+pub fn set_window_corners(window: tauri::Window, corners: String) -> Result<bool, String> {
+  const EXPECTED: &str = "round, roundSmall, square or default";
+  #[cfg(target_os = "windows")]
+  {
+    let value = match corners.as_str() {
+      "round" => DWMWCP_ROUND,
+      "roundSmall" => DWMWCP_ROUNDSMALL,
+      "square" => DWMWCP_DONOTROUND,
+      "default" => DWMWCP_DEFAULT,
+      other => return Err(unknown("corner style", other, EXPECTED)),
+    };
+    Ok(set_dwm_attribute(
+      &window,
+      DWMWA_WINDOW_CORNER_PREFERENCE,
+      value.0,
+    ))
+  }
+  #[cfg(not(target_os = "windows"))]
+  {
+    let _ = window;
+    match corners.as_str() {
+      "round" | "roundSmall" | "square" | "default" => Ok(false),
+      other => Err(unknown("corner style", other, EXPECTED)),
+    }
+  }
+}
+
+/// Drop shadow of the undecorated window. Windows and macOS only.
+#[tauri::command]
+pub fn set_window_shadow(window: tauri::Window, enabled: bool) -> bool {
+  if cfg!(any(target_os = "windows", target_os = "macos")) {
+    window.set_shadow(enabled).is_ok()
+  } else {
+    false
+  }
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThemeEvent {
+  theme: &'static str,
+}
+
+fn theme_name(theme: Theme) -> &'static str {
+  match theme {
+    Theme::Dark => "dark",
+    _ => "light",
+  }
+}
+
+/// Gives the native frame of `window` the light or dark look of `theme`.
+pub fn apply_theme(window: &tauri::Window, theme: Theme) {
+  #[cfg(target_os = "windows")]
+  {
+    set_dwm_attribute(
+      window,
+      DWMWA_USE_IMMERSIVE_DARK_MODE,
+      (theme == Theme::Dark) as i32,
+    );
+  }
+  #[cfg(not(target_os = "windows"))]
+  {
+    let _ = (window, theme);
+  }
+}
+
+/// Follows an OS theme change; called from the `ThemeChanged` window event.
+pub fn on_theme_changed(window: &tauri::Window, theme: Theme) {
+  apply_theme(window, theme);
+  let _ = window.emit_to(
+    window.label(),
+    "theme://changed",
+    ThemeEvent {
+      theme: theme_name(theme),
+    },
+  );
+}
+
+/// Current OS theme, `light` or `dark`, for the web UI to start from.
+#[tauri::command]
+pub fn get_system_theme(window: tauri::Window) -> Result<String, String> {
+  let theme = window.theme().map_err(|e| e.to_string())?;
+  Ok(theme_name(theme).to_string())
+}
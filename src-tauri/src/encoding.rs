@@ -0,0 +1,63 @@
+//! Hex and percent encoding, plus the HMAC the request signers build on.
+
+use std::fmt::Write as _;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Lowercase hex of `bytes`.
+pub fn hex(bytes: &[u8]) -> String {
+  bytes.iter().fold(String::new(), |mut out, b| {
+    let _ = write!(out, "{:02x}", b);
+    out
+  })
+}
+
+/// Bytes of hex `text`, or `None` when it isn't valid hex.
+pub fn unhex(text: &str) -> Option<Vec<u8>> {
+  if !text.len().is_multiple_of(2) {
+    return None;
+  }
+  (0..text.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+    .collect()
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters, as SigV4 expects.
+pub fn uri_encode(s: &str) -> String {
+  let mut out = String::with_capacity(s.len());
+  for b in s.bytes() {
+    if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+      out.push(b as char);
+    } else {
+      let _ = write!(out, "%{:02X}", b);
+    }
+  }
+  out
+}
+
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+  let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(|e| e.to_string())?;
+  mac.update(data);
+  Ok(mac.finalize().into_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn hex_round_trips() {
+    assert_eq!(hex(&[0x00, 0xab, 0xff]), "00abff");
+    assert_eq!(unhex("00ABff"), Some(vec![0x00, 0xab, 0xff]));
+    assert_eq!(unhex("abc"), None);
+    assert_eq!(unhex("zz"), None);
+  }
+
+  #[test]
+  fn only_unreserved_characters_stay_unencoded() {
+    assert_eq!(uri_encode("a-Z_0.9~"), "a-Z_0.9~");
+    assert_eq!(uri_encode("a b/c=é"), "a%20b%2Fc%3D%C3%A9");
+  }
+}
@@ -0,0 +1,504 @@
+//! Query plans as trees the frontend can draw: `EXPLAIN (FORMAT JSON)` on Postgres,
+//! `EXPLAIN FORMAT=TREE` / `EXPLAIN ANALYZE` on MySQL (JSON on servers without the tree
+//! format, such as MariaDB) and `EXPLAIN QUERY PLAN` on SQLite, parsed into one node shape.
+//!
+//! `ANALYZE` runs the statement, so it happens inside a transaction that is rolled back.
+
+use std::collections::HashMap;
+
+use sqlx::Row;
+use tauri::State;
+
+use crate::db::{self, SqlPool};
+use crate::{readonly, AppState};
+
+#[derive(serde::Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanNode {
+  /// Operation, e.g. `Seq Scan`, `Nested loop inner join`, `SEARCH`.
+  pub node_type: String,
+  pub relation: Option<String>,
+  /// Index, conditions or the engine's own wording of the step.
+  pub detail: Option<String>,
+  pub startup_cost: Option<f64>,
+  pub total_cost: Option<f64>,
+  pub estimated_rows: Option<f64>,
+  /// Measured with `ANALYZE`: times are per loop, in milliseconds.
+  pub actual_startup_ms: Option<f64>,
+  pub actual_total_ms: Option<f64>,
+  pub actual_rows: Option<f64>,
+  pub loops: Option<f64>,
+  /// The fields the engine reported for the node, under its own names.
+  pub properties: serde_json::Map<String, serde_json::Value>,
+  pub children: Vec<PlanNode>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryPlan {
+  pub root: PlanNode,
+  pub analyzed: bool,
+  pub planning_ms: Option<f64>,
+  pub execution_ms: Option<f64>,
+  /// The plan as the engine printed it.
+  pub raw: serde_json::Value,
+}
+
+fn number(value: Option<&serde_json::Value>) -> Option<f64> {
+  match value? {
+    serde_json::Value::Number(n) => n.as_f64(),
+    serde_json::Value::String(s) => s.parse().ok(),
+    _ => None,
+  }
+}
+
+fn text(value: Option<&serde_json::Value>) -> Option<String> {
+  match value? {
+    serde_json::Value::String(s) => Some(s.clone()),
+    serde_json::Value::Null => None,
+    other => Some(other.to_string()),
+  }
+}
+
+// -- Postgres ----------------------------------------------------------------------------
+
+/// Keys summarised into `detail`, in the order they are listed.
+const PG_DETAIL_KEYS: &[&str] = &[
+  "Index Cond",
+  "Hash Cond",
+  "Merge Cond",
+  "Join Filter",
+  "Recheck Cond",
+  "Filter",
+];
+
+fn pg_node(mut plan: serde_json::Map<String, serde_json::Value>) -> PlanNode {
+  let children = match plan.remove("Plans") {
+    Some(serde_json::Value::Array(plans)) => plans
+      .into_iter()
+      .filter_map(|p| match p {
+        serde_json::Value::Object(p) => Some(pg_node(p)),
+        _ => None,
+      })
+      .collect(),
+    _ => Vec::new(),
+  };
+  let mut detail: Vec<String> = Vec::new();
+  if let Some(index) = text(plan.get("Index Name")) {
+    detail.push(format!("using {}", index));
+  }
+  for key in PG_DETAIL_KEYS {
+    if let Some(condition) = text(plan.get(*key)) {
+      detail.push(format!("{}: {}", key, condition));
+    }
+  }
+  let node_type = match (text(plan.get("Join Type")), text(plan.get("Node Type"))) {
+    (Some(join), Some(node)) if join != "Inner" => format!("{} {}", node, join),
+    (_, node) => node.unwrap_or_default(),
+  };
+  PlanNode {
+    node_type,
+    relation: text(plan.get("Relation Name"))
+      .or_else(|| text(plan.get("CTE Name")))
+      .or_else(|| text(plan.get("Function Name"))),
+    detail: Some(detail.join("; ")).filter(|d| !d.is_empty()),
+    startup_cost: number(plan.get("Startup Cost")),
+    total_cost: number(plan.get("Total Cost")),
+    estimated_rows: number(plan.get("Plan Rows")),
+    actual_startup_ms: number(plan.get("Actual Startup Time")),
+    actual_total_ms: number(plan.get("Actual Total Time")),
+    actual_rows: number(plan.get("Actual Rows")),
+    loops: number(plan.get("Actual Loops")),
+    properties: plan,
+    children,
+  }
+}
+
+/// Plan of `sql` with `EXPLAIN (FORMAT JSON)`; `analyze` also runs it (rolled back) and adds
+/// the measured times, rows and buffer use.
+#[tauri::command]
+pub async fn postgres_explain(
+  state: State<'_, AppState>,
+  connection: String,
+  sql: String,
+  analyze: Option<bool>,
+) -> Result<QueryPlan, String> {
+  let SqlPool::Postgres(pool) = db::sql_pool(&state, &connection)? else {
+    return Err(format!(
+      "Connection '{}' is not a Postgres connection",
+      connection
+    ));
+  };
+  let analyze = analyze.unwrap_or(false);
+  let explain = if analyze {
+    readonly::check_statement(&state, &connection, &sql)?;
+    format!("EXPLAIN (FORMAT JSON, ANALYZE, BUFFERS) {}", sql)
+  } else {
+    format!("EXPLAIN (FORMAT JSON) {}", sql)
+  };
+  let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+  let raw: serde_json::Value = sqlx::query_scalar(&explain)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+  tx.rollback().await.map_err(|e| e.to_string())?;
+
+  let top = raw
+    .get(0)
+    .and_then(|t| t.as_object())
+    .ok_or_else(|| "Unexpected EXPLAIN output".to_string())?;
+  let root = match top.get("Plan") {
+    Some(serde_json::Value::Object(plan)) => pg_node(plan.clone()),
+    _ => return Err("Unexpected EXPLAIN output".to_string()),
+  };
+  Ok(QueryPlan {
+    root,
+    analyzed: analyze,
+    planning_ms: number(top.get("Planning Time")),
+    execution_ms: number(top.get("Execution Time")),
+    raw,
+  })
+}
+
+// -- MySQL -------------------------------------------------------------------------------
+
+/// Values of `name=value` pairs inside a `(...)` group of a tree line.
+fn tree_figures(group: &str) -> HashMap<&str, &str> {
+  group
+    .split_whitespace()
+    .filter_map(|pair| pair.split_once('='))
+    .collect()
+}
+
+/// One `-> Operation ...  (cost=.. rows=..) (actual time=a..b rows=.. loops=..)` line.
+fn tree_node(line: &str) -> PlanNode {
+  let mut node = PlanNode::default();
+  let mut description = line;
+  // The figures are the trailing parenthesised groups after two spaces
+  if let Some(i) = line.find("  (") {
+    description = &line[..i];
+    for group in line[i..]
+      .split(')')
+      .map(|g| g.trim().trim_start_matches('('))
+    {
+      if group == "never executed" {
+        node.loops = Some(0.0);
+      } else if let Some(actual) = group.strip_prefix("actual ") {
+        let figures = tree_figures(actual);
+        if let Some((startup, total)) = figures.get("time").and_then(|t| t.split_once("..")) {
+          node.actual_startup_ms = startup.parse().ok();
+          node.actual_total_ms = total.parse().ok();
+        }
+        node.actual_rows = figures.get("rows").and_then(|r| r.parse().ok());
+        node.loops = figures.get("loops").and_then(|l| l.parse().ok());
+      } else if group.starts_with("cost=") || group.starts_with("rows=") {
+        let figures = tree_figures(group);
+        match figures.get("cost").map(|c| c.split_once("..")) {
+          Some(Some((startup, total))) => {
+            node.startup_cost = startup.parse().ok();
+            node.total_cost = total.parse().ok();
+          }
+          Some(None) => node.total_cost = figures.get("cost").and_then(|c| c.parse().ok()),
+          None => {}
+        }
+        node.estimated_rows = figures.get("rows").and_then(|r| r.parse().ok());
+      }
+    }
+  }
+  if let Some((operation, rest)) = description.split_once(": ") {
+    node.node_type = operation.to_string();
+    node.detail = Some(rest.to_string());
+  } else if let Some((operation, rest)) = description.split_once(" on ") {
+    node.node_type = operation.to_string();
+    let (relation, detail) = rest.split_once(' ').unwrap_or((rest, ""));
+    node.relation = Some(relation.to_string());
+    node.detail = Some(detail.to_string()).filter(|d| !d.is_empty());
+  } else {
+    node.node_type = description.to_string();
+  }
+  node
+}
+
+fn close_last(stack: &mut Vec<(usize, PlanNode)>, roots: &mut Vec<PlanNode>) {
+  if let Some((_, done)) = stack.pop() {
+    match stack.last_mut() {
+      Some((_, parent)) => parent.children.push(done),
+      None => roots.push(done),
+    }
+  }
+}
+
+/// Parses the indented `EXPLAIN FORMAT=TREE` / `EXPLAIN ANALYZE` output. Lines that don't start
+/// with `->` continue the description of the line above.
+fn parse_tree(output: &str) -> Result<PlanNode, String> {
+  // Open nodes by depth; each is attached to its parent when a shallower line closes it
+  let mut stack: Vec<(usize, PlanNode)> = Vec::new();
+  let mut roots: Vec<PlanNode> = Vec::new();
+  for line in output.lines() {
+    let trimmed = line.trim_start();
+    let Some(step) = trimmed.strip_prefix("-> ") else {
+      if let Some((_, node)) = stack.last_mut() {
+        if !trimmed.is_empty() {
+          let detail = node.detail.get_or_insert_with(String::new);
+          detail.push(' ');
+          detail.push_str(trimmed);
+        }
+      }
+      continue;
+    };
+    let depth = line.len() - trimmed.len();
+    while stack.last().is_some_and(|(d, _)| *d >= depth) {
+      close_last(&mut stack, &mut roots);
+    }
+    stack.push((depth, tree_node(step)));
+  }
+  while !stack.is_empty() {
+    close_last(&mut stack, &mut roots);
+  }
+  match roots.len() {
+    0 => Err("Unexpected EXPLAIN output".to_string()),
+    1 => Ok(roots.remove(0)),
+    _ => Ok(PlanNode {
+      node_type: "Plan".to_string(),
+      children: roots,
+      ..PlanNode::default()
+    }),
+  }
+}
+
+/// Keys of MySQL's JSON plan that nest further steps rather than describe the current one.
+const MYSQL_NESTING_KEYS: &[&str] = &[
+  "query_block",
+  "table",
+  "nested_loop",
+  "ordering_operation",
+  "grouping_operation",
+  "duplicates_removal",
+  "windowing",
+  "materialized_from_subquery",
+  "attached_subqueries",
+  "optimized_away_subqueries",
+  "union_result",
+  "query_specifications",
+  "block-nl-join",
+  "filesort",
+  "temporary_table",
+  "read_sorted_file",
+  "subqueries",
+];
+
+/// Walks MySQL / MariaDB `FORMAT=JSON` output, a step per object under a nesting key.
+fn json_node(kind: &str, mut object: serde_json::Map<String, serde_json::Value>) -> PlanNode {
+  let mut children = Vec::new();
+  for key in MYSQL_NESTING_KEYS {
+    match object.remove(*key) {
+      Some(serde_json::Value::Object(child)) => children.push(json_node(key, child)),
+      Some(serde_json::Value::Array(items)) => {
+        for item in items {
+          if let serde_json::Value::Object(mut item) = item {
+            // Array entries wrap their step, e.g. `{"table": {...}}`
+            let inner = MYSQL_NESTING_KEYS.iter().find_map(|k| match item.get(*k) {
+              Some(serde_json::Value::Object(_)) if item.len() == 1 => Some(*k),
+              _ => None,
+            });
+            match inner.and_then(|k| item.remove(k).map(|v| (k, v))) {
+              Some((k, serde_json::Value::Object(step))) => children.push(json_node(k, step)),
+              _ => children.push(json_node(key, item)),
+            }
+          }
+        }
+      }
+      Some(other) => {
+        object.insert((*key).to_string(), other);
+      }
+      None => {}
+    }
+  }
+  let cost = object.get("cost_info").and_then(|c| c.as_object());
+  let analyzed = object.get("r_loops").is_some();
+  PlanNode {
+    node_type: match text(object.get("access_type")) {
+      Some(access) if kind == "table" => format!("table ({})", access),
+      _ => kind.to_string(),
+    },
+    relation: text(object.get("table_name")),
+    detail: text(object.get("key"))
+      .map(|key| format!("using {}", key))
+      .or_else(|| text(object.get("attached_condition"))),
+    startup_cost: None,
+    total_cost: number(cost.and_then(|c| c.get("prefix_cost").or_else(|| c.get("query_cost"))))
+      .or_else(|| number(object.get("cost"))),
+    estimated_rows: number(
+      object
+        .get("rows_produced_per_join")
+        .or_else(|| object.get("rows")),
+    ),
+    actual_startup_ms: None,
+    actual_total_ms: number(object.get("r_total_time_ms")),
+    actual_rows: if analyzed {
+      number(object.get("r_rows"))
+    } else {
+      None
+    },
+    loops: number(object.get("r_loops")),
+    properties: object,
+    children,
+  }
+}
+
+async fn mysql_text(conn: &mut sqlx::MySqlConnection, sql: &str) -> Result<String, sqlx::Error> {
+  let rows: Vec<Vec<u8>> = sqlx::query_scalar(sql).fetch_all(conn).await?;
+  Ok(
+    rows
+      .iter()
+      .map(|row| String::from_utf8_lossy(row))
+      .collect::<Vec<_>>()
+      .join("\n"),
+  )
+}
+
+/// Plan of `sql` as `EXPLAIN FORMAT=TREE` or, with `analyze`, `EXPLAIN ANALYZE` (run and
+/// rolled back). Servers without the tree format get their `FORMAT=JSON` plan instead.
+#[tauri::command]
+pub async fn mysql_explain(
+  state: State<'_, AppState>,
+  connection: String,
+  sql: String,
+  analyze: Option<bool>,
+) -> Result<QueryPlan, String> {
+  let SqlPool::MySql(pool) = db::sql_pool(&state, &connection)? else {
+    return Err(format!(
+      "Connection '{}' is not a MySQL connection",
+      connection
+    ));
+  };
+  let analyze = analyze.unwrap_or(false);
+  if analyze {
+    readonly::check_statement(&state, &connection, &sql)?;
+  }
+  let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+  let tree_sql = if analyze {
+    format!("EXPLAIN ANALYZE {}", sql)
+  } else {
+    format!("EXPLAIN FORMAT=TREE {}", sql)
+  };
+  let plan = match mysql_text(&mut tx, &tree_sql).await {
+    Ok(tree) => {
+      let root = parse_tree(&tree)?;
+      QueryPlan {
+        root,
+        analyzed: analyze,
+        planning_ms: None,
+        execution_ms: None,
+        raw: serde_json::Value::String(tree),
+      }
+    }
+    // A syntax error: the server predates the tree format (MySQL 5.7, MariaDB)
+    Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("42000") => {
+      let json_sql = if analyze {
+        format!("ANALYZE FORMAT=JSON {}", sql)
+      } else {
+        format!("EXPLAIN FORMAT=JSON {}", sql)
+      };
+      let output = mysql_text(&mut tx, &json_sql)
+        .await
+        .map_err(|e| e.to_string())?;
+      let raw: serde_json::Value = serde_json::from_str(&output).map_err(|e| e.to_string())?;
+      let root = match raw.get("query_block") {
+        Some(serde_json::Value::Object(block)) => json_node("query_block", block.clone()),
+        _ => return Err("Unexpected EXPLAIN output".to_string()),
+      };
+      QueryPlan {
+        execution_ms: root.actual_total_ms,
+        root,
+        analyzed: analyze,
+        planning_ms: None,
+        raw,
+      }
+    }
+    Err(e) => return Err(e.to_string()),
+  };
+  tx.rollback().await.map_err(|e| e.to_string())?;
+  Ok(plan)
+}
+
+// -- SQLite ------------------------------------------------------------------------------
+
+/// One `EXPLAIN QUERY PLAN` row, e.g. `SEARCH users USING INDEX idx_email (email=?)`.
+fn sqlite_node(detail: String) -> PlanNode {
+  let mut words = detail.split_whitespace();
+  let operation = words.next().unwrap_or_default();
+  let relation = match (operation, words.next()) {
+    // Older versions say `SCAN TABLE t`
+    ("SCAN" | "SEARCH", Some("TABLE")) => words.next(),
+    ("SCAN" | "SEARCH", relation) => relation,
+    _ => None,
+  };
+  PlanNode {
+    node_type: match relation {
+      Some(_) => operation.to_string(),
+      None => detail.clone(),
+    },
+    relation: relation.map(str::to_string),
+    detail: Some(detail),
+    ..PlanNode::default()
+  }
+}
+
+/// Plan of `sql` from `EXPLAIN QUERY PLAN`; SQLite reports steps only, without costs.
+#[tauri::command]
+pub async fn sqlite_explain_query_plan(
+  state: State<'_, AppState>,
+  connection: String,
+  sql: String,
+) -> Result<QueryPlan, String> {
+  let SqlPool::Sqlite(pool) = db::sql_pool(&state, &connection)? else {
+    return Err(format!(
+      "Connection '{}' is not a SQLite connection",
+      connection
+    ));
+  };
+  let rows = sqlx::query(&format!("EXPLAIN QUERY PLAN {}", sql))
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  let mut steps: Vec<(i64, i64, String)> = Vec::with_capacity(rows.len());
+  for row in &rows {
+    steps.push((
+      row.try_get("id").map_err(|e| e.to_string())?,
+      row.try_get("parent").map_err(|e| e.to_string())?,
+      row.try_get("detail").map_err(|e| e.to_string())?,
+    ));
+  }
+  let raw = serde_json::Value::Array(
+    steps
+      .iter()
+      .map(
+        |(id, parent, detail)| serde_json::json!({ "id": id, "parent": parent, "detail": detail }),
+      )
+      .collect(),
+  );
+
+  // Rows come parent first; build bottom-up so every child is complete when attached
+  let mut nodes: Vec<(i64, i64, PlanNode)> = steps
+    .into_iter()
+    .map(|(id, parent, detail)| (id, parent, sqlite_node(detail)))
+    .collect();
+  let mut root = PlanNode {
+    node_type: "QUERY PLAN".to_string(),
+    ..PlanNode::default()
+  };
+  while let Some((_, parent, node)) = nodes.pop() {
+    match nodes.iter_mut().rev().find(|(id, _, _)| *id == parent) {
+      Some((_, _, parent)) => parent.children.insert(0, node),
+      None => root.children.insert(0, node),
+    }
+  }
+  Ok(QueryPlan {
+    root,
+    analyzed: false,
+    planning_ms: None,
+    execution_ms: None,
+    raw,
+  })
+}
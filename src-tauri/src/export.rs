@@ -0,0 +1,403 @@
+//! Serializing fetched rows to export formats, whole or one row at a time.
+
+use std::io::Write;
+
+use crate::db::{JsonRow, ResultColumn};
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+  Csv,
+  Tsv,
+  Json,
+  Ndjson,
+}
+
+impl ExportFormat {
+  pub fn parse(name: &str) -> Result<Self, String> {
+    match name.to_lowercase().as_str() {
+      "csv" => Ok(ExportFormat::Csv),
+      "tsv" | "tab" => Ok(ExportFormat::Tsv),
+      "json" => Ok(ExportFormat::Json),
+      "ndjson" | "jsonl" => Ok(ExportFormat::Ndjson),
+      other => Err(format!("Unsupported export format: {}", other)),
+    }
+  }
+
+  pub fn extension(self) -> &'static str {
+    match self {
+      ExportFormat::Csv => "csv",
+      ExportFormat::Tsv => "tsv",
+      ExportFormat::Json => "json",
+      ExportFormat::Ndjson => "ndjson",
+    }
+  }
+
+  /// Field separator of the delimited formats.
+  pub fn delimiter(self) -> Option<char> {
+    match self {
+      ExportFormat::Csv => Some(','),
+      ExportFormat::Tsv => Some('\t'),
+      ExportFormat::Json | ExportFormat::Ndjson => None,
+    }
+  }
+
+  pub fn content_type(self) -> &'static str {
+    match self {
+      ExportFormat::Csv => "text/csv",
+      ExportFormat::Tsv => "text/tab-separated-values",
+      ExportFormat::Json => "application/json",
+      ExportFormat::Ndjson => "application/x-ndjson",
+    }
+  }
+}
+
+/// Compression applied while an export is written, so the uncompressed text is never held in
+/// full.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+  None,
+  Gzip,
+  Zstd,
+}
+
+impl Compression {
+  pub fn parse(name: &str) -> Result<Self, String> {
+    match name.to_lowercase().as_str() {
+      "none" | "" => Ok(Compression::None),
+      "gzip" | "gz" => Ok(Compression::Gzip),
+      "zstd" | "zst" => Ok(Compression::Zstd),
+      other => Err(format!("Unsupported compression: {}", other)),
+    }
+  }
+
+  /// `file_name` with the compression suffix appended.
+  pub fn file_name(self, file_name: &str) -> String {
+    match self {
+      Compression::None => file_name.to_string(),
+      Compression::Gzip => format!("{}.gz", file_name),
+      Compression::Zstd => format!("{}.zst", file_name),
+    }
+  }
+
+  pub fn content_type(self, format: ExportFormat) -> &'static str {
+    match self {
+      Compression::None => format.content_type(),
+      Compression::Gzip => "application/gzip",
+      Compression::Zstd => "application/zstd",
+    }
+  }
+}
+
+/// A writer that compresses everything passing through it. Call [`Encoder::finish`] to write
+/// the trailer; dropping it instead leaves a truncated stream.
+pub enum Encoder<W: Write> {
+  Plain(W),
+  Gzip(Box<flate2::write::GzEncoder<W>>),
+  Zstd(Box<zstd::stream::write::Encoder<'static, W>>),
+}
+
+impl<W: Write> Encoder<W> {
+  pub fn new(out: W, compression: Compression) -> std::io::Result<Self> {
+    Ok(match compression {
+      Compression::None => Encoder::Plain(out),
+      Compression::Gzip => Encoder::Gzip(Box::new(flate2::write::GzEncoder::new(
+        out,
+        flate2::Compression::default(),
+      ))),
+      Compression::Zstd => Encoder::Zstd(Box::new(zstd::stream::write::Encoder::new(
+        out,
+        zstd::DEFAULT_COMPRESSION_LEVEL,
+      )?)),
+    })
+  }
+
+  /// Flushes the compressed stream and returns the underlying writer.
+  pub fn finish(self) -> std::io::Result<W> {
+    match self {
+      Encoder::Plain(mut out) => out.flush().map(|()| out),
+      Encoder::Gzip(encoder) => encoder.finish(),
+      Encoder::Zstd(encoder) => encoder.finish(),
+    }
+  }
+}
+
+impl<W: Write> Write for Encoder<W> {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    match self {
+      Encoder::Plain(out) => out.write(buf),
+      Encoder::Gzip(encoder) => encoder.write(buf),
+      Encoder::Zstd(encoder) => encoder.write(buf),
+    }
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    match self {
+      Encoder::Plain(out) => out.flush(),
+      Encoder::Gzip(encoder) => encoder.flush(),
+      Encoder::Zstd(encoder) => encoder.flush(),
+    }
+  }
+}
+
+/// Text for one CSV cell: strings as-is, NULL as empty, anything else as its JSON text.
+fn cell_text(value: Option<&serde_json::Value>) -> String {
+  match value {
+    None | Some(serde_json::Value::Null) => String::new(),
+    Some(serde_json::Value::String(s)) => s.clone(),
+    Some(other) => other.to_string(),
+  }
+}
+
+/// How delimited formats separate and quote fields.
+#[derive(Clone, Copy, Debug)]
+pub struct Delimited {
+  pub delimiter: char,
+  pub quote: char,
+  pub header: bool,
+}
+
+impl Delimited {
+  /// The usual settings of `format`: its delimiter, `"` quotes and a header line.
+  pub fn of(format: ExportFormat) -> Self {
+    Delimited {
+      delimiter: format.delimiter().unwrap_or(','),
+      quote: '"',
+      header: true,
+    }
+  }
+
+  fn field(&self, text: &str) -> String {
+    if text.contains([self.delimiter, self.quote, '\n', '\r']) {
+      let doubled = format!("{}{}", self.quote, self.quote);
+      format!(
+        "{q}{}{q}",
+        text.replace(self.quote, &doubled),
+        q = self.quote
+      )
+    } else {
+      text.to_string()
+    }
+  }
+
+  fn line<'a>(
+    &self,
+    out: &mut impl Write,
+    fields: impl Iterator<Item = &'a str>,
+  ) -> std::io::Result<()> {
+    let line: Vec<String> = fields.map(|f| self.field(f)).collect();
+    writeln!(out, "{}", line.join(&self.delimiter.to_string()))
+  }
+}
+
+/// Writes rows one at a time, so results of any size are exported without being held in
+/// full: [`RowWriter::begin`] once, [`RowWriter::row`] per row, then [`RowWriter::end`].
+pub struct RowWriter {
+  format: ExportFormat,
+  delimited: Delimited,
+  columns: Vec<ResultColumn>,
+  rows: u64,
+}
+
+impl RowWriter {
+  pub fn new(format: ExportFormat, delimited: Delimited) -> Self {
+    RowWriter {
+      format,
+      delimited,
+      columns: Vec::new(),
+      rows: 0,
+    }
+  }
+
+  /// Rows written so far.
+  pub fn rows(&self) -> u64 {
+    self.rows
+  }
+
+  pub fn begin(&mut self, out: &mut impl Write, columns: &[ResultColumn]) -> std::io::Result<()> {
+    self.columns = columns.to_vec();
+    match self.format {
+      ExportFormat::Csv | ExportFormat::Tsv if self.delimited.header => self
+        .delimited
+        .line(out, columns.iter().map(|c| c.name.as_str())),
+      ExportFormat::Json => write!(out, "["),
+      _ => Ok(()),
+    }
+  }
+
+  pub fn row(&mut self, out: &mut impl Write, row: &JsonRow) -> std::io::Result<()> {
+    match self.format {
+      ExportFormat::Csv | ExportFormat::Tsv => {
+        let cells: Vec<String> = self
+          .columns
+          .iter()
+          .map(|c| cell_text(row.get(&c.name)))
+          .collect();
+        self.delimited.line(out, cells.iter().map(String::as_str))?;
+      }
+      // Laid out like `serde_json::to_writer_pretty` lays out the whole array
+      ExportFormat::Json => {
+        let pretty = serde_json::to_string_pretty(row).map_err(std::io::Error::from)?;
+        let separator = if self.rows == 0 { "" } else { "," };
+        write!(out, "{}\n  {}", separator, pretty.replace('\n', "\n  "))?;
+      }
+      ExportFormat::Ndjson => {
+        serde_json::to_writer(&mut *out, row).map_err(std::io::Error::from)?;
+        writeln!(out)?;
+      }
+    }
+    self.rows += 1;
+    Ok(())
+  }
+
+  pub fn end(&mut self, out: &mut impl Write) -> std::io::Result<()> {
+    match self.format {
+      ExportFormat::Json if self.rows == 0 => writeln!(out, "]"),
+      ExportFormat::Json => writeln!(out, "\n]"),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// Writes `rows` in `format`, with columns in result order.
+pub fn write_rows(
+  out: &mut impl Write,
+  format: ExportFormat,
+  columns: &[ResultColumn],
+  rows: &[JsonRow],
+) -> Result<(), String> {
+  let mut writer = RowWriter::new(format, Delimited::of(format));
+  writer
+    .begin(out, columns)
+    .and_then(|()| rows.iter().try_for_each(|row| writer.row(out, row)))
+    .and_then(|()| writer.end(out))
+    .map_err(|e| e.to_string())
+}
+
+/// Character encoding of exported text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextEncoding {
+  Utf8,
+  /// UTF-8 starting with a byte order mark, which Excel needs to detect it.
+  Utf8Bom,
+  Utf16Le,
+  Utf16Be,
+  /// ISO-8859-1; characters outside it are written as `?`.
+  Latin1,
+}
+
+impl TextEncoding {
+  pub fn parse(name: &str) -> Result<Self, String> {
+    match name.to_lowercase().replace('_', "-").as_str() {
+      "utf-8" | "utf8" | "" => Ok(TextEncoding::Utf8),
+      "utf-8-bom" | "utf8-bom" => Ok(TextEncoding::Utf8Bom),
+      "utf-16le" | "utf-16" => Ok(TextEncoding::Utf16Le),
+      "utf-16be" => Ok(TextEncoding::Utf16Be),
+      "latin1" | "latin-1" | "iso-8859-1" => Ok(TextEncoding::Latin1),
+      other => Err(format!("Unsupported encoding: {}", other)),
+    }
+  }
+}
+
+/// A writer taking UTF-8 text and passing it on in another encoding. Text may arrive split
+/// anywhere, even inside a character; the incomplete end waits for the next write.
+pub struct Transcoder<W: Write> {
+  out: W,
+  encoding: TextEncoding,
+  pending: Vec<u8>,
+}
+
+impl<W: Write> Transcoder<W> {
+  /// Wraps `out`, writing the byte order mark the encoding starts with.
+  pub fn new(mut out: W, encoding: TextEncoding) -> std::io::Result<Self> {
+    match encoding {
+      TextEncoding::Utf8Bom => out.write_all(&[0xEF, 0xBB, 0xBF])?,
+      TextEncoding::Utf16Le => out.write_all(&[0xFF, 0xFE])?,
+      TextEncoding::Utf16Be => out.write_all(&[0xFE, 0xFF])?,
+      TextEncoding::Utf8 | TextEncoding::Latin1 => {}
+    }
+    Ok(Transcoder {
+      out,
+      encoding,
+      pending: Vec::new(),
+    })
+  }
+
+  /// Fails on a character left incomplete, then returns the underlying writer.
+  pub fn finish(self) -> std::io::Result<W> {
+    if !self.pending.is_empty() {
+      return Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        "Text ended inside a UTF-8 character",
+      ));
+    }
+    Ok(self.out)
+  }
+
+  fn encode(&mut self, text: &str) -> std::io::Result<()> {
+    match self.encoding {
+      TextEncoding::Utf8 | TextEncoding::Utf8Bom => self.out.write_all(text.as_bytes()),
+      TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+        let bytes: Vec<u8> = text
+          .encode_utf16()
+          .flat_map(|unit| match self.encoding {
+            TextEncoding::Utf16Be => unit.to_be_bytes(),
+            _ => unit.to_le_bytes(),
+          })
+          .collect();
+        self.out.write_all(&bytes)
+      }
+      TextEncoding::Latin1 => {
+        let bytes: Vec<u8> = text
+          .chars()
+          .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+          .collect();
+        self.out.write_all(&bytes)
+      }
+    }
+  }
+}
+
+impl<W: Write> Write for Transcoder<W> {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    if matches!(self.encoding, TextEncoding::Utf8 | TextEncoding::Utf8Bom) {
+      return self.out.write_all(buf).map(|()| buf.len());
+    }
+    self.pending.extend_from_slice(buf);
+    let pending = std::mem::take(&mut self.pending);
+    let valid = match std::str::from_utf8(&pending) {
+      Ok(_) => pending.len(),
+      Err(e) if e.error_len().is_none() => e.valid_up_to(),
+      Err(e) => {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+      }
+    };
+    let (text, rest) = pending.split_at(valid);
+    self.encode(std::str::from_utf8(text).unwrap_or_default())?;
+    self.pending = rest.to_vec();
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    self.out.flush()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn zstd_round_trips() {
+    assert_eq!(Compression::parse("zst"), Ok(Compression::Zstd));
+    assert_eq!(Compression::Zstd.file_name("rows.csv"), "rows.csv.zst");
+    let mut out = Encoder::new(Vec::new(), Compression::Zstd).unwrap();
+    out.write_all(b"id,name\n1,a\n").unwrap();
+    let compressed = out.finish().unwrap();
+    assert_eq!(
+      zstd::decode_all(compressed.as_slice()).unwrap(),
+      b"id,name\n1,a\n"
+    );
+  }
+}
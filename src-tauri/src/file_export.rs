@@ -0,0 +1,381 @@
+//! Exports of a query result straight to a local file. Rows are read from the server as a
+//! stream and written as they arrive, so results of millions of rows never sit in memory.
+//! CSV and TSV on Postgres go through `COPY ... TO STDOUT` when the values need no masking or
+//! time zone conversion; they then keep Postgres' own text format.
+//!
+//! Exports run as `file_export` background tasks: progress arrives as `task-progress` events
+//! (rows written, or bytes for `COPY`) and `cancel_task` stops one. The file is written as
+//! `<path>.part` and renamed into place when complete, so a failed or cancelled export never
+//! leaves a truncated file at `path`.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use futures::{Stream, StreamExt};
+use sqlx::postgres::PgPoolCopyExt;
+use tauri::{AppHandle, Manager};
+
+use crate::db::{self, JsonRow, SqlPool};
+use crate::export::{
+  Compression, Delimited, Encoder, ExportFormat, RowWriter, TextEncoding, Transcoder,
+};
+use crate::masking::MaskingConfig;
+use crate::tasks::{self, TaskHandle, TaskSpec};
+use crate::{masking, readonly, timezone, transfer, AppState};
+
+/// Least time between two `task-progress` events of an export.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How the file is laid out; everything is optional.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FileExportOptions {
+  /// Field separator of CSV / TSV (`,` and tab by default).
+  pub delimiter: Option<char>,
+  /// Quote around fields holding the delimiter, quotes or line breaks (`"` by default).
+  pub quote: Option<char>,
+  /// Start CSV / TSV with the column names (on by default).
+  pub header: Option<bool>,
+  /// `utf-8` (default), `utf-8-bom`, `utf-16le`, `utf-16be` or `latin1`.
+  pub encoding: Option<String>,
+  /// `none` (default), `gzip` or `zstd`.
+  pub compression: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileExportSummary {
+  pub path: String,
+  /// Rows written; not counted when Postgres `COPY` wrote the file.
+  pub rows: Option<u64>,
+  /// Size of the file written.
+  pub bytes: u64,
+  pub used_copy: bool,
+  pub elapsed_ms: u64,
+}
+
+struct Settings {
+  format: ExportFormat,
+  delimited: Delimited,
+  encoding: TextEncoding,
+  compression: Compression,
+}
+
+fn settings(format: &str, options: &FileExportOptions) -> Result<Settings, String> {
+  let format = ExportFormat::parse(format)?;
+  let mut delimited = Delimited::of(format);
+  if let Some(delimiter) = options.delimiter {
+    delimited.delimiter = delimiter;
+  }
+  if let Some(quote) = options.quote {
+    delimited.quote = quote;
+  }
+  if let Some(header) = options.header {
+    delimited.header = header;
+  }
+  if delimited.delimiter == delimited.quote
+    || [delimited.delimiter, delimited.quote]
+      .iter()
+      .any(|c| matches!(c, '\n' | '\r'))
+  {
+    return Err("The delimiter and quote must differ and can't be line breaks".to_string());
+  }
+  Ok(Settings {
+    format,
+    delimited,
+    encoding: TextEncoding::parse(options.encoding.as_deref().unwrap_or(""))?,
+    compression: Compression::parse(options.compression.as_deref().unwrap_or(""))?,
+  })
+}
+
+/// The file being written, removed again unless [`PartFile::commit`] moves it into place.
+pub(crate) struct PartFile {
+  path: PathBuf,
+  committed: bool,
+}
+
+impl PartFile {
+  pub(crate) fn create(target: &Path) -> Result<(Self, File), String> {
+    let mut name = target.as_os_str().to_owned();
+    name.push(".part");
+    let path = PathBuf::from(name);
+    let file =
+      File::create(&path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+    Ok((
+      PartFile {
+        path,
+        committed: false,
+      },
+      file,
+    ))
+  }
+
+  pub(crate) fn commit(mut self, target: &Path) -> Result<(), String> {
+    std::fs::rename(&self.path, target)
+      .map_err(|e| format!("Cannot write {}: {}", target.display(), e))?;
+    self.committed = true;
+    Ok(())
+  }
+}
+
+impl Drop for PartFile {
+  fn drop(&mut self) {
+    if !self.committed {
+      let _ = std::fs::remove_file(&self.path);
+    }
+  }
+}
+
+type Output = Transcoder<Encoder<BufWriter<File>>>;
+
+/// Reports progress at most every [`PROGRESS_INTERVAL`].
+pub(crate) struct Progress<'a> {
+  task: &'a TaskHandle,
+  last: Instant,
+}
+
+impl<'a> Progress<'a> {
+  pub(crate) fn new(task: &'a TaskHandle) -> Self {
+    Progress {
+      task,
+      last: Instant::now(),
+    }
+  }
+
+  pub(crate) fn report(&mut self, done: u64, message: &str) {
+    if self.last.elapsed() >= PROGRESS_INTERVAL {
+      self.task.progress(done, None, Some(message));
+      self.last = Instant::now();
+    }
+  }
+}
+
+pub(crate) fn io_error(e: std::io::Error) -> String {
+  format!("Writing the export failed: {}", e)
+}
+
+/// Writes every row of `rows`, taking the columns from the first one.
+async fn write_stream<R: sqlx::Row>(
+  mut rows: impl Stream<Item = Result<R, sqlx::Error>> + Unpin,
+  to_json: impl Fn(&R) -> JsonRow,
+  out: &mut Output,
+  writer: &mut RowWriter,
+  mask: Option<&MaskingConfig>,
+  progress: &mut Progress<'_>,
+) -> Result<(), String> {
+  let mut begun = false;
+  while let Some(row) = rows.next().await {
+    let row = row.map_err(|e| e.to_string())?;
+    if !begun {
+      let columns = db::result_columns(std::slice::from_ref(&row));
+      writer.begin(out, &columns).map_err(io_error)?;
+      begun = true;
+    }
+    let mut json = to_json(&row);
+    if let Some(mask) = mask {
+      mask.apply(&mut json);
+    }
+    writer.row(out, &json).map_err(io_error)?;
+    progress.report(writer.rows(), "Writing rows");
+  }
+  if !begun {
+    writer.begin(out, &[]).map_err(io_error)?;
+  }
+  writer.end(out).map_err(io_error)
+}
+
+fn copy_literal(c: char) -> String {
+  if c == '\'' {
+    "''''".to_string()
+  } else {
+    format!("'{}'", c)
+  }
+}
+
+/// Streams `sql` through `COPY ... TO STDOUT` into `out`, returning the bytes copied, or
+/// `None` when Postgres won't run the statement under `COPY` (it isn't a plain query).
+async fn copy_out(
+  pool: &sqlx::PgPool,
+  sql: &str,
+  delimited: Delimited,
+  out: &mut Output,
+  progress: &mut Progress<'_>,
+) -> Result<Option<u64>, String> {
+  let statement = format!(
+    "COPY ({}) TO STDOUT WITH (FORMAT csv, HEADER {}, DELIMITER {}, QUOTE {})",
+    sql.trim().trim_end_matches(';'),
+    delimited.header,
+    copy_literal(delimited.delimiter),
+    copy_literal(delimited.quote)
+  );
+  let mut chunks = match pool.copy_out_raw(&statement).await {
+    Ok(chunks) => chunks,
+    Err(e) => {
+      tracing::debug!("COPY refused, exporting row by row: {}", e);
+      return Ok(None);
+    }
+  };
+  let mut bytes = 0u64;
+  while let Some(chunk) = chunks.next().await {
+    let chunk = chunk.map_err(|e| e.to_string())?;
+    out.write_all(&chunk).map_err(io_error)?;
+    bytes += chunk.len() as u64;
+    progress.report(bytes, "Copying");
+  }
+  Ok(Some(bytes))
+}
+
+/// Carries out an export task: streams the result of `sql` on `connection` into `path`.
+pub async fn write_file(
+  task: &TaskHandle,
+  connection: String,
+  sql: String,
+  format: String,
+  path: String,
+  options: FileExportOptions,
+) -> Result<FileExportSummary, String> {
+  let started = Instant::now();
+  let state = task.app.state::<AppState>();
+  let settings = settings(&format, &options)?;
+  readonly::check_statement(&state, &connection, &sql)?;
+  let pool = db::sql_pool(&state, &connection)?;
+  let tz = timezone::display_zone(&state, &connection);
+  let mask = masking::active(&state, &connection);
+
+  let target = PathBuf::from(&path);
+  let (part, file) = PartFile::create(&target)?;
+  let mut out = Transcoder::new(
+    Encoder::new(BufWriter::new(file), settings.compression).map_err(io_error)?,
+    settings.encoding,
+  )
+  .map_err(io_error)?;
+  let mut progress = Progress::new(task);
+  task.progress(0, None, Some("Reading rows"));
+
+  // COPY needs single-byte delimiter and quote characters
+  let copyable = settings.format.delimiter().is_some()
+    && settings.delimited.delimiter.is_ascii()
+    && settings.delimited.quote.is_ascii()
+    && mask.is_none()
+    && tz.is_none();
+  let copied = match &pool {
+    SqlPool::Postgres(pg) if copyable => {
+      copy_out(pg, &sql, settings.delimited, &mut out, &mut progress).await?
+    }
+    _ => None,
+  };
+  let mut writer = RowWriter::new(settings.format, settings.delimited);
+  if copied.is_none() {
+    match &pool {
+      SqlPool::MySql(pool) => {
+        let rows = sqlx::query(&sql).fetch(pool);
+        let to_json = |row: &_| db::mysql_row_to_json(row, tz.as_ref());
+        write_stream(
+          rows,
+          to_json,
+          &mut out,
+          &mut writer,
+          mask.as_ref(),
+          &mut progress,
+        )
+        .await?;
+      }
+      SqlPool::Postgres(pool) => {
+        let rows = sqlx::query(&sql).fetch(pool);
+        let to_json = |row: &_| db::pg_row_to_json(row, tz.as_ref());
+        write_stream(
+          rows,
+          to_json,
+          &mut out,
+          &mut writer,
+          mask.as_ref(),
+          &mut progress,
+        )
+        .await?;
+      }
+      SqlPool::Sqlite(pool) => {
+        let rows = sqlx::query(&sql).fetch(pool);
+        write_stream(
+          rows,
+          db::sqlite_row_to_json,
+          &mut out,
+          &mut writer,
+          mask.as_ref(),
+          &mut progress,
+        )
+        .await?;
+      }
+    }
+  }
+
+  let file = out
+    .finish()
+    .and_then(Encoder::finish)
+    .and_then(|buffered| {
+      buffered
+        .into_inner()
+        .map_err(std::io::IntoInnerError::into_error)
+    })
+    .map_err(io_error)?;
+  file.sync_all().map_err(io_error)?;
+  let bytes = file.metadata().map_or(0, |m| m.len());
+  drop(file);
+  part.commit(&target)?;
+
+  let rows = copied.is_none().then(|| writer.rows());
+  transfer::record(
+    &state,
+    &connection,
+    transfer::Category::Export,
+    usize::try_from(rows.unwrap_or(0)).unwrap_or(usize::MAX),
+    usize::try_from(copied.unwrap_or(bytes)).unwrap_or(usize::MAX),
+  );
+  Ok(FileExportSummary {
+    path,
+    rows,
+    bytes,
+    used_copy: copied.is_some(),
+    elapsed_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+  })
+}
+
+/// Fails unless the folder `path` goes into exists, so a task isn't started only to fail.
+pub(crate) fn check_folder(path: &str) -> Result<(), String> {
+  match Path::new(path).parent() {
+    Some(dir) if !dir.as_os_str().is_empty() && !dir.is_dir() => {
+      Err(format!("Folder {} does not exist", dir.display()))
+    }
+    _ => Ok(()),
+  }
+}
+
+/// Exports the result of `sql` to the file at `path` as `csv`, `tsv`, `json` or `ndjson` in
+/// the background and returns the task id; the task's result is a [`FileExportSummary`].
+#[tauri::command]
+pub async fn export_query_result(
+  app: AppHandle,
+  connection: String,
+  sql: String,
+  format: String,
+  path: String,
+  options: Option<FileExportOptions>,
+) -> Result<String, String> {
+  let options = options.unwrap_or_default();
+  settings(&format, &options)?;
+  let state = app.state::<AppState>();
+  readonly::check_statement(&state, &connection, &sql)?;
+  db::sql_pool(&state, &connection)?;
+  check_folder(&path)?;
+  Ok(tasks::start(
+    &app,
+    TaskSpec::FileExport {
+      connection,
+      sql,
+      format,
+      path,
+      options,
+    },
+  ))
+}
@@ -0,0 +1,244 @@
+//! Connection health checks. A background task pings every open connection (`SELECT 1` for
+//! SQL, `PING` for Redis, `ping` for MongoDB) and keeps the latest latency and up/down state
+//! for the sidebar's status indicators. A failed check of a MySQL, Postgres or Redis
+//! connection hands it to [`reconnect`].
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::{reconnect, store, AppState};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionHealth {
+  pub connection_id: String,
+  pub engine: &'static str,
+  pub up: bool,
+  /// Round trip of the last successful check.
+  pub latency_ms: Option<u64>,
+  pub checked_at: i64,
+  pub error: Option<String>,
+}
+
+pub enum Probe {
+  Up(Duration),
+  Down(String),
+  /// No connection is open under the id (any more).
+  Gone,
+}
+
+async fn ping_engine(state: &AppState, id: &str, engine: &str) -> Option<Result<(), String>> {
+  let outcome = match engine {
+    "mysql" => {
+      let pool = state
+        .connections
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .mysql
+        .get(id)
+        .cloned()?;
+      tokio::time::timeout(PING_TIMEOUT, sqlx::query("SELECT 1").execute(&pool))
+        .await
+        .map(|r| r.map(|_| ()).map_err(|e| e.to_string()))
+    }
+    "postgres" => {
+      let pool = state
+        .connections
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .postgres
+        .get(id)
+        .cloned()?;
+      tokio::time::timeout(PING_TIMEOUT, sqlx::query("SELECT 1").execute(&pool))
+        .await
+        .map(|r| r.map(|_| ()).map_err(|e| e.to_string()))
+    }
+    "sqlite" => {
+      let pool = state
+        .connections
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .sqlite
+        .get(id)
+        .cloned()?;
+      tokio::time::timeout(PING_TIMEOUT, sqlx::query("SELECT 1").execute(&pool))
+        .await
+        .map(|r| r.map(|_| ()).map_err(|e| e.to_string()))
+    }
+    "redis" => {
+      let client = state
+        .connections
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .redis
+        .get(id)
+        .cloned()?;
+      tokio::time::timeout(PING_TIMEOUT, async move {
+        let mut con = client
+          .get_multiplexed_async_connection()
+          .await
+          .map_err(|e| e.to_string())?;
+        let _: () = redis::cmd("PING")
+          .query_async(&mut con)
+          .await
+          .map_err(|e| e.to_string())?;
+        Ok(())
+      })
+      .await
+    }
+    "mongodb" => {
+      let client = state
+        .connections
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .mongodb
+        .get(id)
+        .cloned()?;
+      tokio::time::timeout(
+        PING_TIMEOUT,
+        client
+          .database("admin")
+          .run_command(mongodb::bson::doc! { "ping": 1 }),
+      )
+      .await
+      .map(|r| r.map(|_| ()).map_err(|e| e.to_string()))
+    }
+    _ => return None,
+  };
+  Some(outcome.unwrap_or_else(|_| Err("Ping timed out".to_string())))
+}
+
+/// Pings connection `id` once.
+pub async fn probe(state: &AppState, id: &str) -> Probe {
+  let Some(engine) = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .engine_of(id)
+  else {
+    return Probe::Gone;
+  };
+  let started = Instant::now();
+  match ping_engine(state, id, engine).await {
+    Some(Ok(())) => Probe::Up(started.elapsed()),
+    Some(Err(e)) => Probe::Down(e),
+    None => Probe::Gone,
+  }
+}
+
+/// Probes `id` and records the outcome; returns the recorded health unless the connection
+/// is gone.
+pub async fn check(state: &AppState, id: &str) -> Option<ConnectionHealth> {
+  let engine = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .engine_of(id)?;
+  let (up, latency_ms, error) = match probe(state, id).await {
+    Probe::Up(latency) => (
+      true,
+      Some(u64::try_from(latency.as_millis()).unwrap_or(u64::MAX)),
+      None,
+    ),
+    Probe::Down(e) => (false, None, Some(e)),
+    Probe::Gone => {
+      state
+        .health
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(id);
+      return None;
+    }
+  };
+  let health = ConnectionHealth {
+    connection_id: id.to_string(),
+    engine,
+    up,
+    latency_ms,
+    checked_at: store::now_ms(),
+    error,
+  };
+  state
+    .health
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(id.to_string(), health.clone());
+  Some(health)
+}
+
+fn open_ids(state: &AppState) -> Vec<String> {
+  let connections = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  connections
+    .mysql
+    .keys()
+    .chain(connections.postgres.keys())
+    .chain(connections.sqlite.keys())
+    .chain(connections.redis.keys())
+    .chain(connections.mongodb.keys())
+    .cloned()
+    .collect()
+}
+
+/// Runs the periodic checks for the lifetime of the app.
+pub async fn monitor(app: AppHandle) {
+  let state = app.state::<AppState>();
+  let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+  ticker.tick().await;
+  loop {
+    ticker.tick().await;
+    let ids = open_ids(&state);
+    state
+      .health
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .retain(|id, _| ids.contains(id));
+    // Connections being recovered are probed by their recovery task
+    let ids: Vec<String> = ids
+      .into_iter()
+      .filter(|id| !reconnect::is_recovering(&state, id))
+      .collect();
+    let checks = futures::future::join_all(ids.iter().map(|id| check(&state, id))).await;
+    for health in checks.into_iter().flatten() {
+      if !health.up && matches!(health.engine, "mysql" | "postgres" | "redis") {
+        reconnect::recover(&app, &health.connection_id, health.engine, health.error);
+      }
+    }
+  }
+}
+
+/// Latest health of every open connection (or just `connection_id`); connections that
+/// haven't been checked yet are checked now.
+#[tauri::command]
+pub async fn get_connection_status(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<Vec<ConnectionHealth>, String> {
+  let ids: Vec<String> = open_ids(&state)
+    .into_iter()
+    .filter(|id| connection_id.as_ref().is_none_or(|wanted| wanted == id))
+    .collect();
+  let known: HashMap<String, ConnectionHealth> = state
+    .health
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .clone();
+  let mut statuses = Vec::with_capacity(ids.len());
+  for id in ids {
+    let health = match known.get(&id) {
+      Some(health) => Some(health.clone()),
+      None => check(&state, &id).await,
+    };
+    statuses.extend(health);
+  }
+  statuses.sort_by(|a, b| a.connection_id.cmp(&b.connection_id));
+  Ok(statuses)
+}
@@ -0,0 +1,194 @@
+//! History of the statements run through the raw executors, kept in the app store so it
+//! survives restarts: what ran where, how long it took, how many rows it touched and
+//! whether it failed.
+
+use std::time::Duration;
+
+use sqlx::SqlitePool;
+use tauri::State;
+
+use crate::{store, usage, workspaces, AppState};
+
+/// Entries kept across all connections; older ones are pruned.
+const MAX_ENTRIES: i64 = 10_000;
+const DEFAULT_LIMIT: i64 = 100;
+
+#[derive(serde::Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+  pub id: i64,
+  pub workspace: String,
+  pub connection_id: String,
+  pub statement: String,
+  pub duration_ms: i64,
+  /// Rows returned, or affected for statements that don't return any; `None` on failure.
+  pub rows: Option<i64>,
+  pub success: bool,
+  pub error: Option<String>,
+  pub executed_at: i64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+  /// Newest first.
+  pub entries: Vec<HistoryEntry>,
+  /// Entries matching the filter across all pages.
+  pub total: i64,
+  pub limit: i64,
+  pub offset: i64,
+}
+
+/// Records a statement run on `connection_id`, with its row count or error. Failures to
+/// record are logged, never returned.
+pub async fn record(
+  state: &AppState,
+  connection_id: &str,
+  statement: &str,
+  elapsed: Duration,
+  outcome: Result<u64, &str>,
+) {
+  usage::observe(state, "query", elapsed, outcome.is_ok());
+  let Ok(pool) = store::pool(state) else {
+    return;
+  };
+  let (rows, error) = match outcome {
+    Ok(rows) => (i64::try_from(rows).ok(), None),
+    Err(e) => (None, Some(e)),
+  };
+  let recorded: Result<(), sqlx::Error> = async {
+    let inserted = sqlx::query(
+      "INSERT INTO query_history (workspace, connection_id, statement, duration_ms, rows, \
+       error, executed_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(workspaces::current(state))
+    .bind(connection_id)
+    .bind(statement)
+    .bind(i64::try_from(elapsed.as_millis()).unwrap_or(i64::MAX))
+    .bind(rows)
+    .bind(error)
+    .bind(store::now_ms())
+    .execute(&pool)
+    .await?;
+    sqlx::query("DELETE FROM query_history WHERE id <= ?")
+      .bind(inserted.last_insert_rowid() - MAX_ENTRIES)
+      .execute(&pool)
+      .await?;
+    Ok(())
+  }
+  .await;
+  if let Err(e) = recorded {
+    tracing::warn!("Failed to record query history: {}", e);
+  }
+}
+
+/// Page of the active workspace's history, optionally narrowed to a connection and to
+/// statements containing `contains` (case-insensitive).
+async fn page(
+  pool: &SqlitePool,
+  workspace: &str,
+  connection_id: Option<&str>,
+  contains: Option<&str>,
+  limit: Option<i64>,
+  offset: Option<i64>,
+) -> Result<HistoryPage, String> {
+  let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, 1000);
+  let offset = offset.unwrap_or(0).max(0);
+  let contains = contains.filter(|c| !c.is_empty()).map(|c| {
+    format!(
+      "%{}%",
+      c.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+    )
+  });
+  let filter = "WHERE workspace = ?1 AND (?2 IS NULL OR connection_id = ?2) \
+                AND (?3 IS NULL OR statement LIKE ?3 ESCAPE '\\')";
+  let (total,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM query_history {}", filter))
+    .bind(workspace)
+    .bind(connection_id)
+    .bind(&contains)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  let entries = sqlx::query_as(&format!(
+    "SELECT id, workspace, connection_id, statement, duration_ms, rows, \
+     error IS NULL AS success, error, executed_at FROM query_history {} \
+     ORDER BY id DESC LIMIT ?4 OFFSET ?5",
+    filter
+  ))
+  .bind(workspace)
+  .bind(connection_id)
+  .bind(&contains)
+  .bind(limit)
+  .bind(offset)
+  .fetch_all(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(HistoryPage {
+    entries,
+    total,
+    limit,
+    offset,
+  })
+}
+
+/// Statements run in the active workspace (on `connection_id` only, if given), newest first.
+#[tauri::command]
+pub async fn get_query_history(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+  limit: Option<i64>,
+  offset: Option<i64>,
+) -> Result<HistoryPage, String> {
+  let pool = store::pool(&state)?;
+  page(
+    &pool,
+    &workspaces::current(&state),
+    connection_id.as_deref(),
+    None,
+    limit,
+    offset,
+  )
+  .await
+}
+
+/// Like [`get_query_history`], keeping only statements that contain `text`.
+#[tauri::command]
+pub async fn search_query_history(
+  state: State<'_, AppState>,
+  text: String,
+  connection_id: Option<String>,
+  limit: Option<i64>,
+  offset: Option<i64>,
+) -> Result<HistoryPage, String> {
+  let pool = store::pool(&state)?;
+  page(
+    &pool,
+    &workspaces::current(&state),
+    connection_id.as_deref(),
+    Some(&text),
+    limit,
+    offset,
+  )
+  .await
+}
+
+/// Deletes the active workspace's history (of `connection_id` only, if given). Returns the
+/// number of entries deleted.
+#[tauri::command]
+pub async fn clear_query_history(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<u64, String> {
+  let pool = store::pool(&state)?;
+  let result = sqlx::query(
+    "DELETE FROM query_history WHERE workspace = ?1 AND (?2 IS NULL OR connection_id = ?2)",
+  )
+  .bind(workspaces::current(&state))
+  .bind(connection_id)
+  .execute(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(result.rows_affected())
+}
@@ -0,0 +1,237 @@
+//! Dialect-aware identifier handling: when a name has to be quoted to keep its meaning, and
+//! how a name typed by the user maps onto the names the catalog actually holds.
+//!
+//! - Postgres folds unquoted names to lower case, so `MyTable` must be quoted to reach a
+//!   table created as `"MyTable"`.
+//! - MySQL keeps the case of unquoted names; whether table names are case-sensitive depends
+//!   on the server's file system (`lower_case_table_names`).
+//! - SQLite matches names case-insensitively, quoted or not.
+//!
+//! Commands that put a table or column name from the frontend into SQL go through
+//! [`quote_mysql`], [`quote_pg`] or [`quote_sqlite`]. They reject names no server would hold
+//! and double the quote character, so a name can never end the quoted identifier early.
+
+use sqlparser::keywords::ALL_KEYWORDS;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+  MySql,
+  Postgres,
+  Sqlite,
+}
+
+/// Always-quoted form of `name`.
+pub fn quote(dialect: Dialect, name: &str) -> String {
+  match dialect {
+    Dialect::MySql => format!("`{}`", name.replace('`', "``")),
+    Dialect::Postgres | Dialect::Sqlite => format!("\"{}\"", name.replace('"', "\"\"")),
+  }
+}
+
+/// Longest name MySQL accepts, in characters.
+const MYSQL_MAX_CHARS: usize = 64;
+/// Longest name Postgres keeps, in bytes; it cuts longer ones short, which could reach a
+/// different object.
+const PG_MAX_BYTES: usize = 63;
+
+/// Checks that `name` is one the dialect can hold: not empty, without NUL characters and
+/// within its length limit (MySQL also refuses trailing spaces).
+pub fn validate(dialect: Dialect, name: &str) -> Result<(), String> {
+  let invalid = |why: &str| Err(format!("Invalid identifier {:?}: {}", name, why));
+  if name.is_empty() {
+    return invalid("empty");
+  }
+  if name.contains('\0') {
+    return invalid("contains a NUL character");
+  }
+  match dialect {
+    Dialect::MySql if name.chars().count() > MYSQL_MAX_CHARS => {
+      invalid("longer than 64 characters")
+    }
+    Dialect::MySql if name.ends_with(' ') => invalid("ends with a space"),
+    Dialect::Postgres if name.len() > PG_MAX_BYTES => invalid("longer than 63 bytes"),
+    _ => Ok(()),
+  }
+}
+
+/// `name` validated and quoted with backticks for MySQL.
+pub fn quote_mysql(name: &str) -> Result<String, String> {
+  validate(Dialect::MySql, name)?;
+  Ok(quote(Dialect::MySql, name))
+}
+
+/// `name` validated and double-quoted for Postgres.
+pub fn quote_pg(name: &str) -> Result<String, String> {
+  validate(Dialect::Postgres, name)?;
+  Ok(quote(Dialect::Postgres, name))
+}
+
+/// `name` validated and double-quoted for SQLite.
+pub fn quote_sqlite(name: &str) -> Result<String, String> {
+  validate(Dialect::Sqlite, name)?;
+  Ok(quote(Dialect::Sqlite, name))
+}
+
+fn is_keyword(name: &str) -> bool {
+  ALL_KEYWORDS
+    .binary_search(&name.to_ascii_uppercase().as_str())
+    .is_ok()
+}
+
+/// Whether `name` written bare would fail to parse or refer to a different object.
+pub fn needs_quoting(dialect: Dialect, name: &str) -> bool {
+  let mut chars = name.chars();
+  let Some(first) = chars.next() else {
+    return true;
+  };
+  // `$` inside bare names is a MySQL/Postgres extension; SQLite gets the portable set
+  let dollar_ok = dialect != Dialect::Sqlite;
+  let plain = (first.is_ascii_alphabetic() || first == '_')
+    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || (c == '$' && dollar_ok));
+  if !plain || is_keyword(name) {
+    return true;
+  }
+  match dialect {
+    Dialect::Postgres => name.chars().any(|c| c.is_ascii_uppercase()),
+    Dialect::MySql | Dialect::Sqlite => false,
+  }
+}
+
+/// `name` as it should appear in generated SQL: bare when that is unambiguous, quoted
+/// otherwise.
+pub fn render(dialect: Dialect, name: &str) -> String {
+  if needs_quoting(dialect, name) {
+    quote(dialect, name)
+  } else {
+    name.to_string()
+  }
+}
+
+/// Finds the catalog name a user-typed `name` refers to: an exact match first, then the
+/// dialect's own folding (lower case for Postgres, case-insensitive for SQLite), then a
+/// case-insensitive match as long as it is unambiguous.
+pub fn resolve<'a>(dialect: Dialect, name: &str, catalog: &'a [String]) -> Result<&'a str, String> {
+  if let Some(exact) = catalog.iter().find(|c| *c == name) {
+    return Ok(exact);
+  }
+  if dialect == Dialect::Postgres {
+    let folded = name.to_lowercase();
+    if let Some(found) = catalog.iter().find(|c| **c == folded) {
+      return Ok(found);
+    }
+  }
+  let matches: Vec<&String> = catalog
+    .iter()
+    .filter(|c| c.eq_ignore_ascii_case(name))
+    .collect();
+  match matches.as_slice() {
+    [only] => Ok(only),
+    [] => Err(format!("Unknown identifier: {}", name)),
+    _ => Err(format!(
+      "Ambiguous identifier '{}': matches {}",
+      name,
+      matches
+        .iter()
+        .map(|m| m.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+    )),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn catalog(names: &[&str]) -> Vec<String> {
+    names.iter().map(ToString::to_string).collect()
+  }
+
+  #[test]
+  fn postgres_quotes_mixed_case_only() {
+    assert_eq!(render(Dialect::Postgres, "mytable"), "mytable");
+    assert_eq!(render(Dialect::Postgres, "MyTable"), "\"MyTable\"");
+    assert_eq!(render(Dialect::Postgres, "my_table2"), "my_table2");
+  }
+
+  #[test]
+  fn mysql_and_sqlite_keep_mixed_case_bare() {
+    assert_eq!(render(Dialect::MySql, "MyTable"), "MyTable");
+    assert_eq!(render(Dialect::Sqlite, "MyTable"), "MyTable");
+  }
+
+  #[test]
+  fn keywords_and_odd_names_are_quoted() {
+    assert_eq!(render(Dialect::MySql, "order"), "`order`");
+    assert_eq!(render(Dialect::Postgres, "user"), "\"user\"");
+    assert_eq!(render(Dialect::Sqlite, "my table"), "\"my table\"");
+    assert_eq!(render(Dialect::Postgres, "1st"), "\"1st\"");
+    assert_eq!(render(Dialect::MySql, "we`ird"), "`we``ird`");
+    assert_eq!(render(Dialect::Postgres, ""), "\"\"");
+    assert_eq!(render(Dialect::MySql, "price$"), "price$");
+    assert_eq!(render(Dialect::Sqlite, "price$"), "\"price$\"");
+  }
+
+  #[test]
+  fn exact_match_wins() {
+    let names = catalog(&["MyTable", "mytable"]);
+    for dialect in [Dialect::MySql, Dialect::Postgres, Dialect::Sqlite] {
+      assert_eq!(resolve(dialect, "MyTable", &names), Ok("MyTable"));
+      assert_eq!(resolve(dialect, "mytable", &names), Ok("mytable"));
+    }
+  }
+
+  #[test]
+  fn postgres_folds_unquoted_input_to_lower_case() {
+    let names = catalog(&["mytable", "MYTABLE"]);
+    assert_eq!(resolve(Dialect::Postgres, "MyTable", &names), Ok("mytable"));
+  }
+
+  #[test]
+  fn case_insensitive_fallback_when_unambiguous() {
+    let names = catalog(&["MyTable", "other"]);
+    assert_eq!(resolve(Dialect::Postgres, "mytable", &names), Ok("MyTable"));
+    assert_eq!(resolve(Dialect::MySql, "MYTABLE", &names), Ok("MyTable"));
+    assert_eq!(resolve(Dialect::Sqlite, "mytable", &names), Ok("MyTable"));
+  }
+
+  #[test]
+  fn hostile_names_stay_inside_the_quotes() {
+    assert_eq!(
+      quote_mysql("x` ; DROP TABLE users; --").unwrap(),
+      "`x`` ; DROP TABLE users; --`"
+    );
+    assert_eq!(
+      quote_pg("x\"; DROP TABLE users; --").unwrap(),
+      "\"x\"\"; DROP TABLE users; --\""
+    );
+    assert_eq!(quote_sqlite("a\"\"b\"").unwrap(), "\"a\"\"\"\"b\"\"\"");
+    // The other dialect's quote needs no escaping
+    assert_eq!(quote_mysql("a\"b").unwrap(), "`a\"b`");
+    assert_eq!(quote_pg("a`b").unwrap(), "\"a`b\"");
+    assert_eq!(quote_sqlite("it's").unwrap(), "\"it's\"");
+  }
+
+  #[test]
+  fn names_no_server_holds_are_rejected() {
+    assert!(quote_mysql("").is_err());
+    assert!(quote_pg("").is_err());
+    assert!(quote_sqlite("").is_err());
+    assert!(quote_pg("users\0; DROP TABLE users").is_err());
+    assert!(quote_sqlite("a\0b").is_err());
+    assert!(quote_mysql("trailing ").is_err());
+    assert!(quote_mysql(&"m".repeat(64)).is_ok());
+    assert!(quote_mysql(&"m".repeat(65)).is_err());
+    assert!(quote_pg(&"p".repeat(63)).is_ok());
+    // Postgres counts bytes: 32 two-byte characters are too many
+    assert!(quote_pg(&"é".repeat(32)).is_err());
+    assert!(quote_sqlite(&"s".repeat(500)).is_ok());
+  }
+
+  #[test]
+  fn ambiguous_or_unknown_names_are_errors() {
+    let names = catalog(&["MyTable", "MYTABLE"]);
+    assert!(resolve(Dialect::MySql, "mytable", &names).is_err());
+    assert!(resolve(Dialect::Sqlite, "missing", &names).is_err());
+  }
+}
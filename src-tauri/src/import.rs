@@ -0,0 +1,666 @@
+//! Loading CSV / JSON / NDJSON files into a table, the counterpart of [`export`]. Source
+//! fields are mapped onto columns (by name, or with a mapping saved as a preset for the
+//! table), and every row is validated against the column types, lengths and NOT NULL
+//! constraints before anything is written. A dry run stops after validation; a real import
+//! writes nothing unless every row is valid, and then inserts all rows in one transaction.
+//!
+//! [`export`]: crate::export
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use tauri::State;
+
+use crate::db::{self, SqlPool};
+use crate::export::ExportFormat;
+use crate::schema::{self, ColumnInfo};
+use crate::{audit, readonly, store, workspaces, AppState};
+
+const DEFAULT_MAX_ERRORS: usize = 100;
+const MAX_ERRORS_LIMIT: usize = 10_000;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldMapping {
+  /// CSV header or JSON key.
+  pub source: String,
+  pub column: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreset {
+  pub table: String,
+  pub name: String,
+  pub mapping: Vec<FieldMapping>,
+  pub updated_at: i64,
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RowError {
+  /// Line of the file the record starts on; `None` for records of a JSON array.
+  pub line: Option<usize>,
+  /// 1-based record number, header excluded.
+  pub record: usize,
+  pub column: Option<String>,
+  pub value: Option<String>,
+  pub message: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+  pub dry_run: bool,
+  pub rows_read: usize,
+  pub rows_valid: usize,
+  pub rows_written: u64,
+  /// Total errors found; only the first ones are listed in `errors`.
+  pub error_count: usize,
+  pub errors: Vec<RowError>,
+  /// Source fields no column was mapped to; their values are ignored.
+  pub unmapped_fields: Vec<String>,
+}
+
+/// One record of the source file. `None` values are NULL (empty CSV cells, JSON `null`).
+struct Record {
+  line: Option<usize>,
+  fields: HashMap<String, Option<String>>,
+}
+
+struct Source {
+  /// Field names in file order.
+  fields: Vec<String>,
+  records: Vec<Record>,
+  /// Records that couldn't be read at all.
+  errors: Vec<RowError>,
+}
+
+/// Splits CSV text into records, each with the line it starts on. Quoted fields may contain
+/// delimiters, doubled quotes and line breaks.
+fn csv_records(text: &str, delimiter: char) -> Vec<(usize, Vec<String>)> {
+  let mut records = Vec::new();
+  let mut fields = Vec::new();
+  let mut field = String::new();
+  let mut in_quotes = false;
+  let mut line = 1;
+  let mut start_line = 1;
+  let mut chars = text.chars().peekable();
+  while let Some(c) = chars.next() {
+    match (in_quotes, c) {
+      (true, '"') if chars.peek() == Some(&'"') => {
+        chars.next();
+        field.push('"');
+      }
+      (true, '"') => in_quotes = false,
+      (false, '"') if field.is_empty() => in_quotes = true,
+      (false, c) if c == delimiter => fields.push(std::mem::take(&mut field)),
+      (false, '\r') if chars.peek() == Some(&'\n') => {}
+      (false, '\n') => {
+        fields.push(std::mem::take(&mut field));
+        if !(fields.len() == 1 && fields[0].is_empty()) {
+          records.push((start_line, std::mem::take(&mut fields)));
+        }
+        fields.clear();
+        line += 1;
+        start_line = line;
+      }
+      (_, c) => {
+        if c == '\n' {
+          line += 1;
+        }
+        field.push(c);
+      }
+    }
+  }
+  if !field.is_empty() || !fields.is_empty() {
+    fields.push(field);
+    records.push((start_line, fields));
+  }
+  records
+}
+
+fn json_text(value: serde_json::Value) -> Option<String> {
+  match value {
+    serde_json::Value::Null => None,
+    serde_json::Value::String(s) => Some(s),
+    other => Some(other.to_string()),
+  }
+}
+
+fn json_record(
+  value: serde_json::Value,
+  line: Option<usize>,
+  record: usize,
+  fields: &mut Vec<String>,
+) -> Result<Record, RowError> {
+  let serde_json::Value::Object(object) = value else {
+    return Err(RowError {
+      line,
+      record,
+      column: None,
+      value: None,
+      message: "Expected a JSON object".to_string(),
+    });
+  };
+  let mut values = HashMap::with_capacity(object.len());
+  for (key, value) in object {
+    if !fields.contains(&key) {
+      fields.push(key.clone());
+    }
+    values.insert(key, json_text(value));
+  }
+  Ok(Record {
+    line,
+    fields: values,
+  })
+}
+
+fn read_source(path: &Path, format: ExportFormat) -> Result<Source, String> {
+  let text = std::fs::read_to_string(path)
+    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+  let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+  let mut source = Source {
+    fields: Vec::new(),
+    records: Vec::new(),
+    errors: Vec::new(),
+  };
+  match format {
+    ExportFormat::Csv | ExportFormat::Tsv => {
+      let mut rows = csv_records(text, format.delimiter().unwrap_or(',')).into_iter();
+      let Some((_, header)) = rows.next() else {
+        return Ok(source);
+      };
+      source.fields = header.iter().map(|h| h.trim().to_string()).collect();
+      for (n, (line, values)) in rows.enumerate() {
+        if values.len() != source.fields.len() {
+          source.errors.push(RowError {
+            line: Some(line),
+            record: n + 1,
+            column: None,
+            value: None,
+            message: format!(
+              "Expected {} fields, found {}",
+              source.fields.len(),
+              values.len()
+            ),
+          });
+          continue;
+        }
+        let fields = source
+          .fields
+          .iter()
+          .cloned()
+          .zip(
+            values
+              .into_iter()
+              .map(|v| Some(v).filter(|v| !v.is_empty())),
+          )
+          .collect();
+        source.records.push(Record {
+          line: Some(line),
+          fields,
+        });
+      }
+    }
+    ExportFormat::Json => {
+      let values: Vec<serde_json::Value> =
+        serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?;
+      for (n, value) in values.into_iter().enumerate() {
+        match json_record(value, None, n + 1, &mut source.fields) {
+          Ok(record) => source.records.push(record),
+          Err(e) => source.errors.push(e),
+        }
+      }
+    }
+    ExportFormat::Ndjson => {
+      let lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty());
+      for (n, (index, line)) in lines.enumerate() {
+        let parsed = serde_json::from_str(line)
+          .map_err(|e| RowError {
+            line: Some(index + 1),
+            record: n + 1,
+            column: None,
+            value: None,
+            message: format!("Invalid JSON: {}", e),
+          })
+          .and_then(|value| json_record(value, Some(index + 1), n + 1, &mut source.fields));
+        match parsed {
+          Ok(record) => source.records.push(record),
+          Err(e) => source.errors.push(e),
+        }
+      }
+    }
+  }
+  Ok(source)
+}
+
+fn parse_datetime(value: &str) -> bool {
+  const FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+  ];
+  chrono::DateTime::parse_from_rfc3339(value).is_ok()
+    || chrono::DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z").is_ok()
+    || FORMATS
+      .iter()
+      .any(|f| chrono::NaiveDateTime::parse_from_str(value, f).is_ok())
+    || chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok()
+}
+
+fn integer_range(data_type: &str, dialect_is_sqlite: bool) -> (i128, i128) {
+  let unsigned = data_type.contains("unsigned");
+  let bits = if data_type.starts_with("tinyint") {
+    8
+  } else if data_type.starts_with("smallint") || data_type == "int2" {
+    16
+  } else if data_type.starts_with("mediumint") {
+    24
+  } else if data_type.starts_with("bigint") || data_type == "int8" || dialect_is_sqlite {
+    64
+  } else {
+    32
+  };
+  if unsigned {
+    (0, (1i128 << bits) - 1)
+  } else {
+    (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+  }
+}
+
+fn is_boolean(data_type: &str) -> bool {
+  let data_type = data_type.to_lowercase();
+  data_type == "tinyint(1)" || data_type.starts_with("bool")
+}
+
+/// Why `value` can't be stored in `column`, if it can't.
+fn check_value(column: &ColumnInfo, value: &str, sqlite: bool) -> Option<String> {
+  let data_type = column.data_type.to_lowercase();
+  if let Some(max) = column.max_length {
+    let length = i64::try_from(value.chars().count()).unwrap_or(i64::MAX);
+    if length > max {
+      return Some(format!("Longer than {} characters ({})", max, length));
+    }
+  }
+  // SQLite stores any value in any column, so only constraints are checked there
+  if sqlite {
+    return None;
+  }
+
+  let is_int = (data_type.contains("int") && !data_type.contains("interval"))
+    || data_type == "serial"
+    || data_type == "bigserial";
+  if is_boolean(&data_type) {
+    let ok = matches!(
+      value.to_lowercase().as_str(),
+      "0" | "1" | "true" | "false" | "t" | "f" | "yes" | "no"
+    );
+    return (!ok).then(|| "Not a boolean".to_string());
+  }
+  if is_int {
+    let (min, max) = integer_range(&data_type, sqlite);
+    return match value.trim().parse::<i128>() {
+      Ok(n) if n < min || n > max => Some(format!("Out of range for {}", column.data_type)),
+      Ok(_) => None,
+      Err(_) => Some("Not an integer".to_string()),
+    };
+  }
+  if data_type.starts_with("decimal") || data_type.starts_with("numeric") {
+    let digits = value.trim().trim_start_matches(['-', '+']);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() && fraction.is_empty()
+      || !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+      return Some("Not a number".to_string());
+    }
+    if let (Some(precision), Some(scale)) = (column.numeric_precision, column.numeric_scale) {
+      let whole_digits = i64::try_from(whole.trim_start_matches('0').len()).unwrap_or(i64::MAX);
+      if whole_digits > precision - scale {
+        return Some(format!("Too many digits for {}", column.data_type));
+      }
+    }
+    return None;
+  }
+  if ["float", "double", "real"]
+    .iter()
+    .any(|t| data_type.starts_with(t))
+  {
+    return value
+      .trim()
+      .parse::<f64>()
+      .is_err()
+      .then(|| "Not a number".to_string());
+  }
+  if data_type.starts_with("timestamp") || data_type.starts_with("datetime") {
+    return (!parse_datetime(value.trim())).then(|| "Not a date and time".to_string());
+  }
+  if data_type == "date" {
+    return chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+      .is_err()
+      .then(|| "Not a date (YYYY-MM-DD)".to_string());
+  }
+  if data_type.starts_with("time") {
+    return chrono::NaiveTime::parse_from_str(value.trim(), "%H:%M:%S%.f")
+      .is_err()
+      .then(|| "Not a time (HH:MM:SS)".to_string());
+  }
+  if data_type.starts_with("json") {
+    return serde_json::from_str::<serde_json::Value>(value)
+      .is_err()
+      .then(|| "Not valid JSON".to_string());
+  }
+  if data_type == "uuid" {
+    let ok = value.len() == 36
+      && value.char_indices().all(|(i, c)| match i {
+        8 | 13 | 18 | 23 => c == '-',
+        _ => c.is_ascii_hexdigit(),
+      });
+    return (!ok).then(|| "Not a UUID".to_string());
+  }
+  if let Some(options) = data_type
+    .strip_prefix("enum(")
+    .and_then(|rest| rest.strip_suffix(')'))
+  {
+    let allowed: Vec<String> = options
+      .split(',')
+      .map(|o| o.trim().trim_matches('\'').replace("''", "'"))
+      .collect();
+    return (!allowed.iter().any(|a| a.eq_ignore_ascii_case(value)))
+      .then(|| format!("Not one of {}", allowed.join(", ")));
+  }
+  None
+}
+
+/// Mapping by name: every source field with a column of the same name (ignoring case).
+fn auto_mapping(fields: &[String], columns: &[ColumnInfo]) -> Vec<FieldMapping> {
+  fields
+    .iter()
+    .filter_map(|field| {
+      columns
+        .iter()
+        .find(|c| c.name.eq_ignore_ascii_case(field))
+        .map(|c| FieldMapping {
+          source: field.clone(),
+          column: c.name.clone(),
+        })
+    })
+    .collect()
+}
+
+async fn load_preset(
+  state: &AppState,
+  table: &str,
+  name: &str,
+) -> Result<Vec<FieldMapping>, String> {
+  let pool = store::pool(state)?;
+  let row: Option<(String,)> = sqlx::query_as(
+    "SELECT mapping FROM import_presets WHERE workspace = ? AND table_name = ? AND name = ?",
+  )
+  .bind(workspaces::current(state))
+  .bind(table)
+  .bind(name)
+  .fetch_optional(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  let (mapping,) = row.ok_or_else(|| format!("Unknown import preset '{}' for {}", name, table))?;
+  serde_json::from_str(&mapping).map_err(|e| format!("Corrupt import preset: {}", e))
+}
+
+fn insert_statement(
+  pool: &SqlPool,
+  table: &str,
+  values: &[(&ColumnInfo, Option<String>)],
+) -> String {
+  // Missing values of columns with a default are left out so the default applies
+  let present: Vec<&(&ColumnInfo, Option<String>)> = values
+    .iter()
+    .filter(|(column, value)| value.is_some() || !column.has_default)
+    .collect();
+  if present.is_empty() {
+    return match pool {
+      SqlPool::MySql(_) => format!("INSERT INTO {} () VALUES ()", pool.table_ref(table)),
+      _ => format!("INSERT INTO {} DEFAULT VALUES", pool.table_ref(table)),
+    };
+  }
+  let columns: Vec<String> = present
+    .iter()
+    .map(|(column, _)| pool.quote_ident(&column.name))
+    .collect();
+  let literals: Vec<String> = present
+    .iter()
+    .map(|(_, value)| match value {
+      Some(value) => pool.quote_literal(value),
+      None => "NULL".to_string(),
+    })
+    .collect();
+  format!(
+    "INSERT INTO {} ({}) VALUES ({})",
+    pool.table_ref(table),
+    columns.join(", "),
+    literals.join(", ")
+  )
+}
+
+/// Validates `path` against `table` and, unless `dry_run`, inserts its rows. Fields are mapped
+/// with `mapping`, the saved preset `preset`, or by name. Lists at most `max_errors` errors.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn import_file(
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+  path: String,
+  format: Option<String>,
+  mapping: Option<Vec<FieldMapping>>,
+  preset: Option<String>,
+  dry_run: Option<bool>,
+  max_errors: Option<usize>,
+) -> Result<ImportReport, String> {
+  let dry_run = dry_run.unwrap_or(false);
+  let max_errors = max_errors
+    .unwrap_or(DEFAULT_MAX_ERRORS)
+    .clamp(1, MAX_ERRORS_LIMIT);
+  let path = Path::new(&path);
+  let format = match format {
+    Some(name) => ExportFormat::parse(&name)?,
+    None => ExportFormat::parse(
+      path
+        .extension()
+        .and_then(|e| e.to_str())
+        .ok_or("Pass the format of files without an extension")?,
+    )?,
+  };
+
+  let pool = db::sql_pool(&state, &connection)?;
+  let table = pool.resolve_table(&table).await?;
+  let columns = schema::column_info(&pool, db::PG_DEFAULT_SCHEMA, &table).await?;
+  let source = read_source(path, format)?;
+
+  let mapping = match (mapping, preset) {
+    (Some(mapping), _) => mapping,
+    (None, Some(preset)) => load_preset(&state, &table, &preset).await?,
+    (None, None) => auto_mapping(&source.fields, &columns),
+  };
+  let mut mapped: Vec<(&ColumnInfo, &str)> = Vec::with_capacity(mapping.len());
+  for field in &mapping {
+    let column = columns
+      .iter()
+      .find(|c| c.name == field.column)
+      .ok_or_else(|| format!("Table {} has no column '{}'", table, field.column))?;
+    if mapped.iter().any(|(c, _)| c.name == column.name) {
+      return Err(format!("Column '{}' is mapped more than once", column.name));
+    }
+    mapped.push((column, field.source.as_str()));
+  }
+  let unmapped_fields: Vec<String> = source
+    .fields
+    .iter()
+    .filter(|f| !mapping.iter().any(|m| &m.source == *f))
+    .cloned()
+    .collect();
+  // Required columns nothing is mapped to fail every row; report them once
+  for column in &columns {
+    if !column.nullable && !column.has_default && !mapped.iter().any(|(c, _)| c.name == column.name)
+    {
+      return Err(format!(
+        "Column '{}' is required but no field is mapped to it",
+        column.name
+      ));
+    }
+  }
+
+  let sqlite = pool.engine() == "sqlite";
+  let rows_read = source.records.len() + source.errors.len();
+  let mut error_count = source.errors.len();
+  let mut errors = Vec::new();
+  let mut statements = Vec::new();
+  let mut rows_valid = 0;
+  for (n, record) in source.records.iter().enumerate() {
+    let mut values = Vec::with_capacity(mapped.len());
+    let mut valid = true;
+    for (column, field) in &mapped {
+      let value = record.fields.get(*field).and_then(Option::as_ref);
+      let problem = match value {
+        None if !column.nullable && !column.has_default => {
+          Some("Required value is missing".to_string())
+        }
+        None => None,
+        Some(value) => check_value(column, value, sqlite),
+      };
+      if let Some(message) = problem {
+        valid = false;
+        error_count += 1;
+        if errors.len() < max_errors {
+          errors.push(RowError {
+            line: record.line,
+            record: n + 1,
+            column: Some(column.name.clone()),
+            value: value.cloned(),
+            message,
+          });
+        }
+      }
+      let value = value.map(|v| {
+        if is_boolean(&column.data_type) {
+          let truthy = matches!(v.to_lowercase().as_str(), "1" | "true" | "t" | "yes");
+          if truthy { "1" } else { "0" }.to_string()
+        } else {
+          v.clone()
+        }
+      });
+      values.push((*column, value));
+    }
+    if valid {
+      rows_valid += 1;
+      if !dry_run && error_count == 0 {
+        statements.push(insert_statement(&pool, &table, &values));
+      }
+    }
+  }
+  errors.extend(source.errors);
+  errors.sort_by_key(|e| e.record);
+  errors.truncate(max_errors);
+
+  let rows_written = if dry_run || error_count > 0 || statements.is_empty() {
+    0
+  } else {
+    readonly::ensure_writable(&state, &connection)?;
+    let written = pool.execute_in_transaction(&statements).await?;
+    audit::record_batch(&state, &connection, &statements, written).await;
+    written
+  };
+  Ok(ImportReport {
+    dry_run,
+    rows_read,
+    rows_valid,
+    rows_written,
+    error_count,
+    errors,
+    unmapped_fields,
+  })
+}
+
+/// Saves (or replaces) the mapping preset `name` for `table`.
+#[tauri::command]
+pub async fn save_import_preset(
+  state: State<'_, AppState>,
+  table: String,
+  name: String,
+  mapping: Vec<FieldMapping>,
+) -> Result<(), String> {
+  if name.trim().is_empty() {
+    return Err("Preset name cannot be empty".to_string());
+  }
+  let pool = store::pool(&state)?;
+  let mapping = serde_json::to_string(&mapping).map_err(|e| e.to_string())?;
+  sqlx::query(
+    "INSERT INTO import_presets (workspace, table_name, name, mapping, updated_at) \
+     VALUES (?, ?, ?, ?, ?) \
+     ON CONFLICT(workspace, table_name, name) DO UPDATE SET mapping = excluded.mapping, \
+     updated_at = excluded.updated_at",
+  )
+  .bind(workspaces::current(&state))
+  .bind(&table)
+  .bind(name.trim())
+  .bind(mapping)
+  .bind(store::now_ms())
+  .execute(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Presets saved for `table` in the active workspace.
+#[tauri::command]
+pub async fn list_import_presets(
+  state: State<'_, AppState>,
+  table: String,
+) -> Result<Vec<ImportPreset>, String> {
+  let pool = store::pool(&state)?;
+  let rows: Vec<(String, String, i64)> = sqlx::query_as(
+    "SELECT name, mapping, updated_at FROM import_presets \
+     WHERE workspace = ? AND table_name = ? ORDER BY name COLLATE NOCASE",
+  )
+  .bind(workspaces::current(&state))
+  .bind(&table)
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  rows
+    .into_iter()
+    .map(|(name, mapping, updated_at)| {
+      Ok(ImportPreset {
+        table: table.clone(),
+        name,
+        mapping: serde_json::from_str(&mapping)
+          .map_err(|e| format!("Corrupt import preset: {}", e))?,
+        updated_at,
+      })
+    })
+    .collect()
+}
+
+#[tauri::command]
+pub async fn delete_import_preset(
+  state: State<'_, AppState>,
+  table: String,
+  name: String,
+) -> Result<(), String> {
+  let pool = store::pool(&state)?;
+  sqlx::query("DELETE FROM import_presets WHERE workspace = ? AND table_name = ? AND name = ?")
+    .bind(workspaces::current(&state))
+    .bind(&table)
+    .bind(&name)
+    .execute(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
@@ -0,0 +1,530 @@
+//! Imports connections saved in other tools, so users moving over don't have to retype them:
+//! DBeaver's `data-sources.json` (with the encrypted `credentials-config.json` next to it),
+//! DataGrip's `dataSources.xml` (user names from `dataSources.local.xml`) and Navicat's NCX
+//! export. Entries are mapped onto [`ConnectionProfile`]s and saved in the active workspace.
+
+use std::path::Path;
+
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use tauri::State;
+
+use crate::encoding::unhex;
+use crate::profiles::{self, ConnectionProfile};
+use crate::{dsn, AppState, SshConfig};
+
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+/// Fixed key DBeaver encrypts `credentials-config.json` with; the IV leads the file.
+const DBEAVER_KEY: [u8; 16] = [
+  0xba, 0xbb, 0x4a, 0x9f, 0x77, 0x4a, 0xb8, 0x53, 0xc9, 0x6c, 0x2d, 0x65, 0x3d, 0xfe, 0x54, 0x4a,
+];
+/// Fixed key and IV of the password fields in Navicat 12+ exports.
+const NAVICAT_KEY: &[u8; 16] = b"libcckeylibcckey";
+const NAVICAT_IV: &[u8; 16] = b"libcciv libcciv ";
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedConnection {
+  pub name: String,
+  pub reason: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedConnections {
+  /// `dbeaver`, `datagrip`, `navicat` or `bundle`.
+  pub source: &'static str,
+  pub profiles: Vec<ConnectionProfile>,
+  pub skipped: Vec<SkippedConnection>,
+}
+
+pub type Mapped = Result<ConnectionProfile, SkippedConnection>;
+
+fn skip(name: &str, reason: impl Into<String>) -> Mapped {
+  Err(SkippedConnection {
+    name: name.to_string(),
+    reason: reason.into(),
+  })
+}
+
+fn decrypt(key: &[u8; 16], iv: &[u8; 16], data: &[u8]) -> Option<String> {
+  let mut buf = data.to_vec();
+  let plain = Aes128CbcDec::new(key.into(), iv.into())
+    .decrypt_padded_mut::<Pkcs7>(&mut buf)
+    .ok()?;
+  String::from_utf8(plain.to_vec()).ok()
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+  value
+    .map(str::trim)
+    .filter(|v| !v.is_empty())
+    .map(str::to_string)
+}
+
+/// Fills the connection fields from a URL in our own connection string syntax, which JDBC
+/// URLs follow once their `jdbc:` prefix is dropped.
+fn apply_url(profile: &mut ConnectionProfile, url: &str) -> Result<(), String> {
+  let parsed = dsn::parse(url.strip_prefix("jdbc:").unwrap_or(url))?;
+  profile.engine = parsed.engine.to_string();
+  if parsed.engine == "sqlite" {
+    profile.path = parsed.path;
+    return Ok(());
+  }
+  profile.host = Some(parsed.host).filter(|h| !h.is_empty());
+  profile.port = Some(parsed.port);
+  profile.username = profile.username.take().or(parsed.username);
+  profile.password = profile.password.take().or(parsed.password);
+  profile.database = parsed.database;
+  profile.socket_path = parsed.socket_path;
+  Ok(())
+}
+
+fn ssh_config(
+  host: Option<String>,
+  port: Option<u16>,
+  username: Option<String>,
+  password: Option<String>,
+  private_key_path: Option<String>,
+  use_agent: bool,
+) -> Option<SshConfig> {
+  Some(SshConfig {
+    host: host?,
+    port: port.unwrap_or(22),
+    username: username.unwrap_or_default(),
+    password,
+    private_key_path,
+    use_agent,
+    jump_hosts: Vec::new(),
+  })
+}
+
+// --- DBeaver ---
+
+fn dbeaver_engine(provider: &str, driver: &str) -> Option<&'static str> {
+  let driver = driver.to_ascii_lowercase();
+  match provider {
+    "mysql" => Some("mysql"),
+    "postgresql" => Some("postgres"),
+    "mongodb" => Some("mongodb"),
+    "redis" => Some("redis"),
+    _ if driver.contains("sqlite") => Some("sqlite"),
+    _ if driver.contains("mariadb") || driver.contains("mysql") => Some("mysql"),
+    _ if driver.contains("postgres") => Some("postgres"),
+    _ => None,
+  }
+}
+
+/// `credentials-config.json` next to `data-sources.json`, decrypted: per connection id, the
+/// `#connection` and `network/<handler>` credentials.
+fn dbeaver_credentials(path: &Path) -> serde_json::Value {
+  let file = path.with_file_name("credentials-config.json");
+  std::fs::read(file)
+    .ok()
+    .filter(|data| data.len() > 16)
+    .and_then(|data| {
+      let iv: [u8; 16] = data[..16].try_into().ok()?;
+      decrypt(&DBEAVER_KEY, &iv, &data[16..])
+    })
+    .and_then(|json| serde_json::from_str(&json).ok())
+    .unwrap_or(serde_json::Value::Null)
+}
+
+fn json_port(value: &serde_json::Value) -> Option<u16> {
+  match value {
+    serde_json::Value::Number(n) => n.as_u64().and_then(|n| u16::try_from(n).ok()),
+    serde_json::Value::String(s) => s.trim().parse().ok(),
+    _ => None,
+  }
+}
+
+fn dbeaver(json: &serde_json::Value, path: &Path) -> Vec<Mapped> {
+  let credentials = dbeaver_credentials(path);
+  let Some(connections) = json["connections"].as_object() else {
+    return Vec::new();
+  };
+  connections
+    .iter()
+    .map(|(id, connection)| {
+      let name = connection["name"].as_str().unwrap_or(id);
+      let config = &connection["configuration"];
+      let Some(engine) = dbeaver_engine(
+        connection["provider"].as_str().unwrap_or_default(),
+        connection["driver"].as_str().unwrap_or_default(),
+      ) else {
+        return skip(name, "Unsupported database type");
+      };
+      let auth = &credentials[id]["#connection"];
+      let mut profile = ConnectionProfile {
+        name: name.to_string(),
+        engine: engine.to_string(),
+        host: non_empty(config["host"].as_str()),
+        port: json_port(&config["port"]),
+        username: non_empty(auth["user"].as_str().or(config["user"].as_str())),
+        password: non_empty(auth["password"].as_str().or(config["password"].as_str())),
+        database: non_empty(config["database"].as_str()),
+        ..Default::default()
+      };
+      if engine == "sqlite" {
+        profile.path = profile.database.take();
+      }
+      if profile.host.is_none() && profile.path.is_none() {
+        if let Some(url) = non_empty(config["url"].as_str()) {
+          if let Err(e) = apply_url(&mut profile, &url) {
+            return skip(name, e);
+          }
+        }
+      }
+
+      let tunnel = &config["handlers"]["ssh_tunnel"];
+      if tunnel["enabled"].as_bool() == Some(true) {
+        let properties = &tunnel["properties"];
+        let login = &credentials[id]["network/ssh_tunnel"];
+        profile.ssh_config = ssh_config(
+          non_empty(properties["host"].as_str()),
+          json_port(&properties["port"]),
+          non_empty(login["user"].as_str().or(properties["user"].as_str())),
+          non_empty(login["password"].as_str()),
+          non_empty(properties["keyPath"].as_str()),
+          properties["authType"].as_str() == Some("AGENT"),
+        );
+      }
+      Ok(profile)
+    })
+    .collect()
+}
+
+// --- XML (DataGrip, Navicat) ---
+
+struct Tag {
+  name: String,
+  attrs: Vec<(String, String)>,
+  closing: bool,
+  /// Text between this tag and the next one.
+  text: String,
+}
+
+impl Tag {
+  fn attr(&self, name: &str) -> Option<&str> {
+    self
+      .attrs
+      .iter()
+      .find(|(key, _)| key == name)
+      .map(|(_, value)| value.as_str())
+  }
+}
+
+fn unescape(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  let mut rest = text;
+  while let Some(amp) = rest.find('&') {
+    out.push_str(&rest[..amp]);
+    rest = &rest[amp..];
+    let Some(semi) = rest.find(';') else {
+      break;
+    };
+    let entity = &rest[1..semi];
+    let decoded = match entity {
+      "amp" => Some('&'),
+      "lt" => Some('<'),
+      "gt" => Some('>'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      _ => entity
+        .strip_prefix("#x")
+        .map(|hex| u32::from_str_radix(hex, 16))
+        .or_else(|| entity.strip_prefix('#').map(str::parse))
+        .and_then(Result::ok)
+        .and_then(char::from_u32),
+    };
+    if let Some(c) = decoded {
+      out.push(c);
+      rest = &rest[semi + 1..];
+    } else {
+      out.push('&');
+      rest = &rest[1..];
+    }
+  }
+  out.push_str(rest);
+  out
+}
+
+/// Flat list of the elements in `xml`, enough for the simple files read here.
+fn scan_tags(xml: &str) -> Vec<Tag> {
+  let mut tags = Vec::new();
+  let mut rest = xml;
+  while let Some(open) = rest.find('<') {
+    rest = &rest[open..];
+    let skip_to = if rest.starts_with("<!--") {
+      Some("-->")
+    } else if rest.starts_with("<?") {
+      Some("?>")
+    } else if rest.starts_with("<!") {
+      Some(">")
+    } else {
+      None
+    };
+    if let Some(end) = skip_to {
+      match rest.find(end) {
+        Some(i) => rest = &rest[i + end.len()..],
+        None => break,
+      }
+      continue;
+    }
+
+    // End of the tag, ignoring '>' inside quoted attribute values
+    let mut quote = None;
+    let Some(close) = rest.char_indices().skip(1).find_map(|(i, c)| {
+      match (quote, c) {
+        (None, '"' | '\'') => quote = Some(c),
+        (Some(q), c) if c == q => quote = None,
+        (None, '>') => return Some(i),
+        _ => {}
+      }
+      None
+    }) else {
+      break;
+    };
+    let inner = rest[1..close].trim_end_matches('/').trim();
+    let closing = inner.starts_with('/');
+    let inner = inner.trim_start_matches('/');
+    let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+
+    let mut attrs = Vec::new();
+    let mut attr_text = &inner[name_end..];
+    while let Some(eq) = attr_text.find('=') {
+      let key = attr_text[..eq].trim().to_string();
+      let value_text = attr_text[eq + 1..].trim_start();
+      let Some(q) = value_text
+        .chars()
+        .next()
+        .filter(|c| *c == '"' || *c == '\'')
+      else {
+        break;
+      };
+      let Some(end) = value_text[1..].find(q) else {
+        break;
+      };
+      attrs.push((key, unescape(&value_text[1..=end])));
+      attr_text = &value_text[end + 2..];
+    }
+
+    rest = &rest[close + 1..];
+    let text_end = rest.find('<').unwrap_or(rest.len());
+    tags.push(Tag {
+      name: inner[..name_end].to_string(),
+      attrs,
+      closing,
+      text: unescape(rest[..text_end].trim()),
+    });
+  }
+  tags
+}
+
+// --- DataGrip ---
+
+/// User names from `dataSources.local.xml`, by data source uuid.
+fn datagrip_users(path: &Path) -> Vec<(String, String)> {
+  let Ok(xml) = std::fs::read_to_string(path.with_file_name("dataSources.local.xml")) else {
+    return Vec::new();
+  };
+  let mut users = Vec::new();
+  let mut uuid = None;
+  for tag in scan_tags(&xml) {
+    match (tag.name.as_str(), tag.closing) {
+      ("data-source", false) => uuid = tag.attr("uuid").map(str::to_string),
+      ("user-name", false) => {
+        if let Some(uuid) = &uuid {
+          users.push((uuid.clone(), tag.text));
+        }
+      }
+      _ => {}
+    }
+  }
+  users
+}
+
+fn datagrip(xml: &str, path: &Path) -> Vec<Mapped> {
+  let users = datagrip_users(path);
+  let mut mapped = Vec::new();
+  let mut current: Option<(String, String, Option<String>, Option<String>)> = None;
+  for tag in scan_tags(xml) {
+    match (tag.name.as_str(), tag.closing) {
+      ("data-source", false) => {
+        current = Some((
+          tag.attr("name").unwrap_or("Unnamed").to_string(),
+          tag.attr("uuid").unwrap_or_default().to_string(),
+          None,
+          None,
+        ));
+      }
+      ("jdbc-url", false) => {
+        if let Some(current) = current.as_mut() {
+          current.2 = Some(tag.text);
+        }
+      }
+      ("user-name", false) => {
+        if let Some(current) = current.as_mut() {
+          current.3 = Some(tag.text);
+        }
+      }
+      ("data-source", true) => {
+        let Some((name, uuid, url, user)) = current.take() else {
+          continue;
+        };
+        let Some(url) = url else {
+          mapped.push(skip(&name, "No JDBC URL"));
+          continue;
+        };
+        let mut profile = ConnectionProfile {
+          name: name.clone(),
+          username: user.or_else(|| {
+            users
+              .iter()
+              .find(|(id, _)| *id == uuid)
+              .map(|(_, user)| user.clone())
+          }),
+          ..Default::default()
+        };
+        mapped.push(match apply_url(&mut profile, &url) {
+          Ok(()) => Ok(profile),
+          Err(_) => skip(&name, format!("Unsupported JDBC URL: {}", url)),
+        });
+      }
+      _ => {}
+    }
+  }
+  mapped
+}
+
+// --- Navicat ---
+
+fn navicat_password(encrypted: Option<&str>) -> Option<String> {
+  let data = unhex(encrypted?.trim())?;
+  decrypt(NAVICAT_KEY, NAVICAT_IV, &data).filter(|p| !p.is_empty())
+}
+
+fn navicat(xml: &str) -> Vec<Mapped> {
+  scan_tags(xml)
+    .into_iter()
+    .filter(|tag| tag.name == "Connection" && !tag.closing)
+    .map(|tag| {
+      let name = tag.attr("ConnectionName").unwrap_or("Unnamed");
+      let engine = match tag.attr("ConnType").unwrap_or_default() {
+        "MYSQL" | "MARIADB" => "mysql",
+        "POSTGRESQL" => "postgres",
+        "SQLITE" => "sqlite",
+        "MONGODB" => "mongodb",
+        "REDIS" => "redis",
+        other => return skip(name, format!("Unsupported connection type {}", other)),
+      };
+      let mut profile = ConnectionProfile {
+        name: name.to_string(),
+        engine: engine.to_string(),
+        host: non_empty(tag.attr("Host")),
+        port: tag.attr("Port").and_then(|p| p.trim().parse().ok()),
+        username: non_empty(tag.attr("UserName")),
+        password: navicat_password(tag.attr("Password")),
+        database: non_empty(tag.attr("Database")).or(non_empty(tag.attr("InitialDatabase"))),
+        path: non_empty(tag.attr("DatabaseFileName")),
+        ..Default::default()
+      };
+      if tag.attr("SSH") == Some("true") {
+        profile.ssh_config = ssh_config(
+          non_empty(tag.attr("SSH_Host")),
+          tag.attr("SSH_Port").and_then(|p| p.trim().parse().ok()),
+          non_empty(tag.attr("SSH_UserName")),
+          navicat_password(tag.attr("SSH_Password")),
+          non_empty(tag.attr("SSH_PrivateKey")),
+          false,
+        );
+      }
+      Ok(profile)
+    })
+    .collect()
+}
+
+/// Reads an export of another tool, recognised by its content.
+fn read_export(path: &Path) -> Result<(&'static str, Vec<Mapped>), String> {
+  let content = std::fs::read_to_string(path)
+    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+  if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
+    if json.get("connections").is_some() {
+      return Ok(("dbeaver", dbeaver(&json, path)));
+    }
+  }
+  if content.contains("<Connections") {
+    return Ok(("navicat", navicat(&content)));
+  }
+  if content.contains("<data-source") {
+    return Ok(("datagrip", datagrip(&content, path)));
+  }
+  Err(
+    "Not a DBeaver data-sources.json, DataGrip dataSources.xml or Navicat .ncx export".to_string(),
+  )
+}
+
+/// Imports the connections in `path` as profiles of the active workspace. Connections whose
+/// name is already taken by a profile of the same engine are skipped, so importing twice is
+/// harmless.
+#[tauri::command]
+pub async fn import_connections(
+  state: State<'_, AppState>,
+  path: String,
+) -> Result<ImportedConnections, String> {
+  let (source, mapped) = read_export(Path::new(&path))?;
+  save_all(&state, source, mapped).await
+}
+
+/// Saves mapped profiles under new ids, skipping names already taken by a profile of the
+/// same engine.
+pub async fn save_all(
+  state: &AppState,
+  source: &'static str,
+  mapped: Vec<Mapped>,
+) -> Result<ImportedConnections, String> {
+  let existing = profiles::all_profiles(state, None).await?;
+
+  let mut imported = ImportedConnections {
+    source,
+    profiles: Vec::new(),
+    skipped: Vec::new(),
+  };
+  for entry in mapped {
+    let mut profile = match entry {
+      Ok(profile) => profile,
+      Err(skipped) => {
+        imported.skipped.push(skipped);
+        continue;
+      }
+    };
+    if existing
+      .iter()
+      .chain(&imported.profiles)
+      .any(|p| p.engine == profile.engine && p.name.eq_ignore_ascii_case(&profile.name))
+    {
+      imported.skipped.push(SkippedConnection {
+        name: profile.name,
+        reason: "A profile with this name already exists".to_string(),
+      });
+      continue;
+    }
+    if profile.engine != "sqlite" && profile.host.is_none() && profile.socket_path.is_none() {
+      imported.skipped.push(SkippedConnection {
+        name: profile.name,
+        reason: "No host".to_string(),
+      });
+      continue;
+    }
+    profile.id.clear();
+    match profiles::save_profile(state, profile.clone()).await {
+      Ok(saved) => imported.profiles.push(saved),
+      Err(reason) => imported.skipped.push(SkippedConnection {
+        name: profile.name,
+        reason,
+      }),
+    }
+  }
+  Ok(imported)
+}
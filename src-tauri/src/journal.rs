@@ -0,0 +1,228 @@
+//! Crash-safe journal of in-flight work, kept in the app store: grid edits waiting to be
+//! applied and background tasks still running. Entries are written when the work starts and
+//! removed when it's applied, discarded or finished, so whatever is left from an earlier run
+//! of the app was cut short by a crash or forced quit. `get_recovery` lists those entries on
+//! the next launch, for the UI to restore the edits and resume or report the tasks.
+
+use std::sync::OnceLock;
+
+use tauri::{AppHandle, State};
+
+use crate::tasks::{self, TaskSpec};
+use crate::{store, AppState};
+
+const KIND_EDITS: &str = "edits";
+const KIND_TASK: &str = "task";
+
+/// Identifies this run of the app; entries written under another one are left over.
+fn run_id() -> &'static str {
+  static RUN_ID: OnceLock<String> = OnceLock::new();
+  RUN_ID.get_or_init(|| format!("{}-{}", store::now_ms(), std::process::id()))
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EditsPayload {
+  connection: String,
+  table: String,
+  changes: serde_json::Value,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveredEdits {
+  pub id: String,
+  pub connection: String,
+  pub table: String,
+  /// Pending changes as the grid journaled them.
+  pub changes: serde_json::Value,
+  pub updated_at: i64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedTask {
+  pub id: String,
+  pub label: String,
+  pub spec: TaskSpec,
+  pub started_at: i64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recovery {
+  pub edits: Vec<RecoveredEdits>,
+  pub tasks: Vec<InterruptedTask>,
+}
+
+async fn put(
+  state: &AppState,
+  id: &str,
+  kind: &str,
+  label: &str,
+  payload: String,
+) -> Result<(), String> {
+  sqlx::query(
+    "INSERT INTO journal (id, kind, run_id, label, payload, updated_at) \
+     VALUES (?, ?, ?, ?, ?, ?) \
+     ON CONFLICT (id) DO UPDATE SET run_id = excluded.run_id, label = excluded.label, \
+     payload = excluded.payload, updated_at = excluded.updated_at",
+  )
+  .bind(id)
+  .bind(kind)
+  .bind(run_id())
+  .bind(label)
+  .bind(payload)
+  .bind(store::now_ms())
+  .execute(&store::pool(state)?)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+async fn remove(state: &AppState, id: &str) -> Result<bool, String> {
+  let result = sqlx::query("DELETE FROM journal WHERE id = ?")
+    .bind(id)
+    .execute(&store::pool(state)?)
+    .await
+    .map_err(|e| e.to_string())?;
+  Ok(result.rows_affected() > 0)
+}
+
+fn edits_id(connection: &str, table: &str) -> String {
+  format!("{}:{}:{}", KIND_EDITS, connection, table)
+}
+
+fn task_entry_id(task_id: &str) -> String {
+  // Task ids restart with every run
+  format!("{}:{}:{}", KIND_TASK, run_id(), task_id)
+}
+
+/// Journals a task as it starts.
+pub async fn task_started(
+  state: &AppState,
+  task_id: &str,
+  label: &str,
+  spec: &TaskSpec,
+) -> Result<(), String> {
+  let payload = serde_json::to_string(spec).map_err(|e| e.to_string())?;
+  put(state, &task_entry_id(task_id), KIND_TASK, label, payload).await
+}
+
+/// Clears a task's entry once it completed, failed or was cancelled.
+pub async fn task_finished(state: &AppState, task_id: &str) {
+  let id = task_entry_id(task_id);
+  if let Err(e) = remove(state, &id).await {
+    tracing::warn!("Failed to clear journal entry {}: {}", id, e);
+  }
+}
+
+/// Journals the grid's pending changes to `table`, replacing earlier ones; empty changes
+/// (`null`, `{}`) clear the entry.
+#[tauri::command]
+pub async fn journal_pending_edits(
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+  changes: serde_json::Value,
+) -> Result<(), String> {
+  let id = edits_id(&connection, &table);
+  let empty = match &changes {
+    serde_json::Value::Null => true,
+    serde_json::Value::Object(map) => map.is_empty(),
+    serde_json::Value::Array(items) => items.is_empty(),
+    _ => false,
+  };
+  if empty {
+    remove(&state, &id).await?;
+    return Ok(());
+  }
+  let label = format!("Unsaved changes to {}", table);
+  let payload = serde_json::to_string(&EditsPayload {
+    connection,
+    table,
+    changes,
+  })
+  .map_err(|e| e.to_string())?;
+  put(&state, &id, KIND_EDITS, &label, payload).await
+}
+
+/// Clears journaled edits of `table` once they are applied or discarded.
+#[tauri::command]
+pub async fn clear_pending_edits(
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+) -> Result<(), String> {
+  remove(&state, &edits_id(&connection, &table)).await?;
+  Ok(())
+}
+
+/// Edits and tasks left over from an earlier run, oldest first.
+#[tauri::command]
+pub async fn get_recovery(state: State<'_, AppState>) -> Result<Recovery, String> {
+  let rows: Vec<(String, String, String, String, i64)> = sqlx::query_as(
+    "SELECT id, kind, label, payload, updated_at FROM journal WHERE run_id <> ? \
+     ORDER BY updated_at",
+  )
+  .bind(run_id())
+  .fetch_all(&store::pool(&state)?)
+  .await
+  .map_err(|e| e.to_string())?;
+  let mut recovery = Recovery {
+    edits: Vec::new(),
+    tasks: Vec::new(),
+  };
+  for (id, kind, label, payload, updated_at) in rows {
+    match kind.as_str() {
+      KIND_EDITS => match serde_json::from_str::<EditsPayload>(&payload) {
+        Ok(edits) => recovery.edits.push(RecoveredEdits {
+          id,
+          connection: edits.connection,
+          table: edits.table,
+          changes: edits.changes,
+          updated_at,
+        }),
+        Err(e) => tracing::warn!("Skipping unreadable journal entry {}: {}", id, e),
+      },
+      KIND_TASK => match serde_json::from_str::<TaskSpec>(&payload) {
+        Ok(spec) => recovery.tasks.push(InterruptedTask {
+          id,
+          label,
+          spec,
+          started_at: updated_at,
+        }),
+        Err(e) => tracing::warn!("Skipping unreadable journal entry {}: {}", id, e),
+      },
+      _ => {}
+    }
+  }
+  Ok(recovery)
+}
+
+/// Drops a recovered entry without acting on it. Returns whether it existed.
+#[tauri::command]
+pub async fn dismiss_recovery(state: State<'_, AppState>, id: String) -> Result<bool, String> {
+  remove(&state, &id).await
+}
+
+/// Starts an interrupted task again from the beginning and returns the new task id.
+#[tauri::command]
+pub async fn resume_task(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  id: String,
+) -> Result<String, String> {
+  let row: Option<(String,)> =
+    sqlx::query_as("SELECT payload FROM journal WHERE id = ? AND kind = ? AND run_id <> ?")
+      .bind(&id)
+      .bind(KIND_TASK)
+      .bind(run_id())
+      .fetch_optional(&store::pool(&state)?)
+      .await
+      .map_err(|e| e.to_string())?;
+  let (payload,) = row.ok_or_else(|| format!("No interrupted task {}", id))?;
+  let spec: TaskSpec = serde_json::from_str(&payload).map_err(|e| e.to_string())?;
+  remove(&state, &id).await?;
+  Ok(tasks::start(&app, spec))
+}
@@ -0,0 +1,161 @@
+//! Local version history of Redis string values, captured whenever a value is viewed or
+//! edited through the app, so an accidental overwrite of a config-style key can be undone.
+//! Kept in the app store, bounded per key.
+
+use tauri::State;
+
+use crate::{connections, readonly, store, AppState};
+
+/// Versions kept per key; older ones are pruned.
+const MAX_VERSIONS: i64 = 50;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Source {
+  /// Value as it was seen when viewed.
+  View,
+  /// Value as it was right before the app overwrote it.
+  BeforeEdit,
+  /// Value the app wrote.
+  Edit,
+  /// Value as it was right before a restore.
+  BeforeRestore,
+}
+
+impl Source {
+  fn as_str(self) -> &'static str {
+    match self {
+      Source::View => "view",
+      Source::BeforeEdit => "beforeEdit",
+      Source::Edit => "edit",
+      Source::BeforeRestore => "beforeRestore",
+    }
+  }
+}
+
+/// Records `value` as the latest version of `key`, unless it equals the latest one already
+/// recorded. Failures are logged, never returned, so history never gets in the way of edits.
+pub async fn record(state: &AppState, connection_id: &str, key: &str, value: &str, source: Source) {
+  let Ok(pool) = store::pool(state) else {
+    return;
+  };
+  let recorded: Result<(), sqlx::Error> = async {
+    let latest: Option<(String,)> = sqlx::query_as(
+      "SELECT value FROM redis_key_history WHERE connection_id = ? AND key = ? \
+       ORDER BY id DESC LIMIT 1",
+    )
+    .bind(connection_id)
+    .bind(key)
+    .fetch_optional(&pool)
+    .await?;
+    if latest.is_some_and(|(latest,)| latest == value) {
+      return Ok(());
+    }
+    sqlx::query(
+      "INSERT INTO redis_key_history (connection_id, key, value, source, captured_at) \
+       VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(connection_id)
+    .bind(key)
+    .bind(value)
+    .bind(source.as_str())
+    .bind(store::now_ms())
+    .execute(&pool)
+    .await?;
+    sqlx::query(
+      "DELETE FROM redis_key_history WHERE connection_id = ?1 AND key = ?2 AND id NOT IN \
+       (SELECT id FROM redis_key_history WHERE connection_id = ?1 AND key = ?2 \
+        ORDER BY id DESC LIMIT ?3)",
+    )
+    .bind(connection_id)
+    .bind(key)
+    .bind(MAX_VERSIONS)
+    .execute(&pool)
+    .await?;
+    Ok(())
+  }
+  .await;
+  if let Err(e) = recorded {
+    tracing::warn!("Failed to record key history: {}", e);
+  }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyVersion {
+  pub version_id: i64,
+  pub value: String,
+  pub source: String,
+  pub captured_at: i64,
+}
+
+/// Recorded versions of `key`, newest first.
+#[tauri::command]
+pub async fn get_key_history(
+  state: State<'_, AppState>,
+  key: String,
+  connection_id: Option<String>,
+) -> Result<Vec<KeyVersion>, String> {
+  let pool = store::pool(&state)?;
+  let rows: Vec<(i64, String, String, i64)> = sqlx::query_as(
+    "SELECT id, value, source, captured_at FROM redis_key_history \
+     WHERE connection_id = ? AND key = ? ORDER BY id DESC",
+  )
+  .bind(connection_id.as_deref().unwrap_or("redis"))
+  .bind(&key)
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(
+    rows
+      .into_iter()
+      .map(|(version_id, value, source, captured_at)| KeyVersion {
+        version_id,
+        value,
+        source,
+        captured_at,
+      })
+      .collect(),
+  )
+}
+
+/// Writes a recorded version back to its key, recording the value it replaces first.
+#[tauri::command]
+pub async fn restore_key_version(
+  state: State<'_, AppState>,
+  version_id: i64,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  let id = connection_id.unwrap_or_else(|| "redis".to_string());
+  let pool = store::pool(&state)?;
+  let (key, value): (String, String) =
+    sqlx::query_as("SELECT key, value FROM redis_key_history WHERE id = ? AND connection_id = ?")
+      .bind(version_id)
+      .bind(&id)
+      .fetch_optional(&pool)
+      .await
+      .map_err(|e| e.to_string())?
+      .ok_or("Unknown key version")?;
+
+  readonly::ensure_writable(&state, &id)?;
+  let client = connections::redis(&state, Some(&id))?;
+  let mut con = client
+    .get_multiplexed_async_connection()
+    .await
+    .map_err(|e| e.to_string())?;
+  let current: Option<String> = redis::cmd("GET")
+    .arg(&key)
+    .query_async(&mut con)
+    .await
+    .map_err(|e| e.to_string())?;
+  if let Some(current) = current {
+    record(&state, &id, &key, &current, Source::BeforeRestore).await;
+  }
+  let _: () = redis::cmd("SET")
+    .arg(&key)
+    .arg(&value)
+    .query_async(&mut con)
+    .await
+    .map_err(|e| e.to_string())?;
+  record(&state, &id, &key, &value, Source::Edit).await;
+  Ok(())
+}
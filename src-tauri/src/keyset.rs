@@ -0,0 +1,294 @@
+//! Keyset ("seek") paging for the table browsers. OFFSET paging makes the server walk every
+//! skipped row, so deep pages on big tables get slower and slower; keyset paging continues
+//! after the primary key of the last row instead (`WHERE pk > ? ORDER BY pk LIMIT ?`), which
+//! an index answers directly at any depth.
+
+use std::fmt::Write as _;
+
+use crate::db::{JsonRow, SqlPool};
+use crate::rowfilter::RowFilter;
+use crate::schema;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowsPage {
+  /// Columns of the table, so the grid can pick an editor per type.
+  pub columns: Vec<schema::TableColumn>,
+  /// One array per column, each `row_count` long, in `columns` order.
+  pub values: Vec<Vec<serde_json::Value>>,
+  pub row_count: usize,
+  /// `after_pk` for the next page in keyset mode, while there may be one.
+  pub next_cursor: Option<String>,
+}
+
+impl RowsPage {
+  /// Column-major page of `rows`: column names are sent once instead of being repeated as
+  /// keys in every row object.
+  pub fn new(
+    columns: Vec<schema::TableColumn>,
+    mut rows: Vec<JsonRow>,
+    next_cursor: Option<String>,
+  ) -> Self {
+    let values = columns
+      .iter()
+      .map(|column| {
+        rows
+          .iter_mut()
+          .map(|row| row.remove(&column.name).unwrap_or(serde_json::Value::Null))
+          .collect()
+      })
+      .collect();
+    RowsPage {
+      columns,
+      values,
+      row_count: rows.len(),
+      next_cursor,
+    }
+  }
+}
+
+/// Column keyset paging over `table` (in Postgres `schema`) orders by: its primary key,
+/// which has to be a single column.
+pub async fn key_column(pool: &SqlPool, schema: &str, table: &str) -> Result<String, String> {
+  match schema::primary_key(pool, schema, table).await?.as_slice() {
+    [column] => Ok(column.clone()),
+    [] => Err(format!(
+      "Table {} has no primary key; keyset paging needs one",
+      table
+    )),
+    _ => Err(format!(
+      "Table {} has a composite primary key; keyset paging needs a single-column one",
+      table
+    )),
+  }
+}
+
+/// Key column when keyset paging is asked for, either directly or by passing a cursor to
+/// continue after. The key decides the order, so it can't be combined with a sort.
+pub async fn key_for(
+  pool: &SqlPool,
+  schema: &str,
+  table: &str,
+  filter: &RowFilter,
+  keyset: Option<bool>,
+  after_pk: Option<&str>,
+) -> Result<Option<String>, String> {
+  if !keyset.unwrap_or(false) && after_pk.is_none() {
+    return Ok(None);
+  }
+  if !filter.order.is_empty() {
+    return Err("Keyset paging orders by the primary key and can't be sorted".to_string());
+  }
+  key_column(pool, schema, table).await.map(Some)
+}
+
+/// Placeholder the `after_pk` cursor is bound at. Cursors arrive as text, so in Postgres the
+/// bind is cast to the type of the key column (in `schema`) for `>` to compare in its order.
+pub async fn after_param(
+  pool: &SqlPool,
+  schema: &str,
+  table: &str,
+  key: &str,
+  placeholder: String,
+) -> Result<String, String> {
+  if !matches!(pool, SqlPool::Postgres(_)) {
+    return Ok(placeholder);
+  }
+  let udt = pool.pg_column_type(schema, table, key).await?;
+  Ok(cast(placeholder, udt.as_deref()))
+}
+
+fn cast(placeholder: String, udt: Option<&str>) -> String {
+  format!("{}::{}", placeholder, udt.unwrap_or("text"))
+}
+
+/// `WHERE`, `ORDER BY` and `LIMIT` of a page query, after the `SELECT ... FROM`. Pages by
+/// the (quoted) `key` when given, continuing after the value bound at placeholder `after`
+/// if that is given; by `offset` in `filter`'s order otherwise.
+pub fn page_clause(
+  filter: &RowFilter,
+  key: Option<&str>,
+  after: Option<&str>,
+  limit: i64,
+  offset: i64,
+) -> String {
+  let mut conditions = filter.conditions.clone();
+  let order = match key {
+    Some(key) => {
+      if let Some(after) = after {
+        conditions.push(format!("{} > {}", key, after));
+      }
+      vec![key.to_string()]
+    }
+    None => filter.order.clone(),
+  };
+  let mut clause = String::new();
+  if !conditions.is_empty() {
+    let _ = write!(clause, " WHERE {}", conditions.join(" AND "));
+  }
+  if !order.is_empty() {
+    let _ = write!(clause, " ORDER BY {}", order.join(", "));
+  }
+  let _ = write!(clause, " LIMIT {}", limit);
+  if key.is_none() {
+    let _ = write!(clause, " OFFSET {}", offset);
+  }
+  clause
+}
+
+/// Cursor for the page after one of `returned` rows ending in `last`: the key of the last
+/// row when the page came back full.
+pub fn next_cursor(
+  last: Option<&JsonRow>,
+  returned: usize,
+  column: &str,
+  limit: i64,
+) -> Option<String> {
+  if i64::try_from(returned).unwrap_or(i64::MAX) < limit {
+    return None;
+  }
+  match last?.get(column)? {
+    serde_json::Value::Null => None,
+    serde_json::Value::String(s) => Some(s.clone()),
+    other => Some(other.to_string()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db;
+  use crate::ident::{self, Dialect};
+
+  async fn page(pool: &sqlx::SqlitePool, after: Option<&str>) -> (Vec<i64>, Option<String>) {
+    let filter = RowFilter::default();
+    let key = "id";
+    let quoted_key = ident::quote(Dialect::Sqlite, key);
+    let sql = format!(
+      "SELECT * FROM items{}",
+      page_clause(&filter, Some(&quoted_key), after.map(|_| "?"), 2, 0)
+    );
+    let mut query = sqlx::query(&sql);
+    if let Some(after) = after {
+      query = query.bind(after);
+    }
+    let rows = query.fetch_all(pool).await.unwrap();
+    let maps: Vec<JsonRow> = rows.iter().map(db::sqlite_row_to_json).collect();
+    let ids = maps.iter().map(|m| m["id"].as_i64().unwrap()).collect();
+    (ids, next_cursor(maps.last(), maps.len(), key, 2))
+  }
+
+  #[test]
+  fn clause_orders_and_continues_after_the_key() {
+    let filter = RowFilter::default();
+    assert_eq!(
+      page_clause(&filter, Some("\"id\""), Some("?"), 50, 100),
+      " WHERE \"id\" > ? ORDER BY \"id\" LIMIT 50"
+    );
+    assert_eq!(
+      page_clause(&filter, None, None, 50, 100),
+      " LIMIT 50 OFFSET 100"
+    );
+  }
+
+  #[test]
+  fn cursor_is_cast_to_the_key_type() {
+    assert_eq!(cast("$3".to_string(), Some("int8")), "$3::int8");
+    assert_eq!(cast("$1".to_string(), None), "$1::text");
+  }
+
+  /// Pages a Postgres table keyed by a bigint, against the server in
+  /// `SPECTRA_TEST_POSTGRES_URL` when one is given.
+  #[tokio::test]
+  async fn pages_a_postgres_table_by_a_bigint_key() {
+    let Ok(url) = std::env::var("SPECTRA_TEST_POSTGRES_URL") else {
+      return;
+    };
+    let pool = sqlx::PgPool::connect(&url).await.unwrap();
+    sqlx::raw_sql(
+      "DROP SCHEMA IF EXISTS keyset_test CASCADE; CREATE SCHEMA keyset_test; \
+       CREATE TABLE keyset_test.items (id bigint PRIMARY KEY, name text); \
+       INSERT INTO keyset_test.items VALUES (2, 'a'), (10, 'b'), (100, 'c');",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let sql_pool = SqlPool::Postgres(pool.clone());
+    let after = after_param(&sql_pool, "keyset_test", "items", "id", "$1".to_string())
+      .await
+      .unwrap();
+    assert_eq!(after, "$1::int8");
+    let sql = format!(
+      "SELECT id FROM keyset_test.items{}",
+      page_clause(&RowFilter::default(), Some("\"id\""), Some(&after), 2, 0)
+    );
+    // compared as text, '10' would sort before '2' and skip the row
+    let ids: Vec<(i64,)> = sqlx::query_as(&sql)
+      .bind("2")
+      .fetch_all(&pool)
+      .await
+      .unwrap();
+    assert_eq!(ids, vec![(10,), (100,)]);
+    sqlx::raw_sql("DROP SCHEMA keyset_test CASCADE")
+      .execute(&pool)
+      .await
+      .unwrap();
+  }
+
+  #[test]
+  fn cursor_only_for_full_pages() {
+    let mut row = JsonRow::new();
+    row.insert("id".to_string(), serde_json::json!(7));
+    assert_eq!(next_cursor(Some(&row), 2, "id", 2), Some("7".to_string()));
+    assert_eq!(next_cursor(Some(&row), 1, "id", 2), None);
+    assert_eq!(next_cursor(Some(&row), 2, "\"id\"", 2), None);
+  }
+
+  #[tokio::test]
+  async fn takes_the_second_page() {
+    let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::raw_sql(
+      "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT); \
+       INSERT INTO items (id, name) VALUES (1, 'a'), (2, 'b'), (5, 'c');",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let (first, cursor) = page(&pool, None).await;
+    assert_eq!(first, vec![1, 2]);
+    assert_eq!(cursor.as_deref(), Some("2"));
+    let (second, cursor) = page(&pool, cursor.as_deref()).await;
+    assert_eq!(second, vec![5]);
+    assert_eq!(cursor, None);
+  }
+
+  #[tokio::test]
+  async fn pages_are_column_major() {
+    let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::raw_sql(
+      "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT); \
+       INSERT INTO items (id, name) VALUES (1, 'a'), (2, NULL);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let sql_pool = SqlPool::Sqlite(pool.clone());
+    let columns = schema::table_schema(&sql_pool, db::PG_DEFAULT_SCHEMA, "items")
+      .await
+      .unwrap();
+    let rows = sqlx::query("SELECT * FROM items ORDER BY id")
+      .fetch_all(&pool)
+      .await
+      .unwrap();
+    let maps = rows.iter().map(db::sqlite_row_to_json).collect();
+    let page = RowsPage::new(columns, maps, None);
+    assert_eq!(page.row_count, 2);
+    assert_eq!(
+      page.values,
+      vec![
+        vec![serde_json::json!(1), serde_json::json!(2)],
+        vec![serde_json::json!("a"), serde_json::Value::Null],
+      ]
+    );
+  }
+}
@@ -5,7 +5,7 @@ use tauri::{
   Manager, State,
 };
 
-use std::sync::Mutex;
+use std::sync::{Mutex, PoisonError};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[cfg(target_os = "windows")]
@@ -13,8 +13,6 @@ use windows::core::BOOL;
 #[cfg(target_os = "windows")]
 use windows::Win32::Foundation::{HWND, LPARAM, RECT};
 #[cfg(target_os = "windows")]
-use windows::Win32::Graphics::Dwm::{DwmSetWindowAttribute, DWMWA_USE_IMMERSIVE_DARK_MODE};
-#[cfg(target_os = "windows")]
 use windows::Win32::Graphics::Gdi::{
   EnumDisplayMonitors, GetMonitorInfoW, MonitorFromWindow, HDC, MONITORINFO,
   MONITOR_DEFAULTTONEAREST, HMONITOR
@@ -24,18 +22,102 @@ use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
 
 use mongodb::{options::ClientOptions, Client};
 use russh::client;
-use sqlx::{
-  mysql::MySqlPoolOptions, postgres::PgPoolOptions, sqlite::SqlitePoolOptions, MySqlPool, PgPool,
-  SqlitePool,
-};
-use sqlx::{Column, Row, TypeInfo, ValueRef}; // For manual JSON conversion
-use std::collections::HashMap;
+use russh_keys::agent::client::{AgentClient, AgentStream};
+use sqlx::Row;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::sync::Mutex as AsyncMutex;
 
-#[derive(serde::Deserialize, Debug)]
+mod audit;
+mod bench;
+mod binlog;
+mod bundles;
+mod cancel;
+mod catalogs;
+mod cdc;
+mod cli;
+mod clipboard;
+mod collation;
+mod confirm;
+mod connections;
+mod conntest;
+mod console;
+mod db;
+mod designer;
+mod destinations;
+mod discovery;
+mod display;
+mod distinct;
+mod dsn;
+mod effects;
+mod encoding;
+mod explain;
+mod export;
+mod file_export;
+mod health;
+mod history;
+mod iam;
+mod ident;
+mod import;
+mod importers;
+mod journal;
+mod key_history;
+mod keyset;
+mod lineage;
+mod logging;
+mod masking;
+mod notify;
+mod parquet_export;
+mod payload;
+mod pools;
+mod profiles;
+mod readonly;
+mod reconnect;
+mod references;
+mod refgraph;
+mod results;
+mod roles;
+mod routines;
+mod rowfilter;
+mod schema;
+mod schema_cache;
+mod script;
+mod scripting;
+mod secrets;
+mod session;
+mod share;
+mod snippets;
+mod sql_dump;
+mod sql_kind;
+mod statements;
+mod store;
+mod streaming;
+mod table_copy;
+mod table_sizes;
+mod tasks;
+mod templates;
+mod timeouts;
+mod timeseries;
+mod timezone;
+mod transfer;
+mod triggers;
+mod tunnels;
+mod updater;
+mod usage;
+mod variables;
+mod view_definitions;
+mod views;
+mod watch;
+mod workspaces;
+mod xlsx_export;
+
+use iam::IamAuth;
+use secrets::SecretKind;
+use variables::Placeholder;
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 struct SshConfig {
   host: String,
@@ -46,6 +128,31 @@ struct SshConfig {
   #[serde(default)] // This ensures missing field in JSON becomes None
   #[allow(dead_code)]
   private_key_path: Option<String>,
+  /// Authenticate with the keys held by the local ssh-agent (Pageant / OpenSSH agent on
+  /// Windows, `SSH_AUTH_SOCK` elsewhere) instead of a password.
+  #[serde(default)]
+  use_agent: bool,
+  /// Bastions to pass through, in order, before `host`; `host` is reached from the last one.
+  #[serde(default)]
+  jump_hosts: Vec<SshHop>,
+}
+
+/// Most jump hosts a tunnel may chain through.
+const MAX_JUMP_HOSTS: usize = 8;
+
+const SSH_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// One SSH login along a tunnel's route.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SshHop {
+  host: String,
+  port: u16,
+  username: String,
+  #[serde(default)]
+  password: Option<String>,
+  #[serde(default)]
+  use_agent: bool,
 }
 
 #[derive(Clone)]
@@ -60,41 +167,233 @@ impl client::Handler for ClientHandler {
 }
 
 struct AppState {
-  redis_client: Mutex<Option<redis::Client>>,
-  mysql_pool: Mutex<Option<MySqlPool>>,
-  pg_pool: Mutex<Option<PgPool>>,
-  sqlite_pool: Mutex<Option<SqlitePool>>,
-  mongo_client: Mutex<Option<Client>>,
-  ssh_sessions: Mutex<HashMap<String, Arc<AsyncMutex<client::Handle<ClientHandler>>>>>,
+  connections: Mutex<connections::Connections>,
+  tunnels: Mutex<tunnels::Tunnels>,
+  supervisors: Mutex<reconnect::Supervisors>,
+  health: Mutex<HashMap<String, health::ConnectionHealth>>,
+  confirmations: Mutex<confirm::Confirmations>,
   is_pinned: Mutex<bool>,
+  variables: Mutex<HashMap<String, HashMap<String, String>>>,
+  masking: Mutex<HashMap<String, masking::MaskingConfig>>,
+  row_watches: Mutex<watch::RowWatches>,
+  table_tails: Mutex<watch::TableTails>,
+  change_feeds: Mutex<cdc::ChangeFeeds>,
+  pg_listeners: Mutex<notify::Listeners>,
+  results: Mutex<results::ResultCache>,
+  templates: Mutex<HashMap<String, templates::SqlTemplate>>,
+  statement_templates: Mutex<HashMap<String, statements::StatementTemplate>>,
+  display_timezones: Mutex<HashMap<String, chrono_tz::Tz>>,
+  payloads: Mutex<payload::Payloads>,
+  scripting: Mutex<Option<scripting::Server>>,
+  /// Connections whose writes are refused.
+  read_only: Mutex<HashSet<String>>,
+  transfers: Mutex<transfer::Transfers>,
+  console_cursors: Mutex<console::Cursors>,
+  tasks: Mutex<tasks::Tasks>,
+  running_queries: Mutex<cancel::RunningQueries>,
+  statement_timeouts: Mutex<timeouts::StatementTimeouts>,
+  row_streams: Mutex<streaming::RowStreams>,
+  sessions: Mutex<session::Sessions>,
+  widget_sizes: Mutex<display::WidgetSizes>,
+  updates: Mutex<updater::Updates>,
+  usage: Mutex<usage::PendingUsage>,
+  sharing: Mutex<share::Sharing>,
+  schema_cache: Mutex<schema_cache::SchemaCache>,
+  /// App-local database, opened during setup.
+  store: Mutex<Option<sqlx::SqlitePool>>,
+  /// Name of the active workspace.
+  workspace: Mutex<String>,
+  /// Set during setup, so background tasks can emit events; `None` in CLI mode.
+  app: Mutex<Option<tauri::AppHandle>>,
+}
+
+impl AppState {
+  fn new() -> Self {
+    AppState {
+      connections: Mutex::new(connections::Connections::default()),
+      tunnels: Mutex::new(HashMap::new()),
+      supervisors: Mutex::new(reconnect::Supervisors::default()),
+      health: Mutex::new(HashMap::new()),
+      confirmations: Mutex::new(HashMap::new()),
+      is_pinned: Mutex::new(true),
+      variables: Mutex::new(HashMap::new()),
+      masking: Mutex::new(HashMap::new()),
+      row_watches: Mutex::new(HashMap::new()),
+      table_tails: Mutex::new(HashMap::new()),
+      change_feeds: Mutex::new(HashMap::new()),
+      pg_listeners: Mutex::new(HashMap::new()),
+      results: Mutex::new(results::ResultCache::default()),
+      templates: Mutex::new(HashMap::new()),
+      statement_templates: Mutex::new(HashMap::new()),
+      display_timezones: Mutex::new(HashMap::new()),
+      payloads: Mutex::new(payload::Payloads::default()),
+      scripting: Mutex::new(None),
+      read_only: Mutex::new(HashSet::new()),
+      transfers: Mutex::new(transfer::Transfers::default()),
+      console_cursors: Mutex::new(HashMap::new()),
+      tasks: Mutex::new(HashMap::new()),
+      running_queries: Mutex::new(HashMap::new()),
+      statement_timeouts: Mutex::new(HashMap::new()),
+      row_streams: Mutex::new(HashMap::new()),
+      sessions: Mutex::new(HashMap::new()),
+      widget_sizes: Mutex::new(HashMap::new()),
+      updates: Mutex::new(updater::Updates::default()),
+      usage: Mutex::new(HashMap::new()),
+      sharing: Mutex::new(share::Sharing::default()),
+      schema_cache: Mutex::new(schema_cache::SchemaCache::default()),
+      store: Mutex::new(None),
+      workspace: Mutex::new(workspaces::DEFAULT_WORKSPACE.to_string()),
+      app: Mutex::new(None),
+    }
+  }
+}
+
+static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
+/// Process-unique id for handles returned to the frontend (watches, cached results, ...).
+pub(crate) fn next_id(prefix: &str) -> String {
+  let n = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+  format!("{}-{}", prefix, n)
 }
 
 // ... (existing commands) ...
 
-async fn establish_ssh_tunnel(
-  ssh_config: SshConfig,
-  remote_host: String,
-  remote_port: u16,
-) -> Result<(u16, Arc<AsyncMutex<client::Handle<ClientHandler>>>), String> {
-  let config = client::Config::default();
-  let config = Arc::new(config);
-  let sh = ClientHandler;
+/// Tries each identity the agent offers until the server accepts one.
+async fn authenticate_with<S: AgentStream + Send + Unpin + 'static>(
+  session: &mut client::Handle<ClientHandler>,
+  username: &str,
+  mut agent: AgentClient<S>,
+) -> Result<(), String> {
+  let identities = agent
+    .request_identities()
+    .await
+    .map_err(|e| format!("SSH agent error: {}", e))?;
+  if identities.is_empty() {
+    return Err("SSH agent has no identities loaded".to_string());
+  }
+  for key in identities {
+    let accepted = session
+      .authenticate_publickey_with(username, key, &mut agent)
+      .await
+      .map_err(|e| format!("SSH Auth Error: {}", e))?;
+    if accepted {
+      return Ok(());
+    }
+  }
+  Err("SSH server rejected every key offered by the agent".to_string())
+}
 
-  let mut session = client::connect(config, (ssh_config.host.as_str(), ssh_config.port), sh)
+#[cfg(unix)]
+async fn authenticate_with_agent(
+  session: &mut client::Handle<ClientHandler>,
+  username: &str,
+) -> Result<(), String> {
+  let agent = AgentClient::connect_env()
     .await
-    .map_err(|e| format!("SSH Connect Error: {}", e))?;
+    .map_err(|e| format!("SSH agent unavailable: {}", e))?;
+  authenticate_with(session, username, agent).await
+}
+
+#[cfg(windows)]
+async fn authenticate_with_agent(
+  session: &mut client::Handle<ClientHandler>,
+  username: &str,
+) -> Result<(), String> {
+  // The Windows OpenSSH agent listens on a named pipe; fall back to Pageant otherwise
+  match AgentClient::connect_named_pipe(r"\\.\pipe\openssh-ssh-agent").await {
+    Ok(agent) => authenticate_with(session, username, agent).await,
+    Err(_) => authenticate_with(session, username, AgentClient::connect_pageant().await).await,
+  }
+}
 
-  if let Some(pwd) = ssh_config.password {
-    session
-      .authenticate_password(ssh_config.username, pwd)
+async fn authenticate(
+  session: &mut client::Handle<ClientHandler>,
+  hop: &SshHop,
+) -> Result<(), String> {
+  if hop.use_agent {
+    authenticate_with_agent(session, &hop.username).await
+  } else if let Some(pwd) = &hop.password {
+    let accepted = session
+      .authenticate_password(hop.username.clone(), pwd.clone())
       .await
       .map_err(|e| format!("SSH Auth Error: {}", e))?;
+    if accepted {
+      Ok(())
+    } else {
+      Err(format!(
+        "SSH Auth Error: {} rejected the password",
+        hop.host
+      ))
+    }
   } else {
-    return Err("Only password auth supported for now".to_string());
+    Err("SSH tunnels need a password or ssh-agent authentication".to_string())
+  }
+}
+
+/// Opens the tunnel's SSH sessions, reaching each jump host and finally `host` over a
+/// direct-tcpip channel of the previous session. The last session is the target; the ones
+/// before it carry it and must stay alive for as long as it is used.
+async fn connect_ssh_chain(
+  ssh_config: SshConfig,
+) -> Result<Vec<client::Handle<ClientHandler>>, String> {
+  if ssh_config.jump_hosts.len() > MAX_JUMP_HOSTS {
+    return Err(format!(
+      "SSH tunnels support at most {} jump hosts",
+      MAX_JUMP_HOSTS
+    ));
+  }
+  // Keepalives let a dead bastion or server close the session instead of hanging it
+  let config = Arc::new(client::Config {
+    keepalive_interval: Some(SSH_KEEPALIVE_INTERVAL),
+    keepalive_max: 3,
+    ..Default::default()
+  });
+  let target = SshHop {
+    host: ssh_config.host,
+    port: ssh_config.port,
+    username: ssh_config.username,
+    password: ssh_config.password,
+    use_agent: ssh_config.use_agent,
+  };
+
+  let mut sessions: Vec<client::Handle<ClientHandler>> = Vec::new();
+  for hop in ssh_config
+    .jump_hosts
+    .into_iter()
+    .chain(std::iter::once(target))
+  {
+    let mut session = match sessions.last() {
+      None => client::connect(config.clone(), (hop.host.as_str(), hop.port), ClientHandler)
+        .await
+        .map_err(|e| format!("SSH Connect Error: {}", e))?,
+      Some(previous) => {
+        let channel = previous
+          .channel_open_direct_tcpip(hop.host.clone(), u32::from(hop.port), "127.0.0.1", 0)
+          .await
+          .map_err(|e| format!("SSH Jump Error ({}:{}): {}", hop.host, hop.port, e))?;
+        client::connect_stream(config.clone(), channel.into_stream(), ClientHandler)
+          .await
+          .map_err(|e| format!("SSH Connect Error ({}): {}", hop.host, e))?
+      }
+    };
+    authenticate(&mut session, &hop).await?;
+    sessions.push(session);
   }
+  Ok(sessions)
+}
+
+/// Forwards `local_port` (any free port when 0) to `remote_host:remote_port` over SSH.
+async fn establish_ssh_tunnel(
+  ssh_config: SshConfig,
+  remote_host: String,
+  remote_port: u16,
+  local_port: u16,
+) -> Result<tunnels::Forwarding, String> {
+  let mut jumps = connect_ssh_chain(ssh_config).await?;
+  let session = jumps.pop().ok_or("SSH tunnel has no target host")?;
 
   let session = Arc::new(AsyncMutex::new(session));
-  let listener = TcpListener::bind("127.0.0.1:0")
+  let listener = TcpListener::bind(("127.0.0.1", local_port))
     .await
     .map_err(|e| e.to_string())?;
   let local_port = listener.local_addr().map_err(|e| e.to_string())?.port();
@@ -103,64 +402,109 @@ async fn establish_ssh_tunnel(
   let r_host = remote_host.clone();
   let r_port = remote_port;
 
-  tokio::spawn(async move {
-    loop {
-      if let Ok((stream, _)) = listener.accept().await {
-        let handle = loop_handle.lock().await;
-        let mut channel = match handle
-          .channel_open_direct_tcpip(r_host.clone(), r_port as u32, "127.0.0.1", 0)
-          .await
-        {
-          Ok(c) => c.into_stream(),
-          Err(e) => {
-            eprintln!("Failed to open channel: {}", e);
-            continue;
-          }
-        };
+  let listener = tokio::spawn(async move {
+    // Owned here so that aborting the listener also ends the forwards it started
+    let mut forwards = tokio::task::JoinSet::new();
+    while let Ok((mut stream, _)) = listener.accept().await {
+      while forwards.try_join_next().is_some() {}
+      let handle = loop_handle.lock().await;
+      let mut channel = match handle
+        .channel_open_direct_tcpip(r_host.clone(), u32::from(r_port), "127.0.0.1", 0)
+        .await
+      {
+        Ok(c) => c.into_stream(),
+        Err(e) => {
+          tracing::warn!("Failed to open channel: {}", e);
+          continue;
+        }
+      };
 
-        tokio::spawn(async move {
-          let mut stream = stream;
-          // channel needs key handling? No, generic.
-          let _ = tokio::io::copy_bidirectional(&mut stream, &mut channel).await;
-        });
-      } else {
-        break;
-      }
+      forwards.spawn(async move {
+        let _ = tokio::io::copy_bidirectional(&mut stream, &mut channel).await;
+      });
     }
   });
 
-  Ok((local_port, session))
+  Ok(tunnels::Forwarding {
+    local_port,
+    session,
+    jumps,
+    listener,
+  })
 }
 
 #[tauri::command]
-async fn connect_sqlite(state: State<'_, AppState>, path: String) -> Result<String, String> {
+async fn connect_sqlite(
+  state: State<'_, AppState>,
+  path: String,
+  connection_id: Option<String>,
+  pool_options: Option<pools::PoolOptions>,
+) -> Result<String, String> {
+  open_sqlite(&state, path, connection_id, pool_options).await
+}
+
+pub(crate) async fn open_sqlite(
+  state: &AppState,
+  path: String,
+  connection_id: Option<String>,
+  pool_options: Option<pools::PoolOptions>,
+) -> Result<String, String> {
+  let id = connections::resolve_id(connection_id, "sqlite")?;
+  connections::ensure_available(state, &id, "sqlite")?;
   let url = format!("sqlite://{}", path);
   // Ensure the file exists? sqlite usually creates if not exists + create_if_missing(true)
-  let pool = SqlitePoolOptions::new()
-    .max_connections(5)
+  let pool = pool_options
+    .unwrap_or_default()
+    .builder::<sqlx::Sqlite>(None)?
+    .after_release(|conn, _| Box::pin(cancel::release_sqlite(conn)))
     .connect(&url)
     .await
     .map_err(|e| e.to_string())?;
 
-  *state.sqlite_pool.lock().unwrap() = Some(pool);
+  let previous = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .sqlite
+    .insert(id.clone(), pool);
+  schema_cache::invalidate(state, &id, None);
+  console::close_for(state, &id);
+  if let Some(previous) = previous {
+    previous.close().await;
+  }
   Ok("Connected to SQLite".to_string())
 }
 
 #[tauri::command]
-async fn disconnect_sqlite(state: State<'_, AppState>) -> Result<(), String> {
-  let pool = state.sqlite_pool.lock().unwrap().take();
+async fn disconnect_sqlite(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  let id = connection_id.unwrap_or_else(|| "sqlite".to_string());
+  let pool = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .sqlite
+    .remove(&id);
+  console::close_for(&state, &id);
   if let Some(pool) = pool {
     pool.close().await;
   }
+  schema_cache::invalidate(&state, &id, None);
   Ok(())
 }
 
 #[tauri::command]
-async fn sqlite_get_tables(state: State<'_, AppState>) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn sqlite_get_tables(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  let id = connection_id.as_deref().unwrap_or("sqlite");
+  if let Some(tables) = schema_cache::get(&state, id, schema_cache::Kind::Tables, "") {
+    return Ok(tables);
+  }
 
   let rows: Vec<(String,)> = sqlx::query_as(
     "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'",
@@ -169,20 +513,77 @@ async fn sqlite_get_tables(state: State<'_, AppState>) -> Result<Vec<String>, St
   .await
   .map_err(|e| e.to_string())?;
 
+  let tables = rows.into_iter().map(|(name,)| name).collect();
+  Ok(schema_cache::insert(
+    &state,
+    id,
+    schema_cache::Kind::Tables,
+    "",
+    tables,
+  ))
+}
+
+#[tauri::command]
+async fn sqlite_get_views(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+
+  let rows: Vec<(String,)> =
+    sqlx::query_as("SELECT name FROM sqlite_master WHERE type = 'view' ORDER BY name")
+      .fetch_all(&pool)
+      .await
+      .map_err(|e| e.to_string())?;
+
   Ok(rows.into_iter().map(|(name,)| name).collect())
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn sqlite_get_rows(
   state: State<'_, AppState>,
   table_name: String,
   limit: i64,
   offset: i64,
-) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  connection_id: Option<String>,
+  query_id: Option<String>,
+  timeout_ms: Option<u64>,
+  keyset: Option<bool>,
+  after_pk: Option<String>,
+  filters: Option<Vec<views::ViewFilter>>,
+  sort: Option<Vec<views::ViewSort>>,
+  search: Option<String>,
+) -> Result<keyset::RowsPage, String> {
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  let timeout = timeouts::resolve(
+    &state,
+    connection_id.as_deref().unwrap_or("sqlite"),
+    timeout_ms,
+  );
+  usage::record_table(&state, connection_id.as_deref(), &table_name);
+  let sql_pool = db::SqlPool::Sqlite(pool.clone());
+  let filter = rowfilter::build(
+    &sql_pool,
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+    filters.as_deref().unwrap_or_default(),
+    sort.as_deref().unwrap_or_default(),
+    search.as_deref(),
+  )
+  .await?;
+  let key = keyset::key_for(
+    &sql_pool,
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+    &filter,
+    keyset,
+    after_pk.as_deref(),
+  )
+  .await?;
+  let mut binds = filter.binds.clone();
+  binds.extend(after_pk.clone());
+  let columns = schema::table_schema(&sql_pool, db::PG_DEFAULT_SCHEMA, &table_name).await?;
 
   // 1. Fetch PK for stable ordering (convention: look for PK in PRAGMA table_info)
   // Or just "rowid" if not present? stick to simple for now.
@@ -190,109 +591,156 @@ async fn sqlite_get_rows(
   // Querying PRAGMA table_info is a bit structured.
   // Let's just do simplistic Select. User can request stable sort later if needed.
 
+  let quoted_key = key.as_deref().map(ident::quote_sqlite).transpose()?;
   let q = format!(
-    "SELECT * FROM \"{}\" LIMIT {} OFFSET {}",
-    table_name, limit, offset
+    "SELECT * FROM {}{}",
+    ident::quote_sqlite(&table_name)?,
+    keyset::page_clause(
+      &filter,
+      quoted_key.as_deref(),
+      after_pk
+        .as_ref()
+        .map(|_| filter.next_placeholder(&sql_pool))
+        .as_deref(),
+      limit,
+      offset
+    )
   );
 
-  let rows = sqlx::query(&q)
-    .fetch_all(&pool)
+  let (mut conn, running) = cancel::sqlite(
+    &state,
+    query_id,
+    connection_id.as_deref().unwrap_or("sqlite"),
+    &pool,
+    timeout,
+  )
+  .await?;
+  let mut query = sqlx::query(&q);
+  for bind in &binds {
+    query = query.bind(bind);
+  }
+  let rows = query
+    .fetch_all(&mut *conn)
     .await
-    .map_err(|e| e.to_string())?;
+    .map_err(|e| running.error(e))?;
+  drop(running);
 
   // Manual JSON conversion
-  let mut json_rows = Vec::new();
-  for row in rows {
-    let mut map = serde_json::Map::new();
-    for col in row.columns() {
-      let name = col.name();
-      // In SQLite, types are dynamic. We try to read based on storage class.
-      // sqlx::Row::try_get is strongly typed.
-      // We can check type_info.
-      // Simplified: Try Text, then others?
-      // Better: use `try_get_raw` and check `type_info`.
-
-      // To simplify logic, we can try to cast everything to string in SQL or handle basic types here.
-      // Let's attempt to get as String first, then standard types if failure?
-      // Actually, Sqlite values can be cast to String easily.
-      // But we want JSON numbers/bools if possible.
-
-      // Hacky but robust: just get everything as String for the viewer?
-      // "Viewer" usually expects strings for editing inputs.
-      // Let's stick to ALL STRINGS for consistency with the Postgres implementation (row_to_json does strings for safety often).
-      // Wait, standard `row_to_json` in Postgres preserves types (Sort of).
-      // But our Frontend treats `pendingChanges` as strings.
-      // Let's try to get as String (TEXT) from DB.
-
-      // `row.try_get::<String, _>(col.ordinal())` might fail if it's an INT.
-      // `row.try_get::<i64, _>(col.ordinal())` ...
-
-      // Let's use `sqlx::ValueRef`.
-      let raw_val = row.try_get_raw(col.ordinal()).unwrap();
-      if raw_val.is_null() {
-        map.insert(name.to_string(), serde_json::Value::Null);
-      } else {
-        let type_info = raw_val.type_info();
-        let type_name = type_info.name();
-        match type_name {
-          "INTEGER" => {
-            let v: i64 = row.get(col.ordinal());
-            map.insert(name.to_string(), serde_json::Value::Number(v.into()));
-          }
-          "REAL" => {
-            let v: f64 = row.get(col.ordinal());
-            map.insert(name.to_string(), serde_json::Value::from(v));
-          }
-          "BOOLEAN" => {
-            let v: bool = row.get(col.ordinal());
-            map.insert(name.to_string(), serde_json::Value::Bool(v));
-          }
-          _ => {
-            let v: String = row.get(col.ordinal());
-            map.insert(name.to_string(), serde_json::Value::String(v));
-          }
-        }
-      }
+  let mut maps: Vec<db::JsonRow> = rows.iter().map(db::sqlite_row_to_json).collect();
+  let next_cursor = key
+    .as_ref()
+    .and_then(|key| keyset::next_cursor(maps.last(), maps.len(), key, limit));
+  let mask = masking::active(&state, connection_id.as_deref().unwrap_or("sqlite"));
+  if let Some(mask) = &mask {
+    for map in &mut maps {
+      mask.apply(map);
     }
-    json_rows.push(serde_json::Value::Object(map).to_string());
   }
 
-  Ok(json_rows)
+  transfer::record(
+    &state,
+    connection_id.as_deref().unwrap_or("sqlite"),
+    transfer::Category::Browse,
+    maps.len(),
+    transfer::rows_bytes(&maps),
+  );
+  Ok(keyset::RowsPage::new(columns, maps, next_cursor))
+}
+
+/// WHERE condition matching a row by the values of its primary key columns, joining
+/// `column_condition(column, n)` for each with AND, where `n` is the placeholder number of its value
+/// counting from `first`. Values bind in the map's order.
+fn pk_condition(
+  pk: &BTreeMap<String, String>,
+  first: usize,
+  column_condition: impl Fn(&str, usize) -> Result<String, String>,
+) -> Result<String, String> {
+  if pk.is_empty() {
+    return Err("A primary key value is required to identify the row".to_string());
+  }
+  Ok(
+    pk.keys()
+      .enumerate()
+      .map(|(i, column)| column_condition(column, first + i))
+      .collect::<Result<Vec<_>, _>>()?
+      .join(" AND "),
+  )
+}
+
+/// Audit parameters of a statement binding `values` followed by the primary key values.
+fn pk_params(values: Vec<serde_json::Value>, pk: &BTreeMap<String, String>) -> serde_json::Value {
+  values
+    .into_iter()
+    .chain(pk.values().map(|v| serde_json::Value::String(v.clone())))
+    .collect()
+}
+
+/// `table` qualified with its Postgres `schema`, for generated statements.
+fn pg_table(schema: &str, table: &str) -> Result<String, String> {
+  Ok(format!(
+    "{}.{}",
+    ident::quote_pg(schema)?,
+    ident::quote_pg(table)?
+  ))
+}
+
+/// Schema cache key of a Postgres `table` (`""` for the table list): qualified outside
+/// `public`, so equally named tables of other schemas don't share an entry.
+fn pg_cache_key(schema: &str, table: &str) -> String {
+  match schema {
+    db::PG_DEFAULT_SCHEMA => table.to_string(),
+    schema => format!("{}.{}", schema, table),
+  }
 }
 
 #[tauri::command]
 async fn sqlite_update_cell(
   state: State<'_, AppState>,
   table_name: String,
-  pk_col: String,
-  pk_val: String,
+  pk: BTreeMap<String, String>,
   col_name: String,
-  new_val: String,
+  new_val: db::CellValue,
+  connection_id: Option<String>,
 ) -> Result<u64, String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("sqlite"))?;
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
 
   // SQLite is dynamic, but we can try to bind as string and let SQLite coerce,
   // OR format the query carefully.
   // Parameter binding `?` works well.
-  // WHERE clause needs to match PK.
+  // WHERE clause needs to match every PK column.
 
   // Safety: table/col names must be escaped quotes.
-  // `pk_val` is passed as string from frontend. We bind it as string.
+  // PK values are passed as strings from frontend. We bind them as strings.
 
+  let condition = pk_condition(&pk, 2, |col, _| {
+    Ok(format!("{} = ?", ident::quote_sqlite(col)?))
+  })?;
   let q = format!(
-    "UPDATE \"{}\" SET \"{}\" = ? WHERE \"{}\" = ?",
-    table_name, col_name, pk_col
+    "UPDATE {} SET {} = {} WHERE {}",
+    ident::quote_sqlite(&table_name)?,
+    ident::quote_sqlite(&col_name)?,
+    new_val.sql("?"),
+    condition
   );
 
-  let result = sqlx::query(&q)
-    .bind(new_val) // Bind as string, SQLite attempts coercion
-    .bind(pk_val)
-    .execute(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
+  let params = pk_params(vec![new_val.audit()], &pk);
+  let mut query = sqlx::query(&q);
+  if new_val.is_bound() {
+    query = query.bind(new_val.bound()); // Bind as string (or NULL), SQLite attempts coercion
+  }
+  for value in pk.into_values() {
+    query = query.bind(value);
+  }
+  let result = query.execute(&pool).await.map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("sqlite"),
+    &q,
+    params,
+    Some(result.rows_affected()),
+  )
+  .await;
 
   Ok(result.rows_affected())
 }
@@ -301,53 +749,55 @@ async fn sqlite_update_cell(
 async fn sqlite_get_primary_key(
   state: State<'_, AppState>,
   table_name: String,
-) -> Result<Option<String>, String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-
-  // PRAGMA table_info(table_name)
-  // returns columns: cid, name, type, notnull, dflt_value, pk
-  let q = format!("PRAGMA table_info(\"{}\")", table_name);
-  let rows = sqlx::query(&q)
-    .fetch_all(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
-
-  for row in rows {
-    let pk: i32 = row.get("pk");
-    if pk > 0 {
-      let name: String = row.get("name");
-      return Ok(Some(name));
-    }
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  let id = connection_id.as_deref().unwrap_or("sqlite");
+  let kind = schema_cache::Kind::PrimaryKey;
+  if let Some(key) = schema_cache::get(&state, id, kind, &table_name) {
+    return Ok(key);
   }
-
-  Ok(None)
+  let key = schema::primary_key(
+    &db::SqlPool::Sqlite(pool),
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+  )
+  .await?;
+  Ok(schema_cache::insert(&state, id, kind, &table_name, key))
 }
 
 #[tauri::command]
 fn greet() -> String {
   let now = SystemTime::now();
-  let epoch_ms = now.duration_since(UNIX_EPOCH).unwrap().as_millis();
+  let epoch_ms = now
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_millis();
   format!("Hello from Rust! Time: {}", epoch_ms)
 }
 
 #[tauri::command]
 fn set_pinned(state: State<'_, AppState>, pinned: bool) {
-  *state.is_pinned.lock().unwrap() = pinned;
+  *state
+    .is_pinned
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner) = pinned;
 }
 
 #[tauri::command]
 fn update_click_region(
   window: tauri::Window,
+  state: State<'_, AppState>,
   width: f64,
   height: f64,
   _align_x: String,
   _align_y: String,
 ) {
-  // Standardize on logical size to automatically handle high-DPI scaling
-  let _ = window.set_size(tauri::Size::Logical(tauri::LogicalSize { width, height }));
+  // Standardize on logical size to automatically handle high-DPI scaling; remembered so a
+  // move to a monitor with another scale can re-apply it
+  let size = tauri::LogicalSize { width, height };
+  display::remember_size(&state, window.label(), size);
+  let _ = window.set_size(tauri::Size::Logical(size));
 }
 
 #[tauri::command]
@@ -380,10 +830,10 @@ fn get_screen_work_area_for_webview(window: &tauri::WebviewWindow) -> (f64, f64,
     let size = m.size();
     let pos = m.position();
     (
-      pos.x as f64 / factor,
-      pos.y as f64 / factor,
-      size.width as f64 / factor,
-      size.height as f64 / factor,
+      f64::from(pos.x) / factor,
+      f64::from(pos.y) / factor,
+      f64::from(size.width) / factor,
+      f64::from(size.height) / factor,
     )
   } else {
     (0.0, 0.0, 800.0, 600.0)
@@ -420,10 +870,10 @@ fn get_screen_work_area(window: tauri::Window) -> (f64, f64, f64, f64) {
     let size = m.size();
     let pos = m.position();
     (
-      pos.x as f64 / factor,
-      pos.y as f64 / factor,
-      size.width as f64 / factor,
-      size.height as f64 / factor,
+      f64::from(pos.x) / factor,
+      f64::from(pos.y) / factor,
+      f64::from(size.width) / factor,
+      f64::from(size.height) / factor,
     )
   } else {
     (0.0, 0.0, 800.0, 600.0)
@@ -486,7 +936,35 @@ fn get_all_monitors_work_area(_window: tauri::Window) -> Vec<(f64, f64, f64, f64
   }
 }
 
+// Fallible off Unix
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)]
+fn unix_socket_addr(path: String) -> Result<redis::ConnectionAddr, String> {
+  Ok(redis::ConnectionAddr::Unix(path.into()))
+}
+
+#[cfg(not(unix))]
+fn unix_socket_addr(_path: String) -> Result<redis::ConnectionAddr, String> {
+  Err("Unix socket connections are not supported on this platform".to_string())
+}
+
+/// Splits a Postgres socket argument into the socket directory and, when the full
+/// `.s.PGSQL.<port>` file path was given, the port encoded in its name.
+fn pg_socket_dir(path: &str) -> (std::path::PathBuf, Option<u16>) {
+  let path = std::path::Path::new(path);
+  let port = path
+    .file_name()
+    .and_then(|name| name.to_str())
+    .and_then(|name| name.strip_prefix(".s.PGSQL."))
+    .and_then(|port| port.parse().ok());
+  match (port, path.parent()) {
+    (Some(port), Some(dir)) => (dir.to_path_buf(), Some(port)),
+    _ => (path.to_path_buf(), None),
+  }
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn connect_redis(
   state: State<'_, AppState>,
   host: String,
@@ -494,27 +972,66 @@ async fn connect_redis(
   password: Option<String>,
   timeout_sec: Option<u64>,
   ssh_config: Option<SshConfig>,
+  socket_path: Option<String>,
+  connection_id: Option<String>,
+  profile_id: Option<String>,
+) -> Result<String, String> {
+  let password = secrets::resolve(profile_id.as_deref(), SecretKind::Password, password).await?;
+  let ssh_config = secrets::resolve_ssh(profile_id.as_deref(), ssh_config).await?;
+  open_redis(
+    &state,
+    host,
+    port,
+    password,
+    0,
+    timeout_sec,
+    ssh_config,
+    socket_path,
+    connection_id,
+  )
+  .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn open_redis(
+  state: &AppState,
+  host: String,
+  port: u16,
+  password: Option<String>,
+  db: i64,
+  timeout_sec: Option<u64>,
+  ssh_config: Option<SshConfig>,
+  socket_path: Option<String>,
+  connection_id: Option<String>,
 ) -> Result<String, String> {
+  let id = connections::resolve_id(connection_id, "redis")?;
+  connections::ensure_available(state, &id, "redis")?;
   let timeout_val = Duration::from_secs(timeout_sec.unwrap_or(5));
 
-  let (final_host, final_port) = if let Some(ssh) = ssh_config {
-    let (local_port, handle) = establish_ssh_tunnel(ssh, host.clone(), port).await?;
-    state
-      .ssh_sessions
-      .lock()
-      .unwrap()
-      .insert("redis".to_string(), handle);
-    ("127.0.0.1".to_string(), local_port)
+  let mut tunnel = None;
+  let addr = if let Some(path) = socket_path {
+    if ssh_config.is_some() {
+      return Err("SSH tunnels cannot be combined with a socket path".to_string());
+    }
+    unix_socket_addr(path)?
   } else {
-    (host, port)
+    let (final_host, final_port) = if let Some(ssh) = ssh_config {
+      let opened = tunnels::open(state, &id, ssh, host.clone(), port).await?;
+      let local_port = opened.local_port;
+      tunnel = Some(opened);
+      ("127.0.0.1".to_string(), local_port)
+    } else {
+      (host, port)
+    };
+    redis::ConnectionAddr::Tcp(final_host, final_port)
   };
 
   let client = redis::Client::open(redis::ConnectionInfo {
-    addr: redis::ConnectionAddr::Tcp(final_host, final_port),
+    addr,
     redis: redis::RedisConnectionInfo {
-      db: 0,
+      db,
       username: None,
-      password: password,
+      password,
       ..Default::default()
     },
   })
@@ -531,18 +1048,36 @@ async fn connect_redis(
     .await
     .map_err(|e| e.to_string())?;
 
-  *state.redis_client.lock().unwrap() = Some(client);
+  state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .redis
+    .insert(id.clone(), client);
+  tunnels::connected(state, &id, tunnel);
+  reconnect::clear(state, &id);
   Ok("Connected to Redis".to_string())
 }
 
 #[tauri::command]
-async fn disconnect_redis(state: State<'_, AppState>) -> Result<(), String> {
-  *state.redis_client.lock().unwrap() = None;
-  state.ssh_sessions.lock().unwrap().remove("redis");
+async fn disconnect_redis(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  let id = connection_id.unwrap_or_else(|| "redis".to_string());
+  state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .redis
+    .remove(&id);
+  tunnels::close_for(&state, &id);
+  reconnect::clear(&state, &id);
   Ok(())
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn connect_mysql(
   state: State<'_, AppState>,
   host: String,
@@ -552,20 +1087,76 @@ async fn connect_mysql(
   database: Option<String>,
   timeout_sec: Option<u64>,
   ssh_config: Option<SshConfig>,
+  iam_auth: Option<IamAuth>,
+  socket_path: Option<String>,
+  connection_id: Option<String>,
+  pool_options: Option<pools::PoolOptions>,
+  profile_id: Option<String>,
+) -> Result<String, String> {
+  let password = secrets::resolve(profile_id.as_deref(), SecretKind::Password, password).await?;
+  let ssh_config = secrets::resolve_ssh(profile_id.as_deref(), ssh_config).await?;
+  open_mysql(
+    &state,
+    host,
+    port,
+    username,
+    password,
+    database,
+    timeout_sec,
+    ssh_config,
+    iam_auth,
+    false,
+    socket_path,
+    connection_id,
+    pool_options,
+  )
+  .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn open_mysql(
+  state: &AppState,
+  host: String,
+  port: u16,
+  username: String,
+  password: Option<String>,
+  database: Option<String>,
+  timeout_sec: Option<u64>,
+  ssh_config: Option<SshConfig>,
+  iam_auth: Option<IamAuth>,
+  require_tls: bool,
+  socket_path: Option<String>,
+  connection_id: Option<String>,
+  pool_options: Option<pools::PoolOptions>,
 ) -> Result<String, String> {
-  use sqlx::mysql::MySqlConnectOptions;
+  use sqlx::mysql::{MySqlConnectOptions, MySqlSslMode};
+
+  let id = connections::resolve_id(connection_id, "mysql")?;
+  connections::ensure_available(state, &id, "mysql")?;
 
   let timeout_val = Duration::from_secs(timeout_sec.unwrap_or(5));
+  let pool_builder = pool_options
+    .unwrap_or_default()
+    .builder(Some(timeout_val))?;
   let db = database.unwrap_or_else(|| "mysql".to_string());
 
-  let (final_host, final_port) = if let Some(ssh) = ssh_config {
-    let (local_port, handle) = establish_ssh_tunnel(ssh, host.clone(), port).await?;
-    state
-      .ssh_sessions
-      .lock()
-      .unwrap()
-      .insert("mysql".to_string(), handle);
-    ("127.0.0.1".to_string(), local_port)
+  // IAM tokens are signed for the real endpoint, not the local tunnel port
+  let iam_token = match &iam_auth {
+    Some(auth) => Some(iam::generate_token(auth, &host, port, &username).await?),
+    None => None,
+  };
+  let iam_endpoint = (host.clone(), port);
+
+  if socket_path.is_some() && ssh_config.is_some() {
+    return Err("SSH tunnels cannot be combined with a socket path".to_string());
+  }
+
+  let tunnel = match ssh_config {
+    Some(ssh) => Some(tunnels::open(state, &id, ssh, host.clone(), port).await?),
+    None => None,
+  };
+  let (final_host, final_port) = if let Some(opened) = &tunnel {
+    ("127.0.0.1".to_string(), opened.local_port)
   } else {
     (host, port)
   };
@@ -576,34 +1167,109 @@ async fn connect_mysql(
     .username(&username)
     .database(&db);
 
-  if let Some(pwd) = password {
+  if let Some(path) = &socket_path {
+    options = options.socket(path);
+  }
+
+  if require_tls {
+    options = options.ssl_mode(MySqlSslMode::Required);
+  }
+
+  if iam_token.is_some() {
+    // Managed instances only accept IAM tokens over TLS via the cleartext plugin
+    options = options
+      .ssl_mode(MySqlSslMode::Required)
+      .enable_cleartext_plugin(true);
+  } else if let Some(pwd) = password {
     if !pwd.is_empty() {
       options = options.password(&pwd);
     }
   }
 
-  let pool = MySqlPoolOptions::new()
-    .max_connections(5)
-    .acquire_timeout(timeout_val)
-    .connect_with(options)
+  let session = session::fresh();
+  let pool = pool_builder
+    .after_connect({
+      let session = session.clone();
+      move |conn, _| {
+        let (_, restore) = session::mysql_statements(&session);
+        Box::pin(session::restore_mysql(conn, restore))
+      }
+    })
+    .before_acquire({
+      let session = session.clone();
+      move |conn, meta| {
+        let outdated =
+          session::predates_change(&session, &meta).then(|| session::mysql_statements(&session));
+        Box::pin(async move {
+          match outdated {
+            Some((generation, restore)) => session::refresh_mysql(conn, generation, restore).await,
+            None => Ok(true),
+          }
+        })
+      }
+    })
+    .connect_with(match &iam_token {
+      Some(token) => options.clone().password(&token.token),
+      None => options.clone(),
+    })
     .await
     .map_err(|e| e.to_string())?;
 
-  *state.mysql_pool.lock().unwrap() = Some(pool);
+  if let (Some(auth), Some(token)) = (iam_auth, iam_token) {
+    let (iam_host, iam_port) = iam_endpoint;
+    iam::spawn_refresh(
+      pool.clone(),
+      auth,
+      iam_host,
+      iam_port,
+      username,
+      token.refresh_after,
+      move |token| options.clone().password(token),
+    );
+  }
+
+  let previous = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .mysql
+    .insert(id.clone(), pool);
+  tunnels::connected(state, &id, tunnel);
+  session::register(state, &id, session);
+  reconnect::clear(state, &id);
+  schema_cache::invalidate(state, &id, None);
+  console::close_for(state, &id);
+  if let Some(previous) = previous {
+    previous.close().await;
+  }
   Ok("Connected to MySQL".to_string())
 }
 
 #[tauri::command]
-async fn disconnect_mysql(state: State<'_, AppState>) -> Result<(), String> {
-  let pool = state.mysql_pool.lock().unwrap().take();
+async fn disconnect_mysql(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  let id = connection_id.unwrap_or_else(|| "mysql".to_string());
+  let pool = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .mysql
+    .remove(&id);
+  console::close_for(&state, &id);
   if let Some(pool) = pool {
     pool.close().await;
   }
-  state.ssh_sessions.lock().unwrap().remove("mysql");
+  tunnels::close_for(&state, &id);
+  reconnect::clear(&state, &id);
+  session::forget(&state, &id);
+  schema_cache::invalidate(&state, &id, None);
   Ok(())
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn connect_postgres(
   state: State<'_, AppState>,
   host: String,
@@ -613,20 +1279,98 @@ async fn connect_postgres(
   database: Option<String>,
   timeout_sec: Option<u64>,
   ssh_config: Option<SshConfig>,
+  iam_auth: Option<IamAuth>,
+  socket_path: Option<String>,
+  connection_id: Option<String>,
+  pool_options: Option<pools::PoolOptions>,
+  profile_id: Option<String>,
+) -> Result<String, String> {
+  let password = secrets::resolve(profile_id.as_deref(), SecretKind::Password, password).await?;
+  let ssh_config = secrets::resolve_ssh(profile_id.as_deref(), ssh_config).await?;
+  open_postgres(
+    &state,
+    host,
+    port,
+    username,
+    password,
+    database,
+    timeout_sec,
+    ssh_config,
+    iam_auth,
+    false,
+    socket_path,
+    connection_id,
+    pool_options,
+  )
+  .await
+}
+
+/// Applies TLS and the password to Postgres connect options. With IAM the token is set as
+/// the password when connecting, so `password` is left out.
+pub(crate) fn pg_auth_options(
+  mut options: sqlx::postgres::PgConnectOptions,
+  password: Option<&str>,
+  iam: bool,
+  require_tls: bool,
+) -> sqlx::postgres::PgConnectOptions {
+  // RDS and Cloud SQL reject IAM tokens over unencrypted connections. Required TLS doesn't
+  // check the certificate's host name, so it also works through a tunnel.
+  if iam || require_tls {
+    options = options.ssl_mode(sqlx::postgres::PgSslMode::Require);
+  }
+
+  if !iam {
+    if let Some(pwd) = password.filter(|pwd| !pwd.is_empty()) {
+      options = options.password(pwd);
+    }
+  }
+  options
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn open_postgres(
+  state: &AppState,
+  host: String,
+  port: u16,
+  username: String,
+  password: Option<String>,
+  database: Option<String>,
+  timeout_sec: Option<u64>,
+  ssh_config: Option<SshConfig>,
+  iam_auth: Option<IamAuth>,
+  require_tls: bool,
+  socket_path: Option<String>,
+  connection_id: Option<String>,
+  pool_options: Option<pools::PoolOptions>,
 ) -> Result<String, String> {
   use sqlx::postgres::{PgConnectOptions, PgSslMode};
 
+  let id = connections::resolve_id(connection_id, "postgres")?;
+  connections::ensure_available(state, &id, "postgres")?;
+
   let timeout_val = Duration::from_secs(timeout_sec.unwrap_or(5));
+  let pool_builder = pool_options
+    .unwrap_or_default()
+    .builder(Some(timeout_val))?;
   let db = database.unwrap_or_else(|| "postgres".to_string());
 
-  let (final_host, final_port) = if let Some(ssh) = ssh_config {
-    let (local_port, handle) = establish_ssh_tunnel(ssh, host.clone(), port).await?;
-    state
-      .ssh_sessions
-      .lock()
-      .unwrap()
-      .insert("postgres".to_string(), handle);
-    ("127.0.0.1".to_string(), local_port)
+  // IAM tokens are signed for the real endpoint, not the local tunnel port
+  let iam_token = match &iam_auth {
+    Some(auth) => Some(iam::generate_token(auth, &host, port, &username).await?),
+    None => None,
+  };
+  let iam_endpoint = (host.clone(), port);
+
+  if socket_path.is_some() && ssh_config.is_some() {
+    return Err("SSH tunnels cannot be combined with a socket path".to_string());
+  }
+
+  let tunnel = match ssh_config {
+    Some(ssh) => Some(tunnels::open(state, &id, ssh, host.clone(), port).await?),
+    None => None,
+  };
+  let (final_host, final_port) = if let Some(opened) = &tunnel {
+    ("127.0.0.1".to_string(), opened.local_port)
   } else {
     (host, port)
   };
@@ -638,35 +1382,108 @@ async fn connect_postgres(
     .database(&db)
     .ssl_mode(PgSslMode::Disable); // Disable SSL via tunnel to avoid hostname mismatch
 
-  if let Some(pwd) = password {
-    if !pwd.is_empty() {
-      options = options.password(&pwd);
+  if let Some(path) = &socket_path {
+    let (dir, socket_port) = pg_socket_dir(path);
+    options = options.socket(dir);
+    if let Some(socket_port) = socket_port {
+      options = options.port(socket_port);
     }
   }
 
+  options = pg_auth_options(
+    options,
+    password.as_deref(),
+    iam_token.is_some(),
+    require_tls,
+  );
+
   // Attempt to connect
-  let pool = PgPoolOptions::new()
-    .max_connections(5)
-    .acquire_timeout(timeout_val)
-    .connect_with(options)
+  let session = session::fresh();
+  let pool = pool_builder
+    .after_connect({
+      let session = session.clone();
+      move |conn, _| {
+        let (_, restore) = session::postgres_settings(&session);
+        Box::pin(session::restore_postgres(conn, restore))
+      }
+    })
+    .before_acquire({
+      let session = session.clone();
+      move |conn, meta| {
+        let outdated =
+          session::predates_change(&session, &meta).then(|| session::postgres_settings(&session));
+        Box::pin(async move {
+          match outdated {
+            Some((generation, restore)) => {
+              session::refresh_postgres(conn, generation, restore).await
+            }
+            None => Ok(true),
+          }
+        })
+      }
+    })
+    .connect_with(match &iam_token {
+      Some(token) => options.clone().password(&token.token),
+      None => options.clone(),
+    })
     .await
     .map_err(|e| e.to_string())?;
 
-  *state.pg_pool.lock().unwrap() = Some(pool);
+  if let (Some(auth), Some(token)) = (iam_auth, iam_token) {
+    let (iam_host, iam_port) = iam_endpoint;
+    iam::spawn_refresh(
+      pool.clone(),
+      auth,
+      iam_host,
+      iam_port,
+      username,
+      token.refresh_after,
+      move |token| options.clone().password(token),
+    );
+  }
+
+  let previous = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .postgres
+    .insert(id.clone(), pool);
+  tunnels::connected(state, &id, tunnel);
+  session::register(state, &id, session);
+  reconnect::clear(state, &id);
+  schema_cache::invalidate(state, &id, None);
+  console::close_for(state, &id);
+  if let Some(previous) = previous {
+    previous.close().await;
+  }
   Ok("Connected to PostgreSQL".to_string())
 }
 
 #[tauri::command]
-async fn disconnect_postgres(state: State<'_, AppState>) -> Result<(), String> {
-  let pool = state.pg_pool.lock().unwrap().take();
+async fn disconnect_postgres(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  let id = connection_id.unwrap_or_else(|| "postgres".to_string());
+  let pool = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .postgres
+    .remove(&id);
+  console::close_for(&state, &id);
   if let Some(pool) = pool {
     pool.close().await;
   }
-  state.ssh_sessions.lock().unwrap().remove("postgres");
+  tunnels::close_for(&state, &id);
+  reconnect::clear(&state, &id);
+  session::forget(&state, &id);
+  schema_cache::invalidate(&state, &id, None);
   Ok(())
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn connect_mongodb(
   state: State<'_, AppState>,
   host: String,
@@ -675,28 +1492,53 @@ async fn connect_mongodb(
   password: Option<String>,
   timeout_sec: Option<u64>,
   ssh_config: Option<SshConfig>,
+  connection_id: Option<String>,
+  profile_id: Option<String>,
 ) -> Result<String, String> {
-  let timeout_val = Duration::from_secs(timeout_sec.unwrap_or(5));
+  let password = secrets::resolve(profile_id.as_deref(), SecretKind::Password, password).await?;
+  let ssh_config = secrets::resolve_ssh(profile_id.as_deref(), ssh_config).await?;
+  open_mongodb(
+    &state,
+    host,
+    port,
+    username,
+    password,
+    timeout_sec,
+    ssh_config,
+    connection_id,
+  )
+  .await
+}
 
-  let (final_host, final_port) = if let Some(ssh) = ssh_config {
-    let (local_port, handle) = establish_ssh_tunnel(ssh, host.clone(), port).await?;
-    state
-      .ssh_sessions
-      .lock()
-      .unwrap()
-      .insert("mongodb".to_string(), handle);
-    ("127.0.0.1".to_string(), local_port)
-  } else {
-    (host, port)
-  };
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn open_mongodb(
+  state: &AppState,
+  host: String,
+  port: u16,
+  username: Option<String>,
+  password: Option<String>,
+  timeout_sec: Option<u64>,
+  ssh_config: Option<SshConfig>,
+  connection_id: Option<String>,
+) -> Result<String, String> {
+  let id = connections::resolve_id(connection_id, "mongodb")?;
+  connections::ensure_available(state, &id, "mongodb")?;
+  let timeout_val = Duration::from_secs(timeout_sec.unwrap_or(5));
+
+  let tunnel = match ssh_config {
+    Some(ssh) => Some(tunnels::open(state, &id, ssh, host.clone(), port).await?),
+    None => None,
+  };
+  let (final_host, final_port) = if let Some(opened) = &tunnel {
+    ("127.0.0.1".to_string(), opened.local_port)
+  } else {
+    (host, port)
+  };
 
   let mut client_options = ClientOptions::parse(format!("mongodb://{}:{}", final_host, final_port))
     .await
     .map_err(|e| e.to_string())?;
 
-  client_options.connect_timeout = Some(timeout_val);
-  client_options.server_selection_timeout = Some(timeout_val);
-
   if let (Some(u), Some(p)) = (username, password) {
     client_options.credential = Some(
       mongodb::options::Credential::builder()
@@ -706,6 +1548,34 @@ async fn connect_mongodb(
     );
   }
 
+  register_mongodb(state, id, client_options, timeout_val, tunnel).await
+}
+
+/// Connects with a full `mongodb://` or `mongodb+srv://` URI, keeping its hosts, credentials
+/// and options (replica set, auth source, TLS, ...).
+pub(crate) async fn open_mongodb_uri(
+  state: &AppState,
+  uri: &str,
+  timeout_sec: Option<u64>,
+  connection_id: Option<String>,
+) -> Result<String, String> {
+  let id = connections::resolve_id(connection_id, "mongodb")?;
+  connections::ensure_available(state, &id, "mongodb")?;
+  let timeout_val = Duration::from_secs(timeout_sec.unwrap_or(5));
+  let client_options = ClientOptions::parse(uri).await.map_err(|e| e.to_string())?;
+  register_mongodb(state, id, client_options, timeout_val, None).await
+}
+
+async fn register_mongodb(
+  state: &AppState,
+  id: String,
+  mut client_options: ClientOptions,
+  timeout_val: Duration,
+  tunnel: Option<tunnels::Opened<'_>>,
+) -> Result<String, String> {
+  client_options.connect_timeout = Some(timeout_val);
+  client_options.server_selection_timeout = Some(timeout_val);
+
   let client = Client::with_options(client_options).map_err(|e| e.to_string())?;
 
   // Ping the server
@@ -714,14 +1584,29 @@ async fn connect_mongodb(
     .await
     .map_err(|e| e.to_string())?;
 
-  *state.mongo_client.lock().unwrap() = Some(client);
+  state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .mongodb
+    .insert(id.clone(), client);
+  tunnels::connected(state, &id, tunnel);
   Ok("Connected to MongoDB".to_string())
 }
 
 #[tauri::command]
-async fn disconnect_mongodb(state: State<'_, AppState>) -> Result<(), String> {
-  *state.mongo_client.lock().unwrap() = None;
-  state.ssh_sessions.lock().unwrap().remove("mongodb");
+async fn disconnect_mongodb(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  let id = connection_id.unwrap_or_else(|| "mongodb".to_string());
+  state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .mongodb
+    .remove(&id);
+  tunnels::close_for(&state, &id);
   Ok(())
 }
 
@@ -729,11 +1614,9 @@ async fn disconnect_mongodb(state: State<'_, AppState>) -> Result<(), String> {
 async fn redis_get_keys(
   state: State<'_, AppState>,
   pattern: String,
+  connection_id: Option<String>,
 ) -> Result<Vec<String>, String> {
-  let client = {
-    let guard = state.redis_client.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  let client = connections::redis(&state, connection_id.as_deref())?;
   let mut con = client
     .get_multiplexed_async_connection()
     .await
@@ -743,15 +1626,24 @@ async fn redis_get_keys(
     .query_async(&mut con)
     .await
     .map_err(|e| e.to_string())?;
+  let bytes = keys.iter().map(String::len).sum();
+  transfer::record(
+    &state,
+    connection_id.as_deref().unwrap_or("redis"),
+    transfer::Category::Redis,
+    keys.len(),
+    bytes,
+  );
   Ok(keys)
 }
 
 #[tauri::command]
-async fn redis_get_value(state: State<'_, AppState>, key: String) -> Result<String, String> {
-  let client = {
-    let guard = state.redis_client.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn redis_get_value(
+  state: State<'_, AppState>,
+  key: String,
+  connection_id: Option<String>,
+) -> Result<String, String> {
+  let client = connections::redis(&state, connection_id.as_deref())?;
   let mut con = client
     .get_multiplexed_async_connection()
     .await
@@ -763,13 +1655,15 @@ async fn redis_get_value(state: State<'_, AppState>, key: String) -> Result<Stri
     .await
     .map_err(|e| e.to_string())?;
 
-  match key_type.as_str() {
+  let value = match key_type.as_str() {
     "string" => {
       let val: String = redis::cmd("GET")
         .arg(&key)
         .query_async(&mut con)
         .await
         .map_err(|e| e.to_string())?;
+      let id = connection_id.as_deref().unwrap_or("redis");
+      key_history::record(&state, id, &key, &val, key_history::Source::View).await;
       Ok(val)
     }
     "hash" => {
@@ -810,7 +1704,17 @@ async fn redis_get_value(state: State<'_, AppState>, key: String) -> Result<Stri
       serde_json::to_string(&val).map_err(|e| e.to_string())
     }
     _ => Ok(format!("Unsupported type: {}", key_type)),
+  };
+  if let Ok(value) = &value {
+    transfer::record(
+      &state,
+      connection_id.as_deref().unwrap_or("redis"),
+      transfer::Category::Redis,
+      1,
+      value.len(),
+    );
   }
+  value
 }
 
 #[tauri::command]
@@ -818,32 +1722,45 @@ async fn redis_set_value(
   state: State<'_, AppState>,
   key: String,
   value: String,
+  connection_id: Option<String>,
 ) -> Result<(), String> {
-  let client = {
-    let guard = state.redis_client.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("redis"))?;
+  let client = connections::redis(&state, connection_id.as_deref())?;
 
   let mut con = client
     .get_multiplexed_async_connection()
     .await
     .map_err(|e| e.to_string())?;
 
+  // Keep the value being replaced so the overwrite can be undone
+  let id = connection_id.as_deref().unwrap_or("redis");
+  let previous: Option<String> = redis::cmd("GET")
+    .arg(&key)
+    .query_async(&mut con)
+    .await
+    .unwrap_or(None);
+  if let Some(previous) = previous {
+    key_history::record(&state, id, &key, &previous, key_history::Source::BeforeEdit).await;
+  }
+
   let _: () = redis::cmd("SET")
-    .arg(key)
-    .arg(value)
+    .arg(&key)
+    .arg(&value)
     .query_async(&mut con)
     .await
     .map_err(|e| e.to_string())?;
+  key_history::record(&state, id, &key, &value, key_history::Source::Edit).await;
   Ok(())
 }
 
 #[tauri::command]
-async fn redis_del_key(state: State<'_, AppState>, key: String) -> Result<(), String> {
-  let client = {
-    let guard = state.redis_client.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn redis_del_key(
+  state: State<'_, AppState>,
+  key: String,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("redis"))?;
+  let client = connections::redis(&state, connection_id.as_deref())?;
   let mut con = client
     .get_multiplexed_async_connection()
     .await
@@ -857,11 +1774,12 @@ async fn redis_del_key(state: State<'_, AppState>, key: String) -> Result<(), St
 }
 
 #[tauri::command]
-async fn redis_get_ttl(state: State<'_, AppState>, key: String) -> Result<i64, String> {
-  let client = {
-    let guard = state.redis_client.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn redis_get_ttl(
+  state: State<'_, AppState>,
+  key: String,
+  connection_id: Option<String>,
+) -> Result<i64, String> {
+  let client = connections::redis(&state, connection_id.as_deref())?;
   let mut con = client
     .get_multiplexed_async_connection()
     .await
@@ -874,12 +1792,168 @@ async fn redis_get_ttl(state: State<'_, AppState>, key: String) -> Result<i64, S
   Ok(ttl)
 }
 
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RedisFlushed {
+  connection_id: String,
+  /// Flushed database, or `None` for FLUSHALL.
+  db_index: Option<i64>,
+  keys_removed: u64,
+}
+
+/// Total keys over all databases, from `INFO keyspace` lines like `db0:keys=12,expires=0`.
+fn keyspace_total(info: &str) -> u64 {
+  info
+    .lines()
+    .filter(|line| line.starts_with("db"))
+    .filter_map(|line| {
+      line
+        .split_once("keys=")?
+        .1
+        .split(',')
+        .next()?
+        .parse::<u64>()
+        .ok()
+    })
+    .sum()
+}
+
+/// Deletes every key of database `db_index`. Guarded by a typed confirmation
+/// (`FLUSHDB <index>`); emits `redis-flushed` with the number of keys removed.
+#[tauri::command]
+async fn redis_flush_db(
+  app: tauri::AppHandle,
+  state: State<'_, AppState>,
+  db_index: i64,
+  confirmation: Option<confirm::Confirmation>,
+  connection_id: Option<String>,
+) -> Result<confirm::Guarded<RedisFlushed>, String> {
+  let id = connection_id.unwrap_or_else(|| "redis".to_string());
+  readonly::ensure_writable(&state, &id)?;
+  let client = connections::redis(&state, Some(&id))?;
+  let mut con = client
+    .get_multiplexed_async_connection()
+    .await
+    .map_err(|e| e.to_string())?;
+  let _: () = redis::cmd("SELECT")
+    .arg(db_index)
+    .query_async(&mut con)
+    .await
+    .map_err(|e| e.to_string())?;
+  let keys: u64 = redis::cmd("DBSIZE")
+    .query_async(&mut con)
+    .await
+    .map_err(|e| e.to_string())?;
+
+  let action = format!("redis-flushdb:{}:{}", id, db_index);
+  let Some(confirmation) = confirmation else {
+    return Ok(confirm::Guarded::ConfirmationRequired(confirm::challenge(
+      &state,
+      &action,
+      &format!("FLUSHDB {}", db_index),
+      format!(
+        "Deletes all {} keys in database {} of '{}'",
+        keys, db_index, id
+      ),
+    )));
+  };
+  confirm::redeem(&state, &confirmation, &action)?;
+
+  let keys: u64 = redis::cmd("DBSIZE")
+    .query_async(&mut con)
+    .await
+    .map_err(|e| e.to_string())?;
+  let _: () = redis::cmd("FLUSHDB")
+    .query_async(&mut con)
+    .await
+    .map_err(|e| e.to_string())?;
+  let flushed = RedisFlushed {
+    connection_id: id,
+    db_index: Some(db_index),
+    keys_removed: keys,
+  };
+  let _ = tauri::Emitter::emit(&app, "redis-flushed", flushed.clone());
+  Ok(confirm::Guarded::Done(flushed))
+}
+
+/// Deletes every key of every database. Guarded by a typed confirmation (`FLUSHALL`); emits
+/// `redis-flushed` with the number of keys removed.
 #[tauri::command]
-async fn redis_execute_raw(state: State<'_, AppState>, command: String) -> Result<String, String> {
-  let client = {
-    let guard = state.redis_client.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
+async fn redis_flush_all(
+  app: tauri::AppHandle,
+  state: State<'_, AppState>,
+  confirmation: Option<confirm::Confirmation>,
+  connection_id: Option<String>,
+) -> Result<confirm::Guarded<RedisFlushed>, String> {
+  let id = connection_id.unwrap_or_else(|| "redis".to_string());
+  readonly::ensure_writable(&state, &id)?;
+  let client = connections::redis(&state, Some(&id))?;
+  let mut con = client
+    .get_multiplexed_async_connection()
+    .await
+    .map_err(|e| e.to_string())?;
+  let info: String = redis::cmd("INFO")
+    .arg("keyspace")
+    .query_async(&mut con)
+    .await
+    .map_err(|e| e.to_string())?;
+
+  let action = format!("redis-flushall:{}", id);
+  let Some(confirmation) = confirmation else {
+    return Ok(confirm::Guarded::ConfirmationRequired(confirm::challenge(
+      &state,
+      &action,
+      "FLUSHALL",
+      format!(
+        "Deletes all {} keys in every database of '{}'",
+        keyspace_total(&info),
+        id
+      ),
+    )));
   };
+  confirm::redeem(&state, &confirmation, &action)?;
+
+  let info: String = redis::cmd("INFO")
+    .arg("keyspace")
+    .query_async(&mut con)
+    .await
+    .map_err(|e| e.to_string())?;
+  let _: () = redis::cmd("FLUSHALL")
+    .query_async(&mut con)
+    .await
+    .map_err(|e| e.to_string())?;
+  let flushed = RedisFlushed {
+    connection_id: id,
+    db_index: None,
+    keys_removed: keyspace_total(&info),
+  };
+  let _ = tauri::Emitter::emit(&app, "redis-flushed", flushed.clone());
+  Ok(confirm::Guarded::Done(flushed))
+}
+
+fn format_redis_value(v: redis::Value) -> String {
+  match v {
+    redis::Value::Nil => "(nil)".to_string(),
+    redis::Value::Int(i) => i.to_string(),
+    redis::Value::BulkString(d) => String::from_utf8_lossy(&d).to_string(),
+    redis::Value::Array(v) => {
+      let items: Vec<String> = v.into_iter().map(format_redis_value).collect();
+      format!("[{}]", items.join(", "))
+    }
+    redis::Value::SimpleString(s) => s,
+    redis::Value::Okay => "OK".to_string(),
+    _ => format!("{:?}", v),
+  }
+}
+
+#[tauri::command]
+async fn redis_execute_raw(
+  state: State<'_, AppState>,
+  command: String,
+  connection_id: Option<String>,
+  timeout_ms: Option<u64>,
+) -> Result<String, String> {
+  let client = connections::redis(&state, connection_id.as_deref())?;
   let mut con = client
     .get_multiplexed_async_connection()
     .await
@@ -889,38 +1963,62 @@ async fn redis_execute_raw(state: State<'_, AppState>, command: String) -> Resul
   if parts.is_empty() {
     return Err("Empty command".to_string());
   }
+  readonly::check_redis_command(
+    &state,
+    connection_id.as_deref().unwrap_or("redis"),
+    parts[0],
+  )?;
 
   let mut cmd = redis::cmd(parts[0]);
   for arg in &parts[1..] {
     cmd.arg(*arg);
   }
 
-  let val: redis::Value = cmd.query_async(&mut con).await.map_err(|e| e.to_string())?;
-
-  fn format_redis_value(v: redis::Value) -> String {
-    match v {
-      redis::Value::Nil => "(nil)".to_string(),
-      redis::Value::Int(i) => i.to_string(),
-      redis::Value::BulkString(d) => String::from_utf8_lossy(&d).to_string(),
-      redis::Value::Array(v) => {
-        let items: Vec<String> = v.into_iter().map(format_redis_value).collect();
-        format!("[{}]", items.join(", "))
-      }
-      redis::Value::SimpleString(s) => s,
-      redis::Value::Okay => "OK".to_string(),
-      _ => format!("{:?}", v),
-    }
-  }
-
-  Ok(format_redis_value(val))
+  let started = std::time::Instant::now();
+  let timeout = timeouts::resolve(
+    &state,
+    connection_id.as_deref().unwrap_or("redis"),
+    timeout_ms,
+  );
+  let executed: Result<redis::Value, String> = timeouts::run(timeout, async {
+    cmd.query_async(&mut con).await.map_err(|e| e.to_string())
+  })
+  .await;
+  let rows = match &executed {
+    Ok(redis::Value::Array(items)) => items.len() as u64,
+    _ => 1,
+  };
+  history::record(
+    &state,
+    connection_id.as_deref().unwrap_or("redis"),
+    &command,
+    started.elapsed(),
+    executed.as_ref().map(|_| rows).map_err(String::as_str),
+  )
+  .await;
+  let val = executed?;
+
+  let out = format_redis_value(val);
+  transfer::record(
+    &state,
+    connection_id.as_deref().unwrap_or("redis"),
+    transfer::Category::Redis,
+    1,
+    out.len(),
+  );
+  Ok(out)
 }
 
 #[tauri::command]
-async fn mysql_get_tables(state: State<'_, AppState>) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn mysql_get_tables(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  let id = connection_id.as_deref().unwrap_or("mysql");
+  if let Some(tables) = schema_cache::get(&state, id, schema_cache::Kind::Tables, "") {
+    return Ok(tables);
+  }
 
   let rows = sqlx::query("SHOW TABLES")
     .fetch_all(&pool)
@@ -940,184 +2038,243 @@ async fn mysql_get_tables(state: State<'_, AppState>) -> Result<Vec<String>, Str
     }
   }
 
-  Ok(tables)
+  Ok(schema_cache::insert(
+    &state,
+    id,
+    schema_cache::Kind::Tables,
+    "",
+    tables,
+  ))
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn mysql_get_rows(
   state: State<'_, AppState>,
   table_name: String,
   limit: i64,
   offset: i64,
-) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  connection_id: Option<String>,
+  query_id: Option<String>,
+  timeout_ms: Option<u64>,
+  keyset: Option<bool>,
+  after_pk: Option<String>,
+  filters: Option<Vec<views::ViewFilter>>,
+  sort: Option<Vec<views::ViewSort>>,
+  search: Option<String>,
+) -> Result<keyset::RowsPage, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  let timeout = timeouts::resolve(
+    &state,
+    connection_id.as_deref().unwrap_or("mysql"),
+    timeout_ms,
+  );
+  usage::record_table(&state, connection_id.as_deref(), &table_name);
+  let sql_pool = db::SqlPool::MySql(pool.clone());
+  let filter = rowfilter::build(
+    &sql_pool,
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+    filters.as_deref().unwrap_or_default(),
+    sort.as_deref().unwrap_or_default(),
+    search.as_deref(),
+  )
+  .await?;
+  let key = keyset::key_for(
+    &sql_pool,
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+    &filter,
+    keyset,
+    after_pk.as_deref(),
+  )
+  .await?;
+  let mut binds = filter.binds.clone();
+  binds.extend(after_pk.clone());
+  let columns = schema::table_schema(&sql_pool, db::PG_DEFAULT_SCHEMA, &table_name).await?;
 
+  let quoted_key = key.as_deref().map(ident::quote_mysql).transpose()?;
   let q = format!(
-    "SELECT * FROM `{}` LIMIT {} OFFSET {}",
-    table_name, limit, offset
+    "SELECT * FROM {}{}",
+    ident::quote_mysql(&table_name)?,
+    keyset::page_clause(
+      &filter,
+      quoted_key.as_deref(),
+      after_pk
+        .as_ref()
+        .map(|_| filter.next_placeholder(&sql_pool))
+        .as_deref(),
+      limit,
+      offset
+    )
   );
 
-  let rows = sqlx::query(&q)
-    .fetch_all(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
-
-  let mut json_rows = Vec::new();
-  for row in rows {
-    let mut map = serde_json::Map::new();
-    for col in row.columns() {
-      let name = col.name();
-      // MySQL Types: Try to get as specific types or fallback to string
-      let raw_val = row.try_get_raw(col.ordinal()).unwrap();
-
-      if raw_val.is_null() {
-        map.insert(name.to_string(), serde_json::Value::Null);
-      } else {
-        let type_info = raw_val.type_info();
-        let type_name = type_info.name();
-        match type_name {
-          "TINYINT" | "SMALLINT" | "INT" | "BIGINT" => {
-            if let Ok(v) = row.try_get::<i64, _>(col.ordinal()) {
-              map.insert(name.to_string(), serde_json::Value::Number(v.into()));
-            } else if let Ok(bytes) = row.try_get::<Vec<u8>, _>(col.ordinal()) {
-              let v = String::from_utf8_lossy(&bytes).to_string();
-              map.insert(name.to_string(), serde_json::Value::String(v));
-            } else if let Ok(v) = row.try_get::<String, _>(col.ordinal()) {
-              map.insert(name.to_string(), serde_json::Value::String(v));
-            } else {
-              map.insert(name.to_string(), serde_json::Value::Null);
-            }
-          }
-          "FLOAT" | "DOUBLE" | "DECIMAL" => {
-            if let Ok(v) = row.try_get::<f64, _>(col.ordinal()) {
-              map.insert(name.to_string(), serde_json::Value::from(v));
-            } else if let Ok(bytes) = row.try_get::<Vec<u8>, _>(col.ordinal()) {
-              let v = String::from_utf8_lossy(&bytes).to_string();
-              map.insert(name.to_string(), serde_json::Value::String(v));
-            } else if let Ok(v) = row.try_get::<String, _>(col.ordinal()) {
-              map.insert(name.to_string(), serde_json::Value::String(v));
-            } else {
-              map.insert(name.to_string(), serde_json::Value::Null);
-            }
-          }
-          "BOOLEAN" => {
-            if let Ok(v) = row.try_get::<bool, _>(col.ordinal()) {
-              map.insert(name.to_string(), serde_json::Value::Bool(v));
-            } else {
-              map.insert(name.to_string(), serde_json::Value::Null);
-            }
-          }
-          "BINARY" | "VARBINARY" | "BLOB" | "TINYBLOB" | "MEDIUMBLOB" | "LONGBLOB" => {
-            if let Ok(bytes) = row.try_get::<Vec<u8>, _>(col.ordinal()) {
-              let v = String::from_utf8_lossy(&bytes).to_string();
-              map.insert(name.to_string(), serde_json::Value::String(v));
-            } else {
-              map.insert(name.to_string(), serde_json::Value::Null);
-            }
-          }
-          _ => {
-            // Try bytes first for potential VARBINARY, then string
-            if let Ok(bytes) = row.try_get::<Vec<u8>, _>(col.ordinal()) {
-              let v = String::from_utf8_lossy(&bytes).to_string();
-              map.insert(name.to_string(), serde_json::Value::String(v));
-            } else if let Ok(v) = row.try_get::<String, _>(col.ordinal()) {
-              map.insert(name.to_string(), serde_json::Value::String(v));
-            } else {
-              map.insert(name.to_string(), serde_json::Value::Null);
-            }
-          }
-        }
+  let q = timeouts::mysql_hint(&q, timeout);
+  let mut retried = false;
+  let rows = loop {
+    let (mut conn, running) = cancel::mysql(
+      &state,
+      query_id.clone(),
+      connection_id.as_deref().unwrap_or("mysql"),
+      &pool,
+      timeout,
+    )
+    .await?;
+    let mut query = sqlx::query(&q);
+    for bind in &binds {
+      query = query.bind(bind);
+    }
+    match query.fetch_all(&mut *conn).await {
+      Ok(rows) => break rows,
+      Err(e) if !retried && session::is_disconnect(&e) => {
+        tracing::warn!("Connection dropped, retrying once: {}", e);
+        conn.close_on_drop();
+        retried = true;
       }
+      Err(e) => return Err(running.error(e)),
+    }
+  };
+
+  let mask = masking::active(&state, connection_id.as_deref().unwrap_or("mysql"));
+  let tz = timezone::display_zone(&state, connection_id.as_deref().unwrap_or("mysql"));
+  let mut maps: Vec<db::JsonRow> = rows
+    .iter()
+    .map(|row| db::mysql_row_to_json(row, tz.as_ref()))
+    .collect();
+  let next_cursor = key
+    .as_ref()
+    .and_then(|key| keyset::next_cursor(maps.last(), maps.len(), key, limit));
+  if let Some(mask) = &mask {
+    for map in &mut maps {
+      mask.apply(map);
     }
-    json_rows.push(serde_json::Value::Object(map).to_string());
   }
 
-  Ok(json_rows)
+  transfer::record(
+    &state,
+    connection_id.as_deref().unwrap_or("mysql"),
+    transfer::Category::Browse,
+    maps.len(),
+    transfer::rows_bytes(&maps),
+  );
+  Ok(keyset::RowsPage::new(columns, maps, next_cursor))
 }
 
 #[tauri::command]
-async fn mysql_get_count(state: State<'_, AppState>, table_name: String) -> Result<i64, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn mysql_get_count(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+  approximate: Option<bool>,
+) -> Result<schema::RowCount, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  if approximate.unwrap_or(false) {
+    let estimate = schema::estimated_rows(
+      &db::SqlPool::MySql(pool.clone()),
+      db::PG_DEFAULT_SCHEMA,
+      &table_name,
+    )
+    .await?;
+    if let Some(count) = estimate {
+      return Ok(schema::RowCount {
+        count,
+        exact: false,
+      });
+    }
+  }
 
-  let q = format!("SELECT COUNT(*) FROM `{}`", table_name);
+  let q = format!("SELECT COUNT(*) FROM {}", ident::quote_mysql(&table_name)?);
 
   let count: (i64,) = sqlx::query_as(&q)
     .fetch_one(&pool)
     .await
     .map_err(|e| e.to_string())?;
 
-  Ok(count.0)
+  Ok(schema::RowCount {
+    count: count.0,
+    exact: true,
+  })
 }
 
 #[tauri::command]
 async fn mysql_get_primary_key(
   state: State<'_, AppState>,
   table_name: String,
-) -> Result<Option<String>, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-
-  let q = "SELECT COLUMN_NAME FROM information_schema.KEY_COLUMN_USAGE WHERE TABLE_NAME = ? AND CONSTRAINT_NAME = 'PRIMARY' AND TABLE_SCHEMA = DATABASE() LIMIT 1";
-
-  let row = sqlx::query(q)
-    .bind(table_name)
-    .fetch_optional(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
-
-  if let Some(r) = row {
-    if let Ok(bytes) = r.try_get::<Vec<u8>, _>(0) {
-      return Ok(String::from_utf8(bytes).ok());
-    } else if let Ok(name) = r.try_get::<String, _>(0) {
-      return Ok(Some(name));
-    }
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  let id = connection_id.as_deref().unwrap_or("mysql");
+  let kind = schema_cache::Kind::PrimaryKey;
+  if let Some(key) = schema_cache::get(&state, id, kind, &table_name) {
+    return Ok(key);
   }
-  Ok(None)
+  let key = schema::primary_key(
+    &db::SqlPool::MySql(pool),
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+  )
+  .await?;
+  Ok(schema_cache::insert(&state, id, kind, &table_name, key))
 }
 
 #[tauri::command]
 async fn mysql_update_cell(
   state: State<'_, AppState>,
   table_name: String,
-  pk_col: String,
-  pk_val: String,
+  pk: BTreeMap<String, String>,
   col_name: String,
-  new_val: String,
+  new_val: db::CellValue,
+  connection_id: Option<String>,
 ) -> Result<u64, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("mysql"))?;
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
 
+  let condition = pk_condition(&pk, 2, |col, _| {
+    Ok(format!("{} = ?", ident::quote_mysql(col)?))
+  })?;
   let q = format!(
-    "UPDATE `{}` SET `{}` = ? WHERE `{}` = ?",
-    table_name, col_name, pk_col
+    "UPDATE {} SET {} = {} WHERE {}",
+    ident::quote_mysql(&table_name)?,
+    ident::quote_mysql(&col_name)?,
+    new_val.sql("?"),
+    condition
   );
 
-  let result = sqlx::query(&q)
-    .bind(new_val)
-    .bind(pk_val)
-    .execute(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
+  let params = pk_params(vec![new_val.audit()], &pk);
+  let mut query = sqlx::query(&q);
+  if new_val.is_bound() {
+    query = match new_val.bound() {
+      Some(text) => {
+        let col_type = db::mysql_data_type(&pool, &table_name, &col_name).await?;
+        db::mysql_bind(query, col_type.as_deref(), text)?
+      }
+      None => query.bind(None::<String>),
+    };
+  }
+  for (pk_col, pk_val) in pk {
+    let pk_type = db::mysql_data_type(&pool, &table_name, &pk_col).await?;
+    query = db::mysql_bind(query, pk_type.as_deref(), pk_val)?;
+  }
+
+  let result = query.execute(&pool).await.map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("mysql"),
+    &q,
+    params,
+    Some(result.rows_affected()),
+  )
+  .await;
 
   Ok(result.rows_affected())
 }
 
 #[tauri::command]
-async fn mysql_get_databases(state: State<'_, AppState>) -> Result<Vec<(String, i64)>, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn mysql_get_databases(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<Vec<(String, i64)>, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
 
   // Query information_schema for size.
   // Uses LEFT JOIN to include empty databases (size as 0).
@@ -1141,56 +2298,34 @@ async fn mysql_get_databases(state: State<'_, AppState>) -> Result<Vec<(String,
 }
 
 #[tauri::command]
-async fn mysql_use_database(state: State<'_, AppState>, database: String) -> Result<(), String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-
-  // USE command is not supported in prepared statement protocol
-  // We need to use raw_sql instead
-  let q = format!("USE `{}`", database);
-  sqlx::raw_sql(&q)
-    .execute(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
-
-  Ok(())
+async fn mysql_use_database(
+  state: State<'_, AppState>,
+  database: String,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  let id = connection_id.unwrap_or_else(|| "mysql".to_string());
+  session::use_mysql_database(&state, &id, database).await
 }
 
 // Get tables with size info for a specific database (doesn't change current database)
 #[tauri::command]
 async fn mysql_get_tables_with_size(
+  app: tauri::AppHandle,
   state: State<'_, AppState>,
   database: String,
+  connection_id: Option<String>,
 ) -> Result<Vec<(String, i64)>, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-
-  let query = format!(
-        "SELECT CONVERT(TABLE_NAME USING utf8) as TABLE_NAME, CAST(COALESCE(DATA_LENGTH + INDEX_LENGTH, 0) AS SIGNED) as size \
-         FROM information_schema.TABLES \
-         WHERE TABLE_SCHEMA = '{}' \
-         ORDER BY TABLE_NAME",
-        database
-    );
-
-  let rows: Vec<(String, i64)> = sqlx::query_as(&query)
-    .fetch_all(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
-
-  Ok(rows)
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  let id = connection_id.as_deref().unwrap_or("mysql");
+  table_sizes::mysql(&app, id, &pool, &database).await
 }
 
 #[tauri::command]
-async fn mysql_get_views(state: State<'_, AppState>) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn mysql_get_views(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
 
   let rows: Vec<(String,)> = sqlx::query_as("SHOW FULL TABLES WHERE Table_type = 'VIEW'")
     .fetch_all(&pool)
@@ -1201,11 +2336,11 @@ async fn mysql_get_views(state: State<'_, AppState>) -> Result<Vec<String>, Stri
 }
 
 #[tauri::command]
-async fn mysql_get_functions(state: State<'_, AppState>) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn mysql_get_functions(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
 
   let rows: Vec<(String,)> = sqlx::query_as("SELECT CONVERT(ROUTINE_NAME USING utf8) FROM information_schema.ROUTINES WHERE ROUTINE_TYPE = 'FUNCTION' AND ROUTINE_SCHEMA = DATABASE()")
         .fetch_all(&pool)
@@ -1216,11 +2351,11 @@ async fn mysql_get_functions(state: State<'_, AppState>) -> Result<Vec<String>,
 }
 
 #[tauri::command]
-async fn mysql_get_procedures(state: State<'_, AppState>) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn mysql_get_procedures(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
 
   let rows: Vec<(String,)> = sqlx::query_as("SELECT CONVERT(ROUTINE_NAME USING utf8) FROM information_schema.ROUTINES WHERE ROUTINE_TYPE = 'PROCEDURE' AND ROUTINE_SCHEMA = DATABASE()")
         .fetch_all(&pool)
@@ -1231,11 +2366,11 @@ async fn mysql_get_procedures(state: State<'_, AppState>) -> Result<Vec<String>,
 }
 
 #[tauri::command]
-async fn postgres_get_databases(state: State<'_, AppState>) -> Result<Vec<(String, i64)>, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn postgres_get_databases(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<Vec<(String, i64)>, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
 
   let rows: Vec<(String, i64)> = sqlx::query_as("SELECT datname::text, pg_database_size(datname) as size FROM pg_database WHERE datistemplate = false AND has_database_privilege(datname, 'CONNECT') ORDER BY datname")
         .fetch_all(&pool)
@@ -1246,55 +2381,77 @@ async fn postgres_get_databases(state: State<'_, AppState>) -> Result<Vec<(Strin
 }
 
 #[tauri::command]
-async fn postgres_get_tables(state: State<'_, AppState>) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn postgres_get_schemas(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  db::pg_schemas(&pool).await
+}
+
+#[tauri::command]
+async fn postgres_get_tables(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+  schema: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  let id = connection_id.as_deref().unwrap_or("postgres");
+  if let Some(tables) = schema_cache::get(
+    &state,
+    id,
+    schema_cache::Kind::Tables,
+    &pg_cache_key(&schema, ""),
+  ) {
+    return Ok(tables);
+  }
 
   let rows: Vec<(String,)> = sqlx::query_as(
-    "SELECT table_name::text FROM information_schema.tables WHERE table_schema = 'public'",
+    "SELECT table_name::text FROM information_schema.tables WHERE table_schema = $1",
   )
+  .bind(&schema)
   .fetch_all(&pool)
   .await
   .map_err(|e| e.to_string())?;
 
-  Ok(rows.into_iter().map(|(name,)| name).collect())
+  let tables = rows.into_iter().map(|(name,)| name).collect();
+  Ok(schema_cache::insert(
+    &state,
+    id,
+    schema_cache::Kind::Tables,
+    &pg_cache_key(&schema, ""),
+    tables,
+  ))
 }
 
 #[tauri::command]
 async fn postgres_get_tables_with_size(
+  app: tauri::AppHandle,
   state: State<'_, AppState>,
+  connection_id: Option<String>,
+  schema: Option<String>,
 ) -> Result<Vec<(String, i64)>, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-
-  let rows: Vec<(String, i64)> = sqlx::query_as(
-    "SELECT table_name::text, pg_total_relation_size(quote_ident(table_name)) as size \
-         FROM information_schema.tables WHERE table_schema = 'public' ORDER BY table_name",
-  )
-  .fetch_all(&pool)
-  .await
-  .map_err(|e| e.to_string())?;
-
-  Ok(rows)
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  let id = connection_id.as_deref().unwrap_or("postgres");
+  table_sizes::postgres(&app, id, &pool, &schema).await
 }
 
 #[tauri::command]
-async fn postgres_get_views(state: State<'_, AppState>) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-
-  let rows: Vec<(String,)> = sqlx::query_as(
-    "SELECT table_name::text FROM information_schema.views WHERE table_schema = 'public'",
-  )
-  .fetch_all(&pool)
-  .await
-  .map_err(|e| e.to_string())?;
+async fn postgres_get_views(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+  schema: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  let rows: Vec<(String,)> =
+    sqlx::query_as("SELECT table_name::text FROM information_schema.views WHERE table_schema = $1")
+      .bind(&schema)
+      .fetch_all(&pool)
+      .await
+      .map_err(|e| e.to_string())?;
 
   Ok(rows.into_iter().map(|(name,)| name).collect())
 }
@@ -1302,13 +2459,13 @@ async fn postgres_get_views(state: State<'_, AppState>) -> Result<Vec<String>, S
 #[tauri::command]
 async fn postgres_get_functions(
   state: State<'_, AppState>,
+  connection_id: Option<String>,
+  schema: Option<String>,
 ) -> Result<Vec<(String, String)>, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-
-  let rows: Vec<(String, String)> = sqlx::query_as("SELECT routine_name::text, specific_name::text FROM information_schema.routines WHERE routine_type = 'FUNCTION' AND routine_schema = 'public' ORDER BY routine_name")
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  let rows: Vec<(String, String)> = sqlx::query_as("SELECT routine_name::text, specific_name::text FROM information_schema.routines WHERE routine_type = 'FUNCTION' AND routine_schema = $1 ORDER BY routine_name")
+        .bind(&schema)
         .fetch_all(&pool)
         .await
         .map_err(|e| e.to_string())?;
@@ -1319,13 +2476,13 @@ async fn postgres_get_functions(
 #[tauri::command]
 async fn postgres_get_procedures(
   state: State<'_, AppState>,
+  connection_id: Option<String>,
+  schema: Option<String>,
 ) -> Result<Vec<(String, String)>, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-
-  let rows: Vec<(String, String)> = sqlx::query_as("SELECT routine_name::text, specific_name::text FROM information_schema.routines WHERE routine_type = 'PROCEDURE' AND routine_schema = 'public' ORDER BY routine_name")
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  let rows: Vec<(String, String)> = sqlx::query_as("SELECT routine_name::text, specific_name::text FROM information_schema.routines WHERE routine_type = 'PROCEDURE' AND routine_schema = $1 ORDER BY routine_name")
+        .bind(&schema)
         .fetch_all(&pool)
         .await
         .map_err(|e| e.to_string())?;
@@ -1334,364 +2491,747 @@ async fn postgres_get_procedures(
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn postgres_get_rows(
   state: State<'_, AppState>,
   table_name: String,
   limit: i64,
   offset: i64,
-) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  connection_id: Option<String>,
+  query_id: Option<String>,
+  timeout_ms: Option<u64>,
+  keyset: Option<bool>,
+  after_pk: Option<String>,
+  filters: Option<Vec<views::ViewFilter>>,
+  sort: Option<Vec<views::ViewSort>>,
+  search: Option<String>,
+  schema: Option<String>,
+) -> Result<keyset::RowsPage, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  let timeout = timeouts::resolve(
+    &state,
+    connection_id.as_deref().unwrap_or("postgres"),
+    timeout_ms,
+  );
+  usage::record_table(&state, connection_id.as_deref(), &table_name);
+  let sql_pool = db::SqlPool::Postgres(pool.clone());
+  let mut filter = rowfilter::build(
+    &sql_pool,
+    &schema,
+    &table_name,
+    filters.as_deref().unwrap_or_default(),
+    sort.as_deref().unwrap_or_default(),
+    search.as_deref(),
+  )
+  .await?;
+  let key = keyset::key_for(
+    &sql_pool,
+    &schema,
+    &table_name,
+    &filter,
+    keyset,
+    after_pk.as_deref(),
+  )
+  .await?;
+  let mut binds = filter.binds.clone();
+  binds.extend(after_pk.clone());
+  let columns = schema::table_schema(&sql_pool, &schema, &table_name).await?;
 
   // Fetch PK for stable sorting
   let pk_q = "
         SELECT kcu.column_name::text
         FROM information_schema.key_column_usage kcu
         JOIN information_schema.table_constraints tc ON kcu.constraint_name = tc.constraint_name
-        WHERE kcu.table_schema = 'public'
+          AND tc.table_schema = kcu.table_schema AND tc.table_name = kcu.table_name
+        WHERE kcu.table_schema = $2
+        AND tc.table_schema = $2
         AND kcu.table_name = $1
         AND tc.constraint_type = 'PRIMARY KEY'
+        ORDER BY kcu.ordinal_position
         LIMIT 1
     ";
 
   let pk_row: Option<(String,)> = sqlx::query_as(pk_q)
     .bind(&table_name)
+    .bind(&schema)
     .fetch_optional(&pool)
     .await
     .unwrap_or(None);
 
-  let inner_q = if let Some((pk,)) = pk_row {
-    format!(
-      "SELECT * FROM public.\"{}\" ORDER BY \"{}\" ASC LIMIT {} OFFSET {}",
-      table_name, pk, limit, offset
-    )
+  // row_to_json turns numeric into JSON numbers, which the webview would parse as lossy
+  // doubles, so exact numeric columns are selected as text instead
+  let column_types: Vec<(String, String)> = sqlx::query_as(
+    "SELECT column_name::text, data_type::text FROM information_schema.columns WHERE table_schema = $2 AND table_name = $1 ORDER BY ordinal_position",
+  )
+  .bind(&table_name)
+  .bind(&schema)
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  let select_list = if column_types.iter().any(|(_, t)| t == "numeric") {
+    column_types
+      .iter()
+      .map(|(name, data_type)| {
+        let quoted = ident::quote_pg(name)?;
+        Ok(if data_type == "numeric" {
+          format!("{}::text AS {}", quoted, quoted)
+        } else {
+          quoted
+        })
+      })
+      .collect::<Result<Vec<_>, String>>()?
+      .join(", ")
   } else {
-    format!(
-      "SELECT * FROM public.\"{}\" LIMIT {} OFFSET {}",
-      table_name, limit, offset
-    )
+    "*".to_string()
   };
 
+  if key.is_none() && filter.order.is_empty() {
+    if let Some((pk,)) = &pk_row {
+      filter.order.push(format!("{} ASC", ident::quote_pg(pk)?));
+    }
+  }
+  let after = match (&key, &after_pk) {
+    (Some(key), Some(_)) => Some(
+      keyset::after_param(
+        &sql_pool,
+        &schema,
+        &table_name,
+        key,
+        filter.next_placeholder(&sql_pool),
+      )
+      .await?,
+    ),
+    _ => None,
+  };
+  let quoted_key = key.as_deref().map(ident::quote_pg).transpose()?;
+  let inner_q = format!(
+    "SELECT {} FROM {}{}",
+    select_list,
+    pg_table(&schema, &table_name)?,
+    keyset::page_clause(
+      &filter,
+      quoted_key.as_deref(),
+      after.as_deref(),
+      limit,
+      offset,
+    )
+  );
+
   let q = format!("SELECT row_to_json(t)::text FROM ({}) t", inner_q);
 
-  let rows: Vec<(String,)> = sqlx::query_as(&q)
+  let mut retried = false;
+  let rows: Vec<(String,)> = loop {
+    let (mut conn, running) = cancel::postgres(
+      &state,
+      query_id.clone(),
+      connection_id.as_deref().unwrap_or("postgres"),
+      &pool,
+      timeout,
+    )
+    .await?;
+    let mut query = sqlx::query_as(&q);
+    for bind in &binds {
+      query = query.bind(bind);
+    }
+    let fetched = query.fetch_all(&mut *conn).await;
+    match fetched {
+      Ok(rows) => {
+        cancel::reset_postgres(&mut conn, &running).await;
+        break rows;
+      }
+      Err(e) if !retried && session::is_disconnect(&e) => {
+        tracing::warn!("Connection dropped, retrying once: {}", e);
+        conn.close_on_drop();
+        retried = true;
+      }
+      Err(e) => {
+        cancel::reset_postgres(&mut conn, &running).await;
+        return Err(running.error(e));
+      }
+    }
+  };
+
+  let next_cursor = key.as_ref().and_then(|key| {
+    let last = rows
+      .last()
+      .and_then(|(json,)| serde_json::from_str(json).ok());
+    keyset::next_cursor(last.as_ref(), rows.len(), key, limit)
+  });
+  let mut json_rows: Vec<String> = rows.into_iter().map(|(json,)| json).collect();
+  if let Some(tz) = timezone::display_zone(&state, connection_id.as_deref().unwrap_or("postgres")) {
+    let instant_columns: Vec<(String,)> = sqlx::query_as(
+      "SELECT column_name::text FROM information_schema.columns WHERE table_schema = $2 AND table_name = $1 AND data_type = 'timestamp with time zone'",
+    )
+    .bind(&table_name)
+    .bind(&schema)
     .fetch_all(&pool)
     .await
     .map_err(|e| e.to_string())?;
-
-  Ok(rows.into_iter().map(|(json,)| json).collect())
-}
-
-#[tauri::command]
-async fn postgres_get_count(state: State<'_, AppState>, table_name: String) -> Result<i64, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
+    let instant_columns: Vec<String> = instant_columns.into_iter().map(|(c,)| c).collect();
+    if !instant_columns.is_empty() {
+      json_rows = timezone::apply_json_rows(json_rows, &instant_columns, tz);
+    }
+  }
+  let json_rows = match masking::active(&state, connection_id.as_deref().unwrap_or("postgres")) {
+    Some(mask) => mask.apply_json_rows(json_rows),
+    None => json_rows,
   };
-
-  let q = format!("SELECT COUNT(*) FROM public.\"{}\"", table_name);
-
-  let count: (i64,) = sqlx::query_as(&q)
-    .fetch_one(&pool)
-    .await
+  transfer::record_serialized(
+    &state,
+    connection_id.as_deref().unwrap_or("postgres"),
+    transfer::Category::Browse,
+    &json_rows,
+  );
+  let maps = json_rows
+    .iter()
+    .map(|json| serde_json::from_str(json))
+    .collect::<Result<Vec<db::JsonRow>, _>>()
     .map_err(|e| e.to_string())?;
-
-  Ok(count.0)
+  Ok(keyset::RowsPage::new(columns, maps, next_cursor))
 }
 
 #[tauri::command]
-async fn postgres_get_primary_key(
+async fn postgres_get_count(
   state: State<'_, AppState>,
   table_name: String,
-) -> Result<Option<String>, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  connection_id: Option<String>,
+  approximate: Option<bool>,
+  schema: Option<String>,
+) -> Result<schema::RowCount, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  if approximate.unwrap_or(false) {
+    let estimate =
+      schema::estimated_rows(&db::SqlPool::Postgres(pool.clone()), &schema, &table_name).await?;
+    if let Some(count) = estimate {
+      return Ok(schema::RowCount {
+        count,
+        exact: false,
+      });
+    }
+  }
 
-  let q = "
-        SELECT kcu.column_name::text
-        FROM information_schema.key_column_usage kcu
-        JOIN information_schema.table_constraints tc ON kcu.constraint_name = tc.constraint_name
-        WHERE kcu.table_schema = 'public'
-        AND kcu.table_name = $1
-        AND tc.constraint_type = 'PRIMARY KEY'
-        LIMIT 1
-    ";
+  let q = format!("SELECT COUNT(*) FROM {}", pg_table(&schema, &table_name)?);
 
-  let row: Option<(String,)> = sqlx::query_as(q)
-    .bind(table_name)
-    .fetch_optional(&pool)
+  let count: (i64,) = sqlx::query_as(&q)
+    .fetch_one(&pool)
     .await
     .map_err(|e| e.to_string())?;
 
-  Ok(row.map(|(r,)| r))
+  Ok(schema::RowCount {
+    count: count.0,
+    exact: true,
+  })
+}
+
+#[tauri::command]
+async fn postgres_get_primary_key(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+  schema: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  let id = connection_id.as_deref().unwrap_or("postgres");
+  let kind = schema_cache::Kind::PrimaryKey;
+  if let Some(key) = schema_cache::get(&state, id, kind, &pg_cache_key(&schema, &table_name)) {
+    return Ok(key);
+  }
+  let key = schema::primary_key(&db::SqlPool::Postgres(pool.clone()), &schema, &table_name).await?;
+  Ok(schema_cache::insert(
+    &state,
+    id,
+    kind,
+    &pg_cache_key(&schema, &table_name),
+    key,
+  ))
 }
 
 #[tauri::command]
 async fn postgres_update_cell(
   state: State<'_, AppState>,
   table_name: String,
-  pk_col: String,
-  pk_val: String,
+  pk: BTreeMap<String, String>,
   col_name: String,
-  new_val: String,
+  new_val: db::CellValue,
+  connection_id: Option<String>,
+  schema: Option<String>,
 ) -> Result<u64, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("postgres"))?;
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
   // 1. Get column type to cast the input string correctly
-  let type_q = "SELECT udt_name::text FROM information_schema.columns WHERE table_schema = 'public' AND table_name = $1 AND column_name = $2";
+  let type_q = "SELECT udt_name::text FROM information_schema.columns WHERE table_schema = $3 AND table_name = $1 AND column_name = $2";
   let type_row: Option<(String,)> = sqlx::query_as(type_q)
     .bind(&table_name)
     .bind(&col_name)
+    .bind(&schema)
     .fetch_optional(&pool)
     .await
     .map_err(|e| e.to_string())?;
 
   // Default to text if not found (shouldn't happen for valid columns)
-  let col_type = type_row.map(|r| r.0).unwrap_or_else(|| "text".to_string());
+  let col_type = type_row.map_or_else(|| "text".to_string(), |r| r.0);
 
   // 2. Update with explicit cast
   // We bind the new value as string ($1) and cast it to the target column type ($1::{col_type})
   // This allows updating numeric, boolean, uuid, etc. columns with string input.
-  // We also cast PK to text ("{pk_col}"::text) to compare against stringified PK value.
+  // We also cast PK columns to text ("{pk_col}"::text) to compare against stringified values.
+  // An expression takes no parameter, so the PK values start at $1 then.
+  let first = if new_val.is_bound() { 2 } else { 1 };
+  let condition = pk_condition(&pk, first, |col, n| {
+    Ok(format!("{}::text = ${}", ident::quote_pg(col)?, n))
+  })?;
   let q = format!(
-    "UPDATE public.\"{}\" SET \"{}\" = $1::{} WHERE \"{}\"::text = $2",
-    table_name, col_name, col_type, pk_col
+    "UPDATE {} SET {} = {} WHERE {}",
+    pg_table(&schema, &table_name)?,
+    ident::quote_pg(&col_name)?,
+    new_val.sql(&format!("$1::{}", col_type)),
+    condition
   );
 
-  let result = sqlx::query(&q)
-    .bind(new_val)
-    .bind(pk_val)
-    .execute(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
+  let params = pk_params(vec![new_val.audit()], &pk);
+  let mut query = sqlx::query(&q);
+  if new_val.is_bound() {
+    query = query.bind(new_val.bound());
+  }
+  for value in pk.into_values() {
+    query = query.bind(value);
+  }
+  let result = query.execute(&pool).await.map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("postgres"),
+    &q,
+    params,
+    Some(result.rows_affected()),
+  )
+  .await;
 
   Ok(result.rows_affected())
 }
 
 #[tauri::command]
-async fn sqlite_execute_raw(state: State<'_, AppState>, sql: String) -> Result<String, String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn sqlite_execute_raw(
+  app: tauri::AppHandle,
+  state: State<'_, AppState>,
+  sql: String,
+  workspace: Option<String>,
+  connection_id: Option<String>,
+  query_id: Option<String>,
+  timeout_ms: Option<u64>,
+) -> Result<String, String> {
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  let timeout = timeouts::resolve(
+    &state,
+    connection_id.as_deref().unwrap_or("sqlite"),
+    timeout_ms,
+  );
 
-  let is_query = sql.trim().to_uppercase().starts_with("SELECT")
-    || sql.trim().to_uppercase().starts_with("PRAGMA")
-    || sql.trim().to_uppercase().starts_with("EXPLAIN");
+  let (sql, binds) = variables::resolve(
+    &state,
+    workspace.as_deref(),
+    &sql,
+    Placeholder::SqliteQuestion,
+  )?;
+  readonly::check_statement(&state, connection_id.as_deref().unwrap_or("sqlite"), &sql)?;
+  let params = serde_json::json!(&binds);
+  let mut query = sqlx::query(&sql);
+  for value in &binds {
+    query = query.bind(value);
+  }
 
-  if is_query {
-    let rows = sqlx::query(&sql)
-      .fetch_all(&pool)
-      .await
-      .map_err(|e| e.to_string())?;
-    let mut json_rows = Vec::new();
-    for row in rows {
-      let mut map = serde_json::Map::new();
-      for col in row.columns() {
-        let name = col.name();
-        let raw_val = row.try_get_raw(col.ordinal()).unwrap();
-        if raw_val.is_null() {
-          map.insert(name.to_string(), serde_json::Value::Null);
+  let is_query = sql_kind::is_read(&sql);
+
+  let started = std::time::Instant::now();
+  let executed: Result<(String, u64), String> = async {
+    if is_query {
+      let cursor_pool = db::SqlPool::Sqlite(pool.clone());
+      let mut json_rows: Vec<serde_json::Value> =
+        if let Some(limit) = console::row_limit(&state, &cursor_pool, &sql).await {
+          console::first_page(
+            &app,
+            &state,
+            connection_id.as_deref().unwrap_or("sqlite"),
+            &cursor_pool,
+            &sql,
+            &binds,
+            limit,
+            query_id.clone(),
+            timeout,
+          )
+          .await?
         } else {
-          let type_info = raw_val.type_info();
-          let type_name = type_info.name();
-          match type_name {
-            "INTEGER" => {
-              let v: i64 = row.get(col.ordinal());
-              map.insert(name.to_string(), serde_json::Value::Number(v.into()));
-            }
-            "REAL" => {
-              let v: f64 = row.get(col.ordinal());
-              map.insert(name.to_string(), serde_json::Value::from(v));
-            }
-            "BOOLEAN" => {
-              let v: bool = row.get(col.ordinal());
-              map.insert(name.to_string(), serde_json::Value::Bool(v));
-            }
-            _ => {
-              let v: String = row.get(col.ordinal());
-              map.insert(name.to_string(), serde_json::Value::String(v));
+          let (mut conn, running) = cancel::sqlite(
+            &state,
+            query_id.clone(),
+            connection_id.as_deref().unwrap_or("sqlite"),
+            &pool,
+            timeout,
+          )
+          .await?;
+          let guard = readonly::guard(
+            &state,
+            connection_id.as_deref().unwrap_or("sqlite"),
+            &cursor_pool,
+          );
+          if let Some(guard) = &guard {
+            guard.begin(&mut *conn).await?;
+          }
+          let fetched = query.fetch_all(&mut *conn).await;
+          if let Some(guard) = &guard {
+            if !guard.end(&mut *conn).await {
+              conn.close_on_drop();
             }
           }
-        }
+          let rows = fetched.map_err(|e| running.error(e))?;
+          rows
+            .iter()
+            .map(|row| serde_json::Value::Object(db::sqlite_row_to_json(row)))
+            .collect()
+        };
+      if let Some(mask) = masking::active(&state, connection_id.as_deref().unwrap_or("sqlite")) {
+        mask.apply_values(&mut json_rows);
+      }
+      let out = serde_json::to_string(&json_rows).map_err(|e| e.to_string())?;
+      transfer::record(
+        &state,
+        connection_id.as_deref().unwrap_or("sqlite"),
+        transfer::Category::Query,
+        json_rows.len(),
+        out.len(),
+      );
+      Ok((out, json_rows.len() as u64))
+    } else {
+      let (mut conn, running) = cancel::sqlite(
+        &state,
+        query_id.clone(),
+        connection_id.as_deref().unwrap_or("sqlite"),
+        &pool,
+        timeout,
+      )
+      .await?;
+      let result = query
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| running.error(e))?;
+      drop(running);
+      if sql_kind::is_write(&sql) {
+        audit::record(
+          &state,
+          connection_id.as_deref().unwrap_or("sqlite"),
+          &sql,
+          params,
+          Some(result.rows_affected()),
+        )
+        .await;
       }
-      json_rows.push(serde_json::Value::Object(map));
+      Ok((
+        format!("Success: {} rows affected", result.rows_affected()),
+        result.rows_affected(),
+      ))
     }
-    Ok(serde_json::to_string(&json_rows).unwrap())
-  } else {
-    let result = sqlx::query(&sql)
-      .execute(&pool)
-      .await
-      .map_err(|e| e.to_string())?;
-    Ok(format!("Success: {} rows affected", result.rows_affected()))
   }
+  .await;
+  history::record(
+    &state,
+    connection_id.as_deref().unwrap_or("sqlite"),
+    &sql,
+    started.elapsed(),
+    executed
+      .as_ref()
+      .map(|(_, rows)| *rows)
+      .map_err(String::as_str),
+  )
+  .await;
+  executed.map(|(out, _)| out)
 }
 
 #[tauri::command]
-async fn mysql_execute_raw(state: State<'_, AppState>, sql: String) -> Result<String, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn mysql_execute_raw(
+  app: tauri::AppHandle,
+  state: State<'_, AppState>,
+  sql: String,
+  workspace: Option<String>,
+  connection_id: Option<String>,
+  query_id: Option<String>,
+  timeout_ms: Option<u64>,
+) -> Result<String, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  let timeout = timeouts::resolve(
+    &state,
+    connection_id.as_deref().unwrap_or("mysql"),
+    timeout_ms,
+  );
 
-  let is_query = sql.trim().to_uppercase().starts_with("SELECT")
-    || sql.trim().to_uppercase().starts_with("SHOW")
-    || sql.trim().to_uppercase().starts_with("DESCRIBE")
-    || sql.trim().to_uppercase().starts_with("EXPLAIN");
+  let (sql, binds) = variables::resolve(&state, workspace.as_deref(), &sql, Placeholder::Question)?;
+  readonly::check_statement(&state, connection_id.as_deref().unwrap_or("mysql"), &sql)?;
+  let params = serde_json::json!(&binds);
+  let hinted = timeouts::mysql_hint(&sql, timeout);
+  let mut query = sqlx::query(&hinted);
+  for value in &binds {
+    query = query.bind(value);
+  }
 
-  if is_query {
-    let rows = sqlx::query(&sql)
-      .fetch_all(&pool)
-      .await
-      .map_err(|e| e.to_string())?;
-    let mut json_rows = Vec::new();
-    for row in rows {
-      let mut map = serde_json::Map::new();
-      for col in row.columns() {
-        let name = col.name();
-        let raw_val = row.try_get_raw(col.ordinal()).unwrap();
-        if raw_val.is_null() {
-          map.insert(name.to_string(), serde_json::Value::Null);
+  let is_query = sql_kind::is_read(&sql);
+
+  let started = std::time::Instant::now();
+  let executed: Result<(String, u64), String> = async {
+    if is_query {
+      let tz = timezone::display_zone(&state, connection_id.as_deref().unwrap_or("mysql"));
+      let cursor_pool = db::SqlPool::MySql(pool.clone());
+      let mut json_rows: Vec<serde_json::Value> =
+        if let Some(limit) = console::row_limit(&state, &cursor_pool, &sql).await {
+          console::first_page(
+            &app,
+            &state,
+            connection_id.as_deref().unwrap_or("mysql"),
+            &cursor_pool,
+            &sql,
+            &binds,
+            limit,
+            query_id.clone(),
+            timeout,
+          )
+          .await?
         } else {
-          let type_info = raw_val.type_info();
-          let type_name = type_info.name();
-          match type_name {
-            "TINYINT" | "SMALLINT" | "INT" | "BIGINT" => {
-              if let Ok(v) = row.try_get::<i64, _>(col.ordinal()) {
-                map.insert(name.to_string(), serde_json::Value::Number(v.into()));
-              } else {
-                let v: String = row.get(col.ordinal());
-                map.insert(name.to_string(), serde_json::Value::String(v));
-              }
-            }
-            "FLOAT" | "DOUBLE" | "DECIMAL" => {
-              if let Ok(v) = row.try_get::<f64, _>(col.ordinal()) {
-                map.insert(name.to_string(), serde_json::Value::from(v));
-              } else {
-                let v: String = row.get(col.ordinal());
-                map.insert(name.to_string(), serde_json::Value::String(v));
-              }
-            }
-            "BOOLEAN" => {
-              if let Ok(v) = row.try_get::<bool, _>(col.ordinal()) {
-                map.insert(name.to_string(), serde_json::Value::Bool(v));
-              } else {
-                let v: String = row.get(col.ordinal());
-                map.insert(name.to_string(), serde_json::Value::String(v));
-              }
-            }
-            _ => {
-              let v: String = row.get(col.ordinal());
-              map.insert(name.to_string(), serde_json::Value::String(v));
+          let (mut conn, running) = cancel::mysql(
+            &state,
+            query_id.clone(),
+            connection_id.as_deref().unwrap_or("mysql"),
+            &pool,
+            timeout,
+          )
+          .await?;
+          let guard = readonly::guard(
+            &state,
+            connection_id.as_deref().unwrap_or("mysql"),
+            &cursor_pool,
+          );
+          if let Some(guard) = &guard {
+            guard.begin(&mut *conn).await?;
+          }
+          let fetched = query.fetch_all(&mut *conn).await;
+          if let Some(guard) = &guard {
+            if !guard.end(&mut *conn).await {
+              conn.close_on_drop();
             }
           }
-        }
+          let rows = fetched.map_err(|e| running.error(e))?;
+          rows
+            .iter()
+            .map(|row| serde_json::Value::Object(db::mysql_row_to_json(row, tz.as_ref())))
+            .collect()
+        };
+      if let Some(mask) = masking::active(&state, connection_id.as_deref().unwrap_or("mysql")) {
+        mask.apply_values(&mut json_rows);
+      }
+      let out = serde_json::to_string(&json_rows).map_err(|e| e.to_string())?;
+      transfer::record(
+        &state,
+        connection_id.as_deref().unwrap_or("mysql"),
+        transfer::Category::Query,
+        json_rows.len(),
+        out.len(),
+      );
+      Ok((out, json_rows.len() as u64))
+    } else {
+      let (mut conn, running) = cancel::mysql(
+        &state,
+        query_id.clone(),
+        connection_id.as_deref().unwrap_or("mysql"),
+        &pool,
+        timeout,
+      )
+      .await?;
+      let result = query
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| running.error(e))?;
+      drop(running);
+      if sql_kind::is_write(&sql) {
+        audit::record(
+          &state,
+          connection_id.as_deref().unwrap_or("mysql"),
+          &sql,
+          params,
+          Some(result.rows_affected()),
+        )
+        .await;
       }
-      json_rows.push(serde_json::Value::Object(map));
+      Ok((
+        format!("Success: {} rows affected", result.rows_affected()),
+        result.rows_affected(),
+      ))
     }
-    Ok(serde_json::to_string(&json_rows).unwrap())
-  } else {
-    let result = sqlx::query(&sql)
-      .execute(&pool)
-      .await
-      .map_err(|e| e.to_string())?;
-    Ok(format!("Success: {} rows affected", result.rows_affected()))
   }
+  .await;
+  history::record(
+    &state,
+    connection_id.as_deref().unwrap_or("mysql"),
+    &sql,
+    started.elapsed(),
+    executed
+      .as_ref()
+      .map(|(_, rows)| *rows)
+      .map_err(String::as_str),
+  )
+  .await;
+  executed.map(|(out, _)| out)
 }
 
 #[tauri::command]
-async fn postgres_execute_raw(state: State<'_, AppState>, sql: String) -> Result<String, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+async fn postgres_execute_raw(
+  app: tauri::AppHandle,
+  state: State<'_, AppState>,
+  sql: String,
+  workspace: Option<String>,
+  connection_id: Option<String>,
+  query_id: Option<String>,
+  timeout_ms: Option<u64>,
+) -> Result<String, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let timeout = timeouts::resolve(
+    &state,
+    connection_id.as_deref().unwrap_or("postgres"),
+    timeout_ms,
+  );
 
-  let is_query = sql.trim().to_uppercase().starts_with("SELECT")
-    || sql.trim().to_uppercase().starts_with("SHOW")
-    || sql.trim().to_uppercase().starts_with("EXPLAIN");
+  let (sql, binds) = variables::resolve(&state, workspace.as_deref(), &sql, Placeholder::Dollar)?;
+  readonly::check_statement(&state, connection_id.as_deref().unwrap_or("postgres"), &sql)?;
+  let params = serde_json::json!(&binds);
+  let mut query = sqlx::query(&sql);
+  for value in &binds {
+    query = query.bind(value);
+  }
 
-  if is_query {
-    // For Postgres, row_to_json is often easier but let's do manual for consistency and because we don't have a wrapper query here
-    let rows = sqlx::query(&sql)
-      .fetch_all(&pool)
-      .await
-      .map_err(|e| e.to_string())?;
-    let mut json_rows = Vec::new();
-    for row in rows {
-      let mut map = serde_json::Map::new();
-      for col in row.columns() {
-        let name = col.name();
-        let raw_val = row.try_get_raw(col.ordinal()).unwrap();
-        if raw_val.is_null() {
-          map.insert(name.to_string(), serde_json::Value::Null);
+  let is_query = sql_kind::is_read(&sql);
+
+  let started = std::time::Instant::now();
+  let executed: Result<(String, u64), String> = async {
+    if is_query {
+      // For Postgres, row_to_json is often easier but let's do manual for consistency and because we don't have a wrapper query here
+      let tz = timezone::display_zone(&state, connection_id.as_deref().unwrap_or("postgres"));
+      let cursor_pool = db::SqlPool::Postgres(pool.clone());
+      let mut json_rows: Vec<serde_json::Value> =
+        if let Some(limit) = console::row_limit(&state, &cursor_pool, &sql).await {
+          console::first_page(
+            &app,
+            &state,
+            connection_id.as_deref().unwrap_or("postgres"),
+            &cursor_pool,
+            &sql,
+            &binds,
+            limit,
+            query_id.clone(),
+            timeout,
+          )
+          .await?
         } else {
-          let type_info = raw_val.type_info();
-          let type_name = type_info.name();
-          match type_name {
-            "INT2" | "INT4" | "INT8" => {
-              if let Ok(v) = row.try_get::<i64, _>(col.ordinal()) {
-                map.insert(name.to_string(), serde_json::Value::Number(v.into()));
-              } else {
-                let v: String = row.get(col.ordinal());
-                map.insert(name.to_string(), serde_json::Value::String(v));
-              }
-            }
-            "FLOAT4" | "FLOAT8" | "NUMERIC" => {
-              if let Ok(v) = row.try_get::<f64, _>(col.ordinal()) {
-                map.insert(name.to_string(), serde_json::Value::from(v));
-              } else {
-                let v: String = row.get(col.ordinal());
-                map.insert(name.to_string(), serde_json::Value::String(v));
-              }
-            }
-            "BOOL" => {
-              if let Ok(v) = row.try_get::<bool, _>(col.ordinal()) {
-                map.insert(name.to_string(), serde_json::Value::Bool(v));
-              } else {
-                let v: String = row.get(col.ordinal());
-                map.insert(name.to_string(), serde_json::Value::String(v));
-              }
-            }
-            _ => {
-              let v: String = row.get(col.ordinal());
-              map.insert(name.to_string(), serde_json::Value::String(v));
+          let (mut conn, running) = cancel::postgres(
+            &state,
+            query_id.clone(),
+            connection_id.as_deref().unwrap_or("postgres"),
+            &pool,
+            timeout,
+          )
+          .await?;
+          let guard = readonly::guard(
+            &state,
+            connection_id.as_deref().unwrap_or("postgres"),
+            &cursor_pool,
+          );
+          if let Some(guard) = &guard {
+            guard.begin(&mut *conn).await?;
+          }
+          let fetched = query.fetch_all(&mut *conn).await;
+          if let Some(guard) = &guard {
+            if !guard.end(&mut *conn).await {
+              conn.close_on_drop();
             }
           }
-        }
+          cancel::reset_postgres(&mut conn, &running).await;
+          let rows = fetched.map_err(|e| running.error(e))?;
+          rows
+            .iter()
+            .map(|row| serde_json::Value::Object(db::pg_row_to_json(row, tz.as_ref())))
+            .collect()
+        };
+      if let Some(mask) = masking::active(&state, connection_id.as_deref().unwrap_or("postgres")) {
+        mask.apply_values(&mut json_rows);
+      }
+      let out = serde_json::to_string(&json_rows).map_err(|e| e.to_string())?;
+      transfer::record(
+        &state,
+        connection_id.as_deref().unwrap_or("postgres"),
+        transfer::Category::Query,
+        json_rows.len(),
+        out.len(),
+      );
+      Ok((out, json_rows.len() as u64))
+    } else {
+      let (mut conn, running) = cancel::postgres(
+        &state,
+        query_id.clone(),
+        connection_id.as_deref().unwrap_or("postgres"),
+        &pool,
+        timeout,
+      )
+      .await?;
+      let executed = query.execute(&mut *conn).await;
+      cancel::reset_postgres(&mut conn, &running).await;
+      let result = executed.map_err(|e| running.error(e))?;
+      drop(running);
+      if sql_kind::is_write(&sql) {
+        audit::record(
+          &state,
+          connection_id.as_deref().unwrap_or("postgres"),
+          &sql,
+          params,
+          Some(result.rows_affected()),
+        )
+        .await;
       }
-      json_rows.push(serde_json::Value::Object(map));
+      Ok((
+        format!("Success: {} rows affected", result.rows_affected()),
+        result.rows_affected(),
+      ))
     }
-    Ok(serde_json::to_string(&json_rows).unwrap())
-  } else {
-    let result = sqlx::query(&sql)
-      .execute(&pool)
-      .await
-      .map_err(|e| e.to_string())?;
-    Ok(format!("Success: {} rows affected", result.rows_affected()))
   }
+  .await;
+  history::record(
+    &state,
+    connection_id.as_deref().unwrap_or("postgres"),
+    &sql,
+    started.elapsed(),
+    executed
+      .as_ref()
+      .map(|(_, rows)| *rows)
+      .map_err(String::as_str),
+  )
+  .await;
+  executed.map(|(out, _)| out)
 }
 
 #[tauri::command]
 async fn mysql_get_columns(
   state: State<'_, AppState>,
   table_name: String,
+  connection_id: Option<String>,
 ) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  let id = connection_id.as_deref().unwrap_or("mysql");
+  let kind = schema_cache::Kind::Columns;
+  if let Some(columns) = schema_cache::get(&state, id, kind, &table_name) {
+    return Ok(columns);
+  }
 
   let q = "SELECT COLUMN_NAME FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? ORDER BY ORDINAL_POSITION";
 
   let rows = sqlx::query(q)
-    .bind(table_name)
+    .bind(&table_name)
     .fetch_all(&pool)
     .await
     .map_err(|e| e.to_string())?;
@@ -1707,48 +3247,233 @@ async fn mysql_get_columns(
     }
   }
 
-  Ok(columns)
+  Ok(schema_cache::insert(&state, id, kind, &table_name, columns))
+}
+
+#[tauri::command]
+async fn mysql_get_table_schema(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<Vec<schema::TableColumn>, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  schema::table_schema(
+    &db::SqlPool::MySql(pool),
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+  )
+  .await
+}
+
+#[tauri::command]
+async fn mysql_get_indexes(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<Vec<schema::IndexInfo>, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  schema::indexes(
+    &db::SqlPool::MySql(pool),
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+  )
+  .await
+}
+
+#[tauri::command]
+async fn mysql_get_foreign_keys(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<Vec<schema::ForeignKey>, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  schema::foreign_keys(
+    &db::SqlPool::MySql(pool),
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+  )
+  .await
+}
+
+#[tauri::command]
+async fn mysql_show_create_table(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<String, String> {
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  sql_dump::table_ddl(
+    &db::SqlPool::MySql(pool),
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+  )
+  .await
 }
 
 #[tauri::command]
 async fn postgres_get_columns(
   state: State<'_, AppState>,
   table_name: String,
+  connection_id: Option<String>,
+  schema: Option<String>,
 ) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  let id = connection_id.as_deref().unwrap_or("postgres");
+  let kind = schema_cache::Kind::Columns;
+  if let Some(columns) = schema_cache::get(&state, id, kind, &pg_cache_key(&schema, &table_name)) {
+    return Ok(columns);
+  }
 
-  let q = "SELECT column_name::text FROM information_schema.columns WHERE table_schema = 'public' AND table_name = $1 ORDER BY ordinal_position";
+  let q = "SELECT column_name::text FROM information_schema.columns WHERE table_schema = $2 AND table_name = $1 ORDER BY ordinal_position";
 
   let rows: Vec<(String,)> = sqlx::query_as(q)
-    .bind(table_name)
+    .bind(&table_name)
+    .bind(&schema)
     .fetch_all(&pool)
     .await
     .map_err(|e| e.to_string())?;
 
-  Ok(rows.into_iter().map(|(name,)| name).collect())
+  let columns = rows.into_iter().map(|(name,)| name).collect();
+  Ok(schema_cache::insert(
+    &state,
+    id,
+    kind,
+    &pg_cache_key(&schema, &table_name),
+    columns,
+  ))
+}
+
+#[tauri::command]
+async fn postgres_get_table_schema(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+  schema: Option<String>,
+) -> Result<Vec<schema::TableColumn>, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  schema::table_schema(&db::SqlPool::Postgres(pool.clone()), &schema, &table_name).await
+}
+
+#[tauri::command]
+async fn postgres_get_indexes(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+  schema: Option<String>,
+) -> Result<Vec<schema::IndexInfo>, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  schema::indexes(&db::SqlPool::Postgres(pool.clone()), &schema, &table_name).await
+}
+
+#[tauri::command]
+async fn postgres_get_foreign_keys(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+  schema: Option<String>,
+) -> Result<Vec<schema::ForeignKey>, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  schema::foreign_keys(&db::SqlPool::Postgres(pool.clone()), &schema, &table_name).await
+}
+
+#[tauri::command]
+async fn postgres_get_table_ddl(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+  schema: Option<String>,
+) -> Result<String, String> {
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  sql_dump::table_ddl(&db::SqlPool::Postgres(pool.clone()), &schema, &table_name).await
 }
 
 #[tauri::command]
 async fn sqlite_get_columns(
   state: State<'_, AppState>,
   table_name: String,
+  connection_id: Option<String>,
 ) -> Result<Vec<String>, String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  let id = connection_id.as_deref().unwrap_or("sqlite");
+  let kind = schema_cache::Kind::Columns;
+  if let Some(columns) = schema_cache::get(&state, id, kind, &table_name) {
+    return Ok(columns);
+  }
 
-  let q = format!("PRAGMA table_info(\"{}\")", table_name);
+  let q = format!("PRAGMA table_info({})", ident::quote_sqlite(&table_name)?);
 
   let rows: Vec<(i32, String, String, i32, Option<String>, i32)> = sqlx::query_as(&q)
     .fetch_all(&pool)
     .await
     .map_err(|e| e.to_string())?;
 
-  Ok(rows.into_iter().map(|(_, name, _, _, _, _)| name).collect())
+  let columns = rows.into_iter().map(|(_, name, _, _, _, _)| name).collect();
+  Ok(schema_cache::insert(&state, id, kind, &table_name, columns))
+}
+
+#[tauri::command]
+async fn sqlite_get_table_schema(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<Vec<schema::TableColumn>, String> {
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  schema::table_schema(
+    &db::SqlPool::Sqlite(pool),
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+  )
+  .await
+}
+
+#[tauri::command]
+async fn sqlite_get_indexes(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<Vec<schema::IndexInfo>, String> {
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  schema::indexes(
+    &db::SqlPool::Sqlite(pool),
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+  )
+  .await
+}
+
+#[tauri::command]
+async fn sqlite_get_foreign_keys(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<Vec<schema::ForeignKey>, String> {
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  schema::foreign_keys(
+    &db::SqlPool::Sqlite(pool),
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+  )
+  .await
+}
+
+#[tauri::command]
+async fn sqlite_get_table_sql(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<String, String> {
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  sql_dump::table_ddl(
+    &db::SqlPool::Sqlite(pool),
+    db::PG_DEFAULT_SCHEMA,
+    &table_name,
+  )
+  .await
 }
 
 #[tauri::command]
@@ -1756,18 +3481,20 @@ async fn mysql_insert_row(
   state: State<'_, AppState>,
   table_name: String,
   data: serde_json::Map<String, serde_json::Value>,
+  connection_id: Option<String>,
 ) -> Result<u64, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("mysql"))?;
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
 
-  let cols: Vec<String> = data.keys().map(|k| format!("`{}`", k)).collect();
+  let cols = data
+    .keys()
+    .map(|k| ident::quote_mysql(k))
+    .collect::<Result<Vec<_>, _>>()?;
   let placeholders: Vec<String> = vec!["?".to_string(); data.len()];
 
   let q = format!(
-    "INSERT INTO `{}` ({}) VALUES ({})",
-    table_name,
+    "INSERT INTO {} ({}) VALUES ({})",
+    ident::quote_mysql(&table_name)?,
     cols.join(", "),
     placeholders.join(", ")
   );
@@ -1779,13 +3506,20 @@ async fn mysql_insert_row(
     } else {
       let s = val
         .as_str()
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| val.to_string());
+        .map_or_else(|| val.to_string(), ToString::to_string);
       query = query.bind(s);
     }
   }
 
   let result = query.execute(&pool).await.map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("mysql"),
+    &q,
+    serde_json::Value::Array(data.values().cloned().collect()),
+    Some(result.rows_affected()),
+  )
+  .await;
   Ok(result.rows_affected())
 }
 
@@ -1794,16 +3528,17 @@ async fn postgres_insert_row(
   state: State<'_, AppState>,
   table_name: String,
   data: serde_json::Map<String, serde_json::Value>,
+  connection_id: Option<String>,
+  schema: Option<String>,
 ) -> Result<u64, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("postgres"))?;
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
   // 1. Fetch types for all columns being inserted to ensure correct casting
-  let type_q = "SELECT column_name::text, udt_name::text FROM information_schema.columns WHERE table_schema = 'public' AND table_name = $1";
+  let type_q = "SELECT column_name::text, udt_name::text FROM information_schema.columns WHERE table_schema = $2 AND table_name = $1";
   let rows: Vec<(String, String)> = sqlx::query_as(type_q)
     .bind(&table_name)
+    .bind(&schema)
     .fetch_all(&pool)
     .await
     .map_err(|e| e.to_string())?;
@@ -1815,16 +3550,16 @@ async fn postgres_insert_row(
   let mut bind_values = Vec::new();
 
   for (i, (k, v)) in data.iter().enumerate() {
-    cols_names.push(format!("\"{}\"", k));
+    cols_names.push(ident::quote_pg(k)?);
 
     // Get the column type for casting
-    let col_type = type_map.get(k).map(|s| s.as_str()).unwrap_or("text");
+    let col_type = type_map.get(k).map_or("text", |s| s.as_str());
     placeholders.push(format!("${}::{}", i + 1, col_type));
 
     // Convert value to string for binding (Postgres will cast via the placeholder)
     let val_str = match v {
       serde_json::Value::String(s) => s.clone(),
-      serde_json::Value::Null => "".to_string(), // Handle null as empty string if bound to a cast?
+      serde_json::Value::Null => String::new(), // Handle null as empty string if bound to a cast?
       // Actually, if it's null, we might want to bind None.
       _ => v.to_string(),
     };
@@ -1832,8 +3567,8 @@ async fn postgres_insert_row(
   }
 
   let q = format!(
-    "INSERT INTO public.\"{}\" ({}) VALUES ({})",
-    table_name,
+    "INSERT INTO {} ({}) VALUES ({})",
+    pg_table(&schema, &table_name)?,
     cols_names.join(", "),
     placeholders.join(", ")
   );
@@ -1848,21 +3583,48 @@ async fn postgres_insert_row(
   }
 
   let result = query.execute(&pool).await.map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("postgres"),
+    &q,
+    serde_json::Value::Array(data.values().cloned().collect()),
+    Some(result.rows_affected()),
+  )
+  .await;
   Ok(result.rows_affected())
 }
 
 #[tauri::command]
-async fn sqlite_get_count(state: State<'_, AppState>, table_name: String) -> Result<i64, String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-  let q = format!("SELECT COUNT(*) FROM \"{}\"", table_name);
+async fn sqlite_get_count(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+  approximate: Option<bool>,
+) -> Result<schema::RowCount, String> {
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  if approximate.unwrap_or(false) {
+    let estimate = schema::estimated_rows(
+      &db::SqlPool::Sqlite(pool.clone()),
+      db::PG_DEFAULT_SCHEMA,
+      &table_name,
+    )
+    .await?;
+    if let Some(count) = estimate {
+      return Ok(schema::RowCount {
+        count,
+        exact: false,
+      });
+    }
+  }
+  let q = format!("SELECT COUNT(*) FROM {}", ident::quote_sqlite(&table_name)?);
   let count: (i64,) = sqlx::query_as(&q)
     .fetch_one(&pool)
     .await
     .map_err(|e| e.to_string())?;
-  Ok(count.0)
+  Ok(schema::RowCount {
+    count: count.0,
+    exact: true,
+  })
 }
 
 #[tauri::command]
@@ -1870,18 +3632,20 @@ async fn sqlite_insert_row(
   state: State<'_, AppState>,
   table_name: String,
   data: serde_json::Map<String, serde_json::Value>,
+  connection_id: Option<String>,
 ) -> Result<u64, String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("sqlite"))?;
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
 
-  let cols: Vec<String> = data.keys().map(|k| format!("\"{}\"", k)).collect();
+  let cols = data
+    .keys()
+    .map(|k| ident::quote_sqlite(k))
+    .collect::<Result<Vec<_>, _>>()?;
   let placeholders: Vec<String> = vec!["?".to_string(); data.len()];
 
   let q = format!(
-    "INSERT INTO \"{}\" ({}) VALUES ({})",
-    table_name,
+    "INSERT INTO {} ({}) VALUES ({})",
+    ident::quote_sqlite(&table_name)?,
     cols.join(", "),
     placeholders.join(", ")
   );
@@ -1893,13 +3657,20 @@ async fn sqlite_insert_row(
     } else {
       let s = val
         .as_str()
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| val.to_string());
+        .map_or_else(|| val.to_string(), ToString::to_string);
       query = query.bind(s);
     }
   }
 
   let result = query.execute(&pool).await.map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("sqlite"),
+    &q,
+    serde_json::Value::Array(data.values().cloned().collect()),
+    Some(result.rows_affected()),
+  )
+  .await;
   Ok(result.rows_affected())
 }
 
@@ -1907,33 +3678,58 @@ async fn sqlite_insert_row(
 async fn mysql_delete_row(
   state: State<'_, AppState>,
   table_name: String,
-  pk_col: String,
-  pk_val: String,
+  pk: BTreeMap<String, String>,
+  connection_id: Option<String>,
 ) -> Result<u64, String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-  let q = format!("DELETE FROM `{}` WHERE `{}` = ?", table_name, pk_col);
-  let result = sqlx::query(&q)
-    .bind(pk_val)
-    .execute(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("mysql"))?;
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  let condition = pk_condition(&pk, 1, |col, _| {
+    Ok(format!("{} = ?", ident::quote_mysql(col)?))
+  })?;
+  let q = format!(
+    "DELETE FROM {} WHERE {}",
+    ident::quote_mysql(&table_name)?,
+    condition
+  );
+  let params = pk_params(Vec::new(), &pk);
+  let mut query = sqlx::query(&q);
+  for (pk_col, pk_val) in pk {
+    let pk_type = db::mysql_data_type(&pool, &table_name, &pk_col).await?;
+    query = db::mysql_bind(query, pk_type.as_deref(), pk_val)?;
+  }
+  let result = query.execute(&pool).await.map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("mysql"),
+    &q,
+    params,
+    Some(result.rows_affected()),
+  )
+  .await;
   Ok(result.rows_affected())
 }
 
 #[tauri::command]
-async fn mysql_drop_table(state: State<'_, AppState>, table_name: String) -> Result<(), String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-  let q = format!("DROP TABLE `{}`", table_name);
+async fn mysql_drop_table(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("mysql"))?;
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  let q = format!("DROP TABLE {}", ident::quote_mysql(&table_name)?);
   sqlx::query(&q)
     .execute(&pool)
     .await
     .map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("mysql"),
+    &q,
+    serde_json::json!([]),
+    None,
+  )
+  .await;
   Ok(())
 }
 
@@ -1941,36 +3737,61 @@ async fn mysql_drop_table(state: State<'_, AppState>, table_name: String) -> Res
 async fn postgres_delete_row(
   state: State<'_, AppState>,
   table_name: String,
-  pk_col: String,
-  pk_val: String,
+  pk: BTreeMap<String, String>,
+  connection_id: Option<String>,
+  schema: Option<String>,
 ) -> Result<u64, String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("postgres"))?;
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  let condition = pk_condition(&pk, 1, |col, n| {
+    Ok(format!("{}::text = ${}", ident::quote_pg(col)?, n))
+  })?;
   let q = format!(
-    "DELETE FROM public.\"{}\" WHERE \"{}\"::text = $1",
-    table_name, pk_col
+    "DELETE FROM {} WHERE {}",
+    pg_table(&schema, &table_name)?,
+    condition
   );
-  let result = sqlx::query(&q)
-    .bind(pk_val)
-    .execute(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
+  let params = pk_params(Vec::new(), &pk);
+  let mut query = sqlx::query(&q);
+  for value in pk.into_values() {
+    query = query.bind(value);
+  }
+  let result = query.execute(&pool).await.map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("postgres"),
+    &q,
+    params,
+    Some(result.rows_affected()),
+  )
+  .await;
   Ok(result.rows_affected())
 }
 
 #[tauri::command]
-async fn postgres_drop_table(state: State<'_, AppState>, table_name: String) -> Result<(), String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-  let q = format!("DROP TABLE public.\"{}\"", table_name);
+async fn postgres_drop_table(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+  schema: Option<String>,
+) -> Result<(), String> {
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("postgres"))?;
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
+  let q = format!("DROP TABLE {}", pg_table(&schema, &table_name)?);
   sqlx::query(&q)
     .execute(&pool)
     .await
     .map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("postgres"),
+    &q,
+    serde_json::json!([]),
+    None,
+  )
+  .await;
   Ok(())
 }
 
@@ -1978,33 +3799,57 @@ async fn postgres_drop_table(state: State<'_, AppState>, table_name: String) ->
 async fn sqlite_delete_row(
   state: State<'_, AppState>,
   table_name: String,
-  pk_col: String,
-  pk_val: String,
+  pk: BTreeMap<String, String>,
+  connection_id: Option<String>,
 ) -> Result<u64, String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-  let q = format!("DELETE FROM \"{}\" WHERE \"{}\" = ?", table_name, pk_col);
-  let result = sqlx::query(&q)
-    .bind(pk_val)
-    .execute(&pool)
-    .await
-    .map_err(|e| e.to_string())?;
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("sqlite"))?;
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  let condition = pk_condition(&pk, 1, |col, _| {
+    Ok(format!("{} = ?", ident::quote_sqlite(col)?))
+  })?;
+  let q = format!(
+    "DELETE FROM {} WHERE {}",
+    ident::quote_sqlite(&table_name)?,
+    condition
+  );
+  let params = pk_params(Vec::new(), &pk);
+  let mut query = sqlx::query(&q);
+  for value in pk.into_values() {
+    query = query.bind(value);
+  }
+  let result = query.execute(&pool).await.map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("sqlite"),
+    &q,
+    params,
+    Some(result.rows_affected()),
+  )
+  .await;
   Ok(result.rows_affected())
 }
 
 #[tauri::command]
-async fn sqlite_drop_table(state: State<'_, AppState>, table_name: String) -> Result<(), String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-  let q = format!("DROP TABLE \"{}\"", table_name);
+async fn sqlite_drop_table(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("sqlite"))?;
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  let q = format!("DROP TABLE {}", ident::quote_sqlite(&table_name)?);
   sqlx::query(&q)
     .execute(&pool)
     .await
     .map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("sqlite"),
+    &q,
+    serde_json::json!([]),
+    None,
+  )
+  .await;
   Ok(())
 }
 #[tauri::command]
@@ -2012,11 +3857,10 @@ async fn redis_rename_key(
   state: State<'_, AppState>,
   old_key: String,
   new_key: String,
+  connection_id: Option<String>,
 ) -> Result<(), String> {
-  let client = {
-    let guard = state.redis_client.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("redis"))?;
+  let client = connections::redis(&state, connection_id.as_deref())?;
   let mut con = client
     .get_multiplexed_async_connection()
     .await
@@ -2035,16 +3879,27 @@ async fn mysql_rename_table(
   state: State<'_, AppState>,
   old_name: String,
   new_name: String,
+  connection_id: Option<String>,
 ) -> Result<(), String> {
-  let pool = {
-    let guard = state.mysql_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-  let q = format!("RENAME TABLE `{}` TO `{}`", old_name, new_name);
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("mysql"))?;
+  let pool = connections::mysql(&state, connection_id.as_deref())?;
+  let q = format!(
+    "RENAME TABLE {} TO {}",
+    ident::quote_mysql(&old_name)?,
+    ident::quote_mysql(&new_name)?
+  );
   sqlx::query(&q)
     .execute(&pool)
     .await
     .map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("mysql"),
+    &q,
+    serde_json::json!([]),
+    None,
+  )
+  .await;
   Ok(())
 }
 
@@ -2053,19 +3908,29 @@ async fn postgres_rename_table(
   state: State<'_, AppState>,
   old_name: String,
   new_name: String,
+  connection_id: Option<String>,
+  schema: Option<String>,
 ) -> Result<(), String> {
-  let pool = {
-    let guard = state.pg_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("postgres"))?;
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pool, schema).await?;
   let q = format!(
-    "ALTER TABLE public.\"{}\" RENAME TO \"{}\"",
-    old_name, new_name
+    "ALTER TABLE {} RENAME TO {}",
+    pg_table(&schema, &old_name)?,
+    ident::quote_pg(&new_name)?
   );
   sqlx::query(&q)
     .execute(&pool)
     .await
     .map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("postgres"),
+    &q,
+    serde_json::json!([]),
+    None,
+  )
+  .await;
   Ok(())
 }
 
@@ -2074,22 +3939,43 @@ async fn sqlite_rename_table(
   state: State<'_, AppState>,
   old_name: String,
   new_name: String,
+  connection_id: Option<String>,
 ) -> Result<(), String> {
-  let pool = {
-    let guard = state.sqlite_pool.lock().unwrap();
-    guard.clone().ok_or("Not connected")?
-  };
-  let q = format!("ALTER TABLE \"{}\" RENAME TO \"{}\"", old_name, new_name);
+  readonly::ensure_writable(&state, connection_id.as_deref().unwrap_or("sqlite"))?;
+  let pool = connections::sqlite(&state, connection_id.as_deref())?;
+  let q = format!(
+    "ALTER TABLE {} RENAME TO {}",
+    ident::quote_sqlite(&old_name)?,
+    ident::quote_sqlite(&new_name)?
+  );
   sqlx::query(&q)
     .execute(&pool)
     .await
     .map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection_id.as_deref().unwrap_or("sqlite"),
+    &q,
+    serde_json::json!([]),
+    None,
+  )
+  .await;
   Ok(())
 }
 
+/// Entry point for headless CLI invocations; see `cli::run`.
+#[must_use]
+pub fn run_cli(args: &[String]) -> Option<i32> {
+  cli::run(args)
+}
+
+// The app has nothing to fall back to when Tauri fails to start
+#[allow(clippy::expect_used, clippy::missing_panics_doc)]
 pub fn run() {
+  logging::init();
   tauri::Builder::default()
     .plugin(tauri_plugin_opener::init())
+    .plugin(tauri_plugin_clipboard_manager::init())
     .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
       let _ = app
         .get_webview_window("main")
@@ -2098,26 +3984,133 @@ pub fn run() {
           let _ = w.set_focus();
         });
     }))
-    .manage(AppState {
-      redis_client: Mutex::new(None),
-      mysql_pool: Mutex::new(None),
-      pg_pool: Mutex::new(None),
-      sqlite_pool: Mutex::new(None),
-      mongo_client: Mutex::new(None),
-      ssh_sessions: Mutex::new(HashMap::new()),
-      is_pinned: Mutex::new(true),
-    })
-    .invoke_handler(tauri::generate_handler![
+    .register_uri_scheme_protocol(payload::SCHEME, payload::serve)
+    .manage(AppState::new())
+    .invoke_handler(usage::counted(tauri::generate_handler![
       greet,
       update_click_region,
       get_screen_work_area,
       get_all_monitors_work_area,
+      connections::list_connections,
+      health::get_connection_status,
       connect_redis,
       redis_get_keys,
       redis_get_value,
       redis_set_value,
       redis_del_key,
       redis_get_ttl,
+      redis_flush_db,
+      redis_flush_all,
+      key_history::get_key_history,
+      key_history::restore_key_version,
+      conntest::test_sqlite_connection,
+      conntest::test_redis_connection,
+      conntest::test_mysql_connection,
+      conntest::test_postgres_connection,
+      conntest::test_mongodb_connection,
+      dsn::parse_connection_string,
+      dsn::connect_connection_string,
+      destinations::save_export_destination,
+      destinations::list_export_destinations,
+      destinations::delete_export_destination,
+      destinations::export_to_destination,
+      importers::import_connections,
+      import::import_file,
+      import::save_import_preset,
+      import::list_import_presets,
+      import::delete_import_preset,
+      bundles::export_profiles,
+      bundles::import_profiles,
+      roles::run_as_role,
+      refgraph::redis_build_reference_graph,
+      logging::get_recent_logs,
+      logging::set_log_level,
+      statements::list_statement_templates,
+      statements::save_statement_template,
+      statements::delete_statement_template,
+      statements::render_statement_template,
+      audit::query_audit_log,
+      audit::export_audit_log,
+      audit::verify_audit_log,
+      scripting::start_scripting_server,
+      scripting::stop_scripting_server,
+      scripting::get_scripting_server,
+      share::start_session_share,
+      share::stop_session_share,
+      share::get_session_share,
+      share::renew_share_invite,
+      share::remove_share_guest,
+      share::join_shared_session,
+      share::list_shared_sessions,
+      share::query_shared_session,
+      share::leave_shared_session,
+      readonly::set_connection_read_only,
+      readonly::get_connection_read_only,
+      transfer::get_transfer_stats,
+      transfer::reset_transfer_stats,
+      history::get_query_history,
+      history::search_query_history,
+      history::clear_query_history,
+      console::fetch_next_page,
+      console::close_cursor,
+      console::close_cursors,
+      snippets::save_snippet,
+      snippets::list_snippets,
+      snippets::delete_snippet,
+      snippets::run_snippet,
+      snippets::export_snippets,
+      snippets::import_snippets,
+      tasks::list_tasks,
+      tasks::cancel_task,
+      tasks::start_count_task,
+      tasks::start_export_task,
+      file_export::export_query_result,
+      sql_dump::export_table_sql,
+      xlsx_export::export_xlsx,
+      parquet_export::export_parquet,
+      clipboard::copy_rows_to_clipboard,
+      schema_cache::refresh_schema_cache,
+      pools::get_pool_stats,
+      tasks::start_import_task,
+      journal::journal_pending_edits,
+      journal::clear_pending_edits,
+      journal::get_recovery,
+      journal::dismiss_recovery,
+      journal::resume_task,
+      references::follow_reference,
+      cancel::cancel_query,
+      cancel::list_running_queries,
+      session::set_session_variable,
+      session::set_search_path,
+      session::get_session_state,
+      views::save_view,
+      views::list_views,
+      views::apply_view,
+      views::delete_view,
+      views::export_views,
+      views::import_views,
+      timeouts::set_statement_timeout,
+      timeouts::get_statement_timeout,
+      timeseries::explore_time_series,
+      timeseries::time_bucket_rows,
+      catalogs::list_catalogs,
+      catalogs::postgres_get_catalog,
+      catalogs::mysql_get_information_schema,
+      explain::mysql_explain,
+      explain::postgres_explain,
+      explain::sqlite_explain_query_plan,
+      streaming::stream_rows,
+      streaming::stop_stream,
+      display::move_to_monitor,
+      effects::set_window_backdrop,
+      effects::set_window_corners,
+      effects::set_window_shadow,
+      effects::get_system_theme,
+      updater::get_update_channel,
+      updater::set_update_channel,
+      updater::check_for_updates,
+      updater::download_update,
+      updater::restart_to_update,
       redis_execute_raw,
       connect_mysql,
       connect_postgres,
@@ -2134,6 +4127,7 @@ pub fn run() {
       postgres_get_primary_key,
       postgres_update_cell,
       sqlite_get_tables,
+      sqlite_get_views,
       sqlite_get_rows,
       sqlite_get_count,
       sqlite_update_cell,
@@ -2141,9 +4135,47 @@ pub fn run() {
       sqlite_execute_raw,
       mysql_execute_raw,
       postgres_execute_raw,
+      script::execute_script,
       mysql_get_columns,
+      mysql_get_table_schema,
+      mysql_get_indexes,
+      mysql_get_foreign_keys,
+      mysql_show_create_table,
       postgres_get_columns,
+      postgres_get_table_schema,
+      postgres_get_indexes,
+      postgres_get_foreign_keys,
+      postgres_get_table_ddl,
       sqlite_get_columns,
+      sqlite_get_table_schema,
+      sqlite_get_indexes,
+      sqlite_get_foreign_keys,
+      sqlite_get_table_sql,
+      designer::mysql_create_table,
+      designer::postgres_create_table,
+      designer::sqlite_create_table,
+      designer::mysql_alter_table,
+      designer::postgres_alter_table,
+      designer::sqlite_alter_table,
+      table_copy::mysql_duplicate_table,
+      table_copy::postgres_duplicate_table,
+      table_copy::sqlite_duplicate_table,
+      view_definitions::mysql_get_view_definition,
+      view_definitions::postgres_get_view_definition,
+      view_definitions::sqlite_get_view_definition,
+      view_definitions::mysql_create_or_replace_view,
+      view_definitions::postgres_create_or_replace_view,
+      view_definitions::sqlite_create_or_replace_view,
+      triggers::mysql_get_triggers,
+      triggers::postgres_get_triggers,
+      triggers::sqlite_get_triggers,
+      triggers::mysql_drop_trigger,
+      triggers::postgres_drop_trigger,
+      triggers::sqlite_drop_trigger,
+      routines::mysql_get_routine_definition,
+      routines::postgres_get_routine_definition,
+      routines::mysql_call_routine,
+      routines::postgres_call_routine,
       mysql_insert_row,
       postgres_insert_row,
       sqlite_insert_row,
@@ -2164,6 +4196,7 @@ pub fn run() {
       mysql_get_functions,
       mysql_get_procedures,
       postgres_get_databases,
+      postgres_get_schemas,
       postgres_get_tables_with_size,
       postgres_get_views,
       postgres_get_functions,
@@ -2173,18 +4206,91 @@ pub fn run() {
       disconnect_mysql,
       disconnect_postgres,
       disconnect_mongodb,
-      set_pinned
-    ])
+      set_pinned,
+      variables::set_variable,
+      variables::list_variables,
+      variables::delete_variable,
+      discovery::discover_local_databases,
+      discovery::probe_host,
+      masking::set_masking_rules,
+      masking::set_masked_mode,
+      masking::get_masking_config,
+      watch::watch_row,
+      watch::unwatch_row,
+      watch::get_row_versions,
+      watch::list_row_watches,
+      watch::tail_table,
+      watch::stop_tail,
+      watch::list_tails,
+      cdc::start_change_feed,
+      cdc::stop_change_feed,
+      cdc::list_change_feeds,
+      notify::postgres_listen,
+      notify::postgres_unlisten,
+      notify::postgres_notify,
+      results::cache_query,
+      results::get_result_page,
+      results::get_result_columnar,
+      results::get_result_page_transfer,
+      payload::fetch_payload,
+      payload::release_payload,
+      profiles::save_connection_profile,
+      profiles::get_connection_profile,
+      profiles::list_connection_profiles,
+      profiles::delete_connection_profile,
+      profiles::connect_profile,
+      secrets::store_secret,
+      secrets::get_secret,
+      secrets::delete_secret,
+      workspaces::list_workspaces,
+      workspaces::create_workspace,
+      workspaces::switch_workspace,
+      workspaces::get_workspace_setting,
+      workspaces::set_workspace_setting,
+      collation::preview_sort,
+      collation::list_collations,
+      usage::get_profile_stats,
+      usage::get_usage_report,
+      usage::purge_usage_data,
+      distinct::distinct_values,
+      tunnels::list_ssh_tunnels,
+      tunnels::close_ssh_tunnel,
+      results::list_results,
+      results::release_result,
+      results::pivot_result,
+      results::aggregate_selection,
+      results::search_result,
+      lineage::get_view_lineage,
+      templates::list_templates,
+      templates::save_template,
+      templates::delete_template,
+      templates::preview_template,
+      templates::run_template,
+      timezone::set_display_timezone,
+      timezone::get_display_timezone,
+      timezone::list_timezones,
+      schema::get_column_info,
+      bench::bench_command
+    ]))
     .on_window_event(|window, event| {
       if let tauri::WindowEvent::Focused(focused) = event {
         let state = window.state::<AppState>();
-        let is_pinned = *state.is_pinned.lock().unwrap();
+        let is_pinned = *state
+          .is_pinned
+          .lock()
+          .unwrap_or_else(PoisonError::into_inner);
         if *focused {
           let _ = window.set_always_on_top(true);
         } else {
           let _ = window.set_always_on_top(is_pinned);
         }
       }
+      if let tauri::WindowEvent::ThemeChanged(theme) = event {
+        effects::on_theme_changed(window, *theme);
+      }
+      if let tauri::WindowEvent::ScaleFactorChanged { scale_factor, .. } = event {
+        display::on_scale_changed(window, *scale_factor);
+      }
       if let tauri::WindowEvent::CloseRequested { api, .. } = event {
         if window.label() == "main" {
           let _ = window.hide();
@@ -2193,7 +4299,32 @@ pub fn run() {
       }
     })
     .setup(|app| {
-      let window = app.get_webview_window("main").unwrap();
+      let data_dir = app.path().app_data_dir()?;
+      if let Err(e) = logging::open_file(&data_dir.join("logs")) {
+        tracing::error!("Failed to open log file: {}", e);
+      }
+      updater::apply_staged(app.handle());
+      let store_path = data_dir.join(store::FILE_NAME);
+      let state = app.state::<AppState>();
+      *state.app.lock().unwrap_or_else(PoisonError::into_inner) = Some(app.handle().clone());
+      tauri::async_runtime::spawn(health::monitor(app.handle().clone()));
+      let opened = tauri::async_runtime::block_on(async {
+        *state.store.lock().unwrap_or_else(PoisonError::into_inner) =
+          Some(store::open(&store_path).await?);
+        workspaces::load_active(&state).await?;
+        variables::load(&state).await
+      });
+      match opened {
+        Ok(()) => {
+          tauri::async_runtime::spawn(profiles::restore_on_startup(app.handle().clone()));
+          tauri::async_runtime::spawn(usage::flush_periodically(app.handle().clone()));
+        }
+        Err(e) => tracing::error!("Failed to open app store {}: {}", store_path.display(), e),
+      }
+
+      let window = app
+        .get_webview_window("main")
+        .ok_or("Main window is missing")?;
 
       // Initialize window size and position for floating widget
       // Set to toolbar size (365x56) and position at bottom-right corner
@@ -2217,21 +4348,13 @@ pub fn run() {
 
       let _ = window.set_position(tauri::Position::Logical(tauri::LogicalPosition { x, y }));
 
+      effects::apply_theme(
+        &window.as_ref().window(),
+        window.theme().unwrap_or(tauri::Theme::Dark),
+      );
+
       #[cfg(target_os = "windows")]
       {
-        let hwnd_handle = window.hwnd().unwrap();
-        let hwnd = HWND(hwnd_handle.0 as _);
-        let dark_mode = 1i32;
-
-        unsafe {
-          let _ = DwmSetWindowAttribute(
-            hwnd,
-            DWMWA_USE_IMMERSIVE_DARK_MODE,
-            &dark_mode as *const _ as *const _,
-            std::mem::size_of::<i32>() as u32,
-          );
-        }
-
         // 额外尝试设置 Webview 的背景色为透明
         let _ = window.set_background_color(Some(tauri::window::Color(0, 0, 0, 0)));
       }
@@ -2274,7 +4397,7 @@ pub fn run() {
         .icon(tray_icon)
         .build(app)?;
 
-      window.show().unwrap();
+      window.show()?;
       #[cfg(debug_assertions)]
       window.open_devtools();
 
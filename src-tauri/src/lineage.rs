@@ -0,0 +1,369 @@
+//! Maps the output columns of a view back to the base table columns they are read from.
+
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+  visit_expressions, Expr, Ident, Query, SelectItem, SetExpr, Statement, TableFactor,
+};
+use sqlparser::dialect::{Dialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect};
+use sqlparser::parser::Parser;
+use tauri::State;
+
+use crate::db::{self, SqlPool};
+use crate::AppState;
+
+#[derive(serde::Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceColumn {
+  /// `None` when an unqualified column can't be pinned to one of several joined tables.
+  pub table: Option<String>,
+  pub column: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnLineage {
+  pub column: String,
+  pub sources: Vec<SourceColumn>,
+  /// Expression text for computed columns; `None` when the column is a plain reference.
+  pub expression: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewLineage {
+  pub view: String,
+  pub definition: String,
+  pub columns: Vec<ColumnLineage>,
+}
+
+enum Relation {
+  Table(String),
+  Derived(Vec<ColumnLineage>),
+}
+
+/// Relations visible in one SELECT, keyed by alias (or bare table name).
+type Scope = Vec<(String, Relation)>;
+type Ctes = HashMap<String, Vec<ColumnLineage>>;
+
+fn object_name(idents: &[Ident]) -> String {
+  idents
+    .iter()
+    .map(|i| i.value.as_str())
+    .collect::<Vec<_>>()
+    .join(".")
+}
+
+fn push_source(sources: &mut Vec<SourceColumn>, source: SourceColumn) {
+  if !sources.contains(&source) {
+    sources.push(source);
+  }
+}
+
+fn query_lineage(query: &Query, outer: &Ctes) -> Vec<ColumnLineage> {
+  let mut ctes = outer.clone();
+  if let Some(with) = &query.with {
+    for cte in &with.cte_tables {
+      let mut columns = query_lineage(&cte.query, &ctes);
+      rename_columns(&mut columns, cte.alias.columns.iter().map(|c| &c.name));
+      ctes.insert(cte.alias.name.value.to_lowercase(), columns);
+    }
+  }
+  set_expr_lineage(&query.body, &ctes)
+}
+
+fn rename_columns<'a>(columns: &mut [ColumnLineage], names: impl Iterator<Item = &'a Ident>) {
+  for (column, name) in columns.iter_mut().zip(names) {
+    column.column.clone_from(&name.value);
+  }
+}
+
+fn set_expr_lineage(body: &SetExpr, ctes: &Ctes) -> Vec<ColumnLineage> {
+  match body {
+    SetExpr::Select(select) => {
+      let mut scope = Scope::new();
+      for from in &select.from {
+        add_relation(&from.relation, ctes, &mut scope);
+        for join in &from.joins {
+          add_relation(&join.relation, ctes, &mut scope);
+        }
+      }
+      select
+        .projection
+        .iter()
+        .flat_map(|item| item_lineage(item, &scope))
+        .collect()
+    }
+    SetExpr::Query(query) => query_lineage(query, ctes),
+    SetExpr::SetOperation { left, right, .. } => {
+      // Output names come from the left branch; each column may read from either side
+      let mut columns = set_expr_lineage(left, ctes);
+      for (column, other) in columns.iter_mut().zip(set_expr_lineage(right, ctes)) {
+        for source in other.sources {
+          push_source(&mut column.sources, source);
+        }
+      }
+      columns
+    }
+    _ => Vec::new(),
+  }
+}
+
+fn add_relation(factor: &TableFactor, ctes: &Ctes, scope: &mut Scope) {
+  match factor {
+    TableFactor::Table { name, alias, .. } => {
+      let bare = name.0.last().map(|i| i.value.clone()).unwrap_or_default();
+      let key = alias
+        .as_ref()
+        .map_or(bare.clone(), |a| a.name.value.clone());
+      let relation = match ctes.get(&bare.to_lowercase()) {
+        Some(columns) if name.0.len() == 1 => Relation::Derived(columns.clone()),
+        _ => Relation::Table(object_name(&name.0)),
+      };
+      scope.push((key, relation));
+    }
+    TableFactor::Derived {
+      subquery,
+      alias: Some(alias),
+      ..
+    } => {
+      let mut columns = query_lineage(subquery, ctes);
+      rename_columns(&mut columns, alias.columns.iter().map(|c| &c.name));
+      scope.push((alias.name.value.clone(), Relation::Derived(columns)));
+    }
+    TableFactor::NestedJoin {
+      table_with_joins, ..
+    } => {
+      add_relation(&table_with_joins.relation, ctes, scope);
+      for join in &table_with_joins.joins {
+        add_relation(&join.relation, ctes, scope);
+      }
+    }
+    _ => {}
+  }
+}
+
+fn relation_columns(relation: &Relation, column: &str) -> Option<Vec<SourceColumn>> {
+  match relation {
+    Relation::Table(table) => Some(vec![SourceColumn {
+      table: Some(table.clone()),
+      column: column.to_string(),
+    }]),
+    Relation::Derived(columns) => {
+      if let Some(found) = columns
+        .iter()
+        .find(|c| c.column.eq_ignore_ascii_case(column))
+      {
+        return Some(found.sources.clone());
+      }
+      // Column may come through a `*` in the derived query
+      let through_wildcard: Vec<SourceColumn> = columns
+        .iter()
+        .filter(|c| c.column == "*")
+        .flat_map(|c| &c.sources)
+        .map(|s| SourceColumn {
+          table: s.table.clone(),
+          column: column.to_string(),
+        })
+        .collect();
+      (!through_wildcard.is_empty()).then_some(through_wildcard)
+    }
+  }
+}
+
+fn find_relation<'a>(scope: &'a Scope, qualifier: &[Ident]) -> Option<&'a Relation> {
+  let full = object_name(qualifier);
+  let last = qualifier
+    .last()
+    .map(|i| i.value.as_str())
+    .unwrap_or_default();
+  scope
+    .iter()
+    .find(|(key, relation)| {
+      key.eq_ignore_ascii_case(last)
+        && match relation {
+          Relation::Table(table) => qualifier.len() == 1 || table.eq_ignore_ascii_case(&full),
+          Relation::Derived(_) => qualifier.len() == 1,
+        }
+    })
+    .map(|(_, relation)| relation)
+}
+
+fn resolve_column(scope: &Scope, idents: &[Ident]) -> Vec<SourceColumn> {
+  let Some((column, qualifier)) = idents.split_last() else {
+    return Vec::new();
+  };
+  let column = column.value.as_str();
+
+  if !qualifier.is_empty() {
+    return find_relation(scope, qualifier)
+      .and_then(|relation| relation_columns(relation, column))
+      .unwrap_or_else(|| {
+        vec![SourceColumn {
+          table: Some(object_name(qualifier)),
+          column: column.to_string(),
+        }]
+      });
+  }
+
+  if let [(_, relation)] = scope.as_slice() {
+    if let Some(sources) = relation_columns(relation, column) {
+      return sources;
+    }
+  }
+  // Several relations: only derived ones tell us which columns they expose
+  scope
+    .iter()
+    .find_map(|(_, relation)| match relation {
+      Relation::Derived(columns) => columns
+        .iter()
+        .find(|c| c.column.eq_ignore_ascii_case(column))
+        .map(|c| c.sources.clone()),
+      Relation::Table(_) => None,
+    })
+    .unwrap_or_else(|| {
+      vec![SourceColumn {
+        table: None,
+        column: column.to_string(),
+      }]
+    })
+}
+
+fn expr_lineage(name: String, expr: &Expr, scope: &Scope) -> ColumnLineage {
+  match expr {
+    Expr::Identifier(ident) => ColumnLineage {
+      column: name,
+      sources: resolve_column(scope, std::slice::from_ref(ident)),
+      expression: None,
+    },
+    Expr::CompoundIdentifier(idents) => ColumnLineage {
+      column: name,
+      sources: resolve_column(scope, idents),
+      expression: None,
+    },
+    _ => {
+      let mut sources = Vec::new();
+      let _ = visit_expressions(expr, |e| {
+        let found = match e {
+          Expr::Identifier(ident) => resolve_column(scope, std::slice::from_ref(ident)),
+          Expr::CompoundIdentifier(idents) => resolve_column(scope, idents),
+          _ => Vec::new(),
+        };
+        for source in found {
+          push_source(&mut sources, source);
+        }
+        ControlFlow::<()>::Continue(())
+      });
+      ColumnLineage {
+        column: name,
+        sources,
+        expression: Some(expr.to_string()),
+      }
+    }
+  }
+}
+
+fn wildcard_lineage(relation: &Relation) -> Vec<ColumnLineage> {
+  match relation {
+    Relation::Table(table) => vec![ColumnLineage {
+      column: "*".to_string(),
+      sources: vec![SourceColumn {
+        table: Some(table.clone()),
+        column: "*".to_string(),
+      }],
+      expression: None,
+    }],
+    Relation::Derived(columns) => columns.clone(),
+  }
+}
+
+fn item_lineage(item: &SelectItem, scope: &Scope) -> Vec<ColumnLineage> {
+  match item {
+    SelectItem::UnnamedExpr(expr) => {
+      let name = match expr {
+        Expr::Identifier(ident) => ident.value.clone(),
+        Expr::CompoundIdentifier(idents) => {
+          idents.last().map(|i| i.value.clone()).unwrap_or_default()
+        }
+        other => other.to_string(),
+      };
+      vec![expr_lineage(name, expr, scope)]
+    }
+    SelectItem::ExprWithAlias { expr, alias } => {
+      vec![expr_lineage(alias.value.clone(), expr, scope)]
+    }
+    SelectItem::Wildcard(_) => scope
+      .iter()
+      .flat_map(|(_, relation)| wildcard_lineage(relation))
+      .collect(),
+    SelectItem::QualifiedWildcard(name, _) => find_relation(scope, &name.0)
+      .map(wildcard_lineage)
+      .unwrap_or_default(),
+  }
+}
+
+async fn view_definition(pool: &SqlPool, view: &str) -> Result<Option<String>, String> {
+  let row: Option<(String,)> = match pool {
+    SqlPool::MySql(pool) => sqlx::query_as(
+      "SELECT CAST(VIEW_DEFINITION AS CHAR) FROM information_schema.VIEWS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+    )
+    .bind(view)
+    .fetch_optional(pool)
+    .await,
+    SqlPool::Postgres(pool) => sqlx::query_as(
+      "SELECT pg_get_viewdef(c.oid, true) FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace WHERE n.nspname = 'public' AND c.relname = $1 AND c.relkind IN ('v', 'm')",
+    )
+    .bind(view)
+    .fetch_optional(pool)
+    .await,
+    SqlPool::Sqlite(pool) => {
+      sqlx::query_as("SELECT sql FROM sqlite_master WHERE type = 'view' AND name = ?")
+        .bind(view)
+        .fetch_optional(pool)
+        .await
+    }
+  }
+  .map_err(|e| e.to_string())?;
+  Ok(row.map(|(definition,)| definition))
+}
+
+/// Parses a view's definition and maps each output column to the table columns it reads.
+///
+/// `*` columns are reported as `*` against their source table rather than expanded.
+#[tauri::command]
+pub async fn get_view_lineage(
+  state: State<'_, AppState>,
+  connection: String,
+  view: String,
+) -> Result<ViewLineage, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let definition = view_definition(&pool, &view)
+    .await?
+    .ok_or_else(|| format!("View not found: {}", view))?;
+
+  let dialect: Box<dyn Dialect> = match pool {
+    SqlPool::MySql(_) => Box::new(MySqlDialect {}),
+    SqlPool::Postgres(_) => Box::new(PostgreSqlDialect {}),
+    SqlPool::Sqlite(_) => Box::new(SQLiteDialect {}),
+  };
+  let statements = Parser::parse_sql(dialect.as_ref(), &definition)
+    .map_err(|e| format!("Failed to parse view definition: {}", e))?;
+
+  let columns = match statements.first() {
+    Some(Statement::Query(query)) => query_lineage(query, &Ctes::new()),
+    // SQLite keeps the full CREATE VIEW statement
+    Some(Statement::CreateView { query, columns, .. }) => {
+      let mut lineage = query_lineage(query, &Ctes::new());
+      rename_columns(&mut lineage, columns.iter().map(|c| &c.name));
+      lineage
+    }
+    _ => return Err("View definition is not a query".to_string()),
+  };
+
+  Ok(ViewLineage {
+    view,
+    definition,
+    columns,
+  })
+}
@@ -0,0 +1,201 @@
+//! Diagnostics log: a `tracing-subscriber` stack that keeps recent events in memory for the
+//! in-app viewer, appends them to daily log files under the app data dir and echoes them to
+//! stderr.
+//!
+//! Events from this crate are kept down to the configured level (`SPECTRA_LOG` at startup,
+//! `info` by default); dependencies only contribute warnings and errors.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::fmt::time::ChronoLocal;
+use tracing_subscriber::fmt::writer::{MakeWriterExt, OptionalWriter};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::{fmt, reload, Layer, Registry};
+
+const FILE_PREFIX: &str = "spectra-studio";
+/// Daily files kept, the current one included.
+const KEPT_FILES: usize = 5;
+const RECENT_CAPACITY: usize = 2_000;
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3f%:z";
+
+static FILTER: OnceLock<reload::Handle<Targets, Registry>> = OnceLock::new();
+static FILE: OnceLock<RollingFileAppender> = OnceLock::new();
+static RECENT: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+  pub timestamp: String,
+  pub level: String,
+  pub target: String,
+  pub message: String,
+}
+
+fn parse_level(name: &str) -> Result<LevelFilter, String> {
+  match name.trim().to_lowercase().as_str() {
+    "error" => Ok(LevelFilter::ERROR),
+    "warn" | "warning" => Ok(LevelFilter::WARN),
+    "info" => Ok(LevelFilter::INFO),
+    "debug" => Ok(LevelFilter::DEBUG),
+    "trace" => Ok(LevelFilter::TRACE),
+    other => Err(format!("Unknown log level: {}", other)),
+  }
+}
+
+/// This crate's events down to `level`, warnings and errors of everything else.
+fn targets(level: LevelFilter) -> Targets {
+  Targets::new()
+    .with_default(LevelFilter::WARN)
+    .with_target(env!("CARGO_CRATE_NAME"), level)
+}
+
+/// The recent entries; a panic while one was being added leaves the buffer usable.
+fn recent() -> MutexGuard<'static, VecDeque<LogEntry>> {
+  RECENT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Message text plus any other fields as `name=value`.
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+  fn record_str(&mut self, field: &Field, value: &str) {
+    if field.name() == "message" {
+      self.0.insert_str(0, value);
+    } else {
+      let _ = write!(self.0, " {}={}", field.name(), value);
+    }
+  }
+
+  fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+    if field.name() == "message" {
+      self.0.insert_str(0, &format!("{:?}", value));
+    } else {
+      let _ = write!(self.0, " {}={:?}", field.name(), value);
+    }
+  }
+}
+
+/// Layer keeping the last [`RECENT_CAPACITY`] events for [`get_recent_logs`].
+struct Recent;
+
+impl<S: Subscriber> Layer<S> for Recent {
+  fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+    let mut message = Message::default();
+    event.record(&mut message);
+    let metadata = event.metadata();
+    let entry = LogEntry {
+      timestamp: chrono::Local::now().format(TIMESTAMP_FORMAT).to_string(),
+      level: metadata.level().to_string(),
+      target: metadata.target().to_string(),
+      message: message.0,
+    };
+    let mut recent = recent();
+    if recent.len() == RECENT_CAPACITY {
+      recent.pop_front();
+    }
+    recent.push_back(entry);
+  }
+}
+
+/// Writer for the log file; discards lines until [`open_file`] was called.
+fn file_writer() -> OptionalWriter<tracing_appender::rolling::RollingWriter<'static>> {
+  match FILE.get() {
+    Some(file) => OptionalWriter::some(file.make_writer()),
+    None => OptionalWriter::none(),
+  }
+}
+
+/// Installs the logger as the global subscriber. Events are only kept in memory and on
+/// stderr until [`open_file`] is called.
+pub fn init() {
+  let level = std::env::var("SPECTRA_LOG")
+    .ok()
+    .and_then(|name| parse_level(&name).ok())
+    .unwrap_or(LevelFilter::INFO);
+  let (filter, handle) = reload::Layer::new(targets(level));
+  let subscriber = Registry::default()
+    .with(filter)
+    .with(
+      fmt::layer()
+        .with_timer(ChronoLocal::new(TIMESTAMP_FORMAT.to_string()))
+        .with_ansi(false)
+        .with_writer(std::io::stderr.and(file_writer)),
+    )
+    .with(Recent);
+  if tracing::subscriber::set_global_default(subscriber).is_ok() {
+    let _ = FILTER.set(handle);
+  }
+}
+
+/// Starts appending to daily log files in `dir`.
+pub fn open_file(dir: &Path) -> Result<(), String> {
+  let file = RollingFileAppender::builder()
+    .rotation(Rotation::DAILY)
+    .filename_prefix(FILE_PREFIX)
+    .filename_suffix("log")
+    .max_log_files(KEPT_FILES)
+    .build(dir)
+    .map_err(|e| e.to_string())?;
+  FILE
+    .set(file)
+    .map_err(|_| "The log file is already open".to_string())
+}
+
+/// The most recent log entries, oldest first: at most `limit` (default 500) of them, and only
+/// those at `level` or more severe when given.
+#[tauri::command]
+pub fn get_recent_logs(
+  limit: Option<usize>,
+  level: Option<String>,
+) -> Result<Vec<LogEntry>, String> {
+  let max = match level {
+    Some(name) => parse_level(&name)?,
+    None => LevelFilter::TRACE,
+  };
+  let mut entries: Vec<LogEntry> = recent()
+    .iter()
+    .rev()
+    .filter(|entry| entry.level.parse::<Level>().map_or(true, |l| l <= max))
+    .take(limit.unwrap_or(500))
+    .cloned()
+    .collect();
+  entries.reverse();
+  Ok(entries)
+}
+
+/// Sets how verbose the app's own logging is: `error`, `warn`, `info`, `debug` or `trace`.
+#[tauri::command]
+pub fn set_log_level(level: String) -> Result<(), String> {
+  let filter = parse_level(&level)?;
+  FILTER
+    .get()
+    .ok_or_else(|| "Logging isn't initialized".to_string())?
+    .modify(|current| *current = targets(filter))
+    .map_err(|e| e.to_string())?;
+  tracing::info!("Log level set to {}", level.trim().to_lowercase());
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn dependencies_only_log_warnings() {
+    let targets = targets(LevelFilter::DEBUG);
+    let own = concat!(env!("CARGO_CRATE_NAME"), "::db");
+    assert!(targets.would_enable(own, &Level::DEBUG));
+    assert!(!targets.would_enable(own, &Level::TRACE));
+    assert!(targets.would_enable("sqlx::query", &Level::WARN));
+    assert!(!targets.would_enable("sqlx::query", &Level::INFO));
+  }
+}
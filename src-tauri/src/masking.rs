@@ -0,0 +1,271 @@
+use std::sync::PoisonError;
+
+use sha2::{Digest, Sha256};
+use tauri::State;
+
+use crate::AppState;
+
+const REDACTED: &str = "****";
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(tag = "strategy", rename_all = "camelCase")]
+pub enum MaskStrategy {
+  /// Replace the whole value.
+  Redact,
+  /// Stable short digest, so equal values still look equal.
+  Hash,
+  /// Keep a few leading/trailing characters, e.g. `jo******om`.
+  #[serde(rename_all = "camelCase")]
+  Partial {
+    #[serde(default = "default_keep")]
+    keep_start: usize,
+    #[serde(default = "default_keep")]
+    keep_end: usize,
+  },
+}
+
+fn default_keep() -> usize {
+  2
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MaskRule {
+  /// Case-insensitive column name glob, e.g. `*email*` or `ssn`.
+  pub pattern: String,
+  #[serde(flatten)]
+  pub strategy: MaskStrategy,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MaskingConfig {
+  pub enabled: bool,
+  pub rules: Vec<MaskRule>,
+}
+
+impl MaskingConfig {
+  fn rule_for(&self, column: &str) -> Option<&MaskRule> {
+    let column = column.to_lowercase();
+    self
+      .rules
+      .iter()
+      .find(|rule| glob_match(&rule.pattern.to_lowercase(), &column))
+  }
+
+  /// Whether values of `column` are masked.
+  pub fn masks(&self, column: &str) -> bool {
+    self.rule_for(column).is_some()
+  }
+
+  /// Masks matching columns of a single row in place.
+  pub fn apply(&self, row: &mut serde_json::Map<String, serde_json::Value>) {
+    for (column, value) in row.iter_mut() {
+      if value.is_null() {
+        continue;
+      }
+      if let Some(rule) = self.rule_for(column) {
+        *value = serde_json::Value::String(mask_value(&rule.strategy, value));
+      }
+    }
+  }
+
+  pub fn apply_values(&self, rows: &mut [serde_json::Value]) {
+    for row in rows {
+      if let serde_json::Value::Object(map) = row {
+        self.apply(map);
+      }
+    }
+  }
+
+  /// Masks rows that were already serialized to JSON text.
+  pub fn apply_json_rows(&self, rows: Vec<String>) -> Vec<String> {
+    rows
+      .into_iter()
+      .map(
+        |row| match serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&row) {
+          Ok(mut map) => {
+            self.apply(&mut map);
+            serde_json::Value::Object(map).to_string()
+          }
+          Err(_) => row,
+        },
+      )
+      .collect()
+  }
+}
+
+fn mask_value(strategy: &MaskStrategy, value: &serde_json::Value) -> String {
+  let text = match value {
+    serde_json::Value::String(s) => s.clone(),
+    other => other.to_string(),
+  };
+  match strategy {
+    MaskStrategy::Redact => REDACTED.to_string(),
+    MaskStrategy::Hash => {
+      let digest = Sha256::digest(text.as_bytes());
+      format!("#{}", crate::encoding::hex(&digest[..6]))
+    }
+    MaskStrategy::Partial {
+      keep_start,
+      keep_end,
+    } => {
+      let chars: Vec<char> = text.chars().collect();
+      if chars.len() <= keep_start + keep_end {
+        return "*".repeat(chars.len().max(1));
+      }
+      let hidden = chars.len() - keep_start - keep_end;
+      let mut out: String = chars[..*keep_start].iter().collect();
+      out.push_str(&"*".repeat(hidden));
+      out.extend(&chars[chars.len() - keep_end..]);
+      out
+    }
+  }
+}
+
+/// Minimal glob matcher supporting `*` and `?`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+  let p: Vec<char> = pattern.chars().collect();
+  let t: Vec<char> = text.chars().collect();
+  let (mut pi, mut ti) = (0, 0);
+  let mut star: Option<(usize, usize)> = None;
+  while ti < t.len() {
+    if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+      pi += 1;
+      ti += 1;
+    } else if pi < p.len() && p[pi] == '*' {
+      star = Some((pi, ti));
+      pi += 1;
+    } else if let Some((star_pi, star_ti)) = star {
+      pi = star_pi + 1;
+      ti = star_ti + 1;
+      star = Some((star_pi, star_ti + 1));
+    } else {
+      return false;
+    }
+  }
+  p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Returns the connection's masking config only while masked mode is switched on.
+pub fn active(state: &AppState, connection: &str) -> Option<MaskingConfig> {
+  state
+    .masking
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(connection)
+    .filter(|config| config.enabled && !config.rules.is_empty())
+    .cloned()
+}
+
+#[tauri::command]
+pub fn set_masking_rules(
+  state: State<'_, AppState>,
+  connection: String,
+  rules: Vec<MaskRule>,
+) -> Result<(), String> {
+  if let Some(rule) = rules.iter().find(|rule| rule.pattern.trim().is_empty()) {
+    return Err(format!("Empty column pattern in masking rule: {:?}", rule));
+  }
+  state
+    .masking
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .entry(connection)
+    .or_default()
+    .rules = rules;
+  Ok(())
+}
+
+#[tauri::command]
+pub fn set_masked_mode(state: State<'_, AppState>, connection: String, enabled: bool) {
+  state
+    .masking
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .entry(connection)
+    .or_default()
+    .enabled = enabled;
+}
+
+#[tauri::command]
+pub fn get_masking_config(state: State<'_, AppState>, connection: String) -> MaskingConfig {
+  state
+    .masking
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(&connection)
+    .cloned()
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn config() -> MaskingConfig {
+    serde_json::from_value(json!({
+      "enabled": true,
+      "rules": [
+        { "pattern": "*email*", "strategy": "partial" },
+        { "pattern": "ssn", "strategy": "redact" },
+        { "pattern": "card_?", "strategy": "hash" },
+      ],
+    }))
+    .unwrap()
+  }
+
+  #[test]
+  fn globs_match_whole_names_ignoring_case() {
+    assert!(glob_match("*email*", "work_email_address"));
+    assert!(glob_match("card_?", "card_1"));
+    assert!(!glob_match("card_?", "card_12"));
+    assert!(!glob_match("ssn", "ssn_hash"));
+    let config = config();
+    assert!(config.masks("Contact_EMAIL"));
+    assert!(config.masks("SSN"));
+    assert!(!config.masks("name"));
+  }
+
+  #[test]
+  fn partial_keeps_the_ends() {
+    let partial = MaskStrategy::Partial {
+      keep_start: 2,
+      keep_end: 2,
+    };
+    assert_eq!(
+      mask_value(&partial, &json!("jo@example.com")),
+      "jo**********om"
+    );
+    assert_eq!(mask_value(&partial, &json!("José")), "****");
+    assert_eq!(mask_value(&partial, &json!("")), "*");
+    assert_eq!(mask_value(&partial, &json!(123_456)), "12**56");
+  }
+
+  #[test]
+  fn hashes_are_stable_and_short() {
+    let a = mask_value(&MaskStrategy::Hash, &json!("4111 1111"));
+    assert_eq!(a, mask_value(&MaskStrategy::Hash, &json!("4111 1111")));
+    assert_ne!(a, mask_value(&MaskStrategy::Hash, &json!("4111 1112")));
+    assert!(a.starts_with('#') && a.len() == 13);
+  }
+
+  #[test]
+  fn rows_are_masked_in_place() {
+    let mut rows = vec![json!({
+      "id": 1,
+      "email": "jo@example.com",
+      "ssn": "123-45-6789",
+      "card_1": null,
+    })];
+    config().apply_values(&mut rows);
+    assert_eq!(
+      rows[0],
+      json!({ "id": 1, "email": "jo**********om", "ssn": "****", "card_1": null })
+    );
+    let masked =
+      config().apply_json_rows(vec![r#"{"ssn":"123"}"#.to_string(), "not json".to_string()]);
+    assert_eq!(masked, vec![r#"{"ssn":"****"}"#, "not json"]);
+  }
+}
@@ -0,0 +1,176 @@
+//! Postgres `LISTEN` / `NOTIFY`, for watching apps that signal through notifications (job
+//! queues, cache invalidation) at work. Each channel listened to takes one connection out of
+//! the pool for as long as it is listened to, and forwards what arrives as
+//! `postgres-notification` events.
+//!
+//! Channel names are taken as written, without folding to lower case: `LISTEN` here quotes
+//! them, and `pg_notify` never folds.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use sqlx::postgres::PgListener;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::ident::{self, Dialect};
+use crate::{audit, connections, readonly, store, AppState};
+
+/// Wait before listening again after the connection couldn't be reopened.
+const RETRY: Duration = Duration::from_secs(5);
+
+/// Listening tasks by connection id and channel.
+pub type Listeners = HashMap<(String, String), tokio::task::JoinHandle<()>>;
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct NotificationEvent {
+  connection_id: String,
+  channel: String,
+  /// `None` on events reporting an `error`.
+  payload: Option<String>,
+  /// Backend PID of the session that sent the notification.
+  process_id: Option<u32>,
+  received_at: i64,
+  error: Option<String>,
+}
+
+/// Emits what `listener` receives until the connection's pool closes.
+async fn forward(app: AppHandle, mut listener: PgListener, key: (String, String)) {
+  let emit = |payload: Option<String>, process_id: Option<u32>, error: Option<String>| {
+    let _ = app.emit(
+      "postgres-notification",
+      NotificationEvent {
+        connection_id: key.0.clone(),
+        channel: key.1.clone(),
+        payload,
+        process_id,
+        received_at: store::now_ms(),
+        error,
+      },
+    );
+  };
+  loop {
+    match listener.try_recv().await {
+      Ok(Some(notification)) => emit(
+        Some(notification.payload().to_string()),
+        Some(notification.process_id()),
+        None,
+      ),
+      // The listener reconnects by itself, but can't get back what was sent meanwhile
+      Ok(None) => emit(
+        None,
+        None,
+        Some("The connection dropped; notifications sent while it was down are lost".to_string()),
+      ),
+      Err(sqlx::Error::PoolClosed) => {
+        emit(None, None, Some("The connection was closed".to_string()));
+        break;
+      }
+      Err(e) => {
+        emit(None, None, Some(e.to_string()));
+        tokio::time::sleep(RETRY).await;
+      }
+    }
+  }
+  let state = app.state::<AppState>();
+  state
+    .pg_listeners
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&key);
+}
+
+/// Starts forwarding notifications on `channel`; listening again to the same channel is a
+/// no-op.
+#[tauri::command]
+pub async fn postgres_listen(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  channel: String,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  ident::validate(Dialect::Postgres, &channel)?;
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let key = (
+    connection_id.unwrap_or_else(|| "postgres".to_string()),
+    channel,
+  );
+  if state
+    .pg_listeners
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .contains_key(&key)
+  {
+    return Ok(());
+  }
+  let mut listener = PgListener::connect_with(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  listener.listen(&key.1).await.map_err(|e| e.to_string())?;
+  let task = tokio::spawn(forward(app, listener, key.clone()));
+  match state
+    .pg_listeners
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .entry(key)
+  {
+    // Another call got there while this one was connecting
+    Entry::Occupied(_) => task.abort(),
+    Entry::Vacant(entry) => {
+      entry.insert(task);
+    }
+  }
+  Ok(())
+}
+
+/// Stops listening on `channel` and hands its connection back to the pool.
+#[tauri::command]
+pub fn postgres_unlisten(
+  state: State<'_, AppState>,
+  channel: String,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  let connection = connection_id.unwrap_or_else(|| "postgres".to_string());
+  let task = state
+    .pg_listeners
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&(connection, channel.clone()))
+    .ok_or_else(|| format!("Not listening on {}", channel))?;
+  // Dropping the listener unlistens before its connection goes back to the pool
+  task.abort();
+  Ok(())
+}
+
+/// Sends a notification on `channel`, with an empty payload when `payload` is `None`.
+#[tauri::command]
+pub async fn postgres_notify(
+  state: State<'_, AppState>,
+  channel: String,
+  payload: Option<String>,
+  connection_id: Option<String>,
+) -> Result<(), String> {
+  let connection = connection_id.as_deref().unwrap_or("postgres");
+  readonly::ensure_writable(&state, connection)?;
+  ident::validate(Dialect::Postgres, &channel)?;
+  let pool = connections::postgres(&state, connection_id.as_deref())?;
+  let payload = payload.unwrap_or_default();
+  let sql = "SELECT pg_notify($1, $2)";
+  sqlx::query(sql)
+    .bind(&channel)
+    .bind(&payload)
+    .execute(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  audit::record(
+    &state,
+    connection,
+    sql,
+    serde_json::json!([channel, payload]),
+    None,
+  )
+  .await;
+  Ok(())
+}
@@ -0,0 +1,444 @@
+//! Parquet exports of a query result. The Arrow schema comes from the statement's column types
+//! (integers, floats, booleans, dates, times and timestamps keep their types; exact decimals,
+//! JSON and everything else are written as UTF-8 text), so a result without rows still gives a
+//! file with the right schema.
+//!
+//! Rows stream in from the server and are collected into record batches of [`BATCH_ROWS`],
+//! which the writer packs into Snappy-compressed row groups; memory stays bounded by one row
+//! group. Like file exports these run as background tasks (`parquet_export`) writing
+//! `<path>.part` until done. Timestamps with a zone are stored in UTC whatever the connection's
+//! display zone.
+
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use arrow_array::builder::{
+  ArrayBuilder, BooleanBuilder, Date32Builder, Float32Builder, Float64Builder, Int16Builder,
+  Int32Builder, Int64Builder, StringBuilder, Time64MicrosecondBuilder, TimestampMicrosecondBuilder,
+  UInt64Builder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use futures::{Stream, StreamExt};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use sqlx::{Column, Executor, TypeInfo};
+use tauri::{AppHandle, Manager};
+
+use crate::db::{self, JsonRow, SqlPool};
+use crate::file_export::{self, PartFile, Progress};
+use crate::ident::Dialect;
+use crate::masking::MaskingConfig;
+use crate::tasks::{self, TaskHandle, TaskSpec};
+use crate::{masking, readonly, transfer, AppState};
+
+/// Rows collected before they are handed to the writer as one record batch.
+const BATCH_ROWS: usize = 8_192;
+/// Largest row group, in rows and in encoded bytes; the writer buffers one at a time.
+const ROW_GROUP_ROWS: usize = 131_072;
+const ROW_GROUP_BYTES: usize = 128 * 1024 * 1024;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParquetExportSummary {
+  pub path: String,
+  pub rows: u64,
+  pub row_groups: usize,
+  /// Size of the file written.
+  pub bytes: u64,
+  pub elapsed_ms: u64,
+}
+
+fn parquet_error(e: parquet::errors::ParquetError) -> String {
+  format!("Writing the Parquet file failed: {}", e)
+}
+
+/// Arrow type for a result column of the engine type `type_name`.
+fn arrow_type(dialect: Dialect, type_name: &str) -> DataType {
+  let utc = || DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()));
+  match type_name.to_ascii_uppercase().as_str() {
+    "BOOL" | "BOOLEAN" => DataType::Boolean,
+    "TINYINT" | "TINYINT UNSIGNED" | "SMALLINT" | "INT2" => DataType::Int16,
+    "SMALLINT UNSIGNED" | "MEDIUMINT" | "MEDIUMINT UNSIGNED" | "INT" | "INT4" => DataType::Int32,
+    "INT UNSIGNED" | "BIGINT" | "INT8" | "INTEGER" => DataType::Int64,
+    "BIGINT UNSIGNED" => DataType::UInt64,
+    "FLOAT" | "FLOAT4" => DataType::Float32,
+    "DOUBLE" | "FLOAT8" | "REAL" => DataType::Float64,
+    "DATE" => DataType::Date32,
+    "TIME" => DataType::Time64(TimeUnit::Microsecond),
+    // A MySQL TIMESTAMP is an instant, a Postgres one a wall-clock time
+    "TIMESTAMP" if dialect == Dialect::MySql => utc(),
+    "TIMESTAMPTZ" => utc(),
+    "TIMESTAMP" | "DATETIME" => DataType::Timestamp(TimeUnit::Microsecond, None),
+    _ => DataType::Utf8,
+  }
+}
+
+/// Schema of the columns `sql` returns; masked columns become text.
+async fn schema(pool: &SqlPool, sql: &str, mask: Option<&MaskingConfig>) -> Result<Schema, String> {
+  fn columns<C: Column>(columns: &[C]) -> Vec<(String, String)> {
+    columns
+      .iter()
+      .map(|c| (c.name().to_string(), c.type_info().name().to_string()))
+      .collect()
+  }
+  let columns = match pool {
+    SqlPool::MySql(pool) => pool.describe(sql).await.map(|d| columns(d.columns())),
+    SqlPool::Postgres(pool) => pool.describe(sql).await.map(|d| columns(d.columns())),
+    SqlPool::Sqlite(pool) => pool.describe(sql).await.map(|d| columns(d.columns())),
+  }
+  .map_err(|e| e.to_string())?;
+  let fields: Vec<Field> = columns
+    .into_iter()
+    .map(|(name, type_name)| {
+      let data_type = match mask {
+        Some(mask) if mask.masks(&name) => DataType::Utf8,
+        _ => arrow_type(pool.dialect(), &type_name),
+      };
+      Field::new(name, data_type, true)
+    })
+    .collect();
+  Ok(Schema::new(fields))
+}
+
+fn parse_date(s: &str) -> Option<chrono::NaiveDate> {
+  chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+}
+
+fn parse_time(s: &str) -> Option<chrono::NaiveTime> {
+  chrono::NaiveTime::parse_from_str(s, "%H:%M:%S%.f").ok()
+}
+
+/// Microseconds since the epoch of a timestamp as the row helpers (or SQLite text) spell it;
+/// values without an offset are taken as they are.
+fn parse_timestamp(s: &str) -> Option<i64> {
+  ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+    .iter()
+    .find_map(|format| chrono::NaiveDateTime::parse_from_str(s, format).ok())
+    .map(|d| d.and_utc().timestamp_micros())
+    .or_else(|| {
+      chrono::DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|d| d.timestamp_micros())
+    })
+}
+
+/// Collects the values of one column until the batch is built.
+enum ColumnBuilder {
+  Boolean(BooleanBuilder),
+  Int16(Int16Builder),
+  Int32(Int32Builder),
+  Int64(Int64Builder),
+  UInt64(UInt64Builder),
+  Float32(Float32Builder),
+  Float64(Float64Builder),
+  Date(Date32Builder),
+  Time(Time64MicrosecondBuilder),
+  Timestamp(TimestampMicrosecondBuilder, Option<Arc<str>>),
+  Text(StringBuilder),
+}
+
+impl ColumnBuilder {
+  fn new(data_type: &DataType) -> Self {
+    match data_type {
+      DataType::Boolean => ColumnBuilder::Boolean(BooleanBuilder::new()),
+      DataType::Int16 => ColumnBuilder::Int16(Int16Builder::new()),
+      DataType::Int32 => ColumnBuilder::Int32(Int32Builder::new()),
+      DataType::Int64 => ColumnBuilder::Int64(Int64Builder::new()),
+      DataType::UInt64 => ColumnBuilder::UInt64(UInt64Builder::new()),
+      DataType::Float32 => ColumnBuilder::Float32(Float32Builder::new()),
+      DataType::Float64 => ColumnBuilder::Float64(Float64Builder::new()),
+      DataType::Date32 => ColumnBuilder::Date(Date32Builder::new()),
+      DataType::Time64(_) => ColumnBuilder::Time(Time64MicrosecondBuilder::new()),
+      DataType::Timestamp(_, zone) => {
+        ColumnBuilder::Timestamp(TimestampMicrosecondBuilder::new(), zone.clone())
+      }
+      _ => ColumnBuilder::Text(StringBuilder::new()),
+    }
+  }
+
+  /// Appends `value`, failing when it doesn't fit the column's type.
+  fn append(&mut self, value: &serde_json::Value) -> Result<(), ()> {
+    use serde_json::Value;
+    // Huge integers and exact numbers arrive as strings
+    fn number<T: std::str::FromStr>(
+      value: &Value,
+      from: impl Fn(&Value) -> Option<T>,
+    ) -> Option<T> {
+      match value {
+        Value::String(s) => s.parse().ok(),
+        other => from(other),
+      }
+    }
+    fn int<T: TryFrom<i64> + TryFrom<u64> + std::str::FromStr>(value: &Value) -> Option<T> {
+      number(value, |v| match (v.as_i64(), v.as_u64(), v.as_bool()) {
+        (Some(n), _, _) => T::try_from(n).ok(),
+        (_, Some(n), _) => T::try_from(n).ok(),
+        (_, _, Some(b)) => T::try_from(i64::from(b)).ok(),
+        _ => None,
+      })
+    }
+    if value.is_null() {
+      self.append_null();
+      return Ok(());
+    }
+    match self {
+      ColumnBuilder::Boolean(b) => {
+        let flag = match value {
+          Value::Bool(flag) => Some(*flag),
+          Value::Number(n) => n.as_i64().map(|n| n != 0),
+          _ => None,
+        };
+        b.append_value(flag.ok_or(())?);
+      }
+      ColumnBuilder::Int16(b) => b.append_value(int(value).ok_or(())?),
+      ColumnBuilder::Int32(b) => b.append_value(int(value).ok_or(())?),
+      ColumnBuilder::Int64(b) => b.append_value(int(value).ok_or(())?),
+      ColumnBuilder::UInt64(b) => b.append_value(int(value).ok_or(())?),
+      #[allow(clippy::cast_possible_truncation)]
+      ColumnBuilder::Float32(b) => {
+        b.append_value(number(value, |v| v.as_f64().map(|n| n as f32)).ok_or(())?);
+      }
+      ColumnBuilder::Float64(b) => b.append_value(number(value, Value::as_f64).ok_or(())?),
+      ColumnBuilder::Date(b) => {
+        let date = value.as_str().and_then(parse_date).ok_or(())?;
+        let days = (date - chrono::NaiveDate::default()).num_days();
+        b.append_value(i32::try_from(days).map_err(|_| ())?);
+      }
+      ColumnBuilder::Time(b) => {
+        let time = value.as_str().and_then(parse_time).ok_or(())?;
+        let micros = (time - chrono::NaiveTime::MIN)
+          .num_microseconds()
+          .ok_or(())?;
+        b.append_value(micros);
+      }
+      ColumnBuilder::Timestamp(b, _) => {
+        b.append_value(value.as_str().and_then(parse_timestamp).ok_or(())?);
+      }
+      ColumnBuilder::Text(b) => match value {
+        Value::String(s) => b.append_value(s),
+        other => b.append_value(other.to_string()),
+      },
+    }
+    Ok(())
+  }
+
+  fn append_null(&mut self) {
+    match self {
+      ColumnBuilder::Boolean(b) => b.append_null(),
+      ColumnBuilder::Int16(b) => b.append_null(),
+      ColumnBuilder::Int32(b) => b.append_null(),
+      ColumnBuilder::Int64(b) => b.append_null(),
+      ColumnBuilder::UInt64(b) => b.append_null(),
+      ColumnBuilder::Float32(b) => b.append_null(),
+      ColumnBuilder::Float64(b) => b.append_null(),
+      ColumnBuilder::Date(b) => b.append_null(),
+      ColumnBuilder::Time(b) => b.append_null(),
+      ColumnBuilder::Timestamp(b, _) => b.append_null(),
+      ColumnBuilder::Text(b) => b.append_null(),
+    }
+  }
+
+  /// The values collected since the last call, leaving the builder empty.
+  fn finish(&mut self) -> ArrayRef {
+    match self {
+      ColumnBuilder::Boolean(b) => ArrayBuilder::finish(b),
+      ColumnBuilder::Int16(b) => ArrayBuilder::finish(b),
+      ColumnBuilder::Int32(b) => ArrayBuilder::finish(b),
+      ColumnBuilder::Int64(b) => ArrayBuilder::finish(b),
+      ColumnBuilder::UInt64(b) => ArrayBuilder::finish(b),
+      ColumnBuilder::Float32(b) => ArrayBuilder::finish(b),
+      ColumnBuilder::Float64(b) => ArrayBuilder::finish(b),
+      ColumnBuilder::Date(b) => ArrayBuilder::finish(b),
+      ColumnBuilder::Time(b) => ArrayBuilder::finish(b),
+      ColumnBuilder::Timestamp(b, zone) => Arc::new(b.finish().with_timezone_opt(zone.clone())),
+      ColumnBuilder::Text(b) => ArrayBuilder::finish(b),
+    }
+  }
+}
+
+/// Rows on their way into the file, one record batch at a time.
+struct Batches {
+  schema: SchemaRef,
+  builders: Vec<ColumnBuilder>,
+  pending: usize,
+  writer: ArrowWriter<File>,
+}
+
+impl Batches {
+  fn new(schema: Schema, file: File) -> Result<Self, String> {
+    let schema = Arc::new(schema);
+    let properties = WriterProperties::builder()
+      .set_compression(Compression::SNAPPY)
+      .set_max_row_group_row_count(Some(ROW_GROUP_ROWS))
+      .set_max_row_group_bytes(Some(ROW_GROUP_BYTES))
+      .build();
+    let writer =
+      ArrowWriter::try_new(file, schema.clone(), Some(properties)).map_err(parquet_error)?;
+    Ok(Batches {
+      builders: schema
+        .fields()
+        .iter()
+        .map(|f| ColumnBuilder::new(f.data_type()))
+        .collect(),
+      schema,
+      pending: 0,
+      writer,
+    })
+  }
+
+  fn row(&mut self, row: &JsonRow, number: u64) -> Result<(), String> {
+    for (field, builder) in self.schema.fields().iter().zip(&mut self.builders) {
+      let value = row.get(field.name()).unwrap_or(&serde_json::Value::Null);
+      builder.append(value).map_err(|()| {
+        format!(
+          "Row {}: {} in column {} is not a valid {}",
+          number,
+          value,
+          field.name(),
+          field.data_type()
+        )
+      })?;
+    }
+    self.pending += 1;
+    if self.pending == BATCH_ROWS {
+      self.flush()?;
+    }
+    Ok(())
+  }
+
+  fn flush(&mut self) -> Result<(), String> {
+    if self.pending == 0 {
+      return Ok(());
+    }
+    let columns = self
+      .builders
+      .iter_mut()
+      .map(ColumnBuilder::finish)
+      .collect();
+    let batch = RecordBatch::try_new(self.schema.clone(), columns).map_err(|e| e.to_string())?;
+    self.pending = 0;
+    self.writer.write(&batch).map_err(parquet_error)
+  }
+
+  /// Writes what is left and the footer; returns the file and the number of row groups.
+  fn finish(mut self) -> Result<(File, usize), String> {
+    self.flush()?;
+    self.writer.flush().map_err(parquet_error)?;
+    let row_groups = self.writer.flushed_row_groups().len();
+    let file = self.writer.into_inner().map_err(parquet_error)?;
+    Ok((file, row_groups))
+  }
+}
+
+async fn write_stream<R: sqlx::Row>(
+  mut rows: impl Stream<Item = Result<R, sqlx::Error>> + Unpin,
+  to_json: impl Fn(&R) -> JsonRow,
+  batches: &mut Batches,
+  mask: Option<&MaskingConfig>,
+  progress: &mut Progress<'_>,
+) -> Result<u64, String> {
+  let mut written = 0u64;
+  while let Some(row) = rows.next().await {
+    let row = row.map_err(|e| e.to_string())?;
+    let mut json = to_json(&row);
+    if let Some(mask) = mask {
+      mask.apply(&mut json);
+    }
+    written += 1;
+    batches.row(&json, written)?;
+    progress.report(written, "Writing rows");
+  }
+  Ok(written)
+}
+
+/// Carries out a Parquet export task: streams the result of `sql` on `connection` into `path`.
+pub async fn write_parquet(
+  task: &TaskHandle,
+  connection: String,
+  sql: String,
+  path: String,
+) -> Result<ParquetExportSummary, String> {
+  let started = Instant::now();
+  let state = task.app.state::<AppState>();
+  readonly::check_statement(&state, &connection, &sql)?;
+  let pool = db::sql_pool(&state, &connection)?;
+  let mask = masking::active(&state, &connection);
+  let schema = schema(&pool, &sql, mask.as_ref()).await?;
+
+  let target = PathBuf::from(&path);
+  let (part, file) = PartFile::create(&target)?;
+  let mut batches = Batches::new(schema, file)?;
+  let mut progress = Progress::new(task);
+  task.progress(0, None, Some("Reading rows"));
+  let rows = match &pool {
+    SqlPool::MySql(pool) => {
+      let rows = sqlx::query(&sql).fetch(pool);
+      let to_json = |row: &_| db::mysql_row_to_json(row, None);
+      write_stream(rows, to_json, &mut batches, mask.as_ref(), &mut progress).await?
+    }
+    SqlPool::Postgres(pool) => {
+      let rows = sqlx::query(&sql).fetch(pool);
+      let to_json = |row: &_| db::pg_row_to_json(row, None);
+      write_stream(rows, to_json, &mut batches, mask.as_ref(), &mut progress).await?
+    }
+    SqlPool::Sqlite(pool) => {
+      let rows = sqlx::query(&sql).fetch(pool);
+      write_stream(
+        rows,
+        db::sqlite_row_to_json,
+        &mut batches,
+        mask.as_ref(),
+        &mut progress,
+      )
+      .await?
+    }
+  };
+
+  let (file, row_groups) = batches.finish()?;
+  file.sync_all().map_err(file_export::io_error)?;
+  let bytes = file.metadata().map_or(0, |m| m.len());
+  drop(file);
+  part.commit(&target)?;
+
+  transfer::record(
+    &state,
+    &connection,
+    transfer::Category::Export,
+    usize::try_from(rows).unwrap_or(usize::MAX),
+    usize::try_from(bytes).unwrap_or(usize::MAX),
+  );
+  Ok(ParquetExportSummary {
+    path,
+    rows,
+    row_groups,
+    bytes,
+    elapsed_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+  })
+}
+
+/// Exports the result of `sql` to the Parquet file at `path` in the background and returns the
+/// task id; the task's result is a [`ParquetExportSummary`].
+#[tauri::command]
+pub async fn export_parquet(
+  app: AppHandle,
+  connection: String,
+  sql: String,
+  path: String,
+) -> Result<String, String> {
+  let state = app.state::<AppState>();
+  readonly::check_statement(&state, &connection, &sql)?;
+  db::sql_pool(&state, &connection)?;
+  file_export::check_folder(&path)?;
+  Ok(tasks::start(
+    &app,
+    TaskSpec::ParquetExport {
+      connection,
+      sql,
+      path,
+    },
+  ))
+}
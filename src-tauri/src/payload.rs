@@ -0,0 +1,162 @@
+//! Out-of-band transfer for large command results. Payloads above `INLINE_LIMIT` are staged
+//! here as already-serialized bytes and the command returns a handle instead; the frontend
+//! then pulls the bytes once, either through `fetch_payload` (raw IPC body, no JSON
+//! re-encoding) or the `payload://` protocol, which lets the webview stream it with `fetch`.
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{Manager, Runtime, State, UriSchemeContext};
+
+use crate::AppState;
+
+/// Responses up to this size are still returned inline through the JSON IPC channel.
+pub const INLINE_LIMIT: usize = 1024 * 1024;
+
+/// Staged payloads nobody picked up are dropped after this long.
+const PAYLOAD_TTL: Duration = Duration::from_mins(2);
+
+pub const SCHEME: &str = "payload";
+
+struct StagedPayload {
+  bytes: Vec<u8>,
+  content_type: &'static str,
+  staged_at: Instant,
+}
+
+#[derive(Default)]
+pub struct Payloads {
+  entries: HashMap<String, StagedPayload>,
+}
+
+impl Payloads {
+  fn insert(&mut self, handle: String, payload: StagedPayload) {
+    self
+      .entries
+      .retain(|_, staged| staged.staged_at.elapsed() < PAYLOAD_TTL);
+    self.entries.insert(handle, payload);
+  }
+
+  /// Payloads are single-use: taking one releases its memory.
+  fn take(&mut self, handle: &str) -> Option<StagedPayload> {
+    self
+      .entries
+      .remove(handle)
+      .filter(|staged| staged.staged_at.elapsed() < PAYLOAD_TTL)
+  }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadHandle {
+  pub handle: String,
+  pub size: usize,
+  pub content_type: &'static str,
+  /// Where the webview can `fetch` the bytes from instead of calling `fetch_payload`.
+  pub url: String,
+}
+
+/// A command result that is either small enough to send inline or staged behind a handle.
+#[derive(serde::Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Transfer<T> {
+  Inline { data: T },
+  Staged(PayloadHandle),
+}
+
+fn payload_url(handle: &str) -> String {
+  // Windows and Android webviews expose custom schemes as `http://<scheme>.localhost`
+  if cfg!(any(target_os = "windows", target_os = "android")) {
+    format!("http://{}.localhost/{}", SCHEME, handle)
+  } else {
+    format!("{}://localhost/{}", SCHEME, handle)
+  }
+}
+
+pub fn stage(state: &AppState, bytes: Vec<u8>, content_type: &'static str) -> PayloadHandle {
+  let handle = crate::next_id("payload");
+  let size = bytes.len();
+  state
+    .payloads
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(
+      handle.clone(),
+      StagedPayload {
+        bytes,
+        content_type,
+        staged_at: Instant::now(),
+      },
+    );
+  PayloadHandle {
+    url: payload_url(&handle),
+    handle,
+    size,
+    content_type,
+  }
+}
+
+/// Returns `value` inline when its JSON form fits under `INLINE_LIMIT`, otherwise stages
+/// the serialized bytes so they never pass through the IPC JSON channel.
+pub fn transfer<T: serde::Serialize>(state: &AppState, value: T) -> Result<Transfer<T>, String> {
+  let bytes = serde_json::to_vec(&value).map_err(|e| e.to_string())?;
+  if bytes.len() <= INLINE_LIMIT {
+    return Ok(Transfer::Inline { data: value });
+  }
+  Ok(Transfer::Staged(stage(state, bytes, "application/json")))
+}
+
+/// Hands a staged payload to the frontend as a raw `ArrayBuffer`.
+#[tauri::command]
+pub fn fetch_payload(
+  state: State<'_, AppState>,
+  handle: String,
+) -> Result<tauri::ipc::Response, String> {
+  let staged = state
+    .payloads
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .take(&handle)
+    .ok_or_else(|| format!("Unknown or expired payload: {}", handle))?;
+  Ok(tauri::ipc::Response::new(staged.bytes))
+}
+
+/// Drops a staged payload the frontend no longer needs.
+#[tauri::command]
+pub fn release_payload(state: State<'_, AppState>, handle: String) {
+  state
+    .payloads
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .take(&handle);
+}
+
+/// Handler for the `payload://` protocol; the request path is the payload handle.
+pub fn serve<R: Runtime>(
+  ctx: UriSchemeContext<'_, R>,
+  request: Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+  let handle = request.uri().path().trim_start_matches('/');
+  let state = ctx.app_handle().state::<AppState>();
+  let staged = state
+    .payloads
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .take(handle);
+  let response = Response::builder().header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+  match staged {
+    Some(staged) => response
+      .header(header::CONTENT_TYPE, staged.content_type)
+      .body(staged.bytes),
+    None => response
+      .status(StatusCode::NOT_FOUND)
+      .body(format!("Unknown or expired payload: {}", handle).into_bytes()),
+  }
+  .unwrap_or_else(|e| {
+    let mut failed = Response::new(e.to_string().into_bytes());
+    *failed.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    failed
+  })
+}
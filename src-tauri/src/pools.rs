@@ -0,0 +1,130 @@
+//! Connection pool sizing and timeouts for the SQL engines, passed as `poolOptions` to the
+//! connect commands, and [`get_pool_stats`] to see how busy an open pool is.
+
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use sqlx::pool::{Pool, PoolOptions as SqlxPoolOptions};
+use sqlx::Database;
+use tauri::State;
+
+use crate::AppState;
+
+/// Connections a pool opens at most unless configured otherwise.
+const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+
+/// How a connection's pool is sized; unset fields keep the defaults.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolOptions {
+  /// Connections open at most (5 by default).
+  pub max_connections: Option<u32>,
+  /// Connections kept open even when idle (none by default).
+  pub min_connections: Option<u32>,
+  /// Seconds to wait for a free connection before failing; the connect timeout by default.
+  pub acquire_timeout_sec: Option<u64>,
+  /// Seconds an idle connection is kept before being closed (600 by default, 0 for never).
+  pub idle_timeout_sec: Option<u64>,
+  /// Seconds after which a connection is replaced (1800 by default, 0 for never).
+  pub max_lifetime_sec: Option<u64>,
+  /// Ping a connection before handing it out (on by default).
+  pub test_before_acquire: Option<bool>,
+}
+
+/// `0` turns a limit off.
+fn limit(seconds: u64) -> Option<Duration> {
+  (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+impl PoolOptions {
+  /// A pool builder configured from `self`, waiting `acquire_timeout` for a free connection
+  /// unless `acquire_timeout_sec` says otherwise.
+  pub fn builder<DB: Database>(
+    &self,
+    acquire_timeout: Option<Duration>,
+  ) -> Result<SqlxPoolOptions<DB>, String> {
+    let max = self.max_connections.unwrap_or(DEFAULT_MAX_CONNECTIONS);
+    let min = self.min_connections.unwrap_or(0);
+    if max == 0 {
+      return Err("A pool needs at least one connection".to_string());
+    }
+    if min > max {
+      return Err(format!(
+        "Minimum connections ({}) exceed the maximum ({})",
+        min, max
+      ));
+    }
+    let mut builder = SqlxPoolOptions::<DB>::new()
+      .max_connections(max)
+      .min_connections(min);
+    if let Some(timeout) = self
+      .acquire_timeout_sec
+      .map(Duration::from_secs)
+      .or(acquire_timeout)
+    {
+      if timeout.is_zero() {
+        return Err("The acquire timeout must be at least one second".to_string());
+      }
+      builder = builder.acquire_timeout(timeout);
+    }
+    if let Some(seconds) = self.idle_timeout_sec {
+      builder = builder.idle_timeout(limit(seconds));
+    }
+    if let Some(seconds) = self.max_lifetime_sec {
+      builder = builder.max_lifetime(limit(seconds));
+    }
+    if let Some(test) = self.test_before_acquire {
+      builder = builder.test_before_acquire(test);
+    }
+    Ok(builder)
+  }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolStats {
+  pub engine: &'static str,
+  /// Connections open, in use or idle.
+  pub size: u32,
+  pub active: u32,
+  pub idle: u32,
+  pub min_connections: u32,
+  pub max_connections: u32,
+}
+
+fn stats<DB: Database>(engine: &'static str, pool: &Pool<DB>) -> PoolStats {
+  let size = pool.size();
+  let idle = u32::try_from(pool.num_idle()).unwrap_or(u32::MAX).min(size);
+  PoolStats {
+    engine,
+    size,
+    active: size - idle,
+    idle,
+    min_connections: pool.options().get_min_connections(),
+    max_connections: pool.options().get_max_connections(),
+  }
+}
+
+/// How many connections the pool of a MySQL, PostgreSQL or SQLite connection holds and how
+/// many of them are in use.
+#[tauri::command]
+pub fn get_pool_stats(
+  state: State<'_, AppState>,
+  connection_id: String,
+) -> Result<PoolStats, String> {
+  let connections = state
+    .connections
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  if let Some(pool) = connections.mysql.get(&connection_id) {
+    Ok(stats("mysql", pool))
+  } else if let Some(pool) = connections.postgres.get(&connection_id) {
+    Ok(stats("postgres", pool))
+  } else if let Some(pool) = connections.sqlite.get(&connection_id) {
+    Ok(stats("sqlite", pool))
+  } else if let Some(engine) = connections.engine_of(&connection_id) {
+    Err(format!("{} connections have no connection pool", engine))
+  } else {
+    Err("Not connected".to_string())
+  }
+}
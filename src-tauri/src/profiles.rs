@@ -0,0 +1,380 @@
+//! Saved connection profiles, persisted in the app store so they survive restarts.
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::iam::IamAuth;
+use crate::pools::PoolOptions;
+use crate::secrets::{self, SecretKind};
+use crate::{readonly, store, usage, workspaces, AppState, SshConfig};
+
+const ENGINES: &[&str] = &["mysql", "postgres", "sqlite", "redis", "mongodb"];
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionProfile {
+  /// Left empty when saving a new profile; an id is assigned.
+  #[serde(default)]
+  pub id: String,
+  pub name: String,
+  /// `mysql`, `postgres`, `sqlite`, `redis` or `mongodb`.
+  pub engine: String,
+  #[serde(default)]
+  pub host: Option<String>,
+  #[serde(default)]
+  pub port: Option<u16>,
+  #[serde(default)]
+  pub username: Option<String>,
+  /// Only ever set on the way in or out: saved passwords live in the OS credential store.
+  #[serde(default)]
+  pub password: Option<String>,
+  #[serde(default)]
+  pub database: Option<String>,
+  /// Database file for SQLite profiles.
+  #[serde(default)]
+  pub path: Option<String>,
+  #[serde(default)]
+  pub socket_path: Option<String>,
+  #[serde(default)]
+  pub timeout_sec: Option<u64>,
+  #[serde(default)]
+  pub ssh_config: Option<SshConfig>,
+  #[serde(default)]
+  pub iam_auth: Option<IamAuth>,
+  #[serde(default)]
+  pub pool_options: Option<PoolOptions>,
+  /// Reconnect this profile (tunnel included) when the app starts.
+  #[serde(default)]
+  pub reconnect_on_startup: bool,
+  /// Refuse writes over this connection (see [`readonly`]).
+  #[serde(default)]
+  pub read_only: bool,
+  #[serde(default)]
+  pub updated_at: i64,
+}
+
+impl ConnectionProfile {
+  fn host(&self) -> Result<String, String> {
+    self
+      .host
+      .clone()
+      .ok_or_else(|| format!("Profile '{}' has no host", self.name))
+  }
+
+  fn port(&self, default: u16) -> u16 {
+    self.port.unwrap_or(default)
+  }
+
+  fn username(&self) -> Result<String, String> {
+    self
+      .username
+      .clone()
+      .ok_or_else(|| format!("Profile '{}' has no username", self.name))
+  }
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RestoreEvent {
+  profile_id: String,
+  name: String,
+  /// `connecting`, `connected` or `failed`.
+  status: &'static str,
+  error: Option<String>,
+}
+
+fn decode(config: &str) -> Result<ConnectionProfile, String> {
+  serde_json::from_str(config).map_err(|e| format!("Corrupt connection profile: {}", e))
+}
+
+pub async fn load_profile(state: &AppState, profile_id: &str) -> Result<ConnectionProfile, String> {
+  let pool = store::pool(state)?;
+  let row: Option<(String,)> =
+    sqlx::query_as("SELECT config FROM connection_profiles WHERE id = ?")
+      .bind(profile_id)
+      .fetch_optional(&pool)
+      .await
+      .map_err(|e| e.to_string())?;
+  let (config,) = row.ok_or_else(|| format!("Unknown connection profile: {}", profile_id))?;
+  decode(&config)
+}
+
+/// Stores passwords in the OS credential store and strips them from the profile, so they
+/// never reach the app store in plaintext.
+async fn move_secrets_to_keychain(profile: &mut ConnectionProfile) -> Result<(), String> {
+  let mut pending = Vec::new();
+  if let Some(password) = profile.password.take().filter(|p| !p.is_empty()) {
+    pending.push((SecretKind::Password, password));
+  }
+  if let Some(password) = profile
+    .ssh_config
+    .as_mut()
+    .and_then(|ssh| ssh.password.take())
+    .filter(|p| !p.is_empty())
+  {
+    pending.push((SecretKind::SshPassword, password));
+  }
+  if let Some(ssh) = profile.ssh_config.as_mut() {
+    for (n, hop) in ssh.jump_hosts.iter_mut().enumerate() {
+      if let Some(password) = hop.password.take().filter(|p| !p.is_empty()) {
+        pending.push((SecretKind::JumpHostPassword(n), password));
+      }
+    }
+  }
+  let profile_id = profile.id.clone();
+  secrets::blocking(move || {
+    pending
+      .iter()
+      .try_for_each(|(kind, secret)| secrets::store(&profile_id, *kind, secret))
+  })
+  .await
+}
+
+/// Creates or replaces a profile and returns it as stored.
+#[tauri::command]
+pub async fn save_connection_profile(
+  state: State<'_, AppState>,
+  profile: ConnectionProfile,
+) -> Result<ConnectionProfile, String> {
+  save_profile(&state, profile).await
+}
+
+pub async fn save_profile(
+  state: &AppState,
+  profile: ConnectionProfile,
+) -> Result<ConnectionProfile, String> {
+  if profile.name.trim().is_empty() {
+    return Err("Profile name cannot be empty".to_string());
+  }
+  if !ENGINES.contains(&profile.engine.as_str()) {
+    return Err(format!("Unsupported engine: {}", profile.engine));
+  }
+  let mut profile = profile;
+  if profile.id.trim().is_empty() {
+    profile.id = crate::next_id(&format!("profile-{}", store::now_ms()));
+  }
+  profile.updated_at = store::now_ms();
+  move_secrets_to_keychain(&mut profile).await?;
+
+  let pool = store::pool(state)?;
+  let config = serde_json::to_string(&profile).map_err(|e| e.to_string())?;
+  sqlx::query(
+    "INSERT INTO connection_profiles (id, name, engine, config, updated_at, workspace) \
+     VALUES (?, ?, ?, ?, ?, ?) \
+     ON CONFLICT(id) DO UPDATE SET name = excluded.name, engine = excluded.engine, \
+     config = excluded.config, updated_at = excluded.updated_at",
+  )
+  .bind(&profile.id)
+  .bind(&profile.name)
+  .bind(&profile.engine)
+  .bind(config)
+  .bind(profile.updated_at)
+  .bind(workspaces::current(state))
+  .execute(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(profile)
+}
+
+#[tauri::command]
+pub async fn get_connection_profile(
+  state: State<'_, AppState>,
+  profile_id: String,
+) -> Result<ConnectionProfile, String> {
+  load_profile(&state, &profile_id).await
+}
+
+/// Profiles of the active workspace.
+pub async fn all_profiles(
+  state: &AppState,
+  engine: Option<String>,
+) -> Result<Vec<ConnectionProfile>, String> {
+  let pool = store::pool(state)?;
+  let rows: Vec<(String,)> = sqlx::query_as(
+    "SELECT config FROM connection_profiles WHERE workspace = ?1 AND (?2 IS NULL OR engine = ?2) \
+     ORDER BY name COLLATE NOCASE",
+  )
+  .bind(workspaces::current(state))
+  .bind(engine)
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  rows.iter().map(|(config,)| decode(config)).collect()
+}
+
+/// Looks a profile up by id, falling back to a case-insensitive name match.
+pub async fn find_profile(state: &AppState, name_or_id: &str) -> Result<ConnectionProfile, String> {
+  if let Ok(profile) = load_profile(state, name_or_id).await {
+    return Ok(profile);
+  }
+  all_profiles(state, None)
+    .await?
+    .into_iter()
+    .find(|p| p.name.eq_ignore_ascii_case(name_or_id))
+    .ok_or_else(|| format!("Unknown connection profile: {}", name_or_id))
+}
+
+#[tauri::command]
+pub async fn list_connection_profiles(
+  state: State<'_, AppState>,
+  engine: Option<String>,
+) -> Result<Vec<ConnectionProfile>, String> {
+  all_profiles(&state, engine).await
+}
+
+#[tauri::command]
+pub async fn delete_connection_profile(
+  state: State<'_, AppState>,
+  profile_id: String,
+) -> Result<(), String> {
+  let pool = store::pool(&state)?;
+  let result = sqlx::query("DELETE FROM connection_profiles WHERE id = ?")
+    .bind(&profile_id)
+    .execute(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  if result.rows_affected() == 0 {
+    return Err(format!("Unknown connection profile: {}", profile_id));
+  }
+  for table in ["profile_usage", "profile_table_usage"] {
+    sqlx::query(&format!("DELETE FROM {} WHERE profile_id = ?", table))
+      .bind(&profile_id)
+      .execute(&pool)
+      .await
+      .map_err(|e| e.to_string())?;
+  }
+  secrets::blocking(move || secrets::delete_all(&profile_id)).await
+}
+
+/// Opens a profile's connection, registered under the profile id as its connection id, and
+/// records the attempt in the profile's usage stats.
+pub async fn connect(state: &AppState, profile: ConnectionProfile) -> Result<String, String> {
+  let profile_id = profile.id.clone();
+  let feature = format!("connect.{}", profile.engine);
+  let started = std::time::Instant::now();
+  let outcome = open(state, profile).await;
+  usage::observe(state, &feature, started.elapsed(), outcome.is_ok());
+  usage::record_connect(state, &profile_id, &outcome).await;
+  outcome
+}
+
+async fn open(state: &AppState, profile: ConnectionProfile) -> Result<String, String> {
+  let mut profile = profile;
+  profile.password =
+    secrets::resolve(Some(&profile.id), SecretKind::Password, profile.password).await?;
+  profile.ssh_config = secrets::resolve_ssh(Some(&profile.id), profile.ssh_config).await?;
+  readonly::set(state, &profile.id, profile.read_only);
+  let id = Some(profile.id.clone());
+  match profile.engine.as_str() {
+    "mysql" => {
+      crate::open_mysql(
+        state,
+        profile.host()?,
+        profile.port(3306),
+        profile.username()?,
+        profile.password,
+        profile.database,
+        profile.timeout_sec,
+        profile.ssh_config,
+        profile.iam_auth,
+        false,
+        profile.socket_path,
+        id,
+        profile.pool_options,
+      )
+      .await
+    }
+    "postgres" => {
+      crate::open_postgres(
+        state,
+        profile.host()?,
+        profile.port(5432),
+        profile.username()?,
+        profile.password,
+        profile.database,
+        profile.timeout_sec,
+        profile.ssh_config,
+        profile.iam_auth,
+        false,
+        profile.socket_path,
+        id,
+        profile.pool_options,
+      )
+      .await
+    }
+    "sqlite" => {
+      let path = profile
+        .path
+        .ok_or_else(|| format!("Profile '{}' has no database file", profile.name))?;
+      crate::open_sqlite(state, path, id, profile.pool_options).await
+    }
+    "redis" => {
+      crate::open_redis(
+        state,
+        profile.host()?,
+        profile.port(6379),
+        profile.password,
+        0,
+        profile.timeout_sec,
+        profile.ssh_config,
+        profile.socket_path,
+        id,
+      )
+      .await
+    }
+    "mongodb" => {
+      crate::open_mongodb(
+        state,
+        profile.host()?,
+        profile.port(27017),
+        profile.username,
+        profile.password,
+        profile.timeout_sec,
+        profile.ssh_config,
+        id,
+      )
+      .await
+    }
+    other => Err(format!("Unsupported engine: {}", other)),
+  }
+}
+
+/// Connects a saved profile; the profile id becomes the connection id.
+#[tauri::command]
+pub async fn connect_profile(
+  state: State<'_, AppState>,
+  profile_id: String,
+) -> Result<String, String> {
+  let profile = load_profile(&state, &profile_id).await?;
+  connect(&state, profile).await
+}
+
+/// Reconnects every profile flagged `reconnect_on_startup`, reporting each one through
+/// `profile-restore` events.
+pub async fn restore_on_startup(app: AppHandle) {
+  let state = app.state::<AppState>();
+  let profiles = match all_profiles(&state, None).await {
+    Ok(profiles) => profiles,
+    Err(e) => {
+      tracing::error!("Failed to load connection profiles: {}", e);
+      return;
+    }
+  };
+
+  for profile in profiles.into_iter().filter(|p| p.reconnect_on_startup) {
+    let event = |status, error| RestoreEvent {
+      profile_id: profile.id.clone(),
+      name: profile.name.clone(),
+      status,
+      error,
+    };
+    let _ = app.emit("profile-restore", event("connecting", None));
+    let outcome = connect(&state, profile.clone()).await;
+    let _ = app.emit(
+      "profile-restore",
+      match outcome {
+        Ok(_) => event("connected", None),
+        Err(e) => event("failed", Some(e)),
+      },
+    );
+  }
+}
@@ -0,0 +1,309 @@
+//! Read-only connections: every write path of the backend checks here first, so browsing a
+//! production database can't change it by accident, whatever the UI sends.
+
+use std::sync::PoisonError;
+
+use futures::future::{BoxFuture, FutureExt};
+use tauri::State;
+
+use crate::db::SqlPool;
+use crate::{sql_kind, AppState};
+
+/// Redis commands a read-only connection may still run through the raw executor.
+const REDIS_READ_COMMANDS: &[&str] = &[
+  "BITCOUNT",
+  "DBSIZE",
+  "ECHO",
+  "EXISTS",
+  "GET",
+  "GETBIT",
+  "GETRANGE",
+  "HEXISTS",
+  "HGET",
+  "HGETALL",
+  "HKEYS",
+  "HLEN",
+  "HMGET",
+  "HSCAN",
+  "HSTRLEN",
+  "HVALS",
+  "INFO",
+  "KEYS",
+  "LINDEX",
+  "LLEN",
+  "LRANGE",
+  "MEMORY",
+  "MGET",
+  "OBJECT",
+  "PFCOUNT",
+  "PING",
+  "PTTL",
+  "SCAN",
+  "SCARD",
+  "SISMEMBER",
+  "SMEMBERS",
+  "SRANDMEMBER",
+  "SSCAN",
+  "STRLEN",
+  "TIME",
+  "TTL",
+  "TYPE",
+  "XINFO",
+  "XLEN",
+  "XRANGE",
+  "XREVRANGE",
+  "ZCARD",
+  "ZCOUNT",
+  "ZRANGE",
+  "ZRANGEBYSCORE",
+  "ZRANK",
+  "ZREVRANGE",
+  "ZREVRANGEBYSCORE",
+  "ZREVRANK",
+  "ZSCAN",
+  "ZSCORE",
+];
+
+/// MongoDB database commands a read-only connection may still run.
+const MONGO_READ_COMMANDS: &[&str] = &[
+  "aggregate",
+  "buildInfo",
+  "collStats",
+  "count",
+  "dbStats",
+  "distinct",
+  "explain",
+  "find",
+  "hello",
+  "listCollections",
+  "listIndexes",
+  "ping",
+  "serverStatus",
+];
+
+pub fn is_read_only(state: &AppState, connection: &str) -> bool {
+  state
+    .read_only
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .contains(connection)
+}
+
+/// Fails when `connection` is read-only.
+pub fn ensure_writable(state: &AppState, connection: &str) -> Result<(), String> {
+  if is_read_only(state, connection) {
+    return Err(format!("Connection '{}' is read-only", connection));
+  }
+  Ok(())
+}
+
+/// Fails when `connection` is read-only and `sql` could write.
+pub fn check_statement(state: &AppState, connection: &str, sql: &str) -> Result<(), String> {
+  if is_read_only(state, connection) && !sql_kind::is_read(sql) {
+    return Err(format!(
+      "Connection '{}' is read-only; only SELECT-style statements can run",
+      connection
+    ));
+  }
+  Ok(())
+}
+
+/// Statements a raw SQL statement on a read-only connection runs between, so the server
+/// itself refuses what [`check_statement`] can't see, such as `SELECT nextval('s')`: a
+/// read-only transaction on MySQL and Postgres, `PRAGMA query_only` on SQLite, as in
+/// [`SqlPool::fetch_read_only`].
+pub struct Guard {
+  begin: &'static str,
+  end: &'static str,
+}
+
+impl Guard {
+  fn for_pool(pool: &SqlPool) -> Guard {
+    match pool {
+      SqlPool::MySql(_) => Guard {
+        begin: "START TRANSACTION READ ONLY",
+        end: "ROLLBACK",
+      },
+      SqlPool::Postgres(_) => Guard {
+        begin: "BEGIN READ ONLY",
+        end: "ROLLBACK",
+      },
+      SqlPool::Sqlite(_) => Guard {
+        begin: "PRAGMA query_only = ON",
+        end: "PRAGMA query_only = OFF",
+      },
+    }
+  }
+
+  /// Puts `conn` under the guard. Boxed, like [`Guard::end`], since a future generic over
+  /// the executor trips up `Send` inference in spawned tasks such as the console's cursors.
+  pub fn begin<'e, E: sqlx::Executor<'e> + 'e>(
+    &self,
+    conn: E,
+  ) -> BoxFuture<'e, Result<(), String>> {
+    let sql = self.begin;
+    async move {
+      conn.execute(sql).await.map_err(|e| e.to_string())?;
+      Ok(())
+    }
+    .boxed()
+  }
+
+  /// Lifts the guard again. A connection where that fails can't go back to the pool, so
+  /// `false` tells the caller to close it.
+  pub fn end<'e, E: sqlx::Executor<'e> + 'e>(&self, conn: E) -> BoxFuture<'e, bool> {
+    let sql = self.end;
+    async move {
+      match conn.execute(sql).await {
+        Ok(_) => true,
+        Err(e) => {
+          tracing::warn!("Cannot lift the read-only guard: {}", e);
+          false
+        }
+      }
+    }
+    .boxed()
+  }
+}
+
+/// Guard for raw statements on `connection` (of `pool`'s engine), when it is read-only.
+pub fn guard(state: &AppState, connection: &str, pool: &SqlPool) -> Option<Guard> {
+  is_read_only(state, connection).then(|| Guard::for_pool(pool))
+}
+
+/// Fails when `connection` is read-only and the raw Redis `command` isn't a known read.
+pub fn check_redis_command(
+  state: &AppState,
+  connection: &str,
+  command: &str,
+) -> Result<(), String> {
+  if is_read_only(state, connection)
+    && !REDIS_READ_COMMANDS.contains(&command.to_uppercase().as_str())
+  {
+    return Err(format!(
+      "Connection '{}' is read-only; {} is not a read command",
+      connection, command
+    ));
+  }
+  Ok(())
+}
+
+/// Fails when `connection` is read-only and the MongoDB `command` document could write.
+pub fn check_mongo_command(
+  state: &AppState,
+  connection: &str,
+  command: &mongodb::bson::Document,
+) -> Result<(), String> {
+  if is_read_only(state, connection) && !is_mongo_read(command) {
+    let name = command.keys().next().map_or("", String::as_str);
+    return Err(format!(
+      "Connection '{}' is read-only; {} could write",
+      connection, name
+    ));
+  }
+  Ok(())
+}
+
+/// Whether the MongoDB `command` document only reads: a known read command, and no `$out`
+/// or `$merge` stage when it is an aggregation.
+pub fn is_mongo_read(command: &mongodb::bson::Document) -> bool {
+  let name = command.keys().next().map_or("", String::as_str);
+  let writes_out = command.get_array("pipeline").is_ok_and(|stages| {
+    stages.iter().any(|stage| {
+      stage
+        .as_document()
+        .is_some_and(|stage| stage.contains_key("$out") || stage.contains_key("$merge"))
+    })
+  });
+  MONGO_READ_COMMANDS.contains(&name) && !writes_out
+}
+
+/// Marks a connection read-only (or writable again). Profiles apply their own flag on
+/// connect.
+#[tauri::command]
+pub fn set_connection_read_only(state: State<'_, AppState>, connection: String, read_only: bool) {
+  set(&state, &connection, read_only);
+}
+
+pub fn set(state: &AppState, connection: &str, read_only: bool) {
+  let mut flags = state
+    .read_only
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  if read_only {
+    flags.insert(connection.to_string());
+  } else {
+    flags.remove(connection);
+  }
+}
+
+#[tauri::command]
+pub fn get_connection_read_only(state: State<'_, AppState>, connection: String) -> bool {
+  is_read_only(&state, &connection)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn guard_blocks_writes_until_lifted() {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+      .max_connections(1)
+      .connect("sqlite::memory:")
+      .await
+      .unwrap();
+    sqlx::raw_sql("CREATE TABLE t (a INTEGER)")
+      .execute(&pool)
+      .await
+      .unwrap();
+    let guard = Guard::for_pool(&SqlPool::Sqlite(pool.clone()));
+    let mut conn = pool.acquire().await.unwrap();
+    guard.begin(&mut *conn).await.unwrap();
+    // what the statement check lets through still can't write
+    let sql = "WITH w AS (SELECT 1) INSERT INTO t SELECT * FROM w";
+    assert!(sqlx::query(sql).execute(&mut *conn).await.is_err());
+    let (count,): (i64,) = sqlx::query_as("SELECT count(*) FROM t")
+      .fetch_one(&mut *conn)
+      .await
+      .unwrap();
+    assert_eq!(count, 0);
+    assert!(guard.end(&mut *conn).await);
+    drop(conn);
+    sqlx::query("INSERT INTO t VALUES (1)")
+      .execute(&pool)
+      .await
+      .unwrap();
+  }
+
+  /// Against the server in `SPECTRA_TEST_POSTGRES_URL` when one is given.
+  #[tokio::test]
+  async fn postgres_guard_refuses_writing_functions() {
+    let Ok(url) = std::env::var("SPECTRA_TEST_POSTGRES_URL") else {
+      return;
+    };
+    let pool = sqlx::postgres::PgPoolOptions::new()
+      .max_connections(1)
+      .connect(&url)
+      .await
+      .unwrap();
+    sqlx::raw_sql("DROP SEQUENCE IF EXISTS readonly_test; CREATE SEQUENCE readonly_test")
+      .execute(&pool)
+      .await
+      .unwrap();
+    let guard = Guard::for_pool(&SqlPool::Postgres(pool.clone()));
+    assert!(sql_kind::is_read("SELECT nextval('readonly_test')"));
+    let mut conn = pool.acquire().await.unwrap();
+    guard.begin(&mut *conn).await.unwrap();
+    let fetched = sqlx::query("SELECT nextval('readonly_test')")
+      .fetch_all(&mut *conn)
+      .await;
+    assert!(fetched.is_err());
+    assert!(guard.end(&mut *conn).await);
+    drop(conn);
+    sqlx::raw_sql("DROP SEQUENCE readonly_test")
+      .execute(&pool)
+      .await
+      .unwrap();
+  }
+}
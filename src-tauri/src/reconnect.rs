@@ -0,0 +1,180 @@
+//! Recovery of MySQL, Postgres and Redis connections that fail a [`health`] check: the
+//! connection is marked as reconnecting, retried with exponential backoff (re-opening its
+//! SSH tunnel if that was lost) and every change is reported as a `connection-status` event.
+//! While a connection is down, commands fail right away with a readable error instead of a
+//! driver timeout.
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::health::{self, Probe};
+use crate::{tunnels, AppState};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Status {
+  Connected,
+  Reconnecting,
+}
+
+#[derive(Default)]
+pub struct Supervisors {
+  tasks: HashMap<String, JoinHandle<()>>,
+  /// Connections currently down, with the number of the retry in progress.
+  down: HashMap<String, u32>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatusEvent {
+  pub connection_id: String,
+  pub engine: &'static str,
+  pub status: Status,
+  /// Retries made so far; 0 once connected.
+  pub attempt: u32,
+  /// Delay before the next retry while reconnecting.
+  pub retry_in_ms: Option<u64>,
+  pub error: Option<String>,
+}
+
+fn emit(app: &AppHandle, event: ConnectionStatusEvent) {
+  let _ = app.emit("connection-status", event);
+}
+
+async fn recover_loop(app: AppHandle, id: String, engine: &'static str, error: Option<String>) {
+  let state = app.state::<AppState>();
+  let mut backoff = INITIAL_BACKOFF;
+  let mut attempt = 0;
+  let mut error = error;
+  loop {
+    attempt += 1;
+    state
+      .supervisors
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .down
+      .insert(id.clone(), attempt);
+    emit(
+      &app,
+      ConnectionStatusEvent {
+        connection_id: id.clone(),
+        engine,
+        status: Status::Reconnecting,
+        attempt,
+        retry_in_ms: Some(u64::try_from(backoff.as_millis()).unwrap_or(u64::MAX)),
+        error: error.take(),
+      },
+    );
+    tokio::time::sleep(backoff).await;
+    backoff = (backoff * 2).min(MAX_BACKOFF);
+
+    if let Err(e) = tunnels::reopen_if_lost(&state, &id).await {
+      error = Some(e);
+      continue;
+    }
+    match health::probe(&state, &id).await {
+      Probe::Up(_) => break,
+      Probe::Down(e) => error = Some(e),
+      Probe::Gone => {
+        clear(&state, &id);
+        return;
+      }
+    }
+  }
+
+  {
+    let mut supervisors = state
+      .supervisors
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    supervisors.down.remove(&id);
+    supervisors.tasks.remove(&id);
+  }
+  health::check(&state, &id).await;
+  emit(
+    &app,
+    ConnectionStatusEvent {
+      connection_id: id,
+      engine,
+      status: Status::Connected,
+      attempt: 0,
+      retry_in_ms: None,
+      error: None,
+    },
+  );
+}
+
+/// Starts reconnecting `id` after a failed check, unless that is already under way.
+pub fn recover(app: &AppHandle, id: &str, engine: &'static str, error: Option<String>) {
+  let state = app.state::<AppState>();
+  let mut supervisors = state
+    .supervisors
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  if supervisors.tasks.contains_key(id) {
+    return;
+  }
+  supervisors.down.insert(id.to_string(), 0);
+  let task = tauri::async_runtime::spawn(recover_loop(app.clone(), id.to_string(), engine, error));
+  supervisors.tasks.insert(id.to_string(), task);
+}
+
+/// Forgets any recovery of `id`, for a connection that was just reopened or closed.
+pub fn clear(state: &AppState, id: &str) {
+  let mut supervisors = state
+    .supervisors
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  supervisors.down.remove(id);
+  if let Some(task) = supervisors.tasks.remove(id) {
+    task.abort();
+  }
+}
+
+pub fn is_recovering(state: &AppState, id: &str) -> bool {
+  state
+    .supervisors
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .tasks
+    .contains_key(id)
+}
+
+/// Fails while `id` is being reconnected, so commands don't wait out a dead pool.
+pub fn ensure_up(state: &AppState, id: &str) -> Result<(), String> {
+  match state
+    .supervisors
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .down
+    .get(id)
+  {
+    Some(attempt) => Err(format!(
+      "Connection '{}' was lost; reconnecting (attempt {})",
+      id,
+      attempt.max(&1)
+    )),
+    None => Ok(()),
+  }
+}
+
+pub fn status(state: &AppState, id: &str) -> Status {
+  if state
+    .supervisors
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .down
+    .contains_key(id)
+  {
+    Status::Reconnecting
+  } else {
+    Status::Connected
+  }
+}
@@ -0,0 +1,170 @@
+//! Click-to-jump navigation between related rows: the row a foreign key points at, and the
+//! rows of other tables pointing at the current one.
+
+use tauri::State;
+
+use crate::db::{self, JsonRow, SqlPool};
+use crate::schema::{self, ForeignKey};
+use crate::{masking, AppState};
+
+/// Referencing rows returned per foreign key.
+const MAX_REFERENCING_ROWS: usize = 50;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferencedRow {
+  pub foreign_key: ForeignKey,
+  /// `None` when the reference is NULL or dangling.
+  pub row: Option<JsonRow>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferencingRows {
+  pub foreign_key: ForeignKey,
+  pub rows: Vec<JsonRow>,
+  /// More rows reference the current one than were returned.
+  pub truncated: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct References {
+  /// Target of `fk_column`, when one was given.
+  pub referenced: Option<ReferencedRow>,
+  /// Rows of other tables (or of the same table) whose foreign keys point at the row.
+  pub referencing: Vec<ReferencingRows>,
+}
+
+/// Text form of a column value for binding, or `None` for NULL.
+fn bind_text(row: &JsonRow, column: &str) -> Option<String> {
+  match row.get(column)? {
+    serde_json::Value::Null => None,
+    serde_json::Value::String(s) => Some(s.clone()),
+    other => Some(other.to_string()),
+  }
+}
+
+/// Rows of `table` whose `columns` equal `values`, at most `limit` of them.
+async fn rows_matching(
+  pool: &SqlPool,
+  table: &str,
+  columns: &[String],
+  values: &[String],
+  limit: usize,
+) -> Result<Vec<JsonRow>, String> {
+  let conditions: Vec<String> = columns
+    .iter()
+    .enumerate()
+    .map(|(i, column)| pool.text_eq(column, i + 1))
+    .collect();
+  let sql = format!(
+    "SELECT * FROM {} WHERE {} LIMIT {}",
+    pool.table_ref(table),
+    conditions.join(" AND "),
+    limit
+  );
+  pool.fetch_rows(&sql, values).await
+}
+
+/// Values of `columns` in `row`, or `None` when any of them is NULL (such a reference
+/// points nowhere).
+fn key_values(row: &JsonRow, columns: &[String]) -> Option<Vec<String>> {
+  columns.iter().map(|c| bind_text(row, c)).collect()
+}
+
+/// Resolves the foreign key on `fk_column` of the row of `table` whose primary key is
+/// `row_pk` to the row it references, and lists the rows referencing that row.
+#[tauri::command]
+pub async fn follow_reference(
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+  row_pk: String,
+  fk_column: Option<String>,
+) -> Result<References, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let table = pool.resolve_table(&table).await?;
+  let pk = match schema::primary_key(&pool, db::PG_DEFAULT_SCHEMA, &table)
+    .await?
+    .as_slice()
+  {
+    [pk] => pk.clone(),
+    [] => return Err(format!("Table {} has no primary key", table)),
+    _ => {
+      return Err(format!(
+        "Table {} has a composite primary key; pass rows by a single-column key",
+        table
+      ))
+    }
+  };
+  let row = rows_matching(&pool, &table, &[pk], std::slice::from_ref(&row_pk), 1)
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| format!("No row of {} has primary key {}", table, row_pk))?;
+  let keys = schema::foreign_keys_involving(&pool, db::PG_DEFAULT_SCHEMA, &table).await?;
+  let mask = masking::active(&state, &connection);
+  let masked = |mut rows: Vec<JsonRow>| {
+    if let Some(mask) = &mask {
+      for row in &mut rows {
+        mask.apply(row);
+      }
+    }
+    rows
+  };
+
+  let referenced = match &fk_column {
+    Some(column) => {
+      let key = keys
+        .iter()
+        .find(|k| k.table == table && k.columns.contains(column))
+        .ok_or_else(|| format!("Column {} of {} is not a foreign key", column, table))?;
+      let target = match key_values(&row, &key.columns) {
+        Some(values) => {
+          let rows = rows_matching(
+            &pool,
+            &key.referenced_table,
+            &key.referenced_columns,
+            &values,
+            1,
+          )
+          .await?;
+          masked(rows).into_iter().next()
+        }
+        None => None,
+      };
+      Some(ReferencedRow {
+        foreign_key: key.clone(),
+        row: target,
+      })
+    }
+    None => None,
+  };
+
+  let mut referencing = Vec::new();
+  for key in keys.iter().filter(|k| k.referenced_table == table) {
+    let Some(values) = key_values(&row, &key.referenced_columns) else {
+      continue;
+    };
+    let mut rows = rows_matching(
+      &pool,
+      &key.table,
+      &key.columns,
+      &values,
+      MAX_REFERENCING_ROWS + 1,
+    )
+    .await?;
+    let truncated = rows.len() > MAX_REFERENCING_ROWS;
+    rows.truncate(MAX_REFERENCING_ROWS);
+    referencing.push(ReferencingRows {
+      foreign_key: key.clone(),
+      rows: masked(rows),
+      truncated,
+    });
+  }
+  Ok(References {
+    referenced,
+    referencing,
+  })
+}
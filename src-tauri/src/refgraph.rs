@@ -0,0 +1,346 @@
+//! Reference graph of Redis keys: which keys mention the ids of which other keys, for seeing
+//! how cached entries depend on each other.
+//!
+//! Every value is read as JSON first (hashes as objects, lists/sets/sorted sets as arrays,
+//! strings parsed when they hold JSON), so the same paths work whatever the key type.
+//! Candidate ids are the leaves selected by `json_paths` (all leaves when none are given);
+//! `id_regex`, when given, then pulls ids out of each candidate (its first capture group, or
+//! the whole match). An id points at the key named by `key_template` with `{id}` replaced,
+//! or else at a key named exactly like the id, or else at keys whose last `:`-separated
+//! segment is the id.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use regex::Regex;
+use tauri::State;
+
+use crate::{connections, AppState};
+
+const DEFAULT_MAX_KEYS: usize = 2_000;
+const MAX_KEYS_LIMIT: usize = 50_000;
+/// Elements read from each list, set or sorted set.
+const MAX_ELEMENTS: isize = 1_000;
+const SCAN_COUNT: usize = 500;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+  pub key: String,
+  /// Redis type, or `missing` for referenced keys that don't exist.
+  pub kind: String,
+}
+
+#[derive(serde::Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEdge {
+  pub from: String,
+  pub to: String,
+  /// Where in the source value the id was found, e.g. `items.*.productId`.
+  pub via: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceGraph {
+  pub nodes: Vec<GraphNode>,
+  pub edges: Vec<GraphEdge>,
+  pub scanned: usize,
+  /// The scan stopped at `max_keys` before covering every matching key.
+  pub truncated: bool,
+  /// Ids that resolved to no key.
+  pub unresolved: usize,
+}
+
+/// Path segments of `a.b.*.c`, also accepting a leading `$.` and `[n]`/`[*]` indexes.
+fn parse_path(path: &str) -> Vec<String> {
+  let path = path.trim();
+  let path = path
+    .strip_prefix("$.")
+    .or_else(|| path.strip_prefix('$'))
+    .unwrap_or(path);
+  path
+    .replace('[', ".")
+    .replace(']', "")
+    .split('.')
+    .filter(|s| !s.is_empty())
+    .map(str::to_string)
+    .collect()
+}
+
+fn select<'a>(value: &'a serde_json::Value, path: &[String], out: &mut Vec<&'a serde_json::Value>) {
+  let Some((head, rest)) = path.split_first() else {
+    out.push(value);
+    return;
+  };
+  match value {
+    serde_json::Value::Object(map) if head == "*" => {
+      map.values().for_each(|v| select(v, rest, out));
+    }
+    serde_json::Value::Object(map) => {
+      if let Some(v) = map.get(head) {
+        select(v, rest, out);
+      }
+    }
+    serde_json::Value::Array(items) if head == "*" => {
+      for v in items {
+        select(v, rest, out);
+      }
+    }
+    serde_json::Value::Array(items) => {
+      if let Some(v) = head.parse::<usize>().ok().and_then(|i| items.get(i)) {
+        select(v, rest, out);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Scalar leaves under `value` as text, with the dotted path leading to each.
+fn leaves(value: &serde_json::Value, path: &str, out: &mut Vec<(String, String)>) {
+  let child = |segment: &str| {
+    if path.is_empty() {
+      segment.to_string()
+    } else {
+      format!("{}.{}", path, segment)
+    }
+  };
+  match value {
+    serde_json::Value::Object(map) => map.iter().for_each(|(k, v)| leaves(v, &child(k), out)),
+    serde_json::Value::Array(items) => items.iter().for_each(|v| leaves(v, &child("*"), out)),
+    serde_json::Value::String(s) => out.push((path.to_string(), s.clone())),
+    serde_json::Value::Number(n) => out.push((path.to_string(), n.to_string())),
+    _ => {}
+  }
+}
+
+/// A string value parsed as JSON when it is a JSON object or array, as a plain string
+/// otherwise.
+fn string_json(text: String) -> serde_json::Value {
+  let trimmed = text.trim_start();
+  if trimmed.starts_with('{') || trimmed.starts_with('[') {
+    if let Ok(parsed) = serde_json::from_str(&text) {
+      return parsed;
+    }
+  }
+  serde_json::Value::String(text)
+}
+
+fn text(value: &redis::Value) -> Option<String> {
+  match value {
+    redis::Value::BulkString(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+    redis::Value::SimpleString(s) => Some(s.clone()),
+    redis::Value::Int(n) => Some(n.to_string()),
+    redis::Value::Double(n) => Some(n.to_string()),
+    _ => None,
+  }
+}
+
+/// The value of a key of Redis type `kind` as JSON.
+fn value_json(kind: &str, value: redis::Value) -> serde_json::Value {
+  let items = match value {
+    redis::Value::Array(items) | redis::Value::Set(items) => items,
+    redis::Value::Map(pairs) => pairs.into_iter().flat_map(|(k, v)| [k, v]).collect(),
+    scalar => return text(&scalar).map(string_json).unwrap_or_default(),
+  };
+  let texts = items.iter().filter_map(text);
+  if kind == "hash" {
+    let texts: Vec<String> = texts.collect();
+    serde_json::Value::Object(
+      texts
+        .chunks(2)
+        .filter(|pair| pair.len() == 2)
+        .map(|pair| (pair[0].clone(), string_json(pair[1].clone())))
+        .collect(),
+    )
+  } else {
+    serde_json::Value::Array(texts.map(string_json).collect())
+  }
+}
+
+fn read_command(kind: &str, key: &str) -> Option<redis::Cmd> {
+  let mut cmd = match kind {
+    "string" => redis::cmd("GET"),
+    "hash" => redis::cmd("HGETALL"),
+    "list" => redis::cmd("LRANGE"),
+    "set" => redis::cmd("SMEMBERS"),
+    "zset" => redis::cmd("ZRANGE"),
+    _ => return None,
+  };
+  cmd.arg(key);
+  if matches!(kind, "list" | "zset") {
+    cmd.arg(0).arg(MAX_ELEMENTS - 1);
+  }
+  Some(cmd)
+}
+
+/// Scans keys matching `pattern` (at most `max_keys`) and returns the graph of references
+/// between them; see the module docs for how ids are found and resolved.
+#[tauri::command]
+pub async fn redis_build_reference_graph(
+  state: State<'_, AppState>,
+  pattern: String,
+  id_regex: Option<String>,
+  json_paths: Option<Vec<String>>,
+  key_template: Option<String>,
+  max_keys: Option<usize>,
+  connection_id: Option<String>,
+) -> Result<ReferenceGraph, String> {
+  let id_regex = match id_regex.filter(|r| !r.is_empty()) {
+    Some(r) => Some(Regex::new(&r).map_err(|e| format!("Invalid id regex: {}", e))?),
+    None => None,
+  };
+  let paths: Vec<(String, Vec<String>)> = json_paths
+    .unwrap_or_default()
+    .into_iter()
+    .filter(|p| !p.trim().is_empty())
+    .map(|p| {
+      let segments = parse_path(&p);
+      (segments.join("."), segments)
+    })
+    .collect();
+  let max_keys = max_keys
+    .unwrap_or(DEFAULT_MAX_KEYS)
+    .clamp(1, MAX_KEYS_LIMIT);
+
+  let client = connections::redis(&state, connection_id.as_deref())?;
+  let mut con = client
+    .get_multiplexed_async_connection()
+    .await
+    .map_err(|e| e.to_string())?;
+
+  // key -> (type, value)
+  let mut values: BTreeMap<String, (String, serde_json::Value)> = BTreeMap::new();
+  let mut cursor: u64 = 0;
+  let mut truncated = false;
+  loop {
+    let (next, mut keys): (u64, Vec<String>) = redis::cmd("SCAN")
+      .arg(cursor)
+      .arg("MATCH")
+      .arg(&pattern)
+      .arg("COUNT")
+      .arg(SCAN_COUNT)
+      .query_async(&mut con)
+      .await
+      .map_err(|e| e.to_string())?;
+    keys.retain(|k| !values.contains_key(k));
+    if values.len() + keys.len() > max_keys {
+      keys.truncate(max_keys - values.len());
+      truncated = true;
+    }
+    if !keys.is_empty() {
+      let mut pipe = redis::pipe();
+      for key in &keys {
+        pipe.cmd("TYPE").arg(key);
+      }
+      let kinds: Vec<String> = pipe
+        .query_async(&mut con)
+        .await
+        .map_err(|e| e.to_string())?;
+      let readable: Vec<(String, String, redis::Cmd)> = keys
+        .into_iter()
+        .zip(kinds)
+        .filter_map(|(key, kind)| read_command(&kind, &key).map(|cmd| (key, kind, cmd)))
+        .collect();
+      let mut pipe = redis::pipe();
+      for (_, _, cmd) in &readable {
+        pipe.add_command(cmd.clone());
+      }
+      let read: Vec<redis::Value> = pipe
+        .query_async(&mut con)
+        .await
+        .map_err(|e| e.to_string())?;
+      for ((key, kind, _), value) in readable.into_iter().zip(read) {
+        let json = value_json(&kind, value);
+        values.insert(key, (kind, json));
+      }
+    }
+    cursor = next;
+    if cursor == 0 || truncated {
+      break;
+    }
+  }
+
+  // Keys by their last `:` segment, for resolving bare ids
+  let mut by_suffix: HashMap<&str, Vec<&str>> = HashMap::new();
+  for key in values.keys() {
+    let suffix = key.rsplit(':').next().unwrap_or(key);
+    by_suffix.entry(suffix).or_default().push(key);
+  }
+
+  let mut edges = BTreeSet::new();
+  let mut missing = BTreeSet::new();
+  let mut unresolved = 0;
+  for (from, (_, value)) in &values {
+    let mut candidates = Vec::new();
+    if paths.is_empty() {
+      leaves(value, "", &mut candidates);
+    } else {
+      for (name, segments) in &paths {
+        let mut selected = Vec::new();
+        select(value, segments, &mut selected);
+        for v in selected {
+          let mut found = Vec::new();
+          leaves(v, "", &mut found);
+          candidates.extend(found.into_iter().map(|(_, text)| (name.clone(), text)));
+        }
+      }
+    }
+    for (via, candidate) in candidates {
+      let ids: Vec<String> = match &id_regex {
+        Some(re) => re
+          .captures_iter(&candidate)
+          .filter_map(|c| c.get(1).or_else(|| c.get(0)))
+          .map(|m| m.as_str().to_string())
+          .collect(),
+        None => vec![candidate],
+      };
+      for id in ids {
+        let targets: Vec<String> = match &key_template {
+          Some(template) => {
+            let key = template.replace("{id}", &id);
+            if !values.contains_key(&key) {
+              missing.insert(key.clone());
+            }
+            vec![key]
+          }
+          None if values.contains_key(&id) => vec![id.clone()],
+          None => by_suffix
+            .get(id.as_str())
+            .map(|keys| keys.iter().map(ToString::to_string).collect())
+            .unwrap_or_default(),
+        };
+        if targets.is_empty() {
+          unresolved += 1;
+        }
+        for to in targets.into_iter().filter(|to| to != from) {
+          edges.insert(GraphEdge {
+            from: from.clone(),
+            to,
+            via: if via.is_empty() {
+              "value".to_string()
+            } else {
+              via.clone()
+            },
+          });
+        }
+      }
+    }
+  }
+
+  let scanned = values.len();
+  let mut nodes: Vec<GraphNode> = values
+    .into_iter()
+    .map(|(key, (kind, _))| GraphNode { key, kind })
+    .collect();
+  nodes.extend(missing.into_iter().map(|key| GraphNode {
+    key,
+    kind: "missing".to_string(),
+  }));
+  Ok(ReferenceGraph {
+    nodes,
+    edges: edges.into_iter().collect(),
+    scanned,
+    truncated,
+    unresolved,
+  })
+}
@@ -0,0 +1,587 @@
+//! Query results kept in the backend so large result sets can be pivoted, searched and
+//! summarized without shipping every row to the webview.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, PoisonError};
+
+use tauri::State;
+
+use crate::db::{self, JsonRow, ResultColumn};
+use crate::payload::{self, Transfer};
+use crate::{masking, readonly, timezone, transfer, variables, AppState};
+
+const MAX_CACHED_RESULTS: usize = 16;
+const MAX_PIVOT_COLUMNS: usize = 1000;
+const MAX_SEARCH_MATCHES: usize = 10_000;
+
+pub struct CachedResult {
+  pub connection: String,
+  pub sql: String,
+  pub columns: Vec<ResultColumn>,
+  pub rows: Vec<JsonRow>,
+}
+
+impl CachedResult {
+  fn has_column(&self, name: &str) -> bool {
+    self.columns.iter().any(|c| c.name == name)
+  }
+}
+
+/// Bounded cache of result sets; the oldest entry is evicted once the limit is reached.
+#[derive(Default)]
+pub struct ResultCache {
+  entries: HashMap<String, Arc<CachedResult>>,
+  order: VecDeque<String>,
+}
+
+impl ResultCache {
+  pub fn insert(&mut self, result: CachedResult) -> String {
+    let id = crate::next_id("result");
+    while self.order.len() >= MAX_CACHED_RESULTS {
+      if let Some(oldest) = self.order.pop_front() {
+        self.entries.remove(&oldest);
+      }
+    }
+    self.entries.insert(id.clone(), Arc::new(result));
+    self.order.push_back(id.clone());
+    id
+  }
+
+  pub fn remove(&mut self, id: &str) -> bool {
+    self.order.retain(|entry| entry != id);
+    self.entries.remove(id).is_some()
+  }
+}
+
+/// Clones the handle out so long scans don't hold the cache lock.
+pub fn cached(state: &AppState, result_id: &str) -> Result<Arc<CachedResult>, String> {
+  state
+    .results
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .entries
+    .get(result_id)
+    .cloned()
+    .ok_or_else(|| format!("Unknown or expired result: {}", result_id))
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultSummary {
+  pub result_id: String,
+  pub columns: Vec<ResultColumn>,
+  pub row_count: usize,
+}
+
+/// Runs a SELECT and keeps the rows in the backend, returning a handle to page through them.
+#[tauri::command]
+pub async fn cache_query(
+  state: State<'_, AppState>,
+  connection: String,
+  sql: String,
+  workspace: Option<String>,
+) -> Result<ResultSummary, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let (resolved, binds) =
+    variables::resolve(&state, workspace.as_deref(), &sql, pool.placeholder_style())?;
+  readonly::check_statement(&state, &connection, &resolved)?;
+  let tz = timezone::display_zone(&state, &connection);
+  let (columns, mut rows) = pool
+    .fetch_with_columns(&resolved, &binds, tz.as_ref())
+    .await?;
+  if let Some(mask) = masking::active(&state, &connection) {
+    for row in &mut rows {
+      mask.apply(row);
+    }
+  }
+
+  let row_count = rows.len();
+  transfer::record(
+    &state,
+    &connection,
+    transfer::Category::Query,
+    row_count,
+    transfer::rows_bytes(&rows),
+  );
+  let result_id = state
+    .results
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(CachedResult {
+      connection,
+      sql,
+      columns: columns.clone(),
+      rows,
+    });
+  Ok(ResultSummary {
+    result_id,
+    columns,
+    row_count,
+  })
+}
+
+#[tauri::command]
+pub fn get_result_page(
+  state: State<'_, AppState>,
+  result_id: String,
+  offset: usize,
+  limit: usize,
+) -> Result<Vec<JsonRow>, String> {
+  let result = cached(&state, &result_id)?;
+  Ok(
+    result
+      .rows
+      .iter()
+      .skip(offset)
+      .take(limit)
+      .cloned()
+      .collect(),
+  )
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnarPage {
+  pub columns: Vec<ResultColumn>,
+  /// One array per column, each `row_count` long, in `columns` order.
+  pub values: Vec<Vec<serde_json::Value>>,
+  pub offset: usize,
+  pub row_count: usize,
+  pub total_rows: usize,
+}
+
+/// Column-major slice of a cached result: column names are sent once instead of being
+/// repeated as keys in every row object, and each column arrives as one homogeneous array.
+pub fn columnar_page(result: &CachedResult, offset: usize, limit: usize) -> ColumnarPage {
+  let rows: Vec<&JsonRow> = result.rows.iter().skip(offset).take(limit).collect();
+  let values = result
+    .columns
+    .iter()
+    .map(|column| {
+      rows
+        .iter()
+        .map(|row| {
+          row
+            .get(&column.name)
+            .cloned()
+            .unwrap_or(serde_json::Value::Null)
+        })
+        .collect()
+    })
+    .collect();
+  ColumnarPage {
+    columns: result.columns.clone(),
+    values,
+    offset,
+    row_count: rows.len(),
+    total_rows: result.rows.len(),
+  }
+}
+
+#[tauri::command]
+pub fn get_result_columnar(
+  state: State<'_, AppState>,
+  result_id: String,
+  offset: usize,
+  limit: usize,
+) -> Result<ColumnarPage, String> {
+  let result = cached(&state, &result_id)?;
+  Ok(columnar_page(&result, offset, limit))
+}
+
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+pub enum ResultPage {
+  Rows(Vec<JsonRow>),
+  Columnar(ColumnarPage),
+}
+
+/// `get_result_page` (or `get_result_columnar` when `columnar` is set) for pages that may be
+/// large: anything over the inline limit comes back as a payload handle to pull separately.
+#[tauri::command]
+pub fn get_result_page_transfer(
+  state: State<'_, AppState>,
+  result_id: String,
+  offset: usize,
+  limit: usize,
+  columnar: Option<bool>,
+) -> Result<Transfer<ResultPage>, String> {
+  let result = cached(&state, &result_id)?;
+  let page = if columnar.unwrap_or(false) {
+    ResultPage::Columnar(columnar_page(&result, offset, limit))
+  } else {
+    ResultPage::Rows(
+      result
+        .rows
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .cloned()
+        .collect(),
+    )
+  };
+  payload::transfer(&state, page)
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultInfo {
+  pub result_id: String,
+  pub connection: String,
+  pub sql: String,
+  pub row_count: usize,
+}
+
+#[tauri::command]
+pub fn list_results(state: State<'_, AppState>) -> Vec<ResultInfo> {
+  let cache = state.results.lock().unwrap_or_else(PoisonError::into_inner);
+  cache
+    .order
+    .iter()
+    .filter_map(|id| {
+      cache.entries.get(id).map(|result| ResultInfo {
+        result_id: id.clone(),
+        connection: result.connection.clone(),
+        sql: result.sql.clone(),
+        row_count: result.rows.len(),
+      })
+    })
+    .collect()
+}
+
+#[tauri::command]
+pub fn release_result(state: State<'_, AppState>, result_id: String) -> Result<(), String> {
+  if state
+    .results
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&result_id)
+  {
+    Ok(())
+  } else {
+    Err(format!("Unknown or expired result: {}", result_id))
+  }
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Aggregate {
+  Count,
+  CountDistinct,
+  Sum,
+  Avg,
+  Min,
+  Max,
+}
+
+/// Numeric view of a cell; decimals often arrive as strings, so those are parsed too.
+pub fn as_number(value: &serde_json::Value) -> Option<f64> {
+  match value {
+    serde_json::Value::Number(n) => n.as_f64(),
+    serde_json::Value::String(s) => s.trim().parse().ok(),
+    serde_json::Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+    _ => None,
+  }
+}
+
+/// Text used to group and de-duplicate cells.
+pub fn as_key(value: &serde_json::Value) -> String {
+  match value {
+    serde_json::Value::String(s) => s.clone(),
+    other => other.to_string(),
+  }
+}
+
+/// Orders numbers numerically and everything else as text, with nulls first.
+pub fn compare_values(a: &serde_json::Value, b: &serde_json::Value) -> Ordering {
+  match (a.is_null(), b.is_null()) {
+    (true, true) => return Ordering::Equal,
+    (true, false) => return Ordering::Less,
+    (false, true) => return Ordering::Greater,
+    _ => {}
+  }
+  match (as_number(a), as_number(b)) {
+    (Some(x), Some(y)) => x.total_cmp(&y),
+    _ => as_key(a).cmp(&as_key(b)),
+  }
+}
+
+/// Running state for one aggregate over a stream of cells. Nulls are skipped.
+#[derive(Default)]
+pub struct Accumulator {
+  count: u64,
+  numeric: u64,
+  sum: f64,
+  min: Option<f64>,
+  max: Option<f64>,
+  distinct: HashSet<String>,
+}
+
+impl Accumulator {
+  pub fn push(&mut self, value: &serde_json::Value) {
+    if value.is_null() {
+      return;
+    }
+    self.count += 1;
+    self.distinct.insert(as_key(value));
+    if let Some(n) = as_number(value) {
+      self.numeric += 1;
+      self.sum += n;
+      self.min = Some(self.min.map_or(n, |m| m.min(n)));
+      self.max = Some(self.max.map_or(n, |m| m.max(n)));
+    }
+  }
+
+  #[allow(clippy::cast_precision_loss)]
+  pub fn finish(&self, agg: Aggregate) -> serde_json::Value {
+    match agg {
+      Aggregate::Count => self.count.into(),
+      Aggregate::CountDistinct => self.distinct.len().into(),
+      Aggregate::Sum if self.numeric > 0 => self.sum.into(),
+      Aggregate::Avg if self.numeric > 0 => (self.sum / self.numeric as f64).into(),
+      Aggregate::Min => self.min.map_or(serde_json::Value::Null, Into::into),
+      Aggregate::Max => self.max.map_or(serde_json::Value::Null, Into::into),
+      _ => serde_json::Value::Null,
+    }
+  }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectionStats {
+  /// Non-null cells.
+  pub count: u64,
+  pub null_count: usize,
+  pub distinct: usize,
+  /// Numeric aggregates consider only cells that parse as numbers; `null` when there are none.
+  pub sum: serde_json::Value,
+  pub avg: serde_json::Value,
+  pub min: serde_json::Value,
+  pub max: serde_json::Value,
+}
+
+/// Spreadsheet-style statistics for the selected cells of one column of a cached result.
+#[tauri::command]
+pub fn aggregate_selection(
+  state: State<'_, AppState>,
+  result_id: String,
+  column: String,
+  row_indexes: Vec<usize>,
+) -> Result<SelectionStats, String> {
+  let result = cached(&state, &result_id)?;
+  if !result.has_column(&column) {
+    return Err(format!("Unknown column: {}", column));
+  }
+
+  let mut acc = Accumulator::default();
+  let mut null_count = 0;
+  for index in row_indexes {
+    let row = result
+      .rows
+      .get(index)
+      .ok_or_else(|| format!("Row index {} out of range", index))?;
+    match row.get(&column) {
+      Some(value) if !value.is_null() => acc.push(value),
+      _ => null_count += 1,
+    }
+  }
+
+  Ok(SelectionStats {
+    count: acc.count,
+    null_count,
+    distinct: acc.distinct.len(),
+    sum: acc.finish(Aggregate::Sum),
+    avg: acc.finish(Aggregate::Avg),
+    min: acc.finish(Aggregate::Min),
+    max: acc.finish(Aggregate::Max),
+  })
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatch {
+  pub row_index: usize,
+  pub column: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+  pub matches: Vec<SearchMatch>,
+  /// Set when the match list was cut off at the limit.
+  pub truncated: bool,
+}
+
+/// Finds cells of a cached result containing `term`, in row then column order.
+#[tauri::command]
+pub fn search_result(
+  state: State<'_, AppState>,
+  result_id: String,
+  term: String,
+  case_sensitive: Option<bool>,
+  limit: Option<usize>,
+) -> Result<SearchResults, String> {
+  let result = cached(&state, &result_id)?;
+  let case_sensitive = case_sensitive.unwrap_or(false);
+  let limit = limit.unwrap_or(MAX_SEARCH_MATCHES).min(MAX_SEARCH_MATCHES);
+  let needle = if case_sensitive {
+    term
+  } else {
+    term.to_lowercase()
+  };
+  if needle.is_empty() {
+    return Ok(SearchResults {
+      matches: Vec::new(),
+      truncated: false,
+    });
+  }
+
+  let mut matches = Vec::new();
+  for (row_index, row) in result.rows.iter().enumerate() {
+    for column in &result.columns {
+      let Some(value) = row.get(&column.name).filter(|v| !v.is_null()) else {
+        continue;
+      };
+      let text = as_key(value);
+      let found = if case_sensitive {
+        text.contains(&needle)
+      } else {
+        text.to_lowercase().contains(&needle)
+      };
+      if !found {
+        continue;
+      }
+      if matches.len() == limit {
+        return Ok(SearchResults {
+          matches,
+          truncated: true,
+        });
+      }
+      matches.push(SearchMatch {
+        row_index,
+        column: column.name.clone(),
+      });
+    }
+  }
+  Ok(SearchResults {
+    matches,
+    truncated: false,
+  })
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PivotRow {
+  pub keys: Vec<serde_json::Value>,
+  /// One cell per entry of `PivotTable::column_keys`; `null` where no rows fell in the cell.
+  pub cells: Vec<serde_json::Value>,
+  pub total: serde_json::Value,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PivotTable {
+  pub row_fields: Vec<String>,
+  pub col_fields: Vec<String>,
+  pub column_keys: Vec<Vec<serde_json::Value>>,
+  pub rows: Vec<PivotRow>,
+}
+
+/// Distinct key tuples, sorted column by column with `compare_values`.
+fn sorted_keys(
+  keys: HashMap<Vec<String>, Vec<serde_json::Value>>,
+) -> Vec<(Vec<String>, Vec<serde_json::Value>)> {
+  let mut keys: Vec<_> = keys.into_iter().collect();
+  keys.sort_by(|(_, a), (_, b)| {
+    a.iter()
+      .zip(b)
+      .map(|(x, y)| compare_values(x, y))
+      .find(|o| o.is_ne())
+      .unwrap_or(Ordering::Equal)
+  });
+  keys
+}
+
+/// Cross-tabulates a cached result: one output row per distinct `rows` key, one cell per
+/// distinct `cols` key, each holding `agg` over `value`.
+#[tauri::command]
+pub fn pivot_result(
+  state: State<'_, AppState>,
+  result_id: String,
+  rows: Vec<String>,
+  cols: Vec<String>,
+  value: String,
+  agg: Aggregate,
+) -> Result<PivotTable, String> {
+  let result = cached(&state, &result_id)?;
+  if let Some(missing) = rows
+    .iter()
+    .chain(&cols)
+    .chain(std::iter::once(&value))
+    .find(|field| !result.has_column(field))
+  {
+    return Err(format!("Unknown column: {}", missing));
+  }
+
+  let key_of = |row: &JsonRow, fields: &[String]| -> (Vec<String>, Vec<serde_json::Value>) {
+    let values: Vec<serde_json::Value> = fields
+      .iter()
+      .map(|f| row.get(f).cloned().unwrap_or(serde_json::Value::Null))
+      .collect();
+    (values.iter().map(as_key).collect(), values)
+  };
+
+  let mut row_keys = HashMap::new();
+  let mut col_keys = HashMap::new();
+  let mut cells: HashMap<(Vec<String>, Vec<String>), Accumulator> = HashMap::new();
+  let mut totals: HashMap<Vec<String>, Accumulator> = HashMap::new();
+
+  for row in &result.rows {
+    let (row_key, row_values) = key_of(row, &rows);
+    let (col_key, col_values) = key_of(row, &cols);
+    if !col_keys.contains_key(&col_key) {
+      if col_keys.len() >= MAX_PIVOT_COLUMNS {
+        return Err(format!(
+          "Pivot would produce more than {} columns",
+          MAX_PIVOT_COLUMNS
+        ));
+      }
+      col_keys.insert(col_key.clone(), col_values);
+    }
+    row_keys.entry(row_key.clone()).or_insert(row_values);
+
+    let cell = row.get(&value).unwrap_or(&serde_json::Value::Null);
+    totals.entry(row_key.clone()).or_default().push(cell);
+    cells.entry((row_key, col_key)).or_default().push(cell);
+  }
+
+  let col_keys = sorted_keys(col_keys);
+  let pivot_rows = sorted_keys(row_keys)
+    .into_iter()
+    .map(|(row_key, keys)| {
+      let row_cells = col_keys
+        .iter()
+        .map(|(col_key, _)| {
+          cells
+            .get(&(row_key.clone(), col_key.clone()))
+            .map_or(serde_json::Value::Null, |acc| acc.finish(agg))
+        })
+        .collect();
+      PivotRow {
+        keys,
+        cells: row_cells,
+        total: totals
+          .get(&row_key)
+          .map_or(serde_json::Value::Null, |acc| acc.finish(agg)),
+      }
+    })
+    .collect();
+
+  Ok(PivotTable {
+    row_fields: rows,
+    col_fields: cols,
+    column_keys: col_keys.into_iter().map(|(_, values)| values).collect(),
+    rows: pivot_rows,
+  })
+}
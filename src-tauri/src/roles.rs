@@ -0,0 +1,122 @@
+//! Running a Postgres statement as another role, to check row-level security policies and
+//! grants the way an application role sees them without opening a separate connection.
+
+use sqlx::Row;
+use tauri::State;
+
+use crate::db::{self, JsonRow, ResultColumn};
+use crate::ident::{self, Dialect};
+use crate::variables::{self, Placeholder};
+use crate::{audit, connections, masking, readonly, sql_kind, timezone, AppState};
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleRun {
+  /// `current_user` while the statement ran.
+  pub role: String,
+  pub columns: Vec<ResultColumn>,
+  pub rows: Vec<JsonRow>,
+  pub rows_affected: u64,
+  /// Whether the transaction was committed rather than rolled back.
+  pub committed: bool,
+}
+
+/// Statements whose result rows are worth returning.
+fn returns_rows(sql: &str) -> bool {
+  let upper = sql.trim_start().to_uppercase();
+  ["SELECT", "WITH", "SHOW", "EXPLAIN", "TABLE", "VALUES"]
+    .iter()
+    .any(|keyword| upper.starts_with(keyword))
+    || upper.contains("RETURNING")
+}
+
+/// Statements that would end the transaction or change the role, defeating the simulation.
+fn escapes_role(sql: &str) -> bool {
+  let upper = sql.trim_start().to_uppercase();
+  let words: Vec<&str> = upper.split_whitespace().take(3).collect();
+  matches!(
+    words.first().copied(),
+    Some("COMMIT" | "ROLLBACK" | "END" | "ABORT" | "BEGIN" | "START" | "RESET")
+  ) || matches!(words.as_slice(), ["SET", "ROLE" | "SESSION", ..])
+}
+
+/// Runs `sql` on a Postgres connection inside a transaction after `SET LOCAL ROLE role`, so
+/// policies and privileges apply as they would for that role. The transaction is rolled back
+/// afterwards unless `commit` is true; either way the role never outlives it.
+#[tauri::command]
+pub async fn run_as_role(
+  state: State<'_, AppState>,
+  connection: String,
+  role: String,
+  sql: String,
+  workspace: Option<String>,
+  commit: Option<bool>,
+) -> Result<RoleRun, String> {
+  let pool = connections::postgres(&state, Some(&connection))?;
+  if role.trim().is_empty() {
+    return Err("Role is required".to_string());
+  }
+  if commit.unwrap_or(false) {
+    readonly::check_statement(&state, &connection, &sql)?;
+  }
+  if escapes_role(&sql) {
+    return Err("Transaction control and role changes cannot run as another role".to_string());
+  }
+  let (sql, binds) = variables::resolve(&state, workspace.as_deref(), &sql, Placeholder::Dollar)?;
+
+  let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+  sqlx::query(&format!(
+    "SET LOCAL ROLE {}",
+    ident::render(Dialect::Postgres, &role)
+  ))
+  .execute(&mut *tx)
+  .await
+  .map_err(|e| format!("Cannot switch to role {}: {}", role, e))?;
+  let current: String = sqlx::query("SELECT current_user::text")
+    .fetch_one(&mut *tx)
+    .await
+    .and_then(|row| row.try_get(0))
+    .map_err(|e| e.to_string())?;
+
+  let params = serde_json::json!(&binds);
+  let mut query = sqlx::query(&sql);
+  for value in binds {
+    query = query.bind(value);
+  }
+  let tz = timezone::display_zone(&state, &connection);
+  let (columns, mut rows, rows_affected) = if returns_rows(&sql) {
+    let rows = query.fetch_all(&mut *tx).await.map_err(|e| e.to_string())?;
+    let columns = db::result_columns(&rows);
+    let json: Vec<JsonRow> = rows
+      .iter()
+      .map(|row| db::pg_row_to_json(row, tz.as_ref()))
+      .collect();
+    let count = json.len() as u64;
+    (columns, json, count)
+  } else {
+    let result = query.execute(&mut *tx).await.map_err(|e| e.to_string())?;
+    (Vec::new(), Vec::new(), result.rows_affected())
+  };
+
+  let committed = commit.unwrap_or(false);
+  if committed {
+    tx.commit().await.map_err(|e| e.to_string())?;
+    if sql_kind::is_write(&sql) {
+      audit::record(&state, &connection, &sql, params, Some(rows_affected)).await;
+    }
+  } else {
+    tx.rollback().await.map_err(|e| e.to_string())?;
+  }
+  if let Some(mask) = masking::active(&state, &connection) {
+    for row in &mut rows {
+      mask.apply(row);
+    }
+  }
+  Ok(RoleRun {
+    role: current,
+    columns,
+    rows,
+    rows_affected,
+    committed,
+  })
+}
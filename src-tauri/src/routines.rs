@@ -0,0 +1,564 @@
+//! Stored functions and procedures: their source and parameters, and calling one with
+//! arguments. Arguments are positional, one per `IN`/`INOUT` (and Postgres `VARIADIC`)
+//! parameter, as text the server converts to the parameter's type; `None` passes NULL.
+//!
+//! Postgres routines can be overloaded, so they are looked up by the specific name
+//! `postgres_get_functions` / `postgres_get_procedures` return, or by plain name while it is
+//! unambiguous.
+
+use futures::TryStreamExt;
+use sqlx::{MySqlPool, PgPool, Row};
+use tauri::State;
+
+use crate::db::{self, JsonRow, ResultColumn, SqlPool};
+use crate::ident::{self, Dialect};
+use crate::{audit, connections, readonly, timezone, AppState};
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutineParameter {
+  /// 1-based position in the signature.
+  pub position: i64,
+  /// `None` for unnamed Postgres parameters.
+  pub name: Option<String>,
+  /// `IN`, `OUT`, `INOUT`, `VARIADIC`, or `TABLE` for a Postgres `RETURNS TABLE` column.
+  pub mode: String,
+  pub data_type: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutineDefinition {
+  pub name: String,
+  pub specific_name: String,
+  /// `FUNCTION` or `PROCEDURE`.
+  pub kind: String,
+  /// Return type of a function.
+  pub returns: Option<String>,
+  pub parameters: Vec<RoutineParameter>,
+  /// The `CREATE` statement; `None` when the server hides it from this user.
+  pub definition: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultSet {
+  pub columns: Vec<ResultColumn>,
+  pub rows: Vec<JsonRow>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutineCallResult {
+  /// The call as it ran.
+  pub sql: String,
+  pub result_sets: Vec<ResultSet>,
+  /// Values of the `OUT` and `INOUT` parameters of a procedure, by name.
+  pub out_params: JsonRow,
+}
+
+/// A routine as found in the catalog.
+struct Routine {
+  name: String,
+  specific_name: String,
+  kind: String,
+  returns: Option<String>,
+  /// `pg_proc.oid`; `None` on MySQL.
+  oid: Option<i64>,
+  /// Postgres schema the routine is in; `None` on MySQL, which looks in the current database.
+  schema: Option<String>,
+  /// A Postgres function declared `STABLE` or `IMMUTABLE`, which can't write.
+  read_only: bool,
+}
+
+/// Normalizes `FUNCTION` / `PROCEDURE` as a caller may spell it.
+fn routine_kind(kind: &str) -> Result<&'static str, String> {
+  match kind.to_uppercase().as_str() {
+    "FUNCTION" => Ok("FUNCTION"),
+    "PROCEDURE" => Ok("PROCEDURE"),
+    _ => Err(format!("Unknown routine kind: {}", kind)),
+  }
+}
+
+/// Finds `name` among the routines of the current MySQL database; `kind` tells a function
+/// and a procedure of the same name apart.
+async fn mysql_routine(
+  pool: &MySqlPool,
+  name: &str,
+  kind: Option<&str>,
+) -> Result<Routine, String> {
+  let kind = kind.map(routine_kind).transpose()?;
+  let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(
+    "SELECT CAST(ROUTINE_NAME AS CHAR), CAST(ROUTINE_TYPE AS CHAR), \
+     CAST(DTD_IDENTIFIER AS CHAR) FROM information_schema.ROUTINES \
+     WHERE ROUTINE_SCHEMA = DATABASE()",
+  )
+  .fetch_all(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  let rows: Vec<_> = rows
+    .into_iter()
+    .filter(|(_, routine_kind, _)| kind.is_none_or(|kind| routine_kind == kind))
+    .collect();
+  let names: Vec<String> = rows.iter().map(|(name, _, _)| name.clone()).collect();
+  let found = ident::resolve(Dialect::MySql, name, &names)?;
+  let matches: Vec<_> = rows.iter().filter(|(name, _, _)| name == found).collect();
+  let [(name, kind, returns)] = matches.as_slice() else {
+    return Err(format!(
+      "{} is both a function and a procedure; say which",
+      found
+    ));
+  };
+  Ok(Routine {
+    name: name.clone(),
+    specific_name: name.clone(),
+    kind: kind.clone(),
+    returns: returns.clone(),
+    oid: None,
+    schema: None,
+    read_only: false,
+  })
+}
+
+/// Finds `name` among the functions and procedures in `schema`, by specific name or by a
+/// name that isn't overloaded.
+async fn pg_routine(pool: &PgPool, schema: &str, name: &str) -> Result<Routine, String> {
+  let rows = sqlx::query(
+    "SELECT p.oid::int8, p.proname::text, p.proname || '_' || p.oid, \
+     CASE p.prokind WHEN 'p' THEN 'PROCEDURE' ELSE 'FUNCTION' END, \
+     CASE p.prokind WHEN 'p' THEN NULL ELSE pg_get_function_result(p.oid) END, \
+     p.provolatile <> 'v' \
+     FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace \
+     WHERE n.nspname = $1 AND p.prokind IN ('f', 'p')",
+  )
+  .bind(schema)
+  .fetch_all(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  let routine = |row: &sqlx::postgres::PgRow| -> Result<Routine, String> {
+    Ok(Routine {
+      oid: Some(row.try_get(0).map_err(|e| e.to_string())?),
+      name: row.try_get(1).map_err(|e| e.to_string())?,
+      specific_name: row.try_get(2).map_err(|e| e.to_string())?,
+      kind: row.try_get(3).map_err(|e| e.to_string())?,
+      returns: row.try_get(4).map_err(|e| e.to_string())?,
+      read_only: row.try_get(5).map_err(|e| e.to_string())?,
+      schema: Some(schema.to_string()),
+    })
+  };
+  let mut routines: Vec<Routine> = rows.iter().map(routine).collect::<Result<_, _>>()?;
+  if let Some(i) = routines.iter().position(|r| r.specific_name == name) {
+    return Ok(routines.swap_remove(i));
+  }
+  let names: Vec<String> = routines.iter().map(|r| r.name.clone()).collect();
+  let found = ident::resolve(Dialect::Postgres, name, &names)?.to_string();
+  let mut matches: Vec<Routine> = routines.into_iter().filter(|r| r.name == found).collect();
+  if matches.len() > 1 {
+    let specific: Vec<&str> = matches.iter().map(|r| r.specific_name.as_str()).collect();
+    return Err(format!(
+      "{} is overloaded; pass one of its specific names: {}",
+      found,
+      specific.join(", ")
+    ));
+  }
+  Ok(matches.remove(0))
+}
+
+/// Finds routine `name`, in Postgres `schema` (the default one when `None`).
+async fn find_routine(
+  pool: &SqlPool,
+  schema: Option<&str>,
+  name: &str,
+  kind: Option<&str>,
+) -> Result<Routine, String> {
+  match pool {
+    SqlPool::MySql(mysql) => mysql_routine(mysql, name, kind).await,
+    SqlPool::Postgres(pg) => pg_routine(pg, schema.unwrap_or(db::PG_DEFAULT_SCHEMA), name).await,
+    SqlPool::Sqlite(_) => Err("SQLite has no stored routines".to_string()),
+  }
+}
+
+/// Parameters of `routine` in signature order; a function's return value isn't one.
+async fn parameters(pool: &SqlPool, routine: &Routine) -> Result<Vec<RoutineParameter>, String> {
+  let rows: Vec<(i64, Option<String>, Option<String>, String)> = match pool {
+    SqlPool::MySql(mysql) => {
+      sqlx::query_as(
+        "SELECT CAST(ORDINAL_POSITION AS SIGNED), CAST(PARAMETER_NAME AS CHAR), \
+         CAST(PARAMETER_MODE AS CHAR), CAST(DTD_IDENTIFIER AS CHAR) \
+         FROM information_schema.PARAMETERS WHERE SPECIFIC_SCHEMA = DATABASE() \
+         AND SPECIFIC_NAME = ? AND ROUTINE_TYPE = ? AND ORDINAL_POSITION > 0 \
+         ORDER BY ORDINAL_POSITION",
+      )
+      .bind(&routine.specific_name)
+      .bind(&routine.kind)
+      .fetch_all(mysql)
+      .await
+    }
+    SqlPool::Postgres(pg) => {
+      sqlx::query_as(
+        "SELECT a.i, NULLIF(p.proargnames[a.i::int], ''), \
+         CASE COALESCE(p.proargmodes[a.i::int]::text, 'i') WHEN 'i' THEN 'IN' WHEN 'o' THEN 'OUT' \
+         WHEN 'b' THEN 'INOUT' WHEN 'v' THEN 'VARIADIC' ELSE 'TABLE' END, \
+         format_type(a.t, NULL) \
+         FROM pg_proc p, unnest(COALESCE(p.proallargtypes, p.proargtypes::oid[])) \
+         WITH ORDINALITY AS a(t, i) WHERE p.oid = $1::oid ORDER BY a.i",
+      )
+      .bind(routine.oid)
+      .fetch_all(pg)
+      .await
+    }
+    SqlPool::Sqlite(_) => return Ok(Vec::new()),
+  }
+  .map_err(|e| e.to_string())?;
+  Ok(
+    rows
+      .into_iter()
+      .map(|(position, name, mode, data_type)| RoutineParameter {
+        position,
+        name,
+        // MySQL leaves the mode of function parameters empty; they are all IN
+        mode: mode.unwrap_or_else(|| "IN".to_string()),
+        data_type,
+      })
+      .collect(),
+  )
+}
+
+async fn routine_definition(
+  pool: &SqlPool,
+  schema: Option<&str>,
+  name: &str,
+  kind: Option<&str>,
+) -> Result<RoutineDefinition, String> {
+  let routine = find_routine(pool, schema, name, kind).await?;
+  let definition: Option<String> = match pool {
+    SqlPool::MySql(mysql) => {
+      let sql = format!(
+        "SHOW CREATE {} {}",
+        routine.kind,
+        pool.quote_ident(&routine.name)
+      );
+      let row = sqlx::query(&sql)
+        .fetch_one(mysql)
+        .await
+        .map_err(|e| e.to_string())?;
+      row.try_get(2).map_err(|e| e.to_string())?
+    }
+    SqlPool::Postgres(pg) => {
+      let (definition,): (Option<String>,) = sqlx::query_as("SELECT pg_get_functiondef($1::oid)")
+        .bind(routine.oid)
+        .fetch_one(pg)
+        .await
+        .map_err(|e| e.to_string())?;
+      definition
+    }
+    SqlPool::Sqlite(_) => None,
+  };
+  Ok(RoutineDefinition {
+    parameters: parameters(pool, &routine).await?,
+    name: routine.name,
+    specific_name: routine.specific_name,
+    kind: routine.kind,
+    returns: routine.returns,
+    definition,
+  })
+}
+
+/// Checks that one argument came for each parameter taking a value.
+fn check_arguments(
+  routine: &Routine,
+  inputs: &[&RoutineParameter],
+  arguments: &[Option<String>],
+) -> Result<(), String> {
+  if inputs.len() != arguments.len() {
+    return Err(format!(
+      "{} takes {} argument(s), got {}",
+      routine.name,
+      inputs.len(),
+      arguments.len()
+    ));
+  }
+  Ok(())
+}
+
+fn literal(argument: Option<&str>) -> String {
+  match argument {
+    Some(value) => db::quote_literal(Dialect::MySql, value),
+    None => "NULL".to_string(),
+  }
+}
+
+/// Calls a MySQL routine on `conn`, one connection so `OUT` parameters can come back through
+/// session variables; every result set a procedure produces is collected.
+async fn call_mysql(
+  conn: &mut sqlx::MySqlConnection,
+  routine: &Routine,
+  parameters: &[RoutineParameter],
+  arguments: &[Option<String>],
+  tz: Option<&chrono_tz::Tz>,
+) -> Result<RoutineCallResult, String> {
+  let name = ident::quote(Dialect::MySql, &routine.name);
+  let inputs: Vec<&RoutineParameter> = parameters.iter().filter(|p| p.mode != "OUT").collect();
+  check_arguments(routine, &inputs, arguments)?;
+
+  if routine.kind == "FUNCTION" {
+    let values: Vec<String> = arguments.iter().map(|a| literal(a.as_deref())).collect();
+    let sql = format!("SELECT {}({}) AS {}", name, values.join(", "), name);
+    let rows = sqlx::query(&sql)
+      .fetch_all(&mut *conn)
+      .await
+      .map_err(|e| e.to_string())?;
+    return Ok(RoutineCallResult {
+      result_sets: vec![ResultSet {
+        columns: db::result_columns(&rows),
+        rows: rows
+          .iter()
+          .map(|row| db::mysql_row_to_json(row, tz))
+          .collect(),
+      }],
+      sql,
+      out_params: JsonRow::new(),
+    });
+  }
+
+  let variable = |p: &RoutineParameter| format!("@spectra_param_{}", p.position);
+  let mut setup = Vec::new();
+  let mut values = Vec::new();
+  let mut arguments = arguments.iter();
+  for parameter in parameters {
+    match parameter.mode.as_str() {
+      "IN" => values.push(literal(arguments.next().and_then(Option::as_deref))),
+      mode => {
+        let value = match mode {
+          "INOUT" => literal(arguments.next().and_then(Option::as_deref)),
+          _ => "NULL".to_string(),
+        };
+        setup.push(format!("SET {} = {}", variable(parameter), value));
+        values.push(variable(parameter));
+      }
+    }
+  }
+  for sql in &setup {
+    sqlx::query(sql)
+      .execute(&mut *conn)
+      .await
+      .map_err(|e| e.to_string())?;
+  }
+  let call = format!("CALL {}({})", name, values.join(", "));
+  let mut result_sets = Vec::new();
+  let mut rows = Vec::new();
+  {
+    let mut stream = sqlx::raw_sql(&call).fetch_many(&mut *conn);
+    while let Some(item) = stream.try_next().await.map_err(|e| e.to_string())? {
+      match item {
+        sqlx::Either::Right(row) => rows.push(row),
+        // The end of one result set, or of the call itself
+        sqlx::Either::Left(_) if !rows.is_empty() => {
+          result_sets.push(ResultSet {
+            columns: db::result_columns(&rows),
+            rows: rows
+              .iter()
+              .map(|row| db::mysql_row_to_json(row, tz))
+              .collect(),
+          });
+          rows.clear();
+        }
+        sqlx::Either::Left(_) => {}
+      }
+    }
+  }
+
+  let mut out_params = JsonRow::new();
+  let outputs: Vec<String> = parameters
+    .iter()
+    .filter(|p| p.mode != "IN")
+    .map(|p| {
+      let name = p.name.clone().unwrap_or_else(|| p.position.to_string());
+      format!("{} AS {}", variable(p), ident::quote(Dialect::MySql, &name))
+    })
+    .collect();
+  if !outputs.is_empty() {
+    let row = sqlx::query(&format!("SELECT {}", outputs.join(", ")))
+      .fetch_one(&mut *conn)
+      .await
+      .map_err(|e| e.to_string())?;
+    out_params = db::mysql_row_to_json(&row, tz);
+  }
+  let sql = setup
+    .into_iter()
+    .chain([call])
+    .collect::<Vec<_>>()
+    .join(";\n");
+  Ok(RoutineCallResult {
+    sql,
+    result_sets,
+    out_params,
+  })
+}
+
+/// Calls a Postgres routine with its arguments bound as text and cast to the parameter types,
+/// which also picks the right overload.
+async fn call_postgres(
+  pool: &PgPool,
+  routine: &Routine,
+  parameters: &[RoutineParameter],
+  arguments: &[Option<String>],
+  tz: Option<&chrono_tz::Tz>,
+) -> Result<RoutineCallResult, String> {
+  let procedure = routine.kind == "PROCEDURE";
+  let inputs: Vec<&RoutineParameter> = parameters
+    .iter()
+    .filter(|p| matches!(p.mode.as_str(), "IN" | "INOUT" | "VARIADIC"))
+    .collect();
+  check_arguments(routine, &inputs, arguments)?;
+  let mut n = 0;
+  let values: Vec<String> = parameters
+    .iter()
+    .filter_map(|p| match p.mode.as_str() {
+      // A procedure's OUT parameters take a placeholder in the call; a function's don't
+      "OUT" if procedure => Some(format!("NULL::{}", p.data_type)),
+      "OUT" | "TABLE" => None,
+      mode => {
+        n += 1;
+        let variadic = if mode == "VARIADIC" { "VARIADIC " } else { "" };
+        Some(format!("{}${}::{}", variadic, n, p.data_type))
+      }
+    })
+    .collect();
+  let target = format!(
+    "{}.{}({})",
+    ident::quote(
+      Dialect::Postgres,
+      routine.schema.as_deref().unwrap_or(db::PG_DEFAULT_SCHEMA)
+    ),
+    ident::quote(Dialect::Postgres, &routine.name),
+    values.join(", ")
+  );
+  let sql = if procedure {
+    format!("CALL {}", target)
+  } else {
+    format!("SELECT * FROM {}", target)
+  };
+  let mut query = sqlx::query(&sql);
+  for argument in arguments {
+    query = query.bind(argument);
+  }
+  let rows = query.fetch_all(pool).await.map_err(|e| e.to_string())?;
+  let rows_json: Vec<JsonRow> = rows.iter().map(|row| db::pg_row_to_json(row, tz)).collect();
+  if procedure {
+    return Ok(RoutineCallResult {
+      sql,
+      result_sets: Vec::new(),
+      out_params: rows_json.into_iter().next().unwrap_or_default(),
+    });
+  }
+  Ok(RoutineCallResult {
+    sql,
+    result_sets: vec![ResultSet {
+      columns: db::result_columns(&rows),
+      rows: rows_json,
+    }],
+    out_params: JsonRow::new(),
+  })
+}
+
+async fn call_routine(
+  state: &AppState,
+  pool: SqlPool,
+  connection: &str,
+  schema: Option<&str>,
+  name: String,
+  kind: Option<String>,
+  arguments: Vec<Option<String>>,
+) -> Result<RoutineCallResult, String> {
+  let routine = find_routine(&pool, schema, &name, kind.as_deref()).await?;
+  // Postgres functions declared STABLE or IMMUTABLE can't write, so read-only connections
+  // may still run them
+  if !routine.read_only {
+    readonly::ensure_writable(state, connection)?;
+  }
+  let parameters = parameters(&pool, &routine).await?;
+  let tz = timezone::display_zone(state, connection);
+  let result = match &pool {
+    SqlPool::MySql(mysql) => {
+      let mut conn = mysql.acquire().await.map_err(|e| e.to_string())?;
+      call_mysql(&mut conn, &routine, &parameters, &arguments, tz.as_ref()).await?
+    }
+    SqlPool::Postgres(pg) => {
+      call_postgres(pg, &routine, &parameters, &arguments, tz.as_ref()).await?
+    }
+    SqlPool::Sqlite(_) => return Err("SQLite has no stored routines".to_string()),
+  };
+  let binds = serde_json::json!(arguments);
+  audit::record(state, connection, &result.sql, binds, None).await;
+  Ok(result)
+}
+
+/// Source and parameters of a MySQL function or procedure; `kind` (`function` or
+/// `procedure`) is only needed when both exist under `name`.
+#[tauri::command]
+pub async fn mysql_get_routine_definition(
+  state: State<'_, AppState>,
+  name: String,
+  kind: Option<String>,
+  connection_id: Option<String>,
+) -> Result<RoutineDefinition, String> {
+  let pool = SqlPool::MySql(connections::mysql(&state, connection_id.as_deref())?);
+  routine_definition(&pool, None, &name, kind.as_deref()).await
+}
+
+/// Source and parameters of a Postgres function or procedure in `schema`, by specific or
+/// plain name.
+#[tauri::command]
+pub async fn postgres_get_routine_definition(
+  state: State<'_, AppState>,
+  name: String,
+  connection_id: Option<String>,
+  schema: Option<String>,
+) -> Result<RoutineDefinition, String> {
+  let pg = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pg, schema).await?;
+  let pool = SqlPool::Postgres(pg);
+  routine_definition(&pool, Some(&schema), &name, None).await
+}
+
+/// Calls a MySQL function or procedure with `arguments`, returning every result set and the
+/// `OUT` parameters.
+#[tauri::command]
+pub async fn mysql_call_routine(
+  state: State<'_, AppState>,
+  name: String,
+  kind: Option<String>,
+  arguments: Vec<Option<String>>,
+  connection_id: Option<String>,
+) -> Result<RoutineCallResult, String> {
+  let pool = SqlPool::MySql(connections::mysql(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("mysql");
+  call_routine(&state, pool, connection, None, name, kind, arguments).await
+}
+
+/// Calls a Postgres function (its rows as the result set) or procedure (its `OUT` and
+/// `INOUT` parameters) in `schema` with `arguments`.
+#[tauri::command]
+pub async fn postgres_call_routine(
+  state: State<'_, AppState>,
+  name: String,
+  arguments: Vec<Option<String>>,
+  connection_id: Option<String>,
+  schema: Option<String>,
+) -> Result<RoutineCallResult, String> {
+  let pg = connections::postgres(&state, connection_id.as_deref())?;
+  let schema = db::pg_schema(&pg, schema).await?;
+  let pool = SqlPool::Postgres(pg);
+  let connection = connection_id.as_deref().unwrap_or("postgres");
+  call_routine(
+    &state,
+    pool,
+    connection,
+    Some(&schema),
+    name,
+    None,
+    arguments,
+  )
+  .await
+}
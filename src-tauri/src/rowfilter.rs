@@ -0,0 +1,310 @@
+//! Server-side filtering, sorting and free-text search for the table browsers. Filters and
+//! sort keys arrive in the shape grid views store them ([`ViewFilter`], [`ViewSort`]); column
+//! names are checked against the table and quoted, and every value is bound, so nothing the
+//! user typed ends up in the SQL text.
+
+use crate::db::SqlPool;
+use crate::schema;
+use crate::views::{ViewFilter, ViewSort};
+
+/// Escape character of the generated `LIKE` patterns; unlike `\` it means the same in every
+/// dialect and under any `sql_mode`.
+const LIKE_ESCAPE: char = '!';
+
+/// WHERE conditions and ORDER BY keys for a row query, with the values the conditions bind
+/// in placeholder order.
+#[derive(Default)]
+pub struct RowFilter {
+  pub conditions: Vec<String>,
+  pub order: Vec<String>,
+  pub binds: Vec<String>,
+}
+
+impl RowFilter {
+  /// Placeholder for a value bound after the filter's own.
+  pub fn next_placeholder(&self, pool: &SqlPool) -> String {
+    pool.placeholder(self.binds.len() + 1)
+  }
+
+  fn bind(&mut self, pool: &SqlPool, value: String) -> String {
+    self.binds.push(value);
+    pool.placeholder(self.binds.len())
+  }
+}
+
+/// Column as text, for pattern matching.
+fn text_expr(pool: &SqlPool, col: &str) -> String {
+  match pool {
+    SqlPool::MySql(_) => format!("CAST({} AS CHAR)", col),
+    SqlPool::Postgres(_) => format!("{}::text", col),
+    SqlPool::Sqlite(_) => format!("CAST({} AS TEXT)", col),
+  }
+}
+
+fn like_pattern(value: &str, prefix: &str, suffix: &str) -> String {
+  let mut escaped = String::with_capacity(value.len());
+  for c in value.to_lowercase().chars() {
+    if matches!(c, '%' | '_') || c == LIKE_ESCAPE {
+      escaped.push(LIKE_ESCAPE);
+    }
+    escaped.push(c);
+  }
+  format!("{}{}{}", prefix, escaped, suffix)
+}
+
+fn like(pool: &SqlPool, col: &str, placeholder: &str, negated: bool) -> String {
+  format!(
+    "LOWER({}) {}LIKE {} ESCAPE '{}'",
+    text_expr(pool, col),
+    if negated { "NOT " } else { "" },
+    placeholder,
+    LIKE_ESCAPE
+  )
+}
+
+fn comparison(operator: &str) -> Option<&'static str> {
+  Some(match operator {
+    "=" => "=",
+    "!=" | "<>" => "<>",
+    "<" => "<",
+    "<=" => "<=",
+    ">" => ">",
+    ">=" => ">=",
+    _ => return None,
+  })
+}
+
+async fn condition(
+  pool: &SqlPool,
+  schema: &str,
+  table: &str,
+  filter: &ViewFilter,
+  out: &mut RowFilter,
+) -> Result<String, String> {
+  let col = pool.quote_ident(&filter.column);
+  match filter.operator.as_str() {
+    "isNull" => return Ok(format!("{} IS NULL", col)),
+    "isNotNull" => return Ok(format!("{} IS NOT NULL", col)),
+    _ => {}
+  }
+  let value = filter.value.clone().ok_or_else(|| {
+    format!(
+      "Filter '{}' on {} needs a value",
+      filter.operator, filter.column
+    )
+  })?;
+  if let Some(op) = comparison(&filter.operator) {
+    let placeholder = out.bind(pool, value);
+    // Postgres won't compare a text parameter with other types, so cast it to the column's
+    let placeholder = match pool.pg_column_type(schema, table, &filter.column).await? {
+      Some(udt) => format!("{}::{}", placeholder, udt),
+      None => placeholder,
+    };
+    return Ok(format!("{} {} {}", col, op, placeholder));
+  }
+  let (pattern, negated) = match filter.operator.as_str() {
+    "contains" => (like_pattern(&value, "%", "%"), false),
+    "notContains" => (like_pattern(&value, "%", "%"), true),
+    "startsWith" => (like_pattern(&value, "", "%"), false),
+    "endsWith" => (like_pattern(&value, "%", ""), false),
+    other => {
+      return Err(format!(
+        "Unknown filter operator '{}'; expected =, !=, <, <=, >, >=, contains, notContains, \
+         startsWith, endsWith, isNull or isNotNull",
+        other
+      ))
+    }
+  };
+  let placeholder = out.bind(pool, pattern);
+  Ok(like(pool, &col, &placeholder, negated))
+}
+
+/// Conditions keeping rows of `table` (in Postgres `schema`) that match every filter and,
+/// when `search` is given, hold it (ignoring case) in any column, plus ORDER BY keys for
+/// `sort`.
+pub async fn build(
+  pool: &SqlPool,
+  schema: &str,
+  table: &str,
+  filters: &[ViewFilter],
+  sort: &[ViewSort],
+  search: Option<&str>,
+) -> Result<RowFilter, String> {
+  let mut out = RowFilter::default();
+  let search = search.map(str::trim).filter(|s| !s.is_empty());
+  if filters.is_empty() && sort.is_empty() && search.is_none() {
+    return Ok(out);
+  }
+
+  let columns: Vec<String> = schema::column_info(pool, schema, table)
+    .await?
+    .into_iter()
+    .map(|c| c.name)
+    .collect();
+  let known = |column: &str| -> Result<(), String> {
+    if columns.iter().any(|c| c == column) {
+      Ok(())
+    } else {
+      Err(format!("Unknown column {} of {}", column, table))
+    }
+  };
+
+  for filter in filters {
+    known(&filter.column)?;
+    let condition = condition(pool, schema, table, filter, &mut out).await?;
+    out.conditions.push(condition);
+  }
+  if let Some(search) = search {
+    let pattern = like_pattern(search, "%", "%");
+    let mut any = Vec::with_capacity(columns.len());
+    for column in &columns {
+      let placeholder = out.bind(pool, pattern.clone());
+      any.push(like(pool, &pool.quote_ident(column), &placeholder, false));
+    }
+    out.conditions.push(format!("({})", any.join(" OR ")));
+  }
+  for key in sort {
+    known(&key.column)?;
+    out.order.push(format!(
+      "{} {}",
+      pool.quote_ident(&key.column),
+      if key.descending { "DESC" } else { "ASC" }
+    ));
+  }
+  Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::db;
+
+  fn filter(column: &str, operator: &str, value: Option<&str>) -> ViewFilter {
+    ViewFilter {
+      column: column.to_string(),
+      operator: operator.to_string(),
+      value: value.map(str::to_string),
+    }
+  }
+
+  async fn pool() -> SqlPool {
+    let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+    sqlx::raw_sql(
+      "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT); \
+       INSERT INTO items VALUES (1, '50% off'), (2, '50 off'), (3, 'a_b'), (4, 'ab'), \
+         (5, NULL);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    SqlPool::Sqlite(pool)
+  }
+
+  async fn ids(pool: &SqlPool, filters: &[ViewFilter], search: Option<&str>) -> Vec<i64> {
+    let sort = [ViewSort {
+      column: "id".to_string(),
+      descending: false,
+    }];
+    let built = build(pool, db::PG_DEFAULT_SCHEMA, "items", filters, &sort, search)
+      .await
+      .unwrap();
+    let filter = if built.conditions.is_empty() {
+      String::new()
+    } else {
+      format!(" WHERE {}", built.conditions.join(" AND "))
+    };
+    let sql = format!(
+      "SELECT id FROM items{} ORDER BY {}",
+      filter,
+      built.order.join(", ")
+    );
+    let SqlPool::Sqlite(sqlite) = pool else {
+      unreachable!()
+    };
+    let mut query = sqlx::query_as::<_, (i64,)>(&sql);
+    for bind in &built.binds {
+      query = query.bind(bind);
+    }
+    let rows = query.fetch_all(sqlite).await.unwrap();
+    rows.into_iter().map(|(id,)| id).collect()
+  }
+
+  #[test]
+  fn operators_map_to_sql() {
+    assert_eq!(comparison("!="), Some("<>"));
+    assert_eq!(comparison("<>"), Some("<>"));
+    assert_eq!(comparison(">="), Some(">="));
+    assert_eq!(comparison("contains"), None);
+    assert_eq!(comparison("; DROP"), None);
+  }
+
+  #[test]
+  fn like_patterns_escape_wildcards() {
+    assert_eq!(like_pattern("50%_Off!", "%", "%"), "%50!%!_off!!%");
+    assert_eq!(like_pattern("ab", "", "%"), "ab%");
+  }
+
+  #[tokio::test]
+  async fn filters_select_rows() {
+    let pool = pool().await;
+    assert_eq!(
+      ids(&pool, &[filter("id", ">=", Some("4"))], None).await,
+      vec![4, 5]
+    );
+    assert_eq!(
+      ids(&pool, &[filter("id", "!=", Some("1"))], None).await,
+      vec![2, 3, 4, 5]
+    );
+    assert_eq!(
+      ids(&pool, &[filter("name", "isNull", None)], None).await,
+      vec![5]
+    );
+    assert_eq!(
+      ids(&pool, &[filter("name", "startsWith", Some("50"))], None).await,
+      vec![1, 2]
+    );
+    assert_eq!(
+      ids(&pool, &[filter("name", "notContains", Some("OFF"))], None).await,
+      vec![3, 4]
+    );
+  }
+
+  #[tokio::test]
+  async fn wildcards_match_literally() {
+    let pool = pool().await;
+    assert_eq!(
+      ids(&pool, &[filter("name", "contains", Some("%"))], None).await,
+      vec![1]
+    );
+    assert_eq!(
+      ids(&pool, &[filter("name", "contains", Some("_"))], None).await,
+      vec![3]
+    );
+    assert_eq!(ids(&pool, &[], Some("a_")).await, vec![3]);
+  }
+
+  #[tokio::test]
+  async fn unknown_columns_and_operators_are_rejected() {
+    let pool = pool().await;
+    let schema = db::PG_DEFAULT_SCHEMA;
+    let unknown = [filter("id; DROP TABLE items", "=", Some("1"))];
+    assert!(build(&pool, schema, "items", &unknown, &[], None)
+      .await
+      .is_err());
+    let sort = [ViewSort {
+      column: "nope".to_string(),
+      descending: true,
+    }];
+    assert!(build(&pool, schema, "items", &[], &sort, None)
+      .await
+      .is_err());
+    let operator = [filter("id", "LIKE", Some("1"))];
+    assert!(build(&pool, schema, "items", &operator, &[], None)
+      .await
+      .is_err());
+    let missing = [filter("id", "=", None)];
+    assert!(build(&pool, schema, "items", &missing, &[], None)
+      .await
+      .is_err());
+  }
+}
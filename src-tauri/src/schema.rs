@@ -0,0 +1,748 @@
+//! Column metadata beyond the plain name lists returned by `*_get_columns`.
+
+use std::collections::HashMap;
+
+use sqlx::Row;
+use tauri::State;
+
+use crate::db::{self, SqlPool};
+use crate::AppState;
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnInfo {
+  pub name: String,
+  /// Declared type as the engine reports it, e.g. `decimal(12,2)` or `numeric`.
+  pub data_type: String,
+  pub nullable: bool,
+  /// Total significant digits for exact numeric columns.
+  pub numeric_precision: Option<i64>,
+  /// Digits after the decimal point for exact numeric columns.
+  pub numeric_scale: Option<i64>,
+  /// Filled in by the database when left out of an insert (default, auto-increment, ...).
+  pub has_default: bool,
+  /// Declared maximum length of character columns.
+  pub max_length: Option<i64>,
+}
+
+/// Precision and scale from a declared type such as `DECIMAL(10, 2)`.
+fn declared_precision(declared: &str) -> (Option<i64>, Option<i64>) {
+  let Some(args) = declared
+    .split_once('(')
+    .and_then(|(_, rest)| rest.split_once(')'))
+    .map(|(args, _)| args)
+  else {
+    return (None, None);
+  };
+  let mut parts = args.split(',').map(|p| p.trim().parse::<i64>().ok());
+  let precision = parts.next().flatten();
+  let scale = parts.next().flatten().or(precision.map(|_| 0));
+  (precision, scale)
+}
+
+fn is_exact_numeric(data_type: &str) -> bool {
+  let lower = data_type.to_lowercase();
+  lower.starts_with("decimal") || lower.starts_with("numeric")
+}
+
+/// `name`, `data_type`, `is_nullable`, `numeric_precision`, `numeric_scale`, `has_default`,
+/// `max_length`
+type PgColumnRow = (
+  String,
+  String,
+  String,
+  Option<i32>,
+  Option<i32>,
+  bool,
+  Option<i32>,
+);
+
+/// Columns of `table`, which lives in `schema` on Postgres; the other engines ignore it.
+pub async fn column_info(
+  pool: &SqlPool,
+  schema: &str,
+  table: &str,
+) -> Result<Vec<ColumnInfo>, String> {
+  match pool {
+    SqlPool::MySql(mysql) => {
+      // information_schema text columns can come back as VARBINARY, so cast explicitly
+      let rows = sqlx::query(
+        "SELECT CAST(COLUMN_NAME AS CHAR), CAST(COLUMN_TYPE AS CHAR), CAST(IS_NULLABLE AS CHAR), \
+         CAST(NUMERIC_PRECISION AS SIGNED), CAST(NUMERIC_SCALE AS SIGNED), \
+         CAST(COLUMN_DEFAULT IS NOT NULL OR EXTRA LIKE '%auto_increment%' \
+           OR EXTRA LIKE '%GENERATED%' AS SIGNED), \
+         CAST(CHARACTER_MAXIMUM_LENGTH AS SIGNED) \
+         FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? \
+         ORDER BY ORDINAL_POSITION",
+      )
+      .bind(table)
+      .fetch_all(mysql)
+      .await
+      .map_err(|e| e.to_string())?;
+      rows
+        .iter()
+        .map(|row| {
+          let data_type: String = row.try_get(1).map_err(|e| e.to_string())?;
+          let exact = is_exact_numeric(&data_type);
+          Ok(ColumnInfo {
+            name: row.try_get(0).map_err(|e| e.to_string())?,
+            nullable: row.try_get::<String, _>(2).map_err(|e| e.to_string())? == "YES",
+            numeric_precision: if exact {
+              row.try_get(3).ok().flatten()
+            } else {
+              None
+            },
+            numeric_scale: if exact {
+              row.try_get(4).ok().flatten()
+            } else {
+              None
+            },
+            has_default: row.try_get::<i64, _>(5).map_err(|e| e.to_string())? != 0,
+            max_length: row.try_get(6).ok().flatten(),
+            data_type,
+          })
+        })
+        .collect()
+    }
+    SqlPool::Postgres(pg) => {
+      let rows: Vec<PgColumnRow> = sqlx::query_as(
+        "SELECT column_name::text, data_type::text, is_nullable::text, \
+         numeric_precision::int, numeric_scale::int, \
+         (column_default IS NOT NULL OR is_identity = 'YES' OR is_generated <> 'NEVER'), \
+         character_maximum_length::int \
+         FROM information_schema.columns WHERE table_schema = $2 AND table_name = $1 \
+         ORDER BY ordinal_position",
+      )
+      .bind(table)
+      .bind(schema)
+      .fetch_all(pg)
+      .await
+      .map_err(|e| e.to_string())?;
+      Ok(
+        rows
+          .into_iter()
+          .map(
+            |(name, data_type, nullable, precision, scale, has_default, max_length)| {
+              let exact = is_exact_numeric(&data_type);
+              ColumnInfo {
+                name,
+                nullable: nullable == "YES",
+                // Unconstrained `numeric` has no declared precision and reports NULL here
+                numeric_precision: precision.filter(|_| exact).map(i64::from),
+                numeric_scale: scale.filter(|_| exact).map(i64::from),
+                has_default,
+                max_length: max_length.map(i64::from),
+                data_type,
+              }
+            },
+          )
+          .collect(),
+      )
+    }
+    SqlPool::Sqlite(sqlite) => {
+      let q = format!("PRAGMA table_info({})", pool.quote_ident(table));
+      let rows: Vec<(i32, String, String, i32, Option<String>, i32)> = sqlx::query_as(&q)
+        .fetch_all(sqlite)
+        .await
+        .map_err(|e| e.to_string())?;
+      Ok(
+        rows
+          .into_iter()
+          .map(|(_, name, data_type, notnull, default, pk)| {
+            let (numeric_precision, numeric_scale) = if is_exact_numeric(&data_type) {
+              declared_precision(&data_type)
+            } else {
+              (None, None)
+            };
+            // An INTEGER PRIMARY KEY aliases the rowid, which SQLite assigns
+            let has_default =
+              default.is_some() || (pk > 0 && data_type.eq_ignore_ascii_case("integer"));
+            ColumnInfo {
+              name,
+              data_type,
+              nullable: notnull == 0,
+              numeric_precision,
+              numeric_scale,
+              has_default,
+              // SQLite doesn't enforce declared lengths
+              max_length: None,
+            }
+          })
+          .collect(),
+      )
+    }
+  }
+}
+
+#[tauri::command]
+pub async fn get_column_info(
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+) -> Result<Vec<ColumnInfo>, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let table = pool.resolve_table(&table).await?;
+  column_info(&pool, db::PG_DEFAULT_SCHEMA, &table).await
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeignKey {
+  pub name: String,
+  /// Table holding the reference.
+  pub table: String,
+  pub columns: Vec<String>,
+  pub referenced_table: String,
+  /// Same order as `columns`.
+  pub referenced_columns: Vec<String>,
+  /// Referential action: `NO ACTION`, `RESTRICT`, `CASCADE`, `SET NULL` or `SET DEFAULT`.
+  pub on_update: String,
+  pub on_delete: String,
+}
+
+/// One column of a foreign key.
+#[derive(sqlx::FromRow)]
+struct KeyColumn {
+  constraint_name: String,
+  source_table: String,
+  source_column: String,
+  target_table: String,
+  target_column: String,
+  on_update: String,
+  on_delete: String,
+}
+
+/// A row of `pragma_foreign_key_list` with the table declaring the key.
+#[derive(sqlx::FromRow)]
+struct SqliteKeyColumn {
+  source: String,
+  id: i64,
+  referenced: String,
+  column: String,
+  to: Option<String>,
+  on_update: String,
+  on_delete: String,
+}
+
+/// Groups key columns, ordered by constraint and column position, into keys.
+fn group_foreign_keys(rows: Vec<KeyColumn>) -> Vec<ForeignKey> {
+  let mut keys: Vec<ForeignKey> = Vec::new();
+  for row in rows {
+    match keys.last_mut() {
+      Some(key) if key.name == row.constraint_name && key.table == row.source_table => {
+        key.columns.push(row.source_column);
+        key.referenced_columns.push(row.target_column);
+      }
+      _ => keys.push(ForeignKey {
+        name: row.constraint_name,
+        table: row.source_table,
+        columns: vec![row.source_column],
+        referenced_table: row.target_table,
+        referenced_columns: vec![row.target_column],
+        on_update: row.on_update,
+        on_delete: row.on_delete,
+      }),
+    }
+  }
+  keys
+}
+
+/// Columns of `table`'s primary key, in key order; empty when it has none. `schema` is the
+/// table's Postgres schema.
+pub async fn primary_key(pool: &SqlPool, schema: &str, table: &str) -> Result<Vec<String>, String> {
+  let rows: Vec<(String,)> = match pool {
+    SqlPool::MySql(mysql) => sqlx::query_as(
+      "SELECT CAST(COLUMN_NAME AS CHAR) FROM information_schema.KEY_COLUMN_USAGE \
+       WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND CONSTRAINT_NAME = 'PRIMARY' \
+       ORDER BY ORDINAL_POSITION",
+    )
+    .bind(table)
+    .fetch_all(mysql)
+    .await
+    .map_err(|e| e.to_string())?,
+    SqlPool::Postgres(pg) => sqlx::query_as(
+      "SELECT a.attname::text FROM pg_index i \
+       JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey) \
+       WHERE i.indrelid = to_regclass($1) AND i.indisprimary \
+       ORDER BY array_position(i.indkey::int2[], a.attnum)",
+    )
+    .bind(pool.table_ref_in(schema, table))
+    .fetch_all(pg)
+    .await
+    .map_err(|e| e.to_string())?,
+    SqlPool::Sqlite(sqlite) => {
+      sqlx::query_as("SELECT name FROM pragma_table_info(?) WHERE pk > 0 ORDER BY pk")
+        .bind(table)
+        .fetch_all(sqlite)
+        .await
+        .map_err(|e| e.to_string())?
+    }
+  };
+  Ok(rows.into_iter().map(|(name,)| name).collect())
+}
+
+/// Row count of a table, read off the statistics when `exact` is false.
+#[derive(serde::Serialize)]
+pub struct RowCount {
+  pub count: i64,
+  pub exact: bool,
+}
+
+/// The number of rows in `table` according to the database's statistics, without scanning it:
+/// `pg_class.reltuples` scaled to the table's current size the way the planner does, InnoDB's
+/// `TABLE_ROWS`, or the largest row count in `sqlite_stat1`. `None` when the table was never
+/// analyzed. `schema` is the table's Postgres schema.
+pub async fn estimated_rows(
+  pool: &SqlPool,
+  schema: &str,
+  table: &str,
+) -> Result<Option<i64>, String> {
+  match pool {
+    SqlPool::MySql(mysql) => {
+      let row: Option<(Option<u64>,)> = sqlx::query_as(
+        "SELECT TABLE_ROWS FROM information_schema.TABLES \
+         WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+      )
+      .bind(table)
+      .fetch_optional(mysql)
+      .await
+      .map_err(|e| e.to_string())?;
+      Ok(
+        row
+          .and_then(|(rows,)| rows)
+          .map(|rows| i64::try_from(rows).unwrap_or(i64::MAX)),
+      )
+    }
+    SqlPool::Postgres(pg) => {
+      let row: Option<(Option<i64>,)> = sqlx::query_as(
+        "SELECT CASE WHEN reltuples < 0 THEN NULL \
+         WHEN relpages = 0 THEN reltuples::bigint \
+         ELSE (reltuples / relpages \
+         * (pg_relation_size(oid) / current_setting('block_size')::int))::bigint END \
+         FROM pg_class WHERE oid = to_regclass($1)",
+      )
+      .bind(pool.table_ref_in(schema, table))
+      .fetch_optional(pg)
+      .await
+      .map_err(|e| e.to_string())?;
+      Ok(row.and_then(|(rows,)| rows))
+    }
+    SqlPool::Sqlite(sqlite) => {
+      let analyzed: Option<(i64,)> = sqlx::query_as(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'sqlite_stat1'",
+      )
+      .fetch_optional(sqlite)
+      .await
+      .map_err(|e| e.to_string())?;
+      if analyzed.is_none() {
+        return Ok(None);
+      }
+      // Each index's entry starts with the rows it covers; partial indexes cover fewer
+      let stats: Vec<(String,)> = sqlx::query_as("SELECT stat FROM sqlite_stat1 WHERE tbl = ?")
+        .bind(table)
+        .fetch_all(sqlite)
+        .await
+        .map_err(|e| e.to_string())?;
+      Ok(
+        stats
+          .iter()
+          .filter_map(|(stat,)| stat.split_whitespace().next()?.parse::<i64>().ok())
+          .max(),
+      )
+    }
+  }
+}
+
+/// Foreign keys declared on `table` and foreign keys of other tables pointing at it, within
+/// the table's Postgres `schema`.
+pub async fn foreign_keys_involving(
+  pool: &SqlPool,
+  schema: &str,
+  table: &str,
+) -> Result<Vec<ForeignKey>, String> {
+  match pool {
+    SqlPool::MySql(mysql) => {
+      let rows = sqlx::query_as(
+        "SELECT CAST(k.CONSTRAINT_NAME AS CHAR) AS constraint_name, \
+         CAST(k.TABLE_NAME AS CHAR) AS source_table, \
+         CAST(k.COLUMN_NAME AS CHAR) AS source_column, \
+         CAST(k.REFERENCED_TABLE_NAME AS CHAR) AS target_table, \
+         CAST(k.REFERENCED_COLUMN_NAME AS CHAR) AS target_column, \
+         CAST(r.UPDATE_RULE AS CHAR) AS on_update, CAST(r.DELETE_RULE AS CHAR) AS on_delete \
+         FROM information_schema.KEY_COLUMN_USAGE k \
+         JOIN information_schema.REFERENTIAL_CONSTRAINTS r \
+         ON r.CONSTRAINT_SCHEMA = k.CONSTRAINT_SCHEMA \
+         AND r.CONSTRAINT_NAME = k.CONSTRAINT_NAME AND r.TABLE_NAME = k.TABLE_NAME \
+         WHERE k.TABLE_SCHEMA = DATABASE() AND k.REFERENCED_TABLE_SCHEMA = DATABASE() \
+         AND (k.TABLE_NAME = ? OR k.REFERENCED_TABLE_NAME = ?) \
+         ORDER BY k.TABLE_NAME, k.CONSTRAINT_NAME, k.ORDINAL_POSITION",
+      )
+      .bind(table)
+      .bind(table)
+      .fetch_all(mysql)
+      .await
+      .map_err(|e| e.to_string())?;
+      Ok(group_foreign_keys(rows))
+    }
+    SqlPool::Postgres(pg) => {
+      let rows = sqlx::query_as(
+        "SELECT c.conname::text AS constraint_name, src.relname::text AS source_table, \
+         a.attname::text AS source_column, dst.relname::text AS target_table, \
+         b.attname::text AS target_column, \
+         CASE c.confupdtype WHEN 'r' THEN 'RESTRICT' WHEN 'c' THEN 'CASCADE' \
+         WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT' ELSE 'NO ACTION' END \
+         AS on_update, \
+         CASE c.confdeltype WHEN 'r' THEN 'RESTRICT' WHEN 'c' THEN 'CASCADE' \
+         WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT' ELSE 'NO ACTION' END \
+         AS on_delete \
+         FROM pg_constraint c \
+         JOIN pg_class src ON src.oid = c.conrelid \
+         JOIN pg_namespace src_ns ON src_ns.oid = src.relnamespace \
+         JOIN pg_class dst ON dst.oid = c.confrelid \
+         JOIN pg_namespace dst_ns ON dst_ns.oid = dst.relnamespace \
+         CROSS JOIN LATERAL unnest(c.conkey, c.confkey) WITH ORDINALITY AS k(src_att, dst_att, n) \
+         JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.src_att \
+         JOIN pg_attribute b ON b.attrelid = c.confrelid AND b.attnum = k.dst_att \
+         WHERE c.contype = 'f' AND src_ns.nspname = $2 AND dst_ns.nspname = $2 \
+         AND (src.relname = $1 OR dst.relname = $1) \
+         ORDER BY src.relname, c.conname, k.n",
+      )
+      .bind(table)
+      .bind(schema)
+      .fetch_all(pg)
+      .await
+      .map_err(|e| e.to_string())?;
+      Ok(group_foreign_keys(rows))
+    }
+    SqlPool::Sqlite(sqlite) => {
+      // `to` is NULL when the reference names only the table, meaning its primary key
+      let rows: Vec<SqliteKeyColumn> = sqlx::query_as(
+        "SELECT m.name AS source, p.id, p.\"table\" AS referenced, p.\"from\" AS \"column\", \
+         p.\"to\", p.on_update, p.on_delete \
+         FROM sqlite_master m JOIN pragma_foreign_key_list(m.name) p \
+         WHERE m.type = 'table' AND (m.name = ?1 OR p.\"table\" = ?1 COLLATE NOCASE) \
+         ORDER BY m.name, p.id, p.seq",
+      )
+      .bind(table)
+      .fetch_all(sqlite)
+      .await
+      .map_err(|e| e.to_string())?;
+      let mut grouped = Vec::with_capacity(rows.len());
+      let mut referenced_keys: HashMap<String, Vec<String>> = HashMap::new();
+      let mut position = 0;
+      for (i, row) in rows.iter().enumerate() {
+        let SqliteKeyColumn {
+          source,
+          id,
+          referenced,
+          column,
+          to,
+          on_update,
+          on_delete,
+        } = row;
+        // SQLite matches table names case-insensitively; report the name as stored
+        let referenced = if referenced.eq_ignore_ascii_case(table) {
+          table
+        } else {
+          referenced
+        };
+        let starts_key = i == 0 || rows[i - 1].source != *source || rows[i - 1].id != *id;
+        position = if starts_key { 0 } else { position + 1 };
+        let to = if let Some(to) = to {
+          to.clone()
+        } else {
+          if !referenced_keys.contains_key(referenced) {
+            let key = primary_key(pool, schema, referenced).await?;
+            referenced_keys.insert(referenced.to_string(), key);
+          }
+          referenced_keys[referenced]
+            .get(position)
+            .cloned()
+            .unwrap_or_default()
+        };
+        grouped.push(KeyColumn {
+          constraint_name: format!("{}_fk_{}", source, id),
+          source_table: source.clone(),
+          source_column: column.clone(),
+          target_table: referenced.to_string(),
+          target_column: to,
+          on_update: on_update.clone(),
+          on_delete: on_delete.clone(),
+        });
+      }
+      Ok(group_foreign_keys(grouped))
+    }
+  }
+}
+
+/// Foreign keys declared on `table`, with the tables and columns they reference.
+pub async fn foreign_keys(
+  pool: &SqlPool,
+  schema: &str,
+  table: &str,
+) -> Result<Vec<ForeignKey>, String> {
+  let mut keys = foreign_keys_involving(pool, schema, table).await?;
+  keys.retain(|key| key.table == table);
+  Ok(keys)
+}
+
+/// How the grid should edit a column, from its declared type.
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ColumnKind {
+  Integer,
+  /// Exact numbers (`decimal`, `numeric`, `money`).
+  Decimal,
+  Float,
+  Boolean,
+  Date,
+  Time,
+  DateTime,
+  Json,
+  Binary,
+  Text,
+}
+
+/// Grid-facing kind of a declared type such as `bigint unsigned`, `timestamp with time zone`
+/// or `VARCHAR(20)`. Unknown types are edited as text.
+pub fn column_kind(data_type: &str) -> ColumnKind {
+  let lower = data_type.to_lowercase();
+  let base = lower.split(['(', ' ']).next().unwrap_or_default();
+  match base {
+    // MySQL's BOOLEAN is an alias of tinyint(1)
+    "tinyint" if lower.starts_with("tinyint(1)") => ColumnKind::Boolean,
+    "bool" | "boolean" | "bit" if !lower.starts_with("bit(") || lower == "bit(1)" => {
+      ColumnKind::Boolean
+    }
+    "int" | "integer" | "tinyint" | "smallint" | "mediumint" | "bigint" | "int2" | "int4"
+    | "int8" | "serial" | "bigserial" | "smallserial" | "year" => ColumnKind::Integer,
+    "decimal" | "numeric" | "money" | "dec" | "fixed" => ColumnKind::Decimal,
+    "float" | "double" | "real" | "float4" | "float8" => ColumnKind::Float,
+    "date" => ColumnKind::Date,
+    "time" | "timetz" => ColumnKind::Time,
+    "datetime" | "timestamp" | "timestamptz" => ColumnKind::DateTime,
+    "json" | "jsonb" => ColumnKind::Json,
+    "blob" | "tinyblob" | "mediumblob" | "longblob" | "binary" | "varbinary" | "bytea" => {
+      ColumnKind::Binary
+    }
+    _ => ColumnKind::Text,
+  }
+}
+
+/// A column of a table as the grid needs it to pick and fill an editor.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TableColumn {
+  pub name: String,
+  /// Declared type as the engine reports it.
+  pub data_type: String,
+  pub kind: ColumnKind,
+  pub nullable: bool,
+  /// Default expression, e.g. `0`, `CURRENT_TIMESTAMP` or `nextval('users_id_seq'::regclass)`.
+  pub default: Option<String>,
+  /// Filled in by the database on insert (`AUTO_INCREMENT`, identity/serial, SQLite rowid).
+  pub auto_increment: bool,
+  pub primary_key: bool,
+}
+
+/// Columns of `table` in declaration order with their types, defaults and key membership.
+/// `schema` is the table's Postgres schema.
+pub async fn table_schema(
+  pool: &SqlPool,
+  schema: &str,
+  table: &str,
+) -> Result<Vec<TableColumn>, String> {
+  // name, data_type, nullable, default, auto_increment
+  let rows: Vec<(String, String, bool, Option<String>, bool)> = match pool {
+    SqlPool::MySql(mysql) => {
+      let rows: Vec<(String, String, i64, Option<String>, i64)> = sqlx::query_as(
+        "SELECT CAST(COLUMN_NAME AS CHAR), CAST(COLUMN_TYPE AS CHAR), \
+         CAST(IS_NULLABLE = 'YES' AS SIGNED), CAST(COLUMN_DEFAULT AS CHAR), \
+         CAST(EXTRA LIKE '%auto_increment%' AS SIGNED) \
+         FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? \
+         ORDER BY ORDINAL_POSITION",
+      )
+      .bind(table)
+      .fetch_all(mysql)
+      .await
+      .map_err(|e| e.to_string())?;
+      rows
+        .into_iter()
+        .map(|(name, data_type, nullable, default, auto_increment)| {
+          (name, data_type, nullable != 0, default, auto_increment != 0)
+        })
+        .collect()
+    }
+    SqlPool::Postgres(pg) => sqlx::query_as(
+      "SELECT column_name::text, \
+       CASE WHEN data_type IN ('USER-DEFINED', 'ARRAY') THEN udt_name::text \
+       ELSE data_type::text END, \
+       is_nullable = 'YES', column_default::text, \
+       (is_identity = 'YES' OR coalesce(column_default LIKE 'nextval(%', false)) \
+       FROM information_schema.columns WHERE table_schema = $2 AND table_name = $1 \
+       ORDER BY ordinal_position",
+    )
+    .bind(table)
+    .bind(schema)
+    .fetch_all(pg)
+    .await
+    .map_err(|e| e.to_string())?,
+    SqlPool::Sqlite(sqlite) => {
+      let rows: Vec<(String, String, bool, Option<String>, i64)> = sqlx::query_as(
+        "SELECT name, type, \"notnull\" = 0, dflt_value, pk FROM pragma_table_info(?) ORDER BY cid",
+      )
+      .bind(table)
+      .fetch_all(sqlite)
+      .await
+      .map_err(|e| e.to_string())?;
+      // Only a lone INTEGER PRIMARY KEY aliases the rowid, which SQLite assigns
+      let single_key = rows.iter().filter(|row| row.4 > 0).count() == 1;
+      rows
+        .into_iter()
+        .map(|(name, data_type, nullable, default, pk)| {
+          let rowid = single_key && pk > 0 && data_type.eq_ignore_ascii_case("integer");
+          (name, data_type, nullable, default, rowid)
+        })
+        .collect()
+    }
+  };
+  let key = primary_key(pool, schema, table).await?;
+  Ok(
+    rows
+      .into_iter()
+      .map(
+        |(name, data_type, nullable, default, auto_increment)| TableColumn {
+          kind: column_kind(&data_type),
+          primary_key: key.contains(&name),
+          name,
+          data_type,
+          nullable,
+          default,
+          auto_increment,
+        },
+      )
+      .collect(),
+  )
+}
+
+/// An index of a table as the Indexes tab lists it.
+#[derive(serde::Serialize, sqlx::FromRow, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexInfo {
+  pub name: String,
+  /// Key columns in index order; expressions as their SQL text, or `(expression)` where the
+  /// engine doesn't report it.
+  pub columns: Vec<String>,
+  pub unique: bool,
+  pub primary: bool,
+  /// Access method or structure, e.g. `BTREE`, `HASH`, `FULLTEXT`, `gin` or `btree`.
+  pub method: String,
+  /// On-disk size; `None` when the engine doesn't expose it to this user.
+  pub size_bytes: Option<i64>,
+  /// `CREATE INDEX` statement, where the engine keeps one (Postgres, SQLite).
+  pub definition: Option<String>,
+}
+
+const EXPRESSION: &str = "(expression)";
+
+/// Indexes of `table` ordered by name, primary key included. `schema` is the table's
+/// Postgres schema.
+pub async fn indexes(pool: &SqlPool, schema: &str, table: &str) -> Result<Vec<IndexInfo>, String> {
+  match pool {
+    SqlPool::MySql(mysql) => {
+      let rows: Vec<(String, Option<String>, i64, String)> = sqlx::query_as(
+        "SELECT CAST(INDEX_NAME AS CHAR), CAST(COLUMN_NAME AS CHAR), \
+         CAST(NON_UNIQUE AS SIGNED), CAST(INDEX_TYPE AS CHAR) \
+         FROM information_schema.STATISTICS \
+         WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? \
+         ORDER BY INDEX_NAME, SEQ_IN_INDEX",
+      )
+      .bind(table)
+      .fetch_all(mysql)
+      .await
+      .map_err(|e| e.to_string())?;
+      // Needs read access to the mysql schema, which plain users often lack
+      let sizes: HashMap<String, i64> = sqlx::query_as(
+        "SELECT CAST(index_name AS CHAR), CAST(stat_value * @@innodb_page_size AS SIGNED) \
+         FROM mysql.innodb_index_stats \
+         WHERE database_name = DATABASE() AND table_name = ? AND stat_name = 'size'",
+      )
+      .bind(table)
+      .fetch_all(mysql)
+      .await
+      .map(|rows: Vec<(String, i64)>| rows.into_iter().collect())
+      .unwrap_or_default();
+      let mut indexes: Vec<IndexInfo> = Vec::new();
+      for (name, column, non_unique, method) in rows {
+        let column = column.unwrap_or_else(|| EXPRESSION.to_string());
+        match indexes.last_mut() {
+          Some(index) if index.name == name => index.columns.push(column),
+          _ => indexes.push(IndexInfo {
+            columns: vec![column],
+            unique: non_unique == 0,
+            primary: name == "PRIMARY",
+            method,
+            size_bytes: sizes.get(&name).copied(),
+            definition: None,
+            name,
+          }),
+        }
+      }
+      Ok(indexes)
+    }
+    SqlPool::Postgres(pg) => sqlx::query_as(
+      "SELECT c.relname::text AS name, \
+       ARRAY(SELECT pg_get_indexdef(i.indexrelid, k, true) \
+       FROM generate_series(1, i.indnkeyatts) k ORDER BY k)::text[] AS columns, \
+       i.indisunique AS unique, i.indisprimary AS primary, am.amname::text AS method, \
+       pg_relation_size(i.indexrelid) AS size_bytes, \
+       pg_get_indexdef(i.indexrelid) AS definition \
+       FROM pg_index i JOIN pg_class c ON c.oid = i.indexrelid \
+       JOIN pg_am am ON am.oid = c.relam \
+       WHERE i.indrelid = to_regclass($1) ORDER BY c.relname",
+    )
+    .bind(pool.table_ref_in(schema, table))
+    .fetch_all(pg)
+    .await
+    .map_err(|e| e.to_string()),
+    SqlPool::Sqlite(sqlite) => {
+      let list: Vec<(String, bool, String, Option<String>)> = sqlx::query_as(
+        "SELECT l.name, l.\"unique\", l.origin, m.sql FROM pragma_index_list(?) l \
+         LEFT JOIN sqlite_master m ON m.type = 'index' AND m.name = l.name ORDER BY l.name",
+      )
+      .bind(table)
+      .fetch_all(sqlite)
+      .await
+      .map_err(|e| e.to_string())?;
+      let mut indexes = Vec::with_capacity(list.len());
+      for (name, unique, origin, definition) in list {
+        let columns: Vec<(Option<String>,)> =
+          sqlx::query_as("SELECT name FROM pragma_index_info(?) ORDER BY seqno")
+            .bind(&name)
+            .fetch_all(sqlite)
+            .await
+            .map_err(|e| e.to_string())?;
+        // dbstat is an optional compile-time feature of SQLite
+        let size: Option<(Option<i64>,)> =
+          sqlx::query_as("SELECT SUM(pgsize) FROM dbstat WHERE name = ?")
+            .bind(&name)
+            .fetch_optional(sqlite)
+            .await
+            .unwrap_or(None);
+        indexes.push(IndexInfo {
+          columns: columns
+            .into_iter()
+            .map(|(column,)| column.unwrap_or_else(|| EXPRESSION.to_string()))
+            .collect(),
+          unique,
+          primary: origin == "pk",
+          method: "btree".to_string(),
+          size_bytes: size.and_then(|(size,)| size),
+          definition,
+          name,
+        });
+      }
+      Ok(indexes)
+    }
+  }
+}
@@ -0,0 +1,115 @@
+//! Cache of the table, column and primary key names the sidebar and grid ask for on every page
+//! load, so they don't cost a round trip to `information_schema` each time on a slow link.
+//!
+//! Entries expire after [`TTL`] and are dropped when the connection is opened or closed, when
+//! DDL runs through the app, or on [`refresh_schema_cache`] for changes made elsewhere.
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+use std::time::{Duration, Instant};
+
+use tauri::State;
+
+use crate::AppState;
+
+/// How long names are served from the cache before they are read again.
+const TTL: Duration = Duration::from_mins(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Kind {
+  Tables,
+  Columns,
+  PrimaryKey,
+}
+
+/// Connection, kind, and table (empty for [`Kind::Tables`]; Postgres tables outside `public`
+/// are schema-qualified).
+type Key = (String, Kind, String);
+
+#[derive(Default)]
+pub struct SchemaCache {
+  entries: HashMap<Key, (Instant, Vec<String>)>,
+}
+
+/// The names cached for `table` on `connection`, unless missing or older than [`TTL`].
+pub fn get(state: &AppState, connection: &str, kind: Kind, table: &str) -> Option<Vec<String>> {
+  let cache = state
+    .schema_cache
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let (stored, names) = cache
+    .entries
+    .get(&(connection.to_string(), kind, table.to_string()))?;
+  (stored.elapsed() < TTL).then(|| names.clone())
+}
+
+/// Caches `names` and hands them back.
+pub fn insert(
+  state: &AppState,
+  connection: &str,
+  kind: Kind,
+  table: &str,
+  names: Vec<String>,
+) -> Vec<String> {
+  state
+    .schema_cache
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .entries
+    .insert(
+      (connection.to_string(), kind, table.to_string()),
+      (Instant::now(), names.clone()),
+    );
+  names
+}
+
+/// Drops what is cached for `connection`: everything, or only the names of `table` (and the
+/// table list, which a renamed or dropped table changes too).
+pub fn invalidate(state: &AppState, connection: &str, table: Option<&str>) {
+  state
+    .schema_cache
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .entries
+    .retain(|(id, kind, name), _| {
+      id != connection || table.is_some_and(|table| *kind != Kind::Tables && name != table)
+    });
+}
+
+/// Whether `sql` changes the schema, judged by its leading keyword.
+fn is_ddl(sql: &str) -> bool {
+  let first = sql
+    .trim_start()
+    .split(|c: char| !c.is_ascii_alphabetic())
+    .next()
+    .unwrap_or("");
+  ["CREATE", "ALTER", "DROP", "RENAME", "COMMENT"]
+    .iter()
+    .any(|keyword| first.eq_ignore_ascii_case(keyword))
+}
+
+/// Drops the cache of `connection` after `sql` ran on it if it was DDL.
+pub fn note_statement(state: &AppState, connection: &str, sql: &str) {
+  if is_ddl(sql) {
+    invalidate(state, connection, None);
+  }
+}
+
+/// Drops cached schema names so the next listing reads them from the server: those of
+/// `table_name` on `connection_id`, everything of `connection_id`, or of every connection.
+#[tauri::command]
+pub fn refresh_schema_cache(
+  state: State<'_, AppState>,
+  connection_id: Option<String>,
+  table_name: Option<String>,
+) {
+  match connection_id {
+    Some(connection) => invalidate(&state, &connection, table_name.as_deref()),
+    None => state
+      .schema_cache
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .entries
+      .clear(),
+  }
+}
@@ -0,0 +1,635 @@
+//! Script mode for the SQL console: a pasted `.sql` file is split into statements and they
+//! run one after another on a single connection, optionally inside one transaction, with a
+//! status, timing and row counts per statement.
+//!
+//! The splitter only cuts at delimiters outside of strings, quoted identifiers and comments,
+//! and knows each dialect's extras:
+//! - MySQL: `#` comments, backslash escapes, and `DELIMITER` lines as in the `mysql` client,
+//!   for procedure and trigger bodies;
+//! - Postgres: dollar-quoted bodies (`$$ ... $$`, `$fn$ ... $fn$`), `E'...'` escapes and
+//!   nested block comments;
+//! - SQLite: `[bracketed]` identifiers and `CREATE TRIGGER ... BEGIN ... END` bodies.
+//!
+//! Statements are sent as plain text (no prepared statements), so anything the server
+//! accepts runs, but `{{variables}}` aren't substituted.
+
+use std::time::Instant;
+
+use futures::TryStreamExt;
+use tauri::State;
+
+use crate::db::{self, SqlPool};
+use crate::ident::Dialect;
+use crate::{audit, history, readonly, sql_kind, AppState};
+
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptStatement {
+  pub sql: String,
+  /// 1-based line of the script the statement starts on.
+  pub line: usize,
+}
+
+struct Splitter<'a> {
+  dialect: Dialect,
+  text: &'a str,
+  pos: usize,
+  line: usize,
+  delimiter: String,
+  out: Vec<ScriptStatement>,
+  start: usize,
+  /// Line of the first character of the current statement that isn't blank or a comment.
+  content_line: Option<usize>,
+  /// Leading words of the current statement, upper-cased, to spot trigger definitions.
+  words: Vec<String>,
+  /// Open `BEGIN`/`CASE` blocks inside a SQLite trigger body.
+  depth: usize,
+}
+
+impl<'a> Splitter<'a> {
+  fn rest(&self) -> &'a str {
+    &self.text[self.pos..]
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.rest().chars().next()
+  }
+
+  fn bump(&mut self) -> Option<char> {
+    let c = self.peek()?;
+    self.pos += c.len_utf8();
+    if c == '\n' {
+      self.line += 1;
+    }
+    Some(c)
+  }
+
+  /// Notes that the current statement has begun; comments before it are left out.
+  fn mark_content(&mut self) {
+    if self.content_line.is_none() {
+      self.content_line = Some(self.line);
+      self.start = self.pos;
+    }
+  }
+
+  fn finish(&mut self, end: usize) {
+    if let Some(line) = self.content_line.take() {
+      let sql = self.text[self.start..end].trim();
+      self.out.push(ScriptStatement {
+        sql: sql.to_string(),
+        line,
+      });
+    }
+    self.start = self.pos;
+    self.words.clear();
+    self.depth = 0;
+  }
+
+  fn skip_line(&mut self) {
+    while let Some(c) = self.bump() {
+      if c == '\n' {
+        break;
+      }
+    }
+  }
+
+  /// Skips a quoted string or identifier whose opening `quote` was just read.
+  fn skip_quoted(&mut self, quote: char, backslash_escapes: bool) {
+    while let Some(c) = self.bump() {
+      if backslash_escapes && c == '\\' {
+        self.bump();
+      } else if c == quote {
+        // A doubled quote stands for itself
+        if self.peek() == Some(quote) {
+          self.bump();
+        } else {
+          return;
+        }
+      }
+    }
+  }
+
+  /// Skips a block comment whose `/*` was just read; Postgres nests them.
+  fn skip_block_comment(&mut self) {
+    let mut depth = 1;
+    while let Some(c) = self.bump() {
+      if c == '*' && self.peek() == Some('/') {
+        self.bump();
+        depth -= 1;
+        if depth == 0 {
+          return;
+        }
+      } else if c == '/' && self.peek() == Some('*') && self.dialect == Dialect::Postgres {
+        self.bump();
+        depth += 1;
+      }
+    }
+  }
+
+  /// Tag of a dollar quote (`$$`, `$body$`) starting at the current position, if any.
+  fn dollar_tag(&self) -> Option<&'a str> {
+    let rest = self.rest();
+    let after = &rest[1..];
+    let end = after.find('$')?;
+    let tag = &after[..end];
+    let valid = tag
+      .chars()
+      .enumerate()
+      .all(|(i, c)| c == '_' || c.is_alphabetic() || (i > 0 && c.is_ascii_digit()));
+    valid.then(|| &rest[..end + 2])
+  }
+
+  /// `DELIMITER x` at the start of a statement (MySQL client syntax): switches delimiters.
+  fn delimiter_command(&mut self) -> bool {
+    let rest = self.rest();
+    let is_command = rest.len() > 9
+      && rest[..9].eq_ignore_ascii_case("delimiter")
+      && rest[9..].starts_with([' ', '\t']);
+    if !is_command {
+      return false;
+    }
+    let line = rest.lines().next().unwrap_or_default();
+    let delimiter = line[9..].trim();
+    if !delimiter.is_empty() {
+      self.delimiter = delimiter.to_string();
+    }
+    self.skip_line();
+    self.start = self.pos;
+    true
+  }
+
+  fn is_trigger(&self) -> bool {
+    let words: Vec<&str> = self.words.iter().map(String::as_str).collect();
+    matches!(
+      words.as_slice(),
+      ["CREATE", "TRIGGER", ..] | ["CREATE", "TEMP" | "TEMPORARY", "TRIGGER", ..]
+    )
+  }
+
+  fn word(&mut self, word: &str) {
+    let upper = word.to_ascii_uppercase();
+    if self.words.len() < 3 {
+      self.words.push(upper.clone());
+    }
+    if self.dialect == Dialect::Sqlite && self.is_trigger() {
+      match upper.as_str() {
+        "BEGIN" | "CASE" => self.depth += 1,
+        "END" => self.depth = self.depth.saturating_sub(1),
+        _ => {}
+      }
+    }
+  }
+
+  fn run(mut self) -> Vec<ScriptStatement> {
+    while let Some(c) = self.peek() {
+      if self.content_line.is_none() && self.dialect == Dialect::MySql && self.delimiter_command() {
+        continue;
+      }
+      if self.rest().starts_with(self.delimiter.as_str()) && self.depth == 0 {
+        let end = self.pos;
+        for _ in 0..self.delimiter.chars().count() {
+          self.bump();
+        }
+        self.finish(end);
+        continue;
+      }
+      let rest = self.rest();
+      if let Some(after) = rest.strip_prefix("--") {
+        // MySQL wants whitespace after the dashes, so `1--1` is arithmetic there
+        let comment =
+          self.dialect != Dialect::MySql || after.chars().next().is_none_or(char::is_whitespace);
+        if comment {
+          self.skip_line();
+          continue;
+        }
+      }
+      if c == '#' && self.dialect == Dialect::MySql {
+        self.skip_line();
+        continue;
+      }
+      if rest.starts_with("/*") {
+        self.pos += 2;
+        self.skip_block_comment();
+        continue;
+      }
+      if c.is_whitespace() {
+        self.bump();
+        continue;
+      }
+
+      self.mark_content();
+      if c == '$' && self.dialect == Dialect::Postgres {
+        if let Some(tag) = self.dollar_tag() {
+          self.pos += tag.len();
+          match self.rest().find(tag) {
+            Some(end) => {
+              for _ in self.rest()[..end + tag.len()].chars() {
+                self.bump();
+              }
+            }
+            None => while self.bump().is_some() {},
+          }
+          continue;
+        }
+      }
+      if c.is_alphabetic() || c == '_' {
+        let len = rest
+          .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+          .unwrap_or(rest.len());
+        let word = &rest[..len];
+        self.pos += len;
+        // E'...' takes backslash escapes in Postgres
+        if self.dialect == Dialect::Postgres
+          && word.eq_ignore_ascii_case("e")
+          && self.peek() == Some('\'')
+        {
+          self.bump();
+          self.skip_quoted('\'', true);
+        } else {
+          self.word(word);
+        }
+        continue;
+      }
+      self.bump();
+      match c {
+        '\'' => self.skip_quoted('\'', self.dialect == Dialect::MySql),
+        '"' => self.skip_quoted('"', self.dialect == Dialect::MySql),
+        '`' if self.dialect != Dialect::Postgres => self.skip_quoted('`', false),
+        '[' if self.dialect == Dialect::Sqlite => self.skip_quoted(']', false),
+        _ => {}
+      }
+    }
+    let end = self.text.len();
+    self.finish(end);
+    self.out
+  }
+}
+
+/// Splits `script` into statements, dropping empty ones and the delimiters between them.
+pub fn split(dialect: Dialect, script: &str) -> Vec<ScriptStatement> {
+  Splitter {
+    dialect,
+    text: script,
+    pos: 0,
+    line: 1,
+    delimiter: ";".to_string(),
+    out: Vec::new(),
+    start: 0,
+    content_line: None,
+    words: Vec::new(),
+    depth: 0,
+  }
+  .run()
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum StatementStatus {
+  Ok,
+  Failed,
+  /// Not run because an earlier statement failed.
+  Skipped,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementResult {
+  pub index: usize,
+  pub line: usize,
+  pub sql: String,
+  pub status: StatementStatus,
+  pub elapsed_ms: u64,
+  pub rows_affected: u64,
+  pub rows_returned: u64,
+  pub error: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptResult {
+  pub statements: Vec<StatementResult>,
+  /// Whether the script ran in a transaction that was committed; `false` after a rollback
+  /// and when no transaction was asked for.
+  pub committed: bool,
+}
+
+/// Runs `statements` in order on `conn`, stopping at the first failure when `stop_on_error`
+/// is set; `affected` reads the row count of a result. Returns whether all of them ran.
+async fn run_on<DB>(
+  conn: &mut DB::Connection,
+  statements: &[ScriptStatement],
+  stop_on_error: bool,
+  affected: fn(&DB::QueryResult) -> u64,
+  results: &mut Vec<StatementResult>,
+) -> bool
+where
+  DB: sqlx::Database,
+  for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+{
+  let mut all_ok = true;
+  for (index, statement) in statements.iter().enumerate() {
+    let mut result = StatementResult {
+      index,
+      line: statement.line,
+      sql: statement.sql.clone(),
+      status: StatementStatus::Skipped,
+      elapsed_ms: 0,
+      rows_affected: 0,
+      rows_returned: 0,
+      error: None,
+    };
+    if !all_ok && stop_on_error {
+      results.push(result);
+      continue;
+    }
+    let started = Instant::now();
+    let mut stream = sqlx::raw_sql(&statement.sql).fetch_many(&mut *conn);
+    let outcome: Result<(), sqlx::Error> = async {
+      while let Some(item) = stream.try_next().await? {
+        match item {
+          sqlx::Either::Left(done) => result.rows_affected += affected(&done),
+          sqlx::Either::Right(_) => result.rows_returned += 1,
+        }
+      }
+      Ok(())
+    }
+    .await;
+    drop(stream);
+    result.elapsed_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    match outcome {
+      Ok(()) => result.status = StatementStatus::Ok,
+      Err(e) => {
+        result.status = StatementStatus::Failed;
+        result.error = Some(e.to_string());
+        all_ok = false;
+      }
+    }
+    results.push(result);
+  }
+  all_ok
+}
+
+/// Runs a multi-statement script on `connection`. In a `transaction` (off by default) the
+/// first failure stops the script and rolls everything back; otherwise `stop_on_error`
+/// (on by default) decides whether the statements after a failure still run. MySQL commits
+/// implicitly around most DDL, so there only DML is rolled back.
+#[tauri::command]
+pub async fn execute_script(
+  state: State<'_, AppState>,
+  connection: String,
+  sql: String,
+  transaction: Option<bool>,
+  stop_on_error: Option<bool>,
+) -> Result<ScriptResult, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let statements = split(pool.dialect(), &sql);
+  if statements.is_empty() {
+    return Err("The script has no statements".to_string());
+  }
+  // Refuse the whole script up front rather than stopping halfway through it
+  for statement in &statements {
+    readonly::check_statement(&state, &connection, &statement.sql)?;
+  }
+  let transaction = transaction.unwrap_or(false);
+  let stop_on_error = transaction || stop_on_error.unwrap_or(true);
+
+  let mut results = Vec::with_capacity(statements.len());
+  let mut committed = false;
+  let e = |e: sqlx::Error| e.to_string();
+  match &pool {
+    SqlPool::MySql(pool) => {
+      let mut conn = pool.acquire().await.map_err(e)?;
+      let affected = |r: &sqlx::mysql::MySqlQueryResult| r.rows_affected();
+      if transaction {
+        let mut tx = sqlx::Connection::begin(&mut *conn).await.map_err(e)?;
+        if run_on::<sqlx::MySql>(&mut *tx, &statements, true, affected, &mut results).await {
+          tx.commit().await.map_err(e)?;
+          committed = true;
+        } else {
+          tx.rollback().await.map_err(e)?;
+        }
+      } else {
+        run_on::<sqlx::MySql>(
+          &mut *conn,
+          &statements,
+          stop_on_error,
+          affected,
+          &mut results,
+        )
+        .await;
+      }
+    }
+    SqlPool::Postgres(pool) => {
+      let mut conn = pool.acquire().await.map_err(e)?;
+      let affected = |r: &sqlx::postgres::PgQueryResult| r.rows_affected();
+      if transaction {
+        let mut tx = sqlx::Connection::begin(&mut *conn).await.map_err(e)?;
+        if run_on::<sqlx::Postgres>(&mut *tx, &statements, true, affected, &mut results).await {
+          tx.commit().await.map_err(e)?;
+          committed = true;
+        } else {
+          tx.rollback().await.map_err(e)?;
+        }
+      } else {
+        run_on::<sqlx::Postgres>(
+          &mut *conn,
+          &statements,
+          stop_on_error,
+          affected,
+          &mut results,
+        )
+        .await;
+      }
+    }
+    SqlPool::Sqlite(pool) => {
+      let mut conn = pool.acquire().await.map_err(e)?;
+      let affected = |r: &sqlx::sqlite::SqliteQueryResult| r.rows_affected();
+      if transaction {
+        let mut tx = sqlx::Connection::begin(&mut *conn).await.map_err(e)?;
+        if run_on::<sqlx::Sqlite>(&mut *tx, &statements, true, affected, &mut results).await {
+          tx.commit().await.map_err(e)?;
+          committed = true;
+        } else {
+          tx.rollback().await.map_err(e)?;
+        }
+      } else {
+        run_on::<sqlx::Sqlite>(
+          &mut *conn,
+          &statements,
+          stop_on_error,
+          affected,
+          &mut results,
+        )
+        .await;
+      }
+    }
+  }
+
+  let mut written = Vec::new();
+  let mut rows_affected = 0;
+  for result in &results {
+    if result.status == StatementStatus::Skipped {
+      continue;
+    }
+    let outcome = match &result.error {
+      Some(error) => Err(error.as_str()),
+      None => Ok(result.rows_affected.max(result.rows_returned)),
+    };
+    history::record(
+      &state,
+      &connection,
+      &result.sql,
+      std::time::Duration::from_millis(result.elapsed_ms),
+      outcome,
+    )
+    .await;
+    // Rolled-back writes never happened
+    let kept = result.status == StatementStatus::Ok && (committed || !transaction);
+    if kept && sql_kind::is_write(&result.sql) {
+      written.push(result.sql.clone());
+      rows_affected += result.rows_affected;
+    }
+  }
+  if !written.is_empty() {
+    audit::record_batch(&state, &connection, &written, rows_affected).await;
+  }
+  Ok(ScriptResult {
+    statements: results,
+    committed,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sql(dialect: Dialect, script: &str) -> Vec<String> {
+    split(dialect, script).into_iter().map(|s| s.sql).collect()
+  }
+
+  #[test]
+  fn splits_at_delimiters_with_start_lines() {
+    let statements = split(
+      Dialect::Postgres,
+      "SELECT 1;\n\n-- next\nSELECT\n  2;;\n  SELECT 3",
+    );
+    assert_eq!(
+      statements,
+      vec![
+        ScriptStatement {
+          sql: "SELECT 1".to_string(),
+          line: 1
+        },
+        ScriptStatement {
+          sql: "SELECT\n  2".to_string(),
+          line: 4
+        },
+        ScriptStatement {
+          sql: "SELECT 3".to_string(),
+          line: 6
+        },
+      ]
+    );
+    assert!(split(Dialect::Sqlite, " ; -- only a comment\n").is_empty());
+  }
+
+  #[test]
+  fn delimiters_inside_quotes_do_not_split() {
+    assert_eq!(
+      sql(
+        Dialect::Sqlite,
+        "SELECT 'a;b', \"c;d\", [e;f], `g;h`; SELECT 'it''s;'"
+      ),
+      ["SELECT 'a;b', \"c;d\", [e;f], `g;h`", "SELECT 'it''s;'"]
+    );
+    // Backslashes escape quotes in MySQL strings only
+    assert_eq!(
+      sql(Dialect::MySql, r"SELECT 'a\';b'; SELECT 2"),
+      [r"SELECT 'a\';b'", "SELECT 2"]
+    );
+    assert_eq!(
+      sql(Dialect::Postgres, r"SELECT 'a\'; SELECT 2"),
+      [r"SELECT 'a\'", "SELECT 2"]
+    );
+    assert_eq!(
+      sql(Dialect::Postgres, r"SELECT E'a\';b'; SELECT 2"),
+      [r"SELECT E'a\';b'", "SELECT 2"]
+    );
+  }
+
+  #[test]
+  fn delimiters_inside_comments_do_not_split() {
+    assert_eq!(
+      sql(Dialect::MySql, "SELECT 1 # x;\n; SELECT 2 /* ; */"),
+      ["SELECT 1 # x;", "SELECT 2 /* ; */"]
+    );
+    assert_eq!(
+      sql(Dialect::Postgres, "SELECT 1 /* a /* ; */ ; */; SELECT 2"),
+      ["SELECT 1 /* a /* ; */ ; */", "SELECT 2"]
+    );
+    // MySQL needs whitespace after `--`, and its comments don't nest
+    assert_eq!(
+      sql(Dialect::MySql, "SELECT 1--1; SELECT 2"),
+      ["SELECT 1--1", "SELECT 2"]
+    );
+    assert_eq!(
+      sql(Dialect::MySql, "SELECT 1 /* a /* */; SELECT 2"),
+      ["SELECT 1 /* a /* */", "SELECT 2"]
+    );
+  }
+
+  #[test]
+  fn mysql_delimiter_command() {
+    let script = "DELIMITER //\n\
+                  CREATE PROCEDURE p() BEGIN SELECT 1; SELECT 2; END//\n\
+                  DELIMITER ;\n\
+                  CALL p();";
+    assert_eq!(
+      split(Dialect::MySql, script),
+      vec![
+        ScriptStatement {
+          sql: "CREATE PROCEDURE p() BEGIN SELECT 1; SELECT 2; END".to_string(),
+          line: 2
+        },
+        ScriptStatement {
+          sql: "CALL p()".to_string(),
+          line: 4
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn postgres_dollar_quoted_bodies() {
+    let script = "CREATE FUNCTION f() RETURNS int AS $fn$ SELECT 1; $$ $fn$ LANGUAGE sql;\n\
+                  DO $$ BEGIN PERFORM 1; END $$; SELECT $1";
+    assert_eq!(
+      sql(Dialect::Postgres, script),
+      [
+        "CREATE FUNCTION f() RETURNS int AS $fn$ SELECT 1; $$ $fn$ LANGUAGE sql",
+        "DO $$ BEGIN PERFORM 1; END $$",
+        "SELECT $1"
+      ]
+    );
+  }
+
+  #[test]
+  fn sqlite_trigger_bodies() {
+    let script = "CREATE TRIGGER t AFTER INSERT ON a BEGIN\n\
+                  \x20 UPDATE b SET n = CASE WHEN n > 0 THEN n END;\n\
+                  \x20 DELETE FROM c;\n\
+                  END; SELECT 1";
+    assert_eq!(
+      sql(Dialect::Sqlite, script),
+      [
+        "CREATE TRIGGER t AFTER INSERT ON a BEGIN\n  UPDATE b SET n = CASE WHEN n > 0 THEN n END;\n  DELETE FROM c;\nEND",
+        "SELECT 1"
+      ]
+    );
+    // Elsewhere `BEGIN` is a statement of its own
+    assert_eq!(
+      sql(Dialect::Sqlite, "BEGIN; SELECT 1; END"),
+      ["BEGIN", "SELECT 1", "END"]
+    );
+  }
+}
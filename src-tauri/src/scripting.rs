@@ -0,0 +1,372 @@
+//! Optional local HTTP endpoint for scripts and Stream Deck-style tools: connection status,
+//! read-only queries, saved exports and templates of the running app.
+//!
+//! The listener binds to 127.0.0.1 only, is off until started, and every request must carry
+//! `Authorization: Bearer <token>` with the token handed out when it was started. Requests
+//! must also name the listener itself as `Host`, which keeps DNS-rebinding pages out.
+//!
+//! Routes (JSON in and out):
+//! - `GET /status`: app version and active workspace
+//! - `GET /connections`: health of the open connections
+//! - `POST /query` `{connection, sql}`: rows of a statement that doesn't write, run in a
+//!   read-only transaction
+//! - `POST /export` `{connection, destinationId, table | query, format?, compression?}`
+//! - `POST /templates/run` `{connection, templateId, params}`
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::{
+  db, destinations, encoding, health, masking, sql_kind, templates, timezone, workspaces, AppState,
+};
+
+const DEFAULT_PORT: u16 = 7878;
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+/// Rows `/query` returns at most; `truncated` tells when more were left out.
+const MAX_QUERY_ROWS: usize = 10_000;
+
+pub struct Server {
+  port: u16,
+  token: String,
+  task: tauri::async_runtime::JoinHandle<()>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+  pub running: bool,
+  pub port: Option<u16>,
+  pub token: Option<String>,
+}
+
+struct Request {
+  method: String,
+  path: String,
+  headers: HashMap<String, String>,
+  body: Vec<u8>,
+}
+
+type Reply = Result<serde_json::Value, (u16, String)>;
+
+fn bad_request(e: impl std::fmt::Display) -> (u16, String) {
+  (400, e.to_string())
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request, (u16, String)> {
+  let mut buf = Vec::new();
+  let mut chunk = [0u8; 4096];
+  let head_end = loop {
+    if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+      break i;
+    }
+    if buf.len() > MAX_HEAD_BYTES {
+      return Err((431, "Request headers too large".to_string()));
+    }
+    let n = stream.read(&mut chunk).await.map_err(bad_request)?;
+    if n == 0 {
+      return Err(bad_request("Incomplete request"));
+    }
+    buf.extend_from_slice(&chunk[..n]);
+  };
+  let head = String::from_utf8_lossy(&buf[..head_end]).into_owned();
+  let mut lines = head.split("\r\n");
+  let mut request_line = lines.next().unwrap_or("").split_whitespace();
+  let method = request_line.next().unwrap_or("").to_string();
+  let path = request_line.next().unwrap_or("").to_string();
+  let headers: HashMap<String, String> = lines
+    .filter_map(|line| line.split_once(':'))
+    .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+    .collect();
+
+  let length: usize = match headers.get("content-length") {
+    Some(value) => value.parse().map_err(bad_request)?,
+    None => 0,
+  };
+  if length > MAX_BODY_BYTES {
+    return Err((413, "Request body too large".to_string()));
+  }
+  let mut body = buf[head_end + 4..].to_vec();
+  while body.len() < length {
+    let n = stream.read(&mut chunk).await.map_err(bad_request)?;
+    if n == 0 {
+      return Err(bad_request("Incomplete request body"));
+    }
+    body.extend_from_slice(&chunk[..n]);
+  }
+  body.truncate(length);
+  Ok(Request {
+    method,
+    path,
+    headers,
+    body,
+  })
+}
+
+async fn write_response(stream: &mut TcpStream, status: u16, body: &serde_json::Value) {
+  let reason = match status {
+    200 => "OK",
+    400 => "Bad Request",
+    401 => "Unauthorized",
+    403 => "Forbidden",
+    404 => "Not Found",
+    405 => "Method Not Allowed",
+    413 => "Payload Too Large",
+    431 => "Request Header Fields Too Large",
+    _ => "Internal Server Error",
+  };
+  let body = body.to_string();
+  let response = format!(
+    "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+     Connection: close\r\n\r\n{}",
+    status,
+    reason,
+    body.len(),
+    body
+  );
+  let _ = stream.write_all(response.as_bytes()).await;
+  let _ = stream.shutdown().await;
+}
+
+/// Compares without stopping at the first difference, so timing doesn't leak the token.
+fn same_token(given: &str, expected: &str) -> bool {
+  given.len() == expected.len()
+    && given
+      .bytes()
+      .zip(expected.bytes())
+      .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+      == 0
+}
+
+fn authorize(request: &Request, port: u16, token: &str) -> Result<(), (u16, String)> {
+  let host = request.headers.get("host").map_or("", String::as_str);
+  if host != format!("127.0.0.1:{}", port) && host != format!("localhost:{}", port) {
+    return Err((403, "Unexpected Host header".to_string()));
+  }
+  let given = request
+    .headers
+    .get("authorization")
+    .and_then(|value| value.strip_prefix("Bearer "))
+    .unwrap_or("");
+  if !same_token(given.trim(), token) {
+    return Err((401, "Missing or invalid token".to_string()));
+  }
+  Ok(())
+}
+
+fn parse_body<T: serde::de::DeserializeOwned>(request: &Request) -> Result<T, (u16, String)> {
+  serde_json::from_slice(&request.body).map_err(bad_request)
+}
+
+fn to_json(value: impl serde::Serialize) -> Reply {
+  serde_json::to_value(value).map_err(|e| (500, e.to_string()))
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryBody {
+  connection: String,
+  sql: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportBody {
+  connection: String,
+  destination_id: String,
+  table: Option<String>,
+  query: Option<String>,
+  format: Option<String>,
+  file_name: Option<String>,
+  compression: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TemplateBody {
+  connection: String,
+  template_id: String,
+  #[serde(default)]
+  params: HashMap<String, String>,
+}
+
+async fn route(app: &AppHandle, request: &Request) -> Reply {
+  let state = app.state::<AppState>();
+  let failed = |e: String| (400, e);
+  match (request.method.as_str(), request.path.as_str()) {
+    ("GET", "/status") => Ok(serde_json::json!({
+      "app": "spectra-studio",
+      "version": env!("CARGO_PKG_VERSION"),
+      "workspace": workspaces::current(&state),
+    })),
+    ("GET", "/connections") => to_json(
+      health::get_connection_status(app.state(), None)
+        .await
+        .map_err(failed)?,
+    ),
+    ("POST", "/query") => {
+      let body: QueryBody = parse_body(request)?;
+      // SQLite would run every statement of a batch
+      if body.sql.trim().trim_end_matches(';').contains(';') {
+        return Err((400, "Send one statement per request".to_string()));
+      }
+      if !sql_kind::is_read(&body.sql) {
+        return Err((
+          403,
+          "Only statements that don't write can run here".to_string(),
+        ));
+      }
+      let pool = db::sql_pool(&state, &body.connection).map_err(failed)?;
+      let tz = timezone::display_zone(&state, &body.connection);
+      // The classifier goes by keywords; the read-only transaction also stops functions
+      // that write
+      let (columns, mut rows, truncated) = pool
+        .fetch_read_only(&body.sql, tz.as_ref(), MAX_QUERY_ROWS)
+        .await
+        .map_err(failed)?;
+      if let Some(mask) = masking::active(&state, &body.connection) {
+        for row in &mut rows {
+          mask.apply(row);
+        }
+      }
+      Ok(serde_json::json!({ "columns": columns, "rows": rows, "truncated": truncated }))
+    }
+    ("POST", "/export") => {
+      let body: ExportBody = parse_body(request)?;
+      to_json(
+        destinations::export_to_destination(
+          app.clone(),
+          app.state(),
+          body.connection,
+          body.destination_id,
+          body.table,
+          body.query,
+          body.format,
+          body.file_name,
+          None,
+          body.compression,
+        )
+        .await
+        .map_err(failed)?,
+      )
+    }
+    ("POST", "/templates/run") => {
+      let body: TemplateBody = parse_body(request)?;
+      to_json(
+        templates::run_template(app.state(), body.connection, body.template_id, body.params)
+          .await
+          .map_err(failed)?,
+      )
+    }
+    (_, "/status" | "/connections" | "/query" | "/export" | "/templates/run") => {
+      Err((405, "Method not allowed".to_string()))
+    }
+    _ => Err((404, "Not found".to_string())),
+  }
+}
+
+async fn serve(app: AppHandle, listener: TcpListener, port: u16, token: String) {
+  while let Ok((mut stream, _)) = listener.accept().await {
+    let (app, token) = (app.clone(), token.clone());
+    tauri::async_runtime::spawn(async move {
+      let reply = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Err(_) => Err((400, "Timed out reading the request".to_string())),
+        Ok(Err(e)) => Err(e),
+        Ok(Ok(request)) => match authorize(&request, port, &token) {
+          Ok(()) => route(&app, &request).await,
+          Err(e) => Err(e),
+        },
+      };
+      match reply {
+        Ok(body) => write_response(&mut stream, 200, &body).await,
+        Err((status, message)) => {
+          write_response(
+            &mut stream,
+            status,
+            &serde_json::json!({ "error": message }),
+          )
+          .await;
+        }
+      }
+    });
+  }
+}
+
+fn info(server: Option<&Server>) -> ServerInfo {
+  ServerInfo {
+    running: server.is_some(),
+    port: server.map(|s| s.port),
+    token: server.map(|s| s.token.clone()),
+  }
+}
+
+/// Starts the local scripting endpoint on `port` (7878 by default) with a fresh token,
+/// replacing a running one.
+#[tauri::command]
+pub async fn start_scripting_server(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  port: Option<u16>,
+) -> Result<ServerInfo, String> {
+  if let Some(server) = state
+    .scripting
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .take()
+  {
+    server.task.abort();
+  }
+  let listener = TcpListener::bind(("127.0.0.1", port.unwrap_or(DEFAULT_PORT)))
+    .await
+    .map_err(|e| {
+      format!(
+        "Cannot listen on port {}: {}",
+        port.unwrap_or(DEFAULT_PORT),
+        e
+      )
+    })?;
+  let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+  let mut secret = [0u8; 32];
+  OsRng.fill_bytes(&mut secret);
+  let token = encoding::hex(&secret);
+  let task = tauri::async_runtime::spawn(serve(app, listener, port, token.clone()));
+  let server = Server { port, token, task };
+  let started = info(Some(&server));
+  *state
+    .scripting
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner) = Some(server);
+  tracing::info!("Scripting endpoint listening on 127.0.0.1:{}", port);
+  Ok(started)
+}
+
+#[tauri::command]
+pub fn stop_scripting_server(state: State<'_, AppState>) {
+  if let Some(server) = state
+    .scripting
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .take()
+  {
+    server.task.abort();
+  }
+}
+
+#[tauri::command]
+pub fn get_scripting_server(state: State<'_, AppState>) -> ServerInfo {
+  info(
+    state
+      .scripting
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .as_ref(),
+  )
+}
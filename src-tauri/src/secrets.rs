@@ -0,0 +1,143 @@
+//! Connection secrets kept in the OS credential store (Windows Credential Manager, macOS
+//! Keychain, libsecret) instead of in profiles, keyed by profile id.
+
+use crate::{SshConfig, MAX_JUMP_HOSTS};
+
+/// Service name the entries are filed under in the credential store.
+const SERVICE: &str = "com.spectra-studio.app";
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SecretKind {
+  /// Database / server password.
+  Password,
+  /// Password for the SSH tunnel login.
+  SshPassword,
+  /// Passphrase protecting the SSH private key.
+  SshPassphrase,
+  /// Password for the n-th (0-based) jump host of the SSH tunnel.
+  JumpHostPassword(usize),
+}
+
+impl SecretKind {
+  const ALL: [SecretKind; 3] = [
+    SecretKind::Password,
+    SecretKind::SshPassword,
+    SecretKind::SshPassphrase,
+  ];
+
+  fn account_suffix(self) -> String {
+    match self {
+      SecretKind::Password => "password".to_string(),
+      SecretKind::SshPassword => "ssh-password".to_string(),
+      SecretKind::SshPassphrase => "ssh-passphrase".to_string(),
+      SecretKind::JumpHostPassword(hop) => format!("ssh-jump-{}-password", hop),
+    }
+  }
+}
+
+fn entry(profile_id: &str, kind: SecretKind) -> Result<keyring::Entry, String> {
+  keyring::Entry::new(
+    SERVICE,
+    &format!("{}:{}", profile_id, kind.account_suffix()),
+  )
+  .map_err(|e| format!("Credential store error: {}", e))
+}
+
+pub fn store(profile_id: &str, kind: SecretKind, secret: &str) -> Result<(), String> {
+  entry(profile_id, kind)?
+    .set_password(secret)
+    .map_err(|e| format!("Failed to store secret: {}", e))
+}
+
+pub fn lookup(profile_id: &str, kind: SecretKind) -> Result<Option<String>, String> {
+  match entry(profile_id, kind)?.get_password() {
+    Ok(secret) => Ok(Some(secret)),
+    Err(keyring::Error::NoEntry) => Ok(None),
+    Err(e) => Err(format!("Failed to read secret: {}", e)),
+  }
+}
+
+pub fn delete(profile_id: &str, kind: SecretKind) -> Result<(), String> {
+  match entry(profile_id, kind)?.delete_credential() {
+    Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+    Err(e) => Err(format!("Failed to delete secret: {}", e)),
+  }
+}
+
+pub fn delete_all(profile_id: &str) -> Result<(), String> {
+  SecretKind::ALL
+    .into_iter()
+    .chain((0..MAX_JUMP_HOSTS).map(SecretKind::JumpHostPassword))
+    .try_for_each(|kind| delete(profile_id, kind))
+}
+
+/// Credential store calls can block (unlock prompts), so they run off the async runtime.
+pub async fn blocking<T: Send + 'static>(
+  f: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+  tokio::task::spawn_blocking(f)
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// `given` when the caller passed a secret, otherwise the one stored for `profile_id`.
+pub async fn resolve(
+  profile_id: Option<&str>,
+  kind: SecretKind,
+  given: Option<String>,
+) -> Result<Option<String>, String> {
+  match (given, profile_id) {
+    (Some(secret), _) => Ok(Some(secret)),
+    (None, Some(profile_id)) => {
+      let profile_id = profile_id.to_string();
+      blocking(move || lookup(&profile_id, kind)).await
+    }
+    (None, None) => Ok(None),
+  }
+}
+
+/// Fills in the SSH login passwords (tunnel host and jump hosts) from the credential store
+/// when they weren't passed.
+pub async fn resolve_ssh(
+  profile_id: Option<&str>,
+  ssh_config: Option<SshConfig>,
+) -> Result<Option<SshConfig>, String> {
+  match ssh_config {
+    Some(mut ssh) => {
+      ssh.password = resolve(profile_id, SecretKind::SshPassword, ssh.password.take()).await?;
+      for (n, hop) in ssh.jump_hosts.iter_mut().enumerate() {
+        if !hop.use_agent {
+          let given = hop.password.take();
+          hop.password = resolve(profile_id, SecretKind::JumpHostPassword(n), given).await?;
+        }
+      }
+      Ok(Some(ssh))
+    }
+    None => Ok(None),
+  }
+}
+
+#[tauri::command]
+pub async fn store_secret(
+  profile_id: String,
+  kind: SecretKind,
+  secret: String,
+) -> Result<(), String> {
+  blocking(move || store(&profile_id, kind, &secret)).await
+}
+
+#[tauri::command]
+pub async fn get_secret(profile_id: String, kind: SecretKind) -> Result<Option<String>, String> {
+  blocking(move || lookup(&profile_id, kind)).await
+}
+
+/// Deletes one secret, or every secret of the profile when `kind` is omitted.
+#[tauri::command]
+pub async fn delete_secret(profile_id: String, kind: Option<SecretKind>) -> Result<(), String> {
+  blocking(move || match kind {
+    Some(kind) => delete(&profile_id, kind),
+    None => delete_all(&profile_id),
+  })
+  .await
+}
@@ -0,0 +1,452 @@
+//! Session state of MySQL and Postgres connections (selected database, `search_path`,
+//! session variables set through the app), kept per connection and restored on every
+//! connection its pool opens. When the server or a proxy drops idle connections, the
+//! replacements the pool opens come up in the same state, so the next command runs as if
+//! nothing happened.
+//!
+//! A change only reaches the pooled connection it ran on, so every change bumps the session's
+//! generation. Connections record the generation they are in as a session variable of their
+//! own; one opened before the latest change checks it when next acquired and has the session
+//! applied again if it is behind.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use sqlx::pool::PoolConnectionMetadata;
+use sqlx::Executor;
+use tauri::State;
+
+use crate::db::{self, SqlPool};
+use crate::ident::{self, Dialect};
+use crate::AppState;
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+  /// Database selected with `mysql_use_database`.
+  pub database: Option<String>,
+  /// Postgres `search_path`, as the server spells it.
+  pub search_path: Option<String>,
+  pub variables: BTreeMap<String, String>,
+  /// Settings changed back to the server's default since the pool was opened, which older
+  /// connections still have to reset.
+  #[serde(skip)]
+  reset: BTreeSet<String>,
+  /// Bumped by every change; `0` until the first.
+  #[serde(skip)]
+  generation: u64,
+  #[serde(skip)]
+  changed_at: Instant,
+}
+
+/// MySQL user variable holding the generation a connection is in.
+const MYSQL_GENERATION: &str = "@spectra_session_generation";
+/// Postgres setting holding the generation a connection is in.
+const PG_GENERATION: &str = "spectra.session_generation";
+
+pub type SessionHandle = Arc<Mutex<Session>>;
+
+pub type Sessions = HashMap<String, SessionHandle>;
+
+/// Session of a connection being opened, in the server's default state. Its pool's hooks
+/// hold on to it; [`register`] it once the pool is open.
+pub fn fresh() -> SessionHandle {
+  Arc::new(Mutex::new(Session {
+    database: None,
+    search_path: None,
+    variables: BTreeMap::new(),
+    reset: BTreeSet::new(),
+    generation: 0,
+    changed_at: Instant::now(),
+  }))
+}
+
+/// Makes `session` the one of connection `id`, replacing what an earlier connection with the
+/// same id had set.
+pub fn register(state: &AppState, id: &str, session: SessionHandle) {
+  state
+    .sessions
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(id.to_string(), session);
+}
+
+pub fn forget(state: &AppState, id: &str) {
+  state
+    .sessions
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(id);
+}
+
+fn handle(state: &AppState, id: &str) -> Result<SessionHandle, String> {
+  state
+    .sessions
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(id)
+    .cloned()
+    .ok_or_else(|| "Not connected".to_string())
+}
+
+fn update(state: &AppState, id: &str, change: impl FnOnce(&mut Session)) -> Result<(), String> {
+  let handle = handle(state, id)?;
+  let mut session = handle.lock().unwrap_or_else(PoisonError::into_inner);
+  change(&mut session);
+  session.generation += 1;
+  session.changed_at = Instant::now();
+  Ok(())
+}
+
+/// Whether a pooled connection was opened before the latest session change, and so may have
+/// missed it; for the pool's `before_acquire` hook.
+pub fn predates_change(session: &SessionHandle, meta: &PoolConnectionMetadata) -> bool {
+  meta.age
+    > session
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .changed_at
+      .elapsed()
+}
+
+/// Whether `value` is a plain decimal number such as `42`, `-1` or `0.5`.
+fn is_numeric_literal(value: &str) -> bool {
+  let digits = value.strip_prefix('-').unwrap_or(value);
+  let (whole, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+  [whole, fraction]
+    .iter()
+    .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Value for `SET SESSION`: plain numbers as they are, anything else as a string literal.
+fn mysql_value(value: &str) -> String {
+  if is_numeric_literal(value) {
+    value.to_string()
+  } else {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''"))
+  }
+}
+
+fn check_variable_name(name: &str) -> Result<(), String> {
+  let valid = !name.is_empty()
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+  if valid {
+    Ok(())
+  } else {
+    Err(format!("Invalid session variable name: {}", name))
+  }
+}
+
+/// Statements that put a MySQL connection in the session's state, and the generation that
+/// state is.
+pub fn mysql_statements(session: &SessionHandle) -> (u64, Vec<String>) {
+  let session = session.lock().unwrap_or_else(PoisonError::into_inner);
+  let mut statements = Vec::new();
+  if let Some(database) = &session.database {
+    // Checked by `use_mysql_database` before it was recorded
+    statements.push(format!("USE {}", ident::quote(Dialect::MySql, database)));
+  }
+  for name in &session.reset {
+    statements.push(format!("SET SESSION {} = DEFAULT", name));
+  }
+  for (name, value) in &session.variables {
+    statements.push(format!("SET SESSION {} = {}", name, mysql_value(value)));
+  }
+  if session.generation > 0 {
+    statements.push(format!("SET {} = {}", MYSQL_GENERATION, session.generation));
+  }
+  (session.generation, statements)
+}
+
+/// Settings that put a Postgres connection in the session's state (`None` resets one to the
+/// server's default), and the generation that state is.
+pub fn postgres_settings(session: &SessionHandle) -> (u64, Vec<(String, Option<String>)>) {
+  let session = session.lock().unwrap_or_else(PoisonError::into_inner);
+  let mut settings: Vec<(String, Option<String>)> = session
+    .reset
+    .iter()
+    .map(|name| (name.clone(), None))
+    .collect();
+  settings.extend(
+    session
+      .variables
+      .iter()
+      .map(|(name, value)| (name.clone(), Some(value.clone()))),
+  );
+  if let Some(path) = &session.search_path {
+    settings.push(("search_path".to_string(), Some(path.clone())));
+  }
+  if session.generation > 0 {
+    settings.push((
+      PG_GENERATION.to_string(),
+      Some(session.generation.to_string()),
+    ));
+  }
+  (session.generation, settings)
+}
+
+/// Restores the session on a MySQL connection the pool just opened.
+pub async fn restore_mysql(
+  conn: &mut sqlx::MySqlConnection,
+  statements: Vec<String>,
+) -> Result<(), sqlx::Error> {
+  for statement in &statements {
+    // USE is not available as a prepared statement
+    conn.execute(sqlx::raw_sql(statement)).await?;
+  }
+  Ok(())
+}
+
+/// Restores the session on a Postgres connection the pool just opened.
+pub async fn restore_postgres(
+  conn: &mut sqlx::PgConnection,
+  settings: Vec<(String, Option<String>)>,
+) -> Result<(), sqlx::Error> {
+  for (name, value) in &settings {
+    match value {
+      Some(value) => {
+        sqlx::query("SELECT set_config($1, $2, false)")
+          .bind(name)
+          .bind(value)
+          .execute(&mut *conn)
+          .await?;
+      }
+      // Checked by `check_variable_name` before it was recorded
+      None => {
+        conn
+          .execute(sqlx::raw_sql(&format!("RESET {}", name)))
+          .await?;
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Applies the session again to a MySQL connection that is behind `generation`; for the
+/// pool's `before_acquire` hook.
+pub async fn refresh_mysql(
+  conn: &mut sqlx::MySqlConnection,
+  generation: u64,
+  statements: Vec<String>,
+) -> Result<bool, sqlx::Error> {
+  let current: Option<u64> =
+    sqlx::query_scalar(&format!("SELECT CAST({} AS UNSIGNED)", MYSQL_GENERATION))
+      .fetch_one(&mut *conn)
+      .await?;
+  if current != Some(generation) {
+    restore_mysql(conn, statements).await?;
+  }
+  Ok(true)
+}
+
+/// Applies the session again to a Postgres connection that is behind `generation`; for the
+/// pool's `before_acquire` hook.
+pub async fn refresh_postgres(
+  conn: &mut sqlx::PgConnection,
+  generation: u64,
+  settings: Vec<(String, Option<String>)>,
+) -> Result<bool, sqlx::Error> {
+  let current: Option<String> = sqlx::query_scalar("SELECT current_setting($1, true)")
+    .bind(PG_GENERATION)
+    .fetch_one(&mut *conn)
+    .await?;
+  if current != Some(generation.to_string()) {
+    restore_postgres(conn, settings).await?;
+  }
+  Ok(true)
+}
+
+/// Whether a statement failed because the connection was dropped under it (server restart,
+/// `wait_timeout`, a proxy closing idle connections).
+pub fn is_disconnect(e: &sqlx::Error) -> bool {
+  match e {
+    sqlx::Error::Io(_) | sqlx::Error::WorkerCrashed => true,
+    sqlx::Error::Database(db) => db.code().is_some_and(|code| {
+      // 08xxx: connection exceptions; 57P01-57P03: Postgres shutting the session down
+      code.starts_with("08") || matches!(code.as_ref(), "57P01" | "57P02" | "57P03")
+    }),
+    _ => false,
+  }
+}
+
+/// Runs a read once more when it fails on a dropped connection; the pool hands out a fresh,
+/// session-restored connection for the second attempt. Only for statements that are safe
+/// to repeat.
+pub async fn retry_once<T, F, Fut>(mut run: F) -> Result<T, sqlx::Error>
+where
+  F: FnMut() -> Fut,
+  Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+  match run().await {
+    Err(e) if is_disconnect(&e) => {
+      tracing::warn!("Connection dropped, retrying once: {}", e);
+      run().await
+    }
+    result => result,
+  }
+}
+
+/// Selects the database MySQL statements run against, for every connection of the pool.
+pub async fn use_mysql_database(
+  state: &AppState,
+  id: &str,
+  database: String,
+) -> Result<(), String> {
+  let pool = crate::connections::mysql(state, Some(id))?;
+  // Fails here if the database does not exist, before anything is recorded
+  let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+  let statement = format!("USE {}", ident::quote_mysql(&database)?);
+  conn
+    .execute(sqlx::raw_sql(&statement))
+    .await
+    .map_err(|e| e.to_string())?;
+  update(state, id, |session| session.database = Some(database))
+}
+
+/// Sets a session variable on every connection of a MySQL or Postgres pool; `None` goes back
+/// to the server's default.
+#[tauri::command]
+pub async fn set_session_variable(
+  state: State<'_, AppState>,
+  connection: String,
+  name: String,
+  value: Option<String>,
+) -> Result<(), String> {
+  check_variable_name(&name)?;
+  let pool = db::sql_pool(&state, &connection)?;
+  match (&pool, &value) {
+    (SqlPool::MySql(pool), Some(value)) => {
+      let statement = format!("SET SESSION {} = {}", name, mysql_value(value));
+      sqlx::query(&statement)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    (SqlPool::Postgres(pool), Some(value)) => {
+      sqlx::query("SELECT set_config($1, $2, false)")
+        .bind(&name)
+        .bind(value)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    (SqlPool::Sqlite(_), _) => {
+      return Err("SQLite connections have no session variables".to_string());
+    }
+    (_, None) => {}
+  }
+  update(&state, &connection, |session| {
+    if let Some(value) = value {
+      session.reset.remove(&name);
+      session.variables.insert(name, value);
+    } else {
+      session.variables.remove(&name);
+      session.reset.insert(name);
+    }
+  })
+}
+
+/// Sets the Postgres `search_path` for every connection of the pool; an empty list goes back
+/// to the server's default.
+#[tauri::command]
+pub async fn set_search_path(
+  state: State<'_, AppState>,
+  connection: String,
+  schemas: Vec<String>,
+) -> Result<(), String> {
+  let SqlPool::Postgres(pool) = db::sql_pool(&state, &connection)? else {
+    return Err("search_path only exists on Postgres".to_string());
+  };
+  let path = if schemas.is_empty() {
+    None
+  } else {
+    let quoted = schemas
+      .iter()
+      .map(|schema| ident::quote_pg(schema))
+      .collect::<Result<Vec<_>, _>>()?;
+    Some(quoted.join(", "))
+  };
+  if let Some(path) = &path {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    sqlx::query("SELECT set_config('search_path', $1, false)")
+      .bind(path)
+      .execute(&mut *conn)
+      .await
+      .map_err(|e| e.to_string())?;
+  }
+  update(&state, &connection, |session| {
+    if path.is_none() {
+      session.reset.insert("search_path".to_string());
+    }
+    session.search_path = path;
+  })
+}
+
+/// Session state the app restores on `connection`.
+#[tauri::command]
+pub fn get_session_state(
+  state: State<'_, AppState>,
+  connection: String,
+) -> Result<Session, String> {
+  let handle = handle(&state, &connection)?;
+  let session = handle
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .clone();
+  Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn only_plain_numbers_stay_unquoted() {
+    assert_eq!(mysql_value("42"), "42");
+    assert_eq!(mysql_value("-1"), "-1");
+    assert_eq!(mysql_value("0.5"), "0.5");
+    assert_eq!(mysql_value("NaN"), "'NaN'");
+    assert_eq!(mysql_value("inf"), "'inf'");
+    assert_eq!(mysql_value("infinity"), "'infinity'");
+    assert_eq!(mysql_value("1e3"), "'1e3'");
+    assert_eq!(mysql_value(".5"), "'.5'");
+    assert_eq!(mysql_value("1; DROP"), "'1; DROP'");
+    assert_eq!(mysql_value("it's"), "'it''s'");
+  }
+
+  #[test]
+  fn restores_reset_variables_and_the_generation() {
+    let session = fresh();
+    assert_eq!(mysql_statements(&session), (0, Vec::new()));
+    {
+      let mut session = session.lock().unwrap_or_else(PoisonError::into_inner);
+      session
+        .variables
+        .insert("sql_mode".to_string(), "ANSI".to_string());
+      session.reset.insert("time_zone".to_string());
+      session.generation = 2;
+    }
+    assert_eq!(
+      mysql_statements(&session),
+      (
+        2,
+        vec![
+          "SET SESSION time_zone = DEFAULT".to_string(),
+          "SET SESSION sql_mode = 'ANSI'".to_string(),
+          "SET @spectra_session_generation = 2".to_string(),
+        ]
+      )
+    );
+    let (generation, settings) = postgres_settings(&session);
+    assert_eq!(generation, 2);
+    assert_eq!(settings[0], ("time_zone".to_string(), None));
+    assert_eq!(
+      settings.last(),
+      Some(&(PG_GENERATION.to_string(), Some("2".to_string())))
+    );
+  }
+}
@@ -0,0 +1,841 @@
+//! Opt-in session sharing for pair debugging: a second instance on the LAN can run read-only
+//! queries through this instance's open connections (and so through its tunnels, VPN or
+//! bastion access) without holding any credentials itself.
+//!
+//! Both sides authenticate with TLS 1.3 raw Ed25519 public keys (RFC 7250) generated for the
+//! running app; there are no certificates to trust. The host hands out an invite code that
+//! carries its address, its key fingerprint and a one-time secret. The guest pins the host key
+//! from the code, and redeeming the secret pins the guest's key on the host, so later requests
+//! only need the TLS handshake.
+//!
+//! Each request is one JSON line over its own TLS connection, answered by one JSON line:
+//! - `{"op": "join", "invite", "name"}`: redeem the invite and list the shared connections
+//! - `{"op": "connections"}`: the shared connections
+//! - `{"op": "query", "connection", "sql"}`: rows of a single statement that only reads, run in
+//!   a read-only transaction and cut off after `MAX_QUERY_ROWS` (`truncated` says so)
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::AlwaysResolvesClientRawPublicKeys;
+use rustls::crypto::{
+  verify_tls13_signature_with_raw_key, CryptoProvider, WebPkiSupportedAlgorithms,
+};
+use rustls::pki_types::{
+  CertificateDer, PrivatePkcs8KeyDer, ServerName, SubjectPublicKeyInfoDer, UnixTime,
+};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::server::AlwaysResolvesServerRawPublicKeys;
+use rustls::sign::CertifiedKey;
+use rustls::{
+  ClientConfig, DigitallySignedStruct, DistinguishedName, ServerConfig, SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use crate::{db, encoding, history, masking, sql_kind, store, timezone, AppState};
+
+const DEFAULT_PORT: u16 = 7879;
+const INVITE_PREFIX: &str = "spectra-share:";
+const MAX_REQUEST_BYTES: u64 = 1024 * 1024;
+const MAX_RESPONSE_BYTES: u64 = 256 * 1024 * 1024;
+/// Rows a guest query returns at most, well inside `MAX_RESPONSE_BYTES` for typical rows.
+const MAX_QUERY_ROWS: usize = 10_000;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Host and guest sides of sharing, kept for the running app.
+#[derive(Default)]
+pub struct Sharing {
+  host: Option<Host>,
+  /// This instance's key as a guest, made on the first join.
+  guest_key: Option<Arc<CertifiedKey>>,
+  joined: HashMap<String, Joined>,
+}
+
+struct Host {
+  address: String,
+  fingerprint: String,
+  connections: Vec<String>,
+  guests: Arc<Mutex<Guests>>,
+  task: tauri::async_runtime::JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct Guests {
+  /// The secret of the invite not redeemed yet; each invite admits one guest.
+  invite: Option<String>,
+  admitted: Vec<Guest>,
+}
+
+struct Guest {
+  name: String,
+  key: Vec<u8>,
+  joined_at: i64,
+  last_seen: i64,
+  queries: u64,
+}
+
+struct Joined {
+  address: String,
+  fingerprint: [u8; 32],
+  host: String,
+  connections: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GuestInfo {
+  pub name: String,
+  pub fingerprint: String,
+  pub joined_at: i64,
+  pub last_seen: i64,
+  pub queries: u64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareInfo {
+  pub running: bool,
+  pub address: Option<String>,
+  pub fingerprint: Option<String>,
+  /// Code to pass to the guest; `None` once redeemed until a new one is issued.
+  pub invite: Option<String>,
+  pub connections: Vec<String>,
+  pub guests: Vec<GuestInfo>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JoinedSession {
+  pub id: String,
+  pub address: String,
+  pub host: String,
+  pub connections: Vec<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Invite {
+  address: String,
+  fingerprint: String,
+  secret: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum Request {
+  Join { invite: String, name: String },
+  Connections,
+  Query { connection: String, sql: String },
+}
+
+fn provider() -> Arc<CryptoProvider> {
+  Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// A fresh Ed25519 key presented as a raw public key.
+fn new_key() -> Result<Arc<CertifiedKey>, String> {
+  let pkcs8 = ring::signature::Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
+    .map_err(|_| "Cannot generate a session key".to_string())?;
+  let der = PrivatePkcs8KeyDer::from(pkcs8.as_ref().to_vec());
+  let key = rustls::crypto::ring::sign::any_eddsa_type(&der).map_err(|e| e.to_string())?;
+  let spki = key
+    .public_key()
+    .ok_or_else(|| "Cannot read the session public key".to_string())?;
+  Ok(Arc::new(CertifiedKey::new(
+    vec![CertificateDer::from(spki.as_ref().to_vec())],
+    key,
+  )))
+}
+
+fn fingerprint(spki: &[u8]) -> [u8; 32] {
+  Sha256::digest(spki).into()
+}
+
+/// Checks the peer's raw public key: the host accepts any guest key at the TLS layer and
+/// admits it by invite, the guest only accepts the host key pinned from the invite.
+#[derive(Debug)]
+struct RawKeyVerifier {
+  pinned: Option<[u8; 32]>,
+  algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl RawKeyVerifier {
+  fn new(pinned: Option<[u8; 32]>) -> Self {
+    Self {
+      pinned,
+      algorithms: provider().signature_verification_algorithms,
+    }
+  }
+
+  fn verify_signature(
+    &self,
+    message: &[u8],
+    key: &CertificateDer<'_>,
+    dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, rustls::Error> {
+    verify_tls13_signature_with_raw_key(
+      message,
+      &SubjectPublicKeyInfoDer::from(key.as_ref()),
+      dss,
+      &self.algorithms,
+    )
+  }
+}
+
+impl ServerCertVerifier for RawKeyVerifier {
+  fn verify_server_cert(
+    &self,
+    end_entity: &CertificateDer<'_>,
+    _intermediates: &[CertificateDer<'_>],
+    _server_name: &ServerName<'_>,
+    _ocsp_response: &[u8],
+    _now: UnixTime,
+  ) -> Result<ServerCertVerified, rustls::Error> {
+    match self.pinned {
+      Some(pinned) if fingerprint(end_entity) == pinned => Ok(ServerCertVerified::assertion()),
+      _ => Err(rustls::Error::General(
+        "The host key doesn't match the invite".to_string(),
+      )),
+    }
+  }
+
+  fn verify_tls12_signature(
+    &self,
+    _message: &[u8],
+    _cert: &CertificateDer<'_>,
+    _dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, rustls::Error> {
+    Err(rustls::Error::General("TLS 1.2 is not used".to_string()))
+  }
+
+  fn verify_tls13_signature(
+    &self,
+    message: &[u8],
+    cert: &CertificateDer<'_>,
+    dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, rustls::Error> {
+    self.verify_signature(message, cert, dss)
+  }
+
+  fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+    vec![SignatureScheme::ED25519]
+  }
+
+  fn requires_raw_public_keys(&self) -> bool {
+    true
+  }
+}
+
+impl ClientCertVerifier for RawKeyVerifier {
+  fn root_hint_subjects(&self) -> &[DistinguishedName] {
+    &[]
+  }
+
+  fn verify_client_cert(
+    &self,
+    _end_entity: &CertificateDer<'_>,
+    _intermediates: &[CertificateDer<'_>],
+    _now: UnixTime,
+  ) -> Result<ClientCertVerified, rustls::Error> {
+    Ok(ClientCertVerified::assertion())
+  }
+
+  fn verify_tls12_signature(
+    &self,
+    _message: &[u8],
+    _cert: &CertificateDer<'_>,
+    _dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, rustls::Error> {
+    Err(rustls::Error::General("TLS 1.2 is not used".to_string()))
+  }
+
+  fn verify_tls13_signature(
+    &self,
+    message: &[u8],
+    cert: &CertificateDer<'_>,
+    dss: &DigitallySignedStruct,
+  ) -> Result<HandshakeSignatureValid, rustls::Error> {
+    self.verify_signature(message, cert, dss)
+  }
+
+  fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+    vec![SignatureScheme::ED25519]
+  }
+
+  fn requires_raw_public_keys(&self) -> bool {
+    true
+  }
+}
+
+fn server_config(key: Arc<CertifiedKey>) -> Result<ServerConfig, String> {
+  Ok(
+    ServerConfig::builder_with_provider(provider())
+      .with_protocol_versions(&[&rustls::version::TLS13])
+      .map_err(|e| e.to_string())?
+      .with_client_cert_verifier(Arc::new(RawKeyVerifier::new(None)))
+      .with_cert_resolver(Arc::new(AlwaysResolvesServerRawPublicKeys::new(key))),
+  )
+}
+
+fn client_config(key: Arc<CertifiedKey>, host: [u8; 32]) -> Result<ClientConfig, String> {
+  Ok(
+    ClientConfig::builder_with_provider(provider())
+      .with_protocol_versions(&[&rustls::version::TLS13])
+      .map_err(|e| e.to_string())?
+      .dangerous()
+      .with_custom_certificate_verifier(Arc::new(RawKeyVerifier::new(Some(host))))
+      .with_client_cert_resolver(Arc::new(AlwaysResolvesClientRawPublicKeys::new(key))),
+  )
+}
+
+/// The address other machines reach this one on: the interface the default route leaves
+/// through. Connecting a UDP socket only picks the route, nothing is sent.
+fn lan_ip() -> IpAddr {
+  UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+    .and_then(|socket| {
+      socket.connect((Ipv4Addr::new(10, 255, 255, 255), 1))?;
+      socket.local_addr()
+    })
+    .map_or(IpAddr::V4(Ipv4Addr::LOCALHOST), |addr| addr.ip())
+}
+
+fn host_name() -> String {
+  std::env::var("COMPUTERNAME")
+    .or_else(|_| std::env::var("HOSTNAME"))
+    .unwrap_or_else(|_| "Spectra Studio".to_string())
+}
+
+async fn read_line<S: AsyncRead + Unpin>(stream: S, limit: u64) -> Result<Vec<u8>, String> {
+  let mut line = Vec::new();
+  BufReader::new(stream.take(limit))
+    .read_until(b'\n', &mut line)
+    .await
+    .map_err(|e| e.to_string())?;
+  if line.last() != Some(&b'\n') {
+    return Err("Incomplete or oversized message".to_string());
+  }
+  Ok(line)
+}
+
+async fn write_line<S: AsyncWrite + Unpin>(
+  stream: &mut S,
+  value: &serde_json::Value,
+) -> Result<(), String> {
+  let mut line = value.to_string().into_bytes();
+  line.push(b'\n');
+  stream.write_all(&line).await.map_err(|e| e.to_string())?;
+  stream.flush().await.map_err(|e| e.to_string())
+}
+
+/// Compares without stopping at the first difference, so timing doesn't leak the secret.
+fn same_secret(given: &str, expected: &str) -> bool {
+  given.len() == expected.len()
+    && given
+      .bytes()
+      .zip(expected.bytes())
+      .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+      == 0
+}
+
+fn admitted_index(guests: &Guests, key: &[u8]) -> Result<usize, String> {
+  guests
+    .admitted
+    .iter()
+    .position(|g| g.key == key)
+    .ok_or_else(|| "Join with an invite code first".to_string())
+}
+
+async fn handle(
+  app: &AppHandle,
+  guests: &Mutex<Guests>,
+  connections: &[String],
+  key: &[u8],
+  request: Request,
+) -> Result<serde_json::Value, String> {
+  let state = app.state::<AppState>();
+  let guest = match request {
+    Request::Join { invite, name } => {
+      let mut guests = guests.lock().unwrap_or_else(PoisonError::into_inner);
+      if admitted_index(&guests, key).is_err() {
+        let expected = guests.invite.take().unwrap_or_default();
+        if expected.is_empty() || !same_secret(&invite, &expected) {
+          guests.invite = Some(expected).filter(|s| !s.is_empty());
+          return Err("The invite code is invalid or was already used".to_string());
+        }
+        let now = store::now_ms();
+        tracing::info!("Shared session joined by '{}'", name);
+        guests.admitted.push(Guest {
+          name,
+          key: key.to_vec(),
+          joined_at: now,
+          last_seen: now,
+          queries: 0,
+        });
+      }
+      return Ok(serde_json::json!({ "host": host_name(), "connections": connections }));
+    }
+    Request::Connections => {
+      admitted_index(&guests.lock().unwrap_or_else(PoisonError::into_inner), key)?;
+      return Ok(serde_json::json!({ "connections": connections }));
+    }
+    Request::Query { connection, sql } => {
+      let name = {
+        let mut guests = guests.lock().unwrap_or_else(PoisonError::into_inner);
+        let index = admitted_index(&guests, key)?;
+        let guest = &mut guests.admitted[index];
+        guest.last_seen = store::now_ms();
+        guest.queries += 1;
+        guest.name.clone()
+      };
+      (name, connection, sql)
+    }
+  };
+  let (name, connection, sql) = guest;
+  if !connections.contains(&connection) {
+    return Err(format!("Connection '{}' is not shared", connection));
+  }
+  if !sql_kind::is_read(&sql) {
+    return Err("Only single statements that read can run in a shared session".to_string());
+  }
+  tracing::info!("Shared session query by '{}' on '{}'", name, connection);
+  let pool = db::sql_pool(&state, &connection)?;
+  let tz = timezone::display_zone(&state, &connection);
+  let started = Instant::now();
+  let fetched = pool
+    .fetch_read_only(&sql, tz.as_ref(), MAX_QUERY_ROWS)
+    .await;
+  let outcome = match &fetched {
+    Ok((_, rows, _)) => Ok(rows.len() as u64),
+    Err(e) => Err(e.as_str()),
+  };
+  history::record(&state, &connection, &sql, started.elapsed(), outcome).await;
+  let (columns, mut rows, truncated) = fetched?;
+  if let Some(mask) = masking::active(&state, &connection) {
+    for row in &mut rows {
+      mask.apply(row);
+    }
+  }
+  Ok(serde_json::json!({ "columns": columns, "rows": rows, "truncated": truncated }))
+}
+
+async fn serve_one(
+  app: AppHandle,
+  acceptor: TlsAcceptor,
+  stream: TcpStream,
+  guests: Arc<Mutex<Guests>>,
+  connections: Arc<Vec<String>>,
+) -> Result<(), String> {
+  let mut tls = tokio::time::timeout(READ_TIMEOUT, acceptor.accept(stream))
+    .await
+    .map_err(|_| "Timed out during the handshake".to_string())?
+    .map_err(|e| e.to_string())?;
+  let key = tls
+    .get_ref()
+    .1
+    .peer_certificates()
+    .and_then(|keys| keys.first())
+    .map(|key| key.as_ref().to_vec())
+    .ok_or_else(|| "The guest presented no key".to_string())?;
+  let line = tokio::time::timeout(READ_TIMEOUT, read_line(&mut tls, MAX_REQUEST_BYTES))
+    .await
+    .map_err(|_| "Timed out reading the request".to_string())??;
+  let reply = match serde_json::from_slice::<Request>(&line) {
+    Ok(request) => handle(&app, &guests, &connections, &key, request).await,
+    Err(e) => Err(e.to_string()),
+  };
+  let body = match reply {
+    Ok(result) => serde_json::json!({ "ok": result }),
+    Err(message) => serde_json::json!({ "error": message }),
+  };
+  write_line(&mut tls, &body).await?;
+  let _ = tls.shutdown().await;
+  Ok(())
+}
+
+async fn serve(
+  app: AppHandle,
+  listener: TcpListener,
+  acceptor: TlsAcceptor,
+  guests: Arc<Mutex<Guests>>,
+  connections: Arc<Vec<String>>,
+) {
+  while let Ok((stream, peer)) = listener.accept().await {
+    let (app, acceptor, guests, connections) = (
+      app.clone(),
+      acceptor.clone(),
+      guests.clone(),
+      connections.clone(),
+    );
+    tauri::async_runtime::spawn(async move {
+      if let Err(e) = serve_one(app, acceptor, stream, guests, connections).await {
+        tracing::warn!("Shared session request from {} failed: {}", peer, e);
+      }
+    });
+  }
+}
+
+fn new_secret() -> String {
+  let mut secret = [0u8; 16];
+  OsRng.fill_bytes(&mut secret);
+  encoding::hex(&secret)
+}
+
+fn invite_code(address: &str, fingerprint: &str, secret: &str) -> String {
+  let invite = Invite {
+    address: address.to_string(),
+    fingerprint: fingerprint.to_string(),
+    secret: secret.to_string(),
+  };
+  let json = serde_json::to_vec(&invite).unwrap_or_default();
+  format!("{}{}", INVITE_PREFIX, URL_SAFE_NO_PAD.encode(json))
+}
+
+fn parse_invite(code: &str) -> Result<(Invite, [u8; 32]), String> {
+  let invalid = || "Not a share invite code".to_string();
+  let encoded = code
+    .trim()
+    .strip_prefix(INVITE_PREFIX)
+    .ok_or_else(invalid)?;
+  let json = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| invalid())?;
+  let invite: Invite = serde_json::from_slice(&json).map_err(|_| invalid())?;
+  let bytes = encoding::unhex(&invite.fingerprint).ok_or_else(invalid)?;
+  let fingerprint = <[u8; 32]>::try_from(bytes.as_slice()).map_err(|_| invalid())?;
+  Ok((invite, fingerprint))
+}
+
+fn share_info(host: Option<&Host>) -> ShareInfo {
+  let Some(host) = host else {
+    return ShareInfo {
+      running: false,
+      address: None,
+      fingerprint: None,
+      invite: None,
+      connections: Vec::new(),
+      guests: Vec::new(),
+    };
+  };
+  let guests = host.guests.lock().unwrap_or_else(PoisonError::into_inner);
+  ShareInfo {
+    running: true,
+    address: Some(host.address.clone()),
+    fingerprint: Some(host.fingerprint.clone()),
+    invite: guests
+      .invite
+      .as_deref()
+      .map(|secret| invite_code(&host.address, &host.fingerprint, secret)),
+    connections: host.connections.clone(),
+    guests: guests
+      .admitted
+      .iter()
+      .map(|g| GuestInfo {
+        name: g.name.clone(),
+        fingerprint: encoding::hex(&fingerprint(&g.key)),
+        joined_at: g.joined_at,
+        last_seen: g.last_seen,
+        queries: g.queries,
+      })
+      .collect(),
+  }
+}
+
+/// Starts sharing `connections` (which must be open SQL connections) with the LAN on `port`
+/// (7879 by default), replacing a running share and dropping its guests.
+#[tauri::command]
+pub async fn start_session_share(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  connections: Vec<String>,
+  port: Option<u16>,
+) -> Result<ShareInfo, String> {
+  if connections.is_empty() {
+    return Err("Choose at least one connection to share".to_string());
+  }
+  for connection in &connections {
+    db::sql_pool(&state, connection)?;
+  }
+  if let Some(host) = state
+    .sharing
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .host
+    .take()
+  {
+    host.task.abort();
+  }
+  let port = port.unwrap_or(DEFAULT_PORT);
+  let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port))
+    .await
+    .map_err(|e| format!("Cannot listen on port {}: {}", port, e))?;
+  let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+  let key = new_key()?;
+  let host_fingerprint = encoding::hex(&fingerprint(&key.cert[0]));
+  let acceptor = TlsAcceptor::from(Arc::new(server_config(key)?));
+  let guests = Arc::new(Mutex::new(Guests {
+    invite: Some(new_secret()),
+    admitted: Vec::new(),
+  }));
+  let task = tauri::async_runtime::spawn(serve(
+    app,
+    listener,
+    acceptor,
+    guests.clone(),
+    Arc::new(connections.clone()),
+  ));
+  let host = Host {
+    address: format!("{}:{}", lan_ip(), port),
+    fingerprint: host_fingerprint,
+    connections,
+    guests,
+    task,
+  };
+  tracing::info!("Sharing session on {}", host.address);
+  let started = share_info(Some(&host));
+  state
+    .sharing
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .host = Some(host);
+  Ok(started)
+}
+
+#[tauri::command]
+pub fn stop_session_share(state: State<'_, AppState>) {
+  if let Some(host) = state
+    .sharing
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .host
+    .take()
+  {
+    host.task.abort();
+  }
+}
+
+#[tauri::command]
+pub fn get_session_share(state: State<'_, AppState>) -> ShareInfo {
+  share_info(
+    state
+      .sharing
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .host
+      .as_ref(),
+  )
+}
+
+/// Issues a new invite code for one more guest, replacing an unredeemed one.
+#[tauri::command]
+pub fn renew_share_invite(state: State<'_, AppState>) -> Result<ShareInfo, String> {
+  let sharing = state.sharing.lock().unwrap_or_else(PoisonError::into_inner);
+  let host = sharing
+    .host
+    .as_ref()
+    .ok_or_else(|| "Session sharing is not running".to_string())?;
+  host
+    .guests
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .invite = Some(new_secret());
+  Ok(share_info(Some(host)))
+}
+
+/// Revokes a guest by its key fingerprint; it needs a new invite to come back.
+#[tauri::command]
+pub fn remove_share_guest(state: State<'_, AppState>, fingerprint: String) {
+  if let Some(host) = state
+    .sharing
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .host
+    .as_ref()
+  {
+    host
+      .guests
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .admitted
+      .retain(|g| encoding::hex(&self::fingerprint(&g.key)) != fingerprint);
+  }
+}
+
+async fn send(
+  key: Arc<CertifiedKey>,
+  address: &str,
+  pinned: [u8; 32],
+  request: &Request,
+) -> Result<serde_json::Value, String> {
+  let connector = TlsConnector::from(Arc::new(client_config(key, pinned)?));
+  let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(address))
+    .await
+    .map_err(|_| format!("Timed out connecting to {}", address))?
+    .map_err(|e| format!("Cannot reach {}: {}", address, e))?;
+  let server_name = ServerName::try_from("spectra-share").map_err(|e| e.to_string())?;
+  let mut tls = tokio::time::timeout(CONNECT_TIMEOUT, connector.connect(server_name, stream))
+    .await
+    .map_err(|_| format!("Timed out connecting to {}", address))?
+    .map_err(|e| format!("Secure connection to {} failed: {}", address, e))?;
+  let request = serde_json::to_value(request).map_err(|e| e.to_string())?;
+  write_line(&mut tls, &request).await?;
+  let line = read_line(&mut tls, MAX_RESPONSE_BYTES).await?;
+  let mut reply: serde_json::Value = serde_json::from_slice(&line).map_err(|e| e.to_string())?;
+  match reply.get("error").and_then(|e| e.as_str()) {
+    Some(message) => Err(message.to_string()),
+    None => Ok(reply["ok"].take()),
+  }
+}
+
+fn guest_key(state: &AppState) -> Result<Arc<CertifiedKey>, String> {
+  let mut sharing = state.sharing.lock().unwrap_or_else(PoisonError::into_inner);
+  if let Some(key) = &sharing.guest_key {
+    return Ok(key.clone());
+  }
+  let key = new_key()?;
+  sharing.guest_key = Some(key.clone());
+  Ok(key)
+}
+
+fn connection_ids(reply: &serde_json::Value) -> Vec<String> {
+  reply["connections"]
+    .as_array()
+    .map(|ids| {
+      ids
+        .iter()
+        .filter_map(|id| id.as_str().map(str::to_string))
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+fn joined_session(id: &str, joined: &Joined) -> JoinedSession {
+  JoinedSession {
+    id: id.to_string(),
+    address: joined.address.clone(),
+    host: joined.host.clone(),
+    connections: joined.connections.clone(),
+  }
+}
+
+/// Joins the instance that issued `invite`, showing `name` (the OS user by default) there.
+#[tauri::command]
+pub async fn join_shared_session(
+  state: State<'_, AppState>,
+  invite: String,
+  name: Option<String>,
+) -> Result<JoinedSession, String> {
+  let (invite, pinned) = parse_invite(&invite)?;
+  let name = name
+    .filter(|n| !n.trim().is_empty())
+    .or_else(|| std::env::var("USERNAME").ok())
+    .or_else(|| std::env::var("USER").ok())
+    .unwrap_or_else(|| "guest".to_string());
+  let request = Request::Join {
+    invite: invite.secret,
+    name,
+  };
+  let reply = send(guest_key(&state)?, &invite.address, pinned, &request).await?;
+  let joined = Joined {
+    address: invite.address,
+    fingerprint: pinned,
+    host: reply["host"].as_str().unwrap_or_default().to_string(),
+    connections: connection_ids(&reply),
+  };
+  let id = invite.fingerprint[..16].to_string();
+  let session = joined_session(&id, &joined);
+  state
+    .sharing
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .joined
+    .insert(id, joined);
+  Ok(session)
+}
+
+/// Sessions joined in this run, with their shared connections refreshed from each host;
+/// hosts that can't be reached keep the last known list.
+#[tauri::command]
+pub async fn list_shared_sessions(
+  state: State<'_, AppState>,
+) -> Result<Vec<JoinedSession>, String> {
+  let targets: Vec<(String, String, [u8; 32])> = state
+    .sharing
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .joined
+    .iter()
+    .map(|(id, j)| (id.clone(), j.address.clone(), j.fingerprint))
+    .collect();
+  if targets.is_empty() {
+    return Ok(Vec::new());
+  }
+  let key = guest_key(&state)?;
+  for (id, address, pinned) in targets {
+    if let Ok(reply) = send(key.clone(), &address, pinned, &Request::Connections).await {
+      if let Some(joined) = state
+        .sharing
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .joined
+        .get_mut(&id)
+      {
+        joined.connections = connection_ids(&reply);
+      }
+    }
+  }
+  let sharing = state.sharing.lock().unwrap_or_else(PoisonError::into_inner);
+  let mut sessions: Vec<JoinedSession> = sharing
+    .joined
+    .iter()
+    .map(|(id, joined)| joined_session(id, joined))
+    .collect();
+  sessions.sort_by(|a, b| a.host.cmp(&b.host));
+  Ok(sessions)
+}
+
+/// Runs a read-only `sql` on `connection` of a joined session, returning `{columns, rows}`.
+#[tauri::command]
+pub async fn query_shared_session(
+  state: State<'_, AppState>,
+  session_id: String,
+  connection: String,
+  sql: String,
+) -> Result<serde_json::Value, String> {
+  if !sql_kind::is_read(&sql) {
+    return Err("Only single statements that read can run in a shared session".to_string());
+  }
+  let (address, pinned) = state
+    .sharing
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .joined
+    .get(&session_id)
+    .map(|j| (j.address.clone(), j.fingerprint))
+    .ok_or_else(|| format!("Shared session '{}' not found", session_id))?;
+  send(
+    guest_key(&state)?,
+    &address,
+    pinned,
+    &Request::Query { connection, sql },
+  )
+  .await
+}
+
+#[tauri::command]
+pub fn leave_shared_session(state: State<'_, AppState>, session_id: String) {
+  state
+    .sharing
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .joined
+    .remove(&session_id);
+}
@@ -0,0 +1,347 @@
+//! Library of named SQL, Redis and MongoDB snippets, organized in folders and kept per
+//! workspace in the app store. Snippets can declare parameters (`{{name}}` placeholders) and
+//! be exported to a file to share them.
+//!
+//! SQL and Redis snippets run through the raw executors, so they get the same read-only
+//! checks, history and auditing as statements typed in the console.
+
+use std::collections::HashMap;
+
+use mongodb::bson::{Bson, Document};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db::{self, SqlPool};
+use crate::templates::{self, ParamKind, TemplateParam};
+use crate::{audit, connections, history, readonly, store, timeouts, workspaces, AppState};
+
+const EXPORT_FORMAT: &str = "spectra-snippets";
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SnippetKind {
+  Sql,
+  /// A Redis command line.
+  Redis,
+  /// A MongoDB database command document, as (extended) JSON.
+  Mongo,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Snippet {
+  /// Assigned on first save when empty.
+  #[serde(default)]
+  pub id: String,
+  pub name: String,
+  /// Slash-separated folder path; empty for the top level.
+  #[serde(default)]
+  pub folder: String,
+  pub kind: SnippetKind,
+  #[serde(default)]
+  pub description: Option<String>,
+  pub body: String,
+  #[serde(default)]
+  pub params: Vec<TemplateParam>,
+  /// Database a MongoDB snippet runs against.
+  #[serde(default)]
+  pub database: Option<String>,
+  #[serde(default)]
+  pub updated_at: i64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnippetFile {
+  format: String,
+  version: u32,
+  snippets: Vec<Snippet>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetRun {
+  /// The snippet with its parameters filled in.
+  pub statement: String,
+  /// What the matching raw executor returns: JSON rows, a row count message, or the Redis
+  /// reply; the command's reply as JSON for MongoDB.
+  pub result: String,
+}
+
+fn normalize_folder(folder: &str) -> String {
+  folder
+    .split('/')
+    .map(str::trim)
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+fn in_folder(snippet: &Snippet, folder: &str) -> bool {
+  folder.is_empty()
+    || snippet.folder == folder
+    || snippet
+      .folder
+      .strip_prefix(folder)
+      .is_some_and(|rest| rest.starts_with('/'))
+}
+
+async fn all_snippets(state: &AppState) -> Result<Vec<Snippet>, String> {
+  let pool = store::pool(state)?;
+  let rows: Vec<(String,)> = sqlx::query_as(
+    "SELECT config FROM snippets WHERE workspace = ? \
+     ORDER BY folder COLLATE NOCASE, name COLLATE NOCASE",
+  )
+  .bind(workspaces::current(state))
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  rows
+    .iter()
+    .map(|(config,)| serde_json::from_str(config).map_err(|e| e.to_string()))
+    .collect()
+}
+
+async fn store_snippet(state: &AppState, snippet: Snippet) -> Result<Snippet, String> {
+  if snippet.name.trim().is_empty() {
+    return Err("Snippet name cannot be empty".to_string());
+  }
+  if snippet.body.trim().is_empty() {
+    return Err("Snippet body cannot be empty".to_string());
+  }
+  let mut snippet = snippet;
+  if snippet.id.trim().is_empty() {
+    snippet.id = crate::next_id(&format!("snippet-{}", store::now_ms()));
+  }
+  snippet.folder = normalize_folder(&snippet.folder);
+  snippet.updated_at = store::now_ms();
+
+  let pool = store::pool(state)?;
+  let config = serde_json::to_string(&snippet).map_err(|e| e.to_string())?;
+  sqlx::query(
+    "INSERT INTO snippets (id, workspace, folder, name, config, updated_at) \
+     VALUES (?, ?, ?, ?, ?, ?) \
+     ON CONFLICT(id) DO UPDATE SET folder = excluded.folder, name = excluded.name, \
+     config = excluded.config, updated_at = excluded.updated_at",
+  )
+  .bind(&snippet.id)
+  .bind(workspaces::current(state))
+  .bind(&snippet.folder)
+  .bind(&snippet.name)
+  .bind(config)
+  .bind(snippet.updated_at)
+  .execute(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(snippet)
+}
+
+/// Creates or replaces a snippet and returns it as stored.
+#[tauri::command]
+pub async fn save_snippet(state: State<'_, AppState>, snippet: Snippet) -> Result<Snippet, String> {
+  store_snippet(&state, snippet).await
+}
+
+/// Snippets of the active workspace, in `folder` and its subfolders when given.
+#[tauri::command]
+pub async fn list_snippets(
+  state: State<'_, AppState>,
+  folder: Option<String>,
+) -> Result<Vec<Snippet>, String> {
+  let folder = normalize_folder(folder.as_deref().unwrap_or(""));
+  let mut snippets = all_snippets(&state).await?;
+  snippets.retain(|s| in_folder(s, &folder));
+  Ok(snippets)
+}
+
+#[tauri::command]
+pub async fn delete_snippet(state: State<'_, AppState>, snippet_id: String) -> Result<(), String> {
+  let pool = store::pool(&state)?;
+  let result = sqlx::query("DELETE FROM snippets WHERE id = ? AND workspace = ?")
+    .bind(&snippet_id)
+    .bind(workspaces::current(&state))
+    .execute(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  if result.rows_affected() == 0 {
+    return Err(format!("Unknown snippet: {}", snippet_id));
+  }
+  Ok(())
+}
+
+async fn find_snippet(state: &AppState, snippet_id: &str) -> Result<Snippet, String> {
+  all_snippets(state)
+    .await?
+    .into_iter()
+    .find(|s| s.id == snippet_id)
+    .ok_or_else(|| format!("Unknown snippet: {}", snippet_id))
+}
+
+/// Value of each declared parameter, checked against its kind but otherwise as given.
+fn raw_values<'a>(
+  declared: &'a [TemplateParam],
+  params: &HashMap<String, String>,
+) -> Result<HashMap<&'a str, String>, String> {
+  let mut values = HashMap::new();
+  for p in declared {
+    let value = params
+      .get(&p.name)
+      .or(p.default.as_ref())
+      .ok_or_else(|| format!("Missing snippet parameter: {}", p.name))?;
+    if p.kind == ParamKind::Number && value.trim().parse::<f64>().is_err() {
+      return Err(format!("Parameter '{}' must be a number", p.name));
+    }
+    values.insert(p.name.as_str(), value.clone());
+  }
+  Ok(values)
+}
+
+async fn run_mongo(
+  state: &AppState,
+  connection: &str,
+  snippet: &Snippet,
+  params: &HashMap<String, String>,
+) -> Result<SnippetRun, String> {
+  let database = snippet
+    .database
+    .as_deref()
+    .filter(|d| !d.is_empty())
+    .ok_or_else(|| format!("Snippet '{}' has no database to run in", snippet.name))?;
+  // Values go in as JSON strings, numbers as numbers
+  let mut values = raw_values(&snippet.params, params)?;
+  for p in &snippet.params {
+    if p.kind != ParamKind::Number {
+      if let Some(value) = values.get_mut(p.name.as_str()) {
+        *value = serde_json::Value::String(value.clone()).to_string();
+      }
+    }
+  }
+  let statement = templates::substitute(&snippet.id, &snippet.body, &values)?;
+  let json: serde_json::Value = serde_json::from_str(&statement)
+    .map_err(|e| format!("Snippet '{}' is not valid JSON: {}", snippet.name, e))?;
+  let Bson::Document(command) = Bson::try_from(json).map_err(|e| e.to_string())? else {
+    return Err("A MongoDB snippet must be a command document".to_string());
+  };
+  readonly::check_mongo_command(state, connection, &command)?;
+  let client = connections::mongodb(state, Some(connection))?;
+
+  let started = std::time::Instant::now();
+  let timeout = timeouts::resolve(state, connection, None);
+  let executed: Result<Document, String> = timeouts::run(timeout, async {
+    client
+      .database(database)
+      .run_command(command.clone())
+      .await
+      .map_err(|e| e.to_string())
+  })
+  .await;
+  history::record(
+    state,
+    connection,
+    &statement,
+    started.elapsed(),
+    executed.as_ref().map(|_| 1).map_err(String::as_str),
+  )
+  .await;
+  let reply = executed?;
+  if !readonly::is_mongo_read(&command) {
+    audit::record(state, connection, &statement, serde_json::json!([]), None).await;
+  }
+  Ok(SnippetRun {
+    statement,
+    result: Bson::Document(reply).into_relaxed_extjson().to_string(),
+  })
+}
+
+/// Fills in a snippet's parameters and runs it on `connection`. `workspace` picks the
+/// variables SQL snippets are resolved against, like in the console.
+#[tauri::command]
+pub async fn run_snippet(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  connection: String,
+  snippet_id: String,
+  params: HashMap<String, String>,
+  workspace: Option<String>,
+) -> Result<SnippetRun, String> {
+  let snippet = find_snippet(&state, &snippet_id).await?;
+  match snippet.kind {
+    SnippetKind::Sql => {
+      let pool = db::sql_pool(&state, &connection)?;
+      let values = templates::param_values(&snippet.params, &pool, &params)?;
+      let statement = templates::substitute(&snippet.id, &snippet.body, &values)?;
+      let (sql, id) = (statement.clone(), Some(connection));
+      let result = match pool {
+        SqlPool::MySql(_) => {
+          crate::mysql_execute_raw(app.clone(), app.state(), sql, workspace, id, None, None).await?
+        }
+        SqlPool::Postgres(_) => {
+          crate::postgres_execute_raw(app.clone(), app.state(), sql, workspace, id, None, None)
+            .await?
+        }
+        SqlPool::Sqlite(_) => {
+          crate::sqlite_execute_raw(app.clone(), app.state(), sql, workspace, id, None, None)
+            .await?
+        }
+      };
+      Ok(SnippetRun { statement, result })
+    }
+    SnippetKind::Redis => {
+      let values = raw_values(&snippet.params, &params)?;
+      // The raw executor splits arguments on whitespace
+      if let Some((name, _)) = values.iter().find(|(_, v)| v.contains(char::is_whitespace)) {
+        return Err(format!(
+          "Parameter '{}' cannot contain whitespace in a Redis snippet",
+          name
+        ));
+      }
+      let statement = templates::substitute(&snippet.id, &snippet.body, &values)?;
+      let result =
+        crate::redis_execute_raw(app.state(), statement.clone(), Some(connection), None).await?;
+      Ok(SnippetRun { statement, result })
+    }
+    SnippetKind::Mongo => run_mongo(&state, &connection, &snippet, &params).await,
+  }
+}
+
+/// Writes the snippets of `folder` (all of them when not given) to `path` as JSON. Returns
+/// the number of snippets written.
+#[tauri::command]
+pub async fn export_snippets(
+  state: State<'_, AppState>,
+  path: String,
+  folder: Option<String>,
+) -> Result<usize, String> {
+  let snippets = list_snippets(state, folder).await?;
+  let file = SnippetFile {
+    format: EXPORT_FORMAT.to_string(),
+    version: EXPORT_VERSION,
+    snippets,
+  };
+  let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+  std::fs::write(&path, json).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+  Ok(file.snippets.len())
+}
+
+/// Adds the snippets of an exported file to the active workspace, replacing snippets with
+/// the same id. Returns the number imported.
+#[tauri::command]
+pub async fn import_snippets(state: State<'_, AppState>, path: String) -> Result<usize, String> {
+  let json = std::fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+  let file: SnippetFile =
+    serde_json::from_str(&json).map_err(|e| format!("Not a snippet file: {}", e))?;
+  if file.format != EXPORT_FORMAT {
+    return Err("Not a snippet file".to_string());
+  }
+  if file.version > EXPORT_VERSION {
+    return Err(format!(
+      "Snippet file version {} is newer than this app supports",
+      file.version
+    ));
+  }
+  let count = file.snippets.len();
+  for snippet in file.snippets {
+    store_snippet(&state, snippet).await?;
+  }
+  Ok(count)
+}
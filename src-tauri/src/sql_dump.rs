@@ -0,0 +1,731 @@
+//! Logical dumps of tables to a `.sql` file: each table's `CREATE TABLE` followed by its rows
+//! as batched `INSERT` statements, which load back into the same engine without `mysqldump` or
+//! `pg_dump` installed. Dumps run as `sql_dump` background tasks and are written as
+//! `<path>.part` until complete, like file exports, and can be compressed while written.
+//!
+//! Values are read in the server's own text form (`::text` on Postgres, `CAST(... AS CHAR)`,
+//! or `HEX()` for binary columns, on MySQL and `quote()` on SQLite) so they load back
+//! unchanged. Indexes and foreign keys are added after every table's rows and sequence or
+//! auto-increment counters are carried over. Views, routines and user-defined types are left
+//! out.
+
+use std::fmt::Write as _;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use futures::{Stream, StreamExt};
+use sqlx::Row;
+use tauri::{AppHandle, Manager};
+
+use crate::db::{self, SqlPool};
+use crate::export::{Compression, Encoder};
+use crate::file_export::{self, io_error, PartFile, Progress};
+use crate::schema::{self, ColumnKind};
+use crate::tasks::{self, TaskHandle, TaskSpec};
+use crate::{ident, masking, transfer, AppState};
+
+/// Rows per `INSERT` unless the options say otherwise.
+const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// What goes into the dump; everything is optional.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlDumpOptions {
+  /// Tables to dump; every table when missing or empty.
+  pub tables: Option<Vec<String>>,
+  /// Precede each `CREATE TABLE` with `DROP TABLE IF EXISTS` (off by default).
+  pub drop_if_exists: Option<bool>,
+  /// Rows per `INSERT` statement (100 by default).
+  pub batch_size: Option<usize>,
+  /// `none` (default), `gzip` or `zstd`.
+  pub compression: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlDumpSummary {
+  pub path: String,
+  pub tables: Vec<String>,
+  pub rows: u64,
+  /// Size of the file written, after compression.
+  pub bytes: u64,
+  pub elapsed_ms: u64,
+}
+
+/// How a value read for the dump is written as a literal.
+#[derive(Clone, Copy)]
+enum Literal {
+  /// String literal of the value's text; the server converts it on insert.
+  Text,
+  /// The text of a number, written bare when it is a finite number.
+  Number,
+  /// Hex digits of a binary value, written as `X'...'`.
+  Hex,
+  /// Already a literal (SQLite's `quote()`).
+  Sql,
+}
+
+/// Everything written for one table.
+struct TableDump {
+  name: String,
+  /// The table as statements address it.
+  table: String,
+  /// Columns whose values are dumped; generated ones are left to the database.
+  columns: Vec<String>,
+  literals: Vec<Literal>,
+  /// Statements before the rows (`DROP TABLE`, `CREATE SEQUENCE`, `CREATE TABLE`, ...).
+  create: Vec<String>,
+  /// `INSERT INTO t (a, b)` clause the rows follow.
+  insert: String,
+  /// Reads the rows, one text expression per column of `columns`.
+  select: String,
+  /// Statements after the rows of every table (indexes, foreign keys, triggers).
+  finish: Vec<String>,
+  /// Statements carrying sequence and `AUTOINCREMENT` counters over, after `finish`.
+  counters: Vec<String>,
+}
+
+impl TableDump {
+  fn new(name: &str, table: String) -> Self {
+    TableDump {
+      name: name.to_string(),
+      insert: format!("INSERT INTO {}", table),
+      table,
+      columns: Vec::new(),
+      literals: Vec::new(),
+      create: Vec::new(),
+      select: String::new(),
+      finish: Vec::new(),
+      counters: Vec::new(),
+    }
+  }
+
+  /// Adds a dumped column read through `expression`.
+  fn column(&mut self, name: &str, expression: String, literal: Literal) {
+    self.columns.push(name.to_string());
+    self.literals.push(literal);
+    self.select.push_str(if self.select.is_empty() {
+      "SELECT "
+    } else {
+      ", "
+    });
+    self.select.push_str(&expression);
+  }
+
+  /// Completes `insert` and `select` once every column was added.
+  fn close(&mut self, pool: &SqlPool, overriding: bool) {
+    let columns: Vec<String> = self.columns.iter().map(|c| pool.quote_ident(c)).collect();
+    self.insert = format!(
+      "{} ({}){}",
+      self.insert,
+      columns.join(", "),
+      if overriding {
+        " OVERRIDING SYSTEM VALUE"
+      } else {
+        ""
+      }
+    );
+    self.select = format!("{} FROM {}", self.select, self.table);
+  }
+}
+
+fn literal_for(data_type: &str) -> Literal {
+  match schema::column_kind(data_type) {
+    ColumnKind::Integer | ColumnKind::Decimal | ColumnKind::Float => Literal::Number,
+    _ => Literal::Text,
+  }
+}
+
+fn render(pool: &SqlPool, literal: Literal, value: Option<&str>) -> String {
+  let Some(value) = value else {
+    return "NULL".to_string();
+  };
+  match literal {
+    Literal::Number if value.parse::<f64>().is_ok_and(f64::is_finite) => value.to_string(),
+    Literal::Text | Literal::Number => pool.quote_literal(value),
+    Literal::Hex => format!("X'{}'", value),
+    Literal::Sql => value.to_string(),
+  }
+}
+
+/// Base tables of the connection, or the ones of `requested` resolved to their catalog names.
+async fn selected_tables(pool: &SqlPool, requested: &[String]) -> Result<Vec<String>, String> {
+  let names: Vec<(String,)> = match pool {
+    SqlPool::MySql(pool) => {
+      sqlx::query_as(
+        "SELECT CAST(TABLE_NAME AS CHAR) FROM information_schema.TABLES \
+         WHERE TABLE_SCHEMA = DATABASE() AND TABLE_TYPE = 'BASE TABLE' ORDER BY TABLE_NAME",
+      )
+      .fetch_all(pool)
+      .await
+    }
+    SqlPool::Postgres(pool) => {
+      sqlx::query_as(
+        "SELECT table_name::text FROM information_schema.tables \
+         WHERE table_schema = $1 AND table_type = 'BASE TABLE' ORDER BY table_name",
+      )
+      .bind(db::PG_DEFAULT_SCHEMA)
+      .fetch_all(pool)
+      .await
+    }
+    SqlPool::Sqlite(pool) => {
+      sqlx::query_as(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' \
+         ORDER BY name",
+      )
+      .fetch_all(pool)
+      .await
+    }
+  }
+  .map_err(|e| e.to_string())?;
+  let names: Vec<String> = names.into_iter().map(|(name,)| name).collect();
+  if requested.is_empty() {
+    return Ok(names);
+  }
+  let mut tables: Vec<String> = Vec::new();
+  for table in requested {
+    let name = ident::resolve(pool.dialect(), table, &names)?.to_string();
+    if !tables.contains(&name) {
+      tables.push(name);
+    }
+  }
+  Ok(tables)
+}
+
+/// MySQL column types whose values are bytes rather than text.
+fn mysql_binary(column_type: &str) -> bool {
+  let base = column_type.split(['(', ' ']).next().unwrap_or_default();
+  schema::column_kind(column_type) == ColumnKind::Binary
+    || matches!(
+      base,
+      "bit"
+        | "geometry"
+        | "point"
+        | "linestring"
+        | "polygon"
+        | "multipoint"
+        | "multilinestring"
+        | "multipolygon"
+        | "geometrycollection"
+        | "geomcollection"
+    )
+}
+
+async fn mysql_table(
+  pool: &SqlPool,
+  mysql: &sqlx::MySqlPool,
+  name: &str,
+  drop: bool,
+) -> Result<TableDump, String> {
+  let mut dump = TableDump::new(name, pool.table_ref(name));
+  let quoted = pool.quote_ident(name);
+  let row = sqlx::query(&format!("SHOW CREATE TABLE {}", quoted))
+    .fetch_one(mysql)
+    .await
+    .map_err(|e| e.to_string())?;
+  let create: String = row.try_get(1).map_err(|e| e.to_string())?;
+  if drop {
+    dump.create.push(format!("DROP TABLE IF EXISTS {}", quoted));
+  }
+  dump.create.push(create);
+
+  let columns: Vec<(String, String, String)> = sqlx::query_as(
+    "SELECT CAST(COLUMN_NAME AS CHAR), CAST(COLUMN_TYPE AS CHAR), CAST(EXTRA AS CHAR) \
+     FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? \
+     ORDER BY ORDINAL_POSITION",
+  )
+  .bind(name)
+  .fetch_all(mysql)
+  .await
+  .map_err(|e| e.to_string())?;
+  for (column, column_type, extra) in columns {
+    // `VIRTUAL GENERATED` / `STORED GENERATED`, but not `DEFAULT_GENERATED`
+    if extra
+      .split_whitespace()
+      .any(|word| word.eq_ignore_ascii_case("GENERATED"))
+    {
+      continue;
+    }
+    let quoted = pool.quote_ident(&column);
+    if mysql_binary(&column_type.to_lowercase()) {
+      dump.column(&column, format!("HEX({})", quoted), Literal::Hex);
+    } else {
+      let literal = literal_for(&column_type);
+      dump.column(&column, format!("CAST({} AS CHAR)", quoted), literal);
+    }
+  }
+  dump.close(pool, false);
+  Ok(dump)
+}
+
+/// Sequence a `nextval('users_id_seq'::regclass)` default draws from, as written there.
+fn nextval_sequence(default: &str) -> Option<&str> {
+  let rest = default.strip_prefix("nextval('")?;
+  rest.find("'::regclass)").map(|end| &rest[..end])
+}
+
+async fn postgres_table(
+  pool: &SqlPool,
+  pg: &sqlx::PgPool,
+  schema: &str,
+  name: &str,
+  drop: bool,
+) -> Result<TableDump, String> {
+  let table = pool.table_ref_in(schema, name);
+  let mut dump = TableDump::new(name, table.clone());
+  let columns: Vec<(String, String, bool, Option<String>, String, String)> = sqlx::query_as(
+    "SELECT a.attname::text, format_type(a.atttypid, a.atttypmod), a.attnotnull, \
+     pg_get_expr(d.adbin, d.adrelid), a.attidentity::text, a.attgenerated::text \
+     FROM pg_attribute a \
+     LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+     WHERE a.attrelid = to_regclass($1) AND a.attnum > 0 AND NOT a.attisdropped \
+     ORDER BY a.attnum",
+  )
+  .bind(&table)
+  .fetch_all(pg)
+  .await
+  .map_err(|e| e.to_string())?;
+  let constraints: Vec<(String, String, String)> = sqlx::query_as(
+    "SELECT conname::text, contype::text, pg_get_constraintdef(oid) FROM pg_constraint \
+     WHERE conrelid = to_regclass($1) AND contype IN ('p', 'u', 'c', 'f', 'x') AND conislocal \
+     ORDER BY contype = 'p' DESC, conname",
+  )
+  .bind(&table)
+  .fetch_all(pg)
+  .await
+  .map_err(|e| e.to_string())?;
+  // Indexes backing a constraint come with the constraint
+  let indexes: Vec<(String,)> = sqlx::query_as(
+    "SELECT pg_get_indexdef(i.indexrelid) FROM pg_index i \
+     WHERE i.indrelid = to_regclass($1) AND NOT EXISTS ( \
+       SELECT 1 FROM pg_constraint c WHERE c.conindid = i.indexrelid \
+       AND c.conrelid = i.indrelid AND c.contype IN ('p', 'u', 'x')) \
+     ORDER BY i.indexrelid",
+  )
+  .bind(&table)
+  .fetch_all(pg)
+  .await
+  .map_err(|e| e.to_string())?;
+
+  if drop {
+    dump
+      .create
+      .push(format!("DROP TABLE IF EXISTS {} CASCADE", table));
+  }
+  let mut lines = Vec::new();
+  let mut owned = Vec::new();
+  let mut overriding = false;
+  for (column, data_type, not_null, default, identity, generated) in &columns {
+    let quoted = pool.quote_ident(column);
+    let mut line = format!("  {} {}", quoted, data_type);
+    match (generated.as_str(), identity.as_str(), default) {
+      ("s", _, Some(expression)) => {
+        let _ = write!(line, " GENERATED ALWAYS AS ({}) STORED", expression);
+      }
+      (_, "a" | "d", _) => {
+        overriding |= identity == "a";
+        let kind = if identity == "a" {
+          "ALWAYS"
+        } else {
+          "BY DEFAULT"
+        };
+        let _ = write!(line, " GENERATED {} AS IDENTITY", kind);
+        dump.counters.push(format!(
+          "SELECT setval(pg_get_serial_sequence({}, {}), COALESCE(MAX({}), 0) + 1, false) \
+           FROM {}",
+          pool.quote_literal(&table),
+          pool.quote_literal(column),
+          quoted,
+          table
+        ));
+      }
+      (_, _, Some(default)) => {
+        let _ = write!(line, " DEFAULT {}", default);
+        if let Some(sequence) = nextval_sequence(default) {
+          dump
+            .create
+            .push(format!("CREATE SEQUENCE IF NOT EXISTS {}", sequence));
+          owned.push(format!(
+            "ALTER SEQUENCE {} OWNED BY {}.{}",
+            sequence, table, quoted
+          ));
+          dump.counters.push(format!(
+            "SELECT setval({}, COALESCE(MAX({}), 0) + 1, false) FROM {}",
+            pool.quote_literal(sequence),
+            quoted,
+            table
+          ));
+        }
+      }
+      _ => {}
+    }
+    if *not_null {
+      line.push_str(" NOT NULL");
+    }
+    lines.push(line);
+    if generated != "s" {
+      dump.column(column, format!("{}::text", quoted), literal_for(data_type));
+    }
+  }
+  for (constraint, kind, definition) in &constraints {
+    let constraint = format!("CONSTRAINT {} {}", pool.quote_ident(constraint), definition);
+    // Foreign keys wait until every table is loaded
+    if kind == "f" {
+      dump
+        .finish
+        .push(format!("ALTER TABLE {} ADD {}", table, constraint));
+    } else {
+      lines.push(format!("  {}", constraint));
+    }
+  }
+  dump.create.push(format!(
+    "CREATE TABLE {} (\n{}\n)",
+    table,
+    lines.join(",\n")
+  ));
+  dump.create.extend(owned);
+  dump
+    .finish
+    .splice(0..0, indexes.into_iter().map(|(index,)| index));
+  dump.close(pool, overriding);
+  Ok(dump)
+}
+
+async fn sqlite_table(
+  pool: &SqlPool,
+  sqlite: &sqlx::SqlitePool,
+  name: &str,
+  drop: bool,
+) -> Result<TableDump, String> {
+  let mut dump = TableDump::new(name, pool.table_ref(name));
+  let (create,): (String,) =
+    sqlx::query_as("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1")
+      .bind(name)
+      .fetch_one(sqlite)
+      .await
+      .map_err(|e| e.to_string())?;
+  if drop {
+    dump
+      .create
+      .push(format!("DROP TABLE IF EXISTS {}", pool.quote_ident(name)));
+  }
+  let autoincrement = create.to_uppercase().contains("AUTOINCREMENT");
+  dump.create.push(create);
+
+  // `hidden` is 2 or 3 for generated columns
+  let columns: Vec<(String, i64)> =
+    sqlx::query_as("SELECT name, hidden FROM pragma_table_xinfo(?1) ORDER BY cid")
+      .bind(name)
+      .fetch_all(sqlite)
+      .await
+      .map_err(|e| e.to_string())?;
+  for (column, hidden) in columns {
+    if hidden == 0 {
+      let expression = format!("quote({})", pool.quote_ident(&column));
+      dump.column(&column, expression, Literal::Sql);
+    }
+  }
+
+  let rest: Vec<(String,)> = sqlx::query_as(
+    "SELECT sql FROM sqlite_master WHERE tbl_name = ?1 AND type IN ('index', 'trigger') \
+     AND sql IS NOT NULL ORDER BY type, name",
+  )
+  .bind(name)
+  .fetch_all(sqlite)
+  .await
+  .map_err(|e| e.to_string())?;
+  dump.finish.extend(rest.into_iter().map(|(sql,)| sql));
+  if autoincrement {
+    let seq: Option<(i64,)> = sqlx::query_as("SELECT seq FROM sqlite_sequence WHERE name = ?1")
+      .bind(name)
+      .fetch_optional(sqlite)
+      .await
+      .map_err(|e| e.to_string())?;
+    if let Some((seq,)) = seq {
+      let literal = pool.quote_literal(name);
+      dump.counters.push(format!(
+        "DELETE FROM sqlite_sequence WHERE name = {}",
+        literal
+      ));
+      dump.counters.push(format!(
+        "INSERT INTO sqlite_sequence (name, seq) VALUES ({}, {})",
+        literal, seq
+      ));
+    }
+  }
+  dump.close(pool, false);
+  Ok(dump)
+}
+
+/// Dump of table `name`, which lives in `schema` on Postgres.
+async fn table_dump(
+  pool: &SqlPool,
+  schema: &str,
+  name: &str,
+  drop: bool,
+) -> Result<TableDump, String> {
+  match pool {
+    SqlPool::MySql(mysql) => mysql_table(pool, mysql, name, drop).await,
+    SqlPool::Postgres(pg) => postgres_table(pool, pg, schema, name, drop).await,
+    SqlPool::Sqlite(sqlite) => sqlite_table(pool, sqlite, name, drop).await,
+  }
+}
+
+/// The DDL recreating `table` as a dump would: `CREATE TABLE` with its constraints, then its
+/// indexes, foreign keys and triggers, each statement ending in `;`. `schema` is the table's
+/// Postgres schema.
+pub(crate) async fn table_ddl(pool: &SqlPool, schema: &str, table: &str) -> Result<String, String> {
+  let table = pool.resolve_table_in(schema, table).await?;
+  let dump = table_dump(pool, schema, &table, false).await?;
+  Ok(
+    dump
+      .create
+      .iter()
+      .chain(&dump.finish)
+      .fold(String::new(), |mut out, statement| {
+        let _ = writeln!(out, "{};", statement);
+        out
+      }),
+  )
+}
+
+/// Statements at the top and bottom of the file for the dialect.
+fn preamble(pool: &SqlPool) -> (&'static [&'static str], &'static [&'static str]) {
+  match pool {
+    SqlPool::MySql(_) => (
+      &["SET NAMES utf8mb4", "SET FOREIGN_KEY_CHECKS = 0"],
+      &["SET FOREIGN_KEY_CHECKS = 1"],
+    ),
+    SqlPool::Postgres(_) => (
+      &[
+        "SET client_encoding = 'UTF8'",
+        "SET standard_conforming_strings = on",
+      ],
+      &[],
+    ),
+    SqlPool::Sqlite(_) => (
+      &["PRAGMA foreign_keys = OFF", "BEGIN TRANSACTION"],
+      &["COMMIT"],
+    ),
+  }
+}
+
+fn statements(out: &mut impl Write, statements: &[impl AsRef<str>]) -> Result<(), String> {
+  for statement in statements {
+    writeln!(out, "{};", statement.as_ref()).map_err(io_error)?;
+  }
+  Ok(())
+}
+
+/// Counts of one dump in progress.
+struct Written<'a> {
+  rows: u64,
+  batch_size: usize,
+  progress: Progress<'a>,
+}
+
+/// Writes the rows of `rows` as `INSERT`s of at most `batch_size` rows each.
+async fn write_rows<R: Row>(
+  pool: &SqlPool,
+  dump: &TableDump,
+  mut rows: impl Stream<Item = Result<R, sqlx::Error>> + Unpin,
+  values: impl Fn(&R) -> Result<Vec<Option<String>>, sqlx::Error>,
+  out: &mut impl Write,
+  written: &mut Written<'_>,
+) -> Result<(), String> {
+  let message = format!("Dumping {}", dump.name);
+  let mut in_batch = 0;
+  while let Some(row) = rows.next().await {
+    let row = row.map_err(|e| e.to_string())?;
+    let values = values(&row).map_err(|e| e.to_string())?;
+    let literals: Vec<String> = values
+      .iter()
+      .zip(&dump.literals)
+      .map(|(value, literal)| render(pool, *literal, value.as_deref()))
+      .collect();
+    if in_batch == 0 {
+      write!(out, "{} VALUES\n  ({})", dump.insert, literals.join(", ")).map_err(io_error)?;
+    } else {
+      write!(out, ",\n  ({})", literals.join(", ")).map_err(io_error)?;
+    }
+    in_batch += 1;
+    if in_batch == written.batch_size {
+      writeln!(out, ";").map_err(io_error)?;
+      in_batch = 0;
+    }
+    written.rows += 1;
+    written.progress.report(written.rows, &message);
+  }
+  if in_batch > 0 {
+    writeln!(out, ";").map_err(io_error)?;
+  }
+  Ok(())
+}
+
+fn text_values<R: Row>(row: &R) -> Result<Vec<Option<String>>, sqlx::Error>
+where
+  usize: sqlx::ColumnIndex<R>,
+  for<'r> Option<String>: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+{
+  (0..row.len()).map(|i| row.try_get(i)).collect()
+}
+
+async fn dump_rows(
+  pool: &SqlPool,
+  dump: &TableDump,
+  out: &mut impl Write,
+  written: &mut Written<'_>,
+) -> Result<(), String> {
+  if dump.columns.is_empty() {
+    return Ok(());
+  }
+  match pool {
+    SqlPool::MySql(mysql) => {
+      let rows = sqlx::query(&dump.select).fetch(mysql);
+      write_rows(pool, dump, rows, text_values, out, written).await
+    }
+    SqlPool::Postgres(pg) => {
+      let rows = sqlx::query(&dump.select).fetch(pg);
+      write_rows(pool, dump, rows, text_values, out, written).await
+    }
+    SqlPool::Sqlite(sqlite) => {
+      let rows = sqlx::query(&dump.select).fetch(sqlite);
+      write_rows(pool, dump, rows, text_values, out, written).await
+    }
+  }
+}
+
+/// Carries out a dump task: writes the tables `options` picks on `connection` to `path`.
+pub async fn write_dump(
+  task: &TaskHandle,
+  connection: String,
+  path: String,
+  options: SqlDumpOptions,
+) -> Result<SqlDumpSummary, String> {
+  let started = Instant::now();
+  let state = task.app.state::<AppState>();
+  let pool = db::sql_pool(&state, &connection)?;
+  let drop_first = options.drop_if_exists.unwrap_or(false);
+  let compression = Compression::parse(options.compression.as_deref().unwrap_or(""))?;
+  let requested = options.tables.unwrap_or_default();
+  let tables = selected_tables(&pool, &requested).await?;
+  if tables.is_empty() {
+    return Err("There are no tables to dump".to_string());
+  }
+
+  task.progress(0, None, Some("Reading table definitions"));
+  let mask = masking::active(&state, &connection);
+  let mut dumps = Vec::with_capacity(tables.len());
+  for table in &tables {
+    let dump = table_dump(&pool, db::PG_DEFAULT_SCHEMA, table, drop_first).await?;
+    if let Some(column) = mask
+      .as_ref()
+      .and_then(|mask| dump.columns.iter().find(|c| mask.masks(c)))
+    {
+      return Err(format!(
+        "Column {}.{} is masked; turn masked mode off to dump it",
+        table, column
+      ));
+    }
+    dumps.push(dump);
+  }
+
+  let target = PathBuf::from(&path);
+  let (part, file) = PartFile::create(&target)?;
+  let mut out = Encoder::new(BufWriter::new(file), compression).map_err(io_error)?;
+  let (head, tail) = preamble(&pool);
+  writeln!(
+    out,
+    "-- SQL dump of {} table(s) on {}\n",
+    dumps.len(),
+    match pool.dialect() {
+      ident::Dialect::MySql => "MySQL",
+      ident::Dialect::Postgres => "PostgreSQL",
+      ident::Dialect::Sqlite => "SQLite",
+    }
+  )
+  .map_err(io_error)?;
+  statements(&mut out, head)?;
+  let mut written = Written {
+    rows: 0,
+    batch_size: options.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1),
+    progress: Progress::new(task),
+  };
+  for dump in &dumps {
+    writeln!(out, "\n-- Table {}\n", dump.name).map_err(io_error)?;
+    statements(&mut out, &dump.create)?;
+    writeln!(out).map_err(io_error)?;
+    dump_rows(&pool, dump, &mut out, &mut written).await?;
+  }
+  if dumps
+    .iter()
+    .any(|dump| !dump.finish.is_empty() || !dump.counters.is_empty())
+  {
+    writeln!(out, "\n-- Indexes, foreign keys and counters\n").map_err(io_error)?;
+    for dump in &dumps {
+      statements(&mut out, &dump.finish)?;
+    }
+    for dump in &dumps {
+      statements(&mut out, &dump.counters)?;
+    }
+  }
+  if !tail.is_empty() {
+    writeln!(out).map_err(io_error)?;
+    statements(&mut out, tail)?;
+  }
+
+  let file = out
+    .finish()
+    .and_then(|buffered| {
+      buffered
+        .into_inner()
+        .map_err(std::io::IntoInnerError::into_error)
+    })
+    .map_err(io_error)?;
+  file.sync_all().map_err(io_error)?;
+  let bytes = file.metadata().map_or(0, |m| m.len());
+  drop(file);
+  part.commit(&target)?;
+
+  transfer::record(
+    &state,
+    &connection,
+    transfer::Category::Export,
+    usize::try_from(written.rows).unwrap_or(usize::MAX),
+    usize::try_from(bytes).unwrap_or(usize::MAX),
+  );
+  Ok(SqlDumpSummary {
+    path,
+    tables,
+    rows: written.rows,
+    bytes,
+    elapsed_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+  })
+}
+
+/// Dumps tables of `connection` to the `.sql` file at `path` as `CREATE TABLE` statements and
+/// batched `INSERT`s in the background and returns the task id; the task's result is a
+/// [`SqlDumpSummary`].
+#[tauri::command]
+pub async fn export_table_sql(
+  app: AppHandle,
+  connection: String,
+  path: String,
+  options: Option<SqlDumpOptions>,
+) -> Result<String, String> {
+  let options = options.unwrap_or_default();
+  let state = app.state::<AppState>();
+  let pool = db::sql_pool(&state, &connection)?;
+  selected_tables(&pool, options.tables.as_deref().unwrap_or_default()).await?;
+  Compression::parse(options.compression.as_deref().unwrap_or(""))?;
+  file_export::check_folder(&path)?;
+  Ok(tasks::start(
+    &app,
+    TaskSpec::SqlDump {
+      connection,
+      path,
+      options,
+    },
+  ))
+}
@@ -0,0 +1,222 @@
+//! What a raw SQL statement does: read, write, or neither (transaction control, `USE`, ...).
+//! Read-only connections let only reads through and the audit log records writes, so both
+//! judge statements here.
+
+use sqlparser::ast::{Query, SetExpr, Statement};
+use sqlparser::dialect::{Dialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect};
+use sqlparser::parser::Parser;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SqlKind {
+  /// Only reads data or metadata.
+  Read,
+  /// Changes data, schema or session state.
+  Write,
+  /// Neither, e.g. `BEGIN` or `USE`.
+  Other,
+}
+
+/// Whether `sql` only reads.
+pub fn is_read(sql: &str) -> bool {
+  kind(sql) == SqlKind::Read
+}
+
+/// Whether `sql` changes data, schema or session state.
+pub fn is_write(sql: &str) -> bool {
+  kind(sql) == SqlKind::Write
+}
+
+/// What `sql` (one statement or several) does. It is parsed with each engine's dialect in
+/// turn; when none accepts it, its leading keyword decides.
+pub fn kind(sql: &str) -> SqlKind {
+  let dialects: [&dyn Dialect; 3] = [&PostgreSqlDialect {}, &MySqlDialect {}, &SQLiteDialect {}];
+  let parsed = dialects
+    .iter()
+    .find_map(|dialect| Parser::parse_sql(*dialect, sql).ok())
+    .filter(|statements| !statements.is_empty());
+  let Some(statements) = parsed else {
+    return match keyword_kind(sql) {
+      // Whatever follows another `;` is unknown
+      SqlKind::Read if sql.trim().trim_end_matches(';').contains(';') => SqlKind::Other,
+      kind => kind,
+    };
+  };
+  let kinds: Vec<SqlKind> = statements.iter().map(statement_kind).collect();
+  if kinds.contains(&SqlKind::Write) {
+    SqlKind::Write
+  } else if kinds.iter().all(|kind| *kind == SqlKind::Read) {
+    SqlKind::Read
+  } else {
+    SqlKind::Other
+  }
+}
+
+fn statement_kind(statement: &Statement) -> SqlKind {
+  match statement {
+    Statement::Query(query) if query_writes(query) => SqlKind::Write,
+    // Plain EXPLAIN only plans the statement
+    Statement::Explain {
+      analyze: true,
+      statement,
+      ..
+    } if statement_kind(statement) == SqlKind::Write => SqlKind::Write,
+    Statement::Pragma { is_eq: true, .. } => SqlKind::Write,
+    Statement::Query(_) | Statement::Explain { .. } | Statement::Pragma { .. } => SqlKind::Read,
+    other => keyword_kind(&other.to_string()),
+  }
+}
+
+/// Whether `query` writes: `SELECT ... INTO`, or an `INSERT`/`UPDATE` in its body or CTEs.
+fn query_writes(query: &Query) -> bool {
+  let ctes_write = query
+    .with
+    .iter()
+    .flat_map(|with| &with.cte_tables)
+    .any(|cte| query_writes(&cte.query));
+  ctes_write || set_expr_writes(&query.body)
+}
+
+fn set_expr_writes(body: &SetExpr) -> bool {
+  match body {
+    SetExpr::Select(select) => select.into.is_some(),
+    SetExpr::Query(query) => query_writes(query),
+    SetExpr::SetOperation { left, right, .. } => set_expr_writes(left) || set_expr_writes(right),
+    SetExpr::Insert(_) | SetExpr::Update(_) => true,
+    SetExpr::Values(_) | SetExpr::Table(_) => false,
+  }
+}
+
+/// `sql` from its first keyword on, past leading whitespace, opening parentheses and
+/// `--`, `#` and (nested) `/* */` comments.
+fn statement_start(sql: &str) -> &str {
+  let mut rest = sql;
+  loop {
+    rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '(');
+    if rest.starts_with("--") || rest.starts_with('#') {
+      rest = rest.find('\n').map_or("", |end| &rest[end..]);
+    } else if rest.starts_with("/*") {
+      let mut depth = 0;
+      let mut end = rest.len();
+      let bytes = rest.as_bytes();
+      let mut i = 0;
+      while i + 1 < bytes.len() {
+        match &bytes[i..i + 2] {
+          b"/*" => {
+            depth += 1;
+            i += 2;
+          }
+          b"*/" => {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+              end = i;
+              break;
+            }
+          }
+          _ => i += 1,
+        }
+      }
+      rest = &rest[end..];
+    } else {
+      return rest;
+    }
+  }
+}
+
+/// Kind of `sql` judged by its leading keyword after any comments, for statements no dialect
+/// parses: DML and DDL, routine calls (`CALL`, `DO`, `EXECUTE`), `SET`, maintenance commands,
+/// `SELECT ... INTO`, a `WITH` or `EXPLAIN ANALYZE` wrapping a write, and `PRAGMA`
+/// assignments write.
+fn keyword_kind(sql: &str) -> SqlKind {
+  let start = statement_start(sql);
+  let upper = start.to_uppercase();
+  let words: Vec<&str> = upper
+    .split(|c: char| !c.is_ascii_alphabetic())
+    .filter(|word| !word.is_empty())
+    .collect();
+  let has = |keywords: &[&str]| words.iter().any(|word| keywords.contains(word));
+  let writes = ["INSERT", "UPDATE", "DELETE", "MERGE"];
+  let write_if = |writes: bool| {
+    if writes {
+      SqlKind::Write
+    } else {
+      SqlKind::Read
+    }
+  };
+  match words.first().copied().unwrap_or("") {
+    "INSERT" | "UPDATE" | "DELETE" | "MERGE" | "REPLACE" | "UPSERT" | "DROP" | "TRUNCATE"
+    | "ALTER" | "CREATE" | "RENAME" | "GRANT" | "REVOKE" | "COPY" | "LOAD" | "CALL" | "DO"
+    | "EXEC" | "EXECUTE" | "SET" | "VACUUM" | "REINDEX" | "CLUSTER" | "REFRESH" | "COMMENT"
+    | "ATTACH" | "DETACH" | "IMPORT" | "LOCK" | "HANDLER" => SqlKind::Write,
+    "SELECT" => write_if(has(&["INTO"])),
+    "WITH" => write_if(has(&writes) || has(&["INTO"])),
+    "EXPLAIN" => write_if(has(&["ANALYZE", "ANALYSE"]) && has(&writes)),
+    "PRAGMA" => write_if(start.contains('=')),
+    "VALUES" | "TABLE" | "SHOW" | "DESCRIBE" | "DESC" => SqlKind::Read,
+    _ => SqlKind::Other,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn plain_statements() {
+    assert!(is_write("insert into t values (1)"));
+    assert!(is_write("  DROP TABLE t"));
+    assert!(is_read("SELECT * FROM t"));
+    assert!(is_read("SHOW TABLES"));
+    assert!(is_read("DESCRIBE t"));
+    assert_eq!(kind("BEGIN"), SqlKind::Other);
+  }
+
+  #[test]
+  fn leading_comments_and_parentheses_are_skipped() {
+    assert!(is_write("/* x */ DELETE FROM t"));
+    assert!(is_write("/* a /* nested */ b */ DELETE FROM t"));
+    assert!(is_write("-- x\nDROP TABLE t"));
+    assert!(is_write("# x\nUPDATE t SET a = 1"));
+    assert!(is_write("(INSERT INTO t VALUES (1))"));
+    assert!(is_read("-- DELETE\nSELECT 1"));
+    assert!(is_read("/* update */ SELECT 'drop' FROM t"));
+  }
+
+  #[test]
+  fn commented_writes_are_not_reads() {
+    assert!(!is_read("-- report\nDELETE FROM t"));
+    assert!(!is_read("/* SELECT */ UPDATE t SET a = 1"));
+    assert!(!is_read("SELECT 1; -- x\nDROP TABLE t"));
+    assert!(!is_read("SELECT 1 /* ; */; DELETE FROM t"));
+  }
+
+  #[test]
+  fn cte_wrapped_writes() {
+    assert!(is_write(
+      "WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d"
+    ));
+    assert!(!is_read(
+      "WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d"
+    ));
+    assert!(is_write(
+      "WITH s AS (SELECT 1 AS a) INSERT INTO t SELECT a FROM s"
+    ));
+    assert!(is_write("WITH s AS (SELECT 1) UPDATE t SET a = 2"));
+    assert!(is_read("WITH s AS (SELECT 1 AS a) SELECT a FROM s"));
+  }
+
+  #[test]
+  fn calls_settings_and_hidden_writes() {
+    assert!(is_write("CALL cleanup()"));
+    assert!(is_write("DO $$ BEGIN PERFORM 1; END $$"));
+    assert!(is_write("EXECUTE stmt"));
+    assert!(is_write("SET search_path = app"));
+    assert!(is_write("VACUUM"));
+    assert!(is_write("SELECT * INTO archive FROM t"));
+    assert!(is_write("EXPLAIN ANALYZE DELETE FROM t"));
+    assert!(!is_write("EXPLAIN DELETE FROM t"));
+    assert!(is_read("EXPLAIN SELECT * FROM t"));
+    assert!(is_write("PRAGMA foreign_keys = OFF"));
+    assert!(is_read("PRAGMA table_info(t)"));
+  }
+}
@@ -0,0 +1,253 @@
+//! Dialect-aware statement templates behind context-menu actions ("Last 100 rows", "Count by
+//! day", ...), so every engine gets the same actions generated the same way.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::PoisonError;
+
+use tauri::State;
+
+use crate::db;
+use crate::templates::{self, param, ParamKind, TemplateParam};
+use crate::AppState;
+
+/// Key of the SQL variant used by engines without one of their own.
+const ANY_ENGINE: &str = "*";
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementTemplate {
+  pub id: String,
+  pub name: String,
+  #[serde(default)]
+  pub description: Option<String>,
+  /// Context values the statement uses; `{{name}}` is replaced by the quoted value.
+  pub params: Vec<TemplateParam>,
+  /// SQL by engine (`mysql`, `postgres`, `sqlite`, or `*` for any other).
+  pub sql: BTreeMap<String, String>,
+  #[serde(default)]
+  pub builtin: bool,
+}
+
+fn builtin(
+  id: &str,
+  name: &str,
+  params: Vec<TemplateParam>,
+  sql: &[(&str, &str)],
+) -> StatementTemplate {
+  StatementTemplate {
+    id: id.to_string(),
+    name: name.to_string(),
+    description: None,
+    params,
+    sql: sql
+      .iter()
+      .map(|(engine, sql)| (engine.to_string(), sql.to_string()))
+      .collect(),
+    builtin: true,
+  }
+}
+
+fn builtin_statements() -> Vec<StatementTemplate> {
+  use ParamKind::{Ident, Number, Table};
+  let table = || param("table", Table, "Table to query", None);
+  let limit = || param("limit", Number, "Maximum rows to return", Some("100"));
+  vec![
+    builtin(
+      "select-first",
+      "First rows",
+      vec![table(), limit()],
+      &[(ANY_ENGINE, "SELECT * FROM {{table}} LIMIT {{limit}}")],
+    ),
+    builtin(
+      "select-last",
+      "Last rows",
+      vec![
+        table(),
+        param("order_column", Ident, "Column that orders the rows", None),
+        limit(),
+      ],
+      &[(
+        ANY_ENGINE,
+        "SELECT * FROM {{table}} ORDER BY {{order_column}} DESC LIMIT {{limit}}",
+      )],
+    ),
+    builtin(
+      "count-rows",
+      "Count rows",
+      vec![table()],
+      &[(ANY_ENGINE, "SELECT COUNT(*) AS count FROM {{table}}")],
+    ),
+    builtin(
+      "count-by-day",
+      "Count by day",
+      vec![
+        table(),
+        param("column", Ident, "Date or timestamp column", None),
+      ],
+      &[
+        (
+          "mysql",
+          "SELECT DATE({{column}}) AS day, COUNT(*) AS count FROM {{table}} \
+           GROUP BY DATE({{column}}) ORDER BY day",
+        ),
+        (
+          "postgres",
+          "SELECT date_trunc('day', {{column}})::date AS day, COUNT(*) AS count FROM {{table}} \
+           GROUP BY 1 ORDER BY 1",
+        ),
+        (
+          "sqlite",
+          "SELECT date({{column}}) AS day, COUNT(*) AS count FROM {{table}} \
+           GROUP BY date({{column}}) ORDER BY day",
+        ),
+      ],
+    ),
+    builtin(
+      "find-duplicates",
+      "Find duplicates on column",
+      vec![
+        table(),
+        param("column", Ident, "Column to check", None),
+        limit(),
+      ],
+      &[(
+        ANY_ENGINE,
+        "SELECT {{column}}, COUNT(*) AS count FROM {{table}} GROUP BY {{column}} \
+         HAVING COUNT(*) > 1 ORDER BY count DESC LIMIT {{limit}}",
+      )],
+    ),
+    builtin(
+      "distinct-values",
+      "Distinct values",
+      vec![
+        table(),
+        param("column", Ident, "Column to list", None),
+        limit(),
+      ],
+      &[(
+        ANY_ENGINE,
+        "SELECT DISTINCT {{column}} FROM {{table}} ORDER BY {{column}} LIMIT {{limit}}",
+      )],
+    ),
+    builtin(
+      "null-count",
+      "Count NULLs",
+      vec![table(), param("column", Ident, "Column to check", None)],
+      &[(
+        ANY_ENGINE,
+        "SELECT COUNT(*) - COUNT({{column}}) AS nulls, COUNT(*) AS total FROM {{table}}",
+      )],
+    ),
+  ]
+}
+
+fn find_statement(state: &AppState, template_id: &str) -> Result<StatementTemplate, String> {
+  if let Some(template) = state
+    .statement_templates
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(template_id)
+  {
+    return Ok(template.clone());
+  }
+  builtin_statements()
+    .into_iter()
+    .find(|t| t.id == template_id)
+    .ok_or_else(|| format!("Unknown statement template: {}", template_id))
+}
+
+#[tauri::command]
+pub fn list_statement_templates(
+  state: State<'_, AppState>,
+  engine: Option<String>,
+) -> Vec<StatementTemplate> {
+  let mut templates = builtin_statements();
+  templates.extend(
+    state
+      .statement_templates
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .values()
+      .cloned(),
+  );
+  templates.retain(|t| {
+    engine
+      .as_ref()
+      .is_none_or(|e| t.sql.contains_key(e) || t.sql.contains_key(ANY_ENGINE))
+  });
+  templates.sort_by(|a, b| a.name.cmp(&b.name));
+  templates
+}
+
+#[tauri::command]
+pub fn save_statement_template(
+  state: State<'_, AppState>,
+  template: StatementTemplate,
+) -> Result<(), String> {
+  if builtin_statements().iter().any(|t| t.id == template.id) {
+    return Err(format!(
+      "Cannot overwrite built-in statement template: {}",
+      template.id
+    ));
+  }
+  if let Some(engine) = template
+    .sql
+    .keys()
+    .find(|e| !matches!(e.as_str(), "mysql" | "postgres" | "sqlite" | ANY_ENGINE))
+  {
+    return Err(format!("Unsupported template engine: {}", engine));
+  }
+  if template.sql.values().all(|s| s.trim().is_empty()) {
+    return Err("Template has no SQL".to_string());
+  }
+  let template = StatementTemplate {
+    builtin: false,
+    ..template
+  };
+  state
+    .statement_templates
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(template.id.clone(), template);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn delete_statement_template(
+  state: State<'_, AppState>,
+  template_id: String,
+) -> Result<(), String> {
+  state
+    .statement_templates
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&template_id)
+    .map(|_| ())
+    .ok_or_else(|| format!("Unknown or built-in statement template: {}", template_id))
+}
+
+/// Generates the statement for a context action on `connection`, with `context` supplying
+/// the template's parameters (table, column, limit, ...).
+#[tauri::command]
+pub fn render_statement_template(
+  state: State<'_, AppState>,
+  connection: String,
+  template_id: String,
+  context: HashMap<String, String>,
+) -> Result<String, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let template = find_statement(&state, &template_id)?;
+  let sql = template
+    .sql
+    .get(pool.engine())
+    .or_else(|| template.sql.get(ANY_ENGINE))
+    .ok_or_else(|| {
+      format!(
+        "Statement template '{}' has no variant for {}",
+        template.id,
+        pool.engine()
+      )
+    })?;
+  let values = templates::param_values(&template.params, &pool, &context)?;
+  templates::substitute(&template.id, sql, &values)
+}
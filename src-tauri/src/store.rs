@@ -0,0 +1,201 @@
+//! App-local SQLite database in the app data directory, for state that has to survive
+//! restarts (connection profiles, ...).
+
+use std::path::{Path, PathBuf};
+use std::sync::PoisonError;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+
+use crate::AppState;
+
+pub const FILE_NAME: &str = "spectra.db";
+
+/// Bundle identifier from `tauri.conf.json`; the app data directory is named after it.
+const APP_IDENTIFIER: &str = "com.spectra-studio.app";
+
+/// Schema migrations in order. The index of the next one to run is kept in the database's
+/// `user_version`, so each statement runs exactly once; only ever append to this list.
+const MIGRATIONS: &[&str] = &[
+  "CREATE TABLE IF NOT EXISTS connection_profiles (
+     id TEXT PRIMARY KEY,
+     name TEXT NOT NULL,
+     engine TEXT NOT NULL,
+     config TEXT NOT NULL,
+     updated_at INTEGER NOT NULL
+   )",
+  "CREATE TABLE workspaces (name TEXT PRIMARY KEY, created_at INTEGER NOT NULL)",
+  "INSERT OR IGNORE INTO workspaces (name, created_at) VALUES ('default', 0)",
+  "ALTER TABLE connection_profiles ADD COLUMN workspace TEXT NOT NULL DEFAULT 'default'",
+  "CREATE TABLE workspace_settings (
+     workspace TEXT NOT NULL,
+     key TEXT NOT NULL,
+     value TEXT NOT NULL,
+     PRIMARY KEY (workspace, key)
+   )",
+  "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+  "CREATE TABLE profile_usage (
+     profile_id TEXT PRIMARY KEY,
+     sessions INTEGER NOT NULL DEFAULT 0,
+     last_connected_at INTEGER,
+     last_error TEXT,
+     last_error_at INTEGER
+   )",
+  "CREATE TABLE profile_table_usage (
+     profile_id TEXT NOT NULL,
+     table_name TEXT NOT NULL,
+     queries INTEGER NOT NULL,
+     last_used_at INTEGER NOT NULL,
+     PRIMARY KEY (profile_id, table_name)
+   )",
+  "CREATE TABLE redis_key_history (
+     id INTEGER PRIMARY KEY AUTOINCREMENT,
+     connection_id TEXT NOT NULL,
+     key TEXT NOT NULL,
+     value TEXT NOT NULL,
+     source TEXT NOT NULL,
+     captured_at INTEGER NOT NULL
+   )",
+  "CREATE INDEX redis_key_history_key ON redis_key_history (connection_id, key, id)",
+  "CREATE TABLE export_destinations (
+     id TEXT PRIMARY KEY,
+     workspace TEXT NOT NULL,
+     name TEXT NOT NULL,
+     config TEXT NOT NULL,
+     updated_at INTEGER NOT NULL
+   )",
+  "CREATE TABLE import_presets (
+     workspace TEXT NOT NULL,
+     table_name TEXT NOT NULL,
+     name TEXT NOT NULL,
+     mapping TEXT NOT NULL,
+     updated_at INTEGER NOT NULL,
+     PRIMARY KEY (workspace, table_name, name)
+   )",
+  "CREATE TABLE audit_log (
+     id INTEGER PRIMARY KEY AUTOINCREMENT,
+     workspace TEXT NOT NULL,
+     connection_id TEXT NOT NULL,
+     statement TEXT NOT NULL,
+     params TEXT NOT NULL,
+     rows_affected INTEGER,
+     executed_at INTEGER NOT NULL,
+     prev_hash TEXT NOT NULL,
+     hash TEXT NOT NULL
+   )",
+  "CREATE INDEX audit_log_executed_at ON audit_log (executed_at)",
+  "CREATE TABLE query_history (
+     id INTEGER PRIMARY KEY AUTOINCREMENT,
+     workspace TEXT NOT NULL,
+     connection_id TEXT NOT NULL,
+     statement TEXT NOT NULL,
+     duration_ms INTEGER NOT NULL,
+     rows INTEGER,
+     error TEXT,
+     executed_at INTEGER NOT NULL
+   )",
+  "CREATE INDEX query_history_workspace ON query_history (workspace, connection_id, id)",
+  "CREATE TABLE snippets (
+     id TEXT PRIMARY KEY,
+     workspace TEXT NOT NULL,
+     folder TEXT NOT NULL,
+     name TEXT NOT NULL,
+     config TEXT NOT NULL,
+     updated_at INTEGER NOT NULL
+   )",
+  "CREATE TABLE grid_views (
+     workspace TEXT NOT NULL,
+     table_name TEXT NOT NULL,
+     name TEXT NOT NULL,
+     config TEXT NOT NULL,
+     applied_at INTEGER,
+     updated_at INTEGER NOT NULL,
+     PRIMARY KEY (workspace, table_name, name)
+   )",
+  "CREATE TABLE journal (
+     id TEXT PRIMARY KEY,
+     kind TEXT NOT NULL,
+     run_id TEXT NOT NULL,
+     label TEXT NOT NULL,
+     payload TEXT NOT NULL,
+     updated_at INTEGER NOT NULL
+   )",
+  "CREATE TABLE usage_daily (
+     day TEXT NOT NULL,
+     kind TEXT NOT NULL,
+     name TEXT NOT NULL,
+     calls INTEGER NOT NULL,
+     errors INTEGER NOT NULL,
+     total_ms INTEGER NOT NULL,
+     max_ms INTEGER NOT NULL,
+     PRIMARY KEY (day, kind, name)
+   )",
+  "CREATE TABLE variables (
+     workspace TEXT NOT NULL,
+     name TEXT NOT NULL,
+     value TEXT NOT NULL,
+     PRIMARY KEY (workspace, name)
+   )",
+];
+
+async fn migrate(pool: &SqlitePool) -> Result<(), String> {
+  let (version,): (i64,) = sqlx::query_as("PRAGMA user_version")
+    .fetch_one(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  for (index, statement) in MIGRATIONS
+    .iter()
+    .enumerate()
+    .skip(usize::try_from(version).unwrap_or(0))
+  {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query(statement)
+      .execute(&mut *tx)
+      .await
+      .map_err(|e| format!("Migration {} failed: {}", index + 1, e))?;
+    sqlx::query(&format!("PRAGMA user_version = {}", index + 1))
+      .execute(&mut *tx)
+      .await
+      .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())?;
+  }
+  Ok(())
+}
+
+pub async fn open(path: &Path) -> Result<SqlitePool, String> {
+  if let Some(dir) = path.parent() {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+  }
+  let options = SqliteConnectOptions::new()
+    .filename(path)
+    .create_if_missing(true);
+  let pool = SqlitePoolOptions::new()
+    .max_connections(2)
+    .connect_with(options)
+    .await
+    .map_err(|e| e.to_string())?;
+  migrate(&pool).await?;
+  Ok(pool)
+}
+
+/// Store location for callers without an app handle (the CLI); matches Tauri's
+/// `app_data_dir()` on desktop platforms.
+pub fn default_path() -> Option<PathBuf> {
+  dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER).join(FILE_NAME))
+}
+
+pub fn pool(state: &AppState) -> Result<SqlitePool, String> {
+  state
+    .store
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .clone()
+    .ok_or_else(|| "App store is not available".to_string())
+}
+
+pub fn now_ms() -> i64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| i64::try_from(d.as_millis()).unwrap_or(i64::MAX))
+    .unwrap_or_default()
+}
@@ -0,0 +1,202 @@
+//! Streaming mode for browsing big tables. Instead of one response holding every row, rows
+//! are read from the server as a stream and sent to the frontend in `rows-chunk` events of
+//! `batch_size` rows, followed by one `rows-done` event. `stop_stream` ends a stream early.
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+
+use futures::{Stream, StreamExt};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::oneshot;
+
+use crate::db::{self, JsonRow, SqlPool};
+use crate::masking::MaskingConfig;
+use crate::{masking, timezone, transfer, AppState};
+
+const DEFAULT_BATCH_SIZE: usize = 1000;
+const MAX_BATCH_SIZE: usize = 50_000;
+
+/// Stop signals of running streams by id.
+pub type RowStreams = HashMap<String, oneshot::Sender<()>>;
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChunkEvent {
+  stream_id: String,
+  /// Position of the chunk in the stream, from 0.
+  index: usize,
+  /// Rows as JSON text.
+  rows: Vec<String>,
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DoneEvent {
+  stream_id: String,
+  row_count: usize,
+  /// Ended by `stop_stream` before reaching the last row.
+  stopped: bool,
+  error: Option<String>,
+}
+
+struct Outcome {
+  rows: usize,
+  bytes: usize,
+  stopped: bool,
+  error: Option<String>,
+}
+
+/// Sends `rows` to the frontend in chunks until it runs out, fails, or `stop` fires.
+async fn pump(
+  app: &AppHandle,
+  stream_id: &str,
+  mut rows: impl Stream<Item = Result<JsonRow, String>> + Unpin,
+  mut stop: oneshot::Receiver<()>,
+  batch_size: usize,
+  mask: Option<MaskingConfig>,
+) -> Outcome {
+  let mut outcome = Outcome {
+    rows: 0,
+    bytes: 0,
+    stopped: false,
+    error: None,
+  };
+  let mut batch = Vec::with_capacity(batch_size);
+  let mut index = 0;
+  loop {
+    let next = tokio::select! {
+      next = rows.next() => next,
+      _ = &mut stop => {
+        outcome.stopped = true;
+        None
+      }
+    };
+    let last = match next {
+      Some(Ok(mut row)) => {
+        if let Some(mask) = &mask {
+          mask.apply(&mut row);
+        }
+        let json = serde_json::Value::Object(row).to_string();
+        outcome.bytes += json.len();
+        batch.push(json);
+        false
+      }
+      Some(Err(e)) => {
+        outcome.error = Some(e);
+        true
+      }
+      None => true,
+    };
+    if batch.len() == batch_size || (last && !batch.is_empty()) {
+      outcome.rows += batch.len();
+      let rows = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+      let _ = app.emit(
+        "rows-chunk",
+        ChunkEvent {
+          stream_id: stream_id.to_string(),
+          index,
+          rows,
+        },
+      );
+      index += 1;
+    }
+    if last {
+      return outcome;
+    }
+  }
+}
+
+/// Starts streaming every row of `table` and returns the stream id the `rows-chunk` and
+/// `rows-done` events carry.
+#[tauri::command]
+pub async fn stream_rows(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+  batch_size: Option<usize>,
+) -> Result<String, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let table = pool.resolve_table(&table).await?;
+  let sql = format!("SELECT * FROM {}", pool.table_ref(&table));
+  let batch_size = batch_size
+    .unwrap_or(DEFAULT_BATCH_SIZE)
+    .clamp(1, MAX_BATCH_SIZE);
+  let tz = timezone::display_zone(&state, &connection);
+  let mask = masking::active(&state, &connection);
+
+  let stream_id = crate::next_id("stream");
+  let (stop, stopped) = oneshot::channel();
+  state
+    .row_streams
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(stream_id.clone(), stop);
+
+  let id = stream_id.clone();
+  tauri::async_runtime::spawn(async move {
+    let outcome = match &pool {
+      SqlPool::MySql(pool) => {
+        let rows = sqlx::query(&sql).fetch(pool).map(|row| {
+          row
+            .map(|row| db::mysql_row_to_json(&row, tz.as_ref()))
+            .map_err(|e| e.to_string())
+        });
+        pump(&app, &id, rows, stopped, batch_size, mask).await
+      }
+      SqlPool::Postgres(pool) => {
+        let rows = sqlx::query(&sql).fetch(pool).map(|row| {
+          row
+            .map(|row| db::pg_row_to_json(&row, tz.as_ref()))
+            .map_err(|e| e.to_string())
+        });
+        pump(&app, &id, rows, stopped, batch_size, mask).await
+      }
+      SqlPool::Sqlite(pool) => {
+        let rows = sqlx::query(&sql).fetch(pool).map(|row| {
+          row
+            .map(|row| db::sqlite_row_to_json(&row))
+            .map_err(|e| e.to_string())
+        });
+        pump(&app, &id, rows, stopped, batch_size, mask).await
+      }
+    };
+    let state = app.state::<AppState>();
+    state
+      .row_streams
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .remove(&id);
+    transfer::record(
+      &state,
+      &connection,
+      transfer::Category::Browse,
+      outcome.rows,
+      outcome.bytes,
+    );
+    let _ = app.emit(
+      "rows-done",
+      DoneEvent {
+        stream_id: id,
+        row_count: outcome.rows,
+        stopped: outcome.stopped,
+        error: outcome.error,
+      },
+    );
+  });
+  Ok(stream_id)
+}
+
+/// Ends a stream early; its `rows-done` event follows. Returns whether it was still running.
+#[tauri::command]
+pub fn stop_stream(state: State<'_, AppState>, stream_id: String) -> bool {
+  match state
+    .row_streams
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&stream_id)
+  {
+    Some(stop) => stop.send(()).is_ok(),
+    None => false,
+  }
+}
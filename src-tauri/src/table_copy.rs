@@ -0,0 +1,357 @@
+//! Duplicating a table, e.g. as a sandbox copy to try risky edits on first. The structure is
+//! copied with `CREATE TABLE ... LIKE` on MySQL and PostgreSQL and from the table's own
+//! `CREATE TABLE` on SQLite; rows, when asked for, follow in batches of [`BATCH`] with
+//! `duplicate-table-progress` events in between.
+//!
+//! Foreign keys come along only on SQLite, where they are part of the table definition;
+//! triggers are never copied.
+
+use sqlparser::ast::{ObjectName, Statement};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::db::{self, SqlPool};
+use crate::designer::{sqlite_ident, sqlite_parser};
+use crate::ident::{self, Dialect};
+use crate::{audit, connections, readonly, schema, AppState};
+
+/// Rows one `INSERT ... SELECT` copies.
+const BATCH: u64 = 10_000;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateTableResult {
+  pub table: String,
+  /// Statements that created the copy.
+  pub statements: Vec<String>,
+  /// Rows copied; `None` when only the structure was.
+  pub rows: Option<u64>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct CopyProgress {
+  connection_id: String,
+  table: String,
+  copied: u64,
+  /// The source's estimated row count, when the server has one.
+  total: Option<u64>,
+}
+
+/// `CREATE TABLE` for `target` from SQLite's definition of `source`, followed by its indexes
+/// under names of their own. A definition that doesn't parse falls back to `CREATE TABLE ...
+/// AS`, which keeps the columns but none of their constraints.
+async fn sqlite_structure(
+  pool: &SqlPool,
+  source: &str,
+  target: &str,
+) -> Result<Vec<String>, String> {
+  let SqlPool::Sqlite(sqlite) = pool else {
+    return Err("Not a SQLite connection".to_string());
+  };
+  let objects: Vec<(String, String, Option<String>)> = sqlx::query_as(
+    "SELECT type, name, sql FROM sqlite_master WHERE tbl_name = ? AND sql IS NOT NULL \
+     ORDER BY type = 'index', name",
+  )
+  .bind(source)
+  .fetch_all(sqlite)
+  .await
+  .map_err(|e| e.to_string())?;
+  let parse = |sql: &str| {
+    let mut parser = sqlite_parser("the table", sql)?;
+    parser.parse_statement().map_err(|e| e.to_string())
+  };
+  let mut statements = Vec::new();
+  for (kind, name, sql) in &objects {
+    let Some(Ok(statement)) = sql.as_deref().map(parse) else {
+      if kind == "table" {
+        statements.push(format!(
+          "CREATE TABLE {} AS SELECT * FROM {} WHERE 0",
+          ident::render(Dialect::Sqlite, target),
+          ident::render(Dialect::Sqlite, source)
+        ));
+      }
+      continue;
+    };
+    match statement {
+      Statement::CreateTable(mut create) if kind == "table" => {
+        create.name = ObjectName(vec![sqlite_ident(target)]);
+        create.if_not_exists = false;
+        statements.push(Statement::CreateTable(create).to_string());
+      }
+      Statement::CreateIndex(mut index) if !statements.is_empty() => {
+        let renamed = match name.strip_prefix(source) {
+          Some(rest) => format!("{}{}", target, rest),
+          None => format!("{}_{}", target, name),
+        };
+        index.name = Some(ObjectName(vec![sqlite_ident(&renamed)]));
+        index.table_name = ObjectName(vec![sqlite_ident(target)]);
+        statements.push(Statement::CreateIndex(index).to_string());
+      }
+      _ => {}
+    }
+  }
+  Ok(statements)
+}
+
+/// Statements creating `target` with the columns, keys and indexes of `source`.
+async fn structure(pool: &SqlPool, source: &str, target: &str) -> Result<Vec<String>, String> {
+  match pool {
+    SqlPool::MySql(_) => Ok(vec![format!(
+      "CREATE TABLE {} LIKE {}",
+      pool.table_ref(target),
+      pool.table_ref(source)
+    )]),
+    SqlPool::Postgres(_) => Ok(vec![format!(
+      "CREATE TABLE {} (LIKE {} INCLUDING ALL)",
+      pool.table_ref(target),
+      pool.table_ref(source)
+    )]),
+    SqlPool::Sqlite(_) => sqlite_structure(pool, source, target).await,
+  }
+}
+
+/// Columns of `table` that take a value on insert: all but generated ones.
+async fn stored_columns(pool: &SqlPool, table: &str) -> Result<Vec<String>, String> {
+  let columns: Vec<(String,)> = match pool {
+    SqlPool::MySql(mysql) => {
+      sqlx::query_as(
+        "SELECT CAST(COLUMN_NAME AS CHAR) FROM information_schema.COLUMNS \
+         WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? \
+         AND EXTRA NOT LIKE '%VIRTUAL GENERATED%' AND EXTRA NOT LIKE '%STORED GENERATED%' \
+         ORDER BY ORDINAL_POSITION",
+      )
+      .bind(table)
+      .fetch_all(mysql)
+      .await
+    }
+    SqlPool::Postgres(pg) => {
+      sqlx::query_as(
+        "SELECT column_name::text FROM information_schema.columns \
+         WHERE table_schema = 'public' AND table_name = $1 AND is_generated = 'NEVER' \
+         ORDER BY ordinal_position",
+      )
+      .bind(table)
+      .fetch_all(pg)
+      .await
+    }
+    SqlPool::Sqlite(sqlite) => {
+      sqlx::query_as("SELECT name FROM pragma_table_xinfo(?) WHERE hidden = 0 ORDER BY cid")
+        .bind(table)
+        .fetch_all(sqlite)
+        .await
+    }
+  }
+  .map_err(|e| e.to_string())?;
+  Ok(columns.into_iter().map(|(name,)| name).collect())
+}
+
+/// Copies the rows of `source` into `target`. With a single-column primary key that happens
+/// in batches, each continuing after the largest key copied so far; otherwise in one
+/// statement.
+async fn copy_rows(
+  app: &AppHandle,
+  pool: &SqlPool,
+  connection: &str,
+  source: &str,
+  target: &str,
+) -> Result<u64, String> {
+  let columns: Vec<String> = stored_columns(pool, source)
+    .await?
+    .iter()
+    .map(|c| pool.quote_ident(c))
+    .collect();
+  let columns = columns.join(", ");
+  let (from, to) = (pool.table_ref(source), pool.table_ref(target));
+  let overriding = match pool {
+    SqlPool::Postgres(_) => " OVERRIDING SYSTEM VALUE",
+    _ => "",
+  };
+  let insert = format!(
+    "INSERT INTO {} ({}){} SELECT {} FROM {}",
+    to, columns, overriding, columns, from
+  );
+  let total = schema::estimated_rows(pool, db::PG_DEFAULT_SCHEMA, source)
+    .await?
+    .map(|rows| u64::try_from(rows).unwrap_or(0));
+  let progress = |copied: u64| {
+    let _ = app.emit(
+      "duplicate-table-progress",
+      CopyProgress {
+        connection_id: connection.to_string(),
+        table: target.to_string(),
+        copied,
+        total,
+      },
+    );
+  };
+
+  let keys = schema::primary_key(pool, db::PG_DEFAULT_SCHEMA, source).await?;
+  let key = if let [key] = keys.as_slice() {
+    pool.quote_ident(key)
+  } else {
+    let copied = pool.execute_in_transaction(&[insert]).await?;
+    progress(copied);
+    return Ok(copied);
+  };
+  let mut copied = 0;
+  loop {
+    let after = if copied == 0 {
+      String::new()
+    } else {
+      format!(" WHERE {} > (SELECT MAX({}) FROM {})", key, key, to)
+    };
+    let batch = format!("{}{} ORDER BY {} LIMIT {}", insert, after, key, BATCH);
+    let rows = pool.execute_in_transaction(&[batch]).await?;
+    copied += rows;
+    progress(copied);
+    if rows < BATCH {
+      return Ok(copied);
+    }
+  }
+}
+
+/// Moves the identity sequences of a PostgreSQL copy past the rows copied into it, so the
+/// next insert doesn't reuse a key.
+async fn sync_identities(pool: &SqlPool, table: &str) -> Result<(), String> {
+  let SqlPool::Postgres(pg) = pool else {
+    return Ok(());
+  };
+  let columns: Vec<(String,)> = sqlx::query_as(
+    "SELECT column_name::text FROM information_schema.columns \
+     WHERE table_schema = 'public' AND table_name = $1 AND is_identity = 'YES'",
+  )
+  .bind(table)
+  .fetch_all(pg)
+  .await
+  .map_err(|e| e.to_string())?;
+  for (column,) in columns {
+    let sql = format!(
+      "SELECT setval(pg_get_serial_sequence({}, {}), MAX({})) FROM {} HAVING MAX({}) IS NOT NULL",
+      pool.quote_literal(&pool.table_ref(table)),
+      pool.quote_literal(&column),
+      pool.quote_ident(&column),
+      pool.table_ref(table),
+      pool.quote_ident(&column)
+    );
+    sqlx::query(&sql)
+      .execute(pg)
+      .await
+      .map_err(|e| e.to_string())?;
+  }
+  Ok(())
+}
+
+async fn duplicate_table(
+  app: &AppHandle,
+  state: &AppState,
+  pool: SqlPool,
+  connection: &str,
+  source_table: String,
+  target_table: String,
+  include_data: Option<bool>,
+) -> Result<DuplicateTableResult, String> {
+  readonly::ensure_writable(state, connection)?;
+  let source = pool.resolve_table(&source_table).await?;
+  ident::validate(pool.dialect(), &target_table)?;
+  let statements = structure(&pool, &source, &target_table).await?;
+  pool.execute_in_transaction(&statements).await?;
+  audit::record(
+    state,
+    connection,
+    &statements.join(";\n"),
+    serde_json::json!([]),
+    None,
+  )
+  .await;
+
+  let mut rows = None;
+  if include_data.unwrap_or(false) {
+    let copied = match copy_rows(app, &pool, connection, &source, &target_table).await {
+      Ok(copied) => sync_identities(&pool, &target_table).await.map(|()| copied),
+      Err(e) => Err(e),
+    };
+    match copied {
+      Ok(copied) => rows = Some(copied),
+      Err(e) => {
+        // Don't leave a half-filled copy behind
+        let drop = format!("DROP TABLE {}", pool.table_ref(&target_table));
+        let _ = pool.execute_in_transaction(&[drop]).await;
+        return Err(e);
+      }
+    }
+  }
+  Ok(DuplicateTableResult {
+    table: target_table,
+    statements,
+    rows,
+  })
+}
+
+#[tauri::command]
+pub async fn mysql_duplicate_table(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  source_table: String,
+  target_table: String,
+  include_data: Option<bool>,
+  connection_id: Option<String>,
+) -> Result<DuplicateTableResult, String> {
+  let pool = SqlPool::MySql(connections::mysql(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("mysql");
+  duplicate_table(
+    &app,
+    &state,
+    pool,
+    connection,
+    source_table,
+    target_table,
+    include_data,
+  )
+  .await
+}
+
+#[tauri::command]
+pub async fn postgres_duplicate_table(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  source_table: String,
+  target_table: String,
+  include_data: Option<bool>,
+  connection_id: Option<String>,
+) -> Result<DuplicateTableResult, String> {
+  let pool = SqlPool::Postgres(connections::postgres(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("postgres");
+  duplicate_table(
+    &app,
+    &state,
+    pool,
+    connection,
+    source_table,
+    target_table,
+    include_data,
+  )
+  .await
+}
+
+#[tauri::command]
+pub async fn sqlite_duplicate_table(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  source_table: String,
+  target_table: String,
+  include_data: Option<bool>,
+  connection_id: Option<String>,
+) -> Result<DuplicateTableResult, String> {
+  let pool = SqlPool::Sqlite(connections::sqlite(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("sqlite");
+  duplicate_table(
+    &app,
+    &state,
+    pool,
+    connection,
+    source_table,
+    target_table,
+    include_data,
+  )
+  .await
+}
@@ -0,0 +1,126 @@
+//! Table sizes for the sidebar. The table names are listed first and their sizes read in
+//! chunks of [`CHUNK`] tables, [`PARALLEL`] queries at a time, so schemas with thousands of
+//! relations don't wait on one long query. Every chunk is emitted as a `table-sizes-chunk`
+//! event as it arrives; the command still returns the whole list, in name order, at the end.
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use futures::StreamExt;
+use sqlx::{MySqlPool, PgPool};
+use tauri::{AppHandle, Emitter};
+
+/// Tables whose sizes one query reads.
+const CHUNK: usize = 200;
+/// Chunk queries in flight at once; below the default pool size so other work isn't starved.
+const PARALLEL: usize = 4;
+
+pub type TableSize = (String, i64);
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ChunkEvent {
+  connection_id: String,
+  tables: Vec<TableSize>,
+}
+
+async fn in_chunks<F, Fut>(
+  app: &AppHandle,
+  connection: &str,
+  names: Vec<String>,
+  load: F,
+) -> Result<Vec<TableSize>, String>
+where
+  F: Fn(Vec<String>) -> Fut,
+  Fut: Future<Output = Result<Vec<TableSize>, String>>,
+{
+  let loads: Vec<Fut> = names
+    .chunks(CHUNK)
+    .map(|chunk| load(chunk.to_vec()))
+    .collect();
+  let mut chunks = futures::stream::iter(loads).buffer_unordered(PARALLEL);
+  let mut sizes = Vec::with_capacity(names.len());
+  while let Some(chunk) = chunks.next().await {
+    let chunk = chunk?;
+    let _ = app.emit(
+      "table-sizes-chunk",
+      ChunkEvent {
+        connection_id: connection.to_string(),
+        tables: chunk.clone(),
+      },
+    );
+    sizes.extend(chunk);
+  }
+  // Back in the order the server listed the names in
+  let position: HashMap<&str, usize> = names
+    .iter()
+    .enumerate()
+    .map(|(i, name)| (name.as_str(), i))
+    .collect();
+  sizes.sort_by_key(|(name, _)| position.get(name.as_str()).copied());
+  Ok(sizes)
+}
+
+/// Size of every table and view in `database`, data and indexes together.
+pub async fn mysql(
+  app: &AppHandle,
+  connection: &str,
+  pool: &MySqlPool,
+  database: &str,
+) -> Result<Vec<TableSize>, String> {
+  let names: Vec<(String,)> = sqlx::query_as(
+    "SELECT CONVERT(TABLE_NAME USING utf8) FROM information_schema.TABLES \
+     WHERE TABLE_SCHEMA = ? ORDER BY TABLE_NAME",
+  )
+  .bind(database)
+  .fetch_all(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  let names = names.into_iter().map(|(name,)| name).collect();
+  in_chunks(app, connection, names, |chunk| async move {
+    let sql = format!(
+      "SELECT CONVERT(TABLE_NAME USING utf8), \
+       CAST(COALESCE(DATA_LENGTH + INDEX_LENGTH, 0) AS SIGNED) \
+       FROM information_schema.TABLES WHERE TABLE_SCHEMA = ? AND TABLE_NAME IN ({})",
+      vec!["?"; chunk.len()].join(", ")
+    );
+    let mut query = sqlx::query_as(&sql).bind(database);
+    for name in &chunk {
+      query = query.bind(name);
+    }
+    query.fetch_all(pool).await.map_err(|e| e.to_string())
+  })
+  .await
+}
+
+/// Total size (heap, indexes and TOAST) of every relation `information_schema.tables` lists
+/// in `schema`.
+pub async fn postgres(
+  app: &AppHandle,
+  connection: &str,
+  pool: &PgPool,
+  schema: &str,
+) -> Result<Vec<TableSize>, String> {
+  let names: Vec<(String,)> = sqlx::query_as(
+    "SELECT table_name::text FROM information_schema.tables \
+     WHERE table_schema = $1 ORDER BY table_name",
+  )
+  .bind(schema)
+  .fetch_all(pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  let names = names.into_iter().map(|(name,)| name).collect();
+  in_chunks(app, connection, names, |chunk| async move {
+    sqlx::query_as(
+      "SELECT c.relname::text, pg_total_relation_size(c.oid) FROM pg_class c \
+       JOIN pg_namespace n ON n.oid = c.relnamespace \
+       WHERE n.nspname = $2 AND c.relname = ANY($1)",
+    )
+    .bind(chunk)
+    .bind(schema)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())
+  })
+  .await
+}
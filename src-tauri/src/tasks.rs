@@ -0,0 +1,547 @@
+//! Background tasks for operations too long to await from the UI (row counts on huge tables,
+//! exports, file, Excel and Parquet exports, SQL dumps, imports). Each task gets an id, runs
+//! on the async runtime's pool, and reports through `task-progress` and `task-complete`
+//! events; `list_tasks` shows running and recently finished ones and `cancel_task` aborts one.
+//!
+//! Cancelling drops the job at its next await point. A statement already sent to the server
+//! may still finish there, but its result is discarded.
+//!
+//! Running tasks are kept in the [`journal`], so ones cut short by a crash can be started
+//! again from their [`TaskSpec`] on the next launch.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::PoisonError;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::file_export::{self, FileExportOptions};
+use crate::import::FieldMapping;
+use crate::parquet_export;
+use crate::sql_dump::{self, SqlDumpOptions};
+use crate::xlsx_export;
+use crate::{db, destinations, import, journal, store, usage, AppState};
+
+/// Finished tasks kept for `list_tasks`; older ones are dropped.
+const MAX_FINISHED: usize = 100;
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskState {
+  Running,
+  Completed,
+  Failed,
+  Cancelled,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskInfo {
+  pub task_id: String,
+  /// What the task does (`count`, `export`, `import`).
+  pub kind: String,
+  pub label: String,
+  pub connection: Option<String>,
+  pub state: TaskState,
+  /// Units of work done so far (bytes, rows, ...), with the total when it is known.
+  pub done: u64,
+  pub total: Option<u64>,
+  /// What the task is doing right now.
+  pub message: Option<String>,
+  pub started_at: i64,
+  pub finished_at: Option<i64>,
+  pub result: Option<serde_json::Value>,
+  pub error: Option<String>,
+}
+
+pub struct Task {
+  info: TaskInfo,
+  handle: Option<tauri::async_runtime::JoinHandle<()>>,
+}
+
+pub type Tasks = HashMap<String, Task>;
+
+/// What a task runs, with everything needed to start it again.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(
+  tag = "kind",
+  rename_all = "camelCase",
+  rename_all_fields = "camelCase"
+)]
+pub enum TaskSpec {
+  Count {
+    connection: String,
+    table: String,
+  },
+  Export {
+    connection: String,
+    destination_id: String,
+    table: Option<String>,
+    query: Option<String>,
+    format: Option<String>,
+    file_name: Option<String>,
+    compression: Option<String>,
+  },
+  FileExport {
+    connection: String,
+    sql: String,
+    format: String,
+    path: String,
+    options: FileExportOptions,
+  },
+  Import {
+    connection: String,
+    table: String,
+    path: String,
+    format: Option<String>,
+    mapping: Option<Vec<FieldMapping>>,
+    preset: Option<String>,
+    dry_run: Option<bool>,
+    max_errors: Option<usize>,
+  },
+  SqlDump {
+    connection: String,
+    path: String,
+    options: SqlDumpOptions,
+  },
+  ParquetExport {
+    connection: String,
+    sql: String,
+    path: String,
+  },
+  XlsxExport {
+    connection: String,
+    sql: String,
+    path: String,
+    sheet_name: Option<String>,
+  },
+}
+
+impl TaskSpec {
+  fn kind(&self) -> &'static str {
+    match self {
+      TaskSpec::Count { .. } => "count",
+      TaskSpec::Export { .. } => "export",
+      TaskSpec::FileExport { .. } => "file_export",
+      TaskSpec::Import { .. } => "import",
+      TaskSpec::SqlDump { .. } => "sql_dump",
+      TaskSpec::ParquetExport { .. } => "parquet_export",
+      TaskSpec::XlsxExport { .. } => "xlsx_export",
+    }
+  }
+
+  fn connection(&self) -> &str {
+    match self {
+      TaskSpec::Count { connection, .. }
+      | TaskSpec::Export { connection, .. }
+      | TaskSpec::FileExport { connection, .. }
+      | TaskSpec::Import { connection, .. }
+      | TaskSpec::SqlDump { connection, .. }
+      | TaskSpec::ParquetExport { connection, .. }
+      | TaskSpec::XlsxExport { connection, .. } => connection,
+    }
+  }
+
+  fn label(&self) -> String {
+    match self {
+      TaskSpec::Count { table, .. } => format!("Count rows of {}", table),
+      TaskSpec::Export {
+        table: Some(table), ..
+      } => format!("Export {}", table),
+      TaskSpec::Export { .. } => "Export query result".to_string(),
+      TaskSpec::FileExport { path, .. } => format!("Export query result to {}", path),
+      TaskSpec::Import { path, table, .. } => format!("Import {} into {}", path, table),
+      TaskSpec::SqlDump { path, .. } => format!("Dump tables to {}", path),
+      TaskSpec::ParquetExport { path, .. } | TaskSpec::XlsxExport { path, .. } => {
+        format!("Export query result to {}", path)
+      }
+    }
+  }
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent {
+  task_id: String,
+  done: u64,
+  total: Option<u64>,
+  message: Option<String>,
+}
+
+/// Passed to a task's job to report progress.
+pub struct TaskHandle {
+  pub app: AppHandle,
+  id: String,
+}
+
+impl TaskHandle {
+  pub fn id(&self) -> &str {
+    &self.id
+  }
+
+  pub fn progress(&self, done: u64, total: Option<u64>, message: Option<&str>) {
+    report(&self.app, &self.id, done, total, message);
+  }
+}
+
+/// Records progress of task `task_id` and emits `task-progress`; does nothing when no such
+/// task is running, so code shared with foreground commands can call it unconditionally.
+pub fn report(
+  app: &AppHandle,
+  task_id: &str,
+  done: u64,
+  total: Option<u64>,
+  message: Option<&str>,
+) {
+  let state = app.state::<AppState>();
+  {
+    let mut tasks = state.tasks.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(task) = tasks
+      .get_mut(task_id)
+      .filter(|t| t.info.state == TaskState::Running)
+    else {
+      return;
+    };
+    task.info.done = done;
+    task.info.total = total;
+    if let Some(message) = message {
+      task.info.message = Some(message.to_string());
+    }
+  }
+  let _ = app.emit(
+    "task-progress",
+    ProgressEvent {
+      task_id: task_id.to_string(),
+      done,
+      total,
+      message: message.map(str::to_string),
+    },
+  );
+}
+
+/// Marks a running task finished and emits `task-complete` with its final info.
+fn finish(
+  app: &AppHandle,
+  task_id: &str,
+  state: TaskState,
+  result: Option<serde_json::Value>,
+  error: Option<String>,
+) {
+  let app_state = app.state::<AppState>();
+  let info = {
+    let mut tasks = app_state
+      .tasks
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    let Some(task) = tasks
+      .get_mut(task_id)
+      .filter(|t| t.info.state == TaskState::Running)
+    else {
+      return;
+    };
+    task.info.state = state;
+    task.info.finished_at = Some(store::now_ms());
+    task.info.result = result;
+    task.info.error = error;
+    task.handle = None;
+    let info = task.info.clone();
+
+    let mut finished: Vec<(i64, String)> = tasks
+      .values()
+      .filter_map(|t| t.info.finished_at.map(|at| (at, t.info.task_id.clone())))
+      .collect();
+    if finished.len() > MAX_FINISHED {
+      finished.sort();
+      for (_, id) in &finished[..finished.len() - MAX_FINISHED] {
+        tasks.remove(id);
+      }
+    }
+    info
+  };
+  let _ = app.emit("task-complete", info);
+}
+
+/// Starts `job`, which carries out `spec`, as a background task and returns its id right
+/// away.
+fn spawn<F, Fut, T>(app: &AppHandle, spec: TaskSpec, job: F) -> String
+where
+  F: FnOnce(TaskHandle) -> Fut,
+  Fut: Future<Output = Result<T, String>> + Send + 'static,
+  T: serde::Serialize,
+{
+  let task_id = crate::next_id("task");
+  let label = spec.label();
+  let feature = format!("task.{}", spec.kind());
+  let info = TaskInfo {
+    task_id: task_id.clone(),
+    kind: spec.kind().to_string(),
+    label: label.clone(),
+    connection: Some(spec.connection().to_string()),
+    state: TaskState::Running,
+    done: 0,
+    total: None,
+    message: None,
+    started_at: store::now_ms(),
+    finished_at: None,
+    result: None,
+    error: None,
+  };
+  let state = app.state::<AppState>();
+  state
+    .tasks
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(task_id.clone(), Task { info, handle: None });
+
+  let work = job(TaskHandle {
+    app: app.clone(),
+    id: task_id.clone(),
+  });
+  let handle = {
+    let (app, task_id) = (app.clone(), task_id.clone());
+    tauri::async_runtime::spawn(async move {
+      let state = app.state::<AppState>();
+      if let Err(e) = journal::task_started(&state, &task_id, &label, &spec).await {
+        tracing::warn!("Failed to journal task {}: {}", task_id, e);
+      }
+      let started = std::time::Instant::now();
+      let (result, error) = match work.await {
+        Ok(value) => match serde_json::to_value(value) {
+          Ok(result) => (Some(result), None),
+          Err(e) => (None, Some(e.to_string())),
+        },
+        Err(e) => (None, Some(e)),
+      };
+      usage::observe(&state, &feature, started.elapsed(), error.is_none());
+      let outcome = if error.is_none() {
+        TaskState::Completed
+      } else {
+        TaskState::Failed
+      };
+      finish(&app, &task_id, outcome, result, error);
+      journal::task_finished(&state, &task_id).await;
+    })
+  };
+  match state
+    .tasks
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get_mut(&task_id)
+  {
+    Some(task) if task.info.state == TaskState::Running => task.handle = Some(handle),
+    // Cancelled before its handle was stored
+    Some(task) if task.info.state == TaskState::Cancelled => handle.abort(),
+    _ => {}
+  }
+  task_id
+}
+
+/// Running and recently finished tasks, newest first.
+#[tauri::command]
+pub fn list_tasks(state: State<'_, AppState>) -> Vec<TaskInfo> {
+  let mut tasks: Vec<TaskInfo> = state
+    .tasks
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .values()
+    .map(|t| t.info.clone())
+    .collect();
+  tasks.sort_by_key(|t| std::cmp::Reverse(t.started_at));
+  tasks
+}
+
+/// Aborts a running task. Returns whether it was still running.
+#[tauri::command]
+pub fn cancel_task(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  task_id: String,
+) -> Result<bool, String> {
+  let handle = {
+    let mut tasks = state.tasks.lock().unwrap_or_else(PoisonError::into_inner);
+    let task = tasks
+      .get_mut(&task_id)
+      .ok_or_else(|| format!("Unknown task: {}", task_id))?;
+    if task.info.state != TaskState::Running {
+      return Ok(false);
+    }
+    task.handle.take()
+  };
+  if let Some(handle) = handle {
+    handle.abort();
+  }
+  finish(&app, &task_id, TaskState::Cancelled, None, None);
+  // The aborted job never gets to clear its journal entry
+  let (app, id) = (app.clone(), task_id.clone());
+  tauri::async_runtime::spawn(async move {
+    journal::task_finished(&app.state::<AppState>(), &id).await;
+  });
+  Ok(true)
+}
+
+/// Starts the background task `spec` describes and returns its id.
+pub fn start(app: &AppHandle, spec: TaskSpec) -> String {
+  match spec.clone() {
+    TaskSpec::Count { connection, table } => spawn(app, spec, |task| async move {
+      let state = task.app.state::<AppState>();
+      let pool = db::sql_pool(&state, &connection)?;
+      let table = pool.resolve_table(&table).await?;
+      task.progress(0, None, Some("Counting rows"));
+      let sql = format!("SELECT COUNT(*) AS count FROM {}", pool.table_ref(&table));
+      let rows = pool.fetch_rows(&sql, &[]).await?;
+      Ok(
+        rows
+          .into_iter()
+          .next()
+          .and_then(|mut row| row.remove("count"))
+          .unwrap_or(serde_json::Value::Null),
+      )
+    }),
+    TaskSpec::Export {
+      connection,
+      destination_id,
+      table,
+      query,
+      format,
+      file_name,
+      compression,
+    } => spawn(app, spec, |task| async move {
+      task.progress(0, None, Some("Reading rows"));
+      destinations::export_to_destination(
+        task.app.clone(),
+        task.app.state(),
+        connection,
+        destination_id,
+        table,
+        query,
+        format,
+        file_name,
+        Some(task.id().to_string()),
+        compression,
+      )
+      .await
+    }),
+    TaskSpec::FileExport {
+      connection,
+      sql,
+      format,
+      path,
+      options,
+    } => spawn(app, spec, |task| async move {
+      file_export::write_file(&task, connection, sql, format, path, options).await
+    }),
+    TaskSpec::Import {
+      connection,
+      table,
+      path,
+      format,
+      mapping,
+      preset,
+      dry_run,
+      max_errors,
+    } => spawn(app, spec, |task| async move {
+      task.progress(0, None, Some("Importing rows"));
+      import::import_file(
+        task.app.state(),
+        connection,
+        table,
+        path,
+        format,
+        mapping,
+        preset,
+        dry_run,
+        max_errors,
+      )
+      .await
+    }),
+    TaskSpec::SqlDump {
+      connection,
+      path,
+      options,
+    } => spawn(app, spec, |task| async move {
+      sql_dump::write_dump(&task, connection, path, options).await
+    }),
+    TaskSpec::ParquetExport {
+      connection,
+      sql,
+      path,
+    } => spawn(app, spec, |task| async move {
+      parquet_export::write_parquet(&task, connection, sql, path).await
+    }),
+    TaskSpec::XlsxExport {
+      connection,
+      sql,
+      path,
+      sheet_name,
+    } => spawn(app, spec, |task| async move {
+      xlsx_export::write_xlsx(&task, connection, sql, path, sheet_name).await
+    }),
+  }
+}
+
+/// Counts the rows of `table` in the background; the result is the count.
+#[tauri::command]
+pub async fn start_count_task(
+  app: AppHandle,
+  connection: String,
+  table: String,
+) -> Result<String, String> {
+  Ok(start(&app, TaskSpec::Count { connection, table }))
+}
+
+/// Runs [`destinations::export_to_destination`] in the background, reporting upload
+/// progress in bytes; the result is the upload summary.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_export_task(
+  app: AppHandle,
+  connection: String,
+  destination_id: String,
+  table: Option<String>,
+  query: Option<String>,
+  format: Option<String>,
+  file_name: Option<String>,
+  compression: Option<String>,
+) -> Result<String, String> {
+  Ok(start(
+    &app,
+    TaskSpec::Export {
+      connection,
+      destination_id,
+      table,
+      query,
+      format,
+      file_name,
+      compression,
+    },
+  ))
+}
+
+/// Runs [`import::import_file`] in the background; the result is the import report.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn start_import_task(
+  app: AppHandle,
+  connection: String,
+  table: String,
+  path: String,
+  format: Option<String>,
+  mapping: Option<Vec<FieldMapping>>,
+  preset: Option<String>,
+  dry_run: Option<bool>,
+  max_errors: Option<usize>,
+) -> Result<String, String> {
+  Ok(start(
+    &app,
+    TaskSpec::Import {
+      connection,
+      table,
+      path,
+      format,
+      mapping,
+      preset,
+      dry_run,
+      max_errors,
+    },
+  ))
+}
@@ -0,0 +1,339 @@
+//! Parameterized SQL scripts for repeatable setup tasks (app users, schemas, grants, ...).
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+
+use tauri::State;
+
+use crate::db::{self, SqlPool};
+use crate::{audit, readonly, AppState};
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ParamKind {
+  /// Quoted as an identifier (table, schema, role names).
+  Ident,
+  /// Quoted as a table reference the way the connection addresses tables.
+  Table,
+  /// Quoted as a string literal (passwords, MySQL user/host names).
+  Literal,
+  /// Inserted as-is after checking it is a plain number.
+  Number,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateParam {
+  pub name: String,
+  pub kind: ParamKind,
+  #[serde(default)]
+  pub description: Option<String>,
+  /// Parameters without a default are required.
+  #[serde(default)]
+  pub default: Option<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlTemplate {
+  pub id: String,
+  pub name: String,
+  #[serde(default)]
+  pub description: Option<String>,
+  /// Engine the statements are written for (`mysql`, `postgres` or `sqlite`).
+  pub engine: String,
+  pub params: Vec<TemplateParam>,
+  /// Statements run in order; `{{name}}` is replaced by the quoted parameter value.
+  pub statements: Vec<String>,
+  #[serde(default)]
+  pub builtin: bool,
+}
+
+pub(crate) fn param(
+  name: &str,
+  kind: ParamKind,
+  description: &str,
+  default: Option<&str>,
+) -> TemplateParam {
+  TemplateParam {
+    name: name.to_string(),
+    kind,
+    description: Some(description.to_string()),
+    default: default.map(str::to_string),
+  }
+}
+
+fn builtin(
+  id: &str,
+  name: &str,
+  engine: &str,
+  params: Vec<TemplateParam>,
+  statements: &[&str],
+) -> SqlTemplate {
+  SqlTemplate {
+    id: id.to_string(),
+    name: name.to_string(),
+    description: None,
+    engine: engine.to_string(),
+    params,
+    statements: statements.iter().map(ToString::to_string).collect(),
+    builtin: true,
+  }
+}
+
+fn builtin_templates() -> Vec<SqlTemplate> {
+  use ParamKind::{Ident, Literal};
+  vec![
+    builtin(
+      "postgres-app-user",
+      "App user with its own schema",
+      "postgres",
+      vec![
+        param("user", Ident, "Login role to create", None),
+        param("password", Literal, "Password for the role", None),
+        param("schema", Ident, "Schema owned by the role", Some("app")),
+      ],
+      &[
+        "CREATE ROLE {{user}} LOGIN PASSWORD {{password}}",
+        "CREATE SCHEMA IF NOT EXISTS {{schema}} AUTHORIZATION {{user}}",
+        "GRANT USAGE, CREATE ON SCHEMA {{schema}} TO {{user}}",
+        "ALTER DEFAULT PRIVILEGES IN SCHEMA {{schema}} GRANT SELECT, INSERT, UPDATE, DELETE ON TABLES TO {{user}}",
+      ],
+    ),
+    builtin(
+      "postgres-readonly-user",
+      "Read-only user",
+      "postgres",
+      vec![
+        param("user", Ident, "Login role to create", None),
+        param("password", Literal, "Password for the role", None),
+        param("schema", Ident, "Schema to grant read access on", Some("public")),
+      ],
+      &[
+        "CREATE ROLE {{user}} LOGIN PASSWORD {{password}}",
+        "GRANT USAGE ON SCHEMA {{schema}} TO {{user}}",
+        "GRANT SELECT ON ALL TABLES IN SCHEMA {{schema}} TO {{user}}",
+        "ALTER DEFAULT PRIVILEGES IN SCHEMA {{schema}} GRANT SELECT ON TABLES TO {{user}}",
+      ],
+    ),
+    builtin(
+      "mysql-app-user",
+      "App user with its own database",
+      "mysql",
+      vec![
+        param("database", Ident, "Database to create", None),
+        param("user", Literal, "User name to create", None),
+        param("host", Literal, "Host the user may connect from", Some("%")),
+        param("password", Literal, "Password for the user", None),
+      ],
+      &[
+        "CREATE DATABASE IF NOT EXISTS {{database}}",
+        "CREATE USER {{user}}@{{host}} IDENTIFIED BY {{password}}",
+        "GRANT SELECT, INSERT, UPDATE, DELETE, CREATE, ALTER, INDEX, DROP ON {{database}}.* TO {{user}}@{{host}}",
+      ],
+    ),
+    builtin(
+      "mysql-readonly-user",
+      "Read-only user",
+      "mysql",
+      vec![
+        param("database", Ident, "Database to grant read access on", None),
+        param("user", Literal, "User name to create", None),
+        param("host", Literal, "Host the user may connect from", Some("%")),
+        param("password", Literal, "Password for the user", None),
+      ],
+      &[
+        "CREATE USER {{user}}@{{host}} IDENTIFIED BY {{password}}",
+        "GRANT SELECT, SHOW VIEW ON {{database}}.* TO {{user}}@{{host}}",
+      ],
+    ),
+  ]
+}
+
+fn find_template(state: &AppState, template_id: &str) -> Result<SqlTemplate, String> {
+  if let Some(template) = state
+    .templates
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(template_id)
+  {
+    return Ok(template.clone());
+  }
+  builtin_templates()
+    .into_iter()
+    .find(|t| t.id == template_id)
+    .ok_or_else(|| format!("Unknown template: {}", template_id))
+}
+
+/// Value of each declared parameter, quoted according to its kind for `pool`.
+pub(crate) fn param_values<'a>(
+  declared: &'a [TemplateParam],
+  pool: &SqlPool,
+  params: &HashMap<String, String>,
+) -> Result<HashMap<&'a str, String>, String> {
+  let mut values = HashMap::new();
+  for p in declared {
+    let value = params
+      .get(&p.name)
+      .or(p.default.as_ref())
+      .ok_or_else(|| format!("Missing template parameter: {}", p.name))?;
+    let rendered = match p.kind {
+      ParamKind::Ident => pool.quote_ident(value),
+      ParamKind::Table => pool.table_ref(value),
+      ParamKind::Literal => pool.quote_literal(value),
+      ParamKind::Number => {
+        value
+          .trim()
+          .parse::<f64>()
+          .map_err(|_| format!("Parameter '{}' must be a number", p.name))?;
+        value.trim().to_string()
+      }
+    };
+    values.insert(p.name.as_str(), rendered);
+  }
+  Ok(values)
+}
+
+/// Replaces each `{{name}}` in `statement` with its value from `values`.
+pub(crate) fn substitute(
+  template_id: &str,
+  statement: &str,
+  values: &HashMap<&str, String>,
+) -> Result<String, String> {
+  let mut out = String::with_capacity(statement.len());
+  let mut rest = statement;
+  while let Some(start) = rest.find("{{") {
+    out.push_str(&rest[..start]);
+    let end = rest[start..]
+      .find("}}")
+      .ok_or_else(|| format!("Unterminated placeholder in template '{}'", template_id))?;
+    let name = rest[start + 2..start + end].trim();
+    let value = values.get(name).ok_or_else(|| {
+      format!(
+        "Template '{}' uses undeclared parameter: {}",
+        template_id, name
+      )
+    })?;
+    out.push_str(value);
+    rest = &rest[start + end + 2..];
+  }
+  out.push_str(rest);
+  Ok(out)
+}
+
+/// Replaces each `{{name}}` with the parameter value quoted according to its kind.
+fn render(
+  template: &SqlTemplate,
+  pool: &SqlPool,
+  params: &HashMap<String, String>,
+) -> Result<Vec<String>, String> {
+  if template.engine != pool.engine() {
+    return Err(format!(
+      "Template '{}' is for {}, not {}",
+      template.id,
+      template.engine,
+      pool.engine()
+    ));
+  }
+  let values = param_values(&template.params, pool, params)?;
+  template
+    .statements
+    .iter()
+    .map(|statement| substitute(&template.id, statement, &values))
+    .collect()
+}
+
+#[tauri::command]
+pub fn list_templates(state: State<'_, AppState>, engine: Option<String>) -> Vec<SqlTemplate> {
+  let mut templates = builtin_templates();
+  templates.extend(
+    state
+      .templates
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .values()
+      .cloned(),
+  );
+  templates.retain(|t| engine.as_ref().is_none_or(|e| &t.engine == e));
+  templates.sort_by(|a, b| {
+    (a.engine.as_str(), a.name.as_str()).cmp(&(b.engine.as_str(), b.name.as_str()))
+  });
+  templates
+}
+
+#[tauri::command]
+pub fn save_template(state: State<'_, AppState>, template: SqlTemplate) -> Result<(), String> {
+  if builtin_templates().iter().any(|t| t.id == template.id) {
+    return Err(format!(
+      "Cannot overwrite built-in template: {}",
+      template.id
+    ));
+  }
+  if !matches!(template.engine.as_str(), "mysql" | "postgres" | "sqlite") {
+    return Err(format!("Unsupported template engine: {}", template.engine));
+  }
+  if template.statements.iter().all(|s| s.trim().is_empty()) {
+    return Err("Template has no statements".to_string());
+  }
+  let template = SqlTemplate {
+    builtin: false,
+    ..template
+  };
+  state
+    .templates
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(template.id.clone(), template);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn delete_template(state: State<'_, AppState>, template_id: String) -> Result<(), String> {
+  state
+    .templates
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&template_id)
+    .map(|_| ())
+    .ok_or_else(|| format!("Unknown or built-in template: {}", template_id))
+}
+
+/// Renders a template without running it, so the SQL can be reviewed first.
+#[tauri::command]
+pub fn preview_template(
+  state: State<'_, AppState>,
+  connection: String,
+  template_id: String,
+  params: HashMap<String, String>,
+) -> Result<Vec<String>, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let template = find_template(&state, &template_id)?;
+  render(&template, &pool, &params)
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateRun {
+  pub statements: Vec<String>,
+  pub rows_affected: u64,
+}
+
+/// Renders a template and runs all of its statements in one transaction.
+#[tauri::command]
+pub async fn run_template(
+  state: State<'_, AppState>,
+  connection: String,
+  template_id: String,
+  params: HashMap<String, String>,
+) -> Result<TemplateRun, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let template = find_template(&state, &template_id)?;
+  readonly::ensure_writable(&state, &connection)?;
+  let statements = render(&template, &pool, &params)?;
+  let rows_affected = pool.execute_in_transaction(&statements).await?;
+  audit::record_batch(&state, &connection, &statements, rows_affected).await;
+  Ok(TemplateRun {
+    statements,
+    rows_affected,
+  })
+}
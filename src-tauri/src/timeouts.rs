@@ -0,0 +1,112 @@
+//! Statement timeouts, set per connection with `set_statement_timeout` or per call with the
+//! `timeout_ms` argument of the executing commands (`0` for none). A statement that runs out
+//! of time fails with an error starting with [`TIMEOUT_CODE`], so the frontend can tell it
+//! apart from other failures.
+//!
+//! Postgres enforces the timeout with `statement_timeout`, MySQL with a `MAX_EXECUTION_TIME`
+//! hint (which only bounds SELECTs), SQLite by interrupting the statement from its progress
+//! handler, and Redis and MongoDB with a timer on the client side.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use tauri::State;
+
+use crate::AppState;
+
+pub const TIMEOUT_CODE: &str = "TIMEOUT";
+
+pub type StatementTimeouts = HashMap<String, Duration>;
+
+/// Timeout for a statement on `connection`: `timeout_ms` when given, the connection's
+/// otherwise.
+pub fn resolve(state: &AppState, connection: &str, timeout_ms: Option<u64>) -> Option<Duration> {
+  match timeout_ms {
+    Some(0) => None,
+    Some(ms) => Some(Duration::from_millis(ms)),
+    None => state
+      .statement_timeouts
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .get(connection)
+      .copied(),
+  }
+}
+
+pub fn error(limit: Duration) -> String {
+  format!(
+    "{}: Statement exceeded its timeout of {} ms",
+    TIMEOUT_CODE,
+    limit.as_millis()
+  )
+}
+
+/// `sql` with a `MAX_EXECUTION_TIME` optimizer hint when it is a SELECT and `timeout` is set.
+pub fn mysql_hint(sql: &str, timeout: Option<Duration>) -> Cow<'_, str> {
+  let Some(limit) = timeout else {
+    return Cow::Borrowed(sql);
+  };
+  let start = sql.len() - sql.trim_start().len();
+  let rest = &sql[start..];
+  let is_select = rest
+    .get(..6)
+    .is_some_and(|keyword| keyword.eq_ignore_ascii_case("select"))
+    && !rest[6..].starts_with(|c: char| c.is_alphanumeric() || c == '_');
+  if !is_select {
+    return Cow::Borrowed(sql);
+  }
+  Cow::Owned(format!(
+    "{}/*+ MAX_EXECUTION_TIME({}) */{}",
+    &sql[..start + 6],
+    limit.as_millis().max(1),
+    &sql[start + 6..]
+  ))
+}
+
+/// Runs a client-side request, failing with a timeout error when it takes longer than
+/// `timeout`.
+pub async fn run<T>(
+  timeout: Option<Duration>,
+  request: impl Future<Output = Result<T, String>>,
+) -> Result<T, String> {
+  match timeout {
+    Some(limit) => tokio::time::timeout(limit, request)
+      .await
+      .map_err(|_| error(limit))?,
+    None => request.await,
+  }
+}
+
+/// Sets the statement timeout of a connection in milliseconds; `None` or `0` removes it.
+#[tauri::command]
+pub fn set_statement_timeout(
+  state: State<'_, AppState>,
+  connection: String,
+  timeout_ms: Option<u64>,
+) {
+  let mut timeouts = state
+    .statement_timeouts
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  match timeout_ms.filter(|ms| *ms > 0) {
+    Some(ms) => {
+      timeouts.insert(connection, Duration::from_millis(ms));
+    }
+    None => {
+      timeouts.remove(&connection);
+    }
+  }
+}
+
+#[tauri::command]
+pub fn get_statement_timeout(state: State<'_, AppState>, connection: String) -> Option<u64> {
+  state
+    .statement_timeouts
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(&connection)
+    .map(|limit| u64::try_from(limit.as_millis()).unwrap_or(u64::MAX))
+}
@@ -0,0 +1,337 @@
+//! Explorer for event and log tables: row counts per time bucket over a range of a timestamp
+//! column, and the rows of one bucket. Only the range condition touches the column itself,
+//! so an index on it keeps both fast on tables far too large to page through in the grid.
+//!
+//! Buckets are aligned to the Unix epoch and times are read as UTC wall-clock times.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use tauri::State;
+
+use crate::db::{self, ResultColumn, SqlPool};
+use crate::{masking, timezone, transfer, AppState};
+
+/// Bucket count aimed for when no bucket size is given.
+const TARGET_BUCKETS: i64 = 200;
+const MAX_BUCKETS: i64 = 10_000;
+const DEFAULT_ROW_LIMIT: usize = 500;
+const MAX_ROW_LIMIT: usize = 10_000;
+/// Bucket sizes picked from when none is given, in seconds.
+const AUTO_BUCKETS: &[i64] = &[
+  1, 10, 60, 300, 900, 3_600, 21_600, 86_400, 604_800, 2_592_000,
+];
+const BOUND_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeRange {
+  /// Inclusive start, RFC 3339 or `YYYY-MM-DD[ HH:MM:SS]` (read as UTC).
+  pub from: String,
+  /// Exclusive end, in the same forms.
+  pub to: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeBucket {
+  /// Start of the bucket in seconds since the Unix epoch.
+  pub start: i64,
+  pub count: i64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSeries {
+  pub time_column: String,
+  pub bucket_seconds: i64,
+  /// Range covered, in seconds since the Unix epoch; `to` is exclusive.
+  pub from: i64,
+  pub to: i64,
+  /// Every bucket of the range in order, empty ones included.
+  pub buckets: Vec<TimeBucket>,
+  pub total: i64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketRows {
+  pub columns: Vec<ResultColumn>,
+  /// Oldest first.
+  pub rows: Vec<serde_json::Value>,
+  pub has_more: bool,
+}
+
+fn parse_bound(value: &str) -> Result<i64, String> {
+  let value = value.trim();
+  if let Ok(instant) = DateTime::parse_from_rfc3339(value) {
+    return Ok(instant.timestamp());
+  }
+  for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"] {
+    if let Ok(naive) = NaiveDateTime::parse_from_str(value, format) {
+      return Ok(naive.and_utc().timestamp());
+    }
+  }
+  NaiveDate::parse_from_str(value, "%Y-%m-%d")
+    .map(|date| date.and_time(NaiveTime::MIN).and_utc().timestamp())
+    .map_err(|_| format!("Not a date or time: {}", value))
+}
+
+/// Bucket size in seconds from `30s`, `5m`, `1h`, `1d` or `1w`.
+fn parse_bucket(bucket: &str) -> Result<i64, String> {
+  let bucket = bucket.trim();
+  let invalid = || {
+    format!(
+      "Invalid bucket size '{}'; use e.g. 30s, 5m, 1h or 1d",
+      bucket
+    )
+  };
+  let split = bucket
+    .len()
+    .checked_sub(1)
+    .filter(|&i| bucket.is_char_boundary(i))
+    .ok_or_else(invalid)?;
+  let (amount, unit) = bucket.split_at(split);
+  let amount: i64 = amount.parse().map_err(|_| invalid())?;
+  let unit = match unit {
+    "s" => 1,
+    "m" => 60,
+    "h" => 3_600,
+    "d" => 86_400,
+    "w" => 604_800,
+    _ => return Err(invalid()),
+  };
+  match amount.checked_mul(unit) {
+    Some(seconds) if seconds > 0 => Ok(seconds),
+    _ => Err(invalid()),
+  }
+}
+
+/// Seconds since the epoch of the timestamp expression `expr`, as an integer.
+fn epoch_expr(pool: &SqlPool, expr: &str) -> String {
+  match pool {
+    SqlPool::MySql(_) => format!("TIMESTAMPDIFF(SECOND, '1970-01-01', {})", expr),
+    SqlPool::Postgres(_) => format!("floor(extract(epoch from {}))::bigint", expr),
+    SqlPool::Sqlite(_) => format!("CAST(strftime('%s', {}) AS INTEGER)", expr),
+  }
+}
+
+/// Start of the bucket of `seconds` width that column `col` falls into.
+fn bucket_expr(pool: &SqlPool, col: &str, seconds: i64) -> String {
+  let epoch = epoch_expr(pool, col);
+  match pool {
+    SqlPool::MySql(_) => format!("CAST(FLOOR({} / {s}) * {s} AS SIGNED)", epoch, s = seconds),
+    SqlPool::Postgres(_) => format!(
+      "(floor({}::numeric / {s}) * {s})::bigint",
+      epoch,
+      s = seconds
+    ),
+    SqlPool::Sqlite(_) => format!("({e} - (({e} % {s}) + {s}) % {s})", e = epoch, s = seconds),
+  }
+}
+
+/// `col >= <from> AND col < <to>` with both bounds bound as text, cast to the column's type
+/// on Postgres.
+async fn range_condition(
+  pool: &SqlPool,
+  table: &str,
+  column: &str,
+  from: i64,
+  to: i64,
+) -> Result<(String, Vec<String>), String> {
+  let col = pool.quote_ident(column);
+  let udt = match pool {
+    SqlPool::Postgres(_) => Some(
+      pool
+        .pg_column_type(db::PG_DEFAULT_SCHEMA, table, column)
+        .await?
+        .ok_or_else(|| format!("Unknown column {} of {}", column, table))?,
+    ),
+    _ => None,
+  };
+  let (lower, upper) = match &udt {
+    Some(udt) => (format!("$1::{}", udt), format!("$2::{}", udt)),
+    None => ("?".to_string(), "?".to_string()),
+  };
+  let suffix = if udt.as_deref() == Some("timestamptz") {
+    "+00"
+  } else {
+    ""
+  };
+  let bound = |seconds: i64| -> Result<String, String> {
+    let instant = DateTime::<Utc>::from_timestamp(seconds, 0)
+      .ok_or_else(|| format!("Time out of range: {}", seconds))?;
+    Ok(format!("{}{}", instant.format(BOUND_FORMAT), suffix))
+  };
+  Ok((
+    format!("{col} >= {} AND {col} < {}", lower, upper, col = col),
+    vec![bound(from)?, bound(to)?],
+  ))
+}
+
+fn json_i64(value: Option<&serde_json::Value>) -> Option<i64> {
+  match value? {
+    serde_json::Value::Number(n) => n.as_i64(),
+    serde_json::Value::String(s) => s.parse().ok(),
+    _ => None,
+  }
+}
+
+/// Earliest and latest time in `column`, as seconds since the epoch.
+async fn extent(pool: &SqlPool, table: &str, column: &str) -> Result<Option<(i64, i64)>, String> {
+  let col = pool.quote_ident(column);
+  let sql = format!(
+    "SELECT {} AS lo, {} AS hi FROM {}",
+    epoch_expr(pool, &format!("MIN({})", col)),
+    epoch_expr(pool, &format!("MAX({})", col)),
+    pool.table_ref(table)
+  );
+  let rows = pool.fetch_rows(&sql, &[]).await?;
+  let Some(row) = rows.first() else {
+    return Ok(None);
+  };
+  Ok(json_i64(row.get("lo")).zip(json_i64(row.get("hi"))))
+}
+
+/// Row counts of `table` per `bucket` (`30s`, `5m`, `1h`, `1d`, ...; picked to give about
+/// 200 buckets when not given) of `time_column` over `range`, the column's whole span when
+/// not given.
+#[tauri::command]
+pub async fn explore_time_series(
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+  time_column: String,
+  range: Option<TimeRange>,
+  bucket: Option<String>,
+) -> Result<TimeSeries, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let table = pool.resolve_table(&table).await?;
+  let (from, to) = match &range {
+    Some(range) => (parse_bound(&range.from)?, parse_bound(&range.to)?),
+    None => match extent(&pool, &table, &time_column).await? {
+      Some((lo, hi)) => (lo, hi + 1),
+      None => {
+        return Ok(TimeSeries {
+          time_column,
+          bucket_seconds: 0,
+          from: 0,
+          to: 0,
+          buckets: Vec::new(),
+          total: 0,
+        })
+      }
+    },
+  };
+  if to <= from {
+    return Err("The end of the range must come after its start".to_string());
+  }
+  let seconds = match &bucket {
+    Some(bucket) => parse_bucket(bucket)?,
+    None => AUTO_BUCKETS
+      .iter()
+      .copied()
+      .find(|s| (to - from) / s <= TARGET_BUCKETS)
+      .unwrap_or(AUTO_BUCKETS[AUTO_BUCKETS.len() - 1]),
+  };
+  let first = from.div_euclid(seconds) * seconds;
+  let count = (to - first + seconds - 1) / seconds;
+  if count > MAX_BUCKETS {
+    return Err(format!(
+      "The range holds {} buckets of {} s; use larger buckets (at most {})",
+      count, seconds, MAX_BUCKETS
+    ));
+  }
+
+  let (condition, binds) = range_condition(&pool, &table, &time_column, from, to).await?;
+  let sql = format!(
+    "SELECT {} AS bucket, COUNT(*) AS count FROM {} WHERE {} GROUP BY 1 ORDER BY 1",
+    bucket_expr(&pool, &pool.quote_ident(&time_column), seconds),
+    pool.table_ref(&table),
+    condition
+  );
+  let counted: Vec<(i64, i64)> = pool
+    .fetch_rows(&sql, &binds)
+    .await?
+    .iter()
+    .filter_map(|row| json_i64(row.get("bucket")).zip(json_i64(row.get("count"))))
+    .collect();
+
+  let mut buckets: Vec<TimeBucket> = (0..count)
+    .map(|i| TimeBucket {
+      start: first + i * seconds,
+      count: 0,
+    })
+    .collect();
+  for (start, rows) in &counted {
+    let index = (start - first) / seconds;
+    if let Some(bucket) = usize::try_from(index).ok().and_then(|i| buckets.get_mut(i)) {
+      bucket.count = *rows;
+    }
+  }
+  Ok(TimeSeries {
+    time_column,
+    bucket_seconds: seconds,
+    from,
+    to,
+    total: counted.iter().map(|(_, rows)| rows).sum(),
+    buckets,
+  })
+}
+
+/// Rows of one bucket from [`explore_time_series`], oldest first, `limit` at a time.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn time_bucket_rows(
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+  time_column: String,
+  bucket_start: i64,
+  bucket_seconds: i64,
+  limit: Option<usize>,
+  offset: Option<usize>,
+) -> Result<BucketRows, String> {
+  if bucket_seconds <= 0 {
+    return Err("Bucket size must be positive".to_string());
+  }
+  let pool = db::sql_pool(&state, &connection)?;
+  let table = pool.resolve_table(&table).await?;
+  let limit = limit.unwrap_or(DEFAULT_ROW_LIMIT).clamp(1, MAX_ROW_LIMIT);
+  let (condition, binds) = range_condition(
+    &pool,
+    &table,
+    &time_column,
+    bucket_start,
+    bucket_start + bucket_seconds,
+  )
+  .await?;
+  // One extra row tells whether the bucket holds more
+  let sql = format!(
+    "SELECT * FROM {} WHERE {} ORDER BY {} LIMIT {} OFFSET {}",
+    pool.table_ref(&table),
+    condition,
+    pool.quote_ident(&time_column),
+    limit + 1,
+    offset.unwrap_or(0)
+  );
+  let tz = timezone::display_zone(&state, &connection);
+  let (columns, mut rows) = pool.fetch_with_columns(&sql, &binds, tz.as_ref()).await?;
+  let has_more = rows.len() > limit;
+  rows.truncate(limit);
+  if let Some(mask) = masking::active(&state, &connection) {
+    for row in &mut rows {
+      mask.apply(row);
+    }
+  }
+  transfer::record(
+    &state,
+    &connection,
+    transfer::Category::Browse,
+    rows.len(),
+    transfer::rows_bytes(&rows),
+  );
+  Ok(BucketRows {
+    columns,
+    rows: rows.into_iter().map(serde_json::Value::Object).collect(),
+    has_more,
+  })
+}
@@ -0,0 +1,90 @@
+//! Per-connection display time zone for timestamp columns that store an absolute instant
+//! (MySQL `TIMESTAMP`, Postgres `timestamptz`). Zone-less `DATETIME`/`timestamp` values are
+//! wall-clock times and are never converted.
+
+use std::sync::PoisonError;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use tauri::State;
+
+use crate::AppState;
+
+/// Instant rendered in the display zone with its UTC offset, so DST is resolved here and the
+/// value still round-trips unambiguously on update.
+pub fn format_instant(instant: DateTime<Utc>, tz: Tz) -> String {
+  instant.with_timezone(&tz).to_rfc3339()
+}
+
+pub fn display_zone(state: &AppState, connection: &str) -> Option<Tz> {
+  state
+    .display_timezones
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get(connection)
+    .copied()
+}
+
+/// Converts the given instant columns of rows already serialized to JSON text (Postgres
+/// `row_to_json` output), leaving anything that doesn't parse as RFC 3339 untouched.
+pub fn apply_json_rows(rows: Vec<String>, columns: &[String], tz: Tz) -> Vec<String> {
+  rows
+    .into_iter()
+    .map(
+      |row| match serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&row) {
+        Ok(mut map) => {
+          for column in columns {
+            if let Some(value) = map.get_mut(column) {
+              let converted = value
+                .as_str()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| format_instant(dt.with_timezone(&Utc), tz));
+              if let Some(converted) = converted {
+                *value = serde_json::Value::String(converted);
+              }
+            }
+          }
+          serde_json::Value::Object(map).to_string()
+        }
+        Err(_) => row,
+      },
+    )
+    .collect()
+}
+
+/// Sets the IANA zone (e.g. `Europe/Berlin`) used to display instants for a connection;
+/// `None` goes back to UTC.
+#[tauri::command]
+pub fn set_display_timezone(
+  state: State<'_, AppState>,
+  connection: String,
+  timezone: Option<String>,
+) -> Result<(), String> {
+  let mut zones = state
+    .display_timezones
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  match timezone {
+    Some(name) => {
+      let tz: Tz = name
+        .trim()
+        .parse()
+        .map_err(|_| format!("Unknown time zone: {}", name))?;
+      zones.insert(connection, tz);
+    }
+    None => {
+      zones.remove(&connection);
+    }
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_display_timezone(state: State<'_, AppState>, connection: String) -> Option<String> {
+  display_zone(&state, &connection).map(|tz| tz.name().to_string())
+}
+
+#[tauri::command]
+pub fn list_timezones() -> Vec<&'static str> {
+  chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name()).collect()
+}
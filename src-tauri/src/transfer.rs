@@ -0,0 +1,177 @@
+//! Rows and approximate bytes each connection has sent the app, split by what asked for
+//! them, so users on metered links can see which views are the expensive ones.
+//!
+//! Bytes are estimated from the decoded values (roughly their JSON size), not measured on
+//! the wire; protocol overhead and compression are not included.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::PoisonError;
+
+use tauri::State;
+
+use crate::db::JsonRow;
+use crate::{store, AppState};
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum Category {
+  /// Table browsing (`*_get_rows`).
+  Browse,
+  /// SQL typed by the user: raw executors and cached results.
+  Query,
+  /// Rows read for exports.
+  Export,
+  /// Redis keys and values.
+  Redis,
+  /// Watches, tails and change feeds polling in the background.
+  Background,
+}
+
+#[derive(serde::Serialize, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Counter {
+  pub requests: u64,
+  pub rows: u64,
+  pub bytes: u64,
+  pub last_at: i64,
+}
+
+impl Counter {
+  fn add(&mut self, other: &Counter) {
+    self.requests += other.requests;
+    self.rows += other.rows;
+    self.bytes += other.bytes;
+    self.last_at = self.last_at.max(other.last_at);
+  }
+}
+
+#[derive(Default)]
+pub struct Transfers {
+  /// When counting started (or was last reset), in ms since the epoch.
+  since: i64,
+  counters: HashMap<String, HashMap<Category, Counter>>,
+}
+
+/// Approximate JSON size of a value.
+fn value_bytes(value: &serde_json::Value) -> usize {
+  match value {
+    serde_json::Value::Null => 4,
+    serde_json::Value::Bool(_) => 5,
+    serde_json::Value::Number(_) => 8,
+    serde_json::Value::String(s) => s.len() + 2,
+    serde_json::Value::Array(items) => items.iter().map(value_bytes).sum::<usize>() + 2,
+    serde_json::Value::Object(map) => {
+      map
+        .iter()
+        .map(|(k, v)| k.len() + 3 + value_bytes(v))
+        .sum::<usize>()
+        + 2
+    }
+  }
+}
+
+/// Approximate size of decoded rows.
+pub fn rows_bytes(rows: &[JsonRow]) -> usize {
+  rows
+    .iter()
+    .map(|row| {
+      row
+        .iter()
+        .map(|(k, v)| k.len() + 3 + value_bytes(v))
+        .sum::<usize>()
+        + 2
+    })
+    .sum()
+}
+
+/// Counts a request whose rows were already serialized to JSON strings.
+pub fn record_serialized(state: &AppState, connection: &str, category: Category, rows: &[String]) {
+  let bytes = rows.iter().map(String::len).sum();
+  record(state, connection, category, rows.len(), bytes);
+}
+
+/// Counts one request on `connection` that returned `rows` rows of about `bytes` bytes.
+pub fn record(state: &AppState, connection: &str, category: Category, rows: usize, bytes: usize) {
+  let mut transfers = state
+    .transfers
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let now = store::now_ms();
+  if transfers.since == 0 {
+    transfers.since = now;
+  }
+  let counter = transfers
+    .counters
+    .entry(connection.to_string())
+    .or_default()
+    .entry(category)
+    .or_default();
+  counter.add(&Counter {
+    requests: 1,
+    rows: rows as u64,
+    bytes: bytes as u64,
+    last_at: now,
+  });
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionTransfer {
+  pub connection_id: String,
+  pub total: Counter,
+  pub categories: BTreeMap<Category, Counter>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferStats {
+  pub since: Option<i64>,
+  /// Heaviest connection first.
+  pub connections: Vec<ConnectionTransfer>,
+}
+
+/// Transfer totals per connection (or just `connection`) and category since the app started
+/// or the stats were last reset.
+#[tauri::command]
+pub fn get_transfer_stats(state: State<'_, AppState>, connection: Option<String>) -> TransferStats {
+  let transfers = state
+    .transfers
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let mut connections: Vec<ConnectionTransfer> = transfers
+    .counters
+    .iter()
+    .filter(|(id, _)| connection.as_ref().is_none_or(|wanted| wanted == *id))
+    .map(|(id, categories)| {
+      let mut total = Counter::default();
+      for c in categories.values() {
+        total.add(c);
+      }
+      ConnectionTransfer {
+        connection_id: id.clone(),
+        total,
+        categories: categories.iter().map(|(k, v)| (*k, *v)).collect(),
+      }
+    })
+    .collect();
+  connections.sort_by_key(|c| std::cmp::Reverse(c.total.bytes));
+  TransferStats {
+    since: (transfers.since != 0).then_some(transfers.since),
+    connections,
+  }
+}
+
+/// Clears the counters of `connection`, or of every connection.
+#[tauri::command]
+pub fn reset_transfer_stats(state: State<'_, AppState>, connection: Option<String>) {
+  let mut transfers = state
+    .transfers
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  match connection {
+    Some(id) => {
+      transfers.counters.remove(&id);
+    }
+    None => *transfers = Transfers::default(),
+  }
+}
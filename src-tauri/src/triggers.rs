@@ -0,0 +1,305 @@
+//! Triggers of a table for the Triggers tab: when they fire, on what, and what they run, plus
+//! dropping one. MySQL and Postgres describe them in their catalogs; SQLite only keeps the
+//! `CREATE TRIGGER` text, which is read apart here.
+
+use sqlx::Row;
+use tauri::State;
+
+use crate::db::SqlPool;
+use crate::{audit, connections, ident, readonly, AppState};
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerInfo {
+  pub name: String,
+  /// `BEFORE`, `AFTER` or `INSTEAD OF`.
+  pub timing: String,
+  /// `INSERT`, `UPDATE`, `DELETE` or `TRUNCATE`; Postgres triggers can fire on several.
+  pub events: Vec<String>,
+  /// Fires `FOR EACH ROW` rather than once per statement.
+  pub for_each_row: bool,
+  /// What the trigger runs: its statement on MySQL, the `BEGIN ... END` block's contents on
+  /// SQLite, and the trigger function's definition on Postgres.
+  pub body: String,
+  /// The `CREATE TRIGGER` statement.
+  pub definition: String,
+  pub enabled: bool,
+}
+
+/// Words of `sql` outside quotes and `--` comments, with their byte offsets.
+fn words(sql: &str) -> Vec<(usize, &str)> {
+  let mut words = Vec::new();
+  let mut quote = None;
+  let mut word_start = None;
+  let mut chars = sql.char_indices().chain([(sql.len(), ' ')]).peekable();
+  while let Some((i, c)) = chars.next() {
+    if let Some(close) = quote {
+      if c == close {
+        quote = None;
+      }
+      continue;
+    }
+    if c.is_alphanumeric() || c == '_' {
+      word_start.get_or_insert(i);
+      continue;
+    }
+    if let Some(start) = word_start.take() {
+      words.push((start, &sql[start..i]));
+    }
+    match (c, chars.peek().map(|(_, next)| *next)) {
+      ('\'' | '"' | '`', _) => quote = Some(c),
+      ('[', _) => quote = Some(']'),
+      ('-', Some('-')) => quote = Some('\n'),
+      _ => {}
+    }
+  }
+  words
+}
+
+/// Timing, event and body of a SQLite `CREATE TRIGGER` statement.
+fn parse_sqlite_trigger(sql: &str) -> (String, String, String) {
+  let words = words(sql);
+  let is = |word: &str, keyword: &str| word.eq_ignore_ascii_case(keyword);
+  let head = words
+    .iter()
+    .take_while(|(_, word)| !is(word, "ON"))
+    .map(|(_, word)| *word);
+  let mut timing = "BEFORE".to_string();
+  let mut event = String::new();
+  for word in head {
+    if ["BEFORE", "AFTER"].iter().any(|k| is(word, k)) {
+      timing = word.to_uppercase();
+    } else if is(word, "INSTEAD") {
+      timing = "INSTEAD OF".to_string();
+    } else if event.is_empty() && ["INSERT", "UPDATE", "DELETE"].iter().any(|k| is(word, k)) {
+      event = word.to_uppercase();
+    }
+  }
+  let begin = words.iter().find(|(_, word)| is(word, "BEGIN"));
+  let end = words.iter().rev().find(|(_, word)| is(word, "END"));
+  let body = match (begin, end) {
+    (Some((start, word)), Some((stop, _))) if start < stop => &sql[start + word.len()..*stop],
+    _ => "",
+  };
+  (timing, event, body.trim().to_string())
+}
+
+/// The event names set in a Postgres `tgtype`.
+fn pg_events(tgtype: i16) -> Vec<String> {
+  [
+    (4, "INSERT"),
+    (16, "UPDATE"),
+    (8, "DELETE"),
+    (32, "TRUNCATE"),
+  ]
+  .iter()
+  .filter(|(bit, _)| tgtype & bit != 0)
+  .map(|(_, event)| event.to_string())
+  .collect()
+}
+
+/// Triggers on `table`, by name.
+pub async fn triggers(pool: &SqlPool, table: &str) -> Result<Vec<TriggerInfo>, String> {
+  match pool {
+    SqlPool::MySql(mysql) => {
+      let rows: Vec<(String, String, String, String, String)> = sqlx::query_as(
+        "SELECT CAST(TRIGGER_NAME AS CHAR), CAST(ACTION_TIMING AS CHAR), \
+         CAST(EVENT_MANIPULATION AS CHAR), CAST(ACTION_ORIENTATION AS CHAR), \
+         CAST(ACTION_STATEMENT AS CHAR) \
+         FROM information_schema.TRIGGERS \
+         WHERE EVENT_OBJECT_SCHEMA = DATABASE() AND EVENT_OBJECT_TABLE = ? \
+         ORDER BY TRIGGER_NAME",
+      )
+      .bind(table)
+      .fetch_all(mysql)
+      .await
+      .map_err(|e| e.to_string())?;
+      Ok(
+        rows
+          .into_iter()
+          .map(|(name, timing, event, orientation, body)| TriggerInfo {
+            definition: format!(
+              "CREATE TRIGGER {} {} {} ON {} FOR EACH {} {}",
+              pool.quote_ident(&name),
+              timing,
+              event,
+              pool.quote_ident(table),
+              orientation,
+              body
+            ),
+            name,
+            timing,
+            events: vec![event],
+            for_each_row: orientation.eq_ignore_ascii_case("ROW"),
+            body,
+            // MySQL has no disabled triggers
+            enabled: true,
+          })
+          .collect(),
+      )
+    }
+    SqlPool::Postgres(pg) => {
+      let rows = sqlx::query(
+        "SELECT t.tgname::text, t.tgtype, pg_get_triggerdef(t.oid, true), \
+         pg_get_functiondef(t.tgfoid), t.tgenabled <> 'D' \
+         FROM pg_trigger t JOIN pg_class c ON c.oid = t.tgrelid \
+         WHERE c.relnamespace = 'public'::regnamespace AND c.relname = $1 \
+         AND NOT t.tgisinternal ORDER BY t.tgname",
+      )
+      .bind(table)
+      .fetch_all(pg)
+      .await
+      .map_err(|e| e.to_string())?;
+      rows
+        .iter()
+        .map(|row| {
+          let tgtype: i16 = row.try_get(1).map_err(|e| e.to_string())?;
+          let timing = match (tgtype & 2 != 0, tgtype & 64 != 0) {
+            (_, true) => "INSTEAD OF",
+            (true, false) => "BEFORE",
+            (false, false) => "AFTER",
+          };
+          Ok(TriggerInfo {
+            name: row.try_get(0).map_err(|e| e.to_string())?,
+            timing: timing.to_string(),
+            events: pg_events(tgtype),
+            for_each_row: tgtype & 1 != 0,
+            body: row.try_get(3).map_err(|e| e.to_string())?,
+            definition: row.try_get(2).map_err(|e| e.to_string())?,
+            enabled: row.try_get(4).map_err(|e| e.to_string())?,
+          })
+        })
+        .collect()
+    }
+    SqlPool::Sqlite(sqlite) => {
+      let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT name, sql FROM sqlite_master WHERE type = 'trigger' AND tbl_name = ? \
+         ORDER BY name",
+      )
+      .bind(table)
+      .fetch_all(sqlite)
+      .await
+      .map_err(|e| e.to_string())?;
+      Ok(
+        rows
+          .into_iter()
+          .map(|(name, sql)| {
+            let (timing, event, body) = parse_sqlite_trigger(&sql);
+            TriggerInfo {
+              name,
+              timing,
+              events: vec![event],
+              for_each_row: true,
+              body,
+              definition: sql,
+              enabled: true,
+            }
+          })
+          .collect(),
+      )
+    }
+  }
+}
+
+async fn drop_trigger(
+  state: &AppState,
+  pool: SqlPool,
+  connection: &str,
+  table_name: String,
+  trigger_name: String,
+) -> Result<String, String> {
+  readonly::ensure_writable(state, connection)?;
+  let table = pool.resolve_table(&table_name).await?;
+  let names: Vec<String> = triggers(&pool, &table)
+    .await?
+    .into_iter()
+    .map(|t| t.name)
+    .collect();
+  let trigger = ident::resolve(pool.dialect(), &trigger_name, &names)
+    .map_err(|_| format!("Table {} has no trigger {}", table, trigger_name))?;
+  let sql = match &pool {
+    SqlPool::Postgres(_) => format!(
+      "DROP TRIGGER {} ON {}",
+      pool.quote_ident(trigger),
+      pool.table_ref(&table)
+    ),
+    _ => format!("DROP TRIGGER {}", pool.quote_ident(trigger)),
+  };
+  pool
+    .execute_in_transaction(std::slice::from_ref(&sql))
+    .await?;
+  audit::record(state, connection, &sql, serde_json::json!([]), None).await;
+  Ok(sql)
+}
+
+#[tauri::command]
+pub async fn mysql_get_triggers(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<Vec<TriggerInfo>, String> {
+  let pool = SqlPool::MySql(connections::mysql(&state, connection_id.as_deref())?);
+  let table = pool.resolve_table(&table_name).await?;
+  triggers(&pool, &table).await
+}
+
+#[tauri::command]
+pub async fn postgres_get_triggers(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<Vec<TriggerInfo>, String> {
+  let pool = SqlPool::Postgres(connections::postgres(&state, connection_id.as_deref())?);
+  let table = pool.resolve_table(&table_name).await?;
+  triggers(&pool, &table).await
+}
+
+#[tauri::command]
+pub async fn sqlite_get_triggers(
+  state: State<'_, AppState>,
+  table_name: String,
+  connection_id: Option<String>,
+) -> Result<Vec<TriggerInfo>, String> {
+  let pool = SqlPool::Sqlite(connections::sqlite(&state, connection_id.as_deref())?);
+  let table = pool.resolve_table(&table_name).await?;
+  triggers(&pool, &table).await
+}
+
+/// Drops trigger `trigger_name` of a MySQL table; returns the statement that ran.
+#[tauri::command]
+pub async fn mysql_drop_trigger(
+  state: State<'_, AppState>,
+  table_name: String,
+  trigger_name: String,
+  connection_id: Option<String>,
+) -> Result<String, String> {
+  let pool = SqlPool::MySql(connections::mysql(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("mysql");
+  drop_trigger(&state, pool, connection, table_name, trigger_name).await
+}
+
+/// Drops trigger `trigger_name` of a PostgreSQL table; returns the statement that ran.
+#[tauri::command]
+pub async fn postgres_drop_trigger(
+  state: State<'_, AppState>,
+  table_name: String,
+  trigger_name: String,
+  connection_id: Option<String>,
+) -> Result<String, String> {
+  let pool = SqlPool::Postgres(connections::postgres(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("postgres");
+  drop_trigger(&state, pool, connection, table_name, trigger_name).await
+}
+
+/// Drops trigger `trigger_name` of a SQLite table; returns the statement that ran.
+#[tauri::command]
+pub async fn sqlite_drop_trigger(
+  state: State<'_, AppState>,
+  table_name: String,
+  trigger_name: String,
+  connection_id: Option<String>,
+) -> Result<String, String> {
+  let pool = SqlPool::Sqlite(connections::sqlite(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("sqlite");
+  drop_trigger(&state, pool, connection, table_name, trigger_name).await
+}
@@ -0,0 +1,310 @@
+//! SSH tunnel bookkeeping. Every tunnel opened for a connection is tracked with its local
+//! port and target, and watched so that a dropped SSH session is reported as `tunnel-lost`
+//! and its listener stops, instead of accepting connections that go nowhere.
+//!
+//! Dropping a tunnel shuts all of it down: the accept loop, the channels it forwards and the
+//! SSH sessions, so replaced or failed connections don't keep local ports and sessions open.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::time::Duration;
+
+use russh::{client, Disconnect};
+use tauri::{Emitter, State};
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::task::JoinHandle;
+
+use crate::{store, AppState, ClientHandler, SshConfig};
+
+/// How often tunnel sessions are checked for having closed.
+const MONITOR_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A freshly established tunnel: the SSH session, the jump sessions carrying it and the task
+/// accepting local connections, which owns the tasks forwarding them.
+pub(crate) struct Forwarding {
+  pub local_port: u16,
+  pub session: Arc<AsyncMutex<client::Handle<ClientHandler>>>,
+  pub jumps: Vec<client::Handle<ClientHandler>>,
+  pub listener: JoinHandle<()>,
+}
+
+pub struct SshTunnel {
+  id: String,
+  local_port: u16,
+  target: String,
+  via: String,
+  opened_at: i64,
+  lost: Arc<AtomicBool>,
+  /// What the tunnel was opened with, to re-establish it after the session is lost.
+  ssh_config: SshConfig,
+  remote_host: String,
+  remote_port: u16,
+  /// Kept so the sessions stay open for as long as the tunnel is registered.
+  session: Arc<AsyncMutex<client::Handle<ClientHandler>>>,
+  jumps: Vec<client::Handle<ClientHandler>>,
+  listener: JoinHandle<()>,
+  monitor: JoinHandle<()>,
+}
+
+impl Drop for SshTunnel {
+  fn drop(&mut self) {
+    self.listener.abort();
+    self.monitor.abort();
+    // Closes the target session, then the jump hosts it ran through, innermost first
+    let session = self.session.clone();
+    let jumps = std::mem::take(&mut self.jumps);
+    tauri::async_runtime::spawn(async move {
+      let _ = session
+        .lock()
+        .await
+        .disconnect(Disconnect::ByApplication, "", "en")
+        .await;
+      for jump in jumps.iter().rev() {
+        let _ = jump.disconnect(Disconnect::ByApplication, "", "en").await;
+      }
+    });
+  }
+}
+
+/// A tunnel opened for a connection that isn't registered yet. Dropping it closes the tunnel,
+/// so a connect that fails after opening one doesn't leave it behind; see [`connected`].
+pub struct Opened<'a> {
+  state: &'a AppState,
+  connection_id: String,
+  tunnel_id: String,
+  pub local_port: u16,
+  keep: bool,
+}
+
+impl Drop for Opened<'_> {
+  fn drop(&mut self) {
+    if self.keep {
+      return;
+    }
+    let mut tunnels = self
+      .state
+      .tunnels
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    if tunnels
+      .get(&self.connection_id)
+      .is_some_and(|tunnel| tunnel.id == self.tunnel_id)
+    {
+      tunnels.remove(&self.connection_id);
+    }
+  }
+}
+
+/// Tunnels keyed by the connection id they carry.
+pub type Tunnels = HashMap<String, SshTunnel>;
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelLost {
+  pub tunnel_id: String,
+  pub connection_id: String,
+  pub local_port: u16,
+  pub target: String,
+}
+
+/// Opens a tunnel to `remote_host:remote_port` for `connection_id`, replacing any tunnel the
+/// connection had. Connect through its `local_port`, then hand it to [`connected`].
+pub async fn open<'a>(
+  state: &'a AppState,
+  connection_id: &str,
+  ssh_config: SshConfig,
+  remote_host: String,
+  remote_port: u16,
+) -> Result<Opened<'a>, String> {
+  let (tunnel_id, local_port) = open_on(
+    state,
+    connection_id,
+    ssh_config,
+    remote_host,
+    remote_port,
+    0,
+  )
+  .await?;
+  Ok(Opened {
+    state,
+    connection_id: connection_id.to_string(),
+    tunnel_id,
+    local_port,
+    keep: false,
+  })
+}
+
+/// Settles the tunnel of a connection that was just registered: keeps `opened` when it
+/// connected through one, and otherwise closes the tunnel an earlier connect left.
+pub fn connected(state: &AppState, connection_id: &str, opened: Option<Opened<'_>>) {
+  match opened {
+    Some(mut opened) => opened.keep = true,
+    None => close_for(state, connection_id),
+  }
+}
+
+async fn open_on(
+  state: &AppState,
+  connection_id: &str,
+  ssh_config: SshConfig,
+  remote_host: String,
+  remote_port: u16,
+  local_port: u16,
+) -> Result<(String, u16), String> {
+  let via = format!(
+    "{}@{}:{}",
+    ssh_config.username, ssh_config.host, ssh_config.port
+  );
+  let forwarding = crate::establish_ssh_tunnel(
+    ssh_config.clone(),
+    remote_host.clone(),
+    remote_port,
+    local_port,
+  )
+  .await?;
+  let id = crate::next_id("tunnel");
+  let target = format!("{}:{}", remote_host, remote_port);
+  let lost = Arc::new(AtomicBool::new(false));
+
+  let monitor = {
+    let session = forwarding.session.clone();
+    let listener = forwarding.listener.abort_handle();
+    let lost = lost.clone();
+    let app = state
+      .app
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .clone();
+    let event = TunnelLost {
+      tunnel_id: id.clone(),
+      connection_id: connection_id.to_string(),
+      local_port: forwarding.local_port,
+      target: target.clone(),
+    };
+    tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(MONITOR_INTERVAL).await;
+        if session.lock().await.is_closed() {
+          break;
+        }
+      }
+      listener.abort();
+      lost.store(true, Ordering::Relaxed);
+      if let Some(app) = app {
+        let _ = app.emit("tunnel-lost", event);
+      }
+    })
+  };
+
+  let local_port = forwarding.local_port;
+  let tunnel = SshTunnel {
+    id: id.clone(),
+    local_port,
+    target,
+    via,
+    opened_at: store::now_ms(),
+    lost,
+    ssh_config,
+    remote_host,
+    remote_port,
+    session: forwarding.session,
+    jumps: forwarding.jumps,
+    listener: forwarding.listener,
+    monitor,
+  };
+  state
+    .tunnels
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(connection_id.to_string(), tunnel);
+  Ok((id, local_port))
+}
+
+/// Re-establishes the tunnel of `connection_id` on its old local port if its SSH session was
+/// lost, so pools pointing at that port work again. No-op for healthy or missing tunnels.
+pub async fn reopen_if_lost(state: &AppState, connection_id: &str) -> Result<(), String> {
+  let lost = {
+    let tunnels = state.tunnels.lock().unwrap_or_else(PoisonError::into_inner);
+    tunnels
+      .get(connection_id)
+      .filter(|tunnel| tunnel.lost.load(Ordering::Relaxed))
+      .map(|tunnel| {
+        (
+          tunnel.ssh_config.clone(),
+          tunnel.remote_host.clone(),
+          tunnel.remote_port,
+          tunnel.local_port,
+        )
+      })
+  };
+  let Some((ssh_config, remote_host, remote_port, local_port)) = lost else {
+    return Ok(());
+  };
+  open_on(
+    state,
+    connection_id,
+    ssh_config,
+    remote_host,
+    remote_port,
+    local_port,
+  )
+  .await?;
+  Ok(())
+}
+
+/// Closes the tunnel carrying `connection_id`, if there is one.
+pub fn close_for(state: &AppState, connection_id: &str) {
+  state
+    .tunnels
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(connection_id);
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelInfo {
+  pub tunnel_id: String,
+  pub connection_id: String,
+  pub local_port: u16,
+  /// `host:port` the tunnel forwards to.
+  pub target: String,
+  /// `user@host:port` of the SSH server the tunnel runs through.
+  pub via: String,
+  pub opened_at: i64,
+  /// The SSH session is still up.
+  pub alive: bool,
+}
+
+#[tauri::command]
+pub fn list_ssh_tunnels(state: State<'_, AppState>) -> Vec<TunnelInfo> {
+  let tunnels = state.tunnels.lock().unwrap_or_else(PoisonError::into_inner);
+  let mut list: Vec<TunnelInfo> = tunnels
+    .iter()
+    .map(|(connection_id, tunnel)| TunnelInfo {
+      tunnel_id: tunnel.id.clone(),
+      connection_id: connection_id.clone(),
+      local_port: tunnel.local_port,
+      target: tunnel.target.clone(),
+      via: tunnel.via.clone(),
+      opened_at: tunnel.opened_at,
+      alive: !tunnel.lost.load(Ordering::Relaxed),
+    })
+    .collect();
+  list.sort_by(|a, b| a.connection_id.cmp(&b.connection_id));
+  list
+}
+
+/// Shuts a tunnel down. The connection it carried stays registered but can no longer reach
+/// its server until it is reconnected.
+#[tauri::command]
+pub fn close_ssh_tunnel(state: State<'_, AppState>, tunnel_id: String) -> Result<(), String> {
+  let mut tunnels = state.tunnels.lock().unwrap_or_else(PoisonError::into_inner);
+  let before = tunnels.len();
+  tunnels.retain(|_, tunnel| tunnel.id != tunnel_id);
+  if tunnels.len() == before {
+    return Err(format!("Unknown SSH tunnel: {}", tunnel_id));
+  }
+  Ok(())
+}
@@ -0,0 +1,651 @@
+//! In-app updates for the packaged desktop app. Every release publishes a manifest in the
+//! shape of Tauri's static updater JSON (`version`, `notes`, `pub_date` and a download `url`
+//! and `signature` per platform). The `stable` channel reads the manifest of the latest full
+//! release; `beta` also reads the one of the rolling `beta` pre-release and takes whichever
+//! is newer.
+//!
+//! `download_update` fetches the installer for this platform in the background, reporting
+//! `update-progress` and then `update-ready` or `update-failed`. The installer must carry a
+//! valid minisign signature (prehashed, as `minisign -S` and `tauri signer sign` make them)
+//! from the key baked in at build time through `SPECTRA_UPDATE_PUBKEY`. Builds without that
+//! key still report updates but won't install them. The installer is written to the app data
+//! directory as it downloads, staged once it's verified, and checked again right before it
+//! runs on the next start, or straight away through `restart_to_update`.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::AsyncWriteExt;
+
+use crate::{store, AppState};
+
+const REPOSITORY: &str = "https://github.com/dsxksss/spectra-studio";
+const MANIFEST_NAME: &str = "latest.json";
+/// Tag of the pre-release the beta channel's builds are published under.
+const BETA_TAG: &str = "beta";
+const CHANNEL_KEY: &str = "update_channel";
+const STAGING_DIR: &str = "updates";
+/// Describes the staged installer; written once the installer is verified and in place.
+const PENDING_FILE: &str = "pending.json";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+const READ_CHUNK: usize = 64 * 1024;
+/// Public key (the base64 line of a minisign `.pub` file) release installers are signed with.
+const PUBLIC_KEY: Option<&str> = option_env!("SPECTRA_UPDATE_PUBKEY");
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Channel {
+  Stable,
+  Beta,
+}
+
+impl Channel {
+  fn parse(name: &str) -> Result<Channel, String> {
+    match name {
+      "stable" => Ok(Channel::Stable),
+      "beta" => Ok(Channel::Beta),
+      other => Err(format!(
+        "Unknown update channel '{}'; expected stable or beta",
+        other
+      )),
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      Channel::Stable => "stable",
+      Channel::Beta => "beta",
+    }
+  }
+
+  fn manifest_urls(self) -> Vec<String> {
+    let stable = format!("{}/releases/latest/download/{}", REPOSITORY, MANIFEST_NAME);
+    match self {
+      Channel::Stable => vec![stable],
+      Channel::Beta => vec![
+        stable,
+        format!(
+          "{}/releases/download/{}/{}",
+          REPOSITORY, BETA_TAG, MANIFEST_NAME
+        ),
+      ],
+    }
+  }
+}
+
+/// Download of an update that is in progress, so a second one isn't started next to it.
+#[derive(Default)]
+pub struct Updates {
+  pub downloading: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct Manifest {
+  version: String,
+  #[serde(default)]
+  notes: Option<String>,
+  #[serde(default)]
+  pub_date: Option<String>,
+  #[serde(default)]
+  platforms: std::collections::HashMap<String, PlatformAsset>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+struct PlatformAsset {
+  url: String,
+  signature: String,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+  pub channel: Channel,
+  pub current_version: String,
+  /// Newest version on the channel.
+  pub latest_version: String,
+  pub available: bool,
+  pub notes: Option<String>,
+  pub pub_date: Option<String>,
+  /// Whether this build can download and install it itself; otherwise the UI links to the
+  /// release page.
+  pub installable: bool,
+  /// Version of an installer already downloaded and waiting for a restart.
+  pub staged_version: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Pending {
+  version: String,
+  file: String,
+  /// Signature from the manifest, to check the installer against before running it.
+  signature: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgressEvent {
+  version: String,
+  downloaded: u64,
+  total: Option<u64>,
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultEvent {
+  version: String,
+  error: Option<String>,
+}
+
+/// Manifest key of this platform, as Tauri names them (`windows-x86_64`, `darwin-aarch64`).
+fn platform_key() -> String {
+  let os = match std::env::consts::OS {
+    "macos" => "darwin",
+    other => other,
+  };
+  format!("{}-{}", os, std::env::consts::ARCH)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum InstallerKind {
+  Nsis,
+  Msi,
+  AppImage,
+}
+
+/// How a downloaded file is installed; `None` for archives (the macOS `.app.tar.gz`) this
+/// build can't apply itself.
+fn installer_kind(file: &str) -> Option<InstallerKind> {
+  let extension = std::path::Path::new(file)
+    .extension()
+    .map(|ext| ext.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+  match extension.as_str() {
+    "exe" if cfg!(target_os = "windows") => Some(InstallerKind::Nsis),
+    "msi" if cfg!(target_os = "windows") => Some(InstallerKind::Msi),
+    "appimage" if cfg!(target_os = "linux") && std::env::var_os("APPIMAGE").is_some() => {
+      Some(InstallerKind::AppImage)
+    }
+    _ => None,
+  }
+}
+
+fn file_name(url: &str) -> String {
+  let path = url.split(['?', '#']).next().unwrap_or_default();
+  path
+    .rsplit('/')
+    .next()
+    .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+    .unwrap_or("update")
+    .to_string()
+}
+
+fn parse_version(version: &str) -> Result<semver::Version, String> {
+  let version = version.trim();
+  semver::Version::parse(version.strip_prefix('v').unwrap_or(version))
+    .map_err(|e| format!("Invalid version '{}': {}", version, e))
+}
+
+fn current_version(app: &AppHandle) -> semver::Version {
+  app.package_info().version.clone()
+}
+
+fn client(app: &AppHandle) -> Result<reqwest::Client, String> {
+  reqwest::Client::builder()
+    .user_agent(format!("SpectraStudio/{}", app.package_info().version))
+    .connect_timeout(HTTP_TIMEOUT)
+    .read_timeout(HTTP_TIMEOUT)
+    .build()
+    .map_err(|e| e.to_string())
+}
+
+/// GET of `url`, following redirects (release assets redirect to a CDN).
+async fn get(client: &reqwest::Client, url: &str) -> Result<reqwest::Response, String> {
+  let response = client
+    .get(url)
+    .send()
+    .await
+    .map_err(|e| format!("GET {} failed: {}", url, e))?;
+  if !response.status().is_success() {
+    return Err(format!(
+      "GET {} failed with HTTP {}",
+      url,
+      response.status().as_u16()
+    ));
+  }
+  Ok(response)
+}
+
+async fn fetch_manifest(client: &reqwest::Client, url: &str) -> Result<Manifest, String> {
+  let body = get(client, url)
+    .await?
+    .bytes()
+    .await
+    .map_err(|e| e.to_string())?;
+  serde_json::from_slice::<Manifest>(&body)
+    .map_err(|e| format!("Invalid update manifest at {}: {}", url, e))
+}
+
+/// Newest release on `channel`, with its version.
+async fn latest(app: &AppHandle, channel: Channel) -> Result<(semver::Version, Manifest), String> {
+  let client = client(app)?;
+  let mut newest: Option<(semver::Version, Manifest)> = None;
+  let mut last_error = None;
+  for url in channel.manifest_urls() {
+    match fetch_manifest(&client, &url).await {
+      Ok(manifest) => {
+        let version = parse_version(&manifest.version)?;
+        if newest.as_ref().is_none_or(|(v, _)| version > *v) {
+          newest = Some((version, manifest));
+        }
+      }
+      // A channel without a release yet is not an error as long as another one answers
+      Err(e) => {
+        tracing::debug!("No update manifest at {}: {}", url, e);
+        last_error = Some(e);
+      }
+    }
+  }
+  newest.ok_or_else(|| last_error.unwrap_or_default())
+}
+
+async fn channel_setting(state: &AppState) -> Result<Channel, String> {
+  let row: Option<(String,)> = sqlx::query_as("SELECT value FROM app_settings WHERE key = ?")
+    .bind(CHANNEL_KEY)
+    .fetch_optional(&store::pool(state)?)
+    .await
+    .map_err(|e| e.to_string())?;
+  match row {
+    Some((name,)) => Channel::parse(&name),
+    None => Ok(Channel::Stable),
+  }
+}
+
+fn staging_dir(app: &AppHandle) -> Result<PathBuf, String> {
+  app
+    .path()
+    .app_data_dir()
+    .map(|dir| dir.join(STAGING_DIR))
+    .map_err(|e| e.to_string())
+}
+
+fn read_pending(dir: &Path) -> Option<Pending> {
+  let text = std::fs::read_to_string(dir.join(PENDING_FILE)).ok()?;
+  serde_json::from_str(&text).ok()
+}
+
+fn decode_base64(text: &str, what: &str) -> Result<Vec<u8>, String> {
+  base64::engine::general_purpose::STANDARD
+    .decode(text.trim())
+    .map_err(|e| format!("Invalid {}: {}", what, e))
+}
+
+fn parse_public_key(public_key: &str) -> Result<PublicKey, String> {
+  PublicKey::from_base64(public_key.trim()).map_err(|e| format!("Invalid update public key: {}", e))
+}
+
+/// Parses a minisign signature: the `.minisig` text, or it base64-encoded as Tauri's
+/// manifests carry it.
+fn parse_signature(signature: &str) -> Result<Signature, String> {
+  let text = if signature.trim_start().starts_with("untrusted comment:") {
+    signature.to_string()
+  } else {
+    String::from_utf8(decode_base64(signature, "update signature")?)
+      .map_err(|_| "Invalid update signature".to_string())?
+  };
+  Signature::decode(&text).map_err(|e| format!("Invalid update signature: {}", e))
+}
+
+fn signature_error(e: minisign_verify::Error) -> String {
+  match e {
+    minisign_verify::Error::UnexpectedKeyId => "Update was signed with a different key".to_string(),
+    minisign_verify::Error::UnsupportedLegacyMode => {
+      "Legacy update signatures aren't supported; sign without minisign -l".to_string()
+    }
+    minisign_verify::Error::InvalidSignature => {
+      "Update signature does not match the download".to_string()
+    }
+    other => format!("Invalid update signature: {}", other),
+  }
+}
+
+/// Checks the file at `path` against `signature` made with `public_key`, trusted comment
+/// included.
+fn verify_file(public_key: &str, signature: &str, path: &Path) -> Result<(), String> {
+  let key = parse_public_key(public_key)?;
+  let signature = parse_signature(signature)?;
+  let mut verifier = key.verify_stream(&signature).map_err(signature_error)?;
+  let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+  let mut buf = vec![0; READ_CHUNK];
+  loop {
+    let n = file.read(&mut buf).map_err(|e| e.to_string())?;
+    if n == 0 {
+      break;
+    }
+    verifier.update(&buf[..n]);
+  }
+  verifier.finalize().map_err(signature_error)
+}
+
+/// Downloads `asset` into the staging directory, verifying it on the way, and stages it for
+/// the next start.
+async fn download(
+  app: &AppHandle,
+  version: &str,
+  asset: &PlatformAsset,
+  public_key: &str,
+) -> Result<(), String> {
+  let key = parse_public_key(public_key)?;
+  let signature = parse_signature(&asset.signature)?;
+  let mut verifier = key.verify_stream(&signature).map_err(signature_error)?;
+
+  let dir = staging_dir(app)?;
+  // Drop whatever an earlier download left behind
+  let _ = tokio::fs::remove_dir_all(&dir).await;
+  tokio::fs::create_dir_all(&dir)
+    .await
+    .map_err(|e| e.to_string())?;
+  let file = file_name(&asset.url);
+  let partial = dir.join(format!("{}.part", file));
+
+  let mut response = get(&client(app)?, &asset.url).await?;
+  let total = response.content_length();
+  let mut out = tokio::fs::File::create(&partial)
+    .await
+    .map_err(|e| e.to_string())?;
+  let mut downloaded = 0;
+  let mut last_emit = 0;
+  while let Some(piece) = response.chunk().await.map_err(|e| e.to_string())? {
+    verifier.update(&piece);
+    out.write_all(&piece).await.map_err(|e| e.to_string())?;
+    downloaded += piece.len() as u64;
+    // Every megabyte is plenty for a progress bar
+    if downloaded - last_emit >= 1 << 20 || Some(downloaded) == total {
+      last_emit = downloaded;
+      let _ = app.emit(
+        "update-progress",
+        ProgressEvent {
+          version: version.to_string(),
+          downloaded,
+          total,
+        },
+      );
+    }
+  }
+  out.sync_all().await.map_err(|e| e.to_string())?;
+  drop(out);
+  if let Some(total) = total {
+    if downloaded != total {
+      return Err(format!(
+        "Download ended after {} of {} bytes",
+        downloaded, total
+      ));
+    }
+  }
+  verifier.finalize().map_err(signature_error)?;
+
+  tokio::fs::rename(&partial, dir.join(&file))
+    .await
+    .map_err(|e| e.to_string())?;
+  let pending = serde_json::to_string(&Pending {
+    version: version.to_string(),
+    file,
+    signature: asset.signature.clone(),
+  })
+  .map_err(|e| e.to_string())?;
+  tokio::fs::write(dir.join(PENDING_FILE), pending)
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Runs the installer staged by an earlier download, if it's newer than this build, and
+/// exits or restarts into the new version. Called first thing during setup; failures are
+/// logged and the app starts as it is.
+pub fn apply_staged(app: &AppHandle) {
+  let Ok(dir) = staging_dir(app) else {
+    return;
+  };
+  let Some(pending) = read_pending(&dir) else {
+    return;
+  };
+  // Whatever happens, try the installer only once
+  let _ = std::fs::remove_file(dir.join(PENDING_FILE));
+  let newer = parse_version(&pending.version).is_ok_and(|v| v > current_version(app));
+  if !newer {
+    let _ = std::fs::remove_dir_all(&dir);
+    return;
+  }
+  let installer = dir.join(&pending.file);
+  // The staged file may have been replaced since it was downloaded, so check it again
+  let verified = PUBLIC_KEY
+    .ok_or_else(|| "This build can't verify updates".to_string())
+    .and_then(|key| verify_file(key, &pending.signature, &installer));
+  if let Err(e) = verified.and_then(|()| install(app, &installer)) {
+    tracing::error!(
+      "Failed to install update {} from {}: {}",
+      pending.version,
+      installer.display(),
+      e
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+  }
+}
+
+fn install(app: &AppHandle, installer: &Path) -> Result<(), String> {
+  let kind = installer_kind(&installer.to_string_lossy())
+    .ok_or("This platform can't install updates in place")?;
+  tracing::info!("Installing update from {}", installer.display());
+  match kind {
+    InstallerKind::Nsis => {
+      std::process::Command::new(installer)
+        .arg("/S")
+        .spawn()
+        .map_err(|e| e.to_string())?;
+      // The installer replaces the running executable, so get out of its way
+      std::process::exit(0)
+    }
+    InstallerKind::Msi => {
+      std::process::Command::new("msiexec")
+        .arg("/i")
+        .arg(installer)
+        .arg("/passive")
+        .spawn()
+        .map_err(|e| e.to_string())?;
+      std::process::exit(0)
+    }
+    InstallerKind::AppImage => {
+      let target = PathBuf::from(std::env::var_os("APPIMAGE").ok_or("Not run as an AppImage")?);
+      // Copy next to the target first so the swap itself is a rename on one file system
+      let incoming = target.with_extension("update");
+      std::fs::copy(installer, &incoming).map_err(|e| e.to_string())?;
+      #[cfg(unix)]
+      {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&incoming, std::fs::Permissions::from_mode(0o755))
+          .map_err(|e| e.to_string())?;
+      }
+      std::fs::rename(&incoming, &target).map_err(|e| e.to_string())?;
+      if let Some(dir) = installer.parent() {
+        let _ = std::fs::remove_dir_all(dir);
+      }
+      app.restart()
+    }
+  }
+}
+
+#[tauri::command]
+pub async fn get_update_channel(state: State<'_, AppState>) -> Result<Channel, String> {
+  channel_setting(&state).await
+}
+
+#[tauri::command]
+pub async fn set_update_channel(state: State<'_, AppState>, channel: String) -> Result<(), String> {
+  let channel = Channel::parse(&channel)?;
+  sqlx::query(
+    "INSERT INTO app_settings (key, value) VALUES (?, ?) \
+     ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+  )
+  .bind(CHANNEL_KEY)
+  .bind(channel.name())
+  .execute(&store::pool(&state)?)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Looks up the newest release on `channel`, the configured one when not given.
+#[tauri::command]
+pub async fn check_for_updates(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  channel: Option<String>,
+) -> Result<UpdateInfo, String> {
+  let channel = match channel {
+    Some(name) => Channel::parse(&name)?,
+    None => channel_setting(&state).await?,
+  };
+  let (version, manifest) = latest(&app, channel).await?;
+  let current = current_version(&app);
+  let installable = PUBLIC_KEY.is_some()
+    && manifest
+      .platforms
+      .get(&platform_key())
+      .is_some_and(|asset| installer_kind(&file_name(&asset.url)).is_some());
+  let staged_version = staging_dir(&app)
+    .ok()
+    .and_then(|dir| read_pending(&dir))
+    .map(|pending| pending.version);
+  Ok(UpdateInfo {
+    channel,
+    current_version: current.to_string(),
+    latest_version: version.to_string(),
+    available: version > current,
+    notes: manifest.notes,
+    pub_date: manifest.pub_date,
+    installable,
+    staged_version,
+  })
+}
+
+/// Starts downloading the newest release on the configured channel in the background and
+/// returns its version; `update-ready` follows once it's verified and staged.
+#[tauri::command]
+pub async fn download_update(app: AppHandle, state: State<'_, AppState>) -> Result<String, String> {
+  let public_key = PUBLIC_KEY.ok_or("This build can't verify updates; install them manually")?;
+  let channel = channel_setting(&state).await?;
+  let (version, manifest) = latest(&app, channel).await?;
+  if version <= current_version(&app) {
+    return Err(format!("Already on the latest version ({})", version));
+  }
+  let asset = manifest
+    .platforms
+    .get(&platform_key())
+    .cloned()
+    .ok_or_else(|| format!("Release {} has no build for {}", version, platform_key()))?;
+  let version = version.to_string();
+  {
+    let mut updates = state.updates.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(running) = &updates.downloading {
+      return Err(format!("Update {} is already downloading", running));
+    }
+    updates.downloading = Some(version.clone());
+  }
+
+  let job_version = version.clone();
+  tauri::async_runtime::spawn(async move {
+    let result = download(&app, &job_version, &asset, public_key).await;
+    let state = app.state::<AppState>();
+    let version = state
+      .updates
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .downloading
+      .take()
+      .unwrap_or_default();
+    match result {
+      Ok(()) => {
+        tracing::info!("Update {} downloaded and staged", version);
+        let _ = app.emit(
+          "update-ready",
+          ResultEvent {
+            version,
+            error: None,
+          },
+        );
+      }
+      Err(e) => {
+        tracing::warn!("Update {} failed to download: {}", version, e);
+        let _ = app.emit(
+          "update-failed",
+          ResultEvent {
+            version,
+            error: Some(e),
+          },
+        );
+      }
+    }
+  });
+  Ok(version)
+}
+
+/// Restarts the app to install the staged update.
+#[tauri::command]
+pub fn restart_to_update(app: AppHandle) -> Result<(), String> {
+  let dir = staging_dir(&app)?;
+  if read_pending(&dir).is_none() {
+    return Err("No update has been downloaded".to_string());
+  }
+  app.restart()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // minisign's own test key and signatures of the four bytes "test"
+  const KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+  const PREHASHED: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+  const LEGACY: &str = "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==";
+
+  fn staged(name: &str, contents: &[u8]) -> PathBuf {
+    let path =
+      std::env::temp_dir().join(format!("spectra-updater-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+  }
+
+  #[test]
+  fn verifies_staged_installers() {
+    let path = staged("good", b"test");
+    assert_eq!(verify_file(KEY, PREHASHED, &path), Ok(()));
+    // Tauri's manifests carry the signature base64-encoded
+    let encoded = base64::engine::general_purpose::STANDARD.encode(PREHASHED);
+    assert_eq!(verify_file(KEY, &encoded, &path), Ok(()));
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn rejects_replaced_installers_and_legacy_signatures() {
+    let path = staged("bad", b"Test");
+    assert_eq!(
+      verify_file(KEY, PREHASHED, &path),
+      Err("Update signature does not match the download".to_string())
+    );
+    assert_eq!(
+      verify_file(KEY, LEGACY, &path),
+      Err("Legacy update signatures aren't supported; sign without minisign -l".to_string())
+    );
+    std::fs::remove_file(path).unwrap();
+  }
+}
@@ -0,0 +1,364 @@
+//! Per-profile usage tracking (sessions, last connect, most browsed tables), kept in the app
+//! store so the launcher can rank profiles and point out ones whose credentials went stale.
+//!
+//! Also local usage insights: how often each command is invoked, and how often and how fast
+//! features (queries, connects, background tasks) run. Nothing leaves the machine; counts
+//! build up in memory, are added to per-day totals in the app store every minute, and
+//! `get_usage_report` summarizes them without names of connections, tables or statements,
+//! so users can look at their own patterns or attach the report to a bug filing.
+
+use std::collections::HashMap;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::{store, workspaces, AppState};
+
+const TOP_TABLES: i64 = 5;
+const FLUSH_INTERVAL: Duration = Duration::from_mins(1);
+const DEFAULT_REPORT_DAYS: u32 = 30;
+
+const KIND_COMMAND: &str = "command";
+const KIND_FEATURE: &str = "feature";
+
+#[derive(Default, Clone, Copy)]
+pub struct Stat {
+  calls: i64,
+  errors: i64,
+  total_ms: i64,
+  max_ms: i64,
+}
+
+/// Counts not yet written to the store, by kind and name.
+pub type PendingUsage = HashMap<(&'static str, String), Stat>;
+
+fn add(state: &AppState, kind: &'static str, name: &str, elapsed: Option<Duration>, ok: bool) {
+  let mut pending = state.usage.lock().unwrap_or_else(PoisonError::into_inner);
+  let stat = pending.entry((kind, name.to_string())).or_default();
+  stat.calls += 1;
+  if !ok {
+    stat.errors += 1;
+  }
+  if let Some(elapsed) = elapsed {
+    let ms = i64::try_from(elapsed.as_millis()).unwrap_or(i64::MAX);
+    stat.total_ms += ms;
+    stat.max_ms = stat.max_ms.max(ms);
+  }
+}
+
+/// Records a run of `feature` (`query`, `connect.mysql`, `task.export`, ...) that took
+/// `elapsed`.
+pub fn observe(state: &AppState, feature: &str, elapsed: Duration, ok: bool) {
+  add(state, KIND_FEATURE, feature, Some(elapsed), ok);
+}
+
+/// Wraps the app's invoke handler to count each command invocation. Async commands answer
+/// after the handler returns, so only the count is kept, not a latency.
+pub fn counted<R: Runtime>(
+  handler: impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static {
+  move |invoke| {
+    let webview = invoke.message.webview_ref();
+    add(
+      &webview.state::<AppState>(),
+      KIND_COMMAND,
+      invoke.message.command(),
+      None,
+      true,
+    );
+    handler(invoke)
+  }
+}
+
+/// Adds the pending counts to today's totals in the store.
+async fn flush(state: &AppState) -> Result<(), String> {
+  let pending = std::mem::take(&mut *state.usage.lock().unwrap_or_else(PoisonError::into_inner));
+  if pending.is_empty() {
+    return Ok(());
+  }
+  let pool = store::pool(state)?;
+  let day = chrono::Utc::now().format("%Y-%m-%d").to_string();
+  let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+  for ((kind, name), stat) in pending {
+    sqlx::query(
+      "INSERT INTO usage_daily (day, kind, name, calls, errors, total_ms, max_ms) \
+       VALUES (?, ?, ?, ?, ?, ?, ?) \
+       ON CONFLICT(day, kind, name) DO UPDATE SET calls = calls + excluded.calls, \
+       errors = errors + excluded.errors, total_ms = total_ms + excluded.total_ms, \
+       max_ms = MAX(max_ms, excluded.max_ms)",
+    )
+    .bind(&day)
+    .bind(kind)
+    .bind(name)
+    .bind(stat.calls)
+    .bind(stat.errors)
+    .bind(stat.total_ms)
+    .bind(stat.max_ms)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+  }
+  tx.commit().await.map_err(|e| e.to_string())
+}
+
+/// Writes pending counts to the store every [`FLUSH_INTERVAL`]; spawned during setup.
+pub async fn flush_periodically(app: AppHandle) {
+  let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+  loop {
+    interval.tick().await;
+    if let Err(e) = flush(&app.state::<AppState>()).await {
+      tracing::warn!("Failed to store usage counts: {}", e);
+    }
+  }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageEntry {
+  pub name: String,
+  pub calls: i64,
+  pub errors: i64,
+  /// Mean and worst latency; `None` for commands, which are only counted.
+  pub avg_ms: Option<f64>,
+  pub max_ms: Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+  pub app_version: String,
+  pub os: String,
+  pub arch: String,
+  /// First day (UTC, `YYYY-MM-DD`) covered.
+  pub since: String,
+  pub days: u32,
+  pub commands: Vec<UsageEntry>,
+  pub features: Vec<UsageEntry>,
+}
+
+/// Summary of the last `days` days of usage (30 by default), most used first.
+#[tauri::command]
+pub async fn get_usage_report(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  days: Option<u32>,
+) -> Result<UsageReport, String> {
+  flush(&state).await?;
+  let days = days.unwrap_or(DEFAULT_REPORT_DAYS).max(1);
+  let since = (chrono::Utc::now() - chrono::Duration::days(i64::from(days) - 1))
+    .format("%Y-%m-%d")
+    .to_string();
+  let rows: Vec<(String, String, i64, i64, i64, i64)> = sqlx::query_as(
+    "SELECT kind, name, SUM(calls), SUM(errors), SUM(total_ms), MAX(max_ms) FROM usage_daily \
+     WHERE day >= ? GROUP BY kind, name ORDER BY SUM(calls) DESC, name",
+  )
+  .bind(&since)
+  .fetch_all(&store::pool(&state)?)
+  .await
+  .map_err(|e| e.to_string())?;
+
+  let mut report = UsageReport {
+    app_version: app.package_info().version.to_string(),
+    os: std::env::consts::OS.to_string(),
+    arch: std::env::consts::ARCH.to_string(),
+    since,
+    days,
+    commands: Vec::new(),
+    features: Vec::new(),
+  };
+  for (kind, name, calls, errors, total_ms, max_ms) in rows {
+    let timed = kind == KIND_FEATURE && calls > 0;
+    #[allow(clippy::cast_precision_loss)]
+    let entry = UsageEntry {
+      name,
+      calls,
+      errors,
+      avg_ms: timed.then(|| total_ms as f64 / calls as f64),
+      max_ms: timed.then_some(max_ms),
+    };
+    if kind == KIND_COMMAND {
+      report.commands.push(entry);
+    } else {
+      report.features.push(entry);
+    }
+  }
+  Ok(report)
+}
+
+/// Deletes all recorded usage: the insights as well as the per-profile stats.
+#[tauri::command]
+pub async fn purge_usage_data(state: State<'_, AppState>) -> Result<(), String> {
+  state
+    .usage
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .clear();
+  let pool = store::pool(&state)?;
+  for table in ["usage_daily", "profile_usage", "profile_table_usage"] {
+    sqlx::query(&format!("DELETE FROM {}", table))
+      .execute(&pool)
+      .await
+      .map_err(|e| e.to_string())?;
+  }
+  Ok(())
+}
+
+/// Connect errors that mean the saved credentials no longer work, as opposed to the server
+/// being unreachable.
+fn is_auth_error(error: &str) -> bool {
+  let lower = error.to_lowercase();
+  [
+    "access denied",
+    "authentication failed",
+    "password",
+    "auth error",
+    "noauth",
+    "wrongpass",
+  ]
+  .iter()
+  .any(|needle| lower.contains(needle))
+}
+
+/// Records a connect attempt for a profile. Failures to record are logged, never returned.
+pub async fn record_connect(state: &AppState, profile_id: &str, outcome: &Result<String, String>) {
+  let Ok(pool) = store::pool(state) else {
+    return;
+  };
+  let now = store::now_ms();
+  let query = match outcome {
+    Ok(_) => sqlx::query(
+      "INSERT INTO profile_usage (profile_id, sessions, last_connected_at) VALUES (?, 1, ?) \
+       ON CONFLICT(profile_id) DO UPDATE SET sessions = sessions + 1, \
+       last_connected_at = excluded.last_connected_at",
+    )
+    .bind(profile_id)
+    .bind(now),
+    Err(e) => sqlx::query(
+      "INSERT INTO profile_usage (profile_id, sessions, last_error, last_error_at) \
+       VALUES (?, 0, ?, ?) \
+       ON CONFLICT(profile_id) DO UPDATE SET last_error = excluded.last_error, \
+       last_error_at = excluded.last_error_at",
+    )
+    .bind(profile_id)
+    .bind(e)
+    .bind(now),
+  };
+  if let Err(e) = query.execute(&pool).await {
+    tracing::warn!("Failed to record profile usage: {}", e);
+  }
+}
+
+/// Counts a table being browsed over `connection_id`. Only connections opened from a profile
+/// (whose connection id is the profile id) are tracked; recording happens in the background.
+pub fn record_table(state: &AppState, connection_id: Option<&str>, table: &str) {
+  let (Some(profile_id), Ok(pool)) = (connection_id, store::pool(state)) else {
+    return;
+  };
+  let (profile_id, table) = (profile_id.to_string(), table.to_string());
+  tauri::async_runtime::spawn(async move {
+    let recorded = sqlx::query(
+      "INSERT INTO profile_table_usage (profile_id, table_name, queries, last_used_at) \
+       SELECT ?1, ?2, 1, ?3 WHERE EXISTS (SELECT 1 FROM connection_profiles WHERE id = ?1) \
+       ON CONFLICT(profile_id, table_name) DO UPDATE SET queries = queries + 1, \
+       last_used_at = excluded.last_used_at",
+    )
+    .bind(profile_id)
+    .bind(table)
+    .bind(store::now_ms())
+    .execute(&pool)
+    .await;
+    if let Err(e) = recorded {
+      tracing::warn!("Failed to record table usage: {}", e);
+    }
+  });
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableUsage {
+  pub table: String,
+  pub queries: i64,
+  pub last_used_at: i64,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileStats {
+  pub profile_id: String,
+  pub name: String,
+  pub sessions: i64,
+  pub last_connected_at: Option<i64>,
+  pub last_error: Option<String>,
+  pub last_error_at: Option<i64>,
+  /// The latest connect attempt failed with what looks like an authentication error.
+  pub stale_credentials: bool,
+  pub top_tables: Vec<TableUsage>,
+}
+
+/// `profile_id`, `name`, `sessions`, `last_connected_at`, `last_error`, `last_error_at`
+type StatsRow = (
+  String,
+  String,
+  Option<i64>,
+  Option<i64>,
+  Option<String>,
+  Option<i64>,
+);
+
+/// Usage for the active workspace's profiles (or just `profile_id`), most recently used first.
+#[tauri::command]
+pub async fn get_profile_stats(
+  state: State<'_, AppState>,
+  profile_id: Option<String>,
+) -> Result<Vec<ProfileStats>, String> {
+  let pool = store::pool(&state)?;
+  let rows: Vec<StatsRow> = sqlx::query_as(
+    "SELECT p.id, p.name, u.sessions, u.last_connected_at, u.last_error, u.last_error_at \
+     FROM connection_profiles p LEFT JOIN profile_usage u ON u.profile_id = p.id \
+     WHERE p.workspace = ?1 AND (?2 IS NULL OR p.id = ?2) \
+     ORDER BY COALESCE(u.last_connected_at, 0) DESC, p.name COLLATE NOCASE",
+  )
+  .bind(workspaces::current(&state))
+  .bind(profile_id)
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+
+  let mut profiles = Vec::with_capacity(rows.len());
+  for (profile_id, name, sessions, last_connected_at, last_error, last_error_at) in rows {
+    let top_tables: Vec<(String, i64, i64)> = sqlx::query_as(
+      "SELECT table_name, queries, last_used_at FROM profile_table_usage \
+       WHERE profile_id = ? ORDER BY queries DESC, last_used_at DESC LIMIT ?",
+    )
+    .bind(&profile_id)
+    .bind(TOP_TABLES)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let stale_credentials = match (&last_error, last_error_at) {
+      (Some(error), Some(failed_at)) => {
+        failed_at > last_connected_at.unwrap_or(0) && is_auth_error(error)
+      }
+      _ => false,
+    };
+    profiles.push(ProfileStats {
+      profile_id,
+      name,
+      sessions: sessions.unwrap_or(0),
+      last_connected_at,
+      last_error,
+      last_error_at,
+      stale_credentials,
+      top_tables: top_tables
+        .into_iter()
+        .map(|(table, queries, last_used_at)| TableUsage {
+          table,
+          queries,
+          last_used_at,
+        })
+        .collect(),
+    });
+  }
+  Ok(profiles)
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::PoisonError;
+
+use tauri::State;
+
+use crate::{store, workspaces, AppState};
+
+/// How bound parameters are spelled in the target dialect.
+#[derive(Clone, Copy)]
+pub enum Placeholder {
+  /// `?` (MySQL), where a backslash escapes the next character of a string literal
+  Question,
+  /// `?` (SQLite), whose string literals have no backslash escapes
+  SqliteQuestion,
+  /// `$1`, `$2`, ... (Postgres)
+  Dollar,
+}
+
+/// Replaces every `${NAME}` outside of string literals, comments and (Postgres) dollar-quoted
+/// bodies with a bound parameter placeholder, returning the rewritten SQL and the values to
+/// bind in order.
+///
+/// Values are always bound as text, so Postgres callers may need an explicit cast
+/// (`${TENANT_ID}::int`) when comparing against non-text columns.
+pub fn substitute(
+  sql: &str,
+  vars: &HashMap<String, String>,
+  placeholder: Placeholder,
+) -> Result<(String, Vec<String>), String> {
+  let postgres = matches!(placeholder, Placeholder::Dollar);
+  let backslash_escapes = matches!(placeholder, Placeholder::Question);
+  let mut out = String::with_capacity(sql.len());
+  let mut binds = Vec::new();
+  let mut rest = sql;
+
+  while let Some(c) = rest.chars().next() {
+    if rest.starts_with("${") {
+      let end = rest
+        .find('}')
+        .ok_or_else(|| format!("Unterminated variable placeholder: {}", rest))?;
+      let name = rest[2..end].trim();
+      let value = vars
+        .get(name)
+        .ok_or_else(|| format!("Undefined variable: {}", name))?;
+      binds.push(value.clone());
+      match placeholder {
+        Placeholder::Question | Placeholder::SqliteQuestion => out.push('?'),
+        Placeholder::Dollar => {
+          let _ = write!(out, "${}", binds.len());
+        }
+      }
+      rest = &rest[end + 1..];
+      continue;
+    }
+
+    // Literals and comments are copied through untouched
+    let len = match c {
+      '\'' | '"' => quoted_len(rest, backslash_escapes),
+      '`' => quoted_len(rest, false),
+      '-' if rest.starts_with("--") => rest.find('\n').map_or(rest.len(), |end| end + 1),
+      '/' if rest.starts_with("/*") => block_comment_len(rest, postgres),
+      // `$` inside an identifier (`a$b`) doesn't open a quote
+      '$' if postgres && !out.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$') => {
+        dollar_quoted_len(rest).unwrap_or(1)
+      }
+      _ => c.len_utf8(),
+    };
+    out.push_str(&rest[..len]);
+    rest = &rest[len..];
+  }
+
+  Ok((out, binds))
+}
+
+/// Length of the quoted literal or identifier `sql` starts with, up to the next unescaped
+/// copy of its opening quote. A doubled quote simply reads as two literals in a row.
+fn quoted_len(sql: &str, backslash_escapes: bool) -> usize {
+  let bytes = sql.as_bytes();
+  let mut i = 1;
+  while i < bytes.len() {
+    if bytes[i] == b'\\' && backslash_escapes {
+      i += 2;
+    } else if bytes[i] == bytes[0] {
+      return i + 1;
+    } else {
+      i += 1;
+    }
+  }
+  sql.len()
+}
+
+/// Length of the `/* */` comment `sql` starts with; Postgres comments nest, MySQL and SQLite
+/// ones end at the first `*/`.
+fn block_comment_len(sql: &str, nested: bool) -> usize {
+  let bytes = sql.as_bytes();
+  let mut depth = 0;
+  let mut i = 0;
+  while i < bytes.len() {
+    if bytes[i..].starts_with(b"/*") && (nested || depth == 0) {
+      depth += 1;
+      i += 2;
+    } else if bytes[i..].starts_with(b"*/") {
+      depth -= 1;
+      i += 2;
+      if depth == 0 {
+        return i;
+      }
+    } else {
+      i += 1;
+    }
+  }
+  sql.len()
+}
+
+/// Length of the dollar-quoted string (`$$...$$` or `$tag$...$tag$`) `sql` starts with, or
+/// `None` when the `$` opens none, e.g. a `$1` parameter.
+fn dollar_quoted_len(sql: &str) -> Option<usize> {
+  let tag_len = sql[1..].find(|c: char| !(c.is_alphanumeric() || c == '_'))? + 2;
+  let tag = &sql[..tag_len];
+  if !tag.ends_with('$') || tag[1..].starts_with(|c: char| c.is_ascii_digit()) {
+    return None;
+  }
+  Some(
+    sql[tag_len..]
+      .find(tag)
+      .map_or(sql.len(), |end| tag_len + end + tag_len),
+  )
+}
+
+/// Resolves `${VAR}` placeholders in `sql` against the given workspace's variables (the
+/// active workspace when `None`).
+pub fn resolve(
+  state: &AppState,
+  workspace: Option<&str>,
+  sql: &str,
+  placeholder: Placeholder,
+) -> Result<(String, Vec<String>), String> {
+  if !sql.contains("${") {
+    return Ok((sql.to_string(), Vec::new()));
+  }
+  let workspace = workspace.map_or_else(|| workspaces::current(state), str::to_string);
+  let guard = state
+    .variables
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let empty = HashMap::new();
+  let vars = guard.get(&workspace).unwrap_or(&empty);
+  substitute(sql, vars, placeholder)
+}
+
+/// Loads the variables of every workspace from the app store.
+pub async fn load(state: &AppState) -> Result<(), String> {
+  let pool = store::pool(state)?;
+  let rows: Vec<(String, String, String)> =
+    sqlx::query_as("SELECT workspace, name, value FROM variables")
+      .fetch_all(&pool)
+      .await
+      .map_err(|e| e.to_string())?;
+  let mut variables = state
+    .variables
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  for (workspace, name, value) in rows {
+    variables.entry(workspace).or_default().insert(name, value);
+  }
+  Ok(())
+}
+
+fn is_valid_name(name: &str) -> bool {
+  !name.is_empty()
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+#[tauri::command]
+pub async fn set_variable(
+  state: State<'_, AppState>,
+  workspace: Option<String>,
+  name: String,
+  value: String,
+) -> Result<(), String> {
+  if !is_valid_name(&name) {
+    return Err(format!("Invalid variable name: {}", name));
+  }
+  let workspace = workspace.unwrap_or_else(|| workspaces::current(&state));
+  let pool = store::pool(&state)?;
+  sqlx::query(
+    "INSERT INTO variables (workspace, name, value) VALUES (?, ?, ?) \
+     ON CONFLICT(workspace, name) DO UPDATE SET value = excluded.value",
+  )
+  .bind(&workspace)
+  .bind(&name)
+  .bind(&value)
+  .execute(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  state
+    .variables
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .entry(workspace)
+    .or_default()
+    .insert(name, value);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn list_variables(
+  state: State<'_, AppState>,
+  workspace: Option<String>,
+) -> Vec<(String, String)> {
+  let workspace = workspace.unwrap_or_else(|| workspaces::current(&state));
+  let guard = state
+    .variables
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let mut vars: Vec<(String, String)> = guard
+    .get(&workspace)
+    .map(|vars| vars.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    .unwrap_or_default();
+  vars.sort();
+  vars
+}
+
+#[tauri::command]
+pub async fn delete_variable(
+  state: State<'_, AppState>,
+  workspace: Option<String>,
+  name: String,
+) -> Result<(), String> {
+  let workspace = workspace.unwrap_or_else(|| workspaces::current(&state));
+  let pool = store::pool(&state)?;
+  sqlx::query("DELETE FROM variables WHERE workspace = ? AND name = ?")
+    .bind(&workspace)
+    .bind(&name)
+    .execute(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  if let Some(vars) = state
+    .variables
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .get_mut(&workspace)
+  {
+    vars.remove(&name);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn vars() -> HashMap<String, String> {
+    HashMap::from([("ID".to_string(), "7".to_string())])
+  }
+
+  fn pg(sql: &str) -> (String, Vec<String>) {
+    substitute(sql, &vars(), Placeholder::Dollar).unwrap()
+  }
+
+  fn mysql(sql: &str) -> (String, Vec<String>) {
+    substitute(sql, &vars(), Placeholder::Question).unwrap()
+  }
+
+  #[test]
+  fn binds_variables_in_order() {
+    assert_eq!(
+      pg("SELECT ${ID}, ${ ID }"),
+      ("SELECT $1, $2".to_string(), vec!["7".to_string(); 2])
+    );
+    assert_eq!(mysql("SELECT ${ID}").0, "SELECT ?");
+    assert!(substitute("SELECT ${NOPE}", &vars(), Placeholder::Question).is_err());
+    assert!(substitute("SELECT ${ID", &vars(), Placeholder::Question).is_err());
+  }
+
+  #[test]
+  fn literals_and_comments_are_left_alone() {
+    for sql in [
+      "SELECT '${ID}'",
+      "SELECT \"${ID}\"",
+      "SELECT `${ID}`",
+      "SELECT 1 -- ${ID}",
+      "SELECT 1 /* ${ID} */",
+      "SELECT 'it''s ${ID}'",
+    ] {
+      assert_eq!(mysql(sql), (sql.to_string(), Vec::new()));
+    }
+    assert_eq!(
+      mysql("SELECT /* x */ ${ID} -- y\n, ${ID}").0,
+      "SELECT /* x */ ? -- y\n, ?"
+    );
+  }
+
+  #[test]
+  fn only_postgres_block_comments_nest() {
+    assert_eq!(
+      pg("/* a /* ${ID} */ ${ID} */ ${ID}").0,
+      "/* a /* ${ID} */ ${ID} */ $1"
+    );
+    assert_eq!(mysql("/* a /* ${ID} */ ${ID}").0, "/* a /* ${ID} */ ?");
+  }
+
+  #[test]
+  fn postgres_dollar_quoted_bodies_are_left_alone() {
+    let sql = "DO $$ BEGIN PERFORM '${ID}'; END $$; SELECT ${ID}";
+    assert_eq!(pg(sql).0, "DO $$ BEGIN PERFORM '${ID}'; END $$; SELECT $1");
+    let sql =
+      "CREATE FUNCTION f() RETURNS int AS $fn$ SELECT ${ID} $$ $fn$ LANGUAGE sql; SELECT ${ID}";
+    assert_eq!(
+      pg(sql).0,
+      "CREATE FUNCTION f() RETURNS int AS $fn$ SELECT ${ID} $$ $fn$ LANGUAGE sql; SELECT $1"
+    );
+    // Parameters and `$` inside identifiers open no quote
+    assert_eq!(
+      pg("PREPARE q AS SELECT a$b$ FROM t WHERE x = $1; SELECT ${ID}").0,
+      "PREPARE q AS SELECT a$b$ FROM t WHERE x = $1; SELECT $1"
+    );
+    // MySQL has no dollar quoting
+    assert_eq!(mysql("SELECT $$ ${ID} $$").0, "SELECT $$ ? $$");
+  }
+
+  #[tokio::test]
+  async fn variables_load_from_the_store() {
+    let path = std::env::temp_dir().join(format!("spectra-variables-{}.db", std::process::id()));
+    let pool = store::open(&path).await.unwrap();
+    sqlx::query("INSERT INTO variables (workspace, name, value) VALUES ('client', 'ID', '7')")
+      .execute(&pool)
+      .await
+      .unwrap();
+    let state = AppState::new();
+    *state.store.lock().unwrap_or_else(PoisonError::into_inner) = Some(pool.clone());
+    load(&state).await.unwrap();
+    pool.close().await;
+    let _ = std::fs::remove_file(&path);
+    let resolved = resolve(
+      &state,
+      Some("client"),
+      "SELECT ${ID}",
+      Placeholder::Question,
+    );
+    assert_eq!(
+      resolved.unwrap(),
+      ("SELECT ?".to_string(), vec!["7".to_string()])
+    );
+  }
+
+  #[test]
+  fn mysql_backslash_escapes_stay_inside_literals() {
+    for sql in ["SELECT 'it\\'s ${ID}'", "SELECT \"say \\\"${ID}\\\"\""] {
+      assert_eq!(mysql(sql), (sql.to_string(), Vec::new()));
+    }
+    assert_eq!(mysql("SELECT 'C:\\\\', ${ID}").0, "SELECT 'C:\\\\', ?");
+    // SQLite and Postgres literals end at the quote after a backslash
+    let sqlite = substitute("SELECT 'C:\\', ${ID}", &vars(), Placeholder::SqliteQuestion);
+    assert_eq!(sqlite.unwrap().0, "SELECT 'C:\\', ?");
+    assert_eq!(pg("SELECT 'C:\\', ${ID}").0, "SELECT 'C:\\', $1");
+  }
+}
@@ -0,0 +1,241 @@
+//! Reading and redefining database views (not the grid views of [`crate::views`]). A view is
+//! edited as its `SELECT`: [`create_or_replace_view`] wraps it in the engine's statement,
+//! `CREATE OR REPLACE VIEW` where there is one and `DROP` + `CREATE` in one transaction on
+//! SQLite.
+
+use sqlx::Row;
+use tauri::State;
+
+use crate::db::SqlPool;
+use crate::{audit, connections, ident, readonly, script, AppState};
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewDefinition {
+  pub name: String,
+  /// The view's `SELECT`.
+  pub definition: String,
+  /// The statement that creates the view as it is now.
+  pub sql: String,
+}
+
+/// The query of a SQLite `CREATE VIEW` statement: what follows its first `AS` outside quotes
+/// and the column list, as written.
+fn sqlite_view_query(sql: &str) -> Option<&str> {
+  let mut quote = None;
+  let mut depth = 0;
+  let mut word_start = None;
+  for (i, c) in sql.char_indices().chain([(sql.len(), ' ')]) {
+    if let Some(close) = quote {
+      if c == close {
+        quote = None;
+      }
+      continue;
+    }
+    if c.is_alphanumeric() || c == '_' {
+      word_start.get_or_insert(i);
+      continue;
+    }
+    if let Some(start) = word_start.take() {
+      if depth == 0 && sql[start..i].eq_ignore_ascii_case("as") {
+        return Some(sql[i..].trim());
+      }
+    }
+    match c {
+      '\'' | '"' | '`' => quote = Some(c),
+      '[' => quote = Some(']'),
+      '(' => depth += 1,
+      ')' => depth -= 1,
+      _ => {}
+    }
+  }
+  None
+}
+
+async fn view_definition(pool: &SqlPool, view: &str) -> Result<ViewDefinition, String> {
+  let name = pool.resolve_table(view).await?;
+  let not_a_view = || format!("{} is not a view", name);
+  let (definition, sql) = match pool {
+    SqlPool::MySql(mysql) => {
+      let row = sqlx::query(&format!("SHOW CREATE VIEW {}", pool.quote_ident(&name)))
+        .fetch_one(mysql)
+        .await
+        .map_err(|e| e.to_string())?;
+      let sql: String = row.try_get(1).map_err(|e| e.to_string())?;
+      let definition: (String,) = sqlx::query_as(
+        "SELECT CAST(VIEW_DEFINITION AS CHAR) FROM information_schema.VIEWS \
+         WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+      )
+      .bind(&name)
+      .fetch_one(mysql)
+      .await
+      .map_err(|e| e.to_string())?;
+      (definition.0, sql)
+    }
+    SqlPool::Postgres(pg) => {
+      let definition: (Option<String>,) = sqlx::query_as(
+        "SELECT pg_get_viewdef(c.oid, true) FROM pg_class c \
+         WHERE c.relnamespace = 'public'::regnamespace AND c.relname = $1 \
+         AND c.relkind = 'v'",
+      )
+      .bind(&name)
+      .fetch_optional(pg)
+      .await
+      .map_err(|e| e.to_string())?
+      .ok_or_else(not_a_view)?;
+      let definition = definition.0.ok_or_else(not_a_view)?;
+      let sql = format!(
+        "CREATE OR REPLACE VIEW {} AS\n{}",
+        pool.table_ref(&name),
+        definition
+      );
+      (definition, sql)
+    }
+    SqlPool::Sqlite(sqlite) => {
+      let sql: (String,) =
+        sqlx::query_as("SELECT sql FROM sqlite_master WHERE type = 'view' AND name = ?")
+          .bind(&name)
+          .fetch_optional(sqlite)
+          .await
+          .map_err(|e| e.to_string())?
+          .ok_or_else(not_a_view)?;
+      let definition = sqlite_view_query(&sql.0)
+        .ok_or_else(|| format!("Cannot find the query of view {}", name))?
+        .to_string();
+      (definition, sql.0)
+    }
+  };
+  Ok(ViewDefinition {
+    name,
+    definition: definition.trim().to_string(),
+    sql,
+  })
+}
+
+/// Whether a PostgreSQL error is the one `CREATE OR REPLACE VIEW` gives when the new query
+/// drops, renames or retypes a column of the view.
+fn needs_recreate(error: &sqlx::Error) -> bool {
+  error
+    .as_database_error()
+    .and_then(sqlx::error::DatabaseError::code)
+    .is_some_and(|code| code == "42P16")
+}
+
+/// Creates view `name` as `query`, or redefines it when it exists. Returns the statements
+/// that ran.
+async fn create_or_replace_view(
+  state: &AppState,
+  pool: SqlPool,
+  connection: &str,
+  name: String,
+  query: String,
+) -> Result<Vec<String>, String> {
+  readonly::ensure_writable(state, connection)?;
+  let dialect = pool.dialect();
+  ident::validate(dialect, &name)?;
+  let query = match script::split(dialect, &query).as_slice() {
+    [statement] => statement.sql.trim().to_string(),
+    [] => return Err("The view has no query".to_string()),
+    _ => return Err("A view is defined by a single query".to_string()),
+  };
+  let view = pool.table_ref(&name);
+  let replace = format!("CREATE OR REPLACE VIEW {} AS\n{}", view, query);
+  let create = format!("CREATE VIEW {} AS\n{}", view, query);
+  let statements = match &pool {
+    SqlPool::Postgres(pg) => match sqlx::query(&replace).execute(pg).await {
+      Ok(_) => vec![replace],
+      // Postgres only replaces a view whose columns stay the same; otherwise drop it first
+      Err(e) if needs_recreate(&e) => {
+        let statements = vec![format!("DROP VIEW {}", view), create];
+        pool.execute_in_transaction(&statements).await?;
+        statements
+      }
+      Err(e) => return Err(e.to_string()),
+    },
+    SqlPool::MySql(_) => {
+      let statements = vec![replace];
+      pool.execute_in_transaction(&statements).await?;
+      statements
+    }
+    SqlPool::Sqlite(_) => {
+      let statements = vec![format!("DROP VIEW IF EXISTS {}", view), create];
+      pool.execute_in_transaction(&statements).await?;
+      statements
+    }
+  };
+  audit::record(
+    state,
+    connection,
+    &statements.join(";\n"),
+    serde_json::json!([]),
+    None,
+  )
+  .await;
+  Ok(statements)
+}
+
+#[tauri::command]
+pub async fn mysql_get_view_definition(
+  state: State<'_, AppState>,
+  view: String,
+  connection_id: Option<String>,
+) -> Result<ViewDefinition, String> {
+  let pool = SqlPool::MySql(connections::mysql(&state, connection_id.as_deref())?);
+  view_definition(&pool, &view).await
+}
+
+#[tauri::command]
+pub async fn postgres_get_view_definition(
+  state: State<'_, AppState>,
+  view: String,
+  connection_id: Option<String>,
+) -> Result<ViewDefinition, String> {
+  let pool = SqlPool::Postgres(connections::postgres(&state, connection_id.as_deref())?);
+  view_definition(&pool, &view).await
+}
+
+#[tauri::command]
+pub async fn sqlite_get_view_definition(
+  state: State<'_, AppState>,
+  view: String,
+  connection_id: Option<String>,
+) -> Result<ViewDefinition, String> {
+  let pool = SqlPool::Sqlite(connections::sqlite(&state, connection_id.as_deref())?);
+  view_definition(&pool, &view).await
+}
+
+#[tauri::command]
+pub async fn mysql_create_or_replace_view(
+  state: State<'_, AppState>,
+  name: String,
+  sql: String,
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = SqlPool::MySql(connections::mysql(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("mysql");
+  create_or_replace_view(&state, pool, connection, name, sql).await
+}
+
+#[tauri::command]
+pub async fn postgres_create_or_replace_view(
+  state: State<'_, AppState>,
+  name: String,
+  sql: String,
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = SqlPool::Postgres(connections::postgres(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("postgres");
+  create_or_replace_view(&state, pool, connection, name, sql).await
+}
+
+#[tauri::command]
+pub async fn sqlite_create_or_replace_view(
+  state: State<'_, AppState>,
+  name: String,
+  sql: String,
+  connection_id: Option<String>,
+) -> Result<Vec<String>, String> {
+  let pool = SqlPool::Sqlite(connections::sqlite(&state, connection_id.as_deref())?);
+  let connection = connection_id.as_deref().unwrap_or("sqlite");
+  create_or_replace_view(&state, pool, connection, name, sql).await
+}
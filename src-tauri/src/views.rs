@@ -0,0 +1,232 @@
+//! Named grid views per table: which columns show and in what order and width, plus the
+//! filters and sort applied. Views are kept per workspace in the app store; the one applied
+//! last to a table is remembered so the grid reopens the way it was left.
+
+use tauri::State;
+
+use crate::{store, workspaces, AppState};
+
+const EXPORT_FORMAT: &str = "spectra-views";
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewColumn {
+  pub name: String,
+  #[serde(default = "visible_by_default")]
+  pub visible: bool,
+  /// Width in pixels; the grid's default when not set.
+  #[serde(default)]
+  pub width: Option<u32>,
+}
+
+fn visible_by_default() -> bool {
+  true
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewFilter {
+  pub column: String,
+  /// Comparison as the grid names it (`=`, `contains`, `isNull`, ...).
+  pub operator: String,
+  #[serde(default)]
+  pub value: Option<String>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewSort {
+  pub column: String,
+  #[serde(default)]
+  pub descending: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GridView {
+  pub table: String,
+  pub name: String,
+  /// Columns in display order.
+  #[serde(default)]
+  pub columns: Vec<ViewColumn>,
+  #[serde(default)]
+  pub filters: Vec<ViewFilter>,
+  /// Sort keys, most significant first.
+  #[serde(default)]
+  pub sort: Vec<ViewSort>,
+  /// Whether this is the view last applied to its table.
+  #[serde(default, skip_deserializing)]
+  pub applied: bool,
+  #[serde(default)]
+  pub updated_at: i64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ViewFile {
+  format: String,
+  version: u32,
+  views: Vec<GridView>,
+}
+
+async fn store_view(state: &AppState, view: GridView) -> Result<GridView, String> {
+  if view.name.trim().is_empty() {
+    return Err("View name cannot be empty".to_string());
+  }
+  if view.table.trim().is_empty() {
+    return Err("A view needs the table it belongs to".to_string());
+  }
+  let mut view = view;
+  view.name = view.name.trim().to_string();
+  view.applied = false;
+  view.updated_at = store::now_ms();
+
+  let pool = store::pool(state)?;
+  let config = serde_json::to_string(&view).map_err(|e| e.to_string())?;
+  sqlx::query(
+    "INSERT INTO grid_views (workspace, table_name, name, config, updated_at) \
+     VALUES (?, ?, ?, ?, ?) \
+     ON CONFLICT(workspace, table_name, name) DO UPDATE SET config = excluded.config, \
+     updated_at = excluded.updated_at",
+  )
+  .bind(workspaces::current(state))
+  .bind(&view.table)
+  .bind(&view.name)
+  .bind(config)
+  .bind(view.updated_at)
+  .execute(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  Ok(view)
+}
+
+/// Views of the active workspace, for `table` when given, with the last applied view of
+/// each table marked.
+async fn load_views(state: &AppState, table: Option<&str>) -> Result<Vec<GridView>, String> {
+  let pool = store::pool(state)?;
+  let rows: Vec<(String, bool)> = sqlx::query_as(
+    "SELECT config, applied_at IS NOT NULL AND applied_at = \
+       (SELECT MAX(applied_at) FROM grid_views o \
+        WHERE o.workspace = v.workspace AND o.table_name = v.table_name) \
+     FROM grid_views v WHERE workspace = ? AND (? IS NULL OR table_name = ?) \
+     ORDER BY table_name, name COLLATE NOCASE",
+  )
+  .bind(workspaces::current(state))
+  .bind(table)
+  .bind(table)
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  rows
+    .into_iter()
+    .map(|(config, applied)| {
+      let mut view: GridView =
+        serde_json::from_str(&config).map_err(|e| format!("Corrupt grid view: {}", e))?;
+      view.applied = applied;
+      Ok(view)
+    })
+    .collect()
+}
+
+/// Saves (or replaces) a view under its table and name and returns it as stored.
+#[tauri::command]
+pub async fn save_view(state: State<'_, AppState>, view: GridView) -> Result<GridView, String> {
+  store_view(&state, view).await
+}
+
+/// Views saved for `table` in the active workspace.
+#[tauri::command]
+pub async fn list_views(
+  state: State<'_, AppState>,
+  table: String,
+) -> Result<Vec<GridView>, String> {
+  load_views(&state, Some(&table)).await
+}
+
+/// Marks view `name` as the one applied to `table`, so it is restored next time the table
+/// opens, and returns it.
+#[tauri::command]
+pub async fn apply_view(
+  state: State<'_, AppState>,
+  table: String,
+  name: String,
+) -> Result<GridView, String> {
+  let pool = store::pool(&state)?;
+  let result = sqlx::query(
+    "UPDATE grid_views SET applied_at = ? WHERE workspace = ? AND table_name = ? AND name = ?",
+  )
+  .bind(store::now_ms())
+  .bind(workspaces::current(&state))
+  .bind(&table)
+  .bind(&name)
+  .execute(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  if result.rows_affected() == 0 {
+    return Err(format!("Unknown view '{}' for {}", name, table));
+  }
+  load_views(&state, Some(&table))
+    .await?
+    .into_iter()
+    .find(|v| v.name == name)
+    .ok_or_else(|| format!("Unknown view '{}' for {}", name, table))
+}
+
+#[tauri::command]
+pub async fn delete_view(
+  state: State<'_, AppState>,
+  table: String,
+  name: String,
+) -> Result<(), String> {
+  let pool = store::pool(&state)?;
+  sqlx::query("DELETE FROM grid_views WHERE workspace = ? AND table_name = ? AND name = ?")
+    .bind(workspaces::current(&state))
+    .bind(&table)
+    .bind(&name)
+    .execute(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Writes the views of the active workspace (of `table` only, when given) to `path` as
+/// JSON. Returns the number of views written.
+#[tauri::command]
+pub async fn export_views(
+  state: State<'_, AppState>,
+  path: String,
+  table: Option<String>,
+) -> Result<usize, String> {
+  let file = ViewFile {
+    format: EXPORT_FORMAT.to_string(),
+    version: EXPORT_VERSION,
+    views: load_views(&state, table.as_deref()).await?,
+  };
+  let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+  std::fs::write(&path, json).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+  Ok(file.views.len())
+}
+
+/// Adds the views of an exported file to the active workspace, replacing views of the same
+/// table and name. Returns the number imported.
+#[tauri::command]
+pub async fn import_views(state: State<'_, AppState>, path: String) -> Result<usize, String> {
+  let json = std::fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+  let file: ViewFile =
+    serde_json::from_str(&json).map_err(|e| format!("Not a grid view file: {}", e))?;
+  if file.format != EXPORT_FORMAT {
+    return Err("Not a grid view file".to_string());
+  }
+  if file.version > EXPORT_VERSION {
+    return Err(format!(
+      "Grid view file version {} is newer than this app supports",
+      file.version
+    ));
+  }
+  let count = file.views.len();
+  for view in file.views {
+    store_view(&state, view).await?;
+  }
+  Ok(count)
+}
@@ -0,0 +1,532 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::db::{self, JsonRow};
+use crate::{masking, transfer, AppState};
+
+const MIN_INTERVAL_MS: u64 = 250;
+const MAX_ROW_VERSIONS: usize = 200;
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnChange {
+  pub column: String,
+  pub old: serde_json::Value,
+  pub new: serde_json::Value,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RowVersion {
+  pub version: u64,
+  pub captured_at_ms: u128,
+  /// `None` once the row has been deleted.
+  pub row: Option<JsonRow>,
+  pub changes: Vec<ColumnChange>,
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RowWatchEvent {
+  watch_id: String,
+  version: Option<RowVersion>,
+  error: Option<String>,
+}
+
+pub struct RowWatch {
+  pub connection: String,
+  pub table: String,
+  pub pk_value: String,
+  versions: Arc<Mutex<Vec<RowVersion>>>,
+  task: tokio::task::JoinHandle<()>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowWatchInfo {
+  pub watch_id: String,
+  pub connection: String,
+  pub table: String,
+  pub pk_value: String,
+  pub versions: usize,
+}
+
+fn now_ms() -> u128 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_millis())
+    .unwrap_or_default()
+}
+
+fn diff_rows(old: Option<&JsonRow>, new: Option<&JsonRow>) -> Vec<ColumnChange> {
+  let empty = JsonRow::new();
+  let (old, new) = (old.unwrap_or(&empty), new.unwrap_or(&empty));
+  let mut columns: Vec<&String> = old.keys().chain(new.keys()).collect();
+  columns.sort();
+  columns.dedup();
+  columns
+    .into_iter()
+    .filter_map(|column| {
+      let before = old.get(column).cloned().unwrap_or(serde_json::Value::Null);
+      let after = new.get(column).cloned().unwrap_or(serde_json::Value::Null);
+      (before != after).then(|| ColumnChange {
+        column: column.clone(),
+        old: before,
+        new: after,
+      })
+    })
+    .collect()
+}
+
+/// Polls a single row by primary key and emits `row-watch` events whenever any of its values
+/// change. The first poll is recorded as version 1 so the history always has a baseline.
+#[tauri::command]
+pub async fn watch_row(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+  pk_col: String,
+  pk_val: String,
+  interval_ms: Option<u64>,
+) -> Result<String, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let table = pool.resolve_table(&table).await?;
+  let interval = Duration::from_millis(interval_ms.unwrap_or(2000).max(MIN_INTERVAL_MS));
+  let sql = format!(
+    "SELECT * FROM {} WHERE {}",
+    pool.table_ref(&table),
+    pool.text_eq(&pk_col, 1)
+  );
+
+  // Fail fast on a bad table/column instead of inside the background task
+  pool.fetch_rows(&sql, std::slice::from_ref(&pk_val)).await?;
+
+  let watch_id = crate::next_id("watch");
+  let versions: Arc<Mutex<Vec<RowVersion>>> = Arc::new(Mutex::new(Vec::new()));
+
+  let task = {
+    let watch_id = watch_id.clone();
+    let versions = versions.clone();
+    let pk_val = pk_val.clone();
+    let connection = connection.clone();
+    tokio::spawn(async move {
+      let mut ticker = tokio::time::interval(interval);
+      let mut version = 0u64;
+      let mut last: Option<Option<JsonRow>> = None;
+      loop {
+        ticker.tick().await;
+        let current = match pool.fetch_rows(&sql, std::slice::from_ref(&pk_val)).await {
+          Ok(mut rows) => {
+            let state = app.state::<AppState>();
+            transfer::record(
+              &state,
+              &connection,
+              transfer::Category::Background,
+              rows.len(),
+              transfer::rows_bytes(&rows),
+            );
+            // Versions are kept and diffed as the user may see them
+            if let Some(mask) = masking::active(&state, &connection) {
+              for row in &mut rows {
+                mask.apply(row);
+              }
+            }
+            if rows.is_empty() {
+              None
+            } else {
+              Some(rows.swap_remove(0))
+            }
+          }
+          Err(e) => {
+            let _ = app.emit(
+              "row-watch",
+              RowWatchEvent {
+                watch_id: watch_id.clone(),
+                version: None,
+                error: Some(e),
+              },
+            );
+            continue;
+          }
+        };
+
+        let changes = match &last {
+          Some(previous) if *previous == current => continue,
+          Some(previous) => diff_rows(previous.as_ref(), current.as_ref()),
+          None => Vec::new(),
+        };
+
+        version += 1;
+        let entry = RowVersion {
+          version,
+          captured_at_ms: now_ms(),
+          row: current.clone(),
+          changes,
+        };
+        {
+          let mut versions = versions.lock().unwrap_or_else(PoisonError::into_inner);
+          versions.push(entry.clone());
+          if versions.len() > MAX_ROW_VERSIONS {
+            versions.remove(0);
+          }
+        }
+        let _ = app.emit(
+          "row-watch",
+          RowWatchEvent {
+            watch_id: watch_id.clone(),
+            version: Some(entry),
+            error: None,
+          },
+        );
+        last = Some(current);
+      }
+    })
+  };
+
+  state
+    .row_watches
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(
+      watch_id.clone(),
+      RowWatch {
+        connection,
+        table,
+        pk_value: pk_val,
+        versions,
+        task,
+      },
+    );
+  Ok(watch_id)
+}
+
+#[tauri::command]
+pub fn unwatch_row(state: State<'_, AppState>, watch_id: String) -> Result<(), String> {
+  let watch = state
+    .row_watches
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&watch_id)
+    .ok_or("Unknown watch")?;
+  watch.task.abort();
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_row_versions(
+  state: State<'_, AppState>,
+  watch_id: String,
+) -> Result<Vec<RowVersion>, String> {
+  let watches = state
+    .row_watches
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let watch = watches.get(&watch_id).ok_or("Unknown watch")?;
+  let versions = watch
+    .versions
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .clone();
+  Ok(versions)
+}
+
+#[tauri::command]
+pub fn list_row_watches(state: State<'_, AppState>) -> Vec<RowWatchInfo> {
+  let watches = state
+    .row_watches
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let mut list: Vec<RowWatchInfo> = watches
+    .iter()
+    .map(|(id, watch)| RowWatchInfo {
+      watch_id: id.clone(),
+      connection: watch.connection.clone(),
+      table: watch.table.clone(),
+      pk_value: watch.pk_value.clone(),
+      versions: watch
+        .versions
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .len(),
+    })
+    .collect();
+  list.sort_by(|a, b| a.watch_id.cmp(&b.watch_id));
+  list
+}
+
+pub type RowWatches = HashMap<String, RowWatch>;
+
+const MAX_TAIL_BATCH: u32 = 500;
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TailEvent {
+  tail_id: String,
+  rows: Vec<JsonRow>,
+  cursor: Option<serde_json::Value>,
+  error: Option<String>,
+}
+
+pub struct TableTail {
+  pub connection: String,
+  pub table: String,
+  pub order_col: String,
+  cursor: Arc<Mutex<Option<serde_json::Value>>>,
+  rows_emitted: Arc<AtomicU64>,
+  task: tokio::task::JoinHandle<()>,
+}
+
+pub type TableTails = HashMap<String, TableTail>;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableTailInfo {
+  pub tail_id: String,
+  pub connection: String,
+  pub table: String,
+  pub order_col: String,
+  pub cursor: Option<serde_json::Value>,
+  pub rows_emitted: u64,
+}
+
+fn cursor_text(value: &serde_json::Value) -> Option<String> {
+  match value {
+    serde_json::Value::Null => None,
+    serde_json::Value::String(s) => Some(s.clone()),
+    other => Some(other.to_string()),
+  }
+}
+
+/// `cursor` as shown for `order_col` under the connection's masking rules; the tail itself
+/// keeps paging by the real value.
+fn masked_cursor(
+  state: &AppState,
+  connection: &str,
+  order_col: &str,
+  cursor: Option<serde_json::Value>,
+) -> Option<serde_json::Value> {
+  let (Some(mask), Some(value)) = (masking::active(state, connection), cursor.clone()) else {
+    return cursor;
+  };
+  let mut row = JsonRow::new();
+  row.insert(order_col.to_string(), value);
+  mask.apply(&mut row);
+  row.remove(order_col)
+}
+
+/// `tail -f` for a table: polls for rows whose `order_col` is greater than the last one seen
+/// and emits only those as `table-tail` events.
+///
+/// `order_col` should be monotonically increasing (an auto-increment id or insert timestamp);
+/// rows that share the last seen value are not re-emitted.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn tail_table(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  connection: String,
+  table: String,
+  order_col: String,
+  interval_ms: Option<u64>,
+  backfill: Option<u32>,
+) -> Result<String, String> {
+  let pool = db::sql_pool(&state, &connection)?;
+  let table = pool.resolve_table(&table).await?;
+  let interval = Duration::from_millis(interval_ms.unwrap_or(1000).max(MIN_INTERVAL_MS));
+  let table_ref = pool.table_ref(&table);
+  let col = pool.quote_ident(&order_col);
+
+  // Postgres won't compare a column against a text bind, so cast to the column's own type
+  let cursor_param = match pool
+    .pg_column_type(db::PG_DEFAULT_SCHEMA, &table, &order_col)
+    .await?
+  {
+    Some(pg_type) => format!("CAST($1 AS {})", pool.quote_ident(&pg_type)),
+    None => pool.placeholder(1),
+  };
+
+  let backfill = backfill.unwrap_or(0).min(MAX_TAIL_BATCH);
+  let mut initial_rows = if backfill > 0 {
+    let sql = format!(
+      "SELECT * FROM {} ORDER BY {} DESC LIMIT {}",
+      table_ref, col, backfill
+    );
+    pool.fetch_rows(&sql, &[]).await?
+  } else {
+    Vec::new()
+  };
+  initial_rows.reverse();
+
+  let initial_cursor = if let Some(row) = initial_rows.last() {
+    row.get(&order_col).cloned()
+  } else {
+    let sql = format!("SELECT MAX({}) AS max_key FROM {}", col, table_ref);
+    pool
+      .fetch_rows(&sql, &[])
+      .await?
+      .first()
+      .and_then(|row| row.get("max_key").cloned())
+  }
+  .filter(|v| !v.is_null());
+
+  let tail_id = crate::next_id("tail");
+  let cursor = Arc::new(Mutex::new(initial_cursor));
+  let rows_emitted = Arc::new(AtomicU64::new(0));
+
+  let task = {
+    let tail_id = tail_id.clone();
+    let cursor = cursor.clone();
+    let rows_emitted = rows_emitted.clone();
+    let order_col = order_col.clone();
+    let connection = connection.clone();
+    tokio::spawn(async move {
+      let emit =
+        |mut rows: Vec<JsonRow>, cursor: Option<serde_json::Value>, error: Option<String>| {
+          let state = app.state::<AppState>();
+          if let Some(mask) = masking::active(&state, &connection) {
+            for row in &mut rows {
+              mask.apply(row);
+            }
+          }
+          let _ = app.emit(
+            "table-tail",
+            TailEvent {
+              tail_id: tail_id.clone(),
+              rows,
+              cursor: masked_cursor(&state, &connection, &order_col, cursor),
+              error,
+            },
+          );
+        };
+
+      if !initial_rows.is_empty() {
+        rows_emitted.fetch_add(initial_rows.len() as u64, Ordering::Relaxed);
+        let current = cursor
+          .lock()
+          .unwrap_or_else(PoisonError::into_inner)
+          .clone();
+        emit(initial_rows, current, None);
+      }
+
+      let mut ticker = tokio::time::interval(interval);
+      ticker.tick().await;
+      loop {
+        ticker.tick().await;
+        let last = cursor
+          .lock()
+          .unwrap_or_else(PoisonError::into_inner)
+          .as_ref()
+          .and_then(cursor_text);
+        let (sql, binds) = match last {
+          Some(last) => (
+            format!(
+              "SELECT * FROM {} WHERE {} > {} ORDER BY {} ASC LIMIT {}",
+              table_ref, col, cursor_param, col, MAX_TAIL_BATCH
+            ),
+            vec![last],
+          ),
+          None => (
+            format!(
+              "SELECT * FROM {} ORDER BY {} ASC LIMIT {}",
+              table_ref, col, MAX_TAIL_BATCH
+            ),
+            Vec::new(),
+          ),
+        };
+
+        let fetched = pool.fetch_rows(&sql, &binds).await;
+        if let Ok(rows) = &fetched {
+          transfer::record(
+            &app.state::<AppState>(),
+            &connection,
+            transfer::Category::Background,
+            rows.len(),
+            transfer::rows_bytes(rows),
+          );
+        }
+        match fetched {
+          Ok(rows) if rows.is_empty() => {}
+          Ok(rows) => {
+            let next = rows
+              .last()
+              .and_then(|row| row.get(&order_col).cloned())
+              .filter(|v| !v.is_null());
+            if next.is_some() {
+              cursor
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone_from(&next);
+            }
+            rows_emitted.fetch_add(rows.len() as u64, Ordering::Relaxed);
+            emit(rows, next, None);
+          }
+          Err(e) => emit(Vec::new(), None, Some(e)),
+        }
+      }
+    })
+  };
+
+  state
+    .table_tails
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .insert(
+      tail_id.clone(),
+      TableTail {
+        connection,
+        table,
+        order_col,
+        cursor,
+        rows_emitted,
+        task,
+      },
+    );
+  Ok(tail_id)
+}
+
+#[tauri::command]
+pub fn stop_tail(state: State<'_, AppState>, tail_id: String) -> Result<(), String> {
+  let tail = state
+    .table_tails
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .remove(&tail_id)
+    .ok_or("Unknown tail")?;
+  tail.task.abort();
+  Ok(())
+}
+
+#[tauri::command]
+pub fn list_tails(state: State<'_, AppState>) -> Vec<TableTailInfo> {
+  let tails = state
+    .table_tails
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner);
+  let mut list: Vec<TableTailInfo> = tails
+    .iter()
+    .map(|(id, tail)| TableTailInfo {
+      tail_id: id.clone(),
+      connection: tail.connection.clone(),
+      table: tail.table.clone(),
+      order_col: tail.order_col.clone(),
+      cursor: masked_cursor(
+        &state,
+        &tail.connection,
+        &tail.order_col,
+        tail
+          .cursor
+          .lock()
+          .unwrap_or_else(PoisonError::into_inner)
+          .clone(),
+      ),
+      rows_emitted: tail.rows_emitted.load(Ordering::Relaxed),
+    })
+    .collect();
+  list.sort_by(|a, b| a.tail_id.cmp(&b.tail_id));
+  list
+}
@@ -0,0 +1,172 @@
+//! Named workspaces that keep connection profiles, variables and settings apart, e.g. one
+//! per client. Exactly one workspace is active; it is remembered across restarts.
+
+use std::sync::PoisonError;
+
+use tauri::{AppHandle, Emitter, State};
+
+use crate::{store, AppState};
+
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+const ACTIVE_KEY: &str = "active_workspace";
+
+pub fn current(state: &AppState) -> String {
+  state
+    .workspace
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .clone()
+}
+
+/// Restores the workspace that was active when the app last ran.
+pub async fn load_active(state: &AppState) -> Result<(), String> {
+  let pool = store::pool(state)?;
+  let row: Option<(String,)> = sqlx::query_as("SELECT value FROM app_settings WHERE key = ?")
+    .bind(ACTIVE_KEY)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  if let Some((name,)) = row {
+    *state
+      .workspace
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner) = name;
+  }
+  Ok(())
+}
+
+async fn exists(pool: &sqlx::SqlitePool, name: &str) -> Result<bool, String> {
+  let row: Option<(String,)> = sqlx::query_as("SELECT name FROM workspaces WHERE name = ?")
+    .bind(name)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  Ok(row.is_some())
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceInfo {
+  pub name: String,
+  pub active: bool,
+  pub profile_count: i64,
+}
+
+#[tauri::command]
+pub async fn list_workspaces(state: State<'_, AppState>) -> Result<Vec<WorkspaceInfo>, String> {
+  let pool = store::pool(&state)?;
+  let rows: Vec<(String, i64)> = sqlx::query_as(
+    "SELECT w.name, COUNT(p.id) FROM workspaces w \
+     LEFT JOIN connection_profiles p ON p.workspace = w.name \
+     GROUP BY w.name ORDER BY w.name COLLATE NOCASE",
+  )
+  .fetch_all(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  let active = current(&state);
+  Ok(
+    rows
+      .into_iter()
+      .map(|(name, profile_count)| WorkspaceInfo {
+        active: name == active,
+        name,
+        profile_count,
+      })
+      .collect(),
+  )
+}
+
+#[tauri::command]
+pub async fn create_workspace(state: State<'_, AppState>, name: String) -> Result<(), String> {
+  let name = name.trim().to_string();
+  if name.is_empty() {
+    return Err("Workspace name cannot be empty".to_string());
+  }
+  let pool = store::pool(&state)?;
+  if exists(&pool, &name).await? {
+    return Err(format!("Workspace already exists: {}", name));
+  }
+  sqlx::query("INSERT INTO workspaces (name, created_at) VALUES (?, ?)")
+    .bind(&name)
+    .bind(store::now_ms())
+    .execute(&pool)
+    .await
+    .map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Makes `name` the active workspace and emits `workspace-changed`. Open connections are
+/// left alone; the frontend decides whether to close them.
+#[tauri::command]
+pub async fn switch_workspace(
+  app: AppHandle,
+  state: State<'_, AppState>,
+  name: String,
+) -> Result<(), String> {
+  let pool = store::pool(&state)?;
+  if !exists(&pool, &name).await? {
+    return Err(format!("Unknown workspace: {}", name));
+  }
+  sqlx::query(
+    "INSERT INTO app_settings (key, value) VALUES (?, ?) \
+     ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+  )
+  .bind(ACTIVE_KEY)
+  .bind(&name)
+  .execute(&pool)
+  .await
+  .map_err(|e| e.to_string())?;
+  state
+    .workspace
+    .lock()
+    .unwrap_or_else(PoisonError::into_inner)
+    .clone_from(&name);
+  let _ = app.emit("workspace-changed", name);
+  Ok(())
+}
+
+#[tauri::command]
+pub async fn get_workspace_setting(
+  state: State<'_, AppState>,
+  key: String,
+) -> Result<Option<String>, String> {
+  setting(&state, &key).await
+}
+
+/// Setting `key` of the active workspace.
+pub async fn setting(state: &AppState, key: &str) -> Result<Option<String>, String> {
+  let pool = store::pool(state)?;
+  let row: Option<(String,)> =
+    sqlx::query_as("SELECT value FROM workspace_settings WHERE workspace = ? AND key = ?")
+      .bind(current(state))
+      .bind(key)
+      .fetch_optional(&pool)
+      .await
+      .map_err(|e| e.to_string())?;
+  Ok(row.map(|(value,)| value))
+}
+
+/// Sets a setting of the active workspace; `None` removes it.
+#[tauri::command]
+pub async fn set_workspace_setting(
+  state: State<'_, AppState>,
+  key: String,
+  value: Option<String>,
+) -> Result<(), String> {
+  let pool = store::pool(&state)?;
+  let query = match &value {
+    Some(value) => sqlx::query(
+      "INSERT INTO workspace_settings (workspace, key, value) VALUES (?, ?, ?) \
+       ON CONFLICT(workspace, key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(current(&state))
+    .bind(&key)
+    .bind(value),
+    None => sqlx::query("DELETE FROM workspace_settings WHERE workspace = ? AND key = ?")
+      .bind(current(&state))
+      .bind(&key),
+  };
+  query.execute(&pool).await.map_err(|e| e.to_string())?;
+  Ok(())
+}
@@ -0,0 +1,371 @@
+//! Excel (`.xlsx`) exports of a query result. Cells are typed from the values and the column
+//! types: numbers, booleans and dates stay numbers, booleans and dates in Excel instead of
+//! text. The header row is bold, frozen and carries an autofilter.
+//!
+//! The worksheet is written in constant memory mode: each row goes to a temporary file as soon
+//! as the next one starts, so only the current row is held while rows stream in from the
+//! server. A result longer than a worksheet allows continues on further sheets. Like file
+//! exports these run as background tasks (`xlsx_export`) writing `<path>.part` until done.
+
+use std::path::PathBuf;
+use std::time::Instant;
+
+use futures::{Stream, StreamExt};
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use tauri::{AppHandle, Manager};
+
+use crate::db::{self, JsonRow, ResultColumn, SqlPool};
+use crate::file_export::{self, PartFile, Progress};
+use crate::masking::MaskingConfig;
+use crate::schema::{self, ColumnKind};
+use crate::tasks::{self, TaskHandle, TaskSpec};
+use crate::{masking, readonly, timezone, transfer, AppState};
+
+/// Rows per worksheet, the header included.
+const MAX_ROWS: u32 = 1_048_576;
+/// Columns per worksheet.
+const MAX_COLUMNS: usize = 16_384;
+/// Characters Excel keeps in a cell; longer text is cut.
+const MAX_TEXT: usize = 32_767;
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XlsxExportSummary {
+  pub path: String,
+  pub rows: u64,
+  /// Worksheets the rows took (more than one past Excel's row limit).
+  pub sheets: usize,
+  /// Size of the file written.
+  pub bytes: u64,
+  pub elapsed_ms: u64,
+}
+
+fn xlsx_error(e: XlsxError) -> String {
+  format!("Writing the workbook failed: {}", e)
+}
+
+/// Excel date formats of the typed columns, and the header format.
+struct Formats {
+  header: Format,
+  date: Format,
+  datetime: Format,
+  time: Format,
+}
+
+impl Formats {
+  fn new() -> Self {
+    Formats {
+      header: Format::new().set_bold(),
+      date: Format::new().set_num_format("yyyy-mm-dd"),
+      datetime: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+      time: Format::new().set_num_format("hh:mm:ss"),
+    }
+  }
+}
+
+/// The workbook being written and the sheet rows currently go to.
+struct Sheets {
+  workbook: Workbook,
+  base_name: String,
+  columns: Vec<ResultColumn>,
+  kinds: Vec<ColumnKind>,
+  formats: Formats,
+  /// Worksheets started so far.
+  count: usize,
+  /// Next row of the current sheet.
+  next_row: u32,
+}
+
+impl Sheets {
+  fn new(base_name: String) -> Self {
+    Sheets {
+      workbook: Workbook::new(),
+      base_name,
+      columns: Vec::new(),
+      kinds: Vec::new(),
+      formats: Formats::new(),
+      count: 0,
+      next_row: 0,
+    }
+  }
+
+  fn sheet(&mut self) -> Result<&mut Worksheet, String> {
+    self
+      .workbook
+      .worksheet_from_index(self.count - 1)
+      .map_err(xlsx_error)
+  }
+
+  /// Starts the first sheet with `columns` as the header.
+  fn begin(&mut self, columns: Vec<ResultColumn>) -> Result<(), String> {
+    if columns.len() > MAX_COLUMNS {
+      return Err(format!(
+        "The result has {} columns; Excel sheets hold at most {}",
+        columns.len(),
+        MAX_COLUMNS
+      ));
+    }
+    self.kinds = columns
+      .iter()
+      .map(|c| schema::column_kind(&c.type_name))
+      .collect();
+    self.columns = columns;
+    self.add_sheet()
+  }
+
+  fn add_sheet(&mut self) -> Result<(), String> {
+    self.close_sheet()?;
+    self.count += 1;
+    let name = if self.count == 1 {
+      self.base_name.clone()
+    } else {
+      format!("{} ({})", self.base_name, self.count)
+    };
+    let sheet = self.workbook.add_worksheet_with_constant_memory();
+    sheet.set_name(name).map_err(xlsx_error)?;
+    for (column, i) in self.columns.iter().zip(0u16..) {
+      let width = column.name.chars().count().clamp(8, 60) + 2;
+      sheet
+        .set_column_width(i, u16::try_from(width).unwrap_or(62))
+        .map_err(xlsx_error)?;
+      sheet
+        .write_string_with_format(0, i, &column.name, &self.formats.header)
+        .map_err(xlsx_error)?;
+    }
+    sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+    self.next_row = 1;
+    Ok(())
+  }
+
+  /// Puts the autofilter over the header and rows of the current sheet.
+  fn close_sheet(&mut self) -> Result<(), String> {
+    if self.count == 0 || self.columns.is_empty() {
+      return Ok(());
+    }
+    let last_row = self.next_row - 1;
+    let last_column = u16::try_from(self.columns.len() - 1).unwrap_or(u16::MAX);
+    self
+      .sheet()?
+      .autofilter(0, 0, last_row, last_column)
+      .map_err(xlsx_error)?;
+    Ok(())
+  }
+
+  fn row(&mut self, row: &JsonRow) -> Result<(), String> {
+    if self.next_row == MAX_ROWS {
+      self.add_sheet()?;
+    }
+    let index = self.next_row;
+    let sheet = self
+      .workbook
+      .worksheet_from_index(self.count - 1)
+      .map_err(xlsx_error)?;
+    for ((column, kind), i) in self.columns.iter().zip(&self.kinds).zip(0u16..) {
+      let value = row.get(&column.name).unwrap_or(&serde_json::Value::Null);
+      write_cell(sheet, &self.formats, *kind, (index, i), value).map_err(xlsx_error)?;
+    }
+    self.next_row += 1;
+    Ok(())
+  }
+}
+
+/// Writes `value` at `(row, column)` as the cell type `kind` calls for, falling back to text
+/// when the value doesn't parse as that type.
+fn write_cell(
+  sheet: &mut Worksheet,
+  formats: &Formats,
+  kind: ColumnKind,
+  (row, column): (u32, u16),
+  value: &serde_json::Value,
+) -> Result<(), XlsxError> {
+  match value {
+    serde_json::Value::Null => return Ok(()),
+    serde_json::Value::Bool(b) => sheet.write_boolean(row, column, *b),
+    serde_json::Value::Number(n) => match n.as_f64() {
+      Some(n) => sheet.write_number(row, column, n),
+      None => sheet.write_string(row, column, n.to_string()),
+    },
+    serde_json::Value::String(s) => match kind {
+      ColumnKind::Decimal => match s.parse::<f64>() {
+        Ok(n) if n.is_finite() => sheet.write_number(row, column, n),
+        _ => sheet.write_string(row, column, text(s)),
+      },
+      ColumnKind::Date => match chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        Ok(d) => sheet.write_datetime_with_format(row, column, d, &formats.date),
+        Err(_) => sheet.write_string(row, column, text(s)),
+      },
+      ColumnKind::DateTime => match parse_datetime(s) {
+        Some(d) => sheet.write_datetime_with_format(row, column, d, &formats.datetime),
+        None => sheet.write_string(row, column, text(s)),
+      },
+      ColumnKind::Time => match chrono::NaiveTime::parse_from_str(s, "%H:%M:%S%.f") {
+        Ok(t) => sheet.write_datetime_with_format(row, column, t, &formats.time),
+        Err(_) => sheet.write_string(row, column, text(s)),
+      },
+      _ => sheet.write_string(row, column, text(s)),
+    },
+    other => sheet.write_string(row, column, text(&other.to_string())),
+  }
+  .map(|_| ())
+}
+
+/// `s` cut to what a cell holds.
+fn text(s: &str) -> &str {
+  match s.char_indices().nth(MAX_TEXT) {
+    Some((end, _)) => &s[..end],
+    None => s,
+  }
+}
+
+/// Wall-clock time of a timestamp as the row helpers format it; zoned ones keep their local
+/// time since Excel dates carry no zone.
+fn parse_datetime(s: &str) -> Option<chrono::NaiveDateTime> {
+  chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+    .ok()
+    .or_else(|| {
+      chrono::DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|d| d.naive_local())
+    })
+}
+
+/// Writes every row of `rows`, taking the columns from the first one.
+async fn write_stream<R: sqlx::Row>(
+  mut rows: impl Stream<Item = Result<R, sqlx::Error>> + Unpin,
+  to_json: impl Fn(&R) -> JsonRow,
+  sheets: &mut Sheets,
+  mask: Option<&MaskingConfig>,
+  progress: &mut Progress<'_>,
+) -> Result<u64, String> {
+  let mut written = 0u64;
+  while let Some(row) = rows.next().await {
+    let row = row.map_err(|e| e.to_string())?;
+    if written == 0 {
+      sheets.begin(db::result_columns(std::slice::from_ref(&row)))?;
+    }
+    let mut json = to_json(&row);
+    if let Some(mask) = mask {
+      mask.apply(&mut json);
+    }
+    sheets.row(&json)?;
+    written += 1;
+    progress.report(written, "Writing rows");
+  }
+  if written == 0 {
+    sheets.begin(Vec::new())?;
+  }
+  sheets.close_sheet()?;
+  Ok(written)
+}
+
+/// Carries out an Excel export task: streams the result of `sql` on `connection` into `path`.
+pub async fn write_xlsx(
+  task: &TaskHandle,
+  connection: String,
+  sql: String,
+  path: String,
+  sheet_name: Option<String>,
+) -> Result<XlsxExportSummary, String> {
+  let started = Instant::now();
+  let state = task.app.state::<AppState>();
+  readonly::check_statement(&state, &connection, &sql)?;
+  let pool = db::sql_pool(&state, &connection)?;
+  let tz = timezone::display_zone(&state, &connection);
+  let mask = masking::active(&state, &connection);
+  let mut sheets = Sheets::new(sheet_name.unwrap_or_else(|| "Result".to_string()));
+  let mut progress = Progress::new(task);
+  task.progress(0, None, Some("Reading rows"));
+
+  let rows = match &pool {
+    SqlPool::MySql(pool) => {
+      let rows = sqlx::query(&sql).fetch(pool);
+      let to_json = |row: &_| db::mysql_row_to_json(row, tz.as_ref());
+      write_stream(rows, to_json, &mut sheets, mask.as_ref(), &mut progress).await?
+    }
+    SqlPool::Postgres(pool) => {
+      let rows = sqlx::query(&sql).fetch(pool);
+      let to_json = |row: &_| db::pg_row_to_json(row, tz.as_ref());
+      write_stream(rows, to_json, &mut sheets, mask.as_ref(), &mut progress).await?
+    }
+    SqlPool::Sqlite(pool) => {
+      let rows = sqlx::query(&sql).fetch(pool);
+      write_stream(
+        rows,
+        db::sqlite_row_to_json,
+        &mut sheets,
+        mask.as_ref(),
+        &mut progress,
+      )
+      .await?
+    }
+  };
+
+  task.progress(rows, None, Some("Saving workbook"));
+  let target = PathBuf::from(&path);
+  let (part, mut file) = PartFile::create(&target)?;
+  let count = sheets.count;
+  let mut workbook = sheets.workbook;
+  // Zipping the sheets is CPU-bound; keep it off the async workers
+  let file = tokio::task::spawn_blocking(move || {
+    workbook.save_to_writer(&mut file).map_err(xlsx_error)?;
+    file.sync_all().map_err(file_export::io_error)?;
+    Ok::<_, String>(file)
+  })
+  .await
+  .map_err(|e| e.to_string())??;
+  let bytes = file.metadata().map_or(0, |m| m.len());
+  drop(file);
+  part.commit(&target)?;
+
+  transfer::record(
+    &state,
+    &connection,
+    transfer::Category::Export,
+    usize::try_from(rows).unwrap_or(usize::MAX),
+    usize::try_from(bytes).unwrap_or(usize::MAX),
+  );
+  Ok(XlsxExportSummary {
+    path,
+    rows,
+    sheets: count,
+    bytes,
+    elapsed_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+  })
+}
+
+/// Exports the result of `sql` to the Excel workbook at `path` in the background and returns
+/// the task id; the task's result is an [`XlsxExportSummary`]. `sheet_name` names the sheet
+/// (`Result` by default).
+#[tauri::command]
+pub async fn export_xlsx(
+  app: AppHandle,
+  connection: String,
+  sql: String,
+  path: String,
+  sheet_name: Option<String>,
+) -> Result<String, String> {
+  if let Some(name) = &sheet_name {
+    // Excel's rules for sheet names; later sheets append " (2)", " (3)", ...
+    if name.is_empty()
+      || name.chars().count() > 25
+      || name.contains(['[', ']', ':', '*', '?', '/', '\\'])
+    {
+      return Err(
+        "Sheet names need 1 to 25 characters and can't contain [ ] : * ? / \\".to_string(),
+      );
+    }
+  }
+  let state = app.state::<AppState>();
+  readonly::check_statement(&state, &connection, &sql)?;
+  db::sql_pool(&state, &connection)?;
+  file_export::check_folder(&path)?;
+  Ok(tasks::start(
+    &app,
+    TaskSpec::XlsxExport {
+      connection,
+      sql,
+      path,
+      sheet_name,
+    },
+  ))
+}
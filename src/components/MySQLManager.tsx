@@ -145,6 +145,9 @@ export default function MySQLManager({ onDisconnect, onDragStart, connectionName
     // Editing
     const [mode, setMode] = useState<'view' | 'edit'>('view');
     const [primaryKey, setPrimaryKey] = useState<string | null>(null);
+    const [primaryKeys, setPrimaryKeys] = useState<string[]>([]);
+    // Primary key column values identifying a row, for update/delete
+    const pkValues = (row: any) => Object.fromEntries(primaryKeys.map(col => [col, String(row[col])]));
     // Batch Editing
     const [pendingChanges, setPendingChanges] = useState<Record<number, Record<string, string>>>({});
     // History
@@ -218,7 +221,7 @@ export default function MySQLManager({ onDisconnect, onDragStart, connectionName
             confirmText: t('delete'),
             onConfirm: async () => {
                 try {
-                    await invoke('mysql_delete_row', { tableName: selectedKey, pkCol: primaryKey, pkVal: String(pkVal) });
+                    await invoke('mysql_delete_row', { tableName: selectedKey, pk: pkValues(row) });
                     showToast(t('row_deleted_success'), 'success');
                     fetchTableData(selectedKey, page);
                     fetchCount(selectedKey);
@@ -465,8 +468,12 @@ export default function MySQLManager({ onDisconnect, onDragStart, connectionName
         setIsLoading(true);
         try {
             const offset = (p - 1) * pageSize;
-            const res = await invoke<string[]>('mysql_get_rows', { tableName: table, limit: pageSize, offset });
-            setKeyValue(`[${res.join(',')}]`);
+            // Column-major page: one value array per column
+            const res = await invoke<{ columns: { name: string }[]; values: unknown[][]; rowCount: number }>('mysql_get_rows', { tableName: table, limit: pageSize, offset });
+            const rows = Array.from({ length: res.rowCount }, (_, i) =>
+                Object.fromEntries(res.columns.map((col, c) => [col.name, res.values[c][i]]))
+            );
+            setKeyValue(JSON.stringify(rows));
         } catch (err) {
             console.error(err);
             setKeyValue(t('error_loading_data'));
@@ -478,7 +485,7 @@ export default function MySQLManager({ onDisconnect, onDragStart, connectionName
 
     const fetchCount = async (table: string) => {
         try {
-            const count = await invoke<number>('mysql_get_count', { tableName: table });
+            const { count } = await invoke<{ count: number }>('mysql_get_count', { tableName: table });
             setTotalRows(count);
         } catch (e) {
             console.error("Failed to fetch count", e);
@@ -487,17 +494,23 @@ export default function MySQLManager({ onDisconnect, onDragStart, connectionName
 
     const fetchPrimaryKey = async (table: string) => {
         try {
-            const pk = await invoke<string | null>('mysql_get_primary_key', { tableName: table });
-            setPrimaryKey(pk);
+            const pks = await invoke<string[]>('mysql_get_primary_key', { tableName: table });
+            setPrimaryKeys(pks);
+            setPrimaryKey(pks[0] ?? null);
         } catch (e) {
             console.error("Failed to fetch PK", e);
             setPrimaryKey(null);
+            setPrimaryKeys([]);
         }
     };
 
     useEffect(() => {
         fetchDatabases();
         fetchKeys();
+        // Release the pooled connections held by console cursors when the console closes
+        return () => {
+            invoke('close_cursors', { connectionId: 'mysql' }).catch(console.error);
+        };
     }, []);
 
     useEffect(() => {
@@ -514,6 +527,7 @@ export default function MySQLManager({ onDisconnect, onDragStart, connectionName
             setKeyValue("");
             setTotalRows(0);
             setPrimaryKey(null);
+            setPrimaryKeys([]);
             setSchemaColumns([]);
         }
     }, [selectedKey]);
@@ -641,8 +655,7 @@ export default function MySQLManager({ onDisconnect, onDragStart, connectionName
                 if (String(row[colName]) !== newVal) {
                     updates.push({
                         tableName: selectedKey,
-                        pkCol: primaryKey,
-                        pkVal: String(pkVal),
+                        pk: pkValues(row),
                         colName,
                         newVal
                     });
@@ -144,6 +144,9 @@ export default function PostgresManager({ onDisconnect, onDragStart, connectionN
     // Editing
     const [mode, setMode] = useState<'view' | 'edit'>('view');
     const [primaryKey, setPrimaryKey] = useState<string | null>(null);
+    const [primaryKeys, setPrimaryKeys] = useState<string[]>([]);
+    // Primary key column values identifying a row, for update/delete
+    const pkValues = (row: any) => Object.fromEntries(primaryKeys.map(col => [col, String(row[col])]));
     // Batch Editing
     const [pendingChanges, setPendingChanges] = useState<Record<number, Record<string, string>>>({});
     // History
@@ -208,7 +211,7 @@ export default function PostgresManager({ onDisconnect, onDragStart, connectionN
             confirmText: t('delete'),
             onConfirm: async () => {
                 try {
-                    await invoke('postgres_delete_row', { tableName: selectedKey, pkCol: primaryKey, pkVal: String(pkVal) });
+                    await invoke('postgres_delete_row', { tableName: selectedKey, pk: pkValues(row) });
                     showToast(t('row_deleted_success'), 'success');
                     fetchTableData(selectedKey, page);
                     fetchCount(selectedKey);
@@ -481,8 +484,12 @@ export default function PostgresManager({ onDisconnect, onDragStart, connectionN
         setIsLoading(true);
         try {
             const offset = (p - 1) * pageSize;
-            const res = await invoke<string[]>('postgres_get_rows', { tableName: table, limit: pageSize, offset });
-            setKeyValue(`[${res.join(',')}]`);
+            // Column-major page: one value array per column
+            const res = await invoke<{ columns: { name: string }[]; values: unknown[][]; rowCount: number }>('postgres_get_rows', { tableName: table, limit: pageSize, offset });
+            const rows = Array.from({ length: res.rowCount }, (_, i) =>
+                Object.fromEntries(res.columns.map((col, c) => [col.name, res.values[c][i]]))
+            );
+            setKeyValue(JSON.stringify(rows));
         } catch (err) {
             console.error(err);
             setKeyValue(t('error_loading_data'));
@@ -494,7 +501,7 @@ export default function PostgresManager({ onDisconnect, onDragStart, connectionN
 
     const fetchCount = async (table: string) => {
         try {
-            const count = await invoke<number>('postgres_get_count', { tableName: table });
+            const { count } = await invoke<{ count: number }>('postgres_get_count', { tableName: table });
             setTotalRows(count);
         } catch (e) {
             console.error("Failed to fetch count", e);
@@ -503,11 +510,13 @@ export default function PostgresManager({ onDisconnect, onDragStart, connectionN
 
     const fetchPrimaryKey = async (table: string) => {
         try {
-            const pk = await invoke<string | null>('postgres_get_primary_key', { tableName: table });
-            setPrimaryKey(pk);
+            const pks = await invoke<string[]>('postgres_get_primary_key', { tableName: table });
+            setPrimaryKeys(pks);
+            setPrimaryKey(pks[0] ?? null);
         } catch (e) {
             console.error("Failed to fetch PK", e);
             setPrimaryKey(null);
+            setPrimaryKeys([]);
         }
     };
 
@@ -524,6 +533,10 @@ export default function PostgresManager({ onDisconnect, onDragStart, connectionN
     useEffect(() => {
         fetchDatabases();
         fetchKeys();
+        // Release the pooled connections held by console cursors when the console closes
+        return () => {
+            invoke('close_cursors', { connectionId: 'postgres' }).catch(console.error);
+        };
     }, []);
 
     useEffect(() => {
@@ -540,6 +553,7 @@ export default function PostgresManager({ onDisconnect, onDragStart, connectionN
             setKeyValue("");
             setTotalRows(0);
             setPrimaryKey(null);
+            setPrimaryKeys([]);
             setSchemaColumns([]);
         }
     }, [selectedKey]);
@@ -674,8 +688,7 @@ export default function PostgresManager({ onDisconnect, onDragStart, connectionN
                 if (String(row[colName]) !== newVal) {
                     updates.push({
                         tableName: selectedKey,
-                        pkCol: primaryKey,
-                        pkVal: String(pkVal),
+                        pk: pkValues(row),
                         colName,
                         newVal
                     });
@@ -119,6 +119,9 @@ export default function SQLiteManager({ onClose: _onClose, onDisconnect, onDragS
     // Editing
     const [mode, setMode] = useState<'view' | 'edit'>('view');
     const [primaryKey, setPrimaryKey] = useState<string | null>(null);
+    const [primaryKeys, setPrimaryKeys] = useState<string[]>([]);
+    // Primary key column values identifying a row, for update/delete
+    const pkValues = (row: any) => Object.fromEntries(primaryKeys.map(col => [col, String(row[col])]));
     // Batch Editing
     const [pendingChanges, setPendingChanges] = useState<Record<number, Record<string, string>>>({});
     // History
@@ -191,7 +194,7 @@ export default function SQLiteManager({ onClose: _onClose, onDisconnect, onDragS
             confirmText: t('delete'),
             onConfirm: async () => {
                 try {
-                    await invoke('sqlite_delete_row', { tableName: selectedKey, pkCol: primaryKey, pkVal: String(pkVal) });
+                    await invoke('sqlite_delete_row', { tableName: selectedKey, pk: pkValues(row) });
                     showToast(t('row_deleted_success'), 'success');
                     fetchTableData(selectedKey, page);
                     fetchCount(selectedKey);
@@ -313,8 +316,12 @@ export default function SQLiteManager({ onClose: _onClose, onDisconnect, onDragS
         setIsLoading(true);
         try {
             const offset = (p - 1) * pageSize;
-            const res = await invoke<string[]>('sqlite_get_rows', { tableName: table, limit: pageSize, offset });
-            setKeyValue(`[${res.join(',')}]`);
+            // Column-major page: one value array per column
+            const res = await invoke<{ columns: { name: string }[]; values: unknown[][]; rowCount: number }>('sqlite_get_rows', { tableName: table, limit: pageSize, offset });
+            const rows = Array.from({ length: res.rowCount }, (_, i) =>
+                Object.fromEntries(res.columns.map((col, c) => [col.name, res.values[c][i]]))
+            );
+            setKeyValue(JSON.stringify(rows));
         } catch (err) {
             console.error(err);
             setKeyValue(t('error_loading_data'));
@@ -328,7 +335,7 @@ export default function SQLiteManager({ onClose: _onClose, onDisconnect, onDragS
 
     const fetchCount = async (table: string) => {
         try {
-            const count = await invoke<number>('sqlite_get_count', { tableName: table });
+            const { count } = await invoke<{ count: number }>('sqlite_get_count', { tableName: table });
             setTotalRows(count);
         } catch (e) {
             console.error("Failed to fetch count", e);
@@ -337,16 +344,22 @@ export default function SQLiteManager({ onClose: _onClose, onDisconnect, onDragS
 
     const fetchPrimaryKey = async (table: string) => {
         try {
-            const pk = await invoke<string | null>('sqlite_get_primary_key', { tableName: table });
-            setPrimaryKey(pk);
+            const pks = await invoke<string[]>('sqlite_get_primary_key', { tableName: table });
+            setPrimaryKeys(pks);
+            setPrimaryKey(pks[0] ?? null);
         } catch (e) {
             console.error("Failed to fetch PK", e);
             setPrimaryKey(null);
+            setPrimaryKeys([]);
         }
     };
 
     useEffect(() => {
         fetchKeys();
+        // Release the pooled connections held by console cursors when the console closes
+        return () => {
+            invoke('close_cursors', { connectionId: 'sqlite' }).catch(console.error);
+        };
     }, []);
 
     useEffect(() => {
@@ -362,6 +375,7 @@ export default function SQLiteManager({ onClose: _onClose, onDisconnect, onDragS
         } else {
             setKeyValue("");
             setPrimaryKey(null);
+            setPrimaryKeys([]);
             setSchemaColumns([]);
             setNewRows([]);
         }
@@ -493,8 +507,7 @@ export default function SQLiteManager({ onClose: _onClose, onDisconnect, onDragS
                 if (String(row[colName]) !== newVal) {
                     updates.push({
                         tableName: selectedKey,
-                        pkCol: primaryKey,
-                        pkVal: String(pkVal),
+                        pk: pkValues(row),
                         colName,
                         newVal
                     });